@@ -5,16 +5,22 @@
 
 use ac_ffmpeg::codec::Encoder;
 use ac_ffmpeg::codec::audio::frame::get_sample_format;
-use ac_ffmpeg::codec::audio::{AudioEncoder, AudioFrameMut, ChannelLayout};
+use ac_ffmpeg::codec::audio::{AudioEncoder, AudioFrameMut};
 use anyhow::{Result, anyhow};
-use bytes::Bytes;
 use cpal::SampleFormat;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use log::{error, info};
+use log::{error, info, warn};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
+use super::pcm::{PCM_FRAME_MS, PCM_SAMPLE_RATE, PcmPacketizer};
+use super::{AudioEncodeConfig, EncodedAudio};
+use crate::pipeline::clock::MediaClock;
+use crate::utils::audio_level::AudioLevel;
+
 fn convert_sample_format(format: SampleFormat) -> ac_ffmpeg::codec::audio::SampleFormat {
     get_sample_format(match format {
         SampleFormat::F32 => "flt",
@@ -25,21 +31,52 @@ fn convert_sample_format(format: SampleFormat) -> ac_ffmpeg::codec::audio::Sampl
 }
 
 pub struct AudioCapture {
-    encoder: AudioEncoder,
-    sender: std::sync::mpsc::SyncSender<Bytes>,
+    /// `None` con l'audio a bassa latenza: i campioni partono come L16
+    encoder: Option<AudioEncoder>,
+    pcm: Option<PcmPacketizer>,
+    channels: usize,
+    sender: std::sync::mpsc::SyncSender<EncodedAudio>,
+    clock: MediaClock,
+    level: AudioLevel,
+    level_buf: Vec<f32>,
+    /// Con il mute si codifica silenzio: lo stream resta continuo
+    muted: Arc<AtomicBool>,
 }
 
 impl AudioCapture {
     fn write_input_data<T>(&mut self, input: &[T])
     where
         T: cpal::Sample,
+        f32: cpal::FromSample<T>,
     {
-        let sample_size = self.encoder.samples_per_frame().unwrap();
+        let muted = self.muted.load(Ordering::Relaxed);
+
+        self.level_buf.clear();
+        if muted {
+            self.level_buf.resize(input.len(), 0.0);
+        } else {
+            self.level_buf
+                .extend(input.iter().map(|&s| s.to_sample::<f32>()));
+        }
+        self.level.update(&self.level_buf);
+
+        let (sender, clock) = (&self.sender, &self.clock);
+        if let Some(pcm) = &mut self.pcm {
+            pcm.push(&self.level_buf, self.channels, |packet| {
+                let _ = sender.send(EncodedAudio::stamp(packet, clock));
+            });
+            return;
+        }
+        let Some(encoder) = &mut self.encoder else {
+            return;
+        };
+
+        let sample_size = encoder.samples_per_frame().unwrap();
 
         let mut frame = AudioFrameMut::silence(
-            self.encoder.codec_parameters().channel_layout(),
-            self.encoder.codec_parameters().sample_format(),
-            self.encoder.codec_parameters().sample_rate(),
+            encoder.codec_parameters().channel_layout(),
+            encoder.codec_parameters().sample_format(),
+            encoder.codec_parameters().sample_rate(),
             sample_size,
         );
 
@@ -52,25 +89,36 @@ impl AudioCapture {
             )
         };
 
-        samples[..input.len()].copy_from_slice(input);
+        // Il frame nasce già silenzioso: da mutato basta non copiare i campioni
+        if !muted {
+            samples[..input.len()].copy_from_slice(input);
+        }
 
-        self.encoder.push(frame.freeze()).unwrap();
+        encoder.push(frame.freeze()).unwrap();
 
         let mut ret = Vec::new();
-        while let Some(packet) = self.encoder.take().unwrap() {
+        while let Some(packet) = encoder.take().unwrap() {
             ret.extend(packet.data());
         }
 
-        let _ = self.sender.send(Bytes::from(ret));
+        let _ = self.sender.send(EncodedAudio::stamp(ret, &self.clock));
     }
 
-    /// Starts audio capture and returns a Tokio channel with Opus-encoded packets.
+    /// Starts audio capture and returns a Tokio channel with Opus-encoded packets
+    /// (raw L16 with `encode.low_latency`), stamped on `clock` as they leave the encoder.
     ///
-    /// The channel closes when the `CancellationToken` is cancelled.
+    /// The channel closes when the `CancellationToken` is cancelled. While
+    /// `muted` is set the packets carry silence, without stopping the device.
     ///
     /// On Windows, this uses WASAPI loopback to capture system audio (what's playing through speakers).
     /// On other platforms, it captures from the default input device (microphone).
-    pub fn start(cancel: CancellationToken) -> Result<mpsc::Receiver<Vec<u8>>> {
+    pub fn start(
+        cancel: CancellationToken,
+        encode: AudioEncodeConfig,
+        level: AudioLevel,
+        muted: Arc<AtomicBool>,
+        clock: MediaClock,
+    ) -> Result<mpsc::Receiver<EncodedAudio>> {
         let host = cpal::default_host();
 
         // On Windows, use loopback to capture system audio (speakers output)
@@ -104,26 +152,52 @@ impl AudioCapture {
             (device, config)
         };
 
-        let encoder = AudioEncoder::builder("libopus")?
-            .sample_rate(config.sample_rate())
-            .channel_layout(ChannelLayout::from_channels(2).unwrap())
-            .sample_format(convert_sample_format(config.sample_format()))
-            .set_option("frame_duration", "10")
-            .build()?;
+        // Samples are copied as-is from the device: the channel count must match it
+        let device_stereo = config.channels() >= 2;
+        if !encode.low_latency && encode.stereo != device_stereo {
+            warn!(
+                "Audio device has {} channels, ignoring the requested {} encoding",
+                config.channels(),
+                if encode.stereo { "stereo" } else { "mono" }
+            );
+        }
+        let (encoder, pcm) = if encode.low_latency {
+            // Niente resampling: il pacchetto L16 è dichiarato a 48kHz
+            if config.sample_rate() != PCM_SAMPLE_RATE {
+                return Err(anyhow!(
+                    "Low-latency audio needs a {}Hz capture device, found {}Hz",
+                    PCM_SAMPLE_RATE,
+                    config.sample_rate()
+                ));
+            }
+            info!("Low-latency audio: raw PCM in {}ms packets", PCM_FRAME_MS);
+            (None, Some(PcmPacketizer::default()))
+        } else {
+            let encoder = AudioEncodeConfig {
+                stereo: device_stereo,
+                ..encode
+            }
+            .build_encoder(
+                config.sample_rate(),
+                convert_sample_format(config.sample_format()),
+            )?;
+            (Some(encoder), None)
+        };
+        let channels = config.channels() as usize;
 
         // Synchronous channel: cpal callback → bridge thread
-        let (sync_tx, sync_rx) = std::sync::mpsc::sync_channel::<Bytes>(256);
+        let (sync_tx, sync_rx) = std::sync::mpsc::sync_channel::<EncodedAudio>(256);
 
         // Async channel: bridge → external consumer
         // Increased from 64 to 256 to handle audio bursts without dropping
-        let (async_tx, async_rx) = mpsc::channel::<Vec<u8>>(256);
+        let (async_tx, async_rx) = mpsc::channel::<EncodedAudio>(256);
 
         // Bridge task: synchronous → asynchronous
         tokio::spawn(async move {
             loop {
                 match sync_rx.recv() {
-                    Ok(data) => {
-                        if async_tx.send(data.to_vec()).await.is_err() {
+                    Ok(packet) => {
+                        if async_tx.send(packet).await.is_err() {
                             info!("Audio output channel closed");
                             break;
                         }
@@ -141,7 +215,13 @@ impl AudioCapture {
         thread::spawn(move || -> Result<()> {
             let mut capturer = AudioCapture {
                 encoder,
+                pcm,
+                channels,
                 sender: sync_tx,
+                clock,
+                level: level.clone(),
+                level_buf: Vec::new(),
+                muted,
             };
 
             let err_fn = |err| error!("Audio stream error: {}", err);
@@ -183,6 +263,7 @@ impl AudioCapture {
             });
 
             stream.pause()?;
+            level.reset();
             info!("Audio capture stopped");
             Ok(())
         });
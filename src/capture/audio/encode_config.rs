@@ -0,0 +1,175 @@
+//! Opus encoder parameters shared by every audio capture backend.
+
+use crate::config::{load_json, save_json};
+use ac_ffmpeg::codec::audio::{AudioEncoder, ChannelLayout, SampleFormat};
+use anyhow::{Result, anyhow};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+use super::pcm::PCM_FRAME_MS;
+
+const AUDIO_FILE: &str = "audio.json";
+
+/// Frame duration accepted by libopus (ms). 2.5 is left out: the RTP
+/// sample duration is expressed in whole milliseconds.
+const OPUS_FRAME_DURATIONS: [u32; 5] = [5, 10, 20, 40, 60];
+/// Bitrate range supported by libopus (bps)
+const OPUS_MIN_BITRATE: u32 = 6_000;
+const OPUS_MAX_BITRATE: u32 = 510_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioEncodeConfig {
+    /// Target bitrate in bit/s
+    pub bitrate: u32,
+    /// Opus frame duration in ms: longer frames mean less packet overhead
+    pub frame_duration_ms: u32,
+    /// Mono halves the bitrate needed for the same quality on speech
+    pub stereo: bool,
+    /// Variable bitrate (libopus default) or constant bitrate
+    pub vbr: bool,
+    /// Raw 16-bit stereo PCM in short packets instead of Opus, for LAN use
+    pub low_latency: bool,
+}
+
+impl Default for AudioEncodeConfig {
+    fn default() -> Self {
+        Self {
+            bitrate: 96_000,
+            frame_duration_ms: 10,
+            stereo: true,
+            vbr: true,
+            low_latency: false,
+        }
+    }
+}
+
+impl AudioEncodeConfig {
+    pub fn load() -> Self {
+        load_json(AUDIO_FILE)
+    }
+
+    pub fn save(&self) {
+        save_json(AUDIO_FILE, self, "audio settings");
+    }
+
+    pub fn channels(&self) -> u32 {
+        if self.stereo || self.low_latency {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Duration of each encoded packet, used as RTP sample duration.
+    pub fn frame_duration(&self) -> Duration {
+        if self.low_latency {
+            return Duration::from_millis(PCM_FRAME_MS as u64);
+        }
+        Duration::from_millis(self.frame_duration_ms as u64)
+    }
+
+    /// Clamp the values to what libopus supports, warning about every change.
+    pub fn validated(self) -> Self {
+        let mut config = self;
+
+        if !OPUS_FRAME_DURATIONS.contains(&config.frame_duration_ms) {
+            let nearest = OPUS_FRAME_DURATIONS
+                .iter()
+                .copied()
+                .min_by_key(|d| d.abs_diff(config.frame_duration_ms))
+                .unwrap_or(10);
+            warn!(
+                "Opus frame duration {}ms not supported, using {}ms",
+                config.frame_duration_ms, nearest
+            );
+            config.frame_duration_ms = nearest;
+        }
+
+        let bitrate = config.bitrate.clamp(OPUS_MIN_BITRATE, OPUS_MAX_BITRATE);
+        if bitrate != config.bitrate {
+            warn!(
+                "Opus bitrate {}bps out of range, using {}bps",
+                config.bitrate, bitrate
+            );
+            config.bitrate = bitrate;
+        }
+
+        config
+    }
+
+    /// Build the libopus encoder with the validated settings.
+    pub fn build_encoder(
+        &self,
+        sample_rate: u32,
+        sample_format: SampleFormat,
+    ) -> Result<AudioEncoder> {
+        let config = self.validated();
+
+        let encoder = AudioEncoder::builder("libopus")?
+            .sample_rate(sample_rate)
+            .channel_layout(
+                ChannelLayout::from_channels(config.channels())
+                    .ok_or_else(|| anyhow!("Invalid channel count {}", config.channels()))?,
+            )
+            .sample_format(sample_format)
+            .set_option("b", config.bitrate.to_string())
+            .set_option("frame_duration", config.frame_duration_ms.to_string())
+            .set_option("vbr", if config.vbr { "on" } else { "off" })
+            .build()?;
+
+        info!(
+            "Opus encoder: {}Hz, {} ch, {}kbps {}, {}ms frames",
+            sample_rate,
+            config.channels(),
+            config.bitrate / 1000,
+            if config.vbr { "VBR" } else { "CBR" },
+            config.frame_duration_ms
+        );
+
+        Ok(encoder)
+    }
+}
+
+/// Bitrate Opus proposto nelle impostazioni (bit/s)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpusBitrate(pub u32);
+
+impl OpusBitrate {
+    pub const PRESETS: [OpusBitrate; 6] = [
+        OpusBitrate(24_000),
+        OpusBitrate(48_000),
+        OpusBitrate(64_000),
+        OpusBitrate(96_000),
+        OpusBitrate(128_000),
+        OpusBitrate(192_000),
+    ];
+}
+
+impl fmt::Display for OpusBitrate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} kbps", self.0 / 1000)
+    }
+}
+
+/// Durata dei frame Opus tra quelle accettate da libopus (ms)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpusFrameDuration(pub u32);
+
+impl OpusFrameDuration {
+    pub const PRESETS: [OpusFrameDuration; 5] = [
+        OpusFrameDuration(OPUS_FRAME_DURATIONS[0]),
+        OpusFrameDuration(OPUS_FRAME_DURATIONS[1]),
+        OpusFrameDuration(OPUS_FRAME_DURATIONS[2]),
+        OpusFrameDuration(OPUS_FRAME_DURATIONS[3]),
+        OpusFrameDuration(OPUS_FRAME_DURATIONS[4]),
+    ];
+}
+
+impl fmt::Display for OpusFrameDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ms frames", self.0)
+    }
+}
@@ -3,11 +3,12 @@
 //! Captures system audio (what's playing through speakers) using WASAPI loopback.
 //! This allows recording application audio, browser audio, etc.
 
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::Duration;
 
 use anyhow::{Result, anyhow};
-use bytes::Bytes;
 use log::{error, info, warn};
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
@@ -16,7 +17,13 @@ use windows::Win32::System::Com::*;
 
 use ac_ffmpeg::codec::Encoder;
 use ac_ffmpeg::codec::audio::frame::get_sample_format;
-use ac_ffmpeg::codec::audio::{AudioEncoder, AudioFrameMut, ChannelLayout};
+use ac_ffmpeg::codec::audio::{AudioEncoder, AudioFrameMut};
+
+use super::pcm::{self, PCM_FRAME_MS, PCM_FRAME_SAMPLES};
+use super::resample::{MixResampler, OPUS_SAMPLE_RATE};
+use super::{AudioEncodeConfig, EncodedAudio};
+use crate::pipeline::clock::MediaClock;
+use crate::utils::audio_level::AudioLevel;
 
 #[derive(Copy, Clone, Debug)]
 enum InputSampleFormat {
@@ -30,18 +37,26 @@ pub struct WasapiLoopbackCapture;
 impl WasapiLoopbackCapture {
     /// Start capturing system audio via WASAPI loopback.
     ///
-    /// Returns a channel with Opus-encoded audio packets.
-    pub fn start(cancel: CancellationToken) -> Result<mpsc::Receiver<Vec<u8>>> {
+    /// Returns a channel with Opus-encoded audio packets (raw L16 with
+    /// `encode.low_latency`), stamped on `clock`.
+    /// While `muted` is set the packets carry silence, so the receiver sees no gap.
+    pub fn start(
+        cancel: CancellationToken,
+        encode: AudioEncodeConfig,
+        level: AudioLevel,
+        muted: Arc<AtomicBool>,
+        clock: MediaClock,
+    ) -> Result<mpsc::Receiver<EncodedAudio>> {
         // Channels for communication
-        let (sync_tx, sync_rx) = std::sync::mpsc::sync_channel::<Bytes>(256);
-        let (async_tx, async_rx) = mpsc::channel::<Vec<u8>>(256);
+        let (sync_tx, sync_rx) = std::sync::mpsc::sync_channel::<EncodedAudio>(256);
+        let (async_tx, async_rx) = mpsc::channel::<EncodedAudio>(256);
 
         // Bridge: sync -> async
         tokio::spawn(async move {
             loop {
                 match sync_rx.recv() {
-                    Ok(data) => {
-                        if async_tx.send(data.to_vec()).await.is_err() {
+                    Ok(packet) => {
+                        if async_tx.send(packet).await.is_err() {
                             info!("Audio output channel closed");
                             break;
                         }
@@ -56,7 +71,7 @@ impl WasapiLoopbackCapture {
 
         // Capture thread - create WASAPI objects inside the thread
         thread::spawn(move || {
-            if let Err(e) = Self::capture_thread(cancel, sync_tx) {
+            if let Err(e) = Self::capture_thread(cancel, sync_tx, encode, level, muted, clock) {
                 error!("WASAPI loopback capture error: {}", e);
             }
         });
@@ -66,7 +81,11 @@ impl WasapiLoopbackCapture {
 
     fn capture_thread(
         cancel: CancellationToken,
-        sender: std::sync::mpsc::SyncSender<Bytes>,
+        sender: std::sync::mpsc::SyncSender<EncodedAudio>,
+        encode: AudioEncodeConfig,
+        level: AudioLevel,
+        muted: Arc<AtomicBool>,
+        clock: MediaClock,
     ) -> Result<()> {
         // Initialize COM for this thread
         unsafe {
@@ -156,25 +175,32 @@ impl WasapiLoopbackCapture {
 
         info!("WASAPI loopback capture started");
 
-        // Create Opus encoder
-        // Force stereo encoding for compatibility with playback/saver pipeline.
-        let output_channels = 2u32;
-        let encoder = AudioEncoder::builder("libopus")?
-            .sample_rate(sample_rate as u32)
-            .channel_layout(ChannelLayout::from_channels(output_channels).unwrap())
-            .sample_format(get_sample_format("flt"))
-            .set_option("frame_duration", "10")
-            .build()?;
-
-        let frame_size = encoder.samples_per_frame().unwrap_or(960); // 20ms at 48kHz
+        // Create Opus encoder: the mix is downmixed to the configured channel count
+        // and resampled to 48kHz before being buffered
+        let output_channels = encode.channels();
+        let resampler = MixResampler::new(sample_rate, output_channels)?;
+        // Audio a bassa latenza: pacchetti PCM da 5ms, senza encoder
+        let (encoder, frame_size) = if encode.low_latency {
+            info!("Low-latency audio: raw PCM in {}ms packets", PCM_FRAME_MS);
+            (None, PCM_FRAME_SAMPLES)
+        } else {
+            let encoder = encode.build_encoder(OPUS_SAMPLE_RATE, get_sample_format("flt"))?;
+            let frame_size = encoder.samples_per_frame().unwrap_or(960); // 20ms at 48kHz
+            (Some(encoder), frame_size)
+        };
 
         let mut capturer = AudioCapturer {
             encoder,
             sender,
+            clock,
             input_channels: input_channels as usize,
             output_channels: output_channels as usize,
             frame_size,
             sample_buffer: Vec::new(),
+            resampler,
+            downmix_buffer: Vec::new(),
+            level,
+            muted,
         };
 
         // Capture loop
@@ -251,6 +277,7 @@ impl WasapiLoopbackCapture {
         unsafe {
             let _ = audio_client.Stop();
         }
+        capturer.level.reset();
 
         // Flush remaining samples
         capturer.flush();
@@ -261,17 +288,27 @@ impl WasapiLoopbackCapture {
 }
 
 struct AudioCapturer {
-    encoder: AudioEncoder,
-    sender: std::sync::mpsc::SyncSender<Bytes>,
+    /// `None` con l'audio a bassa latenza: i campioni partono come L16
+    encoder: Option<AudioEncoder>,
+    sender: std::sync::mpsc::SyncSender<EncodedAudio>,
+    clock: MediaClock,
     input_channels: usize,
     output_channels: usize,
     frame_size: usize,
     sample_buffer: Vec<f32>,
+    /// Presente solo se il mix non è già a 48kHz
+    resampler: Option<MixResampler>,
+    /// Campioni downmixati alla frequenza del dispositivo, prima del resampling
+    downmix_buffer: Vec<f32>,
+    /// RMS/picco del frame appena codificato, letto dalla GUI
+    level: AudioLevel,
+    /// Con il mute si codifica silenzio invece di fermare la cattura
+    muted: Arc<AtomicBool>,
 }
 
 impl AudioCapturer {
     fn process_samples_f32(&mut self, samples: &[f32]) {
-        self.push_downmixed_stereo(samples.iter().copied());
+        self.push_downmixed(samples.iter().copied());
 
         // Encode complete frames
         while self.sample_buffer.len() >= self.frame_size * self.output_channels {
@@ -280,7 +317,7 @@ impl AudioCapturer {
     }
 
     fn process_samples_i16(&mut self, samples: &[i16]) {
-        self.push_downmixed_stereo(samples.iter().map(|&s| s as f32 / 32768.0));
+        self.push_downmixed(samples.iter().map(|&s| s as f32 / 32768.0));
 
         // Encode complete frames
         while self.sample_buffer.len() >= self.frame_size * self.output_channels {
@@ -288,11 +325,12 @@ impl AudioCapturer {
         }
     }
 
-    fn push_downmixed_stereo<I>(&mut self, interleaved: I)
+    fn push_downmixed<I>(&mut self, interleaved: I)
     where
         I: IntoIterator<Item = f32>,
     {
         let mut frame: Vec<f32> = Vec::with_capacity(self.input_channels);
+        self.downmix_buffer.clear();
         for sample in interleaved {
             frame.push(sample);
             if frame.len() == self.input_channels {
@@ -301,11 +339,24 @@ impl AudioCapturer {
                     1 => (frame[0], frame[0]),
                     _ => (frame[0], frame[1]),
                 };
-                self.sample_buffer.push(l);
-                self.sample_buffer.push(r);
+                if self.output_channels == 1 {
+                    self.downmix_buffer.push((l + r) * 0.5);
+                } else {
+                    self.downmix_buffer.push(l);
+                    self.downmix_buffer.push(r);
+                }
                 frame.clear();
             }
         }
+
+        match &mut self.resampler {
+            Some(resampler) => {
+                if let Err(e) = resampler.process(&self.downmix_buffer, &mut self.sample_buffer) {
+                    warn!("Audio resampling failed: {}", e);
+                }
+            }
+            None => self.sample_buffer.extend_from_slice(&self.downmix_buffer),
+        }
     }
 
     fn encode_frame(&mut self) {
@@ -314,11 +365,24 @@ impl AudioCapturer {
             return;
         }
 
+        if self.muted.load(Ordering::Relaxed) {
+            // Il frame diventa silenzio: il VU meter va a zero
+            self.sample_buffer[..samples_needed].fill(0.0);
+        }
+        self.level.update(&self.sample_buffer[..samples_needed]);
+
+        let Some(encoder) = &mut self.encoder else {
+            let packet = pcm::encode_l16(&self.sample_buffer[..samples_needed]);
+            self.sample_buffer.drain(..samples_needed);
+            let _ = self.sender.send(EncodedAudio::stamp(packet, &self.clock));
+            return;
+        };
+
         // Create audio frame (silence returns AudioFrameMut directly, not Result)
         let mut frame = AudioFrameMut::silence(
-            self.encoder.codec_parameters().channel_layout(),
-            self.encoder.codec_parameters().sample_format(),
-            self.encoder.codec_parameters().sample_rate(),
+            encoder.codec_parameters().channel_layout(),
+            encoder.codec_parameters().sample_format(),
+            encoder.codec_parameters().sample_rate(),
             self.frame_size,
         );
 
@@ -335,18 +399,29 @@ impl AudioCapturer {
         self.sample_buffer.drain(..samples_needed);
 
         // Encode frame
-        if let Err(e) = self.encoder.push(frame.freeze()) {
+        if let Err(e) = encoder.push(frame.freeze()) {
             warn!("Failed to push audio frame: {}", e);
             return;
         }
 
         // Get encoded packets
-        while let Ok(Some(packet)) = self.encoder.take() {
-            let _ = self.sender.send(Bytes::from(packet.data().to_vec()));
+        while let Ok(Some(packet)) = encoder.take() {
+            let _ = self
+                .sender
+                .send(EncodedAudio::stamp(packet.data().to_vec(), &self.clock));
         }
     }
 
     fn flush(&mut self) {
+        if let Some(resampler) = &mut self.resampler
+            && let Err(e) = resampler.flush(&mut self.sample_buffer)
+        {
+            warn!("Failed to flush audio resampler: {}", e);
+        }
+        while self.sample_buffer.len() >= self.frame_size * self.output_channels {
+            self.encode_frame();
+        }
+
         // Pad remaining samples to complete a frame
         let samples_needed = self.frame_size * self.output_channels;
         if !self.sample_buffer.is_empty() && self.sample_buffer.len() < samples_needed {
@@ -356,11 +431,16 @@ impl AudioCapturer {
         }
 
         // Flush encoder
-        if let Err(e) = self.encoder.flush() {
+        let Some(encoder) = &mut self.encoder else {
+            return;
+        };
+        if let Err(e) = encoder.flush() {
             warn!("Failed to flush encoder: {}", e);
         }
-        while let Ok(Some(packet)) = self.encoder.take() {
-            let _ = self.sender.send(Bytes::from(packet.data().to_vec()));
+        while let Ok(Some(packet)) = encoder.take() {
+            let _ = self
+                .sender
+                .send(EncodedAudio::stamp(packet.data().to_vec(), &self.clock));
         }
     }
 }
@@ -6,6 +6,8 @@
 
 #[cfg(target_os = "windows")]
 mod loopback;
+#[cfg(target_os = "windows")]
+mod resample;
 
 #[cfg(target_os = "windows")]
 pub use loopback::WasapiLoopbackCapture as AudioCapture;
@@ -15,3 +17,28 @@ mod capture;
 
 #[cfg(not(target_os = "windows"))]
 pub use capture::AudioCapture;
+
+mod encode_config;
+pub mod pcm;
+mod tone;
+
+pub use encode_config::{AudioEncodeConfig, OpusBitrate, OpusFrameDuration};
+pub use tone::TestTone;
+
+use crate::pipeline::clock::MediaClock;
+use crate::pipeline::types::Timestamp;
+
+/// Pacchetto Opus timbrato sul `MediaClock` del caster appena codificato
+pub struct EncodedAudio {
+    pub data: Vec<u8>,
+    pub pts: Timestamp,
+}
+
+impl EncodedAudio {
+    pub fn stamp(data: Vec<u8>, clock: &MediaClock) -> Self {
+        Self {
+            data,
+            pts: clock.audio_now(),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Conversione del mix di sistema a 48kHz per l'encoder Opus.
+//!
+//! Opus accetta solo 8/12/16/24/48kHz: molti dispositivi WASAPI riportano
+//! 44.1kHz (o 96kHz) e senza conversione l'audio esce stonato o l'encoder
+//! fallisce. Usa swresample tramite ac-ffmpeg.
+
+use ac_ffmpeg::codec::audio::frame::get_sample_format;
+use ac_ffmpeg::codec::audio::{AudioFrameMut, AudioResampler, ChannelLayout};
+use anyhow::{Result, anyhow};
+use log::info;
+
+/// Frequenza usata per l'encoding, supportata nativamente da Opus
+pub const OPUS_SAMPLE_RATE: u32 = 48_000;
+
+/// Resampler per campioni f32 interleaved.
+pub struct MixResampler {
+    resampler: AudioResampler,
+    layout: ChannelLayout,
+    channels: usize,
+    source_rate: u32,
+}
+
+impl MixResampler {
+    /// `None` se la frequenza del dispositivo è già 48kHz.
+    pub fn new(source_rate: u32, channels: u32) -> Result<Option<Self>> {
+        if source_rate == OPUS_SAMPLE_RATE {
+            return Ok(None);
+        }
+
+        let layout = ChannelLayout::from_channels(channels)
+            .ok_or_else(|| anyhow!("Invalid channel count {}", channels))?;
+        let format = get_sample_format("flt");
+
+        let resampler = AudioResampler::builder()
+            .source_channel_layout(layout.clone())
+            .source_sample_format(format)
+            .source_sample_rate(source_rate)
+            .target_channel_layout(layout.clone())
+            .target_sample_format(format)
+            .target_sample_rate(OPUS_SAMPLE_RATE)
+            .build()?;
+
+        info!(
+            "Audio resampling enabled: {}Hz -> {}Hz",
+            source_rate, OPUS_SAMPLE_RATE
+        );
+
+        Ok(Some(Self {
+            resampler,
+            layout,
+            channels: channels as usize,
+            source_rate,
+        }))
+    }
+
+    /// Converte un blocco interleaved e accoda il risultato a `out`.
+    pub fn process(&mut self, interleaved: &[f32], out: &mut Vec<f32>) -> Result<()> {
+        let samples = interleaved.len() / self.channels;
+        if samples == 0 {
+            return Ok(());
+        }
+
+        let mut frame = AudioFrameMut::silence(
+            &self.layout,
+            get_sample_format("flt"),
+            self.source_rate,
+            samples,
+        );
+
+        let plane = &mut frame.planes_mut()[0];
+        let data = plane.data_mut();
+        let dst: &mut [f32] = unsafe {
+            std::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut f32, samples * self.channels)
+        };
+        dst.copy_from_slice(&interleaved[..samples * self.channels]);
+
+        self.resampler.push(frame.freeze())?;
+        self.drain(out)
+    }
+
+    /// Svuota i campioni ancora trattenuti dal resampler.
+    pub fn flush(&mut self, out: &mut Vec<f32>) -> Result<()> {
+        self.resampler.flush()?;
+        self.drain(out)
+    }
+
+    fn drain(&mut self, out: &mut Vec<f32>) -> Result<()> {
+        while let Some(frame) = self.resampler.take()? {
+            let count = frame.samples() * self.channels;
+            let data = frame.planes()[0].data();
+            if data.len() < count * 4 {
+                continue;
+            }
+            let src: &[f32] =
+                unsafe { std::slice::from_raw_parts(data.as_ptr() as *const f32, count) };
+            out.extend_from_slice(src);
+        }
+        Ok(())
+    }
+}
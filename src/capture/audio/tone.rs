@@ -0,0 +1,136 @@
+//! Tono di prova: una sinusoide a 1kHz al posto dell'audio catturato, per
+//! verificare la connessione senza dipendere da microfono o altoparlanti.
+
+use ac_ffmpeg::codec::Encoder;
+use ac_ffmpeg::codec::audio::frame::get_sample_format;
+use ac_ffmpeg::codec::audio::{AudioEncoder, AudioFrameMut};
+use anyhow::{Result, anyhow};
+use log::{info, warn};
+use std::f32::consts::TAU;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::pcm::{PCM_FRAME_SAMPLES, PCM_SAMPLE_RATE, encode_l16};
+use super::{AudioEncodeConfig, EncodedAudio};
+use crate::pipeline::clock::MediaClock;
+use crate::utils::audio_level::AudioLevel;
+
+/// Frequenza del tono (Hz)
+const TONE_FREQUENCY: f32 = 1000.0;
+/// -12 dBFS: ben udibile senza saturare
+const TONE_AMPLITUDE: f32 = 0.25;
+
+/// Sinusoide continua da un pacchetto al successivo
+#[derive(Default)]
+struct Oscillator {
+    phase: f32,
+}
+
+impl Oscillator {
+    /// Riempie `out` con `frames` campioni interlacciati su `channels` canali
+    fn fill(&mut self, out: &mut Vec<f32>, frames: usize, channels: usize) {
+        let step = TAU * TONE_FREQUENCY / PCM_SAMPLE_RATE as f32;
+        out.clear();
+        for _ in 0..frames {
+            let sample = self.phase.sin() * TONE_AMPLITUDE;
+            out.extend(std::iter::repeat_n(sample, channels));
+            self.phase = (self.phase + step) % TAU;
+        }
+    }
+}
+
+pub struct TestTone;
+
+impl TestTone {
+    /// Same contract as `AudioCapture::start`, with a generated 1kHz tone at
+    /// 48kHz instead of a device: Opus packets (raw L16 with
+    /// `encode.low_latency`) paced at `encode.frame_duration()`.
+    pub fn start(
+        cancel: CancellationToken,
+        encode: AudioEncodeConfig,
+        level: AudioLevel,
+        muted: Arc<AtomicBool>,
+        clock: MediaClock,
+    ) -> Result<mpsc::Receiver<EncodedAudio>> {
+        let channels = encode.channels() as usize;
+        let mut encoder = if encode.low_latency {
+            None
+        } else {
+            Some(encode.build_encoder(PCM_SAMPLE_RATE, get_sample_format("flt"))?)
+        };
+        let frames = match &encoder {
+            Some(encoder) => encoder
+                .samples_per_frame()
+                .ok_or_else(|| anyhow!("Opus encoder has no fixed frame size"))?,
+            None => PCM_FRAME_SAMPLES,
+        };
+
+        let (tx, rx) = mpsc::channel::<EncodedAudio>(256);
+
+        tokio::spawn(async move {
+            let mut oscillator = Oscillator::default();
+            let mut samples = Vec::with_capacity(frames * channels);
+            let mut interval = tokio::time::interval(encode.frame_duration());
+            info!("Test tone started: {}Hz", TONE_FREQUENCY);
+
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = interval.tick() => {}
+                }
+
+                oscillator.fill(&mut samples, frames, channels);
+                // Con il mute si trasmette silenzio: lo stream resta continuo
+                if muted.load(Ordering::Relaxed) {
+                    samples.fill(0.0);
+                }
+                level.update(&samples);
+
+                let packet = match &mut encoder {
+                    Some(encoder) => match encode_opus(encoder, &samples, frames) {
+                        Ok(packet) => packet,
+                        Err(e) => {
+                            warn!("Test tone encode failed: {}", e);
+                            continue;
+                        }
+                    },
+                    // Un tick è esattamente un pacchetto L16 stereo
+                    None => encode_l16(&samples),
+                };
+                if tx.send(EncodedAudio::stamp(packet, &clock)).await.is_err() {
+                    break;
+                }
+            }
+
+            level.reset();
+            info!("Test tone stopped");
+        });
+
+        Ok(rx)
+    }
+}
+
+/// Codifica un frame di `frames` campioni float interlacciati
+fn encode_opus(encoder: &mut AudioEncoder, samples: &[f32], frames: usize) -> Result<Vec<u8>> {
+    let params = encoder.codec_parameters();
+    let mut frame = AudioFrameMut::silence(
+        params.channel_layout(),
+        params.sample_format(),
+        params.sample_rate(),
+        frames,
+    );
+
+    let data = frame.planes_mut()[0].data_mut();
+    for (bytes, sample) in data.chunks_exact_mut(4).zip(samples) {
+        bytes.copy_from_slice(&sample.to_ne_bytes());
+    }
+
+    encoder.push(frame.freeze())?;
+    let mut packet = Vec::new();
+    while let Some(encoded) = encoder.take()? {
+        packet.extend(encoded.data());
+    }
+    Ok(packet)
+}
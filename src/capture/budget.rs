@@ -0,0 +1,242 @@
+//! Tetto di traffico (MB al minuto) per connessioni a consumo.
+//!
+//! Il loop di cattura registra la dimensione di ogni frame codificato; il
+//! controller stima il rate su una finestra mobile e scala fps, risoluzione
+//! e bitrate a gradini quando si avvicina al tetto, risalendo con isteresi
+//! quando il consumo torna ben al di sotto.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+/// Finestra su cui viene stimato il rate
+const WINDOW: Duration = Duration::from_secs(10);
+/// Intervallo minimo tra due cambi di livello
+const EVALUATE_EVERY: Duration = Duration::from_secs(2);
+/// Sopra questa frazione del tetto si scende di livello
+const DEGRADE_AT: f32 = 0.9;
+/// Sotto questa frazione (per più valutazioni) si risale
+const RECOVER_AT: f32 = 0.55;
+const RECOVER_AFTER: u32 = 3;
+
+/// Gradini di qualità, dal pieno al minimo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum BudgetLevel {
+    #[default]
+    Full,
+    Reduced,
+    Low,
+    Minimal,
+}
+
+impl BudgetLevel {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            0 => Self::Full,
+            1 => Self::Reduced,
+            2 => Self::Low,
+            _ => Self::Minimal,
+        }
+    }
+
+    fn lower(self) -> Self {
+        Self::from_u8((self as u8 + 1).min(Self::Minimal as u8))
+    }
+
+    fn higher(self) -> Self {
+        Self::from_u8((self as u8).saturating_sub(1))
+    }
+
+    /// Frame rate massimo a questo livello.
+    pub fn fps_cap(self) -> u32 {
+        match self {
+            Self::Full => u32::MAX,
+            Self::Reduced => 30,
+            Self::Low => 20,
+            Self::Minimal => 15,
+        }
+    }
+
+    /// Fattore applicato alla risoluzione di uscita del profilo.
+    pub fn scale(self) -> f32 {
+        match self {
+            Self::Full | Self::Reduced => 1.0,
+            Self::Low => 0.75,
+            Self::Minimal => 0.5,
+        }
+    }
+
+    /// Dimensioni di uscita del profilo ridotte secondo il livello, sempre pari.
+    pub fn scale_size(self, (w, h): (u32, u32)) -> (u32, u32) {
+        let scale = |v: u32| (((v as f32 * self.scale()) as u32) & !1).max(2);
+        (scale(w), scale(h))
+    }
+
+    /// Bitrate video (bps) per stare sotto il tetto, `None` = default dell'encoder.
+    pub fn bitrate(self, cap_mb_per_minute: f32) -> Option<u32> {
+        let cap_bps = cap_mb_per_minute * 1_000_000.0 * 8.0 / 60.0;
+        let share = match self {
+            Self::Full => return None,
+            Self::Reduced => 0.8,
+            Self::Low => 0.7,
+            Self::Minimal => 0.6,
+        };
+        Some(((cap_bps * share) as u32).max(100_000))
+    }
+}
+
+/// Consumo corrente, condiviso con la GUI.
+#[derive(Debug, Clone, Default)]
+pub struct BudgetUsage {
+    /// MB/min stimati (f32 come bit)
+    rate: Arc<AtomicU32>,
+    level: Arc<AtomicU8>,
+}
+
+impl BudgetUsage {
+    pub fn mb_per_minute(&self) -> f32 {
+        f32::from_bits(self.rate.load(Ordering::Relaxed))
+    }
+
+    pub fn level(&self) -> BudgetLevel {
+        BudgetLevel::from_u8(self.level.load(Ordering::Relaxed))
+    }
+}
+
+/// Tetto scelto dall'utente nella pagina caster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DataCap {
+    #[default]
+    Unlimited,
+    MbPerMinute(u32),
+}
+
+impl DataCap {
+    /// Presets shown in the caster dropdown.
+    pub const PRESETS: [DataCap; 5] = [
+        DataCap::Unlimited,
+        DataCap::MbPerMinute(5),
+        DataCap::MbPerMinute(10),
+        DataCap::MbPerMinute(20),
+        DataCap::MbPerMinute(40),
+    ];
+
+    pub fn mb_per_minute(&self) -> Option<f32> {
+        match self {
+            Self::Unlimited => None,
+            Self::MbPerMinute(mb) => Some(*mb as f32),
+        }
+    }
+}
+
+impl fmt::Display for DataCap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unlimited => write!(f, "No data cap"),
+            Self::MbPerMinute(mb) => write!(f, "Max {} MB/min", mb),
+        }
+    }
+}
+
+/// Tetto impostato dall'utente, come letto dal loop di cattura.
+#[derive(Debug, Clone)]
+pub struct DataBudget {
+    pub mb_per_minute: f32,
+    pub usage: BudgetUsage,
+}
+
+/// Stato del controllo, vive nel loop di cattura.
+pub struct BudgetController {
+    samples: VecDeque<(Instant, usize)>,
+    window_bytes: usize,
+    level: BudgetLevel,
+    last_evaluation: Instant,
+    calm_evaluations: u32,
+}
+
+impl BudgetController {
+    pub fn new() -> Self {
+        Self {
+            samples: VecDeque::new(),
+            window_bytes: 0,
+            level: BudgetLevel::Full,
+            last_evaluation: Instant::now(),
+            calm_evaluations: 0,
+        }
+    }
+
+    pub fn level(&self) -> BudgetLevel {
+        self.level
+    }
+
+    /// Registra un frame codificato (video) inviato alla rete.
+    pub fn record(&mut self, bytes: usize) {
+        let now = Instant::now();
+        self.samples.push_back((now, bytes));
+        self.window_bytes += bytes;
+        while let Some(&(at, b)) = self.samples.front() {
+            if now.duration_since(at) <= WINDOW {
+                break;
+            }
+            self.window_bytes -= b;
+            self.samples.pop_front();
+        }
+    }
+
+    /// MB/min stimati sulla finestra (o sul tempo trascorso se più breve).
+    fn rate(&self) -> f32 {
+        let Some(&(first, _)) = self.samples.front() else {
+            return 0.0;
+        };
+        let span = first.elapsed().max(Duration::from_secs(1)).as_secs_f32();
+        self.window_bytes as f32 / span * 60.0 / 1_000_000.0
+    }
+
+    /// Aggiorna l'utilizzo pubblicato e ritorna il nuovo livello se cambia.
+    /// Senza tetto torna subito alla qualità piena.
+    pub fn evaluate(&mut self, budget: Option<&DataBudget>) -> Option<BudgetLevel> {
+        let rate = self.rate();
+        if let Some(budget) = budget {
+            budget.usage.rate.store(rate.to_bits(), Ordering::Relaxed);
+        }
+
+        if self.last_evaluation.elapsed() < EVALUATE_EVERY {
+            return None;
+        }
+
+        let previous = self.level;
+        match budget {
+            Some(budget) if rate > budget.mb_per_minute * DEGRADE_AT => {
+                self.level = self.level.lower();
+                self.calm_evaluations = 0;
+            }
+            Some(budget) if rate < budget.mb_per_minute * RECOVER_AT => {
+                self.calm_evaluations += 1;
+                if self.calm_evaluations >= RECOVER_AFTER {
+                    self.level = self.level.higher();
+                    self.calm_evaluations = 0;
+                }
+            }
+            Some(_) => self.calm_evaluations = 0,
+            None => self.level = BudgetLevel::Full,
+        }
+        self.last_evaluation = Instant::now();
+
+        if let Some(budget) = budget {
+            budget
+                .usage
+                .level
+                .store(self.level as u8, Ordering::Relaxed);
+        }
+
+        if previous == self.level {
+            return None;
+        }
+        // I campioni del vecchio livello falserebbero la prossima valutazione
+        self.samples.clear();
+        self.window_bytes = 0;
+        Some(self.level)
+    }
+}
@@ -1,3 +1,4 @@
+use anyhow::anyhow;
 use log::{error, info};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
@@ -5,12 +6,28 @@ use tokio::{
     select,
     sync::{Mutex, Notify, mpsc, watch},
 };
+use tokio_util::sync::CancellationToken;
 
-use crate::assets::FRAME_RATE;
+use crate::capture::budget::{BudgetLevel, BudgetUsage, DataBudget};
 use crate::capture::display::DisplaySelector;
-use crate::capture::{ScreenCapture, ScreenCaptureImpl};
-use crate::encoder::FfmpegEncoder;
+use crate::capture::keycast::Keycast;
+use crate::capture::overlay::CursorHighlight;
+#[cfg(any(test, feature = "test-capture"))]
+use crate::capture::synthetic::TestPatternCapture;
+use crate::capture::timestamp::Timestamp;
+use crate::capture::watermark::Watermark;
+use crate::capture::zoom::Zoom;
+use crate::capture::{
+    BitrateMode, CaptureError, FpsCap, HdrMode, ScreenCapture, ScreenCaptureImpl, Simulcast,
+    SourceEvent, StreamProfile, YUVFrame,
+};
+use crate::encoder::{EncodedVideo, FfmpegEncoder, RateControl, SimulcastLink};
 use crate::gui::common::datastructure::ScreenRect;
+use crate::pipeline::clock::{CaptureTimeline, MediaClock};
+use crate::pipeline::health::{DropSource, PipelineHealth};
+use crate::pipeline::sender::encode_stage::contains_idr;
+use crate::pipeline::tuning::PipelineTuning;
+use crate::pipeline::types::Timestamp;
 
 // ── Stato interno ───────────────────────────────────────────────
 
@@ -43,7 +60,97 @@ pub struct CaptureOpts {
     pub blank_screen: bool,
     pub crop: Option<CropRect>,
     pub paused: bool,
+    /// Tetto dell'adattivo (qualità della rete)
     pub max_fps: u32,
+    /// Tetto dell'utente, già limitato alla frequenza del display
+    pub fps_ceiling: u32,
+    pub profile: StreamProfile,
+    /// Box massimo dell'encoder, applicato dopo il profilo (`None` = nessun tetto)
+    pub scale_to: Option<(u32, u32)>,
+    /// Puntatore del mouse nei frame catturati (runtime solo su WGC)
+    pub show_cursor: bool,
+    /// Anello attorno al cursore + ripple al click, disegnato prima dell'encoding
+    pub cursor_highlight: Option<CursorHighlight>,
+    /// Ultime combinazioni di tasti mostrate sullo stream
+    pub keycast: Option<Keycast>,
+    /// Lente d'ingrandimento animata sopra la sorgente
+    pub zoom: Option<Zoom>,
+    /// Tetto di traffico: vicino al limite scala fps, risoluzione e bitrate
+    pub data_budget: Option<DataBudget>,
+    /// Logo composto in un angolo del frame codificato
+    pub watermark: Option<Watermark>,
+    /// Orario di sistema impresso nel frame codificato
+    pub timestamp: Option<Timestamp>,
+    /// A schermo fermo codifica un frame al secondo (per ora solo WGC)
+    pub content_aware: bool,
+    /// Finestre da coprire di nero (parte del titolo o classe), cercate a
+    /// ogni frame catturato. Solo WGC.
+    pub privacy_exclude: Vec<String>,
+    /// Tone-mapping dei desktop HDR, letto all'avvio della cattura. Solo WGC.
+    pub hdr: HdrMode,
+    /// VBR o bitrate costante, per la sessione
+    pub bitrate_mode: BitrateMode,
+    /// Frame tenuti dal pool degli encoder ricreati dal loop di cattura
+    pub frame_pool: usize,
+    /// Slice massime dell'encoder in byte (MTU delle impostazioni),
+    /// `None` = frame interi, frammentati dal packetizer RTP
+    pub max_slice_size: Option<usize>,
+}
+
+impl CaptureOpts {
+    /// Dimensioni della sorgente (crop o display intero), sempre pari.
+    pub fn source_size(&self, display: (u32, u32)) -> (u32, u32) {
+        match &self.crop {
+            Some(crop) => crop.even_size(),
+            None => display,
+        }
+    }
+
+    /// Dimensioni dell'encoder per una sorgente `src_w`×`src_h`: prima il
+    /// profilo, poi il tetto di `scale_to`. Mai ingrandite, sempre pari.
+    pub fn output_size(&self, src_w: u32, src_h: u32) -> (u32, u32) {
+        let (w, h) = self.profile.output_size(src_w, src_h);
+        match self.scale_to {
+            Some((max_w, max_h)) => StreamProfile::new(max_w, max_h, 0).output_size(w, h),
+            None => (w, h),
+        }
+    }
+
+    /// Rate control dell'encoder a un livello del data budget: in CBR il
+    /// target resta sotto il tetto del budget, se ce n'è uno.
+    pub fn rate_control(&self, level: BudgetLevel) -> RateControl {
+        let cap = self
+            .data_budget
+            .as_ref()
+            .and_then(|b| level.bitrate(b.mb_per_minute));
+        match (self.bitrate_mode.constant_bps(), cap) {
+            (Some(target), cap) => RateControl::Constant(cap.map_or(target, |c| c.min(target))),
+            (None, Some(cap)) => RateControl::Capped(cap),
+            (None, None) => RateControl::Chain,
+        }
+    }
+
+    /// Profilo concreto per una sorgente, quello annunciato ai receiver.
+    pub fn resolve_profile(&self, src_w: u32, src_h: u32) -> StreamProfile {
+        let (width, height) = self.output_size(src_w, src_h);
+        StreamProfile::new(width, height, self.profile_fps())
+    }
+
+    /// Frame rate massimo effettivo: il minimo tra l'adattivo, il profilo e
+    /// il tetto dell'utente.
+    pub fn fps_limit(&self) -> u32 {
+        self.max_fps.clamp(FpsCap::MIN, self.profile_fps())
+    }
+
+    /// Fps del profilo sotto il tetto dell'utente; "Native" segue il tetto
+    fn profile_fps(&self) -> u32 {
+        let ceiling = self.fps_ceiling.clamp(FpsCap::MIN, FpsCap::MAX);
+        if self.profile.is_native() {
+            ceiling
+        } else {
+            self.profile.fps_cap().min(ceiling)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -54,17 +161,103 @@ pub struct CropRect {
     pub h: u32,
 }
 
+impl CropRect {
+    /// Lato minimo di un crop valido (un blocco di crominanza NV12).
+    pub const MIN_SIZE: u32 = 2;
+
+    /// Dimensioni arrotondate al pari (NV12), quelle effettivamente codificate.
+    pub fn even_size(&self) -> (u32, u32) {
+        (self.w + (self.w % 2), self.h + (self.h % 2))
+    }
+
+    /// Limita il crop a un display `(w, h)`. Origine e dimensioni vengono
+    /// allineate al pari per difetto, così il rettangolo non esce mai dal
+    /// frame; `None` se quello che resta è degenere.
+    pub fn clamped(&self, (dw, dh): (u32, u32)) -> Option<CropRect> {
+        let x = self.x.min(dw) & !1;
+        let y = self.y.min(dh) & !1;
+        let w = self.w.min(dw.saturating_sub(x)) & !1;
+        let h = self.h.min(dh.saturating_sub(y)) & !1;
+        (w >= Self::MIN_SIZE && h >= Self::MIN_SIZE).then_some(CropRect { x, y, w, h })
+    }
+}
+
 impl From<&ScreenRect> for CropRect {
+    /// La parte a coordinate negative (fuori dal monitor) viene tagliata,
+    /// non traslata.
     fn from(r: &ScreenRect) -> Self {
+        let (x, y) = (r.x.max(0.0), r.y.max(0.0));
         Self {
-            x: r.x.max(0.0) as u32,
-            y: r.y.max(0.0) as u32,
-            w: r.width.max(0.0) as u32,
-            h: r.height.max(0.0) as u32,
+            x: x as u32,
+            y: y as u32,
+            w: (r.x + r.width - x).max(0.0) as u32,
+            h: (r.y + r.height - y).max(0.0) as u32,
         }
     }
 }
 
+/// Extract a crop region from an NV12 YUVFrame, reusing pre-allocated buffers.
+/// Returns a YUVFrame whose luminance/chrominance data lives in the provided buffers.
+pub(crate) fn extract_crop_nv12_reuse<'a>(
+    frame: &YUVFrame,
+    crop: &CropRect,
+    y_buf: &'a mut Vec<u8>,
+    uv_buf: &'a mut Vec<u8>,
+) -> YUVFrame {
+    let cx = crop.x & !1;
+    let cw = (crop.w + (crop.w % 2)) as usize;
+    let cy = crop.y & !1;
+    let ch = (crop.h + (crop.h % 2)) as usize;
+
+    let src_y_stride = frame.luminance_stride as usize;
+    let src_uv_stride = frame.chrominance_stride as usize;
+
+    // Resize buffers (no-op after first frame if dimensions are constant)
+    y_buf.resize(cw * ch, 0);
+    let uv_h = ch / 2;
+    uv_buf.resize(cw * uv_h, 128);
+
+    // Extract Y plane
+    for row in 0..ch {
+        let src_row = (cy as usize) + row;
+        if src_row >= frame.height as usize {
+            break;
+        }
+        let src_start = src_row * src_y_stride + cx as usize;
+        let dst_start = row * cw;
+        let copy_len = cw.min(frame.luminance_bytes.len().saturating_sub(src_start));
+        if copy_len > 0 {
+            y_buf[dst_start..dst_start + copy_len]
+                .copy_from_slice(&frame.luminance_bytes[src_start..src_start + copy_len]);
+        }
+    }
+
+    // Extract UV plane
+    for row in 0..uv_h {
+        let src_row = (cy as usize / 2) + row;
+        if src_row >= (frame.height as usize / 2) {
+            break;
+        }
+        let src_start = src_row * src_uv_stride + cx as usize;
+        let dst_start = row * cw;
+        let copy_len = cw.min(frame.chrominance_bytes.len().saturating_sub(src_start));
+        if copy_len > 0 {
+            uv_buf[dst_start..dst_start + copy_len]
+                .copy_from_slice(&frame.chrominance_bytes[src_start..src_start + copy_len]);
+        }
+    }
+
+    YUVFrame {
+        display_time: frame.display_time,
+        width: cw as i32,
+        height: ch as i32,
+        luminance_bytes: std::mem::replace(y_buf, Vec::with_capacity(cw * ch)),
+        luminance_stride: cw as i32,
+        chrominance_bytes: std::mem::replace(uv_buf, Vec::with_capacity(cw * uv_h)),
+        chrominance_stride: cw as i32,
+    }
+}
+
 // ── Capturer ────────────────────────────────────────────────────
 
 pub struct Capturer {
@@ -76,32 +269,101 @@ pub struct Capturer {
     opts_tx: watch::Sender<CaptureOpts>,
     opts_rx: watch::Receiver<CaptureOpts>,
     force_idr: Arc<AtomicBool>,
+    /// Task che fa seguire al crop la finestra in primo piano
+    follow_cancel: Option<CancellationToken>,
+    /// Crop bloccato sulla finestra corrente (il task resta attivo)
+    follow_locked: Arc<AtomicBool>,
+    /// Ingresso del loop di inoltro: serve a riavviare la sola cattura
+    /// quando si cambia display con lo stream attivo
+    frame_tx: Option<mpsc::Sender<EncodedVideo>>,
+    /// Frame inoltrati/scartati, letti dalle statistiche del caster
+    health: Option<Arc<PipelineHealth>>,
+    /// Orologio condiviso con la cattura audio: timbra i frame in uscita
+    clock: MediaClock,
+    /// Tetto di fps scelto dall'utente, riapplicato a ogni cambio di display
+    fps_cap: FpsCap,
+    /// La sorgente ha cambiato risoluzione o è stata sostituita dal display
+    /// principale durante la cattura: il profilo annunciato va aggiornato
+    source_changed: Arc<AtomicBool>,
+    /// La finestra o l'area condivisa è sparita: stream oscurato, l'utente
+    /// deve scegliere un'altra sorgente
+    source_lost: Arc<AtomicBool>,
+    /// Il backend rispetta `CaptureOpts::show_cursor`
+    can_hide_cursor: bool,
+    /// Trasmette il pattern di test al posto del display selezionato
+    #[cfg(any(test, feature = "test-capture"))]
+    test_pattern: bool,
+    /// Livello ridotto per i receiver lenti, scelto prima di `start`
+    simulcast: Simulcast,
+    /// Collegamento al secondo encoder, ripassato a ogni encoder ricreato
+    simulcast_link: Option<SimulcastLink>,
+    /// Frame del livello ridotto, da prendere dopo `start`
+    simulcast_rx: Option<mpsc::Receiver<EncodedFrame>>,
+    /// Encoder della catena scelto all'ultimo `start`
+    encoder_name: Option<String>,
+    /// Profondità dei canali e del pool dell'encoder, letta a ogni `start`
+    tuning: PipelineTuning,
 }
 
+/// Intervallo di polling della finestra in primo piano.
+#[cfg(target_os = "windows")]
+const FOLLOW_POLL: std::time::Duration = std::time::Duration::from_millis(150);
+/// Poll consecutivi con lo stesso rettangolo prima di applicare il crop:
+/// evita di ricreare l'encoder mentre una finestra viene trascinata.
+#[cfg(target_os = "windows")]
+const FOLLOW_DEBOUNCE: u32 = 3;
+/// Risoluzione del pattern di test trasmesso al posto dello schermo
+#[cfg(any(test, feature = "test-capture"))]
+const TEST_PATTERN_SIZE: (u32, u32) = (1280, 720);
+
 #[derive(Debug, Clone)]
 pub struct EncodedFrame {
     pub data: Vec<u8>,
     pub sequence_number: u64,
     pub timestamp_ms: u64,
+    /// Istante di cattura sul `MediaClock` del caster
+    pub pts: Timestamp,
 }
 
 pub type CaptureFpsController = Arc<dyn Fn(u32) + Send + Sync>;
 
 impl Capturer {
-    pub fn new(fps: u32) -> Self {
-        let display_capture =
-            ScreenCaptureImpl::new_default().expect("Failed to create screen capture");
-        let initial_fps = fps.clamp(15, FRAME_RATE.max(15));
+    pub fn new(fps: u32) -> Result<Self, CaptureError> {
+        let display_capture = ScreenCaptureImpl::new_default()?;
+        let initial_fps = fps.clamp(FpsCap::MIN, FpsCap::MAX);
+        let fps_cap = FpsCap::default();
+        let refresh_rate = display_capture
+            .selected_display()
+            .ok()
+            .flatten()
+            .and_then(|display| ScreenCaptureImpl::refresh_rate(&display));
 
         let default_opts = CaptureOpts {
             blank_screen: false,
             crop: None,
             paused: false,
             max_fps: initial_fps,
+            fps_ceiling: fps_cap.ceiling(refresh_rate),
+            profile: StreamProfile::default(),
+            scale_to: None,
+            show_cursor: true,
+            cursor_highlight: None,
+            keycast: None,
+            zoom: None,
+            data_budget: None,
+            watermark: None,
+            timestamp: None,
+            content_aware: false,
+            privacy_exclude: Vec::new(),
+            hdr: HdrMode::default(),
+            bitrate_mode: BitrateMode::default(),
+            frame_pool: PipelineTuning::default().frame_pool,
+            max_slice_size: None,
         };
         let (opts_tx, opts_rx) = watch::channel(default_opts);
+        let can_hide_cursor = display_capture.can_hide_cursor();
 
-        Self {
+        Ok(Self {
             capture: Arc::new(Mutex::new(display_capture)),
             state: Arc::new(AtomicU8::new(CaptureState::Stopped as u8)),
             pause_notify: Arc::new(Notify::new()),
@@ -109,7 +371,23 @@ impl Capturer {
             opts_tx,
             opts_rx,
             force_idr: Arc::new(AtomicBool::new(false)),
-        }
+            follow_cancel: None,
+            follow_locked: Arc::new(AtomicBool::new(false)),
+            frame_tx: None,
+            health: None,
+            clock: MediaClock::new(),
+            fps_cap,
+            source_changed: Arc::new(AtomicBool::new(false)),
+            source_lost: Arc::new(AtomicBool::new(false)),
+            can_hide_cursor,
+            #[cfg(any(test, feature = "test-capture"))]
+            test_pattern: false,
+            simulcast: Simulcast::Off,
+            simulcast_link: None,
+            simulcast_rx: None,
+            encoder_name: None,
+            tuning: PipelineTuning::default(),
+        })
     }
 
     // ── Avvio cattura ───────────────────────────────────────────
@@ -124,38 +402,72 @@ impl Capturer {
         self.state
             .store(CaptureState::Playing as u8, Ordering::Release);
 
-        // Crop-aware encoder resolution: if crop is set, use crop dimensions
-        let (enc_w, enc_h) = {
-            let opts = self.opts_rx.borrow();
-            if let Some(crop) = &opts.crop {
-                // Ensure even dimensions for NV12 chroma alignment
-                let w = crop.w + (crop.w % 2);
-                let h = crop.h + (crop.h % 2);
-                (w, h)
-            } else {
-                let cap = self.capture.lock().await;
-                cap.display().resolution()
-            }
-        };
+        // Crop-aware encoder resolution, scaled down to the stream profile
+        let (src_w, src_h) = self.source_size().await;
+        let (enc_w, enc_h) = self.opts_rx.borrow().output_size(src_w, src_h);
+        let rate = self.opts_rx.borrow().rate_control(BudgetLevel::Full);
+        let max_slice_size = self.opts_rx.borrow().max_slice_size;
 
-        // Increased channel capacity to prevent frame dropping under load
-        // At 30fps: 256 frames = ~8 second buffer for network jitter
-        let (tx, rx) = mpsc::channel::<EncodedFrame>(256);
-        let (frame_tx, mut frame_rx) = mpsc::channel::<bytes::Bytes>(128);
+        // Con il profilo bilanciato, a 30fps: 256 frame = ~8 secondi di coda
+        let (tx, rx) = mpsc::channel::<EncodedFrame>(self.tuning.capture_queue);
+        let (frame_tx, mut frame_rx) = mpsc::channel::<EncodedVideo>(self.tuning.encoded_queue);
+        self.frame_tx = Some(frame_tx.clone());
 
         let capture = self.capture.clone();
         let pause_notify = self.pause_notify.clone();
         let stop_notify = self.stop_notify.clone();
         let state_ref = Arc::clone(&self.state);
         let opts_rx = self.opts_rx.clone();
+        let opts_tx = self.opts_tx.clone();
+        let restart_tx = frame_tx.clone();
+        let source_changed = Arc::clone(&self.source_changed);
+        let source_lost = Arc::clone(&self.source_lost);
+        // Il limite di fps resta quello di `CaptureOpts`, come per lo schermo
+        #[cfg(any(test, feature = "test-capture"))]
+        let mut pattern = self.test_pattern.then(|| {
+            let (w, h) = TEST_PATTERN_SIZE;
+            TestPatternCapture::new(w, h).with_fps(FpsCap::MAX)
+        });
+
+        // Stessi frame NV12 verso un secondo encoder ridotto, timbrati a parte
+        let mut low_raw_rx = None;
+        self.simulcast_link = self.simulcast.size().map(|size| {
+            let (output, rx) = mpsc::channel::<EncodedVideo>(self.tuning.encoded_queue);
+            low_raw_rx = Some(rx);
+            SimulcastLink {
+                size,
+                output,
+                force_idr: Arc::new(AtomicBool::new(false)),
+                active: Arc::new(AtomicBool::new(false)),
+            }
+        });
+        let (low_tx, low_rx) = mpsc::channel::<EncodedFrame>(self.tuning.capture_queue);
+        self.simulcast_rx = low_raw_rx.is_some().then_some(low_rx);
+        let simulcast = self.simulcast_link.clone();
 
         // Create encoder and capture its force_idr before moving it
-        let encoder = FfmpegEncoder::new(enc_w, enc_h);
+        let encoder =
+            FfmpegEncoder::with_rate_control(src_w, src_h, enc_w, enc_h, rate, max_slice_size);
+        let mut encoder = match encoder {
+            Ok(encoder) => encoder.with_frame_pool(self.tuning.frame_pool),
+            Err(e) => {
+                self.frame_tx = None;
+                self.state
+                    .store(CaptureState::Stopped as u8, Ordering::Release);
+                return Err(e);
+            }
+        };
+        encoder.simulcast = self.simulcast_link.clone();
         self.force_idr = encoder.force_idr.clone();
+        self.encoder_name = Some(encoder.codec_name.clone());
         let force_idr = self.force_idr.clone();
+        let health = self.health.clone();
+        let clock = self.clock.clone();
 
         let mut sequence_number = 0u64;
-        let start_time = std::time::Instant::now();
+        let mut low_sequence_number = 0u64;
+        // Pts dall'istante di cattura passato all'encoder, non dall'arrivo qui
+        let mut timeline = CaptureTimeline::default();
         let mut total_frames = 0u64;
         let mut dropped_frames = 0u64;
         let mut last_stats_log = std::time::Instant::now();
@@ -164,13 +476,23 @@ impl Capturer {
             info!("=== CAPTURER: Spawn started ===");
 
             // Avvia la cattura interna (scrive frame codificati in frame_tx)
-            {
-                let mut cap = capture.lock().await;
-                if let Err(e) = cap.start_capture(encoder, frame_tx, opts_rx).await {
+            #[cfg(any(test, feature = "test-capture"))]
+            let started = match pattern.as_mut() {
+                Some(pattern) => pattern
+                    .start_capture(encoder, frame_tx, opts_rx)
+                    .await
+                    .map(|_| None),
+                None => start_screen(&capture, &health, encoder, frame_tx, opts_rx).await,
+            };
+            #[cfg(not(any(test, feature = "test-capture")))]
+            let started = start_screen(&capture, &health, encoder, frame_tx, opts_rx).await;
+            let mut source_events = match started {
+                Ok(events) => events,
+                Err(e) => {
                     error!("Capture start failed: {}", e);
                     return;
                 }
-            }
+            };
 
             info!("=== CAPTURER: Capture loop started, waiting for frames ===");
 
@@ -190,26 +512,46 @@ impl Capturer {
                         let s = CaptureState::from_u8(state_ref.load(Ordering::Acquire));
                         match s {
                             CaptureState::Paused => {
-                                pause_notify.notified().await;
-                                continue;
+                                // La pausa (anche quella senza receiver) deve cedere allo stop:
+                                // `notify_waiters` non lascia permessi, le attese si registrano
+                                // prima di ricontrollare lo stato
+                                let resumed = pause_notify.notified();
+                                let stopped = stop_notify.notified();
+                                tokio::pin!(resumed, stopped);
+                                resumed.as_mut().enable();
+                                stopped.as_mut().enable();
+                                match CaptureState::from_u8(state_ref.load(Ordering::Acquire)) {
+                                    CaptureState::Paused => select! {
+                                        _ = resumed => continue,
+                                        _ = stopped => break,
+                                    },
+                                    CaptureState::Stopped => break,
+                                    CaptureState::Playing => continue,
+                                }
                             }
                             CaptureState::Stopped => break,
                             CaptureState::Playing => {}
                         }
 
-                        let timestamp_ms = start_time.elapsed().as_millis() as u64;
+                        let pts = timeline.pts(raw.capture_us, clock.video_now());
                         let frame_size = raw.len();
                         let encoded_frame = EncodedFrame {
-                            data: Vec::from(raw),
+                            data: Vec::from(raw.data),
                             sequence_number,
-                            timestamp_ms,
+                            timestamp_ms: (pts.micros / 1000).max(0) as u64,
+                            pts,
                         };
                         sequence_number += 1;
                         total_frames += 1;
 
+                        let is_keyframe = health.is_some() && contains_idr(&encoded_frame.data);
+
                         // Try to send frame, track drops
                         match tx.try_send(encoded_frame) {
                             Ok(_) => {
+                                if let Some(health) = &health {
+                                    health.record_frame(frame_size, is_keyframe);
+                                }
                                 // Success - log stats periodically
                                 if last_stats_log.elapsed().as_secs() >= 10 {
                                     let drop_rate = if total_frames > 0 {
@@ -228,6 +570,9 @@ impl Capturer {
                                 }
                             }
                             Err(_) => {
+                                if let Some(health) = &health {
+                                    health.record_frame_drop(DropSource::Send);
+                                }
                                 dropped_frames += 1;
                                 if dropped_frames % 30 == 1 {
                                     // Log every 30 drops to avoid spam
@@ -242,6 +587,81 @@ impl Capturer {
                         }
                     }
 
+                    Some(raw) = next_simulcast_frame(&mut low_raw_rx) => {
+                        if !matches!(
+                            CaptureState::from_u8(state_ref.load(Ordering::Acquire)),
+                            CaptureState::Playing
+                        ) {
+                            continue;
+                        }
+                        // Stessa timeline del livello principale: i receiver che
+                        // cambiano livello restano allineati all'audio
+                        let pts = timeline
+                            .map(raw.capture_us)
+                            .unwrap_or_else(|| clock.video_now());
+                        let encoded_frame = EncodedFrame {
+                            data: Vec::from(raw.data),
+                            sequence_number: low_sequence_number,
+                            timestamp_ms: (pts.micros / 1000).max(0) as u64,
+                            pts,
+                        };
+                        low_sequence_number += 1;
+                        if low_tx.try_send(encoded_frame).is_err()
+                            && let Some(link) = &simulcast
+                        {
+                            link.force_idr.store(true, Ordering::Relaxed);
+                        }
+                    }
+
+                    Some(event) = next_source_event(&mut source_events) => {
+                        match event {
+                            SourceEvent::Resized(w, h) => {
+                                // Il crop era relativo alla risoluzione precedente
+                                opts_tx.send_if_modified(|o| {
+                                    let clamped = o.crop.and_then(|c| c.clamped((w, h)));
+                                    let changed = clamped != o.crop;
+                                    o.crop = clamped;
+                                    changed
+                                });
+                            }
+                            // Solo un monitor scollegato ripiega su un altro monitor:
+                            // al posto di una finestra o di un'area non deve comparire
+                            // uno schermo che l'utente non ha scelto di condividere
+                            SourceEvent::WindowClosed => {
+                                blank_lost_source(&opts_tx, &source_lost);
+                                log::warn!("Captured window closed, stream blanked");
+                                continue;
+                            }
+                            SourceEvent::Lost if opts_tx.borrow().crop.is_some() => {
+                                blank_lost_source(&opts_tx, &source_lost);
+                                log::warn!("Display of the captured area disappeared, stream blanked");
+                                continue;
+                            }
+                            SourceEvent::Lost => {
+                                let restarted = fallback_to_primary(
+                                    &capture,
+                                    restart_tx.clone(),
+                                    &opts_tx,
+                                    &force_idr,
+                                    &simulcast,
+                                )
+                                .await;
+                                match restarted {
+                                    Ok(name) => log::warn!(
+                                        "Captured source disappeared, falling back to the primary display {}",
+                                        name
+                                    ),
+                                    Err(e) => {
+                                        error!("Captured source disappeared and no display is left: {}", e);
+                                        break;
+                                    }
+                                }
+                            }
+                            SourceEvent::None => continue,
+                        }
+                        source_changed.store(true, Ordering::Release);
+                    }
+
                     _ = stop_notify.notified() => {
                         info!("CAPTURER: Stop signal received");
                         break;
@@ -255,8 +675,14 @@ impl Capturer {
             );
 
             // Cleanup
-            let mut cap = capture.lock().await;
-            if let Err(e) = cap.stop_capture().await {
+            #[cfg(any(test, feature = "test-capture"))]
+            let stopped = match pattern.as_mut() {
+                Some(pattern) => pattern.stop_capture().await,
+                None => capture.lock().await.stop_capture().await,
+            };
+            #[cfg(not(any(test, feature = "test-capture")))]
+            let stopped = capture.lock().await.stop_capture().await;
+            if let Err(e) = stopped {
                 error!("Capture stop failed: {}", e);
             }
             info!("Capture cleanup completed");
@@ -288,7 +714,8 @@ impl Capturer {
         }
     }
 
-    pub fn stop(&self) {
+    pub fn stop(&mut self) {
+        self.frame_tx = None;
         let current = self.state.load(Ordering::Acquire);
         if current != CaptureState::Stopped as u8 {
             self.state
@@ -296,12 +723,62 @@ impl Capturer {
             self.stop_notify.notify_waiters();
             info!("Capture fully stopped");
         }
+        // Il task di follow non sopravvive allo stream: `is_following_window` torna falso
+        if let Some(cancel) = self.follow_cancel.take() {
+            cancel.cancel();
+        }
+        self.follow_locked.store(false, Ordering::Relaxed);
     }
 
     pub fn is_playing(&self) -> bool {
         self.state.load(Ordering::Acquire) == CaptureState::Playing as u8
     }
 
+    /// Registra frame inviati e scartati nella salute della pipeline.
+    /// Effettivo dal prossimo `start`.
+    pub fn set_health(&mut self, health: Arc<PipelineHealth>) {
+        self.health = Some(health);
+    }
+
+    /// Trasmette barre colore e un riquadro in movimento al posto dello
+    /// schermo, per verificare la connessione. Effettivo dal prossimo `start`.
+    #[cfg(any(test, feature = "test-capture"))]
+    pub fn set_test_pattern(&mut self, enabled: bool) {
+        self.test_pattern = enabled;
+    }
+
+    /// Timbra i frame sull'orologio della cattura audio, così i due flussi
+    /// condividono la stessa timeline. Effettivo dal prossimo `start`.
+    pub fn set_clock(&mut self, clock: MediaClock) {
+        self.clock = clock;
+    }
+
+    // ── Simulcast ─────────────────────────────────────────────
+
+    /// Secondo encoder ridotto per i receiver lenti. Effettivo dal prossimo `start`.
+    pub fn set_simulcast(&mut self, simulcast: Simulcast) {
+        self.simulcast = simulcast;
+    }
+
+    /// Profondità di canali e pool, dal profilo di latenza. Effettiva dal prossimo `start`.
+    pub fn set_tuning(&mut self, tuning: PipelineTuning) {
+        self.opts_tx
+            .send_modify(|o| o.frame_pool = tuning.frame_pool);
+        self.tuning = tuning;
+    }
+
+    /// Frame del livello ridotto, `None` senza simulcast o se già presi.
+    pub fn take_simulcast_frames(&mut self) -> Option<mpsc::Receiver<EncodedFrame>> {
+        self.simulcast_rx.take()
+    }
+
+    /// Flag IDR e interruttore del livello ridotto (condivisi con il secondo encoder).
+    pub fn simulcast_control(&self) -> Option<(Arc<AtomicBool>, Arc<AtomicBool>)> {
+        self.simulcast_link
+            .as_ref()
+            .map(|link| (Arc::clone(&link.force_idr), Arc::clone(&link.active)))
+    }
+
     // ── Force IDR ─────────────────────────────────────────────
 
     /// Get the force_idr flag (shared with the encoder).
@@ -310,8 +787,19 @@ impl Capturer {
         Arc::clone(&self.force_idr)
     }
 
+    /// Encoder H.264 in uso, `None` prima dell'avvio
+    pub fn encoder_name(&self) -> Option<&str> {
+        self.encoder_name.as_deref()
+    }
+
     // ── Opzioni dinamiche ───────────────────────────────────────
 
+    /// Opzioni correnti della cattura, aggiornate live
+    #[cfg(feature = "remote-control")]
+    pub fn opts(&self) -> watch::Receiver<CaptureOpts> {
+        self.opts_rx.clone()
+    }
+
     /// Attiva/disattiva lo schermo nero (sostituisce i frame con dati vuoti).
     pub fn set_blank_screen(&self, blank: bool) {
         self.opts_tx.send_modify(|o| o.blank_screen = blank);
@@ -324,25 +812,336 @@ impl Capturer {
         info!("Capture paused flag: {}", paused);
     }
 
-    /// Imposta (o rimuove) l'area di crop.
-    pub fn set_crop(&self, rect: Option<CropRect>) {
-        self.opts_tx.send_modify(|o| o.crop = rect);
-        info!("Crop: {:?}", rect);
+    /// Imposta (o rimuove) l'area di crop, limitata al display selezionato.
+    ///
+    /// Un crop degenere o fuori dallo schermo viene rifiutato e il crop
+    /// corrente resta invariato.
+    pub fn set_crop(&self, rect: Option<CropRect>) -> anyhow::Result<()> {
+        let crop = match rect {
+            None => None,
+            Some(rect) => {
+                // `try_lock` è ok qui: il lock è tenuto solo durante start/stop
+                let display = match self.test_pattern_size() {
+                    Some(size) => size,
+                    None => self
+                        .capture
+                        .try_lock()
+                        .map(|cap| cap.display().resolution())
+                        .map_err(|_| anyhow!("Capture is busy, crop not applied"))?,
+                };
+                let crop = rect.clamped(display).ok_or_else(|| {
+                    anyhow!("Crop {:?} is outside the display {:?}", rect, display)
+                })?;
+                Some(crop)
+            }
+        };
+
+        self.opts_tx.send_modify(|o| o.crop = crop);
+        info!("Crop: {:?}", crop);
+        Ok(())
+    }
+
+    // ── Finestre escluse ────────────────────────────────────────
+
+    /// Finestre (parte del titolo o classe) coperte di nero nello stream,
+    /// cercate a ogni frame: vale subito anche con la cattura attiva.
+    pub fn set_privacy_exclude(&self, patterns: Vec<String>) {
+        info!("Excluded windows: {:?}", patterns);
+        #[cfg(not(target_os = "windows"))]
+        if !patterns.is_empty() {
+            log::warn!("Excluding windows from capture is only supported on Windows");
+        }
+        self.opts_tx.send_modify(|o| o.privacy_exclude = patterns);
+    }
+
+    // ── Follow finestra attiva ──────────────────────────────────
+
+    /// Fa seguire (o smette di far seguire) al crop la finestra in primo piano.
+    pub fn set_follow_window(&mut self, enabled: bool) {
+        if let Some(cancel) = self.follow_cancel.take() {
+            cancel.cancel();
+        }
+        self.follow_locked.store(false, Ordering::Relaxed);
+
+        if !enabled {
+            info!("Follow window: off");
+            return;
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            let Some(display) = self.selected_display() else {
+                error!("Follow window: no display selected");
+                return;
+            };
+            let display_rect = display.rect();
+
+            let cancel = CancellationToken::new();
+            let token = cancel.clone();
+            let opts_tx = self.opts_tx.clone();
+            let locked = Arc::clone(&self.follow_locked);
+
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(FOLLOW_POLL);
+                let mut candidate: Option<CropRect> = None;
+                let mut stable = 0u32;
+
+                loop {
+                    select! {
+                        _ = token.cancelled() => break,
+                        _ = interval.tick() => {}
+                    }
+                    if locked.load(Ordering::Relaxed) {
+                        continue;
+                    }
+
+                    let Some(crop) = super::wgc::window_follow::foreground_window_rect()
+                        .and_then(|rect| window_crop(rect, display_rect))
+                    else {
+                        continue;
+                    };
+
+                    if candidate != Some(crop) {
+                        candidate = Some(crop);
+                        stable = 0;
+                        continue;
+                    }
+
+                    stable += 1;
+                    if stable == FOLLOW_DEBOUNCE && opts_tx.borrow().crop != Some(crop) {
+                        opts_tx.send_modify(|o| o.crop = Some(crop));
+                        info!("Follow window: crop {:?}", crop);
+                    }
+                }
+            });
+
+            self.follow_cancel = Some(cancel);
+            info!("Follow window: on");
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        error!("Follow window is only supported on Windows");
+    }
+
+    pub fn is_following_window(&self) -> bool {
+        self.follow_cancel.is_some()
+    }
+
+    /// Blocca/sblocca il crop sulla finestra corrente; ritorna il nuovo stato.
+    pub fn toggle_follow_lock(&self) -> bool {
+        let locked = !self.follow_locked.fetch_xor(true, Ordering::Relaxed);
+        info!("Follow window locked: {}", locked);
+        locked
+    }
+
+    pub fn is_follow_locked(&self) -> bool {
+        self.follow_locked.load(Ordering::Relaxed)
+    }
+
+    // ── Follow puntatore ────────────────────────────────────────
+
+    /// Monitor tra cui può saltare il follow del puntatore: niente finestre
+    /// né "All displays". Vuoto fuori da Windows.
+    pub fn cursor_monitors(&self) -> Vec<<ScreenCaptureImpl as DisplaySelector>::Display> {
+        #[cfg(target_os = "windows")]
+        {
+            let mut displays = self.available_displays();
+            displays.retain(|display| !display.is_span() && !display.is_window());
+            displays
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        Vec::new()
+    }
+
+    /// Posizione in `monitors` di quello che contiene il puntatore.
+    pub fn display_under_cursor(
+        monitors: &[<ScreenCaptureImpl as DisplaySelector>::Display],
+    ) -> Option<usize> {
+        #[cfg(target_os = "windows")]
+        {
+            let (x, y) = super::wgc::window_follow::cursor_position()?;
+            let (x, y) = (x as f32, y as f32);
+            monitors.iter().position(|monitor| {
+                let (w, h, mx, my) = monitor.rect();
+                (mx..mx + w).contains(&x) && (my..my + h).contains(&y)
+            })
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            let _ = monitors;
+            None
+        }
+    }
+
+    /// Il puntatore si può nascondere con `set_show_cursor`
+    pub fn can_hide_cursor(&self) -> bool {
+        self.can_hide_cursor
+    }
+
+    /// Mostra o nasconde il puntatore nei frame catturati.
+    pub fn set_show_cursor(&self, show: bool) {
+        self.opts_tx.send_modify(|o| o.show_cursor = show);
+        info!("Cursor capture: {}", show);
+    }
+
+    /// Attiva (Some) o disattiva (None) l'evidenziazione del cursore.
+    pub fn set_cursor_highlight(&self, style: Option<CursorHighlight>) {
+        self.opts_tx.send_modify(|o| o.cursor_highlight = style);
+        info!("Cursor highlight: {}", style.is_some());
+    }
+
+    /// Attiva (Some) o disattiva (None) l'overlay dei tasti premuti.
+    pub fn set_keycast(&self, keycast: Option<Keycast>) {
+        info!("Keycast: {}", keycast.is_some());
+        self.opts_tx.send_modify(|o| o.keycast = keycast);
+    }
+
+    /// Attiva (Some) o rimuove (None) il logo sopra lo stream.
+    pub fn set_watermark(&self, watermark: Option<Watermark>) {
+        info!("Watermark: {:?}", watermark);
+        self.opts_tx.send_modify(|o| o.watermark = watermark);
+    }
+
+    /// Attiva (Some) o rimuove (None) l'orario impresso nello stream.
+    pub fn set_timestamp(&self, timestamp: Option<Timestamp>) {
+        info!("Timestamp: {:?}", timestamp);
+        self.opts_tx.send_modify(|o| o.timestamp = timestamp);
+    }
+
+    /// Apre (Some) o chiude (None) la lente d'ingrandimento.
+    pub fn set_zoom(&self, zoom: Option<Zoom>) {
+        info!("Zoom: {:?}", zoom);
+        self.opts_tx.send_modify(|o| o.zoom = zoom);
+    }
+
+    /// Imposta (Some, MB/min) o rimuove (None) il tetto di traffico. Ritorna
+    /// l'utilizzo condiviso da mostrare nella GUI.
+    pub fn set_data_budget(&self, mb_per_minute: Option<f32>) -> Option<BudgetUsage> {
+        info!("Data budget: {:?} MB/min", mb_per_minute);
+        let budget = mb_per_minute.map(|mb_per_minute| DataBudget {
+            mb_per_minute,
+            usage: BudgetUsage::default(),
+        });
+        let usage = budget.as_ref().map(|b| b.usage.clone());
+        self.opts_tx.send_modify(|o| o.data_budget = budget);
+        usage
+    }
+
+    /// Frame rate ridotto quando lo schermo non cambia, anche a cattura avviata
+    pub fn set_content_aware(&self, enabled: bool) {
+        self.opts_tx.send_modify(|o| o.content_aware = enabled);
+        info!("Content-aware frame rate: {}", enabled);
+    }
+
+    /// Effettivo dal prossimo encoder creato (avvio dello stream)
+    pub fn set_bitrate_mode(&self, mode: BitrateMode) {
+        self.opts_tx.send_modify(|o| o.bitrate_mode = mode);
+        info!("Bitrate mode: {}", mode);
+    }
+
+    /// Slice dell'encoder entro `bytes`, dal prossimo encoder creato
+    pub fn set_max_slice_size(&self, bytes: Option<usize>) {
+        self.opts_tx.send_modify(|o| o.max_slice_size = bytes);
+    }
+
+    /// Effettivo dal prossimo avvio della cattura: il formato del frame pool
+    /// non cambia a sessione aperta
+    pub fn set_hdr_mode(&self, mode: HdrMode) {
+        self.opts_tx.send_modify(|o| o.hdr = mode);
+        info!("HDR capture: {:?}", mode);
+    }
+
+    /// Imposta il profilo di uscita (risoluzione + fps). Se la cattura è attiva
+    /// l'encoder viene ricreato dal loop di cattura al frame successivo.
+    pub fn set_profile(&self, profile: StreamProfile) {
+        self.opts_tx.send_modify(|o| o.profile = profile);
+        info!("Stream profile: {}", profile);
+    }
+
+    /// Tetto alla risoluzione codificata, indipendente dal profilo. Come per
+    /// il profilo, l'encoder viene ricreato dal loop di cattura.
+    pub fn set_scale_to(&self, scale_to: Option<(u32, u32)>) {
+        self.opts_tx.send_modify(|o| o.scale_to = scale_to);
+        info!("Encode scale: {:?}", scale_to);
+    }
+
+    /// Profilo concreto (dimensioni effettive dell'encoder) da annunciare ai receiver.
+    pub async fn resolved_profile(&self) -> StreamProfile {
+        let (src_w, src_h) = self.source_size().await;
+        self.opts_rx.borrow().resolve_profile(src_w, src_h)
+    }
+
+    async fn source_size(&self) -> (u32, u32) {
+        let display = match self.test_pattern_size() {
+            Some(size) => size,
+            None => self.capture.lock().await.display().resolution(),
+        };
+        self.opts_rx.borrow().source_size(display)
+    }
+
+    /// Dimensioni del pattern di test, se trasmesso al posto del display
+    fn test_pattern_size(&self) -> Option<(u32, u32)> {
+        #[cfg(any(test, feature = "test-capture"))]
+        if self.test_pattern {
+            return Some(TEST_PATTERN_SIZE);
+        }
+        None
     }
 
     pub fn set_max_fps(&self, max_fps: u32) {
-        let max_fps = max_fps.clamp(15, FRAME_RATE.max(15));
+        let max_fps = max_fps.clamp(FpsCap::MIN, FpsCap::MAX);
         self.opts_tx.send_modify(|o| o.max_fps = max_fps);
     }
 
     pub fn fps_controller(&self) -> CaptureFpsController {
         let opts_tx = self.opts_tx.clone();
         Arc::new(move |max_fps| {
-            let max_fps = max_fps.clamp(15, FRAME_RATE.max(15));
+            let max_fps = max_fps.clamp(FpsCap::MIN, FpsCap::MAX);
             let _ = opts_tx.send_modify(|o| o.max_fps = max_fps);
         })
     }
 
+    /// Tetto di fps dell'utente, limitato alla frequenza del display
+    /// selezionato. Effettivo dal prossimo frame.
+    pub fn set_fps_cap(&mut self, cap: FpsCap) {
+        self.fps_cap = cap;
+        self.refresh_fps_ceiling();
+    }
+
+    fn refresh_fps_ceiling(&self) {
+        let refresh_rate = self
+            .selected_display()
+            .and_then(|display| ScreenCaptureImpl::refresh_rate(&display));
+        self.apply_fps_ceiling(refresh_rate);
+    }
+
+    /// Vero una volta dopo che la sorgente ha cambiato risoluzione o è stata
+    /// sostituita durante la cattura; riallinea anche il tetto di fps.
+    pub fn take_source_changed(&self) -> bool {
+        let changed = self.source_changed.swap(false, Ordering::AcqRel);
+        if changed {
+            self.refresh_fps_ceiling();
+        }
+        changed
+    }
+
+    /// Vero una volta dopo che la finestra o l'area condivisa è sparita:
+    /// lo stream è già oscurato, resta da avvisare l'utente.
+    pub fn take_source_lost(&self) -> bool {
+        self.source_lost.swap(false, Ordering::AcqRel)
+    }
+
+    fn apply_fps_ceiling(&self, refresh_rate: Option<u32>) {
+        let ceiling = self.fps_cap.ceiling(refresh_rate);
+        self.opts_tx.send_modify(|o| o.fps_ceiling = ceiling);
+        info!(
+            "Capture fps ceiling: {} (display: {:?} Hz)",
+            ceiling, refresh_rate
+        );
+    }
+
     // ── Display management ──────────────────────────────────────
 
     pub fn available_displays(&self) -> Vec<<ScreenCaptureImpl as DisplaySelector>::Display> {
@@ -356,6 +1155,71 @@ impl Capturer {
         }
     }
 
+    /// Finestre catturabili singolarmente (vuoto se il backend non le supporta)
+    pub fn available_windows(&self) -> Vec<<ScreenCaptureImpl as DisplaySelector>::Display> {
+        match self.capture.try_lock() {
+            Ok(mut cap) => cap.available_windows().unwrap_or_default(),
+            Err(_) => {
+                error!("Cannot list windows while capture is locked");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Cambia display. Con la cattura avviata (anche in pausa) riavvia solo
+    /// il backend sul nuovo display: il canale verso il server resta lo stesso
+    /// e il primo frame è un IDR, così i receiver non vedono interruzioni.
+    pub async fn switch_display(
+        &mut self,
+        display: <ScreenCaptureImpl as DisplaySelector>::Display,
+    ) -> anyhow::Result<()> {
+        let refresh_rate = ScreenCaptureImpl::refresh_rate(&display);
+        // Con il pattern di test il display scelto vale per la prossima cattura
+        let Some(frame_tx) = self
+            .frame_tx
+            .clone()
+            .filter(|_| self.test_pattern_size().is_none())
+        else {
+            self.capture.lock().await.select_display(&display)?;
+            self.apply_fps_ceiling(refresh_rate);
+            return Ok(());
+        };
+
+        // Il crop era relativo al display precedente
+        self.opts_tx.send_modify(|o| o.crop = None);
+
+        {
+            let mut cap = self.capture.lock().await;
+            cap.stop_capture().await?;
+            cap.select_display(&display)?;
+            self.apply_fps_ceiling(refresh_rate);
+
+            let (src_w, src_h) = self
+                .opts_rx
+                .borrow()
+                .source_size(cap.display().resolution());
+            let (enc_w, enc_h) = self.opts_rx.borrow().output_size(src_w, src_h);
+            let rate = self.opts_rx.borrow().rate_control(BudgetLevel::Full);
+            let max_slice_size = self.opts_rx.borrow().max_slice_size;
+            let mut encoder =
+                FfmpegEncoder::with_rate_control(src_w, src_h, enc_w, enc_h, rate, max_slice_size)?
+                    .with_frame_pool(self.tuning.frame_pool);
+            encoder.force_idr = self.force_idr.clone();
+            encoder.simulcast = self.simulcast_link.clone();
+            self.force_idr.store(true, Ordering::Relaxed);
+
+            cap.start_capture(encoder, frame_tx, self.opts_rx.clone())
+                .await?;
+        }
+
+        // Il follow usa la geometria del display: va ricalcolato
+        if self.follow_cancel.is_some() {
+            self.set_follow_window(true);
+        }
+        info!("Display switched to {}", display.to_string());
+        Ok(())
+    }
+
     pub fn select_display(&self, display: <ScreenCaptureImpl as DisplaySelector>::Display) {
         if self.is_playing() {
             error!("Cannot change display while capture is running");
@@ -363,11 +1227,10 @@ impl Capturer {
         }
 
         match self.capture.try_lock() {
-            Ok(mut cap) => {
-                if let Err(e) = cap.select_display(&display) {
-                    error!("Failed to select display: {}", e);
-                }
-            }
+            Ok(mut cap) => match cap.select_display(&display) {
+                Ok(()) => self.apply_fps_ceiling(ScreenCaptureImpl::refresh_rate(&display)),
+                Err(e) => error!("Failed to select display: {}", e),
+            },
             Err(_) => {
                 error!("Cannot change display while capture is running");
             }
@@ -381,3 +1244,116 @@ impl Capturer {
         }
     }
 }
+
+/// Avvia la cattura dello schermo; ritorna gli eventi della sorgente, se il
+/// backend li osserva.
+async fn start_screen(
+    capture: &Mutex<ScreenCaptureImpl>,
+    health: &Option<Arc<PipelineHealth>>,
+    encoder: FfmpegEncoder,
+    frame_tx: mpsc::Sender<EncodedVideo>,
+    opts_rx: watch::Receiver<CaptureOpts>,
+) -> anyhow::Result<Option<watch::Receiver<SourceEvent>>> {
+    let mut cap = capture.lock().await;
+    if let Some(health) = health {
+        cap.set_health(Arc::clone(health));
+    }
+    cap.start_capture(encoder, frame_tx, opts_rx)
+        .await
+        .map(|_| cap.source_events())
+}
+
+/// Prossimo evento della sorgente; senza eventi dal backend non si risolve mai
+async fn next_source_event(
+    events: &mut Option<watch::Receiver<SourceEvent>>,
+) -> Option<SourceEvent> {
+    match events {
+        Some(events) => {
+            events.changed().await.ok()?;
+            Some(*events.borrow_and_update())
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Prossimo frame del livello ridotto; senza simulcast non si risolve mai
+async fn next_simulcast_frame(
+    frames: &mut Option<mpsc::Receiver<EncodedVideo>>,
+) -> Option<EncodedVideo> {
+    match frames {
+        Some(frames) => frames.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Oscura lo stream al posto della sorgente sparita e lo segnala al caster
+fn blank_lost_source(opts_tx: &watch::Sender<CaptureOpts>, source_lost: &AtomicBool) {
+    opts_tx.send_modify(|o| o.blank_screen = true);
+    source_lost.store(true, Ordering::Release);
+}
+
+/// Il display catturato è sparito (monitor scollegato, finestra chiusa):
+/// la cattura riparte dal display principale, con un IDR per i receiver.
+/// Ritorna il nome del nuovo display.
+async fn fallback_to_primary(
+    capture: &Mutex<ScreenCaptureImpl>,
+    frame_tx: mpsc::Sender<EncodedVideo>,
+    opts_tx: &watch::Sender<CaptureOpts>,
+    force_idr: &Arc<AtomicBool>,
+    simulcast: &Option<SimulcastLink>,
+) -> anyhow::Result<String> {
+    let mut cap = capture.lock().await;
+    cap.stop_capture().await?;
+
+    let mut displays = cap.available_displays()?;
+    if displays.is_empty() {
+        return Err(anyhow!("no displays available"));
+    }
+    let primary = displays
+        .iter()
+        .position(ScreenCaptureImpl::is_primary)
+        .unwrap_or(0);
+    let display = displays.swap_remove(primary);
+    cap.select_display(&display)?;
+
+    // Il crop era relativo al display precedente
+    opts_tx.send_modify(|o| o.crop = None);
+    let opts = opts_tx.borrow().clone();
+    let (src_w, src_h) = opts.source_size(cap.display().resolution());
+    let (enc_w, enc_h) = opts.output_size(src_w, src_h);
+    let rate = opts.rate_control(BudgetLevel::Full);
+    let mut encoder =
+        FfmpegEncoder::with_rate_control(src_w, src_h, enc_w, enc_h, rate, opts.max_slice_size)?
+            .with_frame_pool(opts.frame_pool);
+    encoder.force_idr = force_idr.clone();
+    encoder.simulcast = simulcast.clone();
+    force_idr.store(true, Ordering::Relaxed);
+
+    cap.start_capture(encoder, frame_tx, opts_tx.subscribe())
+        .await?;
+    Ok(display.to_string())
+}
+
+/// Interseca il rettangolo della finestra (desktop virtuale) con il monitor
+/// `(w, h, x, y)` e lo converte in un crop relativo al monitor, allineato a 2.
+#[cfg(target_os = "windows")]
+fn window_crop(
+    (left, top, right, bottom): (i32, i32, i32, i32),
+    (dw, dh, dx, dy): (f32, f32, f32, f32),
+) -> Option<CropRect> {
+    const MIN_SIZE: i32 = 64;
+    let (dx, dy, dw, dh) = (dx as i32, dy as i32, dw as i32, dh as i32);
+
+    let x0 = (left.clamp(dx, dx + dw) - dx) & !1;
+    let y0 = (top.clamp(dy, dy + dh) - dy) & !1;
+    let x1 = right.clamp(dx, dx + dw) - dx;
+    let y1 = bottom.clamp(dy, dy + dh) - dy;
+    let (w, h) = ((x1 - x0) & !1, (y1 - y0) & !1);
+
+    (w >= MIN_SIZE && h >= MIN_SIZE).then_some(CropRect {
+        x: x0 as u32,
+        y: y0 as u32,
+        w: w as u32,
+        h: h as u32,
+    })
+}
@@ -0,0 +1,13 @@
+//! Human-readable names for the display list
+
+/// Name used for laptop panels, which often report no EDID name
+pub const BUILT_IN_LABEL: &str = "Built-in";
+
+/// "Name (W x H)", or "Display N (W x H)" when the OS gives no usable name.
+/// `number` is 1-based, as in the OS display settings.
+pub fn display_label(name: Option<&str>, number: usize, (width, height): (u32, u32)) -> String {
+    match name.map(str::trim).filter(|n| !n.is_empty()) {
+        Some(name) => format!("{} ({} x {})", name, width, height),
+        None => format!("Display {} ({} x {})", number, width, height),
+    }
+}
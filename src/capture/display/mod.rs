@@ -2,6 +2,22 @@
 //!
 //! Provides traits and types for display selection in screen capture.
 
+pub mod label;
 mod selector;
+pub mod span;
+pub mod thumbnail;
 
 pub use selector::DisplaySelector;
+pub use span::DisplayBounds;
+pub use thumbnail::Thumbnail;
+
+/// Frequenza del monitor principale in Hz, senza aprire una sessione di
+/// cattura. `None` se il sistema non la riporta (es. sotto Wayland).
+pub fn primary_refresh_rate() -> Option<u32> {
+    let displays = display_info::DisplayInfo::all().ok()?;
+    let primary = displays
+        .iter()
+        .find(|d| d.is_primary)
+        .or(displays.first())?;
+    (primary.frequency >= 1.5).then(|| primary.frequency.round() as u32)
+}
@@ -3,6 +3,7 @@
 //! This module provides the `DisplaySelector` trait for selecting
 //! which display to capture.
 
+use crate::capture::display::Thumbnail;
 use anyhow::Result;
 
 /// Trait for selecting a display for screen capture.
@@ -15,9 +16,50 @@ pub trait DisplaySelector {
     /// Returns a list of available displays.
     fn available_displays(&mut self) -> Result<Vec<Self::Display>>;
 
+    /// Returns the top-level windows that can be captured on their own.
+    ///
+    /// They are selected with [`select_display`](Self::select_display) like a
+    /// monitor. Backends without per-window capture return an empty list.
+    fn available_windows(&mut self) -> Result<Vec<Self::Display>> {
+        Ok(Vec::new())
+    }
+
     /// Selects a display for capture.
     fn select_display(&mut self, display: &Self::Display) -> Result<()>;
 
     /// Returns the currently selected display, if any.
     fn selected_display(&self) -> Result<Option<Self::Display>>;
+
+    /// Whether `display` is the OS primary monitor, marked in the display list.
+    fn is_primary(_display: &Self::Display) -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
+
+    /// `(width, height, x, y)` of `display` in physical pixels of the virtual
+    /// desktop, to map points on the stream back to the screen.
+    fn display_rect(display: &Self::Display) -> (f32, f32, f32, f32)
+    where
+        Self: Sized;
+
+    /// Current refresh rate of `display` in Hz, the ceiling for the capture
+    /// frame rate. `None` when the backend can't tell.
+    fn refresh_rate(_display: &Self::Display) -> Option<u32>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// One-shot low-res grab of `display`, at most `max_width` pixels wide.
+    ///
+    /// Returns `None` when the backend can't grab outside a capture session.
+    fn thumbnail(_display: &Self::Display, _max_width: u32) -> Result<Option<Thumbnail>>
+    where
+        Self: Sized,
+    {
+        Ok(None)
+    }
 }
@@ -0,0 +1,116 @@
+//! Virtual canvas spanning every monitor
+//!
+//! Backends expose a synthetic "All Displays" entry: the encoded frame is the
+//! bounding box of the whole desktop, each monitor is copied at its offset
+//! and the gaps between monitors of different sizes stay black.
+
+use crate::capture::YUVFrame;
+
+/// Label of the synthetic entry in the display list
+pub const ALL_DISPLAYS_LABEL: &str = "All Displays";
+
+/// Monitor area in desktop coordinates (the origin can be negative)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl DisplayBounds {
+    /// Bounding box of all the monitors, `None` without monitors.
+    /// Width and height are rounded up to even values for NV12.
+    pub fn union(bounds: &[DisplayBounds]) -> Option<DisplayBounds> {
+        let left = bounds.iter().map(|b| b.x).min()?;
+        let top = bounds.iter().map(|b| b.y).min()?;
+        let right = bounds.iter().map(|b| b.x + b.width as i32).max()?;
+        let bottom = bounds.iter().map(|b| b.y + b.height as i32).max()?;
+
+        let width = (right - left).max(2) as u32;
+        let height = (bottom - top).max(2) as u32;
+        Some(DisplayBounds {
+            x: left,
+            y: top,
+            width: width + (width % 2),
+            height: height + (height % 2),
+        })
+    }
+
+    /// Offset of `self` inside the `canvas` bounds, aligned to even pixels
+    pub fn offset_in(&self, canvas: &DisplayBounds) -> (u32, u32) {
+        (
+            ((self.x - canvas.x).max(0) as u32) & !1,
+            ((self.y - canvas.y).max(0) as u32) & !1,
+        )
+    }
+
+    /// Name shown in the display picklist for the spanning canvas
+    pub fn span_label(&self) -> String {
+        format!("{} ({} x {})", ALL_DISPLAYS_LABEL, self.width, self.height)
+    }
+}
+
+/// Black NV12 canvas (Y 0, UV 128) of the given size, as the blank screen frames
+pub fn black_canvas(width: u32, height: u32) -> YUVFrame {
+    let (w, h) = (width + (width % 2), height + (height % 2));
+    YUVFrame {
+        display_time: 0,
+        width: w as i32,
+        height: h as i32,
+        luminance_bytes: vec![0u8; (w * h) as usize],
+        luminance_stride: w as i32,
+        chrominance_bytes: vec![128u8; (w * h / 2) as usize],
+        chrominance_stride: w as i32,
+    }
+}
+
+/// Copy the whole `canvas` into `frame`, reusing its buffers: the overlays
+/// are drawn on the copy and the canvas stays clean for the next tick
+pub fn copy_canvas_into(canvas: &YUVFrame, frame: &mut YUVFrame) {
+    frame.luminance_bytes.clear();
+    frame
+        .luminance_bytes
+        .extend_from_slice(&canvas.luminance_bytes);
+    frame.chrominance_bytes.clear();
+    frame
+        .chrominance_bytes
+        .extend_from_slice(&canvas.chrominance_bytes);
+    frame.display_time = canvas.display_time;
+    frame.width = canvas.width;
+    frame.height = canvas.height;
+    frame.luminance_stride = canvas.luminance_stride;
+    frame.chrominance_stride = canvas.chrominance_stride;
+}
+
+/// Copy an NV12 frame into `canvas` at `(x, y)`, clipped to the canvas
+pub fn blit_nv12(canvas: &mut YUVFrame, src: &YUVFrame, (x, y): (u32, u32)) {
+    let (x, y) = ((x & !1) as usize, (y & !1) as usize);
+    let (cw, ch) = (canvas.width as usize, canvas.height as usize);
+    if x >= cw || y >= ch {
+        return;
+    }
+    let w = (src.width as usize).min(cw - x) & !1;
+    let h = (src.height as usize).min(ch - y) & !1;
+
+    let (dst_stride, src_stride) = (
+        canvas.luminance_stride as usize,
+        src.luminance_stride as usize,
+    );
+    for row in 0..h {
+        let dst = (y + row) * dst_stride + x;
+        let from = row * src_stride;
+        canvas.luminance_bytes[dst..dst + w].copy_from_slice(&src.luminance_bytes[from..from + w]);
+    }
+
+    let (dst_stride, src_stride) = (
+        canvas.chrominance_stride as usize,
+        src.chrominance_stride as usize,
+    );
+    for row in 0..h / 2 {
+        let dst = (y / 2 + row) * dst_stride + x;
+        let from = row * src_stride;
+        canvas.chrominance_bytes[dst..dst + w]
+            .copy_from_slice(&src.chrominance_bytes[from..from + w]);
+    }
+}
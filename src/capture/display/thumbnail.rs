@@ -0,0 +1,50 @@
+//! Display thumbnails
+//!
+//! Low-resolution one-shot grabs shown by the display selector. They are taken
+//! outside any capture session, so backends that can only grab while capturing
+//! return `None` and the selector falls back to the display name.
+
+use crate::capture::ScreenCaptureImpl;
+use crate::capture::display::DisplaySelector;
+use std::time::Duration;
+
+/// Larghezza massima delle miniature
+pub const THUMBNAIL_WIDTH: u32 = 96;
+
+/// Intervallo di aggiornamento delle miniature nel selettore
+pub const THUMBNAIL_REFRESH: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA8, righe contigue
+    pub rgba: Vec<u8>,
+}
+
+/// Dimensioni della miniatura mantenendo le proporzioni del display
+pub fn thumbnail_size((width, height): (u32, u32), max_width: u32) -> (u32, u32) {
+    let w = width.clamp(1, max_width.max(1));
+    let h = (height as u64 * w as u64 / width.max(1) as u64).max(1) as u32;
+    (w, h)
+}
+
+/// Miniature di tutti i display, nello stesso ordine (`None` se non disponibile)
+pub async fn grab_thumbnails(
+    displays: Vec<<ScreenCaptureImpl as DisplaySelector>::Display>,
+) -> Vec<Option<Thumbnail>> {
+    let count = displays.len();
+    tokio::task::spawn_blocking(move || {
+        displays
+            .iter()
+            .map(|display| {
+                ScreenCaptureImpl::thumbnail(display, THUMBNAIL_WIDTH).unwrap_or_else(|e| {
+                    log::debug!("Thumbnail of {} failed: {}", display.to_string(), e);
+                    None
+                })
+            })
+            .collect()
+    })
+    .await
+    .unwrap_or_else(|_| vec![None; count])
+}
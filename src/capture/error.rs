@@ -0,0 +1,51 @@
+//! Errori di avvio della cattura
+//!
+//! Distinguono i casi che l'utente può risolvere (nessun monitor, permesso
+//! negato, driver grafico) così la GUI mostra un messaggio comprensibile
+//! invece di andare in panic.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureError {
+    /// Nessun monitor collegato o visibile alla sessione
+    NoDisplays,
+    /// Il sistema ha negato la cattura dello schermo
+    PermissionDenied,
+    /// La piattaforma non supporta l'API di cattura
+    Unsupported,
+    /// Creazione del device grafico (D3D11) fallita
+    GraphicsInit(String),
+    /// Qualsiasi altro errore del backend di cattura
+    Backend(String),
+}
+
+impl CaptureError {
+    /// Recupera un `CaptureError` dentro una catena anyhow, il resto diventa `Backend`
+    pub fn from_anyhow(error: anyhow::Error) -> Self {
+        match error.downcast::<CaptureError>() {
+            Ok(error) => error,
+            Err(error) => CaptureError::Backend(format!("{:#}", error)),
+        }
+    }
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::NoDisplays => f.write_str("No display available to capture"),
+            CaptureError::PermissionDenied => {
+                f.write_str("Screen capture permission was denied by the system")
+            }
+            CaptureError::Unsupported => {
+                f.write_str("Screen capture is not supported on this system")
+            }
+            CaptureError::GraphicsInit(e) => {
+                write!(f, "Failed to initialize the graphics device: {}", e)
+            }
+            CaptureError::Backend(e) => write!(f, "Screen capture failed to start: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
@@ -1,30 +1,76 @@
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
-use bytes::Bytes;
 use display_info::DisplayInfo as OsDisplayInfo;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use std::time::{Duration, Instant};
 use tokio::sync::watch;
 use tokio_util::sync::CancellationToken;
 
-use crate::assets::FRAME_RATE;
+use crate::capture::budget::BudgetLevel;
 use crate::capture::display::DisplaySelector;
+use crate::capture::display::label::display_label;
+use crate::capture::display::span::DisplayBounds;
 use crate::capture::{
-    CaptureOpts, CropRect, DisplayInfo, ScreenCapture, ScreenCaptureImpl, YUVFrame,
+    CaptureError, CaptureOpts, CropRect, DisplayInfo, ScreenCapture, ScreenCaptureImpl, YUVFrame,
 };
-use crate::encoder::{FfmpegEncoder, FrameData};
+use crate::encoder::{EncodedVideo, FfmpegEncoder, FrameData};
+use crate::pipeline::health::{DropSource, PipelineHealth};
 
 #[derive(Clone, Debug)]
 pub struct GenericDisplay {
     pub id: u32,
     pub name: String,
+    pub x: i32,
+    pub y: i32,
     pub width: u32,
     pub height: u32,
     pub scale_factor: f64,
+    pub primary: bool,
+    /// Frequenza in Hz ("All Displays": la più bassa)
+    pub refresh_rate: Option<u32>,
+}
+
+impl GenericDisplay {
+    /// Id della voce sintetica "All Displays"
+    pub const ALL_DISPLAYS_ID: u32 = u32::MAX;
+
+    pub fn is_span(&self) -> bool {
+        self.id == Self::ALL_DISPLAYS_ID
+    }
+
+    fn bounds(&self) -> DisplayBounds {
+        DisplayBounds {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Voce che copre l'intero desktop: bounding box di tutti i monitor
+    fn span(displays: &[GenericDisplay]) -> Option<GenericDisplay> {
+        let bounds: Vec<DisplayBounds> = displays.iter().map(|d| d.bounds()).collect();
+        let union = DisplayBounds::union(&bounds)?;
+        Some(GenericDisplay {
+            id: Self::ALL_DISPLAYS_ID,
+            name: union.span_label(),
+            x: union.x,
+            y: union.y,
+            width: union.width,
+            height: union.height,
+            scale_factor: 1.0,
+            primary: false,
+            refresh_rate: displays.iter().filter_map(|d| d.refresh_rate).min(),
+        })
+    }
 }
 
 impl PartialEq for GenericDisplay {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
+            && self.x == other.x
+            && self.y == other.y
             && self.name == other.name
             && self.width == other.width
             && self.height == other.height
@@ -55,25 +101,45 @@ impl DisplayInfo for GenericDisplay {
 pub struct GenericScreenCapture {
     selected_display: GenericDisplay,
     cancel_token: Option<CancellationToken>,
+    health: Option<Arc<PipelineHealth>>,
 }
 
 impl GenericScreenCapture {
+    pub fn new() -> Result<Self, CaptureError> {
+        let displays = Self::load_displays().map_err(CaptureError::from_anyhow)?;
+        Ok(Self {
+            selected_display: displays[0].clone(),
+            cancel_token: None,
+            health: None,
+        })
+    }
+
     fn load_displays() -> Result<Vec<GenericDisplay>> {
         let displays =
             OsDisplayInfo::all().map_err(|e| anyhow!("Failed to enumerate displays: {}", e))?;
 
         let mut out = Vec::with_capacity(displays.len());
-        for d in displays {
+        for (i, d) in displays.into_iter().enumerate() {
             out.push(GenericDisplay {
                 id: d.id,
-                name: format!("{} ({} x {})", d.name, d.width, d.height),
+                name: display_label(Some(&d.name), i + 1, (d.width, d.height)),
+                x: d.x,
+                y: d.y,
                 width: d.width,
                 height: d.height,
                 scale_factor: d.scale_factor as f64,
+                primary: d.is_primary,
+                refresh_rate: (d.frequency >= 1.5).then(|| d.frequency.round() as u32),
             });
         }
         if out.is_empty() {
-            return Err(anyhow!("No displays found"));
+            return Err(CaptureError::NoDisplays.into());
+        }
+        // Con più monitor si può trasmettere anche l'intero desktop
+        if out.len() > 1
+            && let Some(span) = GenericDisplay::span(&out)
+        {
+            out.push(span);
         }
         Ok(out)
     }
@@ -95,13 +161,8 @@ impl GenericScreenCapture {
 
 #[async_trait]
 impl ScreenCapture for GenericScreenCapture {
-    fn new_default() -> Result<ScreenCaptureImpl, anyhow::Error> {
-        let displays = Self::load_displays()?;
-        let selected_display = displays[0].clone();
-        Ok(Self {
-            selected_display,
-            cancel_token: None,
-        })
+    fn new_default() -> Result<ScreenCaptureImpl, CaptureError> {
+        Self::new().map(Into::into)
     }
 
     fn display(&self) -> &dyn DisplayInfo {
@@ -111,7 +172,7 @@ impl ScreenCapture for GenericScreenCapture {
     async fn start_capture(
         &mut self,
         mut encoder: FfmpegEncoder,
-        output: tokio::sync::mpsc::Sender<Bytes>,
+        output: tokio::sync::mpsc::Sender<EncodedVideo>,
         opts_rx: watch::Receiver<CaptureOpts>,
     ) -> Result<(), anyhow::Error> {
         if self.cancel_token.is_some() {
@@ -122,15 +183,20 @@ impl ScreenCapture for GenericScreenCapture {
         self.cancel_token = Some(cancel.clone());
 
         let (dw, dh) = self.selected_display.resolution();
+        let force_idr = encoder.force_idr.clone();
+        let simulcast = encoder.simulcast.clone();
+        let health = self.health.clone();
         tokio::spawn(async move {
             let opts_rx = opts_rx;
             let mut current_crop: Option<CropRect> = opts_rx.borrow().crop;
+            let mut current_profile = opts_rx.borrow().profile;
+            let mut current_scale_to = opts_rx.borrow().scale_to;
             let mut black_frame = if let Some(c) = current_crop {
                 GenericScreenCapture::black_frame(c.w, c.h)
             } else {
                 GenericScreenCapture::black_frame(dw, dh)
             };
-            let mut current_fps = opts_rx.borrow().max_fps.clamp(15, FRAME_RATE.max(15));
+            let mut current_fps = opts_rx.borrow().fps_limit();
             let mut pressure_score: u32 = 0;
             let started = Instant::now();
 
@@ -146,21 +212,41 @@ impl ScreenCapture for GenericScreenCapture {
                     continue;
                 }
 
-                let max_fps = opts.max_fps.clamp(15, FRAME_RATE.max(15));
+                let max_fps = opts.fps_limit();
                 if current_fps > max_fps {
                     current_fps = max_fps;
                 }
 
-                if opts.crop != current_crop {
+                if opts.crop != current_crop
+                    || opts.profile != current_profile
+                    || opts.scale_to != current_scale_to
+                {
                     current_crop = opts.crop;
-                    let (w, h) = if let Some(c) = current_crop {
-                        (c.w, c.h)
-                    } else {
-                        (dw, dh)
-                    };
+                    current_profile = opts.profile;
+                    current_scale_to = opts.scale_to;
+                    let (w, h) = opts.source_size((dw, dh));
                     black_frame = GenericScreenCapture::black_frame(w, h);
-                    encoder =
-                        FfmpegEncoder::new(black_frame.width as u32, black_frame.height as u32);
+                    let (src_w, src_h) = (black_frame.width as u32, black_frame.height as u32);
+                    let (enc_w, enc_h) = opts.output_size(src_w, src_h);
+                    let rate = opts.rate_control(BudgetLevel::Full);
+                    let rebuilt = FfmpegEncoder::with_rate_control(
+                        src_w,
+                        src_h,
+                        enc_w,
+                        enc_h,
+                        rate,
+                        opts.max_slice_size,
+                    );
+                    encoder = match rebuilt {
+                        Ok(encoder) => encoder.with_frame_pool(opts.frame_pool),
+                        Err(e) => {
+                            log::error!("Encoder rebuild failed, capture stopped: {}", e);
+                            break;
+                        }
+                    };
+                    encoder.force_idr = force_idr.clone();
+                    encoder.simulcast = simulcast.clone();
+                    force_idr.store(true, Ordering::Relaxed);
                 }
 
                 let frame_data = if opts.blank_screen {
@@ -169,9 +255,12 @@ impl ScreenCapture for GenericScreenCapture {
                     // Generic fallback backend: keep deterministic timing and format.
                     FrameData::NV12(&black_frame)
                 };
-                match encoder.encode(frame_data, started.elapsed().as_micros() as i64) {
+                match encoder.encode(frame_data, FfmpegEncoder::frame_time(started.elapsed())) {
                     Ok(encoded) => {
                         if output.try_send(encoded).is_err() {
+                            if let Some(health) = &health {
+                                health.record_frame_drop(DropSource::Capture);
+                            }
                             pressure_score = (pressure_score + 3).min(100);
                             current_fps = current_fps.saturating_sub(5).max(15);
                         } else {
@@ -213,6 +302,10 @@ impl ScreenCapture for GenericScreenCapture {
         }
         Ok(())
     }
+
+    fn set_health(&mut self, health: Arc<PipelineHealth>) {
+        self.health = Some(health);
+    }
 }
 
 impl DisplaySelector for GenericScreenCapture {
@@ -230,4 +323,21 @@ impl DisplaySelector for GenericScreenCapture {
     fn selected_display(&self) -> Result<Option<Self::Display>> {
         Ok(Some(self.selected_display.clone()))
     }
+
+    fn is_primary(display: &Self::Display) -> bool {
+        display.primary
+    }
+
+    fn display_rect(display: &Self::Display) -> (f32, f32, f32, f32) {
+        (
+            display.width as f32,
+            display.height as f32,
+            display.x as f32,
+            display.y as f32,
+        )
+    }
+
+    fn refresh_rate(display: &Self::Display) -> Option<u32> {
+        display.refresh_rate
+    }
 }
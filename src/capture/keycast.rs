@@ -0,0 +1,207 @@
+//! Keycast: mostra sullo stream le ultime combinazioni di tasti premute.
+//!
+//! Le etichette arrivano dal listener globale della tastiera e vengono
+//! disegnate sul frame NV12 prima dell'encoding, con dissolvenza in uscita.
+
+use crate::capture::YUVFrame;
+use crate::capture::bitmap_font::{GLYPH_HEIGHT, text_width};
+use crate::capture::overlay::{Nv12Canvas, YuvColor};
+use iced::keyboard::key::Named;
+use iced::keyboard::{Key, Modifiers};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Numero massimo di etichette visibili contemporaneamente
+const MAX_VISIBLE: usize = 3;
+/// Tempo di permanenza di un'etichetta (dissolvenza compresa)
+const VISIBLE_FOR: Duration = Duration::from_millis(2500);
+/// Durata della dissolvenza finale
+const FADE_OUT: Duration = Duration::from_millis(500);
+/// Lo stesso tasto può arrivare sia dal listener globale che da iced
+const DEDUP_WINDOW: Duration = Duration::from_millis(80);
+
+#[derive(Debug)]
+struct KeyStroke {
+    label: String,
+    at: Instant,
+}
+
+/// Coda condivisa delle ultime combinazioni premute.
+///
+/// Clonabile a basso costo: il thread GUI scrive con `push`, il loop di
+/// cattura legge con `visible` ad ogni frame.
+#[derive(Debug, Clone, Default)]
+pub struct Keycast {
+    strokes: Arc<Mutex<VecDeque<KeyStroke>>>,
+}
+
+impl Keycast {
+    pub fn push(&self, label: String) {
+        let Ok(mut strokes) = self.strokes.lock() else {
+            return;
+        };
+
+        if let Some(last) = strokes.back()
+            && last.label == label
+            && last.at.elapsed() < DEDUP_WINDOW
+        {
+            return;
+        }
+
+        strokes.push_back(KeyStroke {
+            label,
+            at: Instant::now(),
+        });
+        while strokes.len() > MAX_VISIBLE {
+            strokes.pop_front();
+        }
+    }
+
+    /// Etichette ancora visibili con la rispettiva opacità (0.0..=1.0),
+    /// dalla più vecchia alla più recente.
+    pub fn visible(&self) -> Vec<(String, f32)> {
+        let Ok(mut strokes) = self.strokes.lock() else {
+            return Vec::new();
+        };
+
+        strokes.retain(|s| s.at.elapsed() < VISIBLE_FOR);
+        strokes
+            .iter()
+            .map(|s| {
+                let left = VISIBLE_FOR.saturating_sub(s.at.elapsed());
+                let alpha = (left.as_secs_f32() / FADE_OUT.as_secs_f32()).min(1.0);
+                (s.label.clone(), alpha)
+            })
+            .collect()
+    }
+}
+
+/// Quali tasti mostrare: le combinazioni con Ctrl/Alt/Win sempre, i tasti
+/// singoli solo se in whitelist (evita di trasmettere ciò che si digita).
+#[derive(Debug, Clone)]
+pub struct KeycastFilter {
+    pub whitelist: Vec<Named>,
+}
+
+impl Default for KeycastFilter {
+    fn default() -> Self {
+        Self {
+            whitelist: vec![
+                Named::F1,
+                Named::F2,
+                Named::F3,
+                Named::F4,
+                Named::F5,
+                Named::F6,
+                Named::F7,
+                Named::F8,
+                Named::F9,
+                Named::F10,
+                Named::F11,
+                Named::F12,
+                Named::Escape,
+                Named::Enter,
+                Named::Pause,
+            ],
+        }
+    }
+}
+
+impl KeycastFilter {
+    /// Etichetta da mostrare per la combinazione, `None` se va filtrata.
+    pub fn label(&self, modifiers: Modifiers, key: &Key) -> Option<String> {
+        let key_label = match key {
+            Key::Character(c) => c.to_uppercase(),
+            Key::Named(named) => format!("{:?}", named),
+            _ => return None,
+        };
+
+        // Windows riporta AltGr come Ctrl+Alt: se il tasto dà un simbolo
+        // (@, €, [...) è testo digitato, non una scorciatoia
+        let alt_gr = modifiers.control()
+            && modifiers.alt()
+            && matches!(key, Key::Character(c) if !c.chars().all(|c| c.is_ascii_alphanumeric()));
+        if alt_gr {
+            return None;
+        }
+
+        let is_combo = modifiers.control() || modifiers.alt() || modifiers.logo();
+        let whitelisted = matches!(key, Key::Named(named) if self.whitelist.contains(named));
+        if !is_combo && !whitelisted {
+            return None;
+        }
+
+        let mut label = String::new();
+        if modifiers.control() {
+            label.push_str("Ctrl+");
+        }
+        if modifiers.alt() {
+            label.push_str("Alt+");
+        }
+        if modifiers.shift() {
+            label.push_str("Shift+");
+        }
+        if modifiers.logo() {
+            label.push_str("Win+");
+        }
+        label.push_str(&key_label);
+        Some(label)
+    }
+}
+
+/// Disegna le etichette in basso a sinistra, la più recente in fondo.
+pub fn draw_keycast(frame: &mut YUVFrame, labels: &[(String, f32)]) {
+    if labels.is_empty() {
+        return;
+    }
+
+    let background = YuvColor::from_rgb([20, 20, 20]);
+    let foreground = YuvColor::from_rgb([255, 255, 255]);
+
+    let mut canvas = Nv12Canvas::new(frame);
+    let scale = (canvas.height() / 270).max(2);
+    let padding = 3 * scale;
+    let margin = 6 * scale;
+    let box_h = GLYPH_HEIGHT * scale + 2 * padding;
+
+    let mut y = canvas.height() - margin - box_h;
+    for (label, alpha) in labels.iter().rev() {
+        let box_w = text_width(label, scale) + 2 * padding;
+        canvas.fill_rect(margin, y, box_w, box_h, background, 0.7 * alpha);
+        canvas.text(
+            margin + padding,
+            y + padding,
+            label,
+            scale,
+            foreground,
+            *alpha,
+        );
+        y -= box_h + scale * 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn altgr_characters_are_not_shown() {
+        let filter = KeycastFilter::default();
+        let ctrl_alt = Modifiers::CTRL | Modifiers::ALT;
+        assert_eq!(filter.label(ctrl_alt, &Key::Character("@".into())), None);
+        assert_eq!(filter.label(ctrl_alt, &Key::Character("€".into())), None);
+        assert_eq!(
+            filter
+                .label(ctrl_alt, &Key::Character("t".into()))
+                .as_deref(),
+            Some("Ctrl+Alt+T")
+        );
+        assert_eq!(
+            filter
+                .label(Modifiers::CTRL, &Key::Character("[".into()))
+                .as_deref(),
+            Some("Ctrl+[")
+        );
+    }
+}
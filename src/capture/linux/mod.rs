@@ -0,0 +1,199 @@
+//! Cattura su Linux.
+//!
+//! Sotto Wayland il grab diretto dello schermo non è permesso: si passa dal
+//! portal ScreenCast (xdg-desktop-portal) e i frame arrivano da PipeWire.
+//! Su X11, o se il portal non è disponibile, resta il backend generico.
+
+mod pipewire_stream;
+mod portal;
+mod portal_capture;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, watch};
+
+use crate::capture::display::DisplaySelector;
+use crate::capture::generic::{GenericDisplay, GenericScreenCapture};
+use crate::capture::{CaptureError, CaptureOpts, DisplayInfo, ScreenCapture, ScreenCaptureImpl};
+use crate::encoder::{EncodedVideo, FfmpegEncoder};
+use crate::pipeline::health::PipelineHealth;
+pub use portal::PortalDisplay;
+use portal::PortalSession;
+pub use portal_capture::PortalCapture;
+
+/// Esito di [`prepare_capture`], consumato dalla prossima `new_default`
+static PREPARED: Mutex<Option<Result<PortalSession, CaptureError>>> = Mutex::new(None);
+
+/// Sessione grafica Wayland, anche con XWayland disponibile
+fn is_wayland_session() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+        || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t.eq_ignore_ascii_case("wayland"))
+}
+
+/// La cattura passa dal portal ScreenCast, che può chiedere all'utente
+/// quale schermo condividere
+pub fn uses_portal() -> bool {
+    is_wayland_session()
+}
+
+/// Cattura consentita senza dialog: `Ok(false)` se il portal chiederà
+/// all'utente quale schermo condividere. Sotto X11 non serve nulla.
+pub fn screen_capture_access() -> Result<bool, CaptureError> {
+    if !is_wayland_session() {
+        return Ok(true);
+    }
+    portal::probe()
+}
+
+/// Apre la sessione del portal in anticipo. Blocca finché l'utente non
+/// risponde al dialog: va chiamata fuori dal thread della UI, prima di
+/// creare il `Capturer`. Sotto X11 non fa nulla.
+pub fn prepare_capture() {
+    if !is_wayland_session() {
+        return;
+    }
+    let opened = PortalSession::open();
+    *PREPARED.lock().unwrap_or_else(|e| e.into_inner()) = Some(opened);
+}
+
+pub enum LinuxCapture {
+    Portal(PortalCapture),
+    X11(GenericScreenCapture),
+}
+
+impl From<GenericScreenCapture> for LinuxCapture {
+    fn from(capture: GenericScreenCapture) -> Self {
+        LinuxCapture::X11(capture)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinuxDisplay {
+    Portal(PortalDisplay),
+    X11(GenericDisplay),
+}
+
+impl ToString for LinuxDisplay {
+    fn to_string(&self) -> String {
+        match self {
+            LinuxDisplay::Portal(display) => display.to_string(),
+            LinuxDisplay::X11(display) => display.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl ScreenCapture for LinuxCapture {
+    fn new_default() -> Result<ScreenCaptureImpl, CaptureError> {
+        if is_wayland_session() {
+            let prepared = PREPARED.lock().unwrap_or_else(|e| e.into_inner()).take();
+            match prepared.unwrap_or_else(PortalSession::open) {
+                Ok(session) => return Ok(LinuxCapture::Portal(PortalCapture::new(session))),
+                // Dialog annullato: l'utente ha scelto di non condividere
+                Err(CaptureError::PermissionDenied) => return Err(CaptureError::PermissionDenied),
+                Err(e) => log::warn!("ScreenCast portal unavailable ({}), falling back to X11", e),
+            }
+        }
+        GenericScreenCapture::new().map(LinuxCapture::X11)
+    }
+
+    fn display(&self) -> &dyn DisplayInfo {
+        match self {
+            LinuxCapture::Portal(capture) => capture.display(),
+            LinuxCapture::X11(capture) => capture.display(),
+        }
+    }
+
+    async fn start_capture(
+        &mut self,
+        encoder: FfmpegEncoder,
+        output: mpsc::Sender<EncodedVideo>,
+        opts_rx: watch::Receiver<CaptureOpts>,
+    ) -> Result<(), anyhow::Error> {
+        match self {
+            LinuxCapture::Portal(capture) => capture.start_capture(encoder, output, opts_rx).await,
+            LinuxCapture::X11(capture) => capture.start_capture(encoder, output, opts_rx).await,
+        }
+    }
+
+    async fn stop_capture(&mut self) -> Result<(), anyhow::Error> {
+        match self {
+            LinuxCapture::Portal(capture) => capture.stop_capture().await,
+            LinuxCapture::X11(capture) => capture.stop_capture().await,
+        }
+    }
+
+    fn set_health(&mut self, health: Arc<PipelineHealth>) {
+        match self {
+            LinuxCapture::Portal(capture) => capture.set_health(health),
+            LinuxCapture::X11(capture) => capture.set_health(health),
+        }
+    }
+}
+
+impl DisplaySelector for LinuxCapture {
+    type Display = LinuxDisplay;
+
+    fn available_displays(&mut self) -> Result<Vec<Self::Display>> {
+        Ok(match self {
+            LinuxCapture::Portal(capture) => capture
+                .available_displays()
+                .into_iter()
+                .map(LinuxDisplay::Portal)
+                .collect(),
+            LinuxCapture::X11(capture) => capture
+                .available_displays()?
+                .into_iter()
+                .map(LinuxDisplay::X11)
+                .collect(),
+        })
+    }
+
+    fn select_display(&mut self, display: &Self::Display) -> Result<()> {
+        match (self, display) {
+            (LinuxCapture::Portal(capture), LinuxDisplay::Portal(display)) => {
+                capture.select_display(display)
+            }
+            (LinuxCapture::X11(capture), LinuxDisplay::X11(display)) => {
+                capture.select_display(display)
+            }
+            _ => Err(anyhow::anyhow!(
+                "Display belongs to another capture backend."
+            )),
+        }
+    }
+
+    fn selected_display(&self) -> Result<Option<Self::Display>> {
+        Ok(match self {
+            LinuxCapture::Portal(capture) => Some(LinuxDisplay::Portal(capture.display().clone())),
+            LinuxCapture::X11(capture) => capture.selected_display()?.map(LinuxDisplay::X11),
+        })
+    }
+
+    fn is_primary(display: &Self::Display) -> bool {
+        match display {
+            LinuxDisplay::Portal(_) => false,
+            LinuxDisplay::X11(display) => GenericScreenCapture::is_primary(display),
+        }
+    }
+
+    fn display_rect(display: &Self::Display) -> (f32, f32, f32, f32) {
+        match display {
+            LinuxDisplay::Portal(display) => (
+                display.width as f32,
+                display.height as f32,
+                display.x as f32,
+                display.y as f32,
+            ),
+            LinuxDisplay::X11(display) => GenericScreenCapture::display_rect(display),
+        }
+    }
+
+    fn refresh_rate(display: &Self::Display) -> Option<u32> {
+        match display {
+            LinuxDisplay::Portal(_) => None,
+            LinuxDisplay::X11(display) => GenericScreenCapture::refresh_rate(display),
+        }
+    }
+}
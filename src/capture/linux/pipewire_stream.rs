@@ -0,0 +1,386 @@
+//! Consumer PipeWire per un nodo ScreenCast.
+//!
+//! Il main loop di PipeWire non è `Send`: gira su un thread dedicato e
+//! converte ogni buffer RGB in NV12 prima di passarlo al loop di cattura.
+
+use std::os::fd::OwnedFd;
+use std::thread::JoinHandle;
+
+use anyhow::{Result, anyhow};
+use pipewire as pw;
+use pw::spa;
+use pw::spa::param::format::{FormatProperties, MediaSubtype, MediaType};
+use pw::spa::param::format_utils;
+use pw::spa::param::video::{VideoFormat, VideoInfoRaw};
+use pw::spa::pod::Pod;
+use pw::stream::{Stream, StreamFlags};
+use tokio::sync::mpsc;
+
+use crate::capture::YUVFrame;
+
+/// Ordine dei canali nei formati RGB a 32 bit accettati
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChannelOrder {
+    Bgr,
+    Rgb,
+}
+
+impl ChannelOrder {
+    fn from_format(format: VideoFormat) -> Option<Self> {
+        match format {
+            VideoFormat::BGRx | VideoFormat::BGRA => Some(ChannelOrder::Bgr),
+            VideoFormat::RGBx | VideoFormat::RGBA => Some(ChannelOrder::Rgb),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct StreamState {
+    order: Option<ChannelOrder>,
+    width: u32,
+    height: u32,
+}
+
+pub struct PipeWireStream {
+    quit_tx: pw::channel::Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PipeWireStream {
+    /// Si collega al nodo `node_id` e inoltra i frame su `frame_tx`.
+    /// Ritorna dopo la connessione dello stream, o con il suo errore.
+    pub fn start(
+        fd: OwnedFd,
+        node_id: u32,
+        max_fps: u32,
+        frame_tx: mpsc::Sender<YUVFrame>,
+    ) -> Result<Self> {
+        let (quit_tx, quit_rx) = pw::channel::channel::<()>();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<(), String>>();
+
+        let thread = std::thread::Builder::new()
+            .name("pipewire-capture".into())
+            .spawn(move || {
+                if let Err(e) = run_stream(fd, node_id, max_fps, frame_tx, quit_rx, &ready_tx) {
+                    log::error!("PipeWire stream error: {}", e);
+                    let _ = ready_tx.send(Err(e.to_string()));
+                }
+            })?;
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                quit_tx,
+                thread: Some(thread),
+            }),
+            Ok(Err(e)) => Err(anyhow!("PipeWire stream failed: {}", e)),
+            Err(_) => Err(anyhow!("PipeWire thread exited before connecting")),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = self.quit_tx.send(());
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for PipeWireStream {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run_stream(
+    fd: OwnedFd,
+    node_id: u32,
+    max_fps: u32,
+    frame_tx: mpsc::Sender<YUVFrame>,
+    quit_rx: pw::channel::Receiver<()>,
+    ready_tx: &std::sync::mpsc::Sender<Result<(), String>>,
+) -> Result<()> {
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect_fd(fd, None)?;
+
+    let _quit = quit_rx.attach(mainloop.loop_(), {
+        let mainloop = mainloop.clone();
+        move |_| mainloop.quit()
+    });
+
+    let stream = Stream::new(
+        &core,
+        "castify-capture",
+        pw::properties::properties! {
+            *pw::keys::MEDIA_TYPE => "Video",
+            *pw::keys::MEDIA_CATEGORY => "Capture",
+            *pw::keys::MEDIA_ROLE => "Screen",
+        },
+    )?;
+
+    let _listener = stream
+        .add_local_listener_with_user_data(StreamState::default())
+        .param_changed(|_, state, id, param| {
+            let Some(param) = param else {
+                return;
+            };
+            if id != spa::param::ParamType::Format.as_raw() {
+                return;
+            }
+            let Ok((MediaType::Video, MediaSubtype::Raw)) = format_utils::parse_format(param)
+            else {
+                return;
+            };
+            let mut info = VideoInfoRaw::default();
+            if info.parse(param).is_err() {
+                return;
+            }
+            state.order = ChannelOrder::from_format(info.format());
+            state.width = info.size().width;
+            state.height = info.size().height;
+            log::info!(
+                "PipeWire format negotiated: {:?} {}x{}",
+                info.format(),
+                state.width,
+                state.height
+            );
+        })
+        .process(move |stream, state| {
+            let Some(mut buffer) = stream.dequeue_buffer() else {
+                return;
+            };
+            let Some(order) = state.order else {
+                return;
+            };
+            let datas = buffer.datas_mut();
+            let Some(data) = datas.first_mut() else {
+                return;
+            };
+            let chunk = data.chunk();
+            let (offset, size, stride) = (
+                chunk.offset() as usize,
+                chunk.size() as usize,
+                chunk.stride().max(0) as usize,
+            );
+            // Buffer senza contenuto (solo metadata del cursore, frame ripetuto)
+            if size == 0 || stride == 0 {
+                return;
+            }
+            let Some(bytes) = data.data() else {
+                return;
+            };
+            let Some(src) = bytes.get(offset..offset + size) else {
+                return;
+            };
+            let frame = packed_rgb_to_nv12(src, stride, state.width, state.height, order);
+            // Loop di cattura in ritardo: il frame viene scartato, non accodato
+            let _ = frame_tx.try_send(frame);
+        })
+        .register()?;
+
+    let format = pw::spa::pod::object!(
+        spa::utils::SpaTypes::ObjectParamFormat,
+        spa::param::ParamType::EnumFormat,
+        pw::spa::pod::property!(FormatProperties::MediaType, Id, MediaType::Video),
+        pw::spa::pod::property!(FormatProperties::MediaSubtype, Id, MediaSubtype::Raw),
+        pw::spa::pod::property!(
+            FormatProperties::VideoFormat,
+            Choice,
+            Enum,
+            Id,
+            VideoFormat::BGRx,
+            VideoFormat::BGRx,
+            VideoFormat::RGBx,
+            VideoFormat::BGRA,
+            VideoFormat::RGBA
+        ),
+        pw::spa::pod::property!(
+            FormatProperties::VideoSize,
+            Choice,
+            Range,
+            Rectangle,
+            spa::utils::Rectangle {
+                width: 1920,
+                height: 1080
+            },
+            spa::utils::Rectangle {
+                width: 2,
+                height: 2
+            },
+            spa::utils::Rectangle {
+                width: 8192,
+                height: 8192
+            }
+        ),
+        pw::spa::pod::property!(
+            FormatProperties::VideoFramerate,
+            Choice,
+            Range,
+            Fraction,
+            spa::utils::Fraction {
+                num: max_fps,
+                denom: 1
+            },
+            spa::utils::Fraction { num: 0, denom: 1 },
+            spa::utils::Fraction {
+                num: 1000,
+                denom: 1
+            }
+        ),
+    );
+    let values: Vec<u8> = pw::spa::pod::serialize::PodSerializer::serialize(
+        std::io::Cursor::new(Vec::new()),
+        &pw::spa::pod::Value::Object(format),
+    )
+    .map_err(|e| anyhow!("Cannot serialize the stream format: {:?}", e))?
+    .0
+    .into_inner();
+    let mut params = [Pod::from_bytes(&values).ok_or(anyhow!("Invalid stream format pod"))?];
+
+    stream.connect(
+        spa::utils::Direction::Input,
+        Some(node_id),
+        StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS,
+        &mut params,
+    )?;
+    let _ = ready_tx.send(Ok(()));
+
+    mainloop.run();
+    let _ = stream.disconnect();
+    Ok(())
+}
+
+/// Converte un frame RGB a 32 bit in NV12 (BT.709 limited, come `YuvColor`).
+/// Le dimensioni vengono arrotondate per difetto a valori pari.
+fn packed_rgb_to_nv12(
+    src: &[u8],
+    stride: usize,
+    width: u32,
+    height: u32,
+    order: ChannelOrder,
+) -> YUVFrame {
+    let rows = (src.len() / stride) as u32;
+    let w = (width.min(stride as u32 / 4) & !1).max(2) as usize;
+    let h = (height.min(rows) & !1).max(2) as usize;
+
+    let (ri, bi) = match order {
+        ChannelOrder::Bgr => (2, 0),
+        ChannelOrder::Rgb => (0, 2),
+    };
+    let rgb = |px: &[u8]| [px[ri] as i32, px[1] as i32, px[bi] as i32];
+    let luma = |[r, g, b]: [i32; 3]| (((47 * r + 157 * g + 16 * b + 128) >> 8) + 16) as u8;
+
+    // Righe mancanti (buffer più corto del formato negoziato) restano nere
+    let mut luminance_bytes = vec![16u8; w * h];
+    let mut chrominance_bytes = vec![128u8; w * h / 2];
+    let row = |y: usize| src.get(y * stride..y * stride + w * 4);
+
+    // Una coppia di righe per volta: insieme danno una riga di crominanza
+    let pairs = luminance_bytes
+        .chunks_exact_mut(2 * w)
+        .zip(chrominance_bytes.chunks_exact_mut(w));
+    for (cy, (luma_rows, chroma_row)) in pairs.enumerate() {
+        let (Some(top), Some(bottom)) = (row(2 * cy), row(2 * cy + 1)) else {
+            break;
+        };
+        let (luma_top, luma_bottom) = luma_rows.split_at_mut(w);
+        for (out, px) in luma_top.iter_mut().zip(top.chunks_exact(4)) {
+            *out = luma(rgb(px));
+        }
+        for (out, px) in luma_bottom.iter_mut().zip(bottom.chunks_exact(4)) {
+            *out = luma(rgb(px));
+        }
+
+        let blocks = top.chunks_exact(8).zip(bottom.chunks_exact(8));
+        for (uv, (t, b)) in chroma_row.chunks_exact_mut(2).zip(blocks) {
+            let mut sum = [0i32; 3];
+            for px in [&t[..4], &t[4..], &b[..4], &b[4..]] {
+                for (acc, c) in sum.iter_mut().zip(rgb(px)) {
+                    *acc += c;
+                }
+            }
+            let [r, g, b] = sum.map(|c| c / 4);
+            let u = ((-26 * r - 87 * g + 112 * b + 128) >> 8) + 128;
+            let v = ((112 * r - 102 * g - 10 * b + 128) >> 8) + 128;
+            uv[0] = u.clamp(0, 255) as u8;
+            uv[1] = v.clamp(0, 255) as u8;
+        }
+    }
+
+    YUVFrame {
+        display_time: 0,
+        width: w as i32,
+        height: h as i32,
+        luminance_bytes,
+        luminance_stride: w as i32,
+        chrominance_bytes,
+        chrominance_stride: w as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frame `width`×`height` di un solo colore, con `pad` byte a fine riga
+    fn solid(
+        rgb: [u8; 3],
+        order: ChannelOrder,
+        width: usize,
+        height: usize,
+        pad: usize,
+    ) -> Vec<u8> {
+        let px = match order {
+            ChannelOrder::Bgr => [rgb[2], rgb[1], rgb[0], 255],
+            ChannelOrder::Rgb => [rgb[0], rgb[1], rgb[2], 255],
+        };
+        let mut row: Vec<u8> = px.repeat(width);
+        row.resize(width * 4 + pad, 0xAA);
+        row.repeat(height)
+    }
+
+    #[test]
+    fn black_and_white_hit_the_limited_range() {
+        for (rgb, y) in [([0, 0, 0], 16), ([255, 255, 255], 235)] {
+            let src = solid(rgb, ChannelOrder::Bgr, 4, 4, 0);
+            let frame = packed_rgb_to_nv12(&src, 16, 4, 4, ChannelOrder::Bgr);
+            assert!(frame.luminance_bytes.iter().all(|&v| v == y));
+            assert!(frame.chrominance_bytes.iter().all(|v| v.abs_diff(128) <= 1));
+        }
+    }
+
+    #[test]
+    fn channel_order_and_stride_padding() {
+        let red = [255, 0, 0];
+        let bgr = packed_rgb_to_nv12(
+            &solid(red, ChannelOrder::Bgr, 6, 4, 8),
+            32,
+            6,
+            4,
+            ChannelOrder::Bgr,
+        );
+        let rgb = packed_rgb_to_nv12(
+            &solid(red, ChannelOrder::Rgb, 6, 4, 0),
+            24,
+            6,
+            4,
+            ChannelOrder::Rgb,
+        );
+        assert_eq!(bgr.luminance_bytes, rgb.luminance_bytes);
+        assert_eq!(bgr.chrominance_bytes, rgb.chrominance_bytes);
+        // Rosso: V sopra il neutro, U sotto
+        assert!(bgr.chrominance_bytes[1] > 200 && bgr.chrominance_bytes[0] < 128);
+    }
+
+    #[test]
+    fn odd_sizes_round_down_to_even() {
+        let src = solid([10, 20, 30], ChannelOrder::Rgb, 5, 3, 0);
+        let frame = packed_rgb_to_nv12(&src, 20, 5, 3, ChannelOrder::Rgb);
+        assert_eq!((frame.width, frame.height), (4, 2));
+        assert_eq!(frame.luminance_bytes.len(), 8);
+        assert_eq!(frame.chrominance_bytes.len(), 4);
+    }
+}
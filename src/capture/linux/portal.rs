@@ -0,0 +1,237 @@
+//! Sessione ScreenCast di xdg-desktop-portal.
+//!
+//! La negoziazione gira su un thread dedicato con un runtime tokio proprio:
+//! la sessione D-Bus deve restare aperta per tutta la cattura e viene chiusa
+//! solo al drop di [`PortalSession`]. Il restore token restituito dal portal
+//! viene salvato, così il dialog di scelta non ricompare a ogni avvio.
+
+use std::os::fd::OwnedFd;
+use std::thread::JoinHandle;
+
+use ashpd::desktop::screencast::{CursorMode, Screencast, SourceType};
+use ashpd::desktop::{PersistMode, ResponseError};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::capture::display::label::display_label;
+use crate::capture::{CaptureError, DisplayInfo};
+use crate::config::{load_json, save_json};
+
+const PORTAL_FILE: &str = "portal.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PortalState {
+    #[serde(default)]
+    restore_token: Option<String>,
+}
+
+fn load_restore_token() -> Option<String> {
+    load_json::<PortalState>(PORTAL_FILE).restore_token
+}
+
+fn save_restore_token(restore_token: Option<&str>) {
+    let state = PortalState {
+        restore_token: restore_token.map(str::to_string),
+    };
+    save_json(PORTAL_FILE, &state, "portal state");
+}
+
+/// Monitor condiviso dall'utente nel dialog del portal
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortalDisplay {
+    pub node_id: u32,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ToString for PortalDisplay {
+    fn to_string(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl DisplayInfo for PortalDisplay {
+    fn resolution(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn dpi_conversion_factor(&self) -> f64 {
+        // Il portal riporta dimensioni già in pixel fisici
+        1.0
+    }
+}
+
+fn portal_error(error: ashpd::Error) -> CaptureError {
+    match error {
+        ashpd::Error::Response(ResponseError::Cancelled) => CaptureError::PermissionDenied,
+        ashpd::Error::PortalNotFound(_) => CaptureError::Unsupported,
+        other => CaptureError::Backend(format!("ScreenCast portal: {}", other)),
+    }
+}
+
+/// Portal ScreenCast raggiungibile e in grado di condividere monitor, senza
+/// aprire sessioni né dialog. `true` se una scelta precedente è ricordata.
+pub fn probe() -> Result<bool, CaptureError> {
+    // Thread proprio: il chiamante può già trovarsi dentro un runtime tokio
+    let probe = std::thread::spawn(|| {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| CaptureError::Backend(e.to_string()))?;
+        runtime.block_on(async {
+            let proxy = Screencast::new().await.map_err(portal_error)?;
+            let sources = proxy.available_source_types().await.map_err(portal_error)?;
+            if !sources.contains(SourceType::Monitor) {
+                return Err(CaptureError::Unsupported);
+            }
+            Ok(load_restore_token().is_some())
+        })
+    });
+    probe
+        .join()
+        .unwrap_or_else(|_| Err(CaptureError::Backend(String::from("portal probe panicked"))))
+}
+
+type Negotiated = (Vec<PortalDisplay>, OwnedFd);
+
+pub struct PortalSession {
+    pub displays: Vec<PortalDisplay>,
+    /// Connessione al remote PipeWire della sessione
+    fd: OwnedFd,
+    close_tx: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PortalSession {
+    /// Apre la sessione; blocca finché l'utente non risponde al dialog
+    /// (immediato se il restore token salvato è ancora valido). Dalla UI
+    /// passa per [`super::prepare_capture`], su un thread bloccante.
+    pub fn open() -> Result<Self, CaptureError> {
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<Negotiated, CaptureError>>();
+        let (close_tx, close_rx) = oneshot::channel::<()>();
+
+        let thread = std::thread::Builder::new()
+            .name("screencast-portal".into())
+            .spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(CaptureError::Backend(e.to_string())));
+                        return;
+                    }
+                };
+
+                runtime.block_on(async move {
+                    let proxy = match Screencast::new().await {
+                        Ok(proxy) => proxy,
+                        Err(e) => {
+                            let _ = ready_tx.send(Err(portal_error(e)));
+                            return;
+                        }
+                    };
+                    let session = match proxy.create_session().await {
+                        Ok(session) => session,
+                        Err(e) => {
+                            let _ = ready_tx.send(Err(portal_error(e)));
+                            return;
+                        }
+                    };
+
+                    let negotiated = async {
+                        let restore_token = load_restore_token();
+                        proxy
+                            .select_sources(
+                                &session,
+                                CursorMode::Embedded,
+                                SourceType::Monitor.into(),
+                                true,
+                                restore_token.as_deref(),
+                                PersistMode::ExplicitlyRevoked,
+                            )
+                            .await?;
+                        let response = proxy.start(&session, None).await?.response()?;
+                        save_restore_token(response.restore_token());
+
+                        let displays = response
+                            .streams()
+                            .iter()
+                            .enumerate()
+                            .map(|(i, stream)| {
+                                let (width, height) = stream.size().unwrap_or((1920, 1080));
+                                let (x, y) = stream.position().unwrap_or((0, 0));
+                                PortalDisplay {
+                                    node_id: stream.pipe_wire_node_id(),
+                                    // Il portal non espone il nome del monitor
+                                    name: display_label(
+                                        None,
+                                        i + 1,
+                                        (width.max(2) as u32, height.max(2) as u32),
+                                    ),
+                                    x,
+                                    y,
+                                    width: width.max(2) as u32,
+                                    height: height.max(2) as u32,
+                                }
+                            })
+                            .collect::<Vec<_>>();
+                        let fd = proxy.open_pipe_wire_remote(&session).await?;
+                        Ok::<_, ashpd::Error>((displays, fd))
+                    }
+                    .await;
+
+                    match negotiated {
+                        Ok(negotiated) => {
+                            let _ = ready_tx.send(Ok(negotiated));
+                            // La sessione resta viva fino al drop di PortalSession
+                            let _ = close_rx.await;
+                        }
+                        Err(e) => {
+                            let _ = ready_tx.send(Err(portal_error(e)));
+                        }
+                    }
+
+                    if let Err(e) = session.close().await {
+                        log::warn!("Failed to close the screencast session: {}", e);
+                    }
+                });
+            })
+            .map_err(|e| CaptureError::Backend(e.to_string()))?;
+
+        let (displays, fd) = ready_rx
+            .recv()
+            .map_err(|_| CaptureError::Backend("ScreenCast portal thread exited".into()))??;
+        if displays.is_empty() {
+            return Err(CaptureError::NoDisplays);
+        }
+        log::info!("ScreenCast portal: {} stream(s) shared", displays.len());
+
+        Ok(Self {
+            displays,
+            fd,
+            close_tx: Some(close_tx),
+            thread: Some(thread),
+        })
+    }
+
+    /// Nuovo handle al remote PipeWire, uno per ogni stream avviato
+    pub fn pipewire_fd(&self) -> anyhow::Result<OwnedFd> {
+        Ok(self.fd.try_clone()?)
+    }
+}
+
+impl Drop for PortalSession {
+    fn drop(&mut self) {
+        if let Some(close_tx) = self.close_tx.take() {
+            let _ = close_tx.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
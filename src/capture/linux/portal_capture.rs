@@ -0,0 +1,297 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use tokio::select;
+use tokio::sync::{mpsc, watch};
+use tokio_util::sync::CancellationToken;
+
+use crate::capture::budget::BudgetController;
+use crate::capture::capturer::extract_crop_nv12_reuse;
+use crate::capture::keycast::draw_keycast;
+use crate::capture::linux::pipewire_stream::PipeWireStream;
+use crate::capture::linux::portal::{PortalDisplay, PortalSession};
+use crate::capture::timestamp::draw_timestamp;
+use crate::capture::watermark::draw_watermark;
+use crate::capture::{CaptureOpts, CropRect, DisplayInfo, YUVFrame};
+use crate::encoder::{EncodedVideo, FfmpegEncoder, FrameData};
+use crate::pipeline::health::{DropSource, PipelineHealth};
+
+/// Cattura Wayland: monitor scelti nel dialog del portal, frame da PipeWire.
+///
+/// Highlight del cursore e zoom non sono supportati: il cursore è già
+/// incluso nei frame (`CursorMode::Embedded`) e la sua posizione non è nota.
+/// Per lo stesso motivo `show_cursor` è ignorato: la modalità del cursore
+/// si sceglie una volta sola, alla creazione della sessione del portal.
+pub struct PortalCapture {
+    session: PortalSession,
+    selected: usize,
+    stream: Option<PipeWireStream>,
+    /// Ingresso del loop di cattura, riusato quando cambia il monitor
+    frame_tx: Option<mpsc::Sender<YUVFrame>>,
+    max_fps: u32,
+    cancel_token: Option<CancellationToken>,
+    health: Option<Arc<PipelineHealth>>,
+}
+
+impl PortalCapture {
+    pub fn new(session: PortalSession) -> Self {
+        Self {
+            session,
+            selected: 0,
+            stream: None,
+            frame_tx: None,
+            max_fps: 60,
+            cancel_token: None,
+            health: None,
+        }
+    }
+
+    pub fn display(&self) -> &PortalDisplay {
+        &self.session.displays[self.selected]
+    }
+
+    fn connect_stream(&mut self) -> Result<()> {
+        let Some(frame_tx) = self.frame_tx.clone() else {
+            return Ok(());
+        };
+        // Prima si chiude lo stream precedente: un solo consumer per sessione
+        self.stream = None;
+        let node_id = self.display().node_id;
+        self.stream = Some(PipeWireStream::start(
+            self.session.pipewire_fd()?,
+            node_id,
+            self.max_fps,
+            frame_tx,
+        )?);
+        Ok(())
+    }
+
+    fn black_frame(width: u32, height: u32) -> YUVFrame {
+        YUVFrame {
+            display_time: 0,
+            width: width as i32,
+            height: height as i32,
+            luminance_bytes: vec![0u8; (width * height) as usize],
+            luminance_stride: width as i32,
+            chrominance_bytes: vec![128u8; (width * height / 2) as usize],
+            chrominance_stride: width as i32,
+        }
+    }
+
+    pub async fn start_capture(
+        &mut self,
+        mut encoder: FfmpegEncoder,
+        output: mpsc::Sender<EncodedVideo>,
+        opts_rx: watch::Receiver<CaptureOpts>,
+    ) -> Result<(), anyhow::Error> {
+        if self.cancel_token.is_some() {
+            return Err(anyhow!("Capture already running"));
+        }
+
+        let (frame_tx, mut frame_rx) = mpsc::channel::<YUVFrame>(2);
+        self.frame_tx = Some(frame_tx);
+        self.max_fps = opts_rx.borrow().fps_limit();
+        if let Err(e) = self.connect_stream() {
+            self.frame_tx = None;
+            return Err(e);
+        }
+
+        let cancel = CancellationToken::new();
+        self.cancel_token = Some(cancel.clone());
+
+        let (dw, dh) = self.display().resolution();
+        let health = self.health.clone();
+        tokio::spawn(async move {
+            // Dimensioni reali dei frame: cambiano con il monitor o la risoluzione
+            let mut display_size = (dw & !1, dh & !1);
+            let mut current_crop: Option<CropRect> = opts_rx.borrow().crop;
+            let mut current_profile = opts_rx.borrow().profile;
+            let mut current_scale_to = opts_rx.borrow().scale_to;
+            let force_idr = encoder.force_idr.clone();
+            let simulcast = encoder.simulcast.clone();
+
+            let mut crop_y_buf: Vec<u8> = Vec::new();
+            let mut crop_uv_buf: Vec<u8> = Vec::new();
+            let mut cached_black_frame: Option<YUVFrame> = None;
+
+            let mut max_fps = opts_rx.borrow().fps_limit();
+            let mut current_fps = max_fps;
+            let mut budget_ctl = BudgetController::new();
+            let mut rebuild_encoder = false;
+            let started = Instant::now();
+            let mut last_encoded: Option<Instant> = None;
+
+            loop {
+                let frame = select! {
+                    _ = cancel.cancelled() => break,
+                    frame = frame_rx.recv() => match frame {
+                        Some(frame) => frame,
+                        None => {
+                            log::error!("PipeWire capture: frame channel closed");
+                            break;
+                        }
+                    },
+                };
+
+                let opts = opts_rx.borrow().clone();
+                max_fps = opts.fps_limit().min(budget_ctl.level().fps_cap());
+                current_fps = current_fps.min(max_fps);
+                if opts.paused {
+                    continue;
+                }
+
+                let interval = Duration::from_micros(1_000_000 / current_fps.max(1) as u64);
+                if last_encoded.is_some_and(|t| t.elapsed() < interval) {
+                    continue;
+                }
+                last_encoded = Some(Instant::now());
+
+                let frame_size = (frame.width as u32, frame.height as u32);
+                if frame_size != display_size {
+                    display_size = frame_size;
+                    rebuild_encoder = true;
+                }
+
+                if opts.crop != current_crop
+                    || opts.profile != current_profile
+                    || opts.scale_to != current_scale_to
+                    || rebuild_encoder
+                {
+                    let level = budget_ctl.level();
+                    let (src_w, src_h) = opts.source_size(display_size);
+                    let (enc_w, enc_h) = level.scale_size(opts.output_size(src_w, src_h));
+                    let rate = opts.rate_control(level);
+                    let rebuilt = FfmpegEncoder::with_rate_control(
+                        src_w,
+                        src_h,
+                        enc_w,
+                        enc_h,
+                        rate,
+                        opts.max_slice_size,
+                    );
+                    encoder = match rebuilt {
+                        Ok(encoder) => encoder.with_frame_pool(opts.frame_pool),
+                        Err(e) => {
+                            log::error!("Encoder rebuild failed, capture stopped: {}", e);
+                            break;
+                        }
+                    };
+                    encoder.force_idr = force_idr.clone();
+                    encoder.simulcast = simulcast.clone();
+                    force_idr.store(true, Ordering::Relaxed);
+                    cached_black_frame = None;
+                    current_crop = opts.crop;
+                    current_profile = opts.profile;
+                    current_scale_to = opts.scale_to;
+                    rebuild_encoder = false;
+                    log::info!(
+                        "Crop/profile/budget changed → encoder recreated at {}x{} (source {}x{})",
+                        enc_w,
+                        enc_h,
+                        src_w,
+                        src_h
+                    );
+                }
+
+                let pts = FfmpegEncoder::frame_time(started.elapsed());
+                let encoded = if opts.blank_screen {
+                    let (src_w, src_h) = opts.source_size(display_size);
+                    let black = cached_black_frame
+                        .get_or_insert_with(|| PortalCapture::black_frame(src_w, src_h));
+                    encoder.encode(FrameData::NV12(black), pts)
+                } else {
+                    let mut frame_to_encode = match current_crop.as_ref() {
+                        Some(crop) => {
+                            extract_crop_nv12_reuse(&frame, crop, &mut crop_y_buf, &mut crop_uv_buf)
+                        }
+                        None => frame,
+                    };
+                    if let Some(watermark) = &opts.watermark {
+                        draw_watermark(&mut frame_to_encode, watermark);
+                    }
+                    if let Some(timestamp) = &opts.timestamp {
+                        draw_timestamp(&mut frame_to_encode, timestamp);
+                    }
+                    let keys = opts
+                        .keycast
+                        .as_ref()
+                        .map(|k| k.visible())
+                        .unwrap_or_default();
+                    draw_keycast(&mut frame_to_encode, &keys);
+                    encoder.encode(FrameData::NV12(&frame_to_encode), pts)
+                };
+
+                match encoded {
+                    Ok(encoded) => {
+                        let len = encoded.len();
+                        if output.try_send(encoded).is_ok() {
+                            budget_ctl.record(len);
+                            current_fps = (current_fps + 1).min(max_fps);
+                        } else {
+                            if let Some(health) = &health {
+                                health.record_frame_drop(DropSource::Capture);
+                            }
+                            current_fps = current_fps.saturating_sub(6).max(15);
+                            force_idr.store(true, Ordering::Relaxed);
+                            log::warn!("Encoder output channel full, frame dropped");
+                        }
+                    }
+                    Err(e) => log::error!("Encode frame failed: {}", e),
+                }
+
+                if let Some(level) = budget_ctl.evaluate(opts.data_budget.as_ref()) {
+                    rebuild_encoder = true;
+                    current_fps = current_fps.min(level.fps_cap());
+                    log::info!("Data budget → quality level {:?}", level);
+                }
+            }
+
+            // Fine sessione: i frame ancora nell'encoder escono prima della chiusura
+            match encoder.flush() {
+                Ok(tail) if !tail.is_empty() => {
+                    let _ = output.try_send(tail);
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Encoder flush failed: {}", e),
+            }
+        });
+
+        Ok(())
+    }
+
+    pub async fn stop_capture(&mut self) -> Result<(), anyhow::Error> {
+        if let Some(cancel) = self.cancel_token.take() {
+            cancel.cancel();
+        }
+        self.stream = None;
+        self.frame_tx = None;
+        Ok(())
+    }
+
+    /// I frame scartati col canale d'uscita pieno contano come perdite di cattura
+    pub fn set_health(&mut self, health: Arc<PipelineHealth>) {
+        self.health = Some(health);
+    }
+
+    pub fn available_displays(&self) -> Vec<PortalDisplay> {
+        self.session.displays.clone()
+    }
+
+    /// Solo tra i monitor condivisi nel dialog; durante la cattura lo
+    /// stream PipeWire viene ricollegato al nuovo nodo.
+    pub fn select_display(&mut self, display: &PortalDisplay) -> Result<()> {
+        let index = self
+            .session
+            .displays
+            .iter()
+            .position(|d| d == display)
+            .ok_or(anyhow!("Display is not shared by the screencast session."))?;
+        if index != self.selected {
+            self.selected = index;
+            self.connect_stream()?;
+        }
+        Ok(())
+    }
+}
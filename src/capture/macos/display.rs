@@ -3,14 +3,17 @@ use apple_sys::ScreenCaptureKit::{
     NSString_NSStringDeprecated, SCDisplay,
 };
 
-use crate::capture::macos::ffi::{from_nsarray, from_nsstring, FromNSArray};
 use crate::capture::DisplayInfo;
+use crate::capture::display::label::display_label;
+use crate::capture::macos::ffi::{FromNSArray, from_nsarray, from_nsstring};
 
 #[derive(Clone, Debug)]
 pub struct Display {
     pub sc_display: SCDisplay,
     scale_factor: usize,
     name: String,
+    /// Posizione in `NSScreen::screens()`, dove il primo è lo schermo principale
+    position: Option<usize>,
 }
 
 unsafe impl Send for Display {}
@@ -18,16 +21,36 @@ unsafe impl Send for Display {}
 impl Display {
     pub fn new(sc_display: SCDisplay) -> Self {
         let ns_screen = unsafe { try_get_ns_screen(sc_display) };
+        let position = ns_screen.as_ref().map(|(position, _)| *position);
         let scale_factor = ns_screen
             .as_ref()
-            .map(|screen| unsafe { screen.backingScaleFactor() as usize })
+            .map(|(_, screen)| unsafe { screen.backingScaleFactor() as usize })
             .unwrap_or(2);
         Self {
             sc_display,
             scale_factor,
             name: unsafe { get_name(sc_display, scale_factor, ns_screen) },
+            position,
         }
     }
+
+    pub fn is_primary(&self) -> bool {
+        self.position == Some(0)
+    }
+
+    /// Angolo in alto a sinistra nel desktop, in pixel fisici. `display_info`
+    /// usa lo stesso id di CoreGraphics e riporta la posizione in punti.
+    pub fn origin(&self) -> (i32, i32) {
+        let id = unsafe { self.sc_display.displayID() };
+        display_info::DisplayInfo::all()
+            .ok()
+            .and_then(|all| all.into_iter().find(|info| info.id == id))
+            .map(|info| {
+                let scale = self.scale_factor as i32;
+                (info.x * scale, info.y * scale)
+            })
+            .unwrap_or_default()
+    }
 }
 
 impl ToString for Display {
@@ -59,35 +82,49 @@ impl DisplayInfo for Display {
     }
 }
 
-unsafe fn try_get_ns_screen(display: SCDisplay) -> Option<NSScreen> {
-    from_nsarray!(NSScreen, NSScreen::screens())
-        .iter()
-        .find_map(|screen| {
-            let screen_dictionary = screen.deviceDescription();
-            if screen_dictionary.0.is_null() {
-                return None;
-            }
-            let screen_id = NSNumber(
-                <NSDictionary as INSDictionary<NSString, NSNumber>>::objectForKey_(
-                    &screen_dictionary,
-                    NSString::alloc().initWithCString_(b"NSScreenNumber\0".as_ptr() as *const _),
-                ),
-            );
-            if screen_id.unsignedIntValue() == display.displayID() {
-                Some(screen.clone())
-            } else {
-                None
-            }
-        })
+unsafe fn try_get_ns_screen(display: SCDisplay) -> Option<(usize, NSScreen)> {
+    unsafe {
+        from_nsarray!(NSScreen, NSScreen::screens())
+            .iter()
+            .enumerate()
+            .find_map(|(position, screen)| {
+                let screen_dictionary = screen.deviceDescription();
+                if screen_dictionary.0.is_null() {
+                    return None;
+                }
+                let screen_id = NSNumber(
+                    <NSDictionary as INSDictionary<NSString, NSNumber>>::objectForKey_(
+                        &screen_dictionary,
+                        NSString::alloc()
+                            .initWithCString_(b"NSScreenNumber\0".as_ptr() as *const _),
+                    ),
+                );
+                if screen_id.unsignedIntValue() == display.displayID() {
+                    Some((position, screen.clone()))
+                } else {
+                    None
+                }
+            })
+    }
 }
 
-unsafe fn get_name(display: SCDisplay, scale_factor: usize, ns_screen: Option<NSScreen>) -> String {
-    let id = display.displayID();
-    let width = display.width() as usize * scale_factor;
-    let height = display.height() as usize * scale_factor;
-    let name = ns_screen
-        .map(|screen| from_nsstring!(screen.localizedName()).to_string())
-        .unwrap_or(format!("Display {}", id));
+unsafe fn get_name(
+    display: SCDisplay,
+    scale_factor: usize,
+    ns_screen: Option<(usize, NSScreen)>,
+) -> String {
+    unsafe {
+        let width = display.width() as u32 * scale_factor as u32;
+        let height = display.height() as u32 * scale_factor as u32;
+        // localizedName è già "Built-in Retina Display" per i pannelli integrati
+        let (number, name) = match ns_screen {
+            Some((position, screen)) => (
+                position + 1,
+                Some(from_nsstring!(screen.localizedName()).to_string()),
+            ),
+            None => (display.displayID() as usize, None),
+        };
 
-    format!("{} ({} x {})", name, width, height)
+        display_label(name.as_deref(), number, (width, height))
+    }
 }
@@ -2,29 +2,46 @@ use apple_sys::ScreenCaptureKit::{
     INSArray, NSArray, NSArray_NSExtendedArray, NSScreen, SCDisplay, SCRunningApplication, SCWindow,
 };
 
-#[macro_export]
 macro_rules! objc_closure {
     ($a:expr) => {
-        &*block::ConcreteBlock::new($a).copy() as *const Block<_, _> as *mut c_void
+        &*::block::ConcreteBlock::new($a).copy() as *const ::block::Block<_, _>
+            as *mut ::std::ffi::c_void
     };
 }
-pub use objc_closure;
+pub(crate) use objc_closure;
 
-#[macro_export]
 macro_rules! from_nsstring {
     ($s:expr) => {
-        std::ffi::CStr::from_ptr($s.cString()).to_str().unwrap()
+        std::ffi::CStr::from_ptr($s.cString())
+            .to_str()
+            .unwrap_or_default()
     };
 }
-pub use from_nsstring;
+pub(crate) use from_nsstring;
 
-#[macro_export]
 macro_rules! from_nsarray {
     ($T:ident, $e:expr) => {
         <Vec<$T>>::from_nsarray($e)
     };
 }
-pub use from_nsarray;
+pub(crate) use from_nsarray;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGPreflightScreenCaptureAccess() -> bool;
+    fn CGRequestScreenCaptureAccess() -> bool;
+}
+
+/// Permesso "Registrazione schermo" già concesso, senza far comparire il prompt
+pub fn screen_capture_access_granted() -> bool {
+    unsafe { CGPreflightScreenCaptureAccess() }
+}
+
+/// Permesso "Registrazione schermo": se manca lo chiede al sistema, che mostra
+/// il prompt solo la prima volta (poi serve Impostazioni → Privacy).
+pub fn request_screen_capture_access() -> bool {
+    unsafe { CGPreflightScreenCaptureAccess() || CGRequestScreenCaptureAccess() }
+}
 
 #[derive(Debug)]
 pub struct UnsafeSendable<T>(pub T);
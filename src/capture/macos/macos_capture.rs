@@ -1,39 +1,83 @@
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
-use ac_ffmpeg::codec::audio::{AudioEncoder, AudioFrameMut, ChannelLayout};
-use ac_ffmpeg::codec::Encoder;
+use anyhow::{Result, anyhow};
 use async_trait::async_trait;
-use bytes::Bytes;
 use tokio::select;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, watch};
 use tokio_util::sync::CancellationToken;
 
+use crate::capture::budget::BudgetController;
+use crate::capture::capturer::extract_crop_nv12_reuse;
 use crate::capture::display::DisplaySelector;
-use crate::capture::macos::pcm_buffer::PCMBuffer;
+use crate::capture::keycast::draw_keycast;
+use crate::capture::macos::ffi::request_screen_capture_access;
 use crate::capture::macos::screen_recorder::ScreenRecorder;
-use crate::capture::{DisplayInfo, ScreenCaptureImpl, YUVFrame};
-use crate::config::Config;
-use crate::encoder::{FfmpegEncoder, FrameData};
-use crate::performance_profiler::PerformanceProfiler;
-use crate::result::Result;
-use crate::{OutputSink, ScreenCapture};
+use crate::capture::timestamp::draw_timestamp;
+use crate::capture::watermark::draw_watermark;
+use crate::capture::{
+    CaptureError, CaptureOpts, CropRect, DisplayInfo, ScreenCapture, ScreenCaptureImpl, YUVFrame,
+};
+use crate::encoder::{EncodedVideo, FfmpegEncoder, FrameData};
+use crate::pipeline::health::{DropSource, PipelineHealth};
 
+/// Cattura ScreenCaptureKit: i frame NV12 arrivano dalla coda di dispatch
+/// e vengono codificati in un task tokio, come nel backend WGC.
+///
+/// Highlight del cursore e zoom non sono ancora supportati: manca un
+/// tracker della posizione del puntatore su macOS. La visibilità del
+/// cursore si legge all'avvio della cattura: il loop non possiede il
+/// recorder, un cambio a stream avviato vale dalla sessione successiva.
 pub struct MacOSCapture {
-    config: Config,
     recorder: ScreenRecorder,
+    cancel_token: Option<CancellationToken>,
+    health: Option<Arc<PipelineHealth>>,
+}
+
+impl MacOSCapture {
+    fn black_frame(width: u32, height: u32) -> YUVFrame {
+        YUVFrame {
+            display_time: 0,
+            width: width as i32,
+            height: height as i32,
+            luminance_bytes: vec![0u8; (width * height) as usize],
+            luminance_stride: width as i32,
+            chrominance_bytes: vec![128u8; (width * height / 2) as usize],
+            chrominance_stride: width as i32,
+        }
+    }
 }
 
 #[async_trait]
 impl ScreenCapture for MacOSCapture {
-    fn new(config: Config) -> Result<ScreenCaptureImpl> {
-        // TODO hot-reload config
+    fn new_default() -> Result<ScreenCaptureImpl, CaptureError> {
+        // Mostra la richiesta di sistema la prima volta; negato → errore esplicito
+        if !request_screen_capture_access() {
+            return Err(CaptureError::PermissionDenied);
+        }
 
         let mut recorder = ScreenRecorder::new();
-        recorder.set_max_fps(config.max_fps as u8);
-        recorder.monitor_available_content();
+        recorder
+            .refresh_available_content()
+            .map_err(CaptureError::from_anyhow)?;
+        if recorder
+            .selected_display()
+            .map_err(CaptureError::from_anyhow)?
+            .is_none()
+        {
+            return Err(CaptureError::NoDisplays);
+        }
+
+        Ok(Self {
+            recorder,
+            cancel_token: None,
+            health: None,
+        })
+    }
 
-        Ok(Self { config, recorder })
+    fn can_hide_cursor(&self) -> bool {
+        true
     }
 
     fn display(&self) -> &dyn DisplayInfo {
@@ -43,97 +87,186 @@ impl ScreenCapture for MacOSCapture {
     async fn start_capture(
         &mut self,
         mut encoder: FfmpegEncoder,
-        output: Arc<Mutex<impl OutputSink + Send + ?Sized>>,
-        mut profiler: PerformanceProfiler,
-        shutdown_token: CancellationToken,
-    ) -> Result<()> {
-        let (video_tx, mut video_rx) = tokio::sync::mpsc::channel::<YUVFrame>(1);
-        let (audio_tx, mut audio_rx) = tokio::sync::mpsc::channel::<PCMBuffer>(1);
-
-        let output_audio = output.clone();
-        let cancel_audio = shutdown_token.clone();
+        output: mpsc::Sender<EncodedVideo>,
+        opts_rx: watch::Receiver<CaptureOpts>,
+    ) -> Result<(), anyhow::Error> {
+        if self.cancel_token.is_some() {
+            return Err(anyhow!("Capture already running"));
+        }
+
+        let (video_tx, mut video_rx) = mpsc::channel::<YUVFrame>(2);
+        self.recorder.set_max_fps(opts_rx.borrow().fps_limit());
+        self.recorder.set_show_cursor(opts_rx.borrow().show_cursor);
+        self.recorder.start(video_tx)?;
+
+        let cancel = CancellationToken::new();
+        self.cancel_token = Some(cancel.clone());
+
+        let display_size = self.display().resolution();
+        let health = self.health.clone();
         tokio::spawn(async move {
-            let mut audio_encoder_opt = None;
+            let mut current_crop: Option<CropRect> = opts_rx.borrow().crop;
+            let mut current_profile = opts_rx.borrow().profile;
+            let mut current_scale_to = opts_rx.borrow().scale_to;
+            let force_idr = encoder.force_idr.clone();
+            let simulcast = encoder.simulcast.clone();
+
+            let mut crop_y_buf: Vec<u8> = Vec::new();
+            let mut crop_uv_buf: Vec<u8> = Vec::new();
+            let mut cached_black_frame: Option<YUVFrame> = None;
+
+            let mut max_fps = opts_rx.borrow().fps_limit();
+            let mut current_fps = max_fps;
+            let mut budget_ctl = BudgetController::new();
+            let mut rebuild_encoder = false;
+            let started = Instant::now();
+            let mut last_encoded: Option<Instant> = None;
+
             loop {
-                select! {
-                    Some(pcm_buffer) = audio_rx.recv() => {
-                        let audio_encoder = audio_encoder_opt.get_or_insert_with(|| {
-                            AudioEncoder::builder("libopus")
-                                .unwrap()
-                                .sample_rate(pcm_buffer.sample_rate as _)
-                                .channel_layout(
-                                    ChannelLayout::from_channels(pcm_buffer.channels as u32).unwrap(),
-                                )
-                                .sample_format(pcm_buffer.sample_format())
-                                .set_option("frame_duration", pcm_buffer.frame_duration)
-                                .build()
-                                .unwrap()
-                        });
-                        let mut audio_frame = AudioFrameMut::silence(
-                            audio_encoder.codec_parameters().channel_layout(),
-                            audio_encoder.codec_parameters().sample_format(),
-                            audio_encoder.codec_parameters().sample_rate(),
-                            pcm_buffer.sample_size,
-                        );
-                        pcm_buffer.write_samples_into(&mut audio_frame);
-                        audio_encoder.push(audio_frame.freeze()).unwrap();
-
-                        let mut ret: Vec<u8> = Vec::new();
-                        while let Some(packet) = audio_encoder.take().unwrap() {
-                            ret.extend(packet.data());
+                let frame = select! {
+                    _ = cancel.cancelled() => break,
+                    frame = video_rx.recv() => match frame {
+                        Some(frame) => frame,
+                        None => {
+                            log::error!("ScreenCaptureKit: frame channel closed");
+                            break;
                         }
-                        output_audio
-                            .lock()
-                            .await
-                            .write_audio(
-                                Bytes::from(ret),
-                                Duration::from_millis(pcm_buffer.frame_duration as u64),
-                            )
-                            .await
-                            .unwrap();
+                    },
+                };
+
+                let opts = opts_rx.borrow().clone();
+                max_fps = opts.fps_limit().min(budget_ctl.level().fps_cap());
+                current_fps = current_fps.min(max_fps);
+                if opts.paused {
+                    continue;
+                }
+
+                // ScreenCaptureKit consegna al massimo gli fps configurati all'avvio:
+                // il limite adattivo viene applicato qui scartando i frame in eccesso
+                let interval = Duration::from_micros(1_000_000 / current_fps.max(1) as u64);
+                if last_encoded.is_some_and(|t| t.elapsed() < interval) {
+                    continue;
+                }
+                last_encoded = Some(Instant::now());
+
+                if opts.crop != current_crop
+                    || opts.profile != current_profile
+                    || opts.scale_to != current_scale_to
+                    || rebuild_encoder
+                {
+                    let level = budget_ctl.level();
+                    let (src_w, src_h) = opts.source_size(display_size);
+                    let (enc_w, enc_h) = level.scale_size(opts.output_size(src_w, src_h));
+                    let rate = opts.rate_control(level);
+                    let rebuilt = FfmpegEncoder::with_rate_control(
+                        src_w,
+                        src_h,
+                        enc_w,
+                        enc_h,
+                        rate,
+                        opts.max_slice_size,
+                    );
+                    encoder = match rebuilt {
+                        Ok(encoder) => encoder.with_frame_pool(opts.frame_pool),
+                        Err(e) => {
+                            log::error!("Encoder rebuild failed, capture stopped: {}", e);
+                            break;
+                        }
+                    };
+                    encoder.force_idr = force_idr.clone();
+                    encoder.simulcast = simulcast.clone();
+                    force_idr.store(true, Ordering::Relaxed);
+                    cached_black_frame = None;
+                    current_crop = opts.crop;
+                    current_profile = opts.profile;
+                    current_scale_to = opts.scale_to;
+                    rebuild_encoder = false;
+                    log::info!(
+                        "Crop/profile/budget changed → encoder recreated at {}x{} (source {}x{})",
+                        enc_w,
+                        enc_h,
+                        src_w,
+                        src_h
+                    );
+                }
+
+                let pts = FfmpegEncoder::frame_time(started.elapsed());
+                let encoded = if opts.blank_screen {
+                    let (src_w, src_h) = opts.source_size(display_size);
+                    let black = cached_black_frame
+                        .get_or_insert_with(|| MacOSCapture::black_frame(src_w, src_h));
+                    encoder.encode(FrameData::NV12(black), pts)
+                } else {
+                    let mut frame_to_encode = match current_crop.as_ref() {
+                        Some(crop) => {
+                            extract_crop_nv12_reuse(&frame, crop, &mut crop_y_buf, &mut crop_uv_buf)
+                        }
+                        None => frame,
+                    };
+                    if let Some(watermark) = &opts.watermark {
+                        draw_watermark(&mut frame_to_encode, watermark);
                     }
-                    _ = cancel_audio.cancelled() => {
-                        break;
+                    if let Some(timestamp) = &opts.timestamp {
+                        draw_timestamp(&mut frame_to_encode, timestamp);
                     }
+                    let keys = opts
+                        .keycast
+                        .as_ref()
+                        .map(|k| k.visible())
+                        .unwrap_or_default();
+                    draw_keycast(&mut frame_to_encode, &keys);
+                    encoder.encode(FrameData::NV12(&frame_to_encode), pts)
+                };
+
+                match encoded {
+                    Ok(encoded) => {
+                        let len = encoded.len();
+                        if output.try_send(encoded).is_ok() {
+                            budget_ctl.record(len);
+                            current_fps = (current_fps + 1).min(max_fps);
+                        } else {
+                            if let Some(health) = &health {
+                                health.record_frame_drop(DropSource::Capture);
+                            }
+                            current_fps = current_fps.saturating_sub(6).max(15);
+                            force_idr.store(true, Ordering::Relaxed);
+                            log::warn!("Encoder output channel full, frame dropped");
+                        }
+                    }
+                    Err(e) => log::error!("Encode frame failed: {}", e),
+                }
+
+                if let Some(level) = budget_ctl.evaluate(opts.data_budget.as_ref()) {
+                    rebuild_encoder = true;
+                    current_fps = current_fps.min(level.fps_cap());
+                    log::info!("Data budget → quality level {:?}", level);
                 }
             }
-        });
 
-        let cancel_video = shutdown_token.clone();
-        let max_fps = self.config.max_fps;
-        tokio::spawn(async move {
-            let mut ticker = tokio::time::interval(Duration::from_millis((1000 / max_fps) as u64));
-            loop {
-                select! {
-                    Some(frame) = video_rx.recv() => {
-                        let frame_time = frame.display_time as f64;
-                        profiler.accept_frame(frame_time as i64);
-                        profiler.done_preprocessing();
-                        let encoded = encoder
-                            .encode(FrameData::NV12(&frame), frame_time as i64)
-                            .unwrap();
-                        let encoded_len = encoded.len();
-                        profiler.done_encoding();
-                        output.lock().await.write(encoded).await.unwrap();
-                        profiler.done_processing(encoded_len);
-                        ticker.tick().await;
-                    }
-                    _ = cancel_video.cancelled() => {
-                        break;
-                    }
+            // Fine sessione: i frame ancora nell'encoder escono prima della chiusura
+            match encoder.flush() {
+                Ok(tail) if !tail.is_empty() => {
+                    let _ = output.try_send(tail);
                 }
+                Ok(_) => {}
+                Err(e) => log::warn!("Encoder flush failed: {}", e),
             }
         });
 
-        self.recorder.start(video_tx, audio_tx);
-
         Ok(())
     }
 
-    async fn stop_capture(&mut self) -> Result<()> {
+    async fn stop_capture(&mut self) -> Result<(), anyhow::Error> {
+        if let Some(cancel) = self.cancel_token.take() {
+            cancel.cancel();
+        }
         self.recorder.stop();
         Ok(())
     }
+
+    fn set_health(&mut self, health: Arc<PipelineHealth>) {
+        self.health = Some(health);
+    }
 }
 
 impl DisplaySelector for MacOSCapture {
@@ -150,4 +283,12 @@ impl DisplaySelector for MacOSCapture {
     fn selected_display(&self) -> Result<Option<Self::Display>> {
         self.recorder.selected_display()
     }
+
+    fn is_primary(display: &Self::Display) -> bool {
+        ScreenRecorder::is_primary(display)
+    }
+
+    fn display_rect(display: &Self::Display) -> (f32, f32, f32, f32) {
+        ScreenRecorder::display_rect(display)
+    }
 }
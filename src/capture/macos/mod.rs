@@ -1,8 +1,13 @@
+//! ScreenCaptureKit backend (macOS 12.3+)
+//!
+//! Adattato dall'implementazione di Mira Screen Share (vedi LICENSE.txt):
+//! solo video, l'audio di sistema passa dal modulo `capture::audio`.
+
+pub use ffi::screen_capture_access_granted;
 pub use macos_capture::MacOSCapture;
 
 mod capture_engine;
 mod display;
 mod ffi;
 pub mod macos_capture;
-mod pcm_buffer;
 mod screen_recorder;
@@ -1,25 +1,53 @@
 //! Screen capture module
 //!
 //! Provides cross-platform screen capture functionality through platform-specific
-//! implementations (Windows Graphics Capture on Windows, generic fallback elsewhere).
+//! implementations (Windows Graphics Capture on Windows, ScreenCaptureKit on macOS,
+//! ScreenCast portal + PipeWire on Wayland, generic fallback elsewhere).
 
 #[cfg(target_os = "windows")]
 mod wgc;
 #[cfg(target_os = "windows")]
 pub use wgc::WGCScreenCapture as ScreenCaptureImpl;
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+pub use macos::MacOSCapture as ScreenCaptureImpl;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::LinuxCapture as ScreenCaptureImpl;
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
 mod generic;
-#[cfg(not(target_os = "windows"))]
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
 pub use generic::GenericScreenCapture as ScreenCaptureImpl;
 
 pub mod audio;
+mod bitmap_font;
+pub mod budget;
 pub mod capturer;
 pub mod display;
+mod error;
+pub mod keycast;
+#[cfg(target_os = "windows")]
+pub mod motion;
+pub mod overlay;
+pub mod permissions;
+#[cfg(target_os = "windows")]
+pub mod privacy;
+mod profile;
+#[cfg(any(test, feature = "test-capture"))]
+pub mod synthetic;
+pub mod timestamp;
 mod traits;
+pub mod watermark;
 #[cfg(target_os = "windows")]
 mod yuv_convert;
+pub mod zoom;
 
+#[derive(Default)]
 pub struct YUVFrame {
     pub display_time: u64,
     pub width: i32,
@@ -40,6 +68,22 @@ pub struct NV12FrameRef<'a> {
 }
 
 pub use capturer::{CaptureOpts, CropRect};
-pub use traits::{DisplayInfo, ScreenCapture};
+#[cfg(target_os = "linux")]
+pub use linux::{prepare_capture, uses_portal};
+
+/// Lavoro bloccante da fare prima di `Capturer::new`: solo il portal di Wayland ne ha
+#[cfg(not(target_os = "linux"))]
+pub fn prepare_capture() {}
+
+/// `Capturer::new` passa da un dialog di sistema: solo il portal di Wayland
+#[cfg(not(target_os = "linux"))]
+pub fn uses_portal() -> bool {
+    false
+}
+pub use error::CaptureError;
+pub use profile::{
+    BitrateMode, ColorSpace, EncodeScale, FpsCap, HdrMode, Simulcast, StreamProfile,
+};
+pub use traits::{DisplayInfo, ScreenCapture, SourceEvent};
 #[cfg(target_os = "windows")]
-pub use yuv_convert::YuvConverter;
+pub use yuv_convert::{ToneMap, YuvConverter};
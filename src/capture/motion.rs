@@ -0,0 +1,306 @@
+//! Frame rate guidato dal contenuto
+//!
+//! Slide e documenti restano fermi per secondi: codificarli a pieno frame
+//! rate spreca banda. Il piano Y di ogni frame viene confrontato, a campione,
+//! con quello precedente; dopo [`STATIC_AFTER`] senza movimento si codifica
+//! un frame ogni [`STATIC_REFRESH`], con un keyframe periodico per chi si
+//! collega a metà. Al primo movimento si torna subito al frame rate pieno.
+//!
+//! [`DuplicateFilter`] è il caso più semplice: un frame identico al precedente
+//! non viene codificato, salvo un refresh ogni [`DUPLICATE_REFRESH`].
+
+use std::time::{Duration, Instant};
+
+/// Passo di campionamento, in pixel e in righe
+const SAMPLE_STEP: usize = 16;
+/// Differenza di luma sotto cui un campione è rumore (dithering, cursore che lampeggia)
+const PIXEL_THRESHOLD: u8 = 8;
+/// Frazione di campioni cambiati oltre cui il frame è in movimento
+const MOTION_THRESHOLD: f32 = 0.002;
+/// Tempo senza movimento prima di rallentare
+pub const STATIC_AFTER: Duration = Duration::from_millis(500);
+/// Intervallo tra i frame codificati a schermo fermo
+pub const STATIC_REFRESH: Duration = Duration::from_secs(1);
+/// Ogni quanti refresh a schermo fermo si forza un keyframe
+const KEYFRAME_EVERY: u32 = 5;
+/// Intervallo del refresh inviato anche se il frame è identico
+pub const DUPLICATE_REFRESH: Duration = Duration::from_secs(2);
+
+/// Stima economica del movimento tra piani Y successivi
+#[derive(Default)]
+struct MotionDetector {
+    previous: Vec<u8>,
+    size: (usize, usize),
+}
+
+impl MotionDetector {
+    /// Frazione di campioni cambiati rispetto al frame precedente
+    /// (1.0 al primo frame o se cambiano le dimensioni)
+    fn sample(&mut self, luma: &[u8], width: usize, stride: usize) -> f32 {
+        let height = luma.len() / stride.max(1);
+        let width = width.min(stride);
+        let samples = (0..height)
+            .step_by(SAMPLE_STEP)
+            .flat_map(|y| (0..width).step_by(SAMPLE_STEP).map(move |x| y * stride + x));
+
+        if self.size != (width, height) {
+            self.size = (width, height);
+            self.previous = samples.map(|i| luma[i]).collect();
+            return 1.0;
+        }
+
+        let mut changed = 0usize;
+        for (prev, i) in self.previous.iter_mut().zip(samples) {
+            if prev.abs_diff(luma[i]) > PIXEL_THRESHOLD {
+                changed += 1;
+            }
+            *prev = luma[i];
+        }
+        changed as f32 / self.previous.len().max(1) as f32
+    }
+}
+
+/// Cosa fare del frame appena catturato
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateDecision {
+    Encode,
+    /// Refresh periodico a schermo fermo, come keyframe
+    EncodeKeyframe,
+    /// Schermo fermo, il frame non viene codificato
+    Skip,
+}
+
+pub struct ContentAwareRate {
+    detector: MotionDetector,
+    last_motion: Instant,
+    last_encoded: Instant,
+    static_refreshes: u32,
+}
+
+impl Default for ContentAwareRate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentAwareRate {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            detector: MotionDetector::default(),
+            last_motion: now,
+            last_encoded: now,
+            static_refreshes: 0,
+        }
+    }
+
+    /// Dimentica il frame precedente: il prossimo conta come movimento
+    pub fn reset(&mut self) {
+        self.detector = MotionDetector::default();
+    }
+
+    pub fn decide(&mut self, luma: &[u8], width: usize, stride: usize) -> RateDecision {
+        self.decide_at(luma, width, stride, Instant::now())
+    }
+
+    fn decide_at(
+        &mut self,
+        luma: &[u8],
+        width: usize,
+        stride: usize,
+        now: Instant,
+    ) -> RateDecision {
+        if self.detector.sample(luma, width, stride) > MOTION_THRESHOLD {
+            if self.is_static(now) {
+                log::debug!("Content-aware rate: motion, back to full frame rate");
+            }
+            self.last_motion = now;
+            self.static_refreshes = 0;
+        }
+
+        if !self.is_static(now) {
+            self.last_encoded = now;
+            return RateDecision::Encode;
+        }
+        if now.duration_since(self.last_encoded) < STATIC_REFRESH {
+            return RateDecision::Skip;
+        }
+
+        self.last_encoded = now;
+        self.static_refreshes += 1;
+        if self.static_refreshes.is_multiple_of(KEYFRAME_EVERY) {
+            RateDecision::EncodeKeyframe
+        } else {
+            RateDecision::Encode
+        }
+    }
+
+    fn is_static(&self, now: Instant) -> bool {
+        now.duration_since(self.last_motion) >= STATIC_AFTER
+    }
+}
+
+/// Scarta i frame il cui piano Y è identico a quello dell'ultimo codificato
+pub struct DuplicateFilter {
+    last_hash: Option<u64>,
+    last_sent: Instant,
+}
+
+impl Default for DuplicateFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DuplicateFilter {
+    pub fn new() -> Self {
+        Self {
+            last_hash: None,
+            last_sent: Instant::now(),
+        }
+    }
+
+    /// Se il frame va codificato: diverso dal precedente o refresh scaduto
+    pub fn is_new(&mut self, luma: &[u8], width: usize, stride: usize) -> bool {
+        self.is_new_at(luma, width, stride, Instant::now())
+    }
+
+    fn is_new_at(&mut self, luma: &[u8], width: usize, stride: usize, now: Instant) -> bool {
+        let hash = luma_hash(luma, width, stride);
+        if self.last_hash == Some(hash) && now.duration_since(self.last_sent) < DUPLICATE_REFRESH {
+            return false;
+        }
+        self.last_hash = Some(hash);
+        self.last_sent = now;
+        true
+    }
+}
+
+/// FNV-1a a parole di 8 byte sulle righe visibili (il padding dello stride è escluso)
+fn luma_hash(luma: &[u8], width: usize, stride: usize) -> u64 {
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let width = width.min(stride);
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for row in luma.chunks(stride.max(1)) {
+        let row = &row[..width.min(row.len())];
+        let mut words = row.chunks_exact(8);
+        for word in &mut words {
+            hash = (hash ^ u64::from_le_bytes(word.try_into().unwrap())).wrapping_mul(PRIME);
+        }
+        for &byte in words.remainder() {
+            hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: usize = 64;
+    const STRIDE: usize = 80;
+    const H: usize = 64;
+
+    fn frame(luma: u8) -> Vec<u8> {
+        vec![luma; STRIDE * H]
+    }
+
+    #[test]
+    fn still_screen_slows_down_and_motion_restores_full_rate() {
+        let start = Instant::now();
+        let mut rate = ContentAwareRate::new();
+        let still = frame(40);
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        assert_eq!(
+            rate.decide_at(&still, W, STRIDE, at(0)),
+            RateDecision::Encode
+        );
+        assert_eq!(
+            rate.decide_at(&still, W, STRIDE, at(100)),
+            RateDecision::Encode
+        );
+        // Fermo da più di STATIC_AFTER: un frame al secondo
+        assert_eq!(
+            rate.decide_at(&still, W, STRIDE, at(600)),
+            RateDecision::Skip
+        );
+        assert_eq!(
+            rate.decide_at(&still, W, STRIDE, at(1200)),
+            RateDecision::Encode
+        );
+        assert_eq!(
+            rate.decide_at(&still, W, STRIDE, at(1500)),
+            RateDecision::Skip
+        );
+
+        let moved = frame(200);
+        assert_eq!(
+            rate.decide_at(&moved, W, STRIDE, at(1600)),
+            RateDecision::Encode
+        );
+        assert_eq!(
+            rate.decide_at(&moved, W, STRIDE, at(1700)),
+            RateDecision::Encode
+        );
+    }
+
+    #[test]
+    fn every_few_static_refreshes_is_a_keyframe() {
+        let start = Instant::now();
+        let mut rate = ContentAwareRate::new();
+        let still = frame(40);
+        rate.decide_at(&still, W, STRIDE, start);
+
+        let refreshes: Vec<_> = (1..=KEYFRAME_EVERY)
+            .map(|n| {
+                let now = start + STATIC_AFTER + STATIC_REFRESH * n;
+                rate.decide_at(&still, W, STRIDE, now)
+            })
+            .collect();
+        assert!(
+            refreshes[..refreshes.len() - 1]
+                .iter()
+                .all(|d| *d == RateDecision::Encode)
+        );
+        assert_eq!(refreshes.last(), Some(&RateDecision::EncodeKeyframe));
+    }
+
+    #[test]
+    fn noise_below_the_threshold_is_not_motion() {
+        let mut detector = MotionDetector::default();
+        assert_eq!(detector.sample(&frame(40), W, STRIDE), 1.0);
+        assert_eq!(
+            detector.sample(&frame(40 + PIXEL_THRESHOLD), W, STRIDE),
+            0.0
+        );
+        assert_eq!(detector.sample(&frame(200), W, STRIDE), 1.0);
+    }
+
+    #[test]
+    fn duplicates_are_dropped_until_the_refresh() {
+        let start = Instant::now();
+        let mut filter = DuplicateFilter::new();
+        let a = frame(10);
+
+        assert!(filter.is_new_at(&a, W, STRIDE, start));
+        assert!(!filter.is_new_at(&a, W, STRIDE, start + Duration::from_millis(500)));
+        assert!(filter.is_new_at(&frame(11), W, STRIDE, start + Duration::from_millis(600)));
+        assert!(filter.is_new_at(&frame(11), W, STRIDE, start + DUPLICATE_REFRESH * 2));
+    }
+
+    #[test]
+    fn stride_padding_does_not_count() {
+        let mut a = frame(10);
+        let mut b = frame(10);
+        for row in 0..H {
+            a[row * STRIDE + W] = 1;
+            b[row * STRIDE + W] = 2;
+        }
+        assert_eq!(luma_hash(&a, W, STRIDE), luma_hash(&b, W, STRIDE));
+
+        b[0] = 99;
+        assert_ne!(luma_hash(&a, W, STRIDE), luma_hash(&b, W, STRIDE));
+    }
+}
@@ -0,0 +1,307 @@
+//! Overlay disegnati direttamente sul frame NV12 prima dell'encoding.
+//!
+//! Lavorare in NV12 evita una conversione BGRA aggiuntiva: la luminanza è
+//! a risoluzione piena, la crominanza (UV interleaved) a metà risoluzione.
+
+use crate::capture::YUVFrame;
+use crate::capture::bitmap_font::{GLYPH_HEIGHT, GLYPH_WIDTH, glyph, text_width};
+use crate::capture::display::span::black_canvas;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// Colore già convertito in YUV (BT.709 limited range, come lo shader del receiver).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct YuvColor {
+    pub y: u8,
+    pub u: u8,
+    pub v: u8,
+}
+
+impl YuvColor {
+    pub fn from_rgb([r, g, b]: [u8; 3]) -> Self {
+        let (r, g, b) = (r as f32, g as f32, b as f32);
+        let y = 16.0 + (0.1826 * r + 0.6142 * g + 0.0620 * b);
+        let u = 128.0 + (-0.1006 * r - 0.3386 * g + 0.4392 * b);
+        let v = 128.0 + (0.4392 * r - 0.3989 * g - 0.0403 * b);
+        Self {
+            y: y.round().clamp(0.0, 255.0) as u8,
+            u: u.round().clamp(0.0, 255.0) as u8,
+            v: v.round().clamp(0.0, 255.0) as u8,
+        }
+    }
+}
+
+/// Stile dell'evidenziazione del cursore, salvato con le impostazioni di cattura.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CursorHighlight {
+    /// Raggio dell'anello in pixel fisici
+    pub radius: u32,
+    /// Spessore dell'anello in pixel fisici
+    pub thickness: u32,
+    pub color: [u8; 3],
+    /// Opacità 0.0..=1.0
+    pub opacity: f32,
+}
+
+impl CursorHighlight {
+    /// Durata dell'animazione "ripple" al click.
+    pub const RIPPLE_DURATION: Duration = Duration::from_millis(450);
+}
+
+impl Default for CursorHighlight {
+    fn default() -> Self {
+        Self {
+            radius: 26,
+            thickness: 4,
+            color: [255, 214, 0],
+            opacity: 0.6,
+        }
+    }
+}
+
+/// Dimensioni dell'anello proposte nelle impostazioni
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RingSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl RingSize {
+    pub const ALL: [RingSize; 3] = [RingSize::Small, RingSize::Medium, RingSize::Large];
+
+    /// (raggio, spessore) in pixel fisici
+    fn radius_thickness(self) -> (u32, u32) {
+        match self {
+            RingSize::Small => (18, 3),
+            RingSize::Medium => (26, 4),
+            RingSize::Large => (40, 6),
+        }
+    }
+
+    /// Preset corrispondente allo stile, `None` se modificato a mano nel file
+    pub fn of(style: &CursorHighlight) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|size| size.radius_thickness() == (style.radius, style.thickness))
+    }
+
+    pub fn apply(self, style: &mut CursorHighlight) {
+        (style.radius, style.thickness) = self.radius_thickness();
+    }
+}
+
+impl fmt::Display for RingSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RingSize::Small => "Small ring",
+            RingSize::Medium => "Medium ring",
+            RingSize::Large => "Large ring",
+        })
+    }
+}
+
+/// Colori dell'anello proposti nelle impostazioni
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RingColor {
+    Yellow,
+    Red,
+    Green,
+    Blue,
+}
+
+impl RingColor {
+    pub const ALL: [RingColor; 4] = [
+        RingColor::Yellow,
+        RingColor::Red,
+        RingColor::Green,
+        RingColor::Blue,
+    ];
+
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            RingColor::Yellow => [255, 214, 0],
+            RingColor::Red => [235, 64, 52],
+            RingColor::Green => [52, 199, 89],
+            RingColor::Blue => [0, 122, 255],
+        }
+    }
+
+    pub fn of(style: &CursorHighlight) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|color| color.rgb() == style.color)
+    }
+
+    pub fn apply(self, style: &mut CursorHighlight) {
+        style.color = self.rgb();
+    }
+}
+
+impl fmt::Display for RingColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RingColor::Yellow => "Yellow",
+            RingColor::Red => "Red",
+            RingColor::Green => "Green",
+            RingColor::Blue => "Blue",
+        })
+    }
+}
+
+/// Stato del cursore relativo al frame da codificare.
+#[derive(Debug, Clone, Copy)]
+pub struct CursorState {
+    pub x: i32,
+    pub y: i32,
+    /// Click in corso: posizione e avanzamento dell'animazione (0.0..1.0)
+    pub ripple: Option<(i32, i32, f32)>,
+}
+
+/// Vista mutabile sui piani di un frame NV12.
+pub struct Nv12Canvas<'a> {
+    y: &'a mut [u8],
+    y_stride: usize,
+    uv: &'a mut [u8],
+    uv_stride: usize,
+    width: i32,
+    height: i32,
+}
+
+impl<'a> Nv12Canvas<'a> {
+    pub fn new(frame: &'a mut YUVFrame) -> Self {
+        Self {
+            y: &mut frame.luminance_bytes,
+            y_stride: frame.luminance_stride as usize,
+            uv: &mut frame.chrominance_bytes,
+            uv_stride: frame.chrominance_stride as usize,
+            width: frame.width,
+            height: frame.height,
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// Alpha-blend di un singolo pixel. La crominanza viene scritta una sola
+    /// volta per blocco 2x2 (sul pixel in alto a sinistra).
+    #[inline]
+    pub fn blend(&mut self, x: i32, y: i32, color: YuvColor, alpha: f32) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height || alpha <= 0.0 {
+            return;
+        }
+        let alpha = alpha.min(1.0);
+        let mix = |dst: u8, src: u8| (dst as f32 + (src as f32 - dst as f32) * alpha) as u8;
+
+        let yi = y as usize * self.y_stride + x as usize;
+        if let Some(p) = self.y.get_mut(yi) {
+            *p = mix(*p, color.y);
+        }
+
+        if x % 2 == 0 && y % 2 == 0 {
+            let ui = (y as usize / 2) * self.uv_stride + x as usize;
+            if ui + 1 < self.uv.len() {
+                self.uv[ui] = mix(self.uv[ui], color.u);
+                self.uv[ui + 1] = mix(self.uv[ui + 1], color.v);
+            }
+        }
+    }
+
+    /// Rettangolo pieno semitrasparente.
+    pub fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: YuvColor, alpha: f32) {
+        let (x0, y0) = (x.max(0), y.max(0));
+        let (x1, y1) = ((x + w).min(self.width), (y + h).min(self.height));
+        for py in y0..y1 {
+            for px in x0..x1 {
+                self.blend(px, py, color, alpha);
+            }
+        }
+    }
+
+    /// Testo col font bitmap 5x7, ogni pixel del glifo diventa un quadrato `scale`x`scale`.
+    pub fn text(&mut self, x: i32, y: i32, text: &str, scale: i32, color: YuvColor, alpha: f32) {
+        let mut pen_x = x;
+        for c in text.chars() {
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (0x10 >> col) != 0 {
+                        self.fill_rect(
+                            pen_x + col * scale,
+                            y + row as i32 * scale,
+                            scale,
+                            scale,
+                            color,
+                            alpha,
+                        );
+                    }
+                }
+            }
+            pen_x += (GLYPH_WIDTH + 1) * scale;
+        }
+    }
+
+    /// Anello antialiasato centrato in (cx, cy).
+    pub fn ring(
+        &mut self,
+        cx: i32,
+        cy: i32,
+        radius: f32,
+        thickness: f32,
+        color: YuvColor,
+        alpha: f32,
+    ) {
+        let half = thickness / 2.0;
+        let outer = (radius + half + 1.0).ceil() as i32;
+        for dy in -outer..=outer {
+            for dx in -outer..=outer {
+                let d = ((dx * dx + dy * dy) as f32).sqrt();
+                let coverage = (half - (d - radius).abs() + 0.5).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    self.blend(cx + dx, cy + dy, color, alpha * coverage);
+                }
+            }
+        }
+    }
+}
+
+/// Disegna anello del cursore e, se presente, il ripple del click.
+pub fn draw_cursor_highlight(frame: &mut YUVFrame, cursor: &CursorState, style: &CursorHighlight) {
+    let color = YuvColor::from_rgb(style.color);
+    let mut canvas = Nv12Canvas::new(frame);
+
+    canvas.ring(
+        cursor.x,
+        cursor.y,
+        style.radius as f32,
+        style.thickness as f32,
+        color,
+        style.opacity,
+    );
+
+    if let Some((x, y, progress)) = cursor.ripple {
+        let radius = style.radius as f32 * (1.0 + progress * 1.5);
+        let alpha = style.opacity * (1.0 - progress);
+        canvas.ring(x, y, radius, style.thickness as f32 * 0.75, color, alpha);
+    }
+}
+
+/// Frame nero con un messaggio centrato, mandato al posto di una sorgente
+/// che per ora non produce immagini (es. finestra ridotta a icona).
+pub fn placeholder_frame(width: u32, height: u32, message: &str) -> YUVFrame {
+    let color = YuvColor::from_rgb([200, 200, 200]);
+    let mut frame = black_canvas(width, height);
+    let mut canvas = Nv12Canvas::new(&mut frame);
+    let scale = (canvas.height() / 240).max(1);
+    let (text_w, text_h) = (text_width(message, scale), GLYPH_HEIGHT * scale);
+    let x = (canvas.width() - text_w) / 2;
+    let y = (canvas.height() - text_h) / 2;
+    canvas.text(x, y, message, scale, color, 1.0);
+    frame
+}
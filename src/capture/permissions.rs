@@ -0,0 +1,188 @@
+//! Verifica dei permessi prima di entrare in modalità caster
+//!
+//! Su macOS (Registrazione schermo, microfono) e sotto Wayland (portal
+//! ScreenCast) la cattura dipende da permessi concessi dall'utente: se
+//! mancano, l'avvio fallisce con errori poco chiari. Qui si controlla lo
+//! stato senza far comparire i prompt del sistema.
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ScreenCapture,
+    Audio,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Access {
+    Granted,
+    /// Lo chiederà il sistema all'avvio della cattura
+    OnFirstUse,
+    /// Scelta precedente ricordata dal portal: vale solo se il portal la
+    /// accetta ancora quando la cattura parte
+    Remembered,
+    /// Negato o non disponibile, con il motivo
+    Missing(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionCheck {
+    pub permission: Permission,
+    pub access: Access,
+}
+
+impl Permission {
+    /// Come concederlo sulla piattaforma corrente
+    pub fn how_to(&self) -> &'static str {
+        match self {
+            Self::ScreenCapture if cfg!(target_os = "macos") => {
+                "Enable this app in System Settings → Privacy & Security → Screen Recording, then restart it."
+            }
+            Self::ScreenCapture => {
+                "Install xdg-desktop-portal with the backend of your desktop (GNOME, KDE, wlroots) and make sure PipeWire is running."
+            }
+            Self::Audio if cfg!(target_os = "macos") => {
+                "Enable this app in System Settings → Privacy & Security → Microphone."
+            }
+            Self::Audio => "Connect an audio device or check the sound settings of the system.",
+        }
+    }
+
+    /// Pagina delle impostazioni di sistema, dove esiste un deep link
+    pub fn settings_url(&self) -> Option<&'static str> {
+        if !cfg!(target_os = "macos") {
+            return None;
+        }
+        Some(match self {
+            Self::ScreenCapture => {
+                "x-apple.systempreferences:com.apple.preference.security?Privacy_ScreenCapture"
+            }
+            Self::Audio => {
+                "x-apple.systempreferences:com.apple.preference.security?Privacy_Microphone"
+            }
+        })
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ScreenCapture => write!(f, "Screen capture"),
+            Self::Audio => write!(f, "Audio capture"),
+        }
+    }
+}
+
+impl PermissionCheck {
+    pub fn is_missing(&self) -> bool {
+        matches!(self.access, Access::Missing(_))
+    }
+}
+
+/// Piattaforme dove la cattura passa da permessi espliciti
+pub fn is_gated() -> bool {
+    cfg!(target_os = "macos") || super::uses_portal()
+}
+
+/// Stato attuale dei permessi. Può bloccare (D-Bus sotto Wayland): va
+/// chiamata fuori dal thread della GUI.
+pub fn check() -> Vec<PermissionCheck> {
+    vec![
+        PermissionCheck {
+            permission: Permission::ScreenCapture,
+            access: screen_capture(),
+        },
+        PermissionCheck {
+            permission: Permission::Audio,
+            access: audio(),
+        },
+    ]
+}
+
+#[cfg(target_os = "macos")]
+fn screen_capture() -> Access {
+    if super::macos::screen_capture_access_granted() {
+        Access::Granted
+    } else {
+        Access::Missing(String::from("Screen Recording is not allowed for this app"))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn screen_capture() -> Access {
+    if !super::uses_portal() {
+        return Access::Granted;
+    }
+    portal_access(super::linux::screen_capture_access())
+}
+
+/// Il restore token può essere stato revocato: si sa solo quando il
+/// portal lo usa, quindi non vale come permesso concesso
+#[cfg(target_os = "linux")]
+fn portal_access(probe: Result<bool, super::CaptureError>) -> Access {
+    use super::CaptureError;
+
+    match probe {
+        Ok(true) => Access::Remembered,
+        Ok(false) => Access::OnFirstUse,
+        Err(CaptureError::Unsupported) => {
+            Access::Missing(String::from("No ScreenCast portal found"))
+        }
+        Err(e) => Access::Missing(e.to_string()),
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn screen_capture() -> Access {
+    Access::Granted
+}
+
+/// Dispositivo da cui parte l'audio: loopback dell'uscita su Windows,
+/// ingresso predefinito altrove
+fn audio() -> Access {
+    let host = cpal::default_host();
+    let config = if cfg!(target_os = "windows") {
+        host.default_output_device()
+            .map(|device| device.default_output_config().map(|_| ()))
+    } else {
+        host.default_input_device()
+            .map(|device| device.default_input_config().map(|_| ()))
+    };
+    match config {
+        None => Access::Missing(String::from("No audio device found")),
+        Some(Err(e)) => Access::Missing(e.to_string()),
+        // macOS chiede il microfono solo quando lo stream parte
+        Some(Ok(())) if cfg!(target_os = "macos") => Access::OnFirstUse,
+        Some(Ok(())) => Access::Granted,
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::capture::CaptureError;
+
+    #[test]
+    fn restore_token_is_not_a_grant() {
+        assert_eq!(portal_access(Ok(true)), Access::Remembered);
+        assert_eq!(portal_access(Ok(false)), Access::OnFirstUse);
+    }
+
+    #[test]
+    fn portal_errors_are_missing() {
+        assert_eq!(
+            portal_access(Err(CaptureError::Unsupported)),
+            Access::Missing(String::from("No ScreenCast portal found"))
+        );
+        assert!(matches!(
+            portal_access(Err(CaptureError::Backend(String::from("no bus")))),
+            Access::Missing(_)
+        ));
+    }
+
+    #[test]
+    fn gated_wherever_the_portal_is_used() {
+        assert_eq!(is_gated(), crate::capture::uses_portal());
+    }
+}
@@ -0,0 +1,208 @@
+//! Finestre escluse dalla cattura: password manager, chat, tutto ciò che non
+//! deve finire nello stream anche se l'utente si dimentica di chiuderlo.
+//!
+//! `SetWindowDisplayAffinity` funziona solo sulle finestre del processo
+//! chiamante, quindi le finestre delle altre app vengono coperte: il loop di
+//! cattura le cerca a ogni frame e riempie di nero la parte non nascosta da
+//! altre finestre, prima di crop, zoom ed encoding.
+
+use crate::capture::YUVFrame;
+use crate::capture::capturer::CropRect;
+use crate::capture::overlay::YuvColor;
+
+/// La finestra corrisponde a una delle voci della lista? Ogni voce è una
+/// parte del titolo oppure il nome esatto della classe, senza distinguere
+/// maiuscole e minuscole.
+pub fn is_excluded(patterns: &[String], title: &str, class: &str) -> bool {
+    let title = title.to_lowercase();
+    patterns
+        .iter()
+        .map(|pattern| pattern.trim())
+        .filter(|pattern| !pattern.is_empty())
+        .any(|pattern| {
+            title.contains(&pattern.to_lowercase()) || class.eq_ignore_ascii_case(pattern)
+        })
+}
+
+/// Parti di `rect` non coperte dalle finestre sopra di lei, tutti
+/// (left, top, right, bottom): solo queste vanno annerite.
+pub fn visible_parts(
+    rect: (i32, i32, i32, i32),
+    above: &[(i32, i32, i32, i32)],
+) -> Vec<(i32, i32, i32, i32)> {
+    let mut parts = vec![rect];
+    for &cover in above {
+        parts = parts
+            .into_iter()
+            .flat_map(|part| subtract(part, cover))
+            .collect();
+        if parts.is_empty() {
+            break;
+        }
+    }
+    parts
+}
+
+/// `part` meno `cover`: fino a quattro strisce (sopra, sotto, sinistra, destra)
+fn subtract(
+    part: (i32, i32, i32, i32),
+    (cl, ct, cr, cb): (i32, i32, i32, i32),
+) -> Vec<(i32, i32, i32, i32)> {
+    let (pl, pt, pr, pb) = part;
+    if cl >= pr || cr <= pl || ct >= pb || cb <= pt {
+        return vec![part];
+    }
+
+    let mut strips = Vec::with_capacity(4);
+    if ct > pt {
+        strips.push((pl, pt, pr, ct));
+    }
+    if cb < pb {
+        strips.push((pl, cb, pr, pb));
+    }
+    let (top, bottom) = (pt.max(ct), pb.min(cb));
+    if cl > pl {
+        strips.push((pl, top, cl, bottom));
+    }
+    if cr < pr {
+        strips.push((cr, top, pr, bottom));
+    }
+    strips
+}
+
+/// Rettangolo di una finestra (left, top, right, bottom, pixel fisici del
+/// desktop virtuale) relativo a un display `(w, h)` con origine `(x, y)`.
+/// Arrotondato al pari verso l'esterno: meglio coprire un pixel in più che
+/// lasciarne uno scoperto.
+pub fn window_mask(
+    (left, top, right, bottom): (i32, i32, i32, i32),
+    (dw, dh): (u32, u32),
+    (dx, dy): (i32, i32),
+) -> Option<CropRect> {
+    let (dw, dh) = (dw as i32, dh as i32);
+
+    let x0 = (left - dx).clamp(0, dw) & !1;
+    let y0 = (top - dy).clamp(0, dh) & !1;
+    let x1 = ((right - dx).clamp(0, dw) + 1) & !1;
+    let y1 = ((bottom - dy).clamp(0, dh) + 1) & !1;
+
+    (x1 > x0 && y1 > y0).then_some(CropRect {
+        x: x0 as u32,
+        y: y0 as u32,
+        w: (x1 - x0).min(dw - x0) as u32,
+        h: (y1 - y0).min(dh - y0) as u32,
+    })
+}
+
+/// Copre di nero le finestre escluse, sul frame intero prima del crop.
+/// Riempimento diretto riga per riga: nessun blend, le maschere sono opache.
+pub fn draw_privacy_masks(frame: &mut YUVFrame, masks: &[CropRect]) {
+    let black = YuvColor::from_rgb([0, 0, 0]);
+    let (width, height) = (frame.width.max(0) as usize, frame.height.max(0) as usize);
+    let y_stride = frame.luminance_stride as usize;
+    let uv_stride = frame.chrominance_stride as usize;
+
+    for mask in masks {
+        let (x0, y0) = (mask.x as usize & !1, mask.y as usize & !1);
+        let x1 = (mask.x as usize + mask.w as usize).min(width);
+        let y1 = (mask.y as usize + mask.h as usize).min(height);
+        if x0 >= x1 || y0 >= y1 {
+            continue;
+        }
+
+        for row in y0..y1 {
+            let start = row * y_stride;
+            if let Some(line) = frame.luminance_bytes.get_mut(start + x0..start + x1) {
+                line.fill(black.y);
+            }
+        }
+        // Un campione UV ogni blocco 2x2, interleaved
+        for row in (y0 / 2)..y1.div_ceil(2) {
+            let start = row * uv_stride + x0;
+            let end = row * uv_stride + x1.div_ceil(2) * 2;
+            if let Some(line) = frame.chrominance_bytes.get_mut(start..end) {
+                for pair in line.chunks_exact_mut(2) {
+                    pair[0] = black.u;
+                    pair[1] = black.v;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_match_title_part_or_exact_class() {
+        let patterns = vec![
+            String::from(" KeePass "),
+            String::from("Chrome_WidgetWin_1"),
+        ];
+        assert!(is_excluded(
+            &patterns,
+            "Database.kdbx - keepassxc",
+            "Qt5QWindow"
+        ));
+        assert!(is_excluded(&patterns, "Inbox", "chrome_widgetwin_1"));
+        assert!(!is_excluded(&patterns, "Inbox", "Chrome_WidgetWin_10"));
+        assert!(!is_excluded(&patterns, "Notepad", "Notepad"));
+        // Una voce vuota non esclude tutto
+        assert!(!is_excluded(&[String::from("  ")], "Notepad", "Notepad"));
+    }
+
+    #[test]
+    fn mask_is_relative_to_the_display_and_rounded_outwards() {
+        // Secondo monitor a destra del primo
+        let mask = window_mask((1925, 101, 2031, 203), (1920, 1080), (1920, 0));
+        assert_eq!(
+            mask,
+            Some(CropRect {
+                x: 4,
+                y: 100,
+                w: 108,
+                h: 104
+            })
+        );
+
+        // Tagliata al bordo del display
+        let mask = window_mask((-50, -50, 100, 100), (1920, 1080), (0, 0));
+        assert_eq!(
+            mask,
+            Some(CropRect {
+                x: 0,
+                y: 0,
+                w: 100,
+                h: 100
+            })
+        );
+
+        // Su un altro display
+        assert_eq!(window_mask((0, 0, 800, 600), (1920, 1080), (1920, 0)), None);
+    }
+
+    #[test]
+    fn windows_above_hide_part_of_the_mask() {
+        let rect = (0, 0, 100, 100);
+        assert_eq!(visible_parts(rect, &[]), vec![rect]);
+        assert!(visible_parts(rect, &[(-10, -10, 200, 200)]).is_empty());
+        assert_eq!(visible_parts(rect, &[(200, 0, 300, 100)]), vec![rect]);
+
+        // Metà destra coperta
+        assert_eq!(
+            visible_parts(rect, &[(50, -10, 200, 200)]),
+            vec![(0, 0, 50, 100)]
+        );
+
+        // Un buco al centro lascia quattro strisce
+        let parts = visible_parts(rect, &[(25, 25, 75, 75)]);
+        let area: i32 = parts.iter().map(|(l, t, r, b)| (r - l) * (b - t)).sum();
+        assert_eq!(area, 100 * 100 - 50 * 50);
+        assert!(
+            parts
+                .iter()
+                .all(|&(l, t, r, b)| { r <= 25 || l >= 75 || b <= 25 || t >= 75 })
+        );
+    }
+}
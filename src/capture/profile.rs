@@ -0,0 +1,431 @@
+use crate::assets::FRAME_RATE;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Risoluzione e frame rate di uscita dello stream.
+///
+/// `width`/`height` sono il box massimo in cui viene scalata la sorgente
+/// (mantenendo l'aspect ratio, senza mai ingrandire). Un profilo con
+/// dimensioni a zero è il profilo "Native": la sorgente viene codificata
+/// alla sua risoluzione originale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamProfile {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+}
+
+impl StreamProfile {
+    pub const HD_30: StreamProfile = StreamProfile::new(1280, 720, 30);
+    pub const FHD_30: StreamProfile = StreamProfile::new(1920, 1080, 30);
+    pub const FHD_60: StreamProfile = StreamProfile::new(1920, 1080, 60);
+    pub const NATIVE: StreamProfile = StreamProfile::new(0, 0, FRAME_RATE);
+
+    /// Presets shown in the caster dropdown.
+    pub const PRESETS: [StreamProfile; 4] = [
+        StreamProfile::HD_30,
+        StreamProfile::FHD_30,
+        StreamProfile::FHD_60,
+        StreamProfile::NATIVE,
+    ];
+
+    pub const fn new(width: u32, height: u32, fps: u32) -> Self {
+        Self { width, height, fps }
+    }
+
+    pub fn is_native(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Frame rate cap applied to the capture loop.
+    pub fn fps_cap(&self) -> u32 {
+        self.fps.clamp(FpsCap::MIN, FpsCap::MAX)
+    }
+
+    /// Encoder output size for a source of `src_w`×`src_h`.
+    ///
+    /// Fits the source inside the profile box preserving the aspect ratio,
+    /// never upscales, and always returns even dimensions (NV12 chroma).
+    pub fn output_size(&self, src_w: u32, src_h: u32) -> (u32, u32) {
+        let even = |v: u32| (v + (v % 2)).max(2);
+
+        if self.is_native() || (src_w <= self.width && src_h <= self.height) {
+            return (even(src_w), even(src_h));
+        }
+
+        let scale = f64::min(
+            self.width as f64 / src_w.max(1) as f64,
+            self.height as f64 / src_h.max(1) as f64,
+        );
+        let w = (src_w as f64 * scale).round() as u32;
+        let h = (src_h as f64 * scale).round() as u32;
+        (even(w & !1), even(h & !1))
+    }
+
+    /// Concrete profile for a given source: the one sent to receivers.
+    pub fn resolve(&self, src_w: u32, src_h: u32) -> StreamProfile {
+        let (width, height) = self.output_size(src_w, src_h);
+        StreamProfile::new(width, height, self.fps_cap())
+    }
+}
+
+/// Tetto al frame rate di cattura scelto dall'utente, indipendente da
+/// `FRAME_RATE`: in cattura viene limitato alla frequenza del display.
+/// Il profilo "Native" lo segue, gli altri restano sotto il proprio fps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FpsCap(pub u32);
+
+impl FpsCap {
+    pub const MIN: u32 = 15;
+    pub const MAX: u32 = 144;
+
+    /// Scelte mostrate nella pagina del caster
+    pub const PRESETS: [FpsCap; 5] = [FpsCap(30), FpsCap(60), FpsCap(90), FpsCap(120), FpsCap(144)];
+
+    /// Tetto effettivo su un display a `refresh_rate` Hz (`None` = sconosciuta)
+    pub fn ceiling(&self, refresh_rate: Option<u32>) -> u32 {
+        let cap = self.0.clamp(Self::MIN, Self::MAX);
+        refresh_rate.map_or(cap, |hz| cap.min(hz.max(Self::MIN)))
+    }
+}
+
+impl Default for FpsCap {
+    fn default() -> Self {
+        FpsCap(FRAME_RATE)
+    }
+}
+
+impl fmt::Display for FpsCap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Max {} fps", self.0)
+    }
+}
+
+/// Tetto alla risoluzione codificata, indipendente da cattura e profilo:
+/// una sorgente 4K può andare in onda a 1080p senza cambiare monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EncodeScale {
+    #[default]
+    Source,
+    P1440,
+    P1080,
+    P720,
+}
+
+impl EncodeScale {
+    pub const ALL: [EncodeScale; 4] = [
+        EncodeScale::Source,
+        EncodeScale::P1440,
+        EncodeScale::P1080,
+        EncodeScale::P720,
+    ];
+
+    /// Box massimo per `CaptureOpts::scale_to`, `None` = risoluzione della sorgente
+    pub fn size(&self) -> Option<(u32, u32)> {
+        match self {
+            Self::Source => None,
+            Self::P1440 => Some((2560, 1440)),
+            Self::P1080 => Some((1920, 1080)),
+            Self::P720 => Some((1280, 720)),
+        }
+    }
+}
+
+impl fmt::Display for EncodeScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.size() {
+            None => write!(f, "Source size"),
+            Some((_, h)) => write!(f, "Max {}p", h),
+        }
+    }
+}
+
+/// Secondo encoder per i receiver lenti (simulcast): box massimo del
+/// livello ridotto, `Off` = un solo encoder per tutti
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Simulcast {
+    #[default]
+    Off,
+    P360,
+    P480,
+    P540,
+}
+
+impl Simulcast {
+    pub const ALL: [Simulcast; 4] = [
+        Simulcast::Off,
+        Simulcast::P360,
+        Simulcast::P480,
+        Simulcast::P540,
+    ];
+
+    pub fn size(&self) -> Option<(u32, u32)> {
+        match self {
+            Self::Off => None,
+            Self::P360 => Some((640, 360)),
+            Self::P480 => Some((854, 480)),
+            Self::P540 => Some((960, 540)),
+        }
+    }
+}
+
+impl fmt::Display for Simulcast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.size() {
+            None => write!(f, "Single stream"),
+            Some((_, h)) => write!(f, "Extra {}p stream", h),
+        }
+    }
+}
+
+/// Rate control scelto per la sessione: a bitrate costante il traffico è
+/// prevedibile sui link con banda rigida, a scapito della qualità sulle
+/// scene complesse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitrateMode {
+    /// VBR/CRF della catena di encoder
+    #[default]
+    Variable,
+    /// Target in kbps
+    Constant(u32),
+}
+
+impl BitrateMode {
+    pub const PRESETS: [BitrateMode; 6] = [
+        BitrateMode::Variable,
+        BitrateMode::Constant(1000),
+        BitrateMode::Constant(2000),
+        BitrateMode::Constant(3000),
+        BitrateMode::Constant(5000),
+        BitrateMode::Constant(8000),
+    ];
+
+    /// Target in bps, `None` in VBR
+    pub fn constant_bps(&self) -> Option<u32> {
+        match self {
+            Self::Variable => None,
+            Self::Constant(kbps) => Some(kbps * 1000),
+        }
+    }
+}
+
+impl fmt::Display for BitrateMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Variable => write!(f, "Variable bitrate"),
+            Self::Constant(kbps) if kbps % 1000 == 0 => {
+                write!(f, "Constant {} Mbps", kbps / 1000)
+            }
+            Self::Constant(kbps) => write!(f, "Constant {} kbps", kbps),
+        }
+    }
+}
+
+/// Cattura dei desktop HDR: in `Auto` il tone-mapping verso SDR si attiva
+/// da solo quando il monitor ha l'HDR acceso (per ora solo WGC)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HdrMode {
+    #[default]
+    Auto,
+    ToneMap,
+    Off,
+}
+
+impl HdrMode {
+    pub const ALL: [HdrMode; 3] = [HdrMode::Auto, HdrMode::ToneMap, HdrMode::Off];
+
+    /// Cattura in virgola mobile + tone-mapping per un display con o senza HDR
+    pub fn tone_map(&self, hdr_display: bool) -> bool {
+        match self {
+            Self::Auto => hdr_display,
+            Self::ToneMap => true,
+            Self::Off => false,
+        }
+    }
+}
+
+impl fmt::Display for HdrMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => write!(f, "HDR: auto"),
+            Self::ToneMap => write!(f, "HDR: always tone-map"),
+            Self::Off => write!(f, "HDR: off"),
+        }
+    }
+}
+
+/// Matrice dei coefficienti YUV↔RGB
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorMatrix {
+    #[default]
+    Bt709,
+    Bt601,
+}
+
+impl ColorMatrix {
+    /// Pesi (Kr, Kb) della luminanza
+    fn weights(&self) -> (f32, f32) {
+        match self {
+            Self::Bt709 => (0.2126, 0.0722),
+            Self::Bt601 => (0.299, 0.114),
+        }
+    }
+}
+
+/// Escursione dei campioni: limited (16-235, 16-240) o full (0-255)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorRange {
+    #[default]
+    Limited,
+    Full,
+}
+
+/// Spazio colore dei frame YUV: come li produce il capturer, come li marca
+/// l'encoder e come li riconverte in RGB il receiver. Se i tre non
+/// coincidono i colori escono slavati o troppo scuri.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorSpace {
+    pub matrix: ColorMatrix,
+    pub range: ColorRange,
+}
+
+/// Coefficienti YUV → RGB (valori normalizzati 0..1, croma centrata su 0.5)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct YuvToRgb {
+    pub y_offset: f32,
+    pub y_scale: f32,
+    pub cr_r: f32,
+    pub cb_g: f32,
+    pub cr_g: f32,
+    pub cb_b: f32,
+}
+
+impl ColorSpace {
+    /// Quello di tutti i capturer (shader WGC, PipeWire, ScreenCaptureKit)
+    pub const CAPTURE: ColorSpace = ColorSpace::new(ColorMatrix::Bt709, ColorRange::Limited);
+
+    pub const ALL: [ColorSpace; 4] = [
+        ColorSpace::CAPTURE,
+        ColorSpace::new(ColorMatrix::Bt709, ColorRange::Full),
+        ColorSpace::new(ColorMatrix::Bt601, ColorRange::Limited),
+        ColorSpace::new(ColorMatrix::Bt601, ColorRange::Full),
+    ];
+
+    pub const fn new(matrix: ColorMatrix, range: ColorRange) -> Self {
+        Self { matrix, range }
+    }
+
+    pub fn yuv_to_rgb(&self) -> YuvToRgb {
+        let (kr, kb) = self.matrix.weights();
+        let kg = 1.0 - kr - kb;
+        let (y_offset, y_scale, c_scale) = match self.range {
+            ColorRange::Limited => (16.0 / 255.0, 255.0 / 219.0, 255.0 / 224.0),
+            ColorRange::Full => (0.0, 1.0, 1.0),
+        };
+        YuvToRgb {
+            y_offset,
+            y_scale,
+            cr_r: 2.0 * (1.0 - kr) * c_scale,
+            cb_g: 2.0 * kb * (1.0 - kb) / kg * c_scale,
+            cr_g: 2.0 * kr * (1.0 - kr) / kg * c_scale,
+            cb_b: 2.0 * (1.0 - kb) * c_scale,
+        }
+    }
+
+    /// Opzioni FFmpeg che lo scrivono nel VUI dell'SPS
+    pub fn ffmpeg_options(&self) -> [(&'static str, &'static str); 4] {
+        let standard = match self.matrix {
+            ColorMatrix::Bt709 => "bt709",
+            ColorMatrix::Bt601 => "smpte170m",
+        };
+        let range = match self.range {
+            ColorRange::Limited => "tv",
+            ColorRange::Full => "pc",
+        };
+        [
+            ("colorspace", standard),
+            ("color_primaries", standard),
+            ("color_trc", standard),
+            ("color_range", range),
+        ]
+    }
+}
+
+impl YuvToRgb {
+    /// Un campione YUV (0..1) in RGB (0..1)
+    pub fn convert(&self, y: f32, u: f32, v: f32) -> [f32; 3] {
+        let y = (y - self.y_offset) * self.y_scale;
+        let (cb, cr) = (u - 0.5, v - 0.5);
+        [
+            y + self.cr_r * cr,
+            y - self.cb_g * cb - self.cr_g * cr,
+            y + self.cb_b * cb,
+        ]
+        .map(|c| c.clamp(0.0, 1.0))
+    }
+}
+
+impl fmt::Display for ColorSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let matrix = match self.matrix {
+            ColorMatrix::Bt709 => "BT.709",
+            ColorMatrix::Bt601 => "BT.601",
+        };
+        let range = match self.range {
+            ColorRange::Limited => "limited",
+            ColorRange::Full => "full",
+        };
+        write!(f, "{} {}", matrix, range)
+    }
+}
+
+impl Default for StreamProfile {
+    fn default() -> Self {
+        StreamProfile::NATIVE
+    }
+}
+
+impl fmt::Display for StreamProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_native() {
+            write!(f, "Native")
+        } else {
+            write!(f, "{}p{}", self.height, self.fps)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bt709_limited_matches_the_shader_constants() {
+        let c = ColorSpace::default().yuv_to_rgb();
+        for (got, want) in [
+            (c.cr_r, 1.7927),
+            (c.cb_g, 0.2132),
+            (c.cr_g, 0.5329),
+            (c.cb_b, 2.1124),
+        ] {
+            assert!((got - want).abs() < 1e-3, "{} != {}", got, want);
+        }
+    }
+
+    #[test]
+    fn range_maps_black_and_white_to_the_ends() {
+        for color in ColorSpace::ALL {
+            let (black, white) = match color.range {
+                ColorRange::Limited => (16.0 / 255.0, 235.0 / 255.0),
+                ColorRange::Full => (0.0, 1.0),
+            };
+            let c = color.yuv_to_rgb();
+            assert_eq!(c.convert(black, 0.5, 0.5), [0.0; 3], "{}", color);
+            for channel in c.convert(white, 0.5, 0.5) {
+                assert!((channel - 1.0).abs() < 1e-5, "{}", color);
+            }
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! Sorgente sintetica per test e loopback: un pattern NV12 deterministico al
+//! posto dello schermo, così encoder e pipeline girano anche su macchine CI
+//! senza display e il receiver può verificare i pixel decodificati.
+//!
+//! Il frame è diviso in due: in alto otto barre colore, in basso un riquadro
+//! che si sposta a ogni frame e il contatore dei frame.
+
+use anyhow::{Result, anyhow, bail};
+use async_trait::async_trait;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use crate::assets::FRAME_RATE;
+use crate::capture::budget::BudgetLevel;
+use crate::capture::overlay::{Nv12Canvas, YuvColor};
+use crate::capture::{
+    CaptureOpts, CropRect, DisplayInfo, ScreenCapture, ScreenCaptureImpl, StreamProfile, YUVFrame,
+};
+use crate::encoder::{EncodedVideo, FfmpegEncoder, FrameData};
+
+/// Pattern di test: barre colore, riquadro in movimento, contatore
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestPattern {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TestPattern {
+    /// Bianco, giallo, ciano, verde, magenta, rosso, blu, nero (RGB)
+    pub const BARS: [[u8; 3]; 8] = [
+        [255, 255, 255],
+        [255, 255, 0],
+        [0, 255, 255],
+        [0, 255, 0],
+        [255, 0, 255],
+        [255, 0, 0],
+        [0, 0, 255],
+        [0, 0, 0],
+    ];
+
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width: (width + (width % 2)).max(16),
+            height: (height + (height % 2)).max(16),
+        }
+    }
+
+    /// Indice della barra che contiene la colonna `x`
+    pub fn bar_at(&self, x: u32) -> usize {
+        ((x as u64 * 8) / self.width as u64).min(7) as usize
+    }
+
+    /// Colonna centrale della barra `bar`, lontana dai bordi sfumati dal codec
+    pub fn bar_center(&self, bar: usize) -> u32 {
+        ((2 * bar as u64 + 1) * self.width as u64 / 16) as u32
+    }
+
+    /// Luma attesa di una barra (stessa conversione degli overlay)
+    pub fn bar_luma(bar: usize) -> u8 {
+        YuvColor::from_rgb(Self::BARS[bar]).y
+    }
+
+    /// Altezza della fascia con le barre (metà superiore, pari per la crominanza)
+    fn bars_height(&self) -> u32 {
+        (self.height / 2) & !1
+    }
+
+    /// Lato del riquadro in movimento
+    fn box_size(&self) -> u32 {
+        (self.height / 8).max(2) & !1
+    }
+
+    /// Posizione (x, y) del riquadro al frame `index`: avanza di 4 pixel a
+    /// frame lungo la metà inferiore e riparte da sinistra
+    pub fn box_position(&self, index: u64) -> (u32, u32) {
+        let size = self.box_size();
+        let travel = self.width.saturating_sub(size) as u64;
+        let x = (index * 4 % travel.max(1)) as u32;
+        let y = self.bars_height() + (self.height - self.bars_height()).saturating_sub(size) / 2;
+        (x, y)
+    }
+
+    /// Frame NV12 `index`, alla risoluzione piena del pattern
+    pub fn render(&self, index: u64) -> YUVFrame {
+        let mut frame = blank_frame(self.width, self.height);
+        let mut canvas = Nv12Canvas::new(&mut frame);
+
+        let bar_w = self.width.div_ceil(8);
+        for (bar, rgb) in Self::BARS.iter().enumerate() {
+            let x = (bar as u32 * self.width / 8) as i32;
+            let h = self.bars_height() as i32;
+            canvas.fill_rect(x, 0, bar_w as i32, h, YuvColor::from_rgb(*rgb), 1.0);
+        }
+
+        let white = YuvColor::from_rgb([255, 255, 255]);
+        let (bx, by) = self.box_position(index);
+        let size = self.box_size() as i32;
+        canvas.fill_rect(bx as i32, by as i32, size, size, white, 1.0);
+
+        let scale = (self.height / 90).max(1) as i32;
+        let text_y = self.height as i32 - 9 * scale;
+        canvas.text(2 * scale, text_y, &index.to_string(), scale, white, 1.0);
+
+        frame
+    }
+
+    /// Controlla il piano Y di un frame YUV420p decodificato: al centro di
+    /// ogni barra la luma deve essere quella attesa entro `tolerance`.
+    pub fn matches_luma(&self, yuv: &[u8], width: u32, height: u32, tolerance: u8) -> bool {
+        if (width, height) != (self.width, self.height) || yuv.len() < (width * height) as usize {
+            return false;
+        }
+        let y = (self.bars_height() / 2) as usize;
+        (0..8).all(|bar| {
+            let x = self.bar_center(bar) as usize;
+            yuv[y * width as usize + x].abs_diff(Self::bar_luma(bar)) <= tolerance
+        })
+    }
+}
+
+/// Frame NV12 nero (Y 16, UV 128)
+fn blank_frame(width: u32, height: u32) -> YUVFrame {
+    let (w, h) = (width as usize, height as usize);
+    YUVFrame {
+        display_time: 0,
+        width: width as i32,
+        height: height as i32,
+        luminance_bytes: vec![16u8; w * h],
+        luminance_stride: width as i32,
+        chrominance_bytes: vec![128u8; w * h / 2],
+        chrominance_stride: width as i32,
+    }
+}
+
+/// Area di `crop` allineata ai pixel pari e limitata al frame: (x, y, w, h)
+fn crop_bounds(width: u32, height: u32, crop: &CropRect) -> (u32, u32, u32, u32) {
+    let x = (crop.x & !1).min(width - 2);
+    let y = (crop.y & !1).min(height - 2);
+    let (w, h) = crop.even_size();
+    (x, y, w.min(width - x).max(2), h.min(height - y).max(2))
+}
+
+/// Ritaglia `crop` da un frame NV12
+fn crop_frame(src: &YUVFrame, crop: &CropRect) -> YUVFrame {
+    let (x, y, w, h) = crop_bounds(src.width as u32, src.height as u32, crop);
+    let (x, y, w, h) = (x as usize, y as usize, w as usize, h as usize);
+
+    let stride = src.luminance_stride as usize;
+    let mut luma = Vec::with_capacity(w * h);
+    for row in y..y + h {
+        luma.extend_from_slice(&src.luminance_bytes[row * stride + x..row * stride + x + w]);
+    }
+    let stride = src.chrominance_stride as usize;
+    let mut chroma = Vec::with_capacity(w * h / 2);
+    for row in y / 2..(y + h) / 2 {
+        chroma.extend_from_slice(&src.chrominance_bytes[row * stride + x..row * stride + x + w]);
+    }
+
+    YUVFrame {
+        display_time: 0,
+        width: w as i32,
+        height: h as i32,
+        luminance_bytes: luma,
+        luminance_stride: w as i32,
+        chrominance_bytes: chroma,
+        chrominance_stride: w as i32,
+    }
+}
+
+struct PatternDisplay {
+    width: u32,
+    height: u32,
+}
+
+impl DisplayInfo for PatternDisplay {
+    fn resolution(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn dpi_conversion_factor(&self) -> f64 {
+        1.0
+    }
+}
+
+/// `ScreenCapture` che codifica il [`TestPattern`] invece dello schermo.
+///
+/// Rispetta `CaptureOpts` come i capturer reali: crop, schermo nero, pausa,
+/// profilo e limite di fps.
+pub struct TestPatternCapture {
+    pattern: TestPattern,
+    fps: u32,
+    display: PatternDisplay,
+    cancel_token: Option<CancellationToken>,
+}
+
+impl TestPatternCapture {
+    pub fn new(width: u32, height: u32) -> Self {
+        let pattern = TestPattern::new(width, height);
+        Self {
+            pattern,
+            fps: FRAME_RATE,
+            display: PatternDisplay {
+                width: pattern.width,
+                height: pattern.height,
+            },
+            cancel_token: None,
+        }
+    }
+
+    /// Frame rate della sorgente (il limite di `CaptureOpts` resta valido)
+    pub fn with_fps(mut self, fps: u32) -> Self {
+        self.fps = fps.max(1);
+        self
+    }
+
+    pub fn pattern(&self) -> TestPattern {
+        self.pattern
+    }
+}
+
+#[async_trait]
+impl ScreenCapture for TestPatternCapture {
+    fn new_default() -> Result<ScreenCaptureImpl, anyhow::Error> {
+        bail!("TestPatternCapture has no default display, use TestPatternCapture::new")
+    }
+
+    fn display(&self) -> &dyn DisplayInfo {
+        &self.display
+    }
+
+    async fn start_capture(
+        &mut self,
+        mut encoder: FfmpegEncoder,
+        output: tokio::sync::mpsc::Sender<EncodedVideo>,
+        opts_rx: watch::Receiver<CaptureOpts>,
+    ) -> Result<(), anyhow::Error> {
+        if self.cancel_token.is_some() {
+            return Err(anyhow!("Capture already running"));
+        }
+
+        let cancel = CancellationToken::new();
+        self.cancel_token = Some(cancel.clone());
+
+        let pattern = self.pattern;
+        let fps = self.fps;
+        let force_idr = encoder.force_idr.clone();
+        let simulcast = encoder.simulcast.clone();
+        tokio::spawn(async move {
+            let started = Instant::now();
+            let mut index: u64 = 0;
+            let mut current_crop: Option<CropRect> = None;
+            let mut current_profile: StreamProfile = opts_rx.borrow().profile;
+            let mut current_scale_to = opts_rx.borrow().scale_to;
+
+            loop {
+                if cancel.is_cancelled() {
+                    break;
+                }
+
+                let opts = opts_rx.borrow().clone();
+                if opts.paused {
+                    tokio::time::sleep(Duration::from_millis(8)).await;
+                    continue;
+                }
+
+                // Crop, profilo o tetto cambiati: encoder alla nuova risoluzione
+                if opts.crop != current_crop
+                    || opts.profile != current_profile
+                    || opts.scale_to != current_scale_to
+                {
+                    current_crop = opts.crop;
+                    current_profile = opts.profile;
+                    current_scale_to = opts.scale_to;
+                    let (src_w, src_h) = match &current_crop {
+                        Some(crop) => {
+                            let (_, _, w, h) = crop_bounds(pattern.width, pattern.height, crop);
+                            (w, h)
+                        }
+                        None => (pattern.width, pattern.height),
+                    };
+                    let (enc_w, enc_h) = opts.output_size(src_w, src_h);
+                    let rate = opts.rate_control(BudgetLevel::Full);
+                    let rebuilt = FfmpegEncoder::with_rate_control(
+                        src_w,
+                        src_h,
+                        enc_w,
+                        enc_h,
+                        rate,
+                        opts.max_slice_size,
+                    );
+                    encoder = match rebuilt {
+                        Ok(encoder) => encoder.with_frame_pool(opts.frame_pool),
+                        Err(e) => {
+                            log::error!("Encoder rebuild failed, capture stopped: {}", e);
+                            break;
+                        }
+                    };
+                    // Stesso flag dei peer: il nuovo encoder parte da un IDR
+                    encoder.force_idr = force_idr.clone();
+                    encoder.simulcast = simulcast.clone();
+                    force_idr.store(true, Ordering::Relaxed);
+                }
+
+                let full = pattern.render(index);
+                let frame = match &current_crop {
+                    Some(crop) => crop_frame(&full, crop),
+                    None => full,
+                };
+                let frame = if opts.blank_screen {
+                    blank_frame(frame.width as u32, frame.height as u32)
+                } else {
+                    frame
+                };
+                index += 1;
+
+                match encoder.encode(
+                    FrameData::NV12(&frame),
+                    FfmpegEncoder::frame_time(started.elapsed()),
+                ) {
+                    Ok(encoded) if !encoded.is_empty() => {
+                        if output.send(encoded).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::warn!("Test pattern encode failed: {}", e),
+                }
+
+                let budget = Duration::from_millis(1000 / fps.min(opts.fps_limit()).max(1) as u64);
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = tokio::time::sleep(budget) => {}
+                }
+            }
+
+            // Coda dell'encoder: anche gli ultimi frame arrivano al receiver
+            if let Ok(tail) = encoder.flush()
+                && !tail.is_empty()
+            {
+                let _ = output.send(tail).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn stop_capture(&mut self) -> Result<(), anyhow::Error> {
+        if let Some(cancel) = self.cancel_token.take() {
+            cancel.cancel();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! Orario di sistema impresso nel frame prima dell'encoding.
+//!
+//! Disegnato col font bitmap degli overlay sul NV12 già pronto per
+//! l'encoder: registrazioni e receiver vedono tutti la stessa ora.
+
+use crate::capture::YUVFrame;
+use crate::capture::bitmap_font::{GLYPH_HEIGHT, text_width};
+use crate::capture::overlay::{Nv12Canvas, YuvColor};
+use crate::capture::watermark::WatermarkCorner;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimestampFormat {
+    #[default]
+    DateTime,
+    /// Con i millisecondi, per allineare più sorgenti
+    DateTimeMillis,
+    Time,
+}
+
+impl TimestampFormat {
+    pub const ALL: [TimestampFormat; 3] = [
+        TimestampFormat::DateTime,
+        TimestampFormat::DateTimeMillis,
+        TimestampFormat::Time,
+    ];
+
+    fn pattern(&self) -> &'static str {
+        match self {
+            TimestampFormat::DateTime => "%Y-%m-%d %H:%M:%S",
+            TimestampFormat::DateTimeMillis => "%Y-%m-%d %H:%M:%S%.3f",
+            TimestampFormat::Time => "%H:%M:%S",
+        }
+    }
+}
+
+impl fmt::Display for TimestampFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TimestampFormat::DateTime => "Date and time",
+            TimestampFormat::DateTimeMillis => "Date and time (ms)",
+            TimestampFormat::Time => "Time only",
+        })
+    }
+}
+
+/// Orario da comporre, con un'etichetta facoltativa davanti
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timestamp {
+    pub corner: WatermarkCorner,
+    pub format: TimestampFormat,
+    /// Es. nome della postazione; il font ha solo maiuscole, cifre e `+-.,/:%`
+    pub label: String,
+    /// Opacità 0.0..=1.0
+    pub opacity: f32,
+}
+
+impl Timestamp {
+    pub fn text(&self, now: DateTime<Local>) -> String {
+        let time = now.format(self.format.pattern()).to_string();
+        match self.label.trim() {
+            "" => time,
+            label => format!("{}  {}", label, time),
+        }
+    }
+}
+
+/// Compone l'orario corrente nell'angolo scelto, su uno sfondo scuro.
+pub fn draw_timestamp(frame: &mut YUVFrame, timestamp: &Timestamp) {
+    if timestamp.opacity <= 0.0 {
+        return;
+    }
+
+    let background = YuvColor::from_rgb([20, 20, 20]);
+    let foreground = YuvColor::from_rgb([255, 255, 255]);
+    let text = timestamp.text(Local::now());
+
+    let mut canvas = Nv12Canvas::new(frame);
+    let scale = (canvas.height() / 360).max(2);
+    let padding = 2 * scale;
+    let margin = (canvas.width().min(canvas.height()) / 40).max(4);
+    let box_w = text_width(&text, scale) + 2 * padding;
+    let box_h = GLYPH_HEIGHT * scale + 2 * padding;
+
+    let x0 = match timestamp.corner {
+        WatermarkCorner::TopLeft | WatermarkCorner::BottomLeft => margin,
+        WatermarkCorner::TopRight | WatermarkCorner::BottomRight => canvas.width() - margin - box_w,
+    };
+    let y0 = match timestamp.corner {
+        WatermarkCorner::TopLeft | WatermarkCorner::TopRight => margin,
+        WatermarkCorner::BottomLeft | WatermarkCorner::BottomRight => {
+            canvas.height() - margin - box_h
+        }
+    };
+
+    canvas.fill_rect(x0, y0, box_w, box_h, background, 0.6 * timestamp.opacity);
+    canvas.text(
+        x0 + padding,
+        y0 + padding,
+        &text,
+        scale,
+        foreground,
+        timestamp.opacity,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn text_follows_format_and_label() {
+        let now = Local.with_ymd_and_hms(2024, 3, 9, 7, 5, 2).unwrap();
+        let mut timestamp = Timestamp {
+            corner: WatermarkCorner::TopLeft,
+            format: TimestampFormat::DateTime,
+            label: String::new(),
+            opacity: 1.0,
+        };
+        assert_eq!(timestamp.text(now), "2024-03-09 07:05:02");
+
+        timestamp.format = TimestampFormat::DateTimeMillis;
+        assert_eq!(timestamp.text(now), "2024-03-09 07:05:02.000");
+
+        timestamp.format = TimestampFormat::Time;
+        timestamp.label = String::from(" CAM 2 ");
+        assert_eq!(timestamp.text(now), "CAM 2  07:05:02");
+    }
+}
@@ -1,25 +1,57 @@
 //! Traits for screen capture functionality
 
-use crate::capture::ScreenCaptureImpl;
-use crate::encoder::FfmpegEncoder;
+use crate::capture::{CaptureError, ScreenCaptureImpl};
+use crate::encoder::{EncodedVideo, FfmpegEncoder};
+use crate::pipeline::health::PipelineHealth;
 use async_trait::async_trait;
+use std::sync::Arc;
 use tokio::sync::watch;
 
 /// Trait for screen capture implementations
 #[async_trait]
 pub trait ScreenCapture {
-    fn new_default() -> Result<ScreenCaptureImpl, anyhow::Error>;
+    fn new_default() -> Result<ScreenCaptureImpl, CaptureError>;
 
     fn display(&self) -> &dyn DisplayInfo;
 
     async fn start_capture(
         &mut self,
         encoder: FfmpegEncoder,
-        output: tokio::sync::mpsc::Sender<bytes::Bytes>,
+        output: tokio::sync::mpsc::Sender<EncodedVideo>,
         opts_rx: watch::Receiver<super::capturer::CaptureOpts>,
     ) -> Result<(), anyhow::Error>;
 
     async fn stop_capture(&mut self) -> Result<(), anyhow::Error>;
+
+    /// Changes of the captured source reported while capturing, `None` for
+    /// backends that can't observe them.
+    fn source_events(&self) -> Option<watch::Receiver<SourceEvent>> {
+        None
+    }
+
+    /// Contatori del caster: i frame che il backend non riesce a consegnare
+    /// al capturer contano come perdite di cattura. Vale dal prossimo
+    /// `start_capture`; ignorato dai backend che non scartano frame.
+    fn set_health(&mut self, _health: Arc<PipelineHealth>) {}
+
+    /// Il backend sa togliere il puntatore dai frame (`CaptureOpts::show_cursor`).
+    /// Il portal e il backend generico lo includono sempre.
+    fn can_hide_cursor(&self) -> bool {
+        false
+    }
+}
+
+/// Cambiamento della sorgente durante la cattura, segnalato dal backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SourceEvent {
+    #[default]
+    None,
+    /// Nuova risoluzione: il backend ha già ricreato l'encoder con un IDR
+    Resized(u32, u32),
+    /// Monitor scollegato: la cattura non riceve più frame
+    Lost,
+    /// Finestra condivisa chiusa: non si ripiega su un altro schermo
+    WindowClosed,
 }
 
 /// Trait for display information
@@ -0,0 +1,225 @@
+//! Watermark: logo PNG composto in un angolo del frame prima dell'encoding.
+//!
+//! Il PNG viene decodificato una volta per percorso ([`WatermarkImage`]) e
+//! scalato a ogni cambio di scala; i pixel sono già convertiti in YUV così il
+//! loop di cattura fa solo l'alpha-blend sul NV12.
+
+use crate::capture::YUVFrame;
+use crate::capture::overlay::{Nv12Canvas, YuvColor};
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+
+/// Lato massimo del logo dopo la scala, evita blend enormi per errore
+const MAX_SIDE: u32 = 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WatermarkCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl WatermarkCorner {
+    pub const ALL: [WatermarkCorner; 4] = [
+        WatermarkCorner::TopLeft,
+        WatermarkCorner::TopRight,
+        WatermarkCorner::BottomLeft,
+        WatermarkCorner::BottomRight,
+    ];
+}
+
+impl fmt::Display for WatermarkCorner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WatermarkCorner::TopLeft => "Top left",
+            WatermarkCorner::TopRight => "Top right",
+            WatermarkCorner::BottomLeft => "Bottom left",
+            WatermarkCorner::BottomRight => "Bottom right",
+        })
+    }
+}
+
+/// PNG decodificato in RGBA, riusato finché il percorso non cambia
+#[derive(Clone)]
+pub struct WatermarkImage {
+    path: String,
+    width: u32,
+    height: u32,
+    rgba: Arc<[[u8; 4]]>,
+}
+
+impl fmt::Debug for WatermarkImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WatermarkImage")
+            .field("path", &self.path)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+impl WatermarkImage {
+    /// Decodifica un PNG, con o senza alpha
+    pub fn decode(path: &str) -> anyhow::Result<Self> {
+        let file = File::open(path).with_context(|| format!("cannot open {}", path))?;
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder
+            .read_info()
+            .with_context(|| format!("{} is not a valid PNG", path))?;
+        let mut buf = vec![0u8; reader.output_buffer_size().context("PNG image too large")?];
+        let info = reader.next_frame(&mut buf)?;
+        let data = &buf[..info.buffer_size()];
+
+        let rgba = match info.color_type {
+            png::ColorType::Rgba => data
+                .chunks_exact(4)
+                .map(|p| [p[0], p[1], p[2], p[3]])
+                .collect(),
+            png::ColorType::Rgb => data
+                .chunks_exact(3)
+                .map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => data
+                .chunks_exact(2)
+                .map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => data.iter().map(|&l| [l, l, l, 255]).collect(),
+            other => bail!("unsupported PNG color type {:?}", other),
+        };
+
+        Ok(Self {
+            path: path.to_string(),
+            width: info.width,
+            height: info.height,
+            rgba,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// Logo pronto per il blend. Clonabile a basso costo (pixel condivisi).
+#[derive(Clone)]
+pub struct Watermark {
+    width: u32,
+    height: u32,
+    /// Colore e alpha (0..=255) per pixel, riga per riga
+    pixels: Arc<[(YuvColor, u8)]>,
+    pub corner: WatermarkCorner,
+    /// Opacità globale 0.0..=1.0, moltiplicata per l'alpha del PNG
+    pub opacity: f32,
+}
+
+impl fmt::Debug for Watermark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watermark")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("corner", &self.corner)
+            .field("opacity", &self.opacity)
+            .finish()
+    }
+}
+
+impl Watermark {
+    /// Logo da un PNG già decodificato, scalato di `scale` (1.0 = originale)
+    pub fn from_image(
+        image: &WatermarkImage,
+        scale: f32,
+        corner: WatermarkCorner,
+        opacity: f32,
+    ) -> Self {
+        let (src_w, src_h) = (image.width, image.height);
+        let (width, height) = scaled_size((src_w, src_h), scale);
+
+        // Nearest neighbour: il logo viene scalato una volta sola
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let sx = (x * src_w / width).min(src_w - 1);
+                let sy = (y * src_h / height).min(src_h - 1);
+                let [r, g, b, a] = image.rgba[(sy * src_w + sx) as usize];
+                (YuvColor::from_rgb([r, g, b]), a)
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+            corner,
+            opacity: opacity.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+/// Dimensioni del logo scalato: un solo fattore per entrambi i lati, ridotto
+/// se il lato maggiore supererebbe [`MAX_SIDE`], così le proporzioni restano.
+fn scaled_size((src_w, src_h): (u32, u32), scale: f32) -> (u32, u32) {
+    let longest = src_w.max(src_h).max(1) as f32;
+    let scale = scale.max(0.01).min(MAX_SIDE as f32 / longest);
+    (
+        ((src_w as f32 * scale).round() as u32).clamp(1, MAX_SIDE),
+        ((src_h as f32 * scale).round() as u32).clamp(1, MAX_SIDE),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_logo_keeps_its_aspect_ratio() {
+        assert_eq!(scaled_size((400, 100), 0.5), (200, 50));
+        assert_eq!(scaled_size((4000, 1000), 1.0), (1024, 256));
+        assert_eq!(scaled_size((300, 600), 4.0), (512, 1024));
+        // Un lato sottilissimo resta almeno di un pixel
+        assert_eq!(scaled_size((5000, 2), 1.0), (1024, 1));
+    }
+}
+
+/// Compone il logo nell'angolo scelto, con un margine proporzionale al frame.
+pub fn draw_watermark(frame: &mut YUVFrame, watermark: &Watermark) {
+    if watermark.opacity <= 0.0 {
+        return;
+    }
+
+    let mut canvas = Nv12Canvas::new(frame);
+    let margin = (canvas.width().min(canvas.height()) / 40).max(4);
+    let (w, h) = (watermark.width as i32, watermark.height as i32);
+
+    let x0 = match watermark.corner {
+        WatermarkCorner::TopLeft | WatermarkCorner::BottomLeft => margin,
+        WatermarkCorner::TopRight | WatermarkCorner::BottomRight => canvas.width() - margin - w,
+    };
+    let y0 = match watermark.corner {
+        WatermarkCorner::TopLeft | WatermarkCorner::TopRight => margin,
+        WatermarkCorner::BottomLeft | WatermarkCorner::BottomRight => canvas.height() - margin - h,
+    };
+
+    for (i, (color, alpha)) in watermark.pixels.iter().enumerate() {
+        if *alpha == 0 {
+            continue;
+        }
+        let (x, y) = (i as i32 % w, i as i32 / w);
+        canvas.blend(
+            x0 + x,
+            y0 + y,
+            *color,
+            *alpha as f32 / 255.0 * watermark.opacity,
+        );
+    }
+}
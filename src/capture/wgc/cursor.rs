@@ -0,0 +1,55 @@
+use crate::capture::overlay::{CursorHighlight, CursorState};
+use std::time::Instant;
+use windows::Win32::Foundation::POINT;
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_LBUTTON, VK_RBUTTON};
+use windows::Win32::UI::WindowsAndMessaging::GetCursorPos;
+
+/// Legge posizione e click del cursore ad ogni frame catturato.
+///
+/// Le coordinate di `GetCursorPos` sono nel desktop virtuale: vengono
+/// riportate nello spazio del frame sottraendo l'origine (monitor + crop).
+pub struct CursorTracker {
+    was_down: bool,
+    last_click: Option<(Instant, i32, i32)>,
+}
+
+impl CursorTracker {
+    pub fn new() -> Self {
+        Self {
+            was_down: false,
+            last_click: None,
+        }
+    }
+
+    pub fn poll(&mut self, origin: (i32, i32)) -> Option<CursorState> {
+        let mut pt = POINT::default();
+        unsafe { GetCursorPos(&mut pt) }.ok()?;
+        let (x, y) = (pt.x - origin.0, pt.y - origin.1);
+
+        // Bit alto = tasto attualmente premuto
+        let down = unsafe {
+            (GetAsyncKeyState(VK_LBUTTON.0 as i32) as u16 & 0x8000) != 0
+                || (GetAsyncKeyState(VK_RBUTTON.0 as i32) as u16 & 0x8000) != 0
+        };
+        if down && !self.was_down {
+            self.last_click = Some((Instant::now(), x, y));
+        }
+        self.was_down = down;
+
+        let ripple = match self.last_click {
+            Some((at, cx, cy)) => {
+                let progress =
+                    at.elapsed().as_secs_f32() / CursorHighlight::RIPPLE_DURATION.as_secs_f32();
+                if progress < 1.0 {
+                    Some((cx, cy, progress))
+                } else {
+                    self.last_click = None;
+                    None
+                }
+            }
+            None => None,
+        };
+
+        Some(CursorState { x, y, ripple })
+    }
+}
@@ -1,31 +1,59 @@
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::fmt;
 use std::mem::size_of;
 
 use windows::Graphics::Capture::GraphicsCaptureItem;
 use windows::Win32::Devices::Display::{
-    DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME, DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME,
-    DISPLAYCONFIG_DEVICE_INFO_HEADER, DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE,
-    DISPLAYCONFIG_SOURCE_DEVICE_NAME, DISPLAYCONFIG_TARGET_DEVICE_NAME, DisplayConfigGetDeviceInfo,
-    GetDisplayConfigBufferSizes, QDC_ONLY_ACTIVE_PATHS, QueryDisplayConfig,
+    DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO,
+    DISPLAYCONFIG_DEVICE_INFO_GET_SDR_WHITE_LEVEL, DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+    DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME, DISPLAYCONFIG_DEVICE_INFO_HEADER,
+    DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO, DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE,
+    DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EMBEDDED, DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL,
+    DISPLAYCONFIG_OUTPUT_TECHNOLOGY_UDI_EMBEDDED, DISPLAYCONFIG_PATH_TARGET_INFO,
+    DISPLAYCONFIG_SDR_WHITE_LEVEL, DISPLAYCONFIG_SOURCE_DEVICE_NAME,
+    DISPLAYCONFIG_TARGET_DEVICE_NAME, DisplayConfigGetDeviceInfo, GetDisplayConfigBufferSizes,
+    QDC_ONLY_ACTIVE_PATHS, QueryDisplayConfig,
 };
-use windows::Win32::Foundation::{LPARAM, RECT};
+use windows::Win32::Foundation::{HWND, LPARAM, RECT};
+use windows::Win32::Graphics::Dwm::{DWMWA_CLOAKED, DwmGetWindowAttribute};
 use windows::Win32::Graphics::Gdi::{
-    EnumDisplayMonitors, GetMonitorInfoA, HDC, HMONITOR, MONITORINFO, MONITORINFOEXA,
+    BI_RGB, BITMAPINFO, BITMAPINFOHEADER, CreateCompatibleBitmap, CreateCompatibleDC, DEVMODEA,
+    DIB_RGB_COLORS, DeleteDC, DeleteObject, ENUM_CURRENT_SETTINGS, EnumDisplayMonitors,
+    EnumDisplaySettingsA, GetDC, GetDIBits, GetMonitorInfoA, HALFTONE, HDC, HMONITOR,
+    MONITOR_DEFAULTTONEAREST, MONITORINFO, MONITORINFOEXA, MONITORINFOF_PRIMARY, MonitorFromWindow,
+    ReleaseDC, SRCCOPY, SelectObject, SetStretchBltMode, StretchBlt,
 };
 use windows::Win32::System::WinRT::Graphics::Capture::IGraphicsCaptureItemInterop;
-use windows::core::BOOL;
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GW_OWNER, GWL_EXSTYLE, GetClientRect, GetWindow, GetWindowLongW,
+    GetWindowPlacement, GetWindowRect, GetWindowTextLengthW, GetWindowTextW,
+    GetWindowThreadProcessId, IsIconic, IsWindowVisible, WINDOWPLACEMENT, WS_EX_TOOLWINDOW,
+};
+use windows::core::{BOOL, PCSTR};
 
-use crate::capture::DisplayInfo;
-use anyhow::Result;
+use crate::capture::display::Thumbnail;
+use crate::capture::display::label::{BUILT_IN_LABEL, display_label};
+use crate::capture::display::span::DisplayBounds;
+use crate::capture::display::thumbnail::thumbnail_size;
+use crate::capture::{DisplayInfo, ToneMap};
+use anyhow::{Result, bail};
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, Debug)]
 pub struct Display {
     pub handle: HMONITOR,
+    /// Finestra da catturare al posto del monitor (nullo per i monitor)
+    pub window: HWND,
     pub name: String,
+    /// Monitor principale di Windows (sempre falso per finestre e "All Displays")
+    pub primary: bool,
+    /// Frequenza del monitor in Hz ("All Displays": la più bassa, finestre: `None`)
+    pub refresh_rate: Option<u32>,
 }
 
-// SAFETY: HMONITOR is a system handle that is safe to send across threads.
+/// Titoli più lunghi vengono troncati nella lista delle sorgenti
+const MAX_WINDOW_TITLE: usize = 48;
+
+// SAFETY: HMONITOR and HWND are system handles that are safe to send across threads.
 unsafe impl Send for Display {}
 
 impl Display {
@@ -38,17 +66,270 @@ impl Display {
     }
 
     pub fn new(handle: HMONITOR) -> Result<Self> {
+        let (name, primary, refresh_rate) = unsafe { get_display_name(handle) };
         Ok(Self {
             handle,
-            name: unsafe { get_display_name(handle) },
+            window: HWND::default(),
+            name,
+            primary,
+            refresh_rate,
         })
     }
 
+    /// Finestre top-level catturabili: visibili, con titolo, non minimizzate
+    /// e non appartenenti a Castify stesso
+    pub fn windows() -> Result<Vec<Self>> {
+        unsafe {
+            let windows = Box::into_raw(Box::<Vec<Display>>::default());
+            let _ = EnumWindows(Some(enum_window), LPARAM(windows as isize));
+            Ok(*Box::from_raw(windows))
+        }
+    }
+
+    pub fn from_window(window: HWND) -> Option<Self> {
+        unsafe {
+            if !IsWindowVisible(window).as_bool() || IsIconic(window).as_bool() {
+                return None;
+            }
+            // Finestre di proprietà (dialog) e tool window non sono sorgenti utili
+            if GetWindow(window, GW_OWNER).is_ok_and(|owner| !owner.is_invalid())
+                || GetWindowLongW(window, GWL_EXSTYLE) as u32 & WS_EX_TOOLWINDOW.0 != 0
+            {
+                return None;
+            }
+            // Le app UWP sospese restano "visibili" ma nascoste da DWM
+            let mut cloaked = 0u32;
+            let _ = DwmGetWindowAttribute(
+                window,
+                DWMWA_CLOAKED,
+                &mut cloaked as *mut u32 as *mut _,
+                size_of::<u32>() as u32,
+            );
+            if cloaked != 0 {
+                return None;
+            }
+
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(window, Some(&mut pid));
+            if pid == std::process::id() {
+                return None;
+            }
+
+            let title = get_window_title(window)?;
+            Some(Self {
+                handle: MonitorFromWindow(window, MONITOR_DEFAULTTONEAREST),
+                window,
+                name: title,
+                primary: false,
+                refresh_rate: None,
+            })
+        }
+    }
+
+    /// Voce sintetica che copre tutti i monitor (handle nullo)
+    pub fn all_displays(monitors: &[Display]) -> Option<Self> {
+        let bounds: Vec<DisplayBounds> = monitors.iter().map(|d| d.bounds()).collect();
+        let union = DisplayBounds::union(&bounds)?;
+        Some(Self {
+            handle: HMONITOR::default(),
+            window: HWND::default(),
+            name: union.span_label(),
+            primary: false,
+            refresh_rate: monitors.iter().filter_map(|d| d.refresh_rate).min(),
+        })
+    }
+
+    pub fn is_span(&self) -> bool {
+        self.handle.is_invalid() && !self.is_window()
+    }
+
+    pub fn is_window(&self) -> bool {
+        !self.window.is_invalid()
+    }
+
+    /// Finestra ridotta a icona o senza area client: WGC smette di mandare
+    /// frame e l'item riporta una dimensione non valida
+    pub fn is_minimized(&self) -> bool {
+        if !self.is_window() {
+            return false;
+        }
+        unsafe {
+            let mut rc = RECT::default();
+            IsIconic(self.window).as_bool()
+                || GetClientRect(self.window, &mut rc).is_err()
+                || rc.right - rc.left < 2
+                || rc.bottom - rc.top < 2
+        }
+    }
+
+    /// Dimensione della finestra una volta ripristinata, valida anche mentre
+    /// è ridotta a icona
+    pub fn restored_size(&self) -> Option<(u32, u32)> {
+        if !self.is_window() {
+            return None;
+        }
+        let mut placement = WINDOWPLACEMENT {
+            length: size_of::<WINDOWPLACEMENT>() as u32,
+            ..Default::default()
+        };
+        unsafe { GetWindowPlacement(self.window, &mut placement) }.ok()?;
+        let rc = placement.rcNormalPosition;
+        let (w, h) = (rc.right - rc.left, rc.bottom - rc.top);
+        (w >= 2 && h >= 2).then_some((w as u32 & !1, h as u32 & !1))
+    }
+
+    /// Area del monitor in coordinate desktop (per la voce sintetica, l'unione)
+    pub fn bounds(&self) -> DisplayBounds {
+        if self.is_span() {
+            let monitors = Self::online().unwrap_or_default();
+            let bounds: Vec<DisplayBounds> = monitors.iter().map(|d| d.bounds()).collect();
+            return DisplayBounds::union(&bounds).unwrap_or(DisplayBounds {
+                x: 0,
+                y: 0,
+                width: 2,
+                height: 2,
+            });
+        }
+        let (w, h, x, y) = self.rect();
+        DisplayBounds {
+            x: x as i32,
+            y: y as i32,
+            width: w as u32,
+            height: h as u32,
+        }
+    }
+
     pub fn select(&self) -> Result<GraphicsCaptureItem> {
+        if self.is_span() {
+            bail!("All Displays has no single capture item, select each monitor");
+        }
         let interop = windows::core::factory::<GraphicsCaptureItem, IGraphicsCaptureItemInterop>()?;
+        if self.is_window() {
+            return Ok(unsafe { interop.CreateForWindow(self.window) }?);
+        }
         Ok(unsafe { interop.CreateForMonitor(self.handle) }?)
     }
 
+    /// Miniatura via GDI: StretchBlt dal DC dello schermo, senza sessione WGC.
+    /// Funziona anche per "All Displays" (coordinate del desktop virtuale).
+    pub fn thumbnail(&self, max_width: u32) -> Result<Thumbnail> {
+        let b = self.bounds();
+        let (tw, th) = thumbnail_size((b.width, b.height), max_width);
+
+        let mut bgra = vec![0u8; (tw * th * 4) as usize];
+        let (copied, lines) = unsafe {
+            let screen = GetDC(None);
+            if screen.is_invalid() {
+                bail!("GetDC failed");
+            }
+            let mem = CreateCompatibleDC(Some(screen));
+            let bitmap = CreateCompatibleBitmap(screen, tw as i32, th as i32);
+            let previous = SelectObject(mem, bitmap.into());
+
+            SetStretchBltMode(mem, HALFTONE);
+            let copied = StretchBlt(
+                mem,
+                0,
+                0,
+                tw as i32,
+                th as i32,
+                Some(screen),
+                b.x,
+                b.y,
+                b.width as i32,
+                b.height as i32,
+                SRCCOPY,
+            );
+            SelectObject(mem, previous);
+
+            // Altezza negativa: righe dall'alto verso il basso
+            let mut info = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: tw as i32,
+                    biHeight: -(th as i32),
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let lines = GetDIBits(
+                mem,
+                bitmap,
+                0,
+                th,
+                Some(bgra.as_mut_ptr() as *mut _),
+                &mut info,
+                DIB_RGB_COLORS,
+            );
+
+            let _ = DeleteObject(bitmap.into());
+            let _ = DeleteDC(mem);
+            ReleaseDC(None, screen);
+            (copied.as_bool(), lines)
+        };
+
+        if !copied || lines <= 0 {
+            bail!("GDI thumbnail grab failed");
+        }
+        for px in bgra.chunks_exact_mut(4) {
+            px.swap(0, 2);
+            px[3] = 255;
+        }
+        Ok(Thumbnail {
+            width: tw,
+            height: th,
+            rgba: bgra,
+        })
+    }
+
+    /// Bianco SDR in nit se il monitor ha l'HDR attivo, `None` per i monitor
+    /// SDR e per "All Displays" (va chiesto a ciascun monitor)
+    pub fn hdr_white_nits(&self) -> Option<f32> {
+        if self.handle.is_invalid() {
+            return None;
+        }
+        unsafe {
+            let target = display_target(&monitor_device_name(self.handle)?)?;
+            let header = |r#type, size| DISPLAYCONFIG_DEVICE_INFO_HEADER {
+                r#type,
+                size,
+                adapterId: target.adapterId,
+                id: target.id,
+            };
+
+            let color = DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO {
+                header: header(
+                    DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO,
+                    size_of::<DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO>() as u32,
+                ),
+                ..Default::default()
+            };
+            if DisplayConfigGetDeviceInfo(&color.header as *const _ as *mut _) != 0 {
+                return None;
+            }
+            // Bit 1: advancedColorEnabled (HDR acceso nelle impostazioni)
+            if color.Anonymous.value & 0b10 == 0 {
+                return None;
+            }
+
+            let white = DISPLAYCONFIG_SDR_WHITE_LEVEL {
+                header: header(
+                    DISPLAYCONFIG_DEVICE_INFO_GET_SDR_WHITE_LEVEL,
+                    size_of::<DISPLAYCONFIG_SDR_WHITE_LEVEL>() as u32,
+                ),
+                ..Default::default()
+            };
+            if DisplayConfigGetDeviceInfo(&white.header as *const _ as *mut _) != 0 {
+                return Some(ToneMap::DEFAULT_WHITE_NITS);
+            }
+            // 1000 = 80 nit
+            Some(white.SDRWhiteLevel as f32 * 80.0 / 1000.0)
+        }
+    }
+
     /// Returns the DPI scale factor for this monitor (e.g. 1.0, 1.25, 1.5, 2.0).
     pub fn dpi_scale(&self) -> f64 {
         use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
@@ -60,9 +341,24 @@ impl Display {
         if dpi_x > 0 { dpi_x as f64 / 96.0 } else { 1.0 }
     }
 
-    /// Returns (width, height, x, y) of the monitor in physical pixels.
+    /// Returns (width, height, x, y) of the monitor (or window) in physical pixels.
     pub fn rect(&self) -> (f32, f32, f32, f32) {
+        if self.is_span() {
+            let b = self.bounds();
+            return (b.width as f32, b.height as f32, b.x as f32, b.y as f32);
+        }
         unsafe {
+            if self.is_window() {
+                let mut rc = RECT::default();
+                let _ = GetWindowRect(self.window, &mut rc);
+                return (
+                    (rc.right - rc.left) as f32,
+                    (rc.bottom - rc.top) as f32,
+                    rc.left as f32,
+                    rc.top as f32,
+                );
+            }
+
             let mut info = MONITORINFO {
                 cbSize: size_of::<MONITORINFO>() as u32,
                 ..Default::default()
@@ -79,9 +375,73 @@ impl Display {
     }
 }
 
-unsafe fn get_display_name(handle: HMONITOR) -> String {
+/// "\\.\DISPLAYn" del monitor, la chiave per QueryDisplayConfig
+unsafe fn monitor_device_name(handle: HMONITOR) -> Option<String> {
+    unsafe {
+        let info = MONITORINFOEXA {
+            monitorInfo: MONITORINFO {
+                cbSize: size_of::<MONITORINFOEXA>() as u32,
+                ..Default::default()
+            },
+            szDevice: [0; 32],
+        };
+        if !GetMonitorInfoA(handle, &info as *const _ as *mut _).as_bool() {
+            return None;
+        }
+        CStr::from_ptr(info.szDevice.as_ptr() as _)
+            .to_str()
+            .ok()
+            .map(str::to_string)
+    }
+}
+
+/// Uscita (adattatore + id) che pilota il monitor `device_name`
+unsafe fn display_target(device_name: &str) -> Option<DISPLAYCONFIG_PATH_TARGET_INFO> {
+    unsafe {
+        let mut num_path_array_elements = 0;
+        let mut num_mode_info_array_elements = 0;
+        let _ = GetDisplayConfigBufferSizes(
+            QDC_ONLY_ACTIVE_PATHS,
+            &mut num_path_array_elements,
+            &mut num_mode_info_array_elements,
+        );
+
+        let mut path_info_array = vec![Default::default(); num_path_array_elements as usize];
+        let mut mode_info_array = vec![Default::default(); num_mode_info_array_elements as usize];
+        let _ = QueryDisplayConfig(
+            QDC_ONLY_ACTIVE_PATHS,
+            &mut num_path_array_elements,
+            path_info_array.as_mut_ptr(),
+            &mut num_mode_info_array_elements,
+            mode_info_array.as_mut_ptr(),
+            None,
+        );
+        path_info_array.truncate(num_path_array_elements as usize);
+
+        path_info_array.iter().find_map(|path| {
+            let source_device_name = DISPLAYCONFIG_SOURCE_DEVICE_NAME {
+                header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
+                    adapterId: path.sourceInfo.adapterId,
+                    id: path.sourceInfo.id,
+                    size: size_of::<DISPLAYCONFIG_SOURCE_DEVICE_NAME>() as u32,
+                    r#type: DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+                },
+                ..Default::default()
+            };
+            let _ = DisplayConfigGetDeviceInfo(&source_device_name.header as *const _ as *mut _);
+            let gdi_device_name =
+                widestring::U16CString::from_ptr_str(source_device_name.viewGdiDeviceName.as_ptr())
+                    .to_string()
+                    .ok()?;
+            (gdi_device_name == device_name).then_some(path.targetInfo)
+        })
+    }
+}
+
+/// Nome leggibile del monitor e se è il principale
+unsafe fn get_display_name(handle: HMONITOR) -> (String, bool, Option<u32>) {
     unsafe {
-        let (device_name, width, height) = {
+        let (device_name, width, height, primary) = {
             let info = MONITORINFOEXA {
                 monitorInfo: MONITORINFO {
                     cbSize: size_of::<MONITORINFOEXA>() as u32,
@@ -97,11 +457,61 @@ unsafe fn get_display_name(handle: HMONITOR) -> String {
                     .to_string(),
                 info.monitorInfo.rcMonitor.right - info.monitorInfo.rcMonitor.left,
                 info.monitorInfo.rcMonitor.bottom - info.monitorInfo.rcMonitor.top,
+                info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY != 0,
             )
         };
-        let name = try_get_user_friendly_name(device_name.clone()).unwrap_or(device_name);
+        // "\\.\DISPLAY2" → 2, lo stesso numero delle impostazioni di Windows
+        let number = device_name
+            .trim_start_matches(r"\\.\DISPLAY")
+            .parse()
+            .unwrap_or(1);
+        let refresh_rate = get_refresh_rate(&device_name);
+        let name = try_get_user_friendly_name(device_name);
 
-        format!("{} ({} x {})", name, width, height)
+        (
+            display_label(name.as_deref(), number, (width as u32, height as u32)),
+            primary,
+            refresh_rate,
+        )
+    }
+}
+
+/// Frequenza corrente del monitor; 0 e 1 indicano il default dell'hardware
+unsafe fn get_refresh_rate(device_name: &str) -> Option<u32> {
+    unsafe {
+        let device = CString::new(device_name).ok()?;
+        let mut mode = DEVMODEA {
+            dmSize: size_of::<DEVMODEA>() as u16,
+            ..Default::default()
+        };
+        EnumDisplaySettingsA(
+            PCSTR(device.as_ptr() as _),
+            ENUM_CURRENT_SETTINGS,
+            &mut mode,
+        )
+        .as_bool()
+        .then_some(mode.dmDisplayFrequency)
+        .filter(|hz| *hz > 1)
+    }
+}
+
+unsafe fn get_window_title(window: HWND) -> Option<String> {
+    unsafe {
+        let len = GetWindowTextLengthW(window);
+        if len <= 0 {
+            return None;
+        }
+        let mut buf = vec![0u16; len as usize + 1];
+        let copied = GetWindowTextW(window, &mut buf);
+        let title = String::from_utf16_lossy(&buf[..copied.max(0) as usize]);
+        let title = title.trim();
+        if title.is_empty() {
+            return None;
+        }
+        Some(match title.char_indices().nth(MAX_WINDOW_TITLE) {
+            Some((cut, _)) => format!("{}…", &title[..cut]),
+            None => title.to_string(),
+        })
     }
 }
 
@@ -176,11 +586,18 @@ unsafe fn try_get_user_friendly_name(device_name: String) -> Option<String> {
                     .to_string()
                     .ok()?;
 
-                    if user_friendly_name.is_empty() {
-                        None
-                    } else {
-                        Some(user_friendly_name)
+                    if !user_friendly_name.is_empty() {
+                        return Some(user_friendly_name);
                     }
+                    // I pannelli dei portatili spesso non hanno un nome EDID
+                    let embedded = [
+                        DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL,
+                        DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EMBEDDED,
+                        DISPLAYCONFIG_OUTPUT_TECHNOLOGY_UDI_EMBEDDED,
+                    ];
+                    embedded
+                        .contains(&target_device_name.outputTechnology)
+                        .then(|| BUILT_IN_LABEL.to_string())
                 } else {
                     None
                 }
@@ -188,6 +605,20 @@ unsafe fn try_get_user_friendly_name(device_name: String) -> Option<String> {
     }
 }
 
+/// Una finestra è identificata dal suo HWND: resta la stessa sorgente anche
+/// se cambia titolo o viene spostata su un altro monitor.
+impl PartialEq for Display {
+    fn eq(&self, other: &Self) -> bool {
+        if self.window.is_invalid() && other.window.is_invalid() {
+            self.handle == other.handle
+        } else {
+            self.window == other.window
+        }
+    }
+}
+
+impl Eq for Display {}
+
 impl fmt::Display for Display {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.name)
@@ -204,11 +635,32 @@ extern "system" fn enum_monitor(monitor: HMONITOR, _: HDC, _: *mut RECT, state:
     true.into()
 }
 
+// callback function for EnumWindows
+extern "system" fn enum_window(window: HWND, state: LPARAM) -> BOOL {
+    unsafe {
+        let state = Box::leak(Box::from_raw(state.0 as *mut Vec<Display>));
+        if let Some(display) = Display::from_window(window) {
+            state.push(display);
+        }
+    }
+    true.into()
+}
+
+impl DisplayInfo for DisplayBounds {
+    fn resolution(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+    fn dpi_conversion_factor(&self) -> f64 {
+        1.0
+    }
+}
+
 impl DisplayInfo for GraphicsCaptureItem {
     fn resolution(&self) -> (u32, u32) {
+        // Le finestre possono avere dimensioni dispari, NV12 no
         (
-            self.Size().unwrap().Width as u32,
-            self.Size().unwrap().Height as u32,
+            self.Size().unwrap().Width as u32 & !1,
+            self.Size().unwrap().Height as u32 & !1,
         )
     }
     fn dpi_conversion_factor(&self) -> f64 {
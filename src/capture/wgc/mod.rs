@@ -1,5 +1,8 @@
+mod cursor;
 mod d3d;
 mod display;
 mod wgc_capture;
+pub(crate) mod window_exclude;
+pub(crate) mod window_follow;
 
 pub use wgc_capture::WGCScreenCapture;
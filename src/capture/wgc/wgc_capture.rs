@@ -1,11 +1,24 @@
-use crate::assets::FRAME_RATE;
-use crate::capture::display::DisplaySelector;
+use crate::capture::budget::BudgetController;
+use crate::capture::capturer::extract_crop_nv12_reuse;
+use crate::capture::display::span::{DisplayBounds, black_canvas, blit_nv12, copy_canvas_into};
+use crate::capture::display::{DisplaySelector, Thumbnail};
+use crate::capture::keycast::draw_keycast;
+use crate::capture::motion::{ContentAwareRate, DuplicateFilter, RateDecision};
+use crate::capture::overlay::{draw_cursor_highlight, placeholder_frame};
+use crate::capture::privacy::{draw_privacy_masks, window_mask};
+use crate::capture::timestamp::draw_timestamp;
+use crate::capture::watermark::draw_watermark;
+use crate::capture::wgc::cursor::CursorTracker;
 use crate::capture::wgc::d3d;
 use crate::capture::wgc::display::Display;
+use crate::capture::wgc::window_exclude::excluded_window_rects;
+use crate::capture::zoom::{ZoomAnimator, scale_nv12_into};
 use crate::capture::{
-    CaptureOpts, CropRect, DisplayInfo, ScreenCapture, ScreenCaptureImpl, YUVFrame, YuvConverter,
+    CaptureError, CaptureOpts, CropRect, DisplayInfo, HdrMode, ScreenCapture, ScreenCaptureImpl,
+    SourceEvent, ToneMap, YUVFrame, YuvConverter,
 };
-use crate::encoder::{FfmpegEncoder, FrameData};
+use crate::encoder::{EncodedVideo, FfmpegEncoder, FrameData};
+use crate::pipeline::health::{DropSource, PipelineHealth};
 use crate::utils::perf::PipelineStats;
 use async_trait::async_trait;
 use std::sync::Arc;
@@ -16,109 +29,348 @@ use windows::Foundation::TypedEventHandler;
 use windows::Graphics::Capture::{
     Direct3D11CaptureFrame, Direct3D11CaptureFramePool, GraphicsCaptureItem, GraphicsCaptureSession,
 };
+use windows::Graphics::DirectX::Direct3D11::IDirect3DDevice;
 use windows::Graphics::DirectX::DirectXPixelFormat;
+use windows::Graphics::SizeInt32;
+use windows::Win32::Foundation::E_ACCESSDENIED;
+use windows::Win32::Graphics::Direct3D11::{ID3D11Device, ID3D11DeviceContext};
 use windows::core::IInspectable;
 
 pub struct WGCScreenCapture {
-    engine: Option<CaptureEngine>,
+    engines: Vec<CaptureEngine>,
     selected_display: Display,
-    session: Option<GraphicsCaptureSession>,
+    sessions: Vec<GraphicsCaptureSession>,
     item: GraphicsCaptureItem,
+    /// Area composta con "All Displays", altrimenti `None`
+    span: Option<DisplayBounds>,
+    /// Ridimensionamenti e sorgenti sparite, letti dal `Capturer`
+    source_events: watch::Sender<SourceEvent>,
+    /// Handler `Closed` degli item catturati, rimossi allo stop
+    closed_tokens: Vec<(GraphicsCaptureItem, i64)>,
+    /// Dimensione da ripristinata della finestra catturata: l'item di una
+    /// finestra ridotta a icona non ha una dimensione utilizzabile
+    restored_size: Option<DisplayBounds>,
+    /// Frame scartati col canale d'uscita pieno, contati come perdite di cattura
+    health: Option<Arc<PipelineHealth>>,
 }
 
+#[derive(Clone)]
 struct CaptureEngine {
     frame_pool: Direct3D11CaptureFramePool,
     duplicator: YuvConverter,
+    d3d_device: IDirect3DDevice,
+    device: Arc<ID3D11Device>,
+    d3d_context: Arc<ID3D11DeviceContext>,
+    frame_arrived: Option<i64>,
+    /// Desktop HDR catturato in FP16 e riportato in SDR dal convertitore
+    tone_map: Option<ToneMap>,
+}
+
+// SAFETY: come per `YuvConverter`, i device D3D11 sono usati da un solo task alla volta.
+unsafe impl Send for CaptureEngine {}
+
+/// Dimensione del frame pool: NV12 richiede lati pari
+fn even_size(size: SizeInt32) -> SizeInt32 {
+    SizeInt32 {
+        Width: size.Width & !1,
+        Height: size.Height & !1,
+    }
+}
+
+/// Item con un'area catturabile (NV12 richiede almeno 2x2)
+fn usable_size(size: SizeInt32) -> bool {
+    size.Width >= 2 && size.Height >= 2
+}
+
+/// Formato del frame pool: FP16 (scRGB) solo quando serve il tone-mapping
+fn pixel_format(tone_map: Option<ToneMap>) -> DirectXPixelFormat {
+    match tone_map {
+        Some(_) => DirectXPixelFormat::R16G16B16A16Float,
+        None => DirectXPixelFormat::B8G8R8A8UIntNormalized,
+    }
+}
+
+/// Tone-mapping per un monitor secondo la modalità scelta
+fn tone_map_for(display: &Display, mode: HdrMode) -> Option<ToneMap> {
+    let white_nits = display.hdr_white_nits();
+    if !mode.tone_map(white_nits.is_some()) {
+        return None;
+    }
+    let tone_map = ToneMap::new(white_nits.unwrap_or(ToneMap::DEFAULT_WHITE_NITS));
+    log::info!(
+        "HDR capture on {}: tone-mapping with SDR white at {:.0} nits",
+        display,
+        tone_map.white_nits
+    );
+    Some(tone_map)
+}
+
+/// Cursore dentro i frame catturati. Su Windows precedenti al 10 2004 la
+/// proprietà non esiste: il cursore resta visibile.
+fn set_cursor_capture(session: &GraphicsCaptureSession, show: bool) {
+    if let Err(e) = session.SetIsCursorCaptureEnabled(show) {
+        log::warn!("Cursor capture toggle not supported: {}", e);
+    }
 }
 
 impl CaptureEngine {
-    fn new(item: &GraphicsCaptureItem) -> Self {
-        let item_size = item.Size().unwrap();
+    fn new(size: SizeInt32, tone_map: Option<ToneMap>) -> Self {
+        let item_size = even_size(size);
         let (device, d3d_device, d3d_context) = d3d::create_direct3d_devices_and_context().unwrap();
         let device = Arc::new(device);
         let d3d_context = Arc::new(d3d_context);
+        let resolution = (item_size.Width as u32, item_size.Height as u32);
+        // Senza shader HDR (compilatore assente) si torna alla cattura SDR
+        let (duplicator, tone_map) = match tone_map {
+            Some(tone_map) => match YuvConverter::with_tone_map(
+                device.clone(),
+                d3d_context.clone(),
+                resolution,
+                Some(tone_map),
+            ) {
+                Ok(duplicator) => (duplicator, Some(tone_map)),
+                Err(e) => {
+                    log::warn!("HDR tone-mapping unavailable, capturing as SDR: {:#}", e);
+                    let duplicator =
+                        YuvConverter::new(device.clone(), d3d_context.clone(), resolution).unwrap();
+                    (duplicator, None)
+                }
+            },
+            None => (
+                YuvConverter::new(device.clone(), d3d_context.clone(), resolution).unwrap(),
+                None,
+            ),
+        };
         let frame_pool = Direct3D11CaptureFramePool::CreateFreeThreaded(
             &d3d_device,
-            DirectXPixelFormat::B8G8R8A8UIntNormalized,
+            pixel_format(tone_map),
             3,
             item_size,
         )
         .unwrap();
-        let duplicator = YuvConverter::new(
-            device,
-            d3d_context,
-            (item_size.Width as u32, item_size.Height as u32),
-        )
-        .unwrap();
         Self {
             frame_pool,
             duplicator,
+            d3d_device,
+            device,
+            d3d_context,
+            frame_arrived: None,
+            tone_map,
+        }
+    }
+
+    /// Finestra ridimensionata: il pool riparte alla nuova dimensione e il
+    /// convertitore viene ricreato, i frame in volo con la vecchia vengono scartati
+    fn resize(&mut self, size: SizeInt32) -> Result<(u32, u32), anyhow::Error> {
+        let size = even_size(size);
+        self.frame_pool
+            .Recreate(&self.d3d_device, pixel_format(self.tone_map), 3, size)?;
+        let resolution = (size.Width as u32, size.Height as u32);
+        self.duplicator = YuvConverter::with_tone_map(
+            self.device.clone(),
+            self.d3d_context.clone(),
+            resolution,
+            self.tone_map,
+        )?;
+        Ok(resolution)
+    }
+
+    fn close(&mut self) -> Result<(), anyhow::Error> {
+        if let Some(token) = self.frame_arrived.take() {
+            self.frame_pool.RemoveFrameArrived(token)?;
         }
+        self.frame_pool.Close()?;
+        Ok(())
     }
 }
 
 #[async_trait]
 impl ScreenCapture for WGCScreenCapture {
-    fn new_default() -> Result<ScreenCaptureImpl, anyhow::Error> {
-        let selected_display = Display::online().unwrap()[0].clone();
-        let item = selected_display.select()?;
+    fn new_default() -> Result<ScreenCaptureImpl, CaptureError> {
+        if !GraphicsCaptureSession::IsSupported().unwrap_or(false) {
+            return Err(CaptureError::Unsupported);
+        }
+        // Il device D3D viene creato ad ogni avvio: meglio scoprire subito se manca
+        d3d::create_direct3d_devices_and_context()
+            .map_err(|e| CaptureError::GraphicsInit(e.to_string()))?;
+
+        let selected_display = Display::online()
+            .map_err(CaptureError::from_anyhow)?
+            .into_iter()
+            .next()
+            .ok_or(CaptureError::NoDisplays)?;
+        let item = selected_display.select().map_err(|e| {
+            match e.downcast_ref::<windows::core::Error>() {
+                Some(error) if error.code() == E_ACCESSDENIED => CaptureError::PermissionDenied,
+                _ => CaptureError::from_anyhow(e),
+            }
+        })?;
         Ok(Self {
-            engine: None,
+            engines: Vec::new(),
             selected_display,
-            session: None,
+            sessions: Vec::new(),
             item,
+            span: None,
+            source_events: watch::Sender::new(SourceEvent::None),
+            closed_tokens: Vec::new(),
+            restored_size: None,
+            health: None,
         })
     }
 
+    fn can_hide_cursor(&self) -> bool {
+        true
+    }
+
     fn display(&self) -> &dyn DisplayInfo {
-        &self.item
+        match (&self.span, &self.restored_size) {
+            (Some(bounds), _) => bounds,
+            // Finestra ridotta a icona: pool ed encoder nascono alla sua dimensione normale
+            (None, Some(restored)) if !self.item.Size().is_ok_and(usable_size) => restored,
+            (None, _) => &self.item,
+        }
     }
 
     async fn start_capture(
         &mut self,
         mut encoder: FfmpegEncoder,
-        output: tokio::sync::mpsc::Sender<bytes::Bytes>,
+        output: tokio::sync::mpsc::Sender<EncodedVideo>,
         opts_rx: watch::Receiver<CaptureOpts>,
     ) -> Result<(), anyhow::Error> {
-        let engine = CaptureEngine::new(&self.item);
-        let item_size = self.item.Size()?;
-
-        let session = engine.frame_pool.CreateCaptureSession(&self.item)?;
+        // "All Displays": un item per monitor, composti nel canvas dell'unione.
+        // WGC non offre una cattura dell'intero desktop in un solo item.
+        // HDR deciso per monitor: con "All Displays" possono convivere HDR e SDR
+        let hdr_mode = opts_rx.borrow().hdr;
+        self.restored_size = self
+            .selected_display
+            .restored_size()
+            .map(|(width, height)| DisplayBounds {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            });
+        let (width, height) = self.display().resolution();
+        let source_size = SizeInt32 {
+            Width: width as i32,
+            Height: height as i32,
+        };
+        let sources: Vec<(GraphicsCaptureItem, (u32, u32), Option<ToneMap>)> = match &self.span {
+            Some(span) => Display::online()?
+                .iter()
+                .map(|monitor| {
+                    Ok((
+                        monitor.select()?,
+                        monitor.bounds().offset_in(span),
+                        tone_map_for(monitor, hdr_mode),
+                    ))
+                })
+                .collect::<Result<_, anyhow::Error>>()?,
+            None => vec![(
+                self.item.clone(),
+                (0, 0),
+                tone_map_for(&self.selected_display, hdr_mode),
+            )],
+        };
 
         // Increased capacity to prevent frame drops when pipeline is under load
-        let (sender, mut receiver) = tokio::sync::mpsc::channel::<Direct3D11CaptureFrame>(8);
-
-        engine.frame_pool.FrameArrived(&TypedEventHandler::<
-            Direct3D11CaptureFramePool,
-            IInspectable,
-        >::new({
-            move |frame_pool, _| {
-                let frame_pool = frame_pool.as_ref().unwrap();
-                let frame = frame_pool.TryGetNextFrame()?;
-                let _ = sender.try_send(frame);
-                Ok(())
-            }
-        }))?;
-
-        session.StartCapture()?;
-        self.session.replace(session);
+        let (sender, mut receiver) =
+            tokio::sync::mpsc::channel::<(usize, Direct3D11CaptureFrame)>(8 * sources.len());
+
+        let mut duplicators = Vec::with_capacity(sources.len());
+        let mut offsets = Vec::with_capacity(sources.len());
+        for (index, (item, offset, tone_map)) in sources.iter().enumerate() {
+            let size = match &self.span {
+                Some(_) => item.Size()?,
+                None => source_size,
+            };
+            let mut engine = CaptureEngine::new(size, *tone_map);
+            let session = engine.frame_pool.CreateCaptureSession(item)?;
+
+            let token = engine.frame_pool.FrameArrived(&TypedEventHandler::<
+                Direct3D11CaptureFramePool,
+                IInspectable,
+            >::new({
+                let sender = sender.clone();
+                move |frame_pool, _| {
+                    let frame_pool = frame_pool.as_ref().unwrap();
+                    let frame = frame_pool.TryGetNextFrame()?;
+                    let _ = sender.try_send((index, frame));
+                    Ok(())
+                }
+            }))?;
+            engine.frame_arrived = Some(token);
+
+            // Monitor scollegato: il Capturer ripiega sul principale. Finestra
+            // chiusa: lo stream va oscurato, mai sostituito con uno schermo
+            let lost = if self.selected_display.is_window() {
+                SourceEvent::WindowClosed
+            } else {
+                SourceEvent::Lost
+            };
+            let closed = item.Closed(
+                &TypedEventHandler::<GraphicsCaptureItem, IInspectable>::new({
+                    let source_events = self.source_events.clone();
+                    move |_, _| {
+                        source_events.send_replace(lost);
+                        Ok(())
+                    }
+                }),
+            )?;
+            self.closed_tokens.push((item.clone(), closed));
+
+            set_cursor_capture(&session, opts_rx.borrow().show_cursor);
+            session.StartCapture()?;
+            self.sessions.push(session);
+            duplicators.push(engine.duplicator.clone());
+            offsets.push(*offset);
+            self.engines.push(engine);
+        }
+        drop(sender);
 
-        let mut duplicator = engine.duplicator.clone();
+        // Una finestra cambia dimensione, o un monitor risoluzione, durante la
+        // cattura: il loop tiene una copia dell'engine per ricreare pool e
+        // convertitore. Con "All Displays" il canvas resta quello di partenza.
+        let mut resizable_engine = self.span.is_none().then(|| self.engines[0].clone());
+        let source_events = self.source_events.clone();
 
-        // Track current crop dynamically — read from opts_rx each frame
+        // Track current crop/profile dynamically — read from opts_rx each frame
         let mut current_crop: Option<CropRect> = opts_rx.borrow().crop;
-
-        // Share the force_idr flag from the encoder
+        let mut current_profile = opts_rx.borrow().profile;
+        let mut current_scale_to = opts_rx.borrow().scale_to;
+        let mut display_size = self.display().resolution();
+        let (_, _, display_x, display_y) = self.selected_display.rect();
+        let display_origin = (display_x as i32, display_y as i32);
+        let spanning = self.span.is_some();
+
+        // Finestra ridotta a icona: WGC non manda frame, al loro posto esce un
+        // segnaposto al secondo finché la finestra non torna visibile
+        let window = self
+            .selected_display
+            .is_window()
+            .then(|| self.selected_display.clone());
+
+        // Il cursore si accende e spegne sulle sessioni già avviate
+        let sessions = self.sessions.clone();
+        let mut current_show_cursor = opts_rx.borrow().show_cursor;
+
+        // Share the force_idr flag and the simulcast link from the encoder
         let force_idr = encoder.force_idr.clone();
+        let simulcast = encoder.simulcast.clone();
+        let health = self.health.clone();
 
         // Pipeline stats for periodic logging
         let stats = Arc::new(PipelineStats::new(encoder.codec_name.clone()));
         let stats_clone = Arc::clone(&stats);
 
-        // Periodic stats logger
+        // Periodic stats logger: cambi di display e fallback riavviano la
+        // cattura nello stesso stream, il logger finisce con il suo loop
         tokio::spawn(async move {
             loop {
                 tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                if Arc::strong_count(&stats_clone) == 1 {
+                    break;
+                }
                 stats_clone.log_summary();
             }
         });
@@ -129,20 +381,48 @@ impl ScreenCapture for WGCScreenCapture {
             // Cache the black frame to avoid per-frame allocation
             let mut cached_black_frame: Option<YUVFrame> = None;
 
+            let mut placeholder_tick = tokio::time::interval(std::time::Duration::from_secs(1));
+            let mut cached_placeholder: Option<YUVFrame> = None;
+            let mut minimized = false;
+            // Orario dell'ultimo frame catturato, base dei pts dei segnaposto
+            let mut last_frame = (0i64, std::time::Instant::now());
+
             // Pre-allocated crop buffers — reused across frames to avoid per-frame allocation
             let mut crop_y_buf: Vec<u8> = Vec::new();
             let mut crop_uv_buf: Vec<u8> = Vec::new();
 
-            let mut max_fps = opts_rx.borrow().max_fps.clamp(15, FRAME_RATE.max(15));
+            let mut cursor_tracker = CursorTracker::new();
+
+            // Canvas dell'intero desktop: i vuoti tra monitor restano neri.
+            // Gli overlay vanno su una copia riusata, il canvas resta pulito
+            let mut span_canvas = spanning.then(|| black_canvas(display_size.0, display_size.1));
+            let mut span_frame = YUVFrame::default();
+
+            // Zoom: regione animata + buffer dedicati per estrazione e upscaling
+            let mut zoom_anim = ZoomAnimator::new();
+            let mut zoom_y_buf: Vec<u8> = Vec::new();
+            let mut zoom_uv_buf: Vec<u8> = Vec::new();
+            let mut zoom_frame = YUVFrame::default();
+
+            let mut max_fps = opts_rx.borrow().fps_limit();
             let mut current_fps: u32 = max_fps;
             let mut pressure_score: u32 = 0;
 
+            // Schermo fermo → un frame al secondo, il movimento riporta il frame rate pieno
+            let mut content_rate = ContentAwareRate::new();
+            // Frame identici al precedente: non codificati, il receiver tiene l'ultimo
+            let mut duplicates = DuplicateFilter::new();
+
+            // Data budget: rate mobile dei byte inviati → livello di qualità
+            let mut budget_ctl = BudgetController::new();
+            let mut rebuild_encoder = false;
+
             let mut frame_count = 0u64;
             let mut last_frame_log = std::time::Instant::now();
 
             loop {
                 select! {
-                    Some(frame) = receiver.recv() => {
+                    Some((source, frame)) = receiver.recv() => {
                         frame_count += 1;
 
                         // Log heartbeat every 5 seconds
@@ -153,56 +433,135 @@ impl ScreenCapture for WGCScreenCapture {
 
                         let frame_start = std::time::Instant::now();
                         let frame_time = frame.SystemRelativeTime().unwrap().Duration;
+                        last_frame = (frame_time, std::time::Instant::now());
+
+                        if let Some(window) = &window {
+                            if window.is_minimized() {
+                                continue;
+                            }
+                            if std::mem::take(&mut minimized) {
+                                log::info!("Captured window restored, capture resumed");
+                                force_idr.store(true, Ordering::Relaxed);
+                            }
+                        }
+
+                        if let Some(engine) = resizable_engine.as_mut()
+                            && let Ok(size) = frame.ContentSize()
+                            && size.Width > 1
+                            && size.Height > 1
+                            && (size.Width as u32 & !1, size.Height as u32 & !1) != display_size
+                        {
+                            match engine.resize(size) {
+                                Ok(resolution) => {
+                                    log::info!(
+                                        "Source resized {}x{} → {}x{}",
+                                        display_size.0, display_size.1, resolution.0, resolution.1
+                                    );
+                                    duplicators[source] = engine.duplicator.clone();
+                                    display_size = resolution;
+                                    rebuild_encoder = true;
+                                    source_events.send_replace(SourceEvent::Resized(
+                                        resolution.0,
+                                        resolution.1,
+                                    ));
+                                }
+                                Err(e) => log::error!("Failed to resize capture: {}", e),
+                            }
+                            continue;
+                        }
 
                         // Read opts dynamically each frame (blank_screen + crop + paused)
                         let opts = opts_rx.borrow().clone();
-                        max_fps = opts.max_fps.clamp(15, FRAME_RATE.max(15));
+                        max_fps = opts.fps_limit().min(budget_ctl.level().fps_cap());
                         if current_fps > max_fps {
                             current_fps = max_fps;
                         }
 
+                        if opts.show_cursor != current_show_cursor {
+                            for session in &sessions {
+                                set_cursor_capture(session, opts.show_cursor);
+                            }
+                            current_show_cursor = opts.show_cursor;
+                        }
+
                         // If paused, skip encoding and continue to next frame
                         if opts.paused {
                             continue;
                         }
 
-                        // Detect crop change and recreate encoder if needed
-                        if opts.crop != current_crop {
-                            let (enc_w, enc_h) = if let Some(ref c) = opts.crop {
-                                (c.w + (c.w % 2), c.h + (c.h % 2))
-                            } else {
-                                (item_size.Width as u32, item_size.Height as u32)
+                        // A crop that only moves keeps the same encoder: recreate it
+                        // only when the source dimensions or the profile change
+                        if opts.crop != current_crop
+                            && opts.profile == current_profile
+                            && opts.scale_to == current_scale_to
+                            && !rebuild_encoder
+                            && opts.crop.map(|c| c.even_size()) == current_crop.map(|c| c.even_size())
+                        {
+                            current_crop = opts.crop;
+                        } else if opts.crop != current_crop
+                            || opts.profile != current_profile
+                            || opts.scale_to != current_scale_to
+                            || rebuild_encoder
+                        {
+                            let level = budget_ctl.level();
+                            let (src_w, src_h) = opts.source_size(display_size);
+                            let (enc_w, enc_h) =
+                                level.scale_size(opts.output_size(src_w, src_h));
+                            let rate = opts.rate_control(level);
+                            let rebuilt = FfmpegEncoder::with_rate_control(
+                                src_w,
+                                src_h,
+                                enc_w,
+                                enc_h,
+                                rate,
+                                opts.max_slice_size,
+                            );
+                            encoder = match rebuilt {
+                                Ok(encoder) => encoder.with_frame_pool(opts.frame_pool),
+                                Err(e) => {
+                                    log::error!("Encoder rebuild failed, capture stopped: {}", e);
+                                    break;
+                                }
                             };
-                            encoder = FfmpegEncoder::new(enc_w, enc_h);
                             encoder.force_idr = force_idr.clone();
+                            encoder.simulcast = simulcast.clone();
                             force_idr.store(true, Ordering::Relaxed);
                             cached_black_frame = None;
+                            cached_placeholder = None;
                             current_crop = opts.crop;
-                            log::info!("Crop changed → encoder recreated at {}x{}", enc_w, enc_h);
+                            current_profile = opts.profile;
+                            current_scale_to = opts.scale_to;
+                            rebuild_encoder = false;
+                            log::info!(
+                                "Crop/profile/budget/size changed → encoder recreated at {}x{} (source {}x{})",
+                                enc_w, enc_h, src_w, src_h
+                            );
                         }
 
                         if opts.blank_screen {
-                            // Encode a black NV12 frame at encoder dimensions
-                            let (enc_w, enc_h) = if let Some(ref c) = current_crop {
-                                (c.w + (c.w % 2), c.h + (c.h % 2))
-                            } else {
-                                (item_size.Width as u32, item_size.Height as u32)
-                            };
+                            // Encode a black NV12 frame at source dimensions (the encoder scales it)
+                            let (src_w, src_h) = opts.source_size(display_size);
                             let black = cached_black_frame.get_or_insert_with(|| {
                                 YUVFrame {
                                     display_time: 0,
-                                    width: enc_w as i32,
-                                    height: enc_h as i32,
-                                    luminance_bytes: vec![0u8; (enc_w * enc_h) as usize],
-                                    luminance_stride: enc_w as i32,
-                                    chrominance_bytes: vec![128u8; (enc_w * enc_h / 2) as usize],
-                                    chrominance_stride: enc_w as i32,
+                                    width: src_w as i32,
+                                    height: src_h as i32,
+                                    luminance_bytes: vec![0u8; (src_w * src_h) as usize],
+                                    luminance_stride: src_w as i32,
+                                    chrominance_bytes: vec![128u8; (src_w * src_h / 2) as usize],
+                                    chrominance_stride: src_w as i32,
                                 }
                             });
                             match encoder.encode(FrameData::NV12(black), frame_time) {
                                 Ok(encoded) => {
+                                    let len = encoded.len();
                                     if output.try_send(encoded).is_err() {
                                         stats.frames_skipped.fetch_add(1, Ordering::Relaxed);
+                                        if let Some(health) = &health {
+                                            health.record_frame_drop(DropSource::Capture);
+                                        }
+                                    } else {
+                                        budget_ctl.record(len);
                                     }
                                 }
                                 Err(e) => {
@@ -215,7 +574,60 @@ impl ScreenCapture for WGCScreenCapture {
                         let surface =
                             d3d::get_d3d_interface_from_object(&frame.Surface().unwrap()).unwrap();
 
-                        let encoded_result = if current_crop.is_none() {
+                        let keys = opts.keycast.as_ref().map(|k| k.visible()).unwrap_or_default();
+
+                        let zooming = zoom_anim.is_active(opts.zoom.as_ref());
+
+                        // Finestre escluse visibili su questa sorgente, cercate su ogni
+                        // frame: una finestra appena aperta o spostata non resta scoperta
+                        let masks: Vec<CropRect> = if opts.privacy_exclude.is_empty() {
+                            Vec::new()
+                        } else {
+                            excluded_window_rects(&opts.privacy_exclude)
+                                .into_iter()
+                                .filter_map(|rect| window_mask(rect, display_size, display_origin))
+                                .collect()
+                        };
+
+                        let duplicator = &mut duplicators[source];
+
+                        if !opts.content_aware {
+                            content_rate.reset();
+                        }
+                        // Decide sul frame che verrebbe codificato: false = identico o
+                        // schermo fermo, si salta
+                        let mut rate_check = |luma: &[u8], width: usize, stride: i32| {
+                            let changed = duplicates.is_new(luma, width, stride as usize);
+                            // Un keyframe richiesto (es. nuovo peer) esce comunque
+                            if force_idr.load(Ordering::Relaxed) {
+                                return true;
+                            }
+                            if !changed {
+                                stats.frames_duplicate.fetch_add(1, Ordering::Relaxed);
+                                return false;
+                            }
+                            if !opts.content_aware {
+                                return true;
+                            }
+                            match content_rate.decide(luma, width, stride as usize) {
+                                RateDecision::Skip => false,
+                                RateDecision::EncodeKeyframe => {
+                                    force_idr.store(true, Ordering::Relaxed);
+                                    true
+                                }
+                                RateDecision::Encode => true,
+                            }
+                        };
+
+                        let encoded_result = if !spanning
+                            && current_crop.is_none()
+                            && opts.cursor_highlight.is_none()
+                            && keys.is_empty()
+                            && opts.watermark.is_none()
+                            && opts.timestamp.is_none()
+                            && !zooming
+                            && masks.is_empty()
+                        {
                             // Fast path: map NV12 planes and encode directly, avoiding YUVFrame allocation/copy.
                             let t_capture = std::time::Instant::now();
                             duplicator.capture_with_nv12_view(surface, |nv12_view| {
@@ -223,44 +635,144 @@ impl ScreenCapture for WGCScreenCapture {
                                     t_capture.elapsed().as_micros() as u64,
                                     Ordering::Relaxed,
                                 );
+                                if !rate_check(
+                                    nv12_view.luminance_bytes,
+                                    display_size.0 as usize,
+                                    nv12_view.luminance_stride,
+                                ) {
+                                    return Ok(None);
+                                }
                                 let t_encode = std::time::Instant::now();
                                 let encoded = encoder.encode(FrameData::NV12Ref(nv12_view), frame_time);
                                 stats
                                     .encode_us
                                     .fetch_add(t_encode.elapsed().as_micros() as u64, Ordering::Relaxed);
-                                encoded
+                                encoded.map(Some)
                             })
                         } else {
                             let t_capture = std::time::Instant::now();
-                            let yuv_frame = duplicator.capture(surface).unwrap();
+                            let captured = duplicator.capture(surface).unwrap();
+                            let mut yuv_frame = match span_canvas.as_mut() {
+                                Some(canvas) => {
+                                    // Aggiorna solo i monitor arrivati, gli altri restano
+                                    // all'ultimo frame. Quelli in coda dal tick precedente
+                                    // entrano nello stesso frame: un solo encode per tick
+                                    blit_nv12(canvas, &captured, offsets[source]);
+                                    canvas.display_time = captured.display_time;
+                                    while let Ok((source, frame)) = receiver.try_recv() {
+                                        let surface = d3d::get_d3d_interface_from_object(
+                                            &frame.Surface().unwrap(),
+                                        )
+                                        .unwrap();
+                                        let captured =
+                                            duplicators[source].capture(surface).unwrap();
+                                        blit_nv12(canvas, &captured, offsets[source]);
+                                    }
+                                    copy_canvas_into(canvas, &mut span_frame);
+                                    std::mem::take(&mut span_frame)
+                                }
+                                None => captured,
+                            };
                             stats
                                 .capture_us
                                 .fetch_add(t_capture.elapsed().as_micros() as u64, Ordering::Relaxed);
 
-                            // Crop extraction: reuse pre-allocated buffers, swap instead of clone
-                            let frame_to_encode = extract_crop_nv12_reuse(
-                                &yuv_frame,
-                                current_crop.as_ref().unwrap(),
-                                &mut crop_y_buf,
-                                &mut crop_uv_buf,
-                            );
+                            // Coperte prima di ogni altra cosa: crop e zoom non devono
+                            // poterle scoprire
+                            draw_privacy_masks(&mut yuv_frame, &masks);
 
-                            let t_encode = std::time::Instant::now();
-                            let encoded = encoder.encode(FrameData::NV12(&frame_to_encode), frame_time);
-                            stats
-                                .encode_us
-                                .fetch_add(t_encode.elapsed().as_micros() as u64, Ordering::Relaxed);
+                            let cursor = if opts.cursor_highlight.is_some() || zooming {
+                                cursor_tracker.poll(display_origin)
+                            } else {
+                                None
+                            };
+
+                            // Overlays are drawn on the full frame, before the crop
+                            if let Some(style) = &opts.cursor_highlight
+                                && let Some(cursor) = &cursor
+                            {
+                                draw_cursor_highlight(&mut yuv_frame, cursor, style);
+                            }
+
+                            let mut frame_to_encode = match current_crop.as_ref() {
+                                // Crop extraction: reuse pre-allocated buffers, swap instead of clone
+                                Some(crop) => {
+                                    let cropped = extract_crop_nv12_reuse(
+                                        &yuv_frame,
+                                        crop,
+                                        &mut crop_y_buf,
+                                        &mut crop_uv_buf,
+                                    );
+                                    if spanning {
+                                        span_frame = yuv_frame;
+                                    }
+                                    cropped
+                                }
+                                None => yuv_frame,
+                            };
+
+                            // Magnifier: extract the animated region and scale it back to
+                            // the source size, so the encoder dimensions never change
+                            if zooming {
+                                let source = (frame_to_encode.width as u32, frame_to_encode.height as u32);
+                                let offset = current_crop.map(|c| ((c.x & !1) as i32, (c.y & !1) as i32)).unwrap_or((0, 0));
+                                let pointer = cursor.map(|c| (c.x - offset.0, c.y - offset.1));
+                                if let Some(region) = zoom_anim.step(opts.zoom.as_ref(), pointer, source) {
+                                    let zoomed = extract_crop_nv12_reuse(
+                                        &frame_to_encode,
+                                        &region,
+                                        &mut zoom_y_buf,
+                                        &mut zoom_uv_buf,
+                                    );
+                                    scale_nv12_into(&zoomed, source, &mut zoom_frame);
+                                    // Lo scambio tiene vivi i piani della sorgente, riusati al
+                                    // prossimo frame invece di riallocare
+                                    std::mem::swap(&mut frame_to_encode, &mut zoom_frame);
+                                }
+                            }
+
+                            // Keycast, watermark and timestamp are anchored to the encoded frame, so they stay visible when cropping
+                            if let Some(watermark) = &opts.watermark {
+                                draw_watermark(&mut frame_to_encode, watermark);
+                            }
+                            if let Some(timestamp) = &opts.timestamp {
+                                draw_timestamp(&mut frame_to_encode, timestamp);
+                            }
+                            draw_keycast(&mut frame_to_encode, &keys);
+
+                            let encoded = if rate_check(
+                                &frame_to_encode.luminance_bytes,
+                                frame_to_encode.width as usize,
+                                frame_to_encode.luminance_stride,
+                            ) {
+                                let t_encode = std::time::Instant::now();
+                                let encoded = encoder.encode(FrameData::NV12(&frame_to_encode), frame_time);
+                                stats
+                                    .encode_us
+                                    .fetch_add(t_encode.elapsed().as_micros() as u64, Ordering::Relaxed);
+                                encoded.map(Some)
+                            } else {
+                                Ok(None)
+                            };
+                            // La copia del canvas torna disponibile per il prossimo tick
+                            if spanning && current_crop.is_none() {
+                                span_frame = frame_to_encode;
+                            }
                             encoded
                         };
 
                         match encoded_result {
-                            Ok(encoded) => {
+                            // Schermo fermo: niente da inviare fino al prossimo refresh
+                            Ok(None) => {}
+                            Ok(Some(encoded)) => {
                                 stats.frames_encoded.fetch_add(1, Ordering::Relaxed);
 
                                 let t_send = std::time::Instant::now();
+                                let len = encoded.len();
                                 // Use try_send to prevent blocking the capture pipeline
                                 match output.try_send(encoded) {
                                     Ok(_) => {
+                                        budget_ctl.record(len);
                                         pressure_score = pressure_score.saturating_sub(1);
                                         stats.send_us.fetch_add(t_send.elapsed().as_micros() as u64, Ordering::Relaxed);
                                     }
@@ -269,6 +781,9 @@ impl ScreenCapture for WGCScreenCapture {
                                         pressure_score = (pressure_score + 3).min(100);
                                         current_fps = current_fps.saturating_sub(6).max(15);
                                         stats.frames_skipped.fetch_add(1, Ordering::Relaxed);
+                                        if let Some(health) = &health {
+                                            health.record_frame_drop(DropSource::Capture);
+                                        }
                                         force_idr.store(true, Ordering::Relaxed);
                                         log::warn!("Encoder output channel full, frame dropped");
                                     }
@@ -293,6 +808,14 @@ impl ScreenCapture for WGCScreenCapture {
                             current_fps = (current_fps + 1).min(max_fps);
                         }
 
+                        // Data budget: vicino al tetto scende fps/risoluzione/bitrate,
+                        // ben al di sotto risale (l'encoder viene ricreato al frame successivo)
+                        if let Some(level) = budget_ctl.evaluate(opts.data_budget.as_ref()) {
+                            rebuild_encoder = true;
+                            current_fps = current_fps.min(level.fps_cap());
+                            log::info!("Data budget → quality level {:?}", level);
+                        }
+
                         stats.current_fps.store(current_fps as u64, Ordering::Relaxed);
 
                         let remaining = budget_ms.saturating_sub(elapsed_ms);
@@ -300,6 +823,39 @@ impl ScreenCapture for WGCScreenCapture {
                             tokio::time::sleep(std::time::Duration::from_millis(remaining)).await;
                         }
                     }
+                    // Canale dei frame chiuso: il ramo si spegne e il loop esce dall'`else`
+                    _ = placeholder_tick.tick(), if window.is_some() && !receiver.is_closed() => {
+                        if !window.as_ref().is_some_and(Display::is_minimized) {
+                            continue;
+                        }
+                        if !minimized {
+                            minimized = true;
+                            log::info!("Captured window minimized, sending a placeholder until it is restored");
+                        }
+                        let opts = opts_rx.borrow().clone();
+                        if opts.paused {
+                            continue;
+                        }
+                        let (src_w, src_h) = opts.source_size(display_size);
+                        let placeholder = cached_placeholder.get_or_insert_with(|| {
+                            placeholder_frame(src_w, src_h, "WINDOW MINIMIZED")
+                        });
+                        let frame_time = last_frame.0 + FfmpegEncoder::frame_time(last_frame.1.elapsed());
+                        match encoder.encode(FrameData::NV12(placeholder), frame_time) {
+                            Ok(encoded) => {
+                                let len = encoded.len();
+                                if output.try_send(encoded).is_err() {
+                                    stats.frames_skipped.fetch_add(1, Ordering::Relaxed);
+                                    if let Some(health) = &health {
+                                        health.record_frame_drop(DropSource::Capture);
+                                    }
+                                } else {
+                                    budget_ctl.record(len);
+                                }
+                            }
+                            Err(e) => log::error!("Encode placeholder frame failed: {}", e),
+                        }
+                    }
                     else => {
                         log::error!("WGC Capture: receiver.recv() returned None - channel closed!");
                         break;
@@ -307,112 +863,107 @@ impl ScreenCapture for WGCScreenCapture {
                 }
             }
 
+            // Fine sessione: i frame ancora nell'encoder escono prima della chiusura
+            match encoder.flush() {
+                Ok(tail) if !tail.is_empty() => {
+                    let _ = output.try_send(tail);
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Encoder flush failed: {}", e),
+            }
+
             log::error!(
                 "=== WGC Capture loop EXITED after {} frames ===",
                 frame_count
             );
         });
 
-        self.engine.replace(engine);
-
         Ok(())
     }
 
     async fn stop_capture(&mut self) -> Result<(), anyhow::Error> {
-        if let Some(session) = self.session.take() {
+        for (item, token) in self.closed_tokens.drain(..) {
+            let _ = item.RemoveClosed(token);
+        }
+        for session in self.sessions.drain(..) {
             session.Close()?;
         }
-        self.engine.take();
+        // Senza handler il canale dei frame si chiude e il loop termina
+        for engine in self.engines.iter_mut() {
+            if let Err(e) = engine.close() {
+                log::warn!("Failed to close frame pool: {}", e);
+            }
+        }
+        self.engines.clear();
         Ok(())
     }
+
+    fn set_health(&mut self, health: Arc<PipelineHealth>) {
+        self.health = Some(health);
+    }
+
+    fn source_events(&self) -> Option<watch::Receiver<SourceEvent>> {
+        Some(self.source_events.subscribe())
+    }
 }
 
 impl DisplaySelector for WGCScreenCapture {
     type Display = Display;
 
     fn available_displays(&mut self) -> Result<Vec<Display>, anyhow::Error> {
-        Display::online()
+        let mut displays = Display::online()?;
+        if displays.len() > 1
+            && let Some(span) = Display::all_displays(&displays)
+        {
+            displays.push(span);
+        }
+        Ok(displays)
     }
 
     fn select_display(&mut self, display: &Display) -> Result<(), anyhow::Error> {
         // Switching display while an active session is running is not supported.
         // The caller should stop capture first, select display, then start again.
-        if self.session.is_some() {
+        if !self.sessions.is_empty() {
             return Err(anyhow::anyhow!(
                 "Cannot switch display while capture is running"
             ));
         }
 
         // IMPORTANT: update the actual capture item, not only internal engine state.
-        self.item = display.select()?;
-        self.engine = None;
+        // "All Displays" mantiene l'item del primo monitor, i singoli item
+        // vengono creati all'avvio della cattura
+        if display.is_span() {
+            self.span = Some(display.bounds());
+        } else {
+            self.item = display.select()?;
+            self.span = None;
+        }
+        self.engines.clear();
         self.selected_display = display.clone();
         Ok(())
     }
 
+    fn available_windows(&mut self) -> Result<Vec<Display>, anyhow::Error> {
+        Display::windows()
+    }
+
     fn selected_display(&self) -> Result<Option<Self::Display>, anyhow::Error> {
         Ok(Some(self.selected_display.clone()))
     }
-}
 
-/// Extract a crop region from an NV12 YUVFrame, reusing pre-allocated buffers.
-/// Returns a YUVFrame whose luminance/chrominance data lives in the provided buffers.
-fn extract_crop_nv12_reuse<'a>(
-    frame: &YUVFrame,
-    crop: &CropRect,
-    y_buf: &'a mut Vec<u8>,
-    uv_buf: &'a mut Vec<u8>,
-) -> YUVFrame {
-    let cx = crop.x & !1;
-    let cw = (crop.w + (crop.w % 2)) as usize;
-    let cy = crop.y & !1;
-    let ch = (crop.h + (crop.h % 2)) as usize;
-
-    let src_y_stride = frame.luminance_stride as usize;
-    let src_uv_stride = frame.chrominance_stride as usize;
-
-    // Resize buffers (no-op after first frame if dimensions are constant)
-    y_buf.resize(cw * ch, 0);
-    let uv_h = ch / 2;
-    uv_buf.resize(cw * uv_h, 128);
-
-    // Extract Y plane
-    for row in 0..ch {
-        let src_row = (cy as usize) + row;
-        if src_row >= frame.height as usize {
-            break;
-        }
-        let src_start = src_row * src_y_stride + cx as usize;
-        let dst_start = row * cw;
-        let copy_len = cw.min(frame.luminance_bytes.len().saturating_sub(src_start));
-        if copy_len > 0 {
-            y_buf[dst_start..dst_start + copy_len]
-                .copy_from_slice(&frame.luminance_bytes[src_start..src_start + copy_len]);
-        }
+    fn is_primary(display: &Display) -> bool {
+        display.primary
     }
 
-    // Extract UV plane
-    for row in 0..uv_h {
-        let src_row = (cy as usize / 2) + row;
-        if src_row >= (frame.height as usize / 2) {
-            break;
-        }
-        let src_start = src_row * src_uv_stride + cx as usize;
-        let dst_start = row * cw;
-        let copy_len = cw.min(frame.chrominance_bytes.len().saturating_sub(src_start));
-        if copy_len > 0 {
-            uv_buf[dst_start..dst_start + copy_len]
-                .copy_from_slice(&frame.chrominance_bytes[src_start..src_start + copy_len]);
-        }
+    fn display_rect(display: &Display) -> (f32, f32, f32, f32) {
+        display.rect()
+    }
+
+    fn refresh_rate(display: &Display) -> Option<u32> {
+        display.refresh_rate
     }
 
-    YUVFrame {
-        display_time: frame.display_time,
-        width: cw as i32,
-        height: ch as i32,
-        luminance_bytes: std::mem::replace(y_buf, Vec::with_capacity(cw * ch)),
-        luminance_stride: cw as i32,
-        chrominance_bytes: std::mem::replace(uv_buf, Vec::with_capacity(cw * uv_h)),
-        chrominance_stride: cw as i32,
+    fn thumbnail(display: &Display, max_width: u32) -> Result<Option<Thumbnail>, anyhow::Error> {
+        display.thumbnail(max_width).map(Some)
     }
 }
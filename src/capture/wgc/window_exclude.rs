@@ -0,0 +1,108 @@
+use std::mem::size_of;
+
+use windows::Win32::Foundation::{HWND, LPARAM, RECT};
+use windows::Win32::Graphics::Dwm::{
+    DWMWA_CLOAKED, DWMWA_EXTENDED_FRAME_BOUNDS, DwmGetWindowAttribute,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GWL_EXSTYLE, GetClassNameW, GetWindowLongW, GetWindowRect, GetWindowTextW,
+    GetWindowThreadProcessId, IsIconic, IsWindowVisible, WS_EX_LAYERED, WS_EX_TRANSPARENT,
+};
+use windows::core::BOOL;
+
+use crate::capture::privacy::{is_excluded, visible_parts};
+
+/// Lista delle voci da escludere e rettangoli trovati, passata al callback
+struct Search<'a> {
+    patterns: &'a [String],
+    rects: Vec<(i32, i32, i32, i32)>,
+    /// Finestre opache già enumerate, cioè sopra quella corrente
+    above: Vec<(i32, i32, i32, i32)>,
+}
+
+/// Rettangoli (left, top, right, bottom) delle parti visibili delle finestre
+/// che corrispondono a `patterns`, in pixel fisici del desktop virtuale.
+/// `EnumWindows` va dall'alto verso il basso: quello che le finestre sopra
+/// coprono resta com'è.
+///
+/// A differenza della lista delle sorgenti include dialog e tool window: una
+/// richiesta di password va coperta come la finestra principale.
+pub fn excluded_window_rects(patterns: &[String]) -> Vec<(i32, i32, i32, i32)> {
+    let mut search = Search {
+        patterns,
+        rects: Vec::new(),
+        above: Vec::new(),
+    };
+    let state = LPARAM(&mut search as *mut Search as isize);
+    unsafe {
+        let _ = EnumWindows(Some(enum_window), state);
+    }
+    search.rects
+}
+
+/// Titolo o classe completi, senza il taglio della lista delle sorgenti
+fn window_text(read: impl FnOnce(&mut [u16]) -> i32) -> String {
+    let mut buf = [0u16; 256];
+    let len = read(&mut buf);
+    String::from_utf16_lossy(&buf[..len.clamp(0, buf.len() as i32) as usize])
+}
+
+// callback function for EnumWindows
+extern "system" fn enum_window(window: HWND, state: LPARAM) -> BOOL {
+    unsafe {
+        let search = &mut *(state.0 as *mut Search);
+        if !IsWindowVisible(window).as_bool() || IsIconic(window).as_bool() {
+            return true.into();
+        }
+
+        // Le app UWP sospese restano "visibili" ma nascoste da DWM
+        let mut cloaked = 0u32;
+        let _ = DwmGetWindowAttribute(
+            window,
+            DWMWA_CLOAKED,
+            &mut cloaked as *mut u32 as *mut _,
+            size_of::<u32>() as u32,
+        );
+        if cloaked != 0 {
+            return true.into();
+        }
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(window, Some(&mut pid));
+
+        let title = window_text(|buf| GetWindowTextW(window, buf));
+        let class = window_text(|buf| GetClassNameW(window, buf));
+        if pid != std::process::id() && is_excluded(search.patterns, &title, &class) {
+            // Rettangolo intero, bordi invisibili compresi: meglio abbondare
+            let mut rect = RECT::default();
+            if GetWindowRect(window, &mut rect).is_ok() {
+                let rect = (rect.left, rect.top, rect.right, rect.bottom);
+                search.rects.extend(visible_parts(rect, &search.above));
+            }
+        }
+        if let Some(bounds) = occluding_bounds(window) {
+            search.above.push(bounds);
+        }
+    }
+    true.into()
+}
+
+/// Area che la finestra copre davvero: senza i bordi invisibili di
+/// ridimensionamento e `None` per le finestre trasparenti o layered, che
+/// possono lasciar vedere quello che c'è sotto.
+fn occluding_bounds(window: HWND) -> Option<(i32, i32, i32, i32)> {
+    let ex_style = unsafe { GetWindowLongW(window, GWL_EXSTYLE) } as u32;
+    if ex_style & (WS_EX_LAYERED.0 | WS_EX_TRANSPARENT.0) != 0 {
+        return None;
+    }
+    let mut rect = RECT::default();
+    unsafe {
+        DwmGetWindowAttribute(
+            window,
+            DWMWA_EXTENDED_FRAME_BOUNDS,
+            &mut rect as *mut RECT as *mut _,
+            size_of::<RECT>() as u32,
+        )
+    }
+    .ok()?;
+    Some((rect.left, rect.top, rect.right, rect.bottom))
+}
@@ -0,0 +1,35 @@
+use windows::Win32::Foundation::{POINT, RECT};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetCursorPos, GetForegroundWindow, GetWindowRect, GetWindowThreadProcessId, IsIconic,
+};
+
+/// Posizione del puntatore in pixel fisici del desktop virtuale.
+pub fn cursor_position() -> Option<(i32, i32)> {
+    let mut pt = POINT::default();
+    unsafe { GetCursorPos(&mut pt) }.ok()?;
+    Some((pt.x, pt.y))
+}
+
+/// Rettangolo (left, top, right, bottom) della finestra in primo piano, in
+/// pixel fisici del desktop virtuale.
+///
+/// Ritorna `None` se non c'è una finestra valida, se è minimizzata o se
+/// appartiene a Castify stesso (cliccare sulla GUI non deve spostare il crop).
+pub fn foreground_window_rect() -> Option<(i32, i32, i32, i32)> {
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_invalid() || IsIconic(hwnd).as_bool() {
+            return None;
+        }
+
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        if pid == std::process::id() {
+            return None;
+        }
+
+        let mut rect = RECT::default();
+        GetWindowRect(hwnd, &mut rect).ok()?;
+        Some((rect.left, rect.top, rect.right, rect.bottom))
+    }
+}
@@ -2,4 +2,4 @@ mod dx_math;
 mod shader;
 mod yuv_converter;
 
-pub use yuv_converter::YuvConverter;
+pub use yuv_converter::{ToneMap, YuvConverter};
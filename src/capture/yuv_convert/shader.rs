@@ -1,3 +1,52 @@
+use anyhow::anyhow;
+use windows::Win32::Graphics::Direct3D::Fxc::D3DCompile;
+use windows::Win32::Graphics::Direct3D::ID3DBlob;
+use windows::core::PCSTR;
+
 pub static VERTEX_SHADER_BYTES: &[u8] = include_bytes!("shader/vertex_shader.cso");
 pub static PIXEL_SHADER_LUMINANCE_BYTES: &[u8] = include_bytes!("shader/pixel_shader_y.cso");
 pub static PIXEL_SHADER_CHROMINANCE_BYTES: &[u8] = include_bytes!("shader/pixel_shader_uv.cso");
+
+/// Tone-mapping HDR → SDR, compilato solo quando serve (d3dcompiler_47 è
+/// parte di Windows 10+)
+static PIXEL_SHADER_HDR_SOURCE: &str = include_str!("shader/PixelShaderHdr.hlsl");
+
+/// Bytecode dell'entry point `PS_Y` o `PS_UV` dello shader HDR
+pub fn compile_hdr_pixel_shader(entry_point: &std::ffi::CStr) -> Result<Vec<u8>, anyhow::Error> {
+    unsafe {
+        let mut code: Option<ID3DBlob> = None;
+        let mut errors: Option<ID3DBlob> = None;
+        let result = D3DCompile(
+            PIXEL_SHADER_HDR_SOURCE.as_ptr() as *const _,
+            PIXEL_SHADER_HDR_SOURCE.len(),
+            PCSTR(c"PixelShaderHdr.hlsl".as_ptr() as *const u8),
+            None,
+            None,
+            PCSTR(entry_point.as_ptr() as *const u8),
+            PCSTR(c"ps_4_0".as_ptr() as *const u8),
+            0,
+            0,
+            &mut code,
+            Some(&mut errors),
+        );
+        if let Err(e) = result {
+            let message = errors
+                .map(|blob| blob_bytes(&blob).to_vec())
+                .unwrap_or_default();
+            return Err(anyhow!(
+                "HDR shader {:?} failed to compile: {} {}",
+                entry_point,
+                e,
+                String::from_utf8_lossy(&message)
+            ));
+        }
+        let code = code.ok_or_else(|| anyhow!("HDR shader {:?} produced no code", entry_point))?;
+        Ok(blob_bytes(&code).to_vec())
+    }
+}
+
+unsafe fn blob_bytes(blob: &ID3DBlob) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(blob.GetBufferPointer() as *const u8, blob.GetBufferSize())
+    }
+}
@@ -0,0 +1,77 @@
+//----------------------------------------------------------------------
+// Tone-mapping of scRGB (FP16, linear, 1.0 = 80 nits) desktop captures
+// to SDR, followed by the same BT.709 conversion as PixelShaderY/UV.
+// Compiled at runtime (D3DCompile) with entry points PS_Y and PS_UV.
+//----------------------------------------------------------------------
+
+Texture2D tx : register(t0);
+SamplerState samLinear : register(s0);
+
+cbuffer ToneMap : register(b0)
+{
+	// SDR white level in scRGB units (nits / 80)
+	float WhiteLevel;
+	// Brightest highlight kept distinguishable, relative to WhiteLevel
+	float PeakRatio;
+	float2 Padding;
+};
+
+struct PS_INPUT
+{
+	float4 Pos : SV_POSITION;
+	float2 Tex : TEXCOORD;
+};
+
+static const float3x1 RGBtoYCoeffVector =
+{
+	0.182585f, // 0.2126f * 219 / 255,
+	0.614230f, //0.7152f,
+	0.062007f, //0.0722f,
+};
+
+static const float3x2 RGBtoUVCoeffMatrix =
+{
+	-0.100644f,  0.439214f,
+	-0.338570f, -0.398941f,
+	 0.439214f, -0.040273f,
+};
+
+// Extended Reinhard on the brightest channel: the SDR range stays almost
+// linear, highlights up to PeakRatio roll off into white keeping their hue
+float3 ToneMapSdr(float3 scrgb)
+{
+	float3 rgb = max(scrgb, 0.0f) / WhiteLevel;
+	float peak = max(max(rgb.r, rgb.g), rgb.b);
+	if (peak <= 0.0f)
+	{
+		return float3(0.0f, 0.0f, 0.0f);
+	}
+	float mapped = peak * (1.0f + peak / (PeakRatio * PeakRatio)) / (1.0f + peak);
+	return saturate(rgb * (mapped / peak));
+}
+
+// Linear BT.709 → sRGB transfer, as the 8-bit desktop would have it
+float3 LinearToSrgb(float3 rgb)
+{
+	float3 low = rgb * 12.92f;
+	float3 high = 1.055f * pow(rgb, 1.0f / 2.4f) - 0.055f;
+	return lerp(low, high, step(0.0031308f, rgb));
+}
+
+float3 SamplePixel(float2 tex)
+{
+	float4 pixel = tx.Sample(samLinear, tex);
+	return LinearToSrgb(ToneMapSdr(pixel.rgb));
+}
+
+float PS_Y(PS_INPUT input) : SV_TARGET
+{
+	float y = mul(SamplePixel(input.Tex), RGBtoYCoeffVector);
+	return saturate(y + 0.0625f);
+}
+
+float2 PS_UV(PS_INPUT input) : SV_Target
+{
+	float2 uv = mul(SamplePixel(input.Tex), RGBtoUVCoeffMatrix);
+	return saturate(uv + float2(0.5f, 0.5f));
+}
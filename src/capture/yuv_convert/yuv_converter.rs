@@ -15,6 +15,40 @@ use windows::{
     core::PCSTR,
 };
 
+/// Scala in nit di scRGB: 1.0 corrisponde a 80 nit
+const SCRGB_NITS: f32 = 80.0;
+
+/// Tone-mapping verso SDR per i desktop HDR, catturati in scRGB (FP16)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneMap {
+    /// Livello del bianco SDR scelto in Windows ("SDR content brightness")
+    pub white_nits: f32,
+    /// Luci più intense ancora distinguibili prima di saturare
+    pub peak_nits: f32,
+}
+
+impl ToneMap {
+    /// Picco tipico dei monitor HDR consumer
+    pub const DEFAULT_PEAK_NITS: f32 = 1000.0;
+    /// Bianco SDR di Windows quando il monitor non lo riporta
+    pub const DEFAULT_WHITE_NITS: f32 = 200.0;
+
+    pub fn new(white_nits: f32) -> Self {
+        Self {
+            white_nits: white_nits.max(SCRGB_NITS),
+            peak_nits: Self::DEFAULT_PEAK_NITS,
+        }
+    }
+}
+
+/// Costanti del pixel shader HDR, allineate a 16 byte come vuole D3D11
+#[repr(C)]
+struct ToneMapConstants {
+    white_level: f32,
+    peak_ratio: f32,
+    _padding: [f32; 2],
+}
+
 #[derive(Clone)]
 pub struct YuvConverter {
     device: Arc<ID3D11Device>,
@@ -38,6 +72,9 @@ pub struct YuvConverter {
     chrominance_viewport: [D3D11_VIEWPORT; 1],
     chrominance_rtv: [Option<ID3D11RenderTargetView>; 1],
 
+    /// Costanti del tone-mapping, `None` per la cattura SDR (BGRA8)
+    tone_map_constants: Option<ID3D11Buffer>,
+
     resolution: (u32, u32),
 }
 
@@ -48,12 +85,32 @@ impl YuvConverter {
         device: Arc<ID3D11Device>,
         device_context: Arc<ID3D11DeviceContext>,
         resolution: (u32, u32),
+    ) -> Result<YuvConverter, anyhow::Error> {
+        Self::with_tone_map(device, device_context, resolution, None)
+    }
+
+    /// Con `tone_map` le texture in ingresso sono scRGB FP16 (R16G16B16A16)
+    /// e vengono riportate in SDR prima della conversione NV12
+    pub fn with_tone_map(
+        device: Arc<ID3D11Device>,
+        device_context: Arc<ID3D11DeviceContext>,
+        resolution: (u32, u32),
+        tone_map: Option<ToneMap>,
     ) -> Result<YuvConverter, anyhow::Error> {
         unsafe {
-            let backend_texture = init_backend_resources(&device, resolution)?;
+            let format = match tone_map {
+                Some(_) => DXGI_FORMAT_R16G16B16A16_FLOAT,
+                None => DXGI_FORMAT_B8G8R8A8_UNORM,
+            };
+            let backend_texture = init_backend_resources(&device, resolution, format)?;
 
             let (vertex_shader, vertex_buffer, pixel_shader_luminance, pixel_shader_chrominance) =
-                init_shaders(&device)?;
+                init_shaders(&device, tone_map.is_some())?;
+
+            let tone_map_constants = match tone_map {
+                Some(tone_map) => Some(init_tone_map_constants(&device, tone_map)?),
+                None => None,
+            };
 
             let (
                 luminance_render_texture,
@@ -91,6 +148,7 @@ impl YuvConverter {
                 chrominance_staging_texture,
                 chrominance_viewport: [chrominance_viewport],
                 chrominance_rtv: [Some(chrominance_rtv)],
+                tone_map_constants,
                 resolution,
             })
         }
@@ -220,6 +278,11 @@ impl YuvConverter {
 
             self.device_context.VSSetShader(&self.vertex_shader, None);
 
+            if let Some(constants) = &self.tone_map_constants {
+                self.device_context
+                    .PSSetConstantBuffers(0, Some(&[Some(constants.clone())]));
+            }
+
             // draw lumina plane
 
             self.device_context
@@ -255,11 +318,11 @@ impl YuvConverter {
             Ok(())
         }
     }
-
 }
 
 unsafe fn init_shaders(
     device: &ID3D11Device,
+    tone_map: bool,
 ) -> Result<
     (
         ID3D11VertexShader,
@@ -295,16 +358,24 @@ unsafe fn init_shaders(
             Some(&mut vertex_buffer),
         )?;
 
+        let (luminance_bytes, chrominance_bytes) = if tone_map {
+            (
+                shader::compile_hdr_pixel_shader(c"PS_Y")?,
+                shader::compile_hdr_pixel_shader(c"PS_UV")?,
+            )
+        } else {
+            (
+                shader::PIXEL_SHADER_LUMINANCE_BYTES.to_vec(),
+                shader::PIXEL_SHADER_CHROMINANCE_BYTES.to_vec(),
+            )
+        };
+
         let mut pixel_shader_luminance = None;
-        device.CreatePixelShader(
-            shader::PIXEL_SHADER_LUMINANCE_BYTES,
-            None,
-            Some(&mut pixel_shader_luminance),
-        )?;
+        device.CreatePixelShader(&luminance_bytes, None, Some(&mut pixel_shader_luminance))?;
 
         let mut pixel_shader_chrominance = None;
         device.CreatePixelShader(
-            shader::PIXEL_SHADER_CHROMINANCE_BYTES,
+            &chrominance_bytes,
             None,
             Some(&mut pixel_shader_chrominance),
         )?;
@@ -355,9 +426,40 @@ unsafe fn init_chrominance_resources(
     }
 }
 
+unsafe fn init_tone_map_constants(
+    device: &ID3D11Device,
+    tone_map: ToneMap,
+) -> Result<ID3D11Buffer, anyhow::Error> {
+    unsafe {
+        let constants = ToneMapConstants {
+            white_level: tone_map.white_nits / SCRGB_NITS,
+            peak_ratio: (tone_map.peak_nits / tone_map.white_nits).max(1.0),
+            _padding: [0.0; 2],
+        };
+        let buffer_desc = D3D11_BUFFER_DESC {
+            ByteWidth: std::mem::size_of::<ToneMapConstants>() as u32,
+            Usage: D3D11_USAGE_IMMUTABLE,
+            BindFlags: D3D11_BIND_CONSTANT_BUFFER.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+            StructureByteStride: 0,
+        };
+        let subresource_data = D3D11_SUBRESOURCE_DATA {
+            pSysMem: &constants as *const _ as *const c_void,
+            SysMemPitch: 0,
+            SysMemSlicePitch: 0,
+        };
+
+        let mut buffer = None;
+        device.CreateBuffer(&buffer_desc, Some(&subresource_data), Some(&mut buffer))?;
+        Ok(buffer.unwrap())
+    }
+}
+
 unsafe fn init_backend_resources(
     device: &ID3D11Device,
     resolution: (u32, u32),
+    format: DXGI_FORMAT,
 ) -> Result<ID3D11Texture2D, anyhow::Error> {
     unsafe {
         let mut texture_desc: D3D11_TEXTURE2D_DESC = std::mem::zeroed();
@@ -365,7 +467,7 @@ unsafe fn init_backend_resources(
         texture_desc.Height = resolution.1;
         texture_desc.MipLevels = 1;
         texture_desc.ArraySize = 1;
-        texture_desc.Format = DXGI_FORMAT_B8G8R8A8_UNORM;
+        texture_desc.Format = format;
         texture_desc.SampleDesc.Count = 1;
         texture_desc.SampleDesc.Quality = 0;
         texture_desc.Usage = D3D11_USAGE_DEFAULT;
@@ -0,0 +1,282 @@
+//! Lente d'ingrandimento: una sotto-regione della sorgente viene estratta e
+//! riscalata a piena dimensione, così l'encoder non va mai ricreato.
+//!
+//! Gli spostamenti della regione e i cambi di fattore sono animati con uno
+//! smorzamento esponenziale invece di saltare da un punto all'altro.
+
+use crate::capture::{CropRect, YUVFrame};
+use std::time::Instant;
+
+/// Costante di tempo dell'animazione (secondi): più bassa = più reattiva
+const SMOOTHING: f32 = 0.12;
+/// Sotto questa soglia lo zoom è considerato chiuso (fast path di nuovo attivo)
+const MIN_FACTOR: f32 = 1.01;
+pub const MAX_FACTOR: f32 = 6.0;
+/// Spostamento per ogni scatto da tastiera, in frazioni della regione
+const NUDGE_STEP: f32 = 0.25;
+
+/// Regione ingrandita richiesta dal presentatore.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Zoom {
+    /// Fattore di ingrandimento (1.0 = nessuno)
+    pub factor: f32,
+    /// Centro normalizzato (0.0..=1.0) sulla sorgente, `None` = segue il mouse
+    pub center: Option<(f32, f32)>,
+    /// Scatti da tastiera accumulati (x, y): al primo la regione smette di
+    /// seguire il mouse e si sposta da dove si trova
+    pub nudge: (i32, i32),
+}
+
+impl Default for Zoom {
+    fn default() -> Self {
+        Self {
+            factor: 2.0,
+            center: None,
+            nudge: (0, 0),
+        }
+    }
+}
+
+/// Stato animato della lente, vive nel loop di cattura.
+pub struct ZoomAnimator {
+    factor: f32,
+    center: (f32, f32),
+    last_step: Option<Instant>,
+    /// Centro fissato dagli scatti da tastiera e ultimi scatti applicati
+    pinned: Option<(f32, f32)>,
+    nudge: (i32, i32),
+}
+
+impl ZoomAnimator {
+    pub fn new() -> Self {
+        Self {
+            factor: 1.0,
+            center: (0.5, 0.5),
+            last_step: None,
+            pinned: None,
+            nudge: (0, 0),
+        }
+    }
+
+    /// `true` finché la lente è aperta o sta ancora chiudendosi.
+    pub fn is_active(&self, target: Option<&Zoom>) -> bool {
+        target.is_some() || self.factor > MIN_FACTOR
+    }
+
+    /// Avanza l'animazione verso `target` e ritorna la regione da estrarre
+    /// in coordinate della sorgente (`size`), `None` se lo zoom è chiuso.
+    ///
+    /// `cursor` è la posizione del mouse relativa alla sorgente, usata
+    /// quando il centro non è fissato.
+    pub fn step(
+        &mut self,
+        target: Option<&Zoom>,
+        cursor: Option<(i32, i32)>,
+        size: (u32, u32),
+    ) -> Option<CropRect> {
+        let (w, h) = (size.0 as f32, size.1 as f32);
+        let now = Instant::now();
+        let dt = self
+            .last_step
+            .map(|t| now.duration_since(t).as_secs_f32())
+            .unwrap_or(0.0);
+
+        match target {
+            Some(zoom) => self.apply_nudge(zoom),
+            // Una lente riaperta riparte dal mouse con gli scatti azzerati
+            None => {
+                self.pinned = None;
+                self.nudge = (0, 0);
+            }
+        }
+
+        let (target_factor, target_center) = match target {
+            Some(zoom) => {
+                let center = zoom.center.or(self.pinned).or_else(|| {
+                    cursor.map(|(x, y)| {
+                        (
+                            (x as f32 / w).clamp(0.0, 1.0),
+                            (y as f32 / h).clamp(0.0, 1.0),
+                        )
+                    })
+                });
+                (
+                    zoom.factor.clamp(1.0, MAX_FACTOR),
+                    center.unwrap_or(self.center),
+                )
+            }
+            // In chiusura la regione resta dov'è e il fattore torna a 1
+            None => (1.0, self.center),
+        };
+
+        // Primo frame: parte già centrato sul bersaglio, anima solo il fattore
+        if self.last_step.is_none() {
+            self.center = target_center;
+        }
+        self.last_step = Some(now);
+
+        let k = 1.0 - (-dt / SMOOTHING).exp();
+        self.factor += (target_factor - self.factor) * k;
+        self.center.0 += (target_center.0 - self.center.0) * k;
+        self.center.1 += (target_center.1 - self.center.1) * k;
+
+        if target.is_none() && self.factor <= MIN_FACTOR {
+            self.factor = 1.0;
+            self.last_step = None;
+            return None;
+        }
+
+        Some(self.region(size))
+    }
+
+    /// Sposta il centro fissato dei nuovi scatti, partendo da dove la regione
+    /// si trova ora; il passo è relativo alla regione, non alla sorgente.
+    fn apply_nudge(&mut self, zoom: &Zoom) {
+        let (dx, dy) = (zoom.nudge.0 - self.nudge.0, zoom.nudge.1 - self.nudge.1);
+        self.nudge = zoom.nudge;
+        if dx == 0 && dy == 0 {
+            return;
+        }
+        let step = NUDGE_STEP / zoom.factor.clamp(1.0, MAX_FACTOR);
+        let (x, y) = self.pinned.unwrap_or(self.center);
+        self.pinned = Some((
+            (x + dx as f32 * step).clamp(0.0, 1.0),
+            (y + dy as f32 * step).clamp(0.0, 1.0),
+        ));
+    }
+
+    /// Rettangolo di `size / factor` centrato su `center`, tenuto dentro la
+    /// sorgente e allineato al pari per il sottocampionamento NV12.
+    fn region(&self, (w, h): (u32, u32)) -> CropRect {
+        let rw = ((w as f32 / self.factor) as u32).clamp(2, w) & !1;
+        let rh = ((h as f32 / self.factor) as u32).clamp(2, h) & !1;
+
+        let cx = self.center.0 * w as f32;
+        let cy = self.center.1 * h as f32;
+        let x = (cx - rw as f32 / 2.0).clamp(0.0, (w - rw) as f32) as u32 & !1;
+        let y = (cy - rh as f32 / 2.0).clamp(0.0, (h - rh) as f32) as u32 & !1;
+
+        CropRect { x, y, w: rw, h: rh }
+    }
+}
+
+/// Riscala un frame NV12 a `dst_w`x`dst_h` con interpolazione bilineare
+/// dentro `dst`, i cui piani restano allocati da un frame all'altro.
+pub fn scale_nv12_into(src: &YUVFrame, (dst_w, dst_h): (u32, u32), dst: &mut YUVFrame) {
+    let (dw, dh) = (dst_w as usize, dst_h as usize);
+    let (sw, sh) = (src.width as usize, src.height as usize);
+
+    dst.display_time = src.display_time;
+    dst.width = dw as i32;
+    dst.height = dh as i32;
+    dst.luminance_stride = dw as i32;
+    dst.chrominance_stride = dw as i32;
+    dst.luminance_bytes.resize(dw * dh, 0);
+    dst.chrominance_bytes.resize(dw * (dh / 2), 128);
+
+    // Luminanza: un canale, passo 1
+    bilinear(
+        &src.luminance_bytes,
+        src.luminance_stride as usize,
+        (sw, sh),
+        &mut dst.luminance_bytes,
+        dw,
+        (dw, dh),
+        1,
+    );
+    // Crominanza: UV interleaved a metà risoluzione, due canali
+    bilinear(
+        &src.chrominance_bytes,
+        src.chrominance_stride as usize,
+        (sw / 2, sh / 2),
+        &mut dst.chrominance_bytes,
+        dw,
+        (dw / 2, dh / 2),
+        2,
+    );
+}
+
+/// Bilineare in virgola fissa (8 bit di frazione) su un piano con
+/// `channels` campioni interleaved per pixel.
+fn bilinear(
+    src: &[u8],
+    src_stride: usize,
+    (sw, sh): (usize, usize),
+    dst: &mut [u8],
+    dst_stride: usize,
+    (dw, dh): (usize, usize),
+    channels: usize,
+) {
+    if sw == 0 || sh == 0 || dw == 0 || dh == 0 {
+        return;
+    }
+
+    let x_step = ((sw << 8) / dw).max(1);
+    let y_step = ((sh << 8) / dh).max(1);
+
+    for dy in 0..dh {
+        let fy = dy * y_step;
+        let y0 = (fy >> 8).min(sh - 1);
+        let y1 = (y0 + 1).min(sh - 1);
+        let wy = fy & 0xFF;
+        let row0 = y0 * src_stride;
+        let row1 = y1 * src_stride;
+
+        for dx in 0..dw {
+            let fx = dx * x_step;
+            let x0 = (fx >> 8).min(sw - 1);
+            let x1 = (x0 + 1).min(sw - 1);
+            let wx = fx & 0xFF;
+
+            for c in 0..channels {
+                let sample = |row: usize, x: usize| {
+                    src.get(row + x * channels + c).copied().unwrap_or(0) as usize
+                };
+                let top = sample(row0, x0) * (256 - wx) + sample(row0, x1) * wx;
+                let bottom = sample(row1, x0) * (256 - wx) + sample(row1, x1) * wx;
+                let v = (top * (256 - wy) + bottom * wy) >> 16;
+                dst[dy * dst_stride + dx * channels + c] = v as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nudges_leave_the_mouse_and_move_one_step() {
+        let mut anim = ZoomAnimator::new();
+        let zoom = Zoom::default();
+        let size = (1920, 1080);
+
+        // Segue il mouse al centro della sorgente
+        anim.step(Some(&zoom), Some((960, 540)), size);
+
+        // Uno scatto a destra: il mouse non conta più
+        let nudged = Zoom {
+            nudge: (1, 0),
+            ..zoom
+        };
+        anim.step(Some(&nudged), Some((0, 0)), size);
+        assert_eq!(anim.pinned, Some((0.5 + NUDGE_STEP / 2.0, 0.5)));
+
+        // Fermo sullo stesso scatto: la regione converge a destra del centro
+        for _ in 0..50 {
+            anim.last_step = anim
+                .last_step
+                .map(|t| t - std::time::Duration::from_secs(1));
+            anim.step(Some(&nudged), Some((0, 0)), size);
+        }
+        assert_eq!(
+            anim.region(size),
+            CropRect {
+                x: 720,
+                y: 270,
+                w: 960,
+                h: 540
+            }
+        );
+    }
+}
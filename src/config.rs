@@ -1,20 +1,50 @@
+use crate::capture::audio::AudioEncodeConfig;
+use crate::capture::budget::DataCap;
+use crate::capture::keycast::KeycastFilter;
+use crate::capture::overlay::CursorHighlight;
+use crate::capture::timestamp::{Timestamp, TimestampFormat};
+use crate::capture::watermark::{Watermark, WatermarkCorner, WatermarkImage};
+use crate::capture::zoom::Zoom;
+use crate::capture::{
+    BitrateMode, ColorSpace, EncodeScale, FpsCap, HdrMode, Simulcast, StreamProfile,
+};
+use crate::display::{DisplayPolicy, VideoFit};
 use crate::gui::common::hotkeys::KeyTypes;
+use crate::pipeline::receiver::LatencyProfile;
+use crate::pipeline::receiver::latency_guard::DEFAULT_MAX_LATENCY_MS;
+use crate::pipeline::sender::RtpMtu;
+use crate::pipeline::stats_log::{STATS_LOG_INTERVAL, StatsFormat, StatsLogTarget};
 use crate::utils::flags::Flags;
-use crate::utils::path::default_saving_path;
+use crate::utils::logging::{self, LogLevel};
+use crate::utils::monitors::Monitors;
+use crate::utils::net::common::default_instance_name;
+use crate::utils::net::webhook::is_webhook_url;
+use crate::utils::path::{config_file_path, default_saving_path};
 use crate::utils::sos::SignalOfStop;
 use crate::utils::string::capitalize_first_letter;
 use crate::workers::WorkerClose;
 use crate::workers::caster::Caster;
-use crate::workers::receiver::Receiver;
+use crate::workers::cursor_follow::FollowCursorDelay;
+use crate::workers::idle::IdleTimeout;
+use crate::workers::receiver::{DEFAULT_CONNECT_TIMEOUT_SECS, Receiver};
+use crate::workers::save_stream::SegmentPolicy;
+use anyhow::Context;
 use castbox::Arw;
 use chrono::Local;
-use iced::Size;
 use iced::keyboard::key::Named;
 use iced::keyboard::{Key, Modifiers};
+use iced::window::Position;
+use iced::{Point, Size};
 use local_ip_address::local_ip;
 use native_dialog::DialogBuilder;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
 use std::net::{IpAddr, Ipv4Addr};
 use std::ops::DerefMut;
+use std::path::Path;
+use std::time::Duration;
 
 pub enum Mode {
     Caster(Caster),
@@ -36,6 +66,17 @@ pub struct HotkeyMap {
     pub record: (Modifiers, Key),
     pub end_session: (Modifiers, Key),
     pub blank_screen: (Modifiers, Key),
+    pub cursor_highlight: (Modifiers, Key),
+    pub cursor_capture: (Modifiers, Key),
+    pub keycast: (Modifiers, Key),
+    pub follow_lock: (Modifiers, Key),
+    pub zoom: (Modifiers, Key),
+    pub debug_overlay: (Modifiers, Key),
+    pub cycle_display: (Modifiers, Key),
+    pub screenshot: (Modifiers, Key),
+    pub mute: (Modifiers, Key),
+    pub annotations: (Modifiers, Key),
+    pub chapter_mark: (Modifiers, Key),
     pub updating: KeyTypes,
 }
 
@@ -46,14 +87,234 @@ impl Default for HotkeyMap {
             record: (Modifiers::CTRL, Key::Named(Named::F11)),
             end_session: (Modifiers::CTRL, Key::Character("w".parse().unwrap())),
             blank_screen: (Modifiers::CTRL, Key::Named(Named::F2)),
+            cursor_highlight: (Modifiers::CTRL, Key::Named(Named::F3)),
+            cursor_capture: (Modifiers::CTRL, Key::Named(Named::F1)),
+            keycast: (Modifiers::CTRL, Key::Named(Named::F4)),
+            follow_lock: (Modifiers::CTRL, Key::Named(Named::F5)),
+            zoom: (Modifiers::CTRL, Key::Named(Named::F6)),
+            debug_overlay: (Modifiers::CTRL, Key::Named(Named::F7)),
+            cycle_display: (Modifiers::CTRL, Key::Named(Named::F8)),
+            screenshot: (Modifiers::CTRL, Key::Named(Named::F9)),
+            mute: (Modifiers::CTRL, Key::Named(Named::F12)),
+            annotations: (Modifiers::CTRL | Modifiers::SHIFT, Key::Named(Named::F1)),
+            chapter_mark: (Modifiers::CTRL | Modifiers::SHIFT, Key::Named(Named::F11)),
             updating: KeyTypes::None,
         }
     }
 }
 
+/// File delle scorciatoie personalizzate, nella cartella di configurazione
+const HOTKEYS_FILE: &str = "hotkeys.json";
+
+/// Tasti nominati che si possono salvare (quelli sensati per una scorciatoia)
+const STORABLE_NAMED_KEYS: [Named; 30] = [
+    Named::F1,
+    Named::F2,
+    Named::F3,
+    Named::F4,
+    Named::F5,
+    Named::F6,
+    Named::F7,
+    Named::F8,
+    Named::F9,
+    Named::F10,
+    Named::F11,
+    Named::F12,
+    Named::Escape,
+    Named::Space,
+    Named::Tab,
+    Named::Enter,
+    Named::Backspace,
+    Named::Delete,
+    Named::Insert,
+    Named::Home,
+    Named::End,
+    Named::PageUp,
+    Named::PageDown,
+    Named::ArrowUp,
+    Named::ArrowDown,
+    Named::ArrowLeft,
+    Named::ArrowRight,
+    Named::Pause,
+    Named::PrintScreen,
+    Named::ScrollLock,
+];
+
+#[derive(Serialize, Deserialize)]
+struct StoredHotkey {
+    modifiers: u32,
+    key: String,
+}
+
+impl StoredHotkey {
+    fn from_binding((modifiers, key): &(Modifiers, Key)) -> Option<Self> {
+        let key = match key {
+            Key::Character(c) => format!("char:{}", c),
+            Key::Named(named) if STORABLE_NAMED_KEYS.contains(named) => {
+                format!("named:{:?}", named)
+            }
+            _ => return None,
+        };
+        Some(Self {
+            modifiers: modifiers.bits(),
+            key,
+        })
+    }
+
+    fn binding(&self) -> Option<(Modifiers, Key)> {
+        let key = if let Some(c) = self.key.strip_prefix("char:") {
+            Key::Character(c.into())
+        } else {
+            let name = self.key.strip_prefix("named:")?;
+            let named = STORABLE_NAMED_KEYS
+                .into_iter()
+                .find(|named| format!("{:?}", named) == name)?;
+            Key::Named(named)
+        };
+        Some((Modifiers::from_bits_truncate(self.modifiers), key))
+    }
+}
+
+impl HotkeyMap {
+    pub fn get(&self, action: KeyTypes) -> Option<&(Modifiers, Key)> {
+        Some(match action {
+            KeyTypes::Pause => &self.pause,
+            KeyTypes::Record => &self.record,
+            KeyTypes::Close => &self.end_session,
+            KeyTypes::BlankScreen => &self.blank_screen,
+            KeyTypes::CursorHighlight => &self.cursor_highlight,
+            KeyTypes::CursorCapture => &self.cursor_capture,
+            KeyTypes::Keycast => &self.keycast,
+            KeyTypes::FollowLock => &self.follow_lock,
+            KeyTypes::Zoom => &self.zoom,
+            KeyTypes::DebugOverlay => &self.debug_overlay,
+            KeyTypes::CycleDisplay => &self.cycle_display,
+            KeyTypes::Screenshot => &self.screenshot,
+            KeyTypes::Mute => &self.mute,
+            KeyTypes::Annotations => &self.annotations,
+            KeyTypes::ChapterMark => &self.chapter_mark,
+            KeyTypes::None => return None,
+        })
+    }
+
+    fn get_mut(&mut self, action: KeyTypes) -> Option<&mut (Modifiers, Key)> {
+        Some(match action {
+            KeyTypes::Pause => &mut self.pause,
+            KeyTypes::Record => &mut self.record,
+            KeyTypes::Close => &mut self.end_session,
+            KeyTypes::BlankScreen => &mut self.blank_screen,
+            KeyTypes::CursorHighlight => &mut self.cursor_highlight,
+            KeyTypes::CursorCapture => &mut self.cursor_capture,
+            KeyTypes::Keycast => &mut self.keycast,
+            KeyTypes::FollowLock => &mut self.follow_lock,
+            KeyTypes::Zoom => &mut self.zoom,
+            KeyTypes::DebugOverlay => &mut self.debug_overlay,
+            KeyTypes::CycleDisplay => &mut self.cycle_display,
+            KeyTypes::Screenshot => &mut self.screenshot,
+            KeyTypes::Mute => &mut self.mute,
+            KeyTypes::Annotations => &mut self.annotations,
+            KeyTypes::ChapterMark => &mut self.chapter_mark,
+            KeyTypes::None => return None,
+        })
+    }
+
+    /// Azione che usa già `binding`, escluso `action` stesso.
+    ///
+    /// Pause e Record condividono di default la stessa combinazione (entrambe
+    /// avviano/fermano lo streaming), quindi non sono in conflitto tra loro.
+    pub fn conflict(&self, action: KeyTypes, binding: &(Modifiers, Key)) -> Option<KeyTypes> {
+        let shared = |a: KeyTypes, b: KeyTypes| {
+            matches!(
+                (a, b),
+                (KeyTypes::Pause, KeyTypes::Record) | (KeyTypes::Record, KeyTypes::Pause)
+            )
+        };
+        KeyTypes::ALL
+            .into_iter()
+            .filter(|&other| other != action && !shared(action, other))
+            .find(|&other| self.get(other) == Some(binding))
+    }
+
+    /// Assegna `binding` ad `action`; in caso di conflitto non cambia nulla
+    /// e restituisce l'azione che usa già la combinazione.
+    pub fn assign(&mut self, action: KeyTypes, binding: (Modifiers, Key)) -> Result<(), KeyTypes> {
+        if let Some(other) = self.conflict(action, &binding) {
+            return Err(other);
+        }
+        if let Some(slot) = self.get_mut(action) {
+            *slot = binding;
+        }
+        Ok(())
+    }
+
+    /// Scorciatoie salvate, con i default per quelle mancanti o illeggibili
+    pub fn load() -> Self {
+        let mut map = HotkeyMap::default();
+        let Some(path) = config_file_path(HOTKEYS_FILE) else {
+            return map;
+        };
+        let Ok(content) = fs::read_to_string(&path) else {
+            return map;
+        };
+        let stored: HashMap<String, StoredHotkey> = match serde_json::from_str(&content) {
+            Ok(stored) => stored,
+            Err(e) => {
+                log::warn!("Ignoring {}: {}", path.display(), e);
+                return map;
+            }
+        };
+
+        for action in KeyTypes::ALL {
+            if let Some(binding) = stored
+                .get(action.config_key())
+                .and_then(StoredHotkey::binding)
+                && let Some(slot) = map.get_mut(action)
+            {
+                *slot = binding;
+            }
+        }
+
+        // Un file modificato a mano può contenere duplicati: meglio i default
+        // che due azioni sulla stessa combinazione
+        if let Some(action) = KeyTypes::ALL.into_iter().find(|&action| {
+            map.get(action)
+                .is_some_and(|b| map.conflict(action, b).is_some())
+        }) {
+            log::warn!(
+                "Hotkey for {:?} is used twice in {}, using defaults",
+                action,
+                path.display()
+            );
+            return HotkeyMap::default();
+        }
+        map
+    }
+
+    /// Salva le scorciatoie correnti nella cartella di configurazione
+    pub fn save(&self) {
+        let stored: HashMap<&str, StoredHotkey> = KeyTypes::ALL
+            .into_iter()
+            .filter_map(|action| {
+                let binding = StoredHotkey::from_binding(self.get(action)?)?;
+                Some((action.config_key(), binding))
+            })
+            .collect();
+        save_json(HOTKEYS_FILE, &stored, "hotkeys");
+    }
+
+    /// Ripristina i default e li salva
+    pub fn reset(&mut self) {
+        *self = HotkeyMap::default();
+        self.save();
+    }
+}
+
 pub struct Config {
     pub shortcuts: HotkeyMap,
-    pub window_size: Size,
+    /// Dimensione e posizione della finestra principale
+    pub window: WindowGeometry,
+    /// Impostazioni generali dell'app (chiusura nella tray)
+    pub general: GeneralSettings,
     pub e_time: u64,
     pub mode: Option<Mode>,
     pub public_ip: Arw<Option<Ipv4Addr>>,
@@ -61,16 +322,51 @@ pub struct Config {
     pub sos: SignalOfStop,
     pub multi_instance: bool,
     pub fps: u32,
+    /// Tetto di fps e finestre escluse, salvati tra le sessioni
+    pub capture: CaptureSettings,
+    pub stream_profile: StreamProfile,
+    /// Tetto alla risoluzione codificata, indipendente dal profilo
+    pub encode_scale: EncodeScale,
+    /// VBR o bitrate costante, dal prossimo caster
+    pub bitrate_mode: BitrateMode,
+    /// Frame rate ridotto a schermo fermo (slide, documenti)
+    pub content_aware: bool,
+    pub keycast_filter: KeycastFilter,
+    pub zoom: Zoom,
+    pub audio_encode: AudioEncodeConfig,
+    pub data_cap: DataCap,
+    /// Compromesso latenza/fluidità del receiver
+    pub latency_profile: LatencyProfile,
+    /// Nome con cui il caster si annuncia via mDNS
+    pub caster_name: String,
+    /// Passphrase opzionale per lo scambio SDP manuale (vuota = disattivata)
+    pub manual_passphrase: String,
+    /// Cartella e modello del nome per i file salvati
+    pub output: OutputSettings,
+    /// Dispositivo audio su cui il receiver riproduce
+    pub playback: PlaybackSettings,
+    /// Verbosità del log e copia su file
+    pub logging: LogSettings,
+    /// Logo composto sullo stream del caster
+    pub watermark: WatermarkSettings,
+    /// Orario impresso sullo stream del caster
+    pub timestamp: TimestampSettings,
+    /// URL notificati su stream, client e registrazioni
+    pub webhooks: WebhookSettings,
+    /// Ultimi caster a cui il receiver si è connesso
+    pub recent_casters: RecentCasters,
+    /// Invia all'altro lato il testo copiato (solo per la sessione)
+    pub clipboard_share: bool,
+    /// Applica il testo copiato dall'altro lato (solo per la sessione)
+    pub clipboard_accept: bool,
 }
 
 impl Config {
     pub fn new(flags: Flags) -> Self {
         let conf = Config {
-            shortcuts: Default::default(),
-            window_size: Size {
-                width: 680f32,
-                height: 460f32,
-            },
+            shortcuts: HotkeyMap::load(),
+            window: WindowGeometry::load(),
+            general: GeneralSettings::load(),
             e_time: 0,
             mode: None,
             public_ip: Arw::new(None),
@@ -84,6 +380,27 @@ impl Config {
             sos: SignalOfStop::new(),
             multi_instance: flags.multi_instance,
             fps: 30,
+            capture: CaptureSettings::load(),
+            stream_profile: StreamProfile::default(),
+            encode_scale: EncodeScale::default(),
+            bitrate_mode: BitrateMode::default(),
+            content_aware: false,
+            keycast_filter: KeycastFilter::default(),
+            zoom: Zoom::default(),
+            audio_encode: AudioEncodeConfig::load(),
+            data_cap: DataCap::default(),
+            latency_profile: LatencyProfile::default(),
+            caster_name: default_instance_name(),
+            manual_passphrase: String::new(),
+            output: OutputSettings::load(),
+            playback: PlaybackSettings::load(),
+            logging: LogSettings::load(),
+            watermark: WatermarkSettings::load(),
+            timestamp: TimestampSettings::load(),
+            webhooks: WebhookSettings::load(),
+            recent_casters: RecentCasters::load(),
+            clipboard_share: false,
+            clipboard_accept: false,
         };
 
         let public_ip = Arw::clone(&conf.public_ip);
@@ -101,25 +418,670 @@ impl Config {
             mode.close();
         }
     }
+
+    /// Chiusura ordinata prima di uscire: ferma il registratore (EOS al muxer),
+    /// poi chiude caster/receiver (token audio, capturer, encoder, peer).
+    /// Restituisce il task del registratore da attendere prima di `exit`.
+    pub fn shutdown(&mut self) -> Option<tokio::task::JoinHandle<()>> {
+        let recording = match &mut self.mode {
+            Some(Mode::Receiver(receiver)) => receiver.save_finish(),
+            _ => None,
+        };
+        self.reset_mode();
+        recording
+    }
 }
 
-pub fn saving_path() -> String {
-    let default_path = default_saving_path();
+// ── File di configurazione ──────────────────────────────────────
 
-    let save_p = DialogBuilder::file()
-        .set_location(&default_path)
-        .set_filename(&*Local::now().format("%Y-%m-%d_%H-%M-%S").to_string())
-        .set_title("Save")
-        .add_filter("Video", ["mp4", "mkv", "mov"])
-        .save_single_file()
-        .show()
-        .unwrap();
+/// Legge `name` dalla cartella di configurazione: file assente, illeggibile
+/// o non valido danno il default del tipo.
+pub fn load_json<T: DeserializeOwned + Default>(name: &str) -> T {
+    let Some(path) = config_file_path(name) else {
+        return T::default();
+    };
+    let Ok(content) = fs::read_to_string(&path) else {
+        return T::default();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        log::warn!("Ignoring {}: {}", path.display(), e);
+        T::default()
+    })
+}
+
+/// Scrive `value` in `name`; `what` dice nei log cosa non è stato salvato
+pub fn save_json<T: Serialize + ?Sized>(name: &str, value: &T, what: &str) {
+    let Some(path) = config_file_path(name) else {
+        log::warn!("No configuration directory, {} not saved", what);
+        return;
+    };
+    let result = serde_json::to_string_pretty(value)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| Ok(fs::write(&path, json)?));
+    if let Err(e) = result {
+        log::error!("Failed to save {} to {}: {}", what, path.display(), e);
+    }
+}
+
+// ── Generali ────────────────────────────────────────────────────
+
+const GENERAL_FILE: &str = "general.json";
+
+/// Comportamento dell'app indipendente da caster e receiver
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneralSettings {
+    /// Chiudere la finestra principale la nasconde nella tray; se spento
+    /// chiuderla esce dall'app
+    pub close_to_tray: bool,
+}
+
+impl Default for GeneralSettings {
+    fn default() -> Self {
+        GeneralSettings {
+            close_to_tray: true,
+        }
+    }
+}
+
+impl GeneralSettings {
+    pub fn load() -> Self {
+        load_json(GENERAL_FILE)
+    }
+
+    pub fn save(&self) {
+        save_json(GENERAL_FILE, self, "general settings");
+    }
+}
+
+// ── Output ──────────────────────────────────────────────────────
+
+const OUTPUT_FILE: &str = "output.json";
+
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{mode}_{date}_{time}";
+
+/// Una caduta entro questo tempo continua la registrazione nello stesso file
+const DEFAULT_RESUME_SECONDS: u32 = 30;
+
+/// Token sostituiti nel nome dei file salvati
+pub const FILENAME_TOKENS: [&str; 4] = ["{date}", "{time}", "{monitor}", "{mode}"];
+
+/// Cartella e nome dei file per registrazioni e screenshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputSettings {
+    pub directory: String,
+    pub filename_template: String,
+    /// Nuovo file ogni N minuti (0 = disattivato)
+    pub segment_minutes: u32,
+    /// Nuovo file ogni N megabyte (0 = disattivato)
+    pub segment_megabytes: u32,
+    /// Secondi di conto alla rovescia prima di stream/registrazione (0 = subito)
+    pub countdown_seconds: u32,
+    /// Dopo una caduta più lunga di così la registrazione passa a un nuovo file
+    /// (0 = nuovo file ad ogni caduta)
+    pub resume_seconds: u32,
+    /// Scrive periodicamente le statistiche dello stream su file
+    pub stats_log: bool,
+    pub stats_format: StatsFormat,
+    /// Cartella dei log statistiche, vuota = cartella di salvataggio
+    pub stats_directory: String,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        OutputSettings {
+            directory: default_saving_path(),
+            filename_template: String::from(DEFAULT_FILENAME_TEMPLATE),
+            segment_minutes: 0,
+            segment_megabytes: 0,
+            countdown_seconds: 0,
+            resume_seconds: DEFAULT_RESUME_SECONDS,
+            stats_log: false,
+            stats_format: StatsFormat::default(),
+            stats_directory: String::new(),
+        }
+    }
+}
+
+impl OutputSettings {
+    pub fn load() -> Self {
+        load_json(OUTPUT_FILE)
+    }
+
+    pub fn save(&self) {
+        save_json(OUTPUT_FILE, self, "output settings");
+    }
+
+    /// Nome del file (senza estensione) con i token espansi.
+    /// I caratteri non ammessi nei nomi di file diventano `_`.
+    pub fn file_name(&self, mode: &str, monitor: &str) -> String {
+        let template = match self.filename_template.trim() {
+            "" => DEFAULT_FILENAME_TEMPLATE,
+            template => template,
+        };
+        let now = Local::now();
+
+        let name: String = template
+            .replace("{date}", &now.format("%Y-%m-%d").to_string())
+            .replace("{time}", &now.format("%H-%M-%S").to_string())
+            .replace("{monitor}", monitor)
+            .replace("{mode}", mode)
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect();
+
+        name.trim().to_string()
+    }
+
+    /// Percorso completo del prossimo file da salvare. Un nome già usato
+    /// (modello senza `{time}`, o due salvataggi nello stesso secondo) riceve
+    /// un contatore invece di sovrascrivere il file esistente.
+    pub fn file_path(&self, mode: &str, monitor: &str, extension: &str) -> String {
+        let dir = Path::new(&self.directory);
+        let name = self.file_name(mode, monitor);
+        // Le registrazioni a segmenti partono da `<nome>_001`
+        let taken = |stem: &str| {
+            dir.join(format!("{}.{}", stem, extension)).exists()
+                || dir.join(format!("{}_001.{}", stem, extension)).exists()
+        };
+
+        let mut stem = name.clone();
+        let mut counter = 1;
+        while taken(&stem) {
+            counter += 1;
+            stem = format!("{}_{}", name, counter);
+        }
+        dir.join(format!("{}.{}", stem, extension))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub fn segment_policy(&self) -> SegmentPolicy {
+        SegmentPolicy {
+            max_duration: (self.segment_minutes > 0)
+                .then_some(Duration::from_secs(self.segment_minutes as u64 * 60)),
+            max_bytes: (self.segment_megabytes > 0)
+                .then_some(self.segment_megabytes as u64 * 1024 * 1024),
+            resume_within: (self.resume_seconds > 0)
+                .then_some(Duration::from_secs(self.resume_seconds as u64)),
+        }
+    }
 
-    if let Some(path) = save_p {
-        path.into_os_string().into_string().unwrap()
-    } else {
-        default_path
+    /// Cartella effettiva dei log statistiche
+    pub fn stats_directory(&self) -> &str {
+        match self.stats_directory.trim() {
+            "" => &self.directory,
+            directory => directory,
+        }
+    }
+
+    /// File del log statistiche per una nuova sessione, `None` se disattivato
+    pub fn stats_log_target(&self, role: &str) -> Option<StatsLogTarget> {
+        if !self.stats_log {
+            return None;
+        }
+        let name = format!(
+            "{}_stats_{}.{}",
+            role,
+            Local::now().format("%Y-%m-%d_%H-%M-%S"),
+            self.stats_format.extension()
+        );
+        Some(StatsLogTarget {
+            path: Path::new(self.stats_directory()).join(name),
+            format: self.stats_format,
+            interval: STATS_LOG_INTERVAL,
+        })
     }
+
+    /// Verifica che la cartella esista (la crea se serve) e sia scrivibile
+    pub fn check_writable(&self) -> anyhow::Result<()> {
+        let dir = Path::new(&self.directory);
+        fs::create_dir_all(dir).with_context(|| format!("cannot create {}", self.directory))?;
+        let probe = dir.join(format!(".{}_write_test", app_id()));
+        fs::write(&probe, b"").with_context(|| format!("{} is not writable", self.directory))?;
+        let _ = fs::remove_file(probe);
+        Ok(())
+    }
+}
+
+// ── Capture ─────────────────────────────────────────────────────
+
+const CAPTURE_FILE: &str = "capture.json";
+
+/// Impostazioni di cattura del caster
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureSettings {
+    /// Tetto al frame rate di cattura, limitato alla frequenza del display
+    pub max_fps: FpsCap,
+    /// Finestre mai mostrate nello stream: parte del titolo o nome della classe
+    pub exclude_windows: Vec<String>,
+    /// Tone-mapping verso SDR dei desktop HDR
+    pub hdr: HdrMode,
+    /// Checklist dei permessi già mostrata al primo avvio del caster
+    pub permissions_checked: bool,
+    /// Slice massime dell'encoder video (più basse su VPN e tunnel)
+    pub rtp_mtu: RtpMtu,
+    /// Pausa della cattura dopo questo tempo senza receiver
+    pub idle_timeout: IdleTimeout,
+    /// Secondo encoder ridotto per i receiver lenti, dal prossimo stream
+    pub simulcast: Simulcast,
+    /// Cattura sul monitor col puntatore, cambiato al volo
+    pub follow_cursor: bool,
+    /// Tempo sul nuovo monitor prima del cambio
+    pub follow_cursor_delay: FollowCursorDelay,
+    /// Anello attorno al puntatore, acceso e spento con la scorciatoia
+    pub cursor_highlight: CursorHighlight,
+    /// Puntatore tolto dallo stream, dove il backend lo permette
+    pub hide_cursor: bool,
+}
+
+impl CaptureSettings {
+    pub fn load() -> Self {
+        load_json(CAPTURE_FILE)
+    }
+
+    pub fn save(&self) {
+        save_json(CAPTURE_FILE, self, "capture settings");
+    }
+}
+
+// ── Webhooks ────────────────────────────────────────────────────
+
+const WEBHOOKS_FILE: &str = "webhooks.json";
+
+/// Endpoint HTTP che ricevono gli eventi di stream e registrazione
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookSettings {
+    pub urls: Vec<String>,
+}
+
+impl WebhookSettings {
+    pub fn load() -> Self {
+        let mut settings: WebhookSettings = load_json(WEBHOOKS_FILE);
+        settings.retain_valid();
+        settings
+    }
+
+    pub fn save(&self) {
+        let mut settings = self.clone();
+        settings.retain_valid();
+        save_json(WEBHOOKS_FILE, &settings, "webhooks");
+    }
+
+    /// Scarta gli URL che non sono webhook validi (file modificato a mano)
+    fn retain_valid(&mut self) {
+        self.urls.retain(|url| {
+            let valid = is_webhook_url(url);
+            if !valid {
+                log::warn!("Ignoring webhook URL {:?}", url);
+            }
+            valid
+        });
+    }
+}
+
+// ── Playback ────────────────────────────────────────────────────
+
+const PLAYBACK_FILE: &str = "playback.json";
+
+/// Uscita audio e video del receiver
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaybackSettings {
+    /// Nome del dispositivo cpal, `None` = default di sistema
+    pub output_device: Option<String>,
+    /// Drop aggressivo (latenza) o un frame alla volta (completezza)
+    pub display_policy: DisplayPolicy,
+    /// Correzione manuale del lip-sync: > 0 ritarda il video, < 0 l'audio
+    pub av_offset_ms: i64,
+    /// Ritardo massimo accumulato nei buffer prima di ripartire dal
+    /// keyframe successivo, 0 = disattivato
+    pub max_latency_ms: u32,
+    /// Volume dell'audio ricevuto, in percentuale (100 = invariato)
+    pub volume_percent: u32,
+    /// Frame presentati a cadenza fissa invece che all'arrivo
+    pub smooth_motion: bool,
+    /// Secondi per raggiungere il caster prima di rinunciare, 0 = senza limite
+    pub connect_timeout_secs: u32,
+    /// Matrice e range della conversione YUV → RGB (BT.709 limited come il caster)
+    pub color: ColorSpace,
+    /// Video con le bande (proporzioni originali) o stirato sulla finestra
+    pub fit: VideoFit,
+}
+
+impl Default for PlaybackSettings {
+    fn default() -> Self {
+        Self {
+            output_device: None,
+            display_policy: DisplayPolicy::default(),
+            av_offset_ms: 0,
+            max_latency_ms: DEFAULT_MAX_LATENCY_MS,
+            volume_percent: 100,
+            smooth_motion: false,
+            connect_timeout_secs: DEFAULT_CONNECT_TIMEOUT_SECS,
+            color: ColorSpace::default(),
+            fit: VideoFit::default(),
+        }
+    }
+}
+
+impl PlaybackSettings {
+    pub fn load() -> Self {
+        load_json(PLAYBACK_FILE)
+    }
+
+    pub fn save(&self) {
+        save_json(PLAYBACK_FILE, self, "playback settings");
+    }
+}
+
+// ── Log ─────────────────────────────────────────────────────────
+
+const LOG_SETTINGS_FILE: &str = "logging.json";
+
+/// Verbosità del log e copia su file
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    pub level: LogLevel,
+    /// Copia il log nella cartella `logs` della configurazione
+    pub to_file: bool,
+}
+
+impl LogSettings {
+    pub fn load() -> Self {
+        load_json(LOG_SETTINGS_FILE)
+    }
+
+    pub fn save(&self) {
+        save_json(LOG_SETTINGS_FILE, self, "log settings");
+    }
+
+    /// Applica livello e copia su file al logger già inizializzato
+    pub fn apply(&self) {
+        logging::set_level(self.level);
+        logging::set_file_enabled(self.to_file);
+    }
+}
+
+// ── Connessioni recenti ─────────────────────────────────────────
+
+const RECENT_FILE: &str = "recent.json";
+
+/// Numero massimo di indirizzi ricordati
+const MAX_RECENT_CASTERS: usize = 8;
+
+/// Indirizzi dei caster usati di recente, dal più recente
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentCasters {
+    addresses: Vec<String>,
+}
+
+impl RecentCasters {
+    pub fn load() -> Self {
+        load_json(RECENT_FILE)
+    }
+
+    pub fn save(&self) {
+        save_json(RECENT_FILE, self, "recent casters");
+    }
+
+    /// Porta l'indirizzo in cima alla lista, senza duplicati
+    pub fn remember(&mut self, address: &str) {
+        let address = address.trim();
+        if address.is_empty() {
+            return;
+        }
+        self.addresses.retain(|a| !a.eq_ignore_ascii_case(address));
+        self.addresses.insert(0, address.to_string());
+        self.addresses.truncate(MAX_RECENT_CASTERS);
+    }
+
+    pub fn clear(&mut self) {
+        self.addresses.clear();
+    }
+
+    pub fn last(&self) -> Option<&str> {
+        self.addresses.first().map(String::as_str)
+    }
+
+    pub fn addresses(&self) -> &[String] {
+        &self.addresses
+    }
+}
+
+// ── Finestra principale ─────────────────────────────────────────
+
+const WINDOW_FILE: &str = "window.json";
+
+/// La finestra principale non si restringe sotto questa dimensione
+pub const MIN_WINDOW_SIZE: Size = Size {
+    width: 680f32,
+    height: 460f32,
+};
+
+/// Dimensione e posizione della finestra principale, ripristinate
+/// all'apertura successiva
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowGeometry {
+    pub width: f32,
+    pub height: f32,
+    /// Angolo in alto a sinistra, `None` = centrata
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        WindowGeometry {
+            width: MIN_WINDOW_SIZE.width,
+            height: MIN_WINDOW_SIZE.height,
+            x: None,
+            y: None,
+        }
+    }
+}
+
+impl WindowGeometry {
+    pub fn load() -> Self {
+        load_json(WINDOW_FILE)
+    }
+
+    pub fn save(&self) {
+        save_json(WINDOW_FILE, self, "window geometry");
+    }
+
+    pub fn size(&self) -> Size {
+        Size {
+            width: self.width.max(MIN_WINDOW_SIZE.width),
+            height: self.height.max(MIN_WINDOW_SIZE.height),
+        }
+    }
+
+    pub fn set_position(&mut self, position: Point) {
+        self.x = Some(position.x);
+        self.y = Some(position.y);
+    }
+
+    /// Posizione salvata, riportata dentro il monitor su cui cade (o il
+    /// principale) se nel frattempo è finita fuori schermo
+    pub fn position(&self) -> Position {
+        let (Some(x), Some(y)) = (self.x, self.y) else {
+            return Position::Centered;
+        };
+        let monitors = Monitors::new();
+        let Some(monitor) = monitors.at_point(x, y) else {
+            return Position::Centered;
+        };
+        let (mx, my, mw, mh) = monitor.logical_rect();
+        let size = self.size();
+        Position::Specific(Point {
+            x: x.clamp(mx, (mx + mw - size.width).max(mx)),
+            y: y.clamp(my, (my + mh - size.height).max(my)),
+        })
+    }
+}
+
+// ── Watermark ───────────────────────────────────────────────────
+
+const WATERMARK_FILE: &str = "watermark.json";
+
+/// Logo del caster: percorso del PNG, angolo, opacità e scala
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatermarkSettings {
+    /// PNG da comporre, vuoto = nessun logo
+    pub path: String,
+    pub corner: WatermarkCorner,
+    /// Opacità in percentuale (0-100)
+    pub opacity: u32,
+    /// Scala rispetto alla dimensione originale, in percentuale
+    pub scale: u32,
+}
+
+impl Default for WatermarkSettings {
+    fn default() -> Self {
+        WatermarkSettings {
+            path: String::new(),
+            corner: WatermarkCorner::default(),
+            opacity: 80,
+            scale: 100,
+        }
+    }
+}
+
+impl WatermarkSettings {
+    pub fn load() -> Self {
+        load_json(WATERMARK_FILE)
+    }
+
+    pub fn save(&self) {
+        save_json(WATERMARK_FILE, self, "watermark settings");
+    }
+
+    /// Logo pronto per il capturer, `Ok(None)` se non configurato.
+    /// `image` tiene il PNG decodificato: si rilegge solo se cambia il percorso.
+    pub fn build(&self, image: &mut Option<WatermarkImage>) -> anyhow::Result<Option<Watermark>> {
+        if self.path.trim().is_empty() {
+            return Ok(None);
+        }
+        if image.as_ref().is_none_or(|image| image.path() != self.path) {
+            *image = Some(WatermarkImage::decode(&self.path)?);
+        }
+        Ok(image.as_ref().map(|image| {
+            Watermark::from_image(
+                image,
+                self.scale.max(1) as f32 / 100.0,
+                self.corner,
+                self.opacity.min(100) as f32 / 100.0,
+            )
+        }))
+    }
+}
+
+// ── Timestamp ───────────────────────────────────────────────────
+
+const TIMESTAMP_FILE: &str = "timestamp.json";
+
+/// Orario impresso sullo stream: posizione, formato, etichetta e opacità
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimestampSettings {
+    pub enabled: bool,
+    pub corner: WatermarkCorner,
+    pub format: TimestampFormat,
+    /// Testo davanti all'orario, vuoto = nessuno
+    pub label: String,
+    /// Opacità in percentuale (0-100)
+    pub opacity: u32,
+}
+
+impl Default for TimestampSettings {
+    fn default() -> Self {
+        TimestampSettings {
+            enabled: false,
+            corner: WatermarkCorner::TopRight,
+            format: TimestampFormat::default(),
+            label: String::new(),
+            opacity: 90,
+        }
+    }
+}
+
+impl TimestampSettings {
+    pub fn load() -> Self {
+        load_json(TIMESTAMP_FILE)
+    }
+
+    pub fn save(&self) {
+        save_json(TIMESTAMP_FILE, self, "timestamp settings");
+    }
+
+    /// Overlay per il capturer, `None` se disattivato
+    pub fn build(&self) -> Option<Timestamp> {
+        self.enabled.then(|| Timestamp {
+            corner: self.corner,
+            format: self.format,
+            label: self.label.trim().to_string(),
+            opacity: self.opacity.min(100) as f32 / 100.0,
+        })
+    }
+}
+
+/// Selettore del PNG usato come watermark, `None` se annullato
+pub fn pick_watermark(current: &str) -> Option<String> {
+    let location = Path::new(current)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .unwrap_or_else(default_saving_path);
+    DialogBuilder::file()
+        .set_location(&location)
+        .set_title("Watermark image")
+        .add_filter("PNG Image", ["png"])
+        .open_single_file()
+        .show()
+        .ok()
+        .flatten()
+        .and_then(|path| path.into_os_string().into_string().ok())
+}
+
+/// Selettore della cartella di salvataggio, `None` se annullato
+pub fn pick_directory(current: &str) -> Option<String> {
+    DialogBuilder::file()
+        .set_location(current)
+        .set_title("Save folder")
+        .open_single_dir()
+        .show()
+        .ok()
+        .flatten()
+        .and_then(|path| path.into_os_string().into_string().ok())
+}
+
+/// Percorso per l'export PNG delle annotazioni, `None` se il dialog viene annullato.
+pub fn image_saving_path(directory: &str) -> Option<String> {
+    DialogBuilder::file()
+        .set_location(directory)
+        .set_filename(&*format!(
+            "annotations_{}.png",
+            Local::now().format("%Y-%m-%d_%H-%M-%S")
+        ))
+        .set_title("Save annotations")
+        .add_filter("PNG Image", ["png"])
+        .save_single_file()
+        .show()
+        .ok()
+        .flatten()
+        .and_then(|path| path.into_os_string().into_string().ok())
 }
 
 pub fn app_version() -> &'static str {
@@ -3,16 +3,31 @@ use ac_ffmpeg::codec::audio::AudioDecoder;
 use ac_ffmpeg::packet::PacketMut;
 use anyhow::Result;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 
+use crate::capture::audio::pcm;
+use crate::utils::audio_level::AudioLevel;
+
 /// Maximum samples in the ring buffer (at 48kHz stereo, this is ~170ms of audio)
 /// This prevents unbounded memory growth and limits audio latency
 const MAX_BUFFER_SAMPLES: usize = 16384;
+/// Ring buffer dell'audio PCM a bassa latenza (~40ms): basta a coprire un
+/// callback del dispositivo, il resto viene scartato
+const LOW_LATENCY_BUFFER_SAMPLES: usize = 4096;
 const I16_TO_F32: f32 = 1.0 / 32768.0;
+/// Guadagno massimo del volume di riproduzione (+6 dB)
+pub const MAX_VOLUME: f32 = 2.0;
 
 pub struct AudioPlayer {
     sample_buffer: Arc<Mutex<AudioRingBuffer>>,
     decoder: AudioDecoder,
+    /// Pacchetti L16 invece di Opus, con un buffer più corto
+    low_latency: bool,
+    /// Il dispositivo è stato scollegato: va ricreato il player
+    device_lost: Arc<AtomicBool>,
+    /// Guadagno applicato nel callback di uscita (bit di un `f32`)
+    volume: Arc<AtomicU32>,
     _stream: cpal::Stream, // kept alive
 }
 
@@ -70,6 +85,52 @@ unsafe impl Send for AudioPlayer {}
 
 impl AudioPlayer {
     pub fn new() -> Result<Self> {
+        Self::with_level(AudioLevel::default())
+    }
+
+    /// Riproduce sul dispositivo indicato (nome da `list_output_devices`),
+    /// `None` per quello di sistema.
+    pub fn new_with_device(device: Option<&str>) -> Result<Self> {
+        Self::with_device(device, AudioLevel::default())
+    }
+
+    /// Come `new`, pubblicando il livello di ciò che viene effettivamente riprodotto.
+    pub fn with_level(level: AudioLevel) -> Result<Self> {
+        Self::with_device(None, level)
+    }
+
+    /// Nomi dei dispositivi di uscita disponibili
+    pub fn list_output_devices() -> Vec<String> {
+        match cpal::default_host().output_devices() {
+            Ok(devices) => devices.filter_map(|d| d.name().ok()).collect(),
+            Err(e) => {
+                log::warn!("Cannot enumerate audio output devices: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Dispositivo richiesto, o quello di default se non esiste più
+    fn output_device(host: &cpal::Host, name: Option<&str>) -> Result<cpal::Device> {
+        if let Some(name) = name {
+            let found = host
+                .output_devices()
+                .ok()
+                .and_then(|mut devices| devices.find(|d| d.name().is_ok_and(|n| n == name)));
+            match found {
+                Some(device) => return Ok(device),
+                None => log::warn!(
+                    "Audio output device '{}' not found, falling back to default",
+                    name
+                ),
+            }
+        }
+        host.default_output_device()
+            .ok_or_else(|| anyhow::anyhow!("No output audio device"))
+    }
+
+    /// Dispositivo scelto (`None` = default) con VU meter.
+    pub fn with_device(device: Option<&str>, level: AudioLevel) -> Result<Self> {
         let decoder = AudioDecoder::new("libopus").or_else(|e| {
             log::warn!(
                 "libopus decoder not available ({}), trying built-in opus decoder",
@@ -79,9 +140,7 @@ impl AudioPlayer {
         })?;
 
         let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .ok_or_else(|| anyhow::anyhow!("No output audio device"))?;
+        let device = Self::output_device(&host, device)?;
         let config = cpal::StreamConfig {
             channels: 2,
             sample_rate: 48000,
@@ -90,6 +149,10 @@ impl AudioPlayer {
 
         let sample_buffer = Arc::new(Mutex::new(AudioRingBuffer::new(MAX_BUFFER_SAMPLES)));
         let buffer_clone = Arc::clone(&sample_buffer);
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let device_lost_cb = Arc::clone(&device_lost);
+        let volume = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+        let volume_cb = Arc::clone(&volume);
 
         let stream = device.build_output_stream(
             config,
@@ -99,8 +162,15 @@ impl AudioPlayer {
                 } else {
                     output.fill(0.0);
                 }
+                apply_gain(output, f32::from_bits(volume_cb.load(Ordering::Relaxed)));
+                level.update(output);
+            },
+            move |err| {
+                log::error!("Audio output error: {}", err);
+                if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+                    device_lost_cb.store(true, Ordering::Relaxed);
+                }
             },
-            |err| log::error!("Audio output error: {}", err),
             None,
         )?;
         stream.play()?;
@@ -108,18 +178,57 @@ impl AudioPlayer {
         Ok(Self {
             sample_buffer,
             decoder,
+            low_latency: false,
+            device_lost,
+            volume,
             _stream: stream,
         })
     }
 
-    pub fn play(&mut self, opus_data: &[u8]) -> Result<()> {
-        let packet = PacketMut::from(opus_data).freeze();
+    /// Il dispositivo di uscita non è più disponibile
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed)
+    }
+
+    /// Guadagno dell'uscita, da 0 (silenzio) a `MAX_VOLUME`; effettivo dal
+    /// prossimo callback del dispositivo
+    pub fn set_volume(&self, volume: f32) {
+        let volume = volume.clamp(0.0, MAX_VOLUME);
+        self.volume.store(volume.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Passa all'audio PCM del caster (o torna a Opus); svuota il buffer
+    pub fn set_low_latency(&mut self, low_latency: bool) {
+        if self.low_latency == low_latency {
+            return;
+        }
+        self.low_latency = low_latency;
+        let capacity = if low_latency {
+            LOW_LATENCY_BUFFER_SAMPLES
+        } else {
+            MAX_BUFFER_SAMPLES
+        };
+        if let Ok(mut buf) = self.sample_buffer.lock() {
+            *buf = AudioRingBuffer::new(capacity);
+        }
+    }
+
+    pub fn play(&mut self, data: &[u8]) -> Result<()> {
+        if self.low_latency {
+            let mut samples = Vec::with_capacity(data.len() / 2);
+            pcm::decode_l16(data, &mut samples);
+            if let Ok(mut buf) = self.sample_buffer.lock() {
+                buf.push(&samples);
+            }
+            return Ok(());
+        }
+        let packet = PacketMut::from(data).freeze();
         match self.decoder.try_push(packet) {
             Ok(()) => {}
             Err(e) => {
                 if e.is_again() {
                     self.drain_frames();
-                    let retry = PacketMut::from(opus_data).freeze();
+                    let retry = PacketMut::from(data).freeze();
                     if let Err(e) = self.decoder.try_push(retry) {
                         log::warn!("Audio decode retry failed: {}", e);
                     }
@@ -175,6 +284,16 @@ impl AudioPlayer {
     }
 }
 
+/// Scala i campioni tagliandoli a [-1, 1], così un guadagno > 1 non va in clipping
+fn apply_gain(samples: &mut [f32], gain: f32) {
+    if gain == 1.0 {
+        return;
+    }
+    for sample in samples.iter_mut() {
+        *sample = (*sample * gain).clamp(-1.0, 1.0);
+    }
+}
+
 fn append_planar_stereo(
     out: &mut Vec<f32>,
     left: &[u8],
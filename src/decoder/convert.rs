@@ -0,0 +1,98 @@
+//! Packed RGBA copies of a decoded frame.
+//!
+//! Screenshots and frame export want packed RGB, the renderer keeps the
+//! decoder's YUV planes: the conversion happens only where it is needed.
+
+use super::{PixelLayout, i420_len};
+use crate::capture::ColorSpace;
+
+/// Convert a tightly packed `width`×`height` frame to RGBA with opaque alpha.
+/// `color` is the matrix and range of the YUV data.
+pub fn to_rgba(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    layout: PixelLayout,
+    color: ColorSpace,
+) -> Vec<u8> {
+    match layout {
+        PixelLayout::I420 => i420_to_rgba(data, width, height, color),
+        PixelLayout::Nv12 => i420_to_rgba(&nv12_to_i420(data, width, height), width, height, color),
+    }
+}
+
+/// Split the interleaved UV plane of an NV12 frame into U and V planes
+fn nv12_to_i420(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let y_size = width * height;
+    let uv_size = width.div_ceil(2) * height.div_ceil(2);
+    let mut out = Vec::with_capacity(i420_len(width, height));
+    out.extend_from_slice(&data[..y_size]);
+    let uv = &data[y_size..y_size + uv_size * 2];
+    out.extend(uv.iter().step_by(2));
+    out.extend(uv.iter().skip(1).step_by(2));
+    out
+}
+
+/// I420 → RGBA, same math as the shader.
+fn i420_to_rgba(data: &[u8], width: usize, height: usize, color: ColorSpace) -> Vec<u8> {
+    let k = color.yuv_to_rgb();
+    let (y_plane, chroma) = data.split_at(width * height);
+    let chroma_w = width.div_ceil(2);
+    let (u_plane, v_plane) = chroma.split_at(chroma_w * height.div_ceil(2));
+
+    let mut out = vec![255u8; width * height * 4];
+    for row in 0..height {
+        for col in 0..width {
+            let c = (row / 2) * chroma_w + col / 2;
+            let rgb = k.convert(
+                y_plane[row * width + col] as f32 / 255.0,
+                u_plane[c] as f32 / 255.0,
+                v_plane[c] as f32 / 255.0,
+            );
+            let pixel = &mut out[(row * width + col) * 4..][..3];
+            for (channel, value) in pixel.iter_mut().zip(rgb) {
+                *channel = (value * 255.0).round() as u8;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nv12_and_i420_give_the_same_picture() {
+        // 3x3: chroma planes round odd sizes up
+        let color = ColorSpace::CAPTURE;
+        let i420: Vec<u8> = (0..i420_len(3, 3) as u8).map(|v| v * 9).collect();
+        let mut nv12 = i420[..9].to_vec();
+        nv12.extend(
+            i420[9..13]
+                .iter()
+                .zip(&i420[13..17])
+                .flat_map(|(&u, &v)| [u, v]),
+        );
+        assert_eq!(
+            to_rgba(&nv12, 3, 3, PixelLayout::Nv12, color),
+            to_rgba(&i420, 3, 3, PixelLayout::I420, color)
+        );
+    }
+
+    #[test]
+    fn limited_range_black_and_white_are_full_scale() {
+        let color = ColorSpace::CAPTURE;
+        for (y, expected) in [(16, 0), (235, 255)] {
+            let i420 = [y, y, y, y, 128, 128];
+            for pixel in to_rgba(&i420, 2, 2, PixelLayout::I420, color).chunks_exact(4) {
+                assert!(
+                    pixel[..3].iter().all(|v| v.abs_diff(expected) <= 1),
+                    "{:?}",
+                    pixel
+                );
+                assert_eq!(pixel[3], 255);
+            }
+        }
+    }
+}
@@ -161,3 +161,41 @@ fn contains_nal_type(data: &[u8], target_type: u8) -> bool {
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::sender::packetizer::{RtpMtu, packetize};
+
+    /// SPS, PPS e un IDR di 3000 byte: a ogni MTU l'IDR va in più FU-A
+    fn keyframe() -> Vec<u8> {
+        let sps = vec![0x67, 0x42, 0x00, 0x1f];
+        let pps = vec![0x68, 0xce, 0x3c, 0x80];
+        let idr: Vec<u8> = std::iter::once(0x65)
+            .chain((0..3000u32).map(|i| (i % 251) as u8 | 1))
+            .collect();
+        [sps, pps, idr]
+            .iter()
+            .flat_map(|nal| START_CODE.into_iter().chain(nal.iter().copied()))
+            .collect()
+    }
+
+    #[test]
+    fn test_multi_fragment_keyframe_roundtrip() {
+        let au = keyframe();
+        for mtu in RtpMtu::PRESETS.map(|mtu| mtu.bytes()) {
+            let packets = packetize(&au, mtu);
+            assert!(packets.iter().all(|(p, _)| p.len() <= mtu));
+            assert!(packets.iter().filter(|(p, _)| p[0] & 0x1F == 28).count() > 2);
+            assert_eq!(packets.iter().filter(|(_, marker)| *marker).count(), 1);
+            assert!(packets.last().unwrap().1);
+
+            let mut depacketizer = H264Depacketizer::new();
+            let mut out = None;
+            for (payload, marker) in &packets {
+                out = depacketizer.push(payload, *marker);
+            }
+            assert_eq!(out, Some(au.clone()), "MTU {}", mtu);
+        }
+    }
+}
@@ -1,48 +1,117 @@
 use ac_ffmpeg::codec::Decoder;
 use ac_ffmpeg::codec::video::VideoDecoder;
+use ac_ffmpeg::codec::video::frame::{PixelFormat, get_pixel_format};
 use ac_ffmpeg::packet::PacketMut;
 use ac_ffmpeg::time::{TimeBase, Timestamp};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{PixelLayout, i420_len};
+
+/// Decoder fallback chain: hardware decoders first, then FFmpeg's software
+/// decoder, mirroring the encoder chain. Every entry hands frames back in
+/// system memory (NV12 for the hardware ones), so the render path is the
+/// same whatever is picked. Decoders missing from the FFmpeg build, or
+/// without a matching GPU, fail to open and are skipped.
+const DECODER_CHAIN: &[(&str, &str)] = &[
+    ("h264_cuvid", "NVDEC"),
+    ("h264_qsv", "Quick Sync"),
+    ("h264_v4l2m2m", "V4L2"),
+    ("h264", "Software"),
+];
+
+/// First chain entry worth trying: hardware decoders that open but never
+/// produce a frame are skipped for the rest of the process
+static CHAIN_START: AtomicUsize = AtomicUsize::new(0);
 
 /// H.264 video decoder using FFmpeg.
 ///
 /// # Performance Optimizations
 /// - Pre-allocated output buffer reused across frames
 /// - Optimized YUV plane extraction with fast paths
-/// - Hardware acceleration via DXVA2/D3D11VA on Windows, VAAPI on Linux, VideoToolbox on macOS
+/// - Hardware decoding (NVDEC, Quick Sync, V4L2) when available, see [`DECODER_CHAIN`]
 pub struct FfmpegDecoder {
     decoder: VideoDecoder,
+    /// Position in [`DECODER_CHAIN`]
+    chain_index: usize,
+    /// At least one frame came out of this decoder
+    produced: bool,
     frame_count: i64,
     /// Reusable buffer for packed YUV output to avoid per-frame allocation
     packed_buffer: Vec<u8>,
     /// Cached dimensions for buffer reuse
     cached_dims: Option<(usize, usize)>,
+    /// Layout of the last decoded frame
+    layout: PixelLayout,
+    nv12_format: PixelFormat,
 }
 
 unsafe impl Send for FfmpegDecoder {}
 
 impl FfmpegDecoder {
-    /// Create a new H.264 decoder.
-    ///
-    /// The decoder will automatically use hardware acceleration when available:
-    /// - Windows: DXVA2 or D3D11VA
-    /// - macOS: VideoToolbox
-    /// - Linux: VAAPI or VDPAU
+    /// Create a new H.264 decoder: the first entry of [`DECODER_CHAIN`]
+    /// that opens, falling back to software decoding.
     pub fn new() -> Result<Self, ac_ffmpeg::Error> {
-        let decoder = VideoDecoder::builder("h264")?
+        let start = CHAIN_START
+            .load(Ordering::Relaxed)
+            .min(DECODER_CHAIN.len() - 1);
+        let mut last_error = None;
+        for (index, (codec, label)) in DECODER_CHAIN.iter().enumerate().skip(start) {
+            match Self::open(codec) {
+                Ok(decoder) => {
+                    log::info!("Decoder: using {} ({})", codec, label);
+                    return Ok(Self::with_decoder(decoder, index));
+                }
+                Err(e) => {
+                    log::debug!("Decoder {} skipped: {}", codec, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.expect("decoder chain is not empty"))
+    }
+
+    fn open(codec: &str) -> Result<VideoDecoder, ac_ffmpeg::Error> {
+        VideoDecoder::builder(codec)?
             .time_base(TimeBase::new(1, 90_000))
-            .build()?;
+            .build()
+    }
 
-        Ok(Self {
+    fn with_decoder(decoder: VideoDecoder, chain_index: usize) -> Self {
+        Self {
             decoder,
+            chain_index,
+            produced: false,
             frame_count: 0,
             packed_buffer: Vec::new(),
             cached_dims: None,
-        })
+            layout: PixelLayout::I420,
+            nv12_format: get_pixel_format("nv12"),
+        }
+    }
+
+    /// Short name of the active decoder, shown by the metrics overlay
+    pub fn label(&self) -> &'static str {
+        DECODER_CHAIN[self.chain_index].1
     }
 
-    /// Decode an H.264 access unit (Annex B) and return packed YUV420p plane data.
-    /// The returned Vec contains Y plane (w*h) + U plane (w/2 * h/2) + V plane (w/2 * h/2)
-    /// contiguously, with stride-padding stripped.
+    /// Called before the decoder is recreated after a run of failures: a
+    /// hardware decoder that never produced a frame is skipped from now on,
+    /// so the next one in the chain gets its turn. Returns true if it was.
+    pub fn skip_if_unusable(&self) -> bool {
+        if self.produced || self.chain_index + 1 >= DECODER_CHAIN.len() {
+            return false;
+        }
+        log::warn!(
+            "Decoder {} produced no frames, falling back to the next decoder",
+            DECODER_CHAIN[self.chain_index].0
+        );
+        CHAIN_START.fetch_max(self.chain_index + 1, Ordering::Relaxed);
+        true
+    }
+
+    /// Decode an H.264 access unit (Annex B) and return packed plane data,
+    /// with stride-padding stripped. The layout (see [`Self::layout`]) is I420
+    /// or NV12 depending on the decoder.
     ///
     /// # Performance
     /// - Reuses internal buffer to avoid allocations
@@ -59,24 +128,54 @@ impl FfmpegDecoder {
 
         match self.decoder.take() {
             Ok(Some(frame)) => {
+                self.produced = true;
                 let w = frame.width();
                 let h = frame.height();
                 let planes = frame.planes();
-                let (y_d, u_d, v_d) = (planes[0].data(), planes[1].data(), planes[2].data());
-                let (y_s, u_s, v_s) = (
-                    planes[0].line_size(),
-                    planes[1].line_size(),
-                    planes[2].line_size(),
-                );
-                let (uw, uh) = (w / 2, h / 2);
-                let total = w * h + uw * uh * 2;
+                let (uw, uh) = (w.div_ceil(2), h.div_ceil(2));
 
                 // Reuse buffer if dimensions match, otherwise reallocate
                 if self.cached_dims != Some((w, h)) {
-                    self.packed_buffer.resize(total, 0);
+                    self.packed_buffer.resize(i420_len(w, h), 0);
                     self.cached_dims = Some((w, h));
                 }
 
+                // NV12 (hardware decoders) is kept as is: the shader reads the
+                // interleaved UV plane directly
+                if frame.pixel_format() == self.nv12_format {
+                    let y_size = w * h;
+                    extract_plane(
+                        &mut self.packed_buffer[..y_size],
+                        planes[0].data(),
+                        planes[0].line_size(),
+                        w,
+                        h,
+                    );
+                    extract_plane(
+                        &mut self.packed_buffer[y_size..],
+                        planes[1].data(),
+                        planes[1].line_size(),
+                        uw * 2,
+                        uh,
+                    );
+                    self.layout = PixelLayout::Nv12;
+                    return Some((self.packed_buffer.clone(), w, h));
+                }
+
+                // Qualsiasi altro formato deve essere planare 4:2:0
+                if planes.len() < 3 {
+                    log::warn!(
+                        "Decoder: unsupported pixel format from {}",
+                        DECODER_CHAIN[self.chain_index].0
+                    );
+                    return None;
+                }
+                let (y_d, u_d, v_d) = (planes[0].data(), planes[1].data(), planes[2].data());
+                let (y_s, u_s, v_s) = (
+                    planes[0].line_size(),
+                    planes[1].line_size(),
+                    planes[2].line_size(),
+                );
                 pack_yuv420(
                     &mut self.packed_buffer,
                     Plane {
@@ -99,6 +198,8 @@ impl FfmpegDecoder {
                     },
                 );
 
+                self.layout = PixelLayout::I420;
+
                 // Return a clone of the buffer (needed for ownership)
                 // This is still faster than allocating a new Vec each time
                 Some((self.packed_buffer.clone(), w, h))
@@ -111,6 +212,11 @@ impl FfmpegDecoder {
         }
     }
 
+    /// Layout of the frame returned by the last successful `decode`
+    pub fn layout(&self) -> PixelLayout {
+        self.layout
+    }
+
     #[inline]
     fn next_pts(&mut self) -> Timestamp {
         self.frame_count += 1;
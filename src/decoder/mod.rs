@@ -2,20 +2,56 @@
 //!
 //! Provides H.264 video decoding via FFmpeg and audio playback via cpal.
 
+use crate::capture::ColorSpace;
+use convert::to_rgba;
+
+mod convert;
 mod depacketizer;
 mod ffmpeg;
 
 pub mod audio;
 
-/// Decoded video frame with raw pixel data.
+/// Plane layout of a decoded frame. Both are 4:2:0 and have the same size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PixelLayout {
+    /// Y, U and V planes one after the other (software decoder output)
+    #[default]
+    I420,
+    /// Y plane followed by interleaved UV (hardware decoder output)
+    Nv12,
+}
+
+/// Decoded video frame with raw pixel data, tagged with its layout.
 #[derive(Debug, Clone)]
 pub struct VideoFrame {
     pub data: Vec<u8>,
     pub width: u32,
     pub height: u32,
+    pub layout: PixelLayout,
+}
+
+impl VideoFrame {
+    /// Packed RGBA copy of the frame (screenshots, frame export), converted
+    /// with `color`, the matrix and range the caster encoded with.
+    pub fn to_rgba(&self, color: ColorSpace) -> Vec<u8> {
+        to_rgba(
+            &self.data,
+            self.width as usize,
+            self.height as usize,
+            self.layout,
+            color,
+        )
+    }
+}
+
+/// Size in bytes of a tightly packed I420 frame.
+///
+/// Chroma planes round odd dimensions up, as FFmpeg does.
+pub fn i420_len(width: usize, height: usize) -> usize {
+    width * height + width.div_ceil(2) * height.div_ceil(2) * 2
 }
 
-pub use audio::AudioPlayer;
+pub use audio::{AudioPlayer, MAX_VOLUME};
 pub use depacketizer::H264Depacketizer;
 pub use ffmpeg::FfmpegDecoder;
 
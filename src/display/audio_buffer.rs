@@ -2,8 +2,39 @@
 //!
 //! Provides a lock-free ring buffer that can absorb jitter in audio
 //! sample delivery, preventing underruns and overruns.
+//!
+//! Underruns (reader finds too few samples) and overruns (writer finds no
+//! room) are counted. With a [`ResyncConfig`] the reader also corrects them:
+//! excess latency is trimmed by dropping the oldest samples, and after an
+//! underrun playback waits in silence until a small cushion is buffered again
+//! instead of stuttering sample by sample. Only the reader moves `read_pos`,
+//! so both corrections keep the single-producer/single-consumer invariants.
+
+use crate::pipeline::health::PipelineHealth;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+/// Drift correction for [`AudioRingBuffer`] (all values in samples)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResyncConfig {
+    /// Buffered samples above which the reader drops the oldest ones
+    pub max_latency: usize,
+    /// Buffered samples kept after an overrun resync
+    pub target_latency: usize,
+    /// Samples to buffer again before resuming after an underrun
+    pub underrun_cushion: usize,
+}
 
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+impl ResyncConfig {
+    /// Stereo 48 kHz: resync above 150ms back to 60ms, 20ms cushion
+    pub fn stereo_48k() -> Self {
+        Self {
+            max_latency: 48_000 * 2 * 150 / 1000,
+            target_latency: 48_000 * 2 * 60 / 1000,
+            underrun_cushion: 48_000 * 2 * 20 / 1000,
+        }
+    }
+}
 
 /// Ring buffer for audio samples with jitter compensation
 ///
@@ -24,6 +55,16 @@ pub struct AudioRingBuffer {
     capacity: usize,
     /// Whether the buffer has data available
     has_data: AtomicBool,
+    /// Reads that found fewer samples than requested (after playback started)
+    underruns: AtomicU64,
+    /// Writes that had to drop samples, plus latency resyncs
+    overruns: AtomicU64,
+    /// Set by the reader once it has played something
+    started: AtomicBool,
+    /// Reader is waiting for the underrun cushion (resync only)
+    refilling: AtomicBool,
+    resync: Option<ResyncConfig>,
+    health: Option<Arc<PipelineHealth>>,
 }
 
 // Safety: AudioRingBuffer can be shared between threads
@@ -42,6 +83,38 @@ impl AudioRingBuffer {
             read_pos: AtomicUsize::new(0),
             capacity,
             has_data: AtomicBool::new(false),
+            underruns: AtomicU64::new(0),
+            overruns: AtomicU64::new(0),
+            started: AtomicBool::new(false),
+            refilling: AtomicBool::new(false),
+            resync: None,
+            health: None,
+        }
+    }
+
+    /// Enable drift correction on underrun/overrun
+    pub fn with_resync(mut self, config: ResyncConfig) -> Self {
+        self.resync = Some(config);
+        self
+    }
+
+    /// Also report underruns/overruns to the pipeline health metrics
+    pub fn with_health(mut self, health: Arc<PipelineHealth>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    fn record_underrun(&self) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+        if let Some(health) = &self.health {
+            health.record_audio_underrun();
+        }
+    }
+
+    fn record_overrun(&self) {
+        self.overruns.fetch_add(1, Ordering::Relaxed);
+        if let Some(health) = &self.health {
+            health.record_audio_overrun();
         }
     }
 
@@ -61,6 +134,9 @@ impl AudioRingBuffer {
         };
 
         let to_write = samples.len().min(available);
+        if to_write < samples.len() {
+            self.record_overrun();
+        }
         if to_write == 0 {
             return 0;
         }
@@ -89,16 +165,45 @@ impl AudioRingBuffer {
     /// Returns the number of actual samples read (not silence).
     pub fn read(&self, output: &mut [f32]) -> usize {
         let write = self.write_pos.load(Ordering::Acquire);
-        let read = self.read_pos.load(Ordering::Relaxed);
+        let mut read = self.read_pos.load(Ordering::Relaxed);
 
         // Available samples
-        let available = if write >= read {
+        let mut available = if write >= read {
             write - read
         } else {
             self.capacity - read + write
         };
 
+        if let Some(resync) = self.resync {
+            // Overrun: too much latency accumulated, skip the oldest samples
+            if available > resync.max_latency {
+                let skip = available - resync.target_latency.min(available);
+                read = (read + skip) % self.capacity;
+                available -= skip;
+                self.read_pos.store(read, Ordering::Release);
+                self.record_overrun();
+            }
+
+            // After an underrun stay silent until the cushion is rebuilt
+            if self.refilling.load(Ordering::Relaxed) {
+                if available < resync.underrun_cushion.max(output.len()) {
+                    output.fill(0.0);
+                    return 0;
+                }
+                self.refilling.store(false, Ordering::Relaxed);
+            }
+        }
+
         let to_read = output.len().min(available);
+        if to_read < output.len() && self.started.load(Ordering::Relaxed) {
+            self.record_underrun();
+            if self.resync.is_some() {
+                self.refilling.store(true, Ordering::Relaxed);
+            }
+        }
+        if to_read > 0 {
+            self.started.store(true, Ordering::Relaxed);
+        }
 
         // Read samples
         for (i, sample) in output.iter_mut().enumerate().take(to_read) {
@@ -149,11 +254,23 @@ impl AudioRingBuffer {
         self.available() as f32 / self.capacity as f32
     }
 
+    /// Number of underruns since creation
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Number of overruns (dropped writes or latency resyncs) since creation
+    pub fn overruns(&self) -> u64 {
+        self.overruns.load(Ordering::Relaxed)
+    }
+
     /// Reset the buffer (clear all data)
     pub fn reset(&self) {
         self.write_pos.store(0, Ordering::Release);
         self.read_pos.store(0, Ordering::Release);
         self.has_data.store(false, Ordering::Release);
+        self.started.store(false, Ordering::Relaxed);
+        self.refilling.store(false, Ordering::Relaxed);
     }
 }
 
@@ -196,6 +313,77 @@ mod tests {
         assert!(written < 6); // Not all samples fit
     }
 
+    #[test]
+    fn test_underrun_overrun_counters() {
+        let buf = AudioRingBuffer::new(4);
+
+        // Reading before anything was played is not an underrun
+        let mut output = [0.0f32; 2];
+        buf.read(&mut output);
+        assert_eq!(buf.underruns(), 0);
+
+        assert_eq!(buf.write(&[1.0, 2.0, 3.0, 4.0]), 3);
+        assert_eq!(buf.overruns(), 1);
+
+        assert_eq!(buf.read(&mut output), 2);
+        assert_eq!(buf.underruns(), 0);
+
+        // Playing and running dry: underrun
+        assert_eq!(buf.read(&mut output), 1);
+        assert_eq!(buf.underruns(), 1);
+    }
+
+    #[test]
+    fn test_resync_trims_latency() {
+        let health = Arc::new(PipelineHealth::new());
+        let buf = AudioRingBuffer::new(64)
+            .with_resync(ResyncConfig {
+                max_latency: 16,
+                target_latency: 4,
+                underrun_cushion: 4,
+            })
+            .with_health(health.clone());
+
+        let samples: Vec<f32> = (0..20).map(|i| i as f32).collect();
+        buf.write(&samples);
+
+        // The oldest 16 samples are dropped, leaving the newest 4
+        let mut output = [0.0f32; 2];
+        assert_eq!(buf.read(&mut output), 2);
+        assert_eq!(output, [16.0, 17.0]);
+        assert_eq!(buf.overruns(), 1);
+        assert_eq!(health.audio_overruns(), 1);
+    }
+
+    #[test]
+    fn test_resync_silence_after_underrun() {
+        let buf = AudioRingBuffer::new(64).with_resync(ResyncConfig {
+            max_latency: 48,
+            target_latency: 16,
+            underrun_cushion: 8,
+        });
+
+        let mut output = [0.0f32; 4];
+        buf.write(&[0.5; 4]);
+        assert_eq!(buf.read(&mut output), 4);
+
+        buf.write(&[1.0, 2.0]);
+        assert_eq!(buf.read(&mut output), 2);
+        assert_eq!(buf.underruns(), 1);
+
+        // Below the cushion: silence, nothing consumed
+        buf.write(&[3.0, 4.0, 5.0]);
+        assert_eq!(buf.read(&mut output), 0);
+        assert_eq!(output, [0.0; 4]);
+        assert_eq!(buf.available(), 3);
+
+        // Cushion rebuilt: playback resumes in order
+        buf.write(&[6.0, 7.0, 8.0, 9.0, 10.0]);
+        assert_eq!(buf.read(&mut output), 4);
+        assert_eq!(output, [3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(buf.underruns(), 1);
+    }
+
     #[test]
     fn test_wrap_around() {
         let buf = AudioRingBuffer::new(8);
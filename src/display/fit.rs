@@ -0,0 +1,76 @@
+//! How the received video fills the player area
+//!
+//! The caster's aspect ratio rarely matches the receiver window. `Fit`
+//! keeps the picture undistorted and leaves bars on the sides that don't
+//! match (letterbox/pillarbox); `Fill` stretches it over the whole area.
+
+use iced::Rectangle;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VideoFit {
+    /// Keep the aspect ratio, bars where the area is wider or taller
+    #[default]
+    Fit,
+    /// Stretch to the whole area
+    Fill,
+}
+
+impl VideoFit {
+    pub fn toggled(self) -> Self {
+        match self {
+            VideoFit::Fit => VideoFit::Fill,
+            VideoFit::Fill => VideoFit::Fit,
+        }
+    }
+
+    /// Part of `bounds` covered by a `width`×`height` frame, centered.
+    /// Without a frame size yet the whole area is used.
+    pub fn inner_rect(self, bounds: Rectangle, (width, height): (i32, i32)) -> Rectangle {
+        if self == VideoFit::Fill || width <= 0 || height <= 0 || bounds.height <= 0.0 {
+            return bounds;
+        }
+
+        let video_aspect = width as f32 / height as f32;
+        let (w, h) = if bounds.width / bounds.height > video_aspect {
+            (bounds.height * video_aspect, bounds.height)
+        } else {
+            (bounds.width, bounds.width / video_aspect)
+        };
+        Rectangle {
+            x: bounds.x + (bounds.width - w) / 2.0,
+            y: bounds.y + (bounds.height - h) / 2.0,
+            width: w,
+            height: h,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::{Point, Size};
+
+    #[test]
+    fn fit_centers_the_frame_inside_the_bounds() {
+        let bounds = Rectangle::new(Point::new(10.0, 20.0), Size::new(400.0, 100.0));
+
+        // 16:9 in an area wider than the frame: pillarbox
+        let inner = VideoFit::Fit.inner_rect(bounds, (1920, 1080));
+        assert!((inner.height - 100.0).abs() < 1e-3);
+        assert!((inner.width - 100.0 * 16.0 / 9.0).abs() < 1e-3);
+        assert!((inner.x - (10.0 + (400.0 - inner.width) / 2.0)).abs() < 1e-3);
+        assert_eq!(inner.y, 20.0);
+
+        // 4:1 in a square area: letterbox
+        let square = Rectangle::new(Point::ORIGIN, Size::new(200.0, 200.0));
+        let inner = VideoFit::Fit.inner_rect(square, (400, 100));
+        assert_eq!(
+            inner,
+            Rectangle::new(Point::new(0.0, 75.0), Size::new(200.0, 50.0))
+        );
+
+        assert_eq!(VideoFit::Fill.inner_rect(bounds, (1920, 1080)), bounds);
+        assert_eq!(VideoFit::Fit.inner_rect(bounds, (0, 0)), bounds);
+    }
+}
@@ -1,7 +1,11 @@
 //! Display components for lock-free video rendering
 
 pub mod audio_buffer;
+pub mod fit;
+pub mod policy;
 pub mod video_buffer;
 
 pub use audio_buffer::AudioRingBuffer;
-pub use video_buffer::TripleBuffer;
+pub use fit::VideoFit;
+pub use policy::DisplayPolicy;
+pub use video_buffer::{Delivery, FrameDelivery, TripleBuffer};
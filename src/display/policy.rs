@@ -0,0 +1,46 @@
+//! Frame dropping policy between the decoder and the renderer
+//!
+//! The renderer uploads at most one frame per redraw, so when decoding
+//! outpaces the GPU something has to give. `Latency` always shows the newest
+//! frame and drops the superseded ones; `Completeness` holds each frame until
+//! the renderer has picked it up, accepting some delay to skip fewer frames.
+//! Dropped frames are counted as frame drops in `PipelineHealth`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// Longest a frame waits for the renderer under `Completeness` before the
+/// next one replaces it anyway (e.g. the window is minimized)
+pub const COMPLETENESS_MAX_WAIT: Duration = Duration::from_millis(100);
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DisplayPolicy {
+    /// Show the newest frame, drop anything older
+    #[default]
+    Latency = 0,
+    /// Show every frame the renderer can keep up with, in order
+    Completeness = 1,
+}
+
+impl DisplayPolicy {
+    pub const ALL: [DisplayPolicy; 2] = [DisplayPolicy::Latency, DisplayPolicy::Completeness];
+
+    /// Decode a value stored with `as u8` (unknown values map to the default)
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => DisplayPolicy::Completeness,
+            _ => DisplayPolicy::Latency,
+        }
+    }
+}
+
+impl fmt::Display for DisplayPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DisplayPolicy::Latency => "Prefer latency",
+            DisplayPolicy::Completeness => "Prefer completeness",
+        })
+    }
+}
@@ -27,9 +27,17 @@
 //!
 //! The atomic operations ensure that swaps are visible across threads and that
 //! no data races can occur.
+//!
+//! # Generations
+//!
+//! Every commit is stamped with a monotonically increasing generation (the
+//! first commit is 1, 0 means "never written"). The stamp travels with the
+//! buffer, so a [`ReadGuard`] knows exactly which commit it is showing and the
+//! renderer can tell a repeated frame from a new one and count the commits it
+//! never saw (see [`FrameDelivery`]).
 
 use std::cell::UnsafeCell;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 
 /// Lock-free triple buffer for passing data between a single writer and single reader
 ///
@@ -74,6 +82,13 @@ pub struct TripleBuffer<T> {
 
     /// Flag indicating a new frame is available
     has_new: AtomicBool,
+
+    /// Generation of the commit held by each buffer (written only by the
+    /// writer while it owns the buffer, published by the `ready_idx` swap)
+    generations: [AtomicU64; 3],
+
+    /// Generation of the most recent commit
+    latest_generation: AtomicU64,
 }
 
 // Safety: TripleBuffer can be sent between threads if T can be sent
@@ -107,6 +122,8 @@ impl<T> TripleBuffer<T> {
             ready_idx: AtomicUsize::new(1),
             read_idx: AtomicUsize::new(2),
             has_new: AtomicBool::new(false),
+            generations: [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)],
+            latest_generation: AtomicU64::new(0),
         }
     }
 
@@ -147,6 +164,14 @@ impl<T> TripleBuffer<T> {
         self.has_new.load(Ordering::Acquire)
     }
 
+    /// Generation of the latest commit (0 if nothing was written yet)
+    ///
+    /// Cheap to poll: compare it with [`ReadGuard::generation`] to know
+    /// whether a `read()` would return a different frame.
+    pub fn latest_generation(&self) -> u64 {
+        self.latest_generation.load(Ordering::Acquire)
+    }
+
     /// Get a buffer by index (unsafe, for internal use)
     ///
     /// # Safety
@@ -175,12 +200,18 @@ impl<T> TripleBuffer<T> {
 
     /// Commit the write buffer, making it available to the reader
     fn commit_write(&self) {
-        // Swap write and ready buffers
+        // Stamp the buffer before publishing it: the AcqRel swap below makes
+        // the generation visible together with the data
         let write_idx = self.write_idx.load(Ordering::Relaxed);
+        let generation = self.latest_generation.load(Ordering::Relaxed) + 1;
+        self.generations[write_idx].store(generation, Ordering::Relaxed);
+
+        // Swap write and ready buffers
         let ready_idx = self.ready_idx.swap(write_idx, Ordering::AcqRel);
         self.write_idx.store(ready_idx, Ordering::Release);
 
         // Signal that a new frame is available
+        self.latest_generation.store(generation, Ordering::Release);
         self.has_new.store(true, Ordering::Release);
     }
 }
@@ -250,6 +281,75 @@ impl<'a, T> ReadGuard<'a, T> {
         // Safety: ReadGuard has shared access to the read buffer at buffer_idx
         unsafe { self.buffer.get_buffer(self.buffer_idx) }
     }
+
+    /// Generation of the commit being read (0 if nothing was written yet)
+    pub fn generation(&self) -> u64 {
+        self.buffer.generations[self.buffer_idx].load(Ordering::Acquire)
+    }
+}
+
+/// Outcome of one read as seen by [`FrameDelivery`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Nothing has been committed yet
+    Empty,
+    /// Same frame as the previous read
+    Duplicate,
+    /// A newer frame; `skipped` commits were overwritten before being read
+    New { skipped: u64 },
+}
+
+/// Renderer-side bookkeeping over [`ReadGuard::generation`]: detects repeated
+/// frames and counts the commits the reader never saw, for the stats overlay.
+#[derive(Debug, Default, Clone)]
+pub struct FrameDelivery {
+    last_generation: u64,
+    delivered: u64,
+    duplicates: u64,
+    skipped: u64,
+}
+
+impl FrameDelivery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the generation returned by a read
+    pub fn observe(&mut self, generation: u64) -> Delivery {
+        if generation == 0 {
+            return Delivery::Empty;
+        }
+        if generation <= self.last_generation {
+            self.duplicates += 1;
+            return Delivery::Duplicate;
+        }
+
+        let skipped = generation - self.last_generation - 1;
+        self.last_generation = generation;
+        self.delivered += 1;
+        self.skipped += skipped;
+        Delivery::New { skipped }
+    }
+
+    /// Generation of the last new frame observed
+    pub fn last_generation(&self) -> u64 {
+        self.last_generation
+    }
+
+    /// New frames observed
+    pub fn delivered(&self) -> u64 {
+        self.delivered
+    }
+
+    /// Reads that returned an already observed frame
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    /// Commits overwritten before the reader got to them
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
 }
 
 impl<'a, T> std::ops::Deref for ReadGuard<'a, T> {
@@ -402,6 +502,101 @@ mod tests {
         assert_eq!(read[1], 43);
     }
 
+    #[test]
+    fn test_generation_per_commit() {
+        let buffer = TripleBuffer::new(Vec::<u8>::new);
+        assert_eq!(buffer.latest_generation(), 0);
+        assert_eq!(buffer.read().generation(), 0);
+
+        for i in 0..3 {
+            let mut write = buffer.write();
+            write.clear();
+            write.push(i);
+            write.commit();
+        }
+        assert_eq!(buffer.latest_generation(), 3);
+
+        // The read buffer carries the generation of the commit it holds
+        let read = buffer.read();
+        assert_eq!(read.generation(), 3);
+        assert_eq!(&**read, &[2]);
+        drop(read);
+
+        // No new commit: same frame, same generation
+        assert_eq!(buffer.read().generation(), 3);
+    }
+
+    #[test]
+    fn test_frame_delivery_tracking() {
+        let buffer = TripleBuffer::new(Vec::<u8>::new);
+        let mut delivery = FrameDelivery::new();
+
+        assert_eq!(
+            delivery.observe(buffer.read().generation()),
+            Delivery::Empty
+        );
+
+        buffer.write().commit();
+        assert_eq!(
+            delivery.observe(buffer.read().generation()),
+            Delivery::New { skipped: 0 }
+        );
+        assert_eq!(
+            delivery.observe(buffer.read().generation()),
+            Delivery::Duplicate
+        );
+
+        // Three commits between reads: the reader only sees the last one
+        for _ in 0..3 {
+            buffer.write().commit();
+        }
+        assert_eq!(
+            delivery.observe(buffer.read().generation()),
+            Delivery::New { skipped: 2 }
+        );
+
+        assert_eq!(delivery.last_generation(), 4);
+        assert_eq!(delivery.delivered(), 2);
+        assert_eq!(delivery.duplicates(), 1);
+        assert_eq!(delivery.skipped(), 2);
+    }
+
+    #[test]
+    fn test_concurrent_generations_monotonic() {
+        let buffer = Arc::new(TripleBuffer::new(|| 0u64));
+        let buffer_clone = buffer.clone();
+
+        // The writer stores the generation it is about to commit as payload
+        let writer = thread::spawn(move || {
+            for i in 1..=1000u64 {
+                let mut write = buffer_clone.write();
+                *write = i;
+                write.commit();
+            }
+        });
+
+        let reader = thread::spawn(move || {
+            let mut delivery = FrameDelivery::new();
+            for _ in 0..1000 {
+                let read = buffer.read();
+                let generation = read.generation();
+                if generation > 0 {
+                    assert_eq!(*read, generation, "generation does not match payload");
+                }
+                delivery.observe(generation);
+            }
+            delivery
+        });
+
+        writer.join().unwrap();
+        let delivery = reader.join().unwrap();
+        assert!(delivery.last_generation() <= 1000);
+        assert_eq!(
+            delivery.delivered() + delivery.skipped(),
+            delivery.last_generation()
+        );
+    }
+
     #[test]
     fn test_auto_commit_on_drop() {
         let buffer = TripleBuffer::new(Vec::<u8>::new);
@@ -1,11 +1,15 @@
-use crate::capture::{NV12FrameRef, YUVFrame};
-use crate::encoder::frame_pool::FramePool;
-use ac_ffmpeg::codec::video::VideoEncoder;
+use crate::capture::{ColorSpace, NV12FrameRef, StreamProfile, YUVFrame};
+use crate::encoder::frame_pool::{DEFAULT_POOL_CAPACITY, FramePool};
+use crate::encoder::parameter_sets::ParameterSets;
+use ac_ffmpeg::codec::video::scaler::Algorithm;
+use ac_ffmpeg::codec::video::{VideoEncoder, VideoFrameScaler};
 use ac_ffmpeg::codec::{Encoder, video};
 use ac_ffmpeg::time::{TimeBase, Timestamp};
 use bytes::Bytes;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
 
 /// Encoder fallback chain: try hardware encoders first, then software.
 /// Optimized for low latency streaming with hardware acceleration.
@@ -83,44 +87,371 @@ const ENCODER_CHAIN: &[(&str, &[(&str, &str)])] = &[
     ),
 ];
 
+/// Controllo del bitrate con cui viene creato un encoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateControl {
+    /// Opzioni della catena: VBR, CRF per libx264
+    #[default]
+    Chain,
+    /// VBR con tetto in bps (data budget, livello simulcast)
+    Capped(u32),
+    /// Bitrate costante in bps: niente picchi sui cambi di scena
+    Constant(u32),
+}
+
+impl RateControl {
+    /// Opzioni della catena sostituite da questa modalità
+    fn overrides(&self, key: &str) -> bool {
+        match self {
+            Self::Chain => false,
+            Self::Capped(_) => matches!(key, "b" | "maxrate" | "bufsize"),
+            Self::Constant(_) => matches!(key, "b" | "maxrate" | "bufsize" | "rc" | "crf"),
+        }
+    }
+}
+
 pub struct FfmpegEncoder {
     encoder: VideoEncoder,
+    /// Present only when the stream profile asks for a smaller output than the source.
+    scaler: Option<VideoFrameScaler>,
     frame_pool: FramePool,
-    pixel_format: String,
+    /// Frame tenuti da ciascun pool, dal `PipelineTuning`
+    pool_capacity: usize,
+    /// BGR0 → NV12 (creato al primo frame BGR0, es. dal capturer generico)
+    bgr0: Option<Bgr0Converter>,
+    /// SPS/PPS ripetuti davanti a ogni IDR, `None` = uscita dell'encoder così com'è
+    parameter_sets: Option<ParameterSets>,
     w: usize,
     h: usize,
+    out_w: usize,
+    out_h: usize,
     pub force_idr: Arc<AtomicBool>,
     pub codec_name: String,
+    /// Secondo livello (simulcast): come `force_idr`, va ricollegato a ogni
+    /// encoder ricreato dal capturer
+    pub simulcast: Option<SimulcastLink>,
+    /// Thread del livello ridotto, avviato al primo frame con `active` acceso
+    low_tier: Option<std::sync::mpsc::SyncSender<video::VideoFrame>>,
+    /// Slice massime in byte, anche per il livello ridotto
+    max_slice_size: Option<usize>,
 }
 
 unsafe impl Send for FfmpegEncoder {}
 
+/// Frame BGR0 pool-backed + swscale verso l'NV12 dell'encoder: la conversione
+/// e l'eventuale scaling del profilo avvengono in un solo passaggio.
+struct Bgr0Converter {
+    pool: FramePool,
+    scaler: VideoFrameScaler,
+}
+
+impl Bgr0Converter {
+    fn new(
+        w: usize,
+        h: usize,
+        out_w: usize,
+        out_h: usize,
+        time_base: TimeBase,
+        pool_capacity: usize,
+    ) -> Result<Self, anyhow::Error> {
+        let bgr0 = video::frame::get_pixel_format("bgr0");
+        let scaler = VideoFrameScaler::builder()
+            .source_pixel_format(bgr0)
+            .source_width(w)
+            .source_height(h)
+            .target_pixel_format(video::frame::get_pixel_format("nv12"))
+            .target_width(out_w)
+            .target_height(out_h)
+            .algorithm(Algorithm::Bilinear)
+            .build()?;
+        log::info!(
+            "BGR0 input: converting {}x{} → NV12 {}x{}",
+            w,
+            h,
+            out_w,
+            out_h
+        );
+        Ok(Self {
+            pool: FramePool::new(w, h, time_base, bgr0, pool_capacity),
+            scaler,
+        })
+    }
+}
+
+/// Where the simulcast low tier goes: the same NV12 frames, scaled into
+/// `size` and encoded by a second encoder on its own thread.
+#[derive(Clone)]
+pub struct SimulcastLink {
+    /// Box massimo del livello ridotto (mai ingrandito)
+    pub size: (u32, u32),
+    pub output: mpsc::Sender<EncodedVideo>,
+    /// Keyframe richiesti per il livello ridotto (peer appena passati a questo livello)
+    pub force_idr: Arc<AtomicBool>,
+    /// Almeno un peer servito dal livello ridotto: spento, non si codifica
+    pub active: Arc<AtomicBool>,
+}
+
+/// Encoder del livello ridotto e scaler dalla sorgente
+struct LowTier {
+    encoder: VideoEncoder,
+    scaler: VideoFrameScaler,
+    parameter_sets: ParameterSets,
+    /// Dimensioni dei frame in ingresso, per ricrearlo se cambiano
+    src: (usize, usize),
+}
+
+impl LowTier {
+    /// Bit/s per pixel del livello ridotto: ~800 kbps a 480p
+    const BITS_PER_PIXEL: u32 = 2;
+
+    fn new(
+        src_w: usize,
+        src_h: usize,
+        size: (u32, u32),
+        max_slice_size: Option<usize>,
+    ) -> Result<Self, anyhow::Error> {
+        let (out_w, out_h) =
+            StreamProfile::new(size.0, size.1, 0).output_size(src_w as u32, src_h as u32);
+        let (out_w, out_h) = (out_w as usize, out_h as usize);
+        let pixel_format = video::frame::get_pixel_format("nv12");
+        let bitrate = (out_w * out_h) as u32 * Self::BITS_PER_PIXEL;
+        let (encoder, codec_name) = FfmpegEncoder::try_create_encoder(
+            out_w,
+            out_h,
+            TimeBase::new(1, 90_000),
+            pixel_format,
+            RateControl::Capped(bitrate),
+            max_slice_size,
+        )?;
+        let scaler = VideoFrameScaler::builder()
+            .source_pixel_format(pixel_format)
+            .source_width(src_w)
+            .source_height(src_h)
+            .target_pixel_format(pixel_format)
+            .target_width(out_w)
+            .target_height(out_h)
+            .algorithm(Algorithm::Bilinear)
+            .build()?;
+        log::info!(
+            "Simulcast low tier: {} at {}x{}, {} kbps",
+            codec_name,
+            out_w,
+            out_h,
+            bitrate / 1000
+        );
+        Ok(Self {
+            encoder,
+            scaler,
+            parameter_sets: ParameterSets::default(),
+            src: (src_w, src_h),
+        })
+    }
+
+    /// Thread che codifica i frame ricevuti finché il mittente resta vivo;
+    /// l'encoder si ricrea quando cambiano le dimensioni della sorgente.
+    fn spawn(
+        link: SimulcastLink,
+        max_slice_size: Option<usize>,
+    ) -> std::io::Result<std::sync::mpsc::SyncSender<video::VideoFrame>> {
+        // Un frame in attesa: se il thread è indietro il capturer non aspetta
+        let (tx, rx) = std::sync::mpsc::sync_channel::<video::VideoFrame>(1);
+        std::thread::Builder::new()
+            .name("simulcast-encoder".into())
+            .spawn(move || {
+                let mut tier: Option<LowTier> = None;
+                for frame in rx {
+                    let src = (frame.width(), frame.height());
+                    if tier.as_ref().is_none_or(|tier| tier.src != src) {
+                        match LowTier::new(src.0, src.1, link.size, max_slice_size) {
+                            Ok(created) => tier = Some(created),
+                            Err(e) => {
+                                log::warn!("Simulcast low tier unavailable: {}", e);
+                                return;
+                            }
+                        }
+                    }
+                    let Some(tier) = tier.as_mut() else {
+                        return;
+                    };
+
+                    let picture_type = if link.force_idr.swap(false, Ordering::Relaxed) {
+                        video::frame::PictureType::I
+                    } else {
+                        video::frame::PictureType::None
+                    };
+                    match tier.encode(&frame, picture_type) {
+                        Ok(data) if !data.is_empty() => {
+                            // Canale pieno: il frame si perde, il prossimo sarà un IDR
+                            if link.output.try_send(data).is_err() {
+                                link.force_idr.store(true, Ordering::Relaxed);
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(e) => log::warn!("Simulcast low tier encode failed: {}", e),
+                    }
+                }
+            })?;
+        Ok(tx)
+    }
+
+    fn encode(
+        &mut self,
+        frame: &video::VideoFrame,
+        picture_type: video::frame::PictureType,
+    ) -> Result<EncodedVideo, anyhow::Error> {
+        let scaled = self.scaler.scale(frame)?;
+        self.encoder.push(scaled.with_picture_type(picture_type))?;
+        let mut ret = Vec::new();
+        let mut capture_us = pts_micros(frame.pts()).unwrap_or_default();
+        while let Some(packet) = self.encoder.take()? {
+            ret.extend_from_slice(packet.data());
+            capture_us = pts_micros(packet.pts()).unwrap_or(capture_us);
+        }
+        self.parameter_sets.apply(&mut ret);
+        Ok(EncodedVideo {
+            data: Bytes::from(ret),
+            capture_us,
+        })
+    }
+}
+
+/// Access unit Annex B con l'istante di cattura del frame da cui è nato:
+/// l'unica sorgente per il pts dell'encoder e per il timestamp RTP.
+#[derive(Debug, Clone, Default)]
+pub struct EncodedVideo {
+    pub data: Bytes,
+    /// Microsecondi sull'orologio del capturer (vedi [`FfmpegEncoder::capture_micros`])
+    pub capture_us: i64,
+}
+
+impl EncodedVideo {
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+/// Microsecondi → tick del time base a 90 kHz di encoder e muxer
+fn micros_to_pts(micros: i64) -> i64 {
+    micros * 9 / 100
+}
+
+/// Pts a 90 kHz di un frame o pacchetto → microsecondi, `None` se assente
+fn pts_micros(pts: Timestamp) -> Option<i64> {
+    (!pts.is_null()).then(|| pts.timestamp() * 100 / 9)
+}
+
 pub enum FrameData<'a> {
     NV12(&'a YUVFrame),
     NV12Ref(NV12FrameRef<'a>),
+    /// Packed BGR0/BGRA (capturer generici): convertito in NV12 dall'encoder
     BGR0(&'a [u8]),
 }
 
 impl FfmpegEncoder {
-    pub fn new(w: u32, h: u32) -> Self {
-        let w = if w.is_multiple_of(2) { w } else { w + 1 } as usize;
-        let h = if h.is_multiple_of(2) { h } else { h + 1 } as usize;
+    pub fn new(w: u32, h: u32) -> Result<Self, anyhow::Error> {
+        Self::new_scaled(w, h, w, h)
+    }
+
+    /// Create an encoder that accepts `src_w`×`src_h` NV12 frames and
+    /// produces an `out_w`×`out_h` H.264 stream, scaling in between if needed.
+    /// Fails when no encoder of the chain initializes or the scaler can't
+    /// be built for these sizes.
+    pub fn new_scaled(
+        src_w: u32,
+        src_h: u32,
+        out_w: u32,
+        out_h: u32,
+    ) -> Result<Self, anyhow::Error> {
+        Self::with_rate_control(src_w, src_h, out_w, out_h, RateControl::Chain, None)
+    }
+
+    /// Like [`Self::new_scaled`], with the bitrate capped (data budget) or
+    /// held constant (CBR mode). [`RateControl::Chain`] keeps the chain defaults.
+    /// With `max_slice_size`, encoders that can split a frame into slices
+    /// keep every NAL within it, so each one fits a single RTP packet.
+    /// `None` leaves whole frames to the RTP packetizer (FU-A).
+    pub fn with_rate_control(
+        src_w: u32,
+        src_h: u32,
+        out_w: u32,
+        out_h: u32,
+        rate: RateControl,
+        max_slice_size: Option<usize>,
+    ) -> Result<Self, anyhow::Error> {
+        let even = |v: u32| if v.is_multiple_of(2) { v } else { v + 1 } as usize;
+        let (w, h) = (even(src_w), even(src_h));
+        let (out_w, out_h) = (even(out_w), even(out_h));
         let time_base = TimeBase::new(1, 90_000);
 
         let pixel_format = video::frame::get_pixel_format("nv12");
 
-        let (encoder, codec_name) = Self::try_create_encoder(w, h, time_base, pixel_format);
+        let (encoder, codec_name) =
+            Self::try_create_encoder(out_w, out_h, time_base, pixel_format, rate, max_slice_size)?;
         log::info!("Using encoder: {}", codec_name);
+        match rate {
+            RateControl::Chain => {}
+            RateControl::Capped(bitrate) => {
+                log::info!("Bitrate capped to {} kbps", bitrate / 1000)
+            }
+            RateControl::Constant(bitrate) => {
+                log::info!("Constant bitrate: {} kbps", bitrate / 1000)
+            }
+        }
 
-        Self {
+        let scaler = ((w, h) != (out_w, out_h))
+            .then(|| {
+                log::info!("Scaling {}x{} → {}x{}", w, h, out_w, out_h);
+                VideoFrameScaler::builder()
+                    .source_pixel_format(pixel_format)
+                    .source_width(w)
+                    .source_height(h)
+                    .target_pixel_format(pixel_format)
+                    .target_width(out_w)
+                    .target_height(out_h)
+                    .algorithm(Algorithm::Bilinear)
+                    .build()
+                    .map_err(|e| anyhow::anyhow!("Unable to create NV12 frame scaler: {}", e))
+            })
+            .transpose()?;
+
+        Ok(Self {
             encoder,
-            pixel_format: String::from("nv12"),
-            frame_pool: FramePool::new(w, h, time_base, pixel_format),
+            scaler,
+            frame_pool: FramePool::new(w, h, time_base, pixel_format, DEFAULT_POOL_CAPACITY),
+            pool_capacity: DEFAULT_POOL_CAPACITY,
+            bgr0: None,
+            parameter_sets: Some(ParameterSets::default()),
             force_idr: Arc::new(AtomicBool::new(false)),
             codec_name,
             w,
             h,
-        }
+            out_w,
+            out_h,
+            simulcast: None,
+            low_tier: None,
+            max_slice_size,
+        })
+    }
+
+    /// SPS/PPS in banda davanti a ogni keyframe, così un receiver che si
+    /// collega a metà stream decodifica dal primo IDR. Attivo di default.
+    pub fn with_parameter_sets(mut self, enabled: bool) -> Self {
+        self.parameter_sets = enabled.then(ParameterSets::default);
+        self
+    }
+
+    /// Keep up to `capacity` input frames around for reuse instead of the
+    /// default pool size
+    pub fn with_frame_pool(mut self, capacity: usize) -> Self {
+        let time_base = TimeBase::new(1, 90_000);
+        let nv12 = video::frame::get_pixel_format("nv12");
+        self.frame_pool = FramePool::new(self.w, self.h, time_base, nv12, capacity);
+        self.pool_capacity = capacity;
+        self
     }
 
     fn try_create_encoder(
@@ -128,32 +459,142 @@ impl FfmpegEncoder {
         h: usize,
         time_base: TimeBase,
         pixel_format: video::frame::PixelFormat,
-    ) -> (VideoEncoder, String) {
+        rate: RateControl,
+        max_slice_size: Option<usize>,
+    ) -> Result<(VideoEncoder, String), anyhow::Error> {
         for (codec, options) in ENCODER_CHAIN {
-            let mut builder = match VideoEncoder::builder(codec) {
-                Ok(b) => b,
-                Err(e) => {
-                    log::debug!("Encoder {} not available, skipping: {}", codec, e);
-                    continue;
+            let built = Self::build_encoder(
+                codec,
+                options,
+                (w, h),
+                time_base,
+                pixel_format,
+                rate,
+                max_slice_size,
+            );
+            match built {
+                Ok(enc) => return Ok((enc, codec.to_string())),
+                Err(e) => log::debug!("Encoder {} skipped: {}", codec, e),
+            }
+        }
+        anyhow::bail!("No H.264 encoder available — install FFmpeg with at least libx264 support");
+    }
+
+    fn build_encoder(
+        codec: &str,
+        options: &[(&str, &str)],
+        (w, h): (usize, usize),
+        time_base: TimeBase,
+        pixel_format: video::frame::PixelFormat,
+        rate: RateControl,
+        max_slice_size: Option<usize>,
+    ) -> Result<VideoEncoder, anyhow::Error> {
+        let mut builder = VideoEncoder::builder(codec)
+            .map_err(|e| anyhow::anyhow!("not available: {}", e))?
+            .pixel_format(pixel_format)
+            .width(w)
+            .height(h)
+            .time_base(time_base);
+        for (k, v) in options {
+            if rate.overrides(k) {
+                continue;
+            }
+            builder = builder.set_option(k, v);
+        }
+        match rate {
+            RateControl::Chain => {}
+            RateControl::Capped(bitrate) => {
+                let (rate, bufsize) = (bitrate.to_string(), (bitrate * 2).to_string());
+                // libx264 resta in CRF: il VBV (maxrate + bufsize) fa da tetto
+                if codec != "libx264" {
+                    builder = builder.set_option("b", &rate);
                 }
-            };
-            builder = builder
-                .pixel_format(pixel_format)
-                .width(w)
-                .height(h)
-                .time_base(time_base);
-            for (k, v) in *options {
-                builder = builder.set_option(k, v);
+                builder = builder
+                    .set_option("maxrate", &rate)
+                    .set_option("bufsize", bufsize);
             }
-            match builder.build() {
-                Ok(enc) => return (enc, codec.to_string()),
-                Err(e) => {
-                    log::debug!("Encoder {} failed to initialize: {}", codec, e);
-                    continue;
+            RateControl::Constant(bitrate) => {
+                // Buffer di un secondo: il rate resta piatto anche sui keyframe
+                let rate = bitrate.to_string();
+                builder = builder
+                    .set_option("b", &rate)
+                    .set_option("maxrate", &rate)
+                    .set_option("bufsize", &rate);
+                builder = match codec {
+                    "h264_nvenc" | "h264_amf" => builder.set_option("rc", "cbr"),
+                    // QSV passa in CBR da solo con maxrate uguale al target; per
+                    // libx264 basta il VBV, senza il filler di nal-hrd=cbr
+                    _ => builder,
+                };
+            }
+        }
+        // Slice entro il payload RTP; NVENC e AMF non hanno un limite in
+        // byte e restano affidati alla frammentazione FU-A
+        if let Some(max_slice_size) = max_slice_size {
+            let max_slice = max_slice_size.to_string();
+            match codec {
+                "libx264" => {
+                    builder =
+                        builder.set_option("x264-params", format!("slice-max-size={}", max_slice))
                 }
+                "h264_qsv" => builder = builder.set_option("max_slice_size", &max_slice),
+                _ => {}
             }
         }
-        panic!("No H.264 encoder available — install FFmpeg with at least libx264 support");
+        // Matrice e range dei capturer marcati nello stream (VUI dell'SPS)
+        for (k, v) in ColorSpace::CAPTURE.ffmpeg_options() {
+            builder = builder.set_option(k, v);
+        }
+        builder
+            .build()
+            .map_err(|e| anyhow::anyhow!("failed to initialize: {}", e))
+    }
+
+    /// Prova ogni encoder della catena a `w`×`h`, nell'ordine in cui
+    /// verrebbero scelti: `Ok` se si inizializza, altrimenti il motivo.
+    pub fn probe_chain(w: u32, h: u32) -> Vec<(&'static str, Result<(), String>)> {
+        let pixel_format = video::frame::get_pixel_format("nv12");
+        ENCODER_CHAIN
+            .iter()
+            .map(|(codec, options)| {
+                let result = Self::build_encoder(
+                    codec,
+                    options,
+                    (w as usize, h as usize),
+                    TimeBase::new(1, 90_000),
+                    pixel_format,
+                    RateControl::Chain,
+                    None,
+                )
+                .map(|_| ())
+                .map_err(|e| e.to_string());
+                (*codec, result)
+            })
+            .collect()
+    }
+
+    /// `frame_time` (nell'unità del capturer della piattaforma) per un frame
+    /// catturato `elapsed` dopo l'avvio; inverso di [`Self::capture_micros`].
+    pub fn frame_time(elapsed: Duration) -> i64 {
+        if cfg!(target_os = "windows") {
+            (elapsed.as_nanos() / 100) as i64
+        } else if cfg!(target_os = "macos") {
+            elapsed.as_nanos() as i64
+        } else {
+            elapsed.as_micros() as i64
+        }
+    }
+
+    /// `frame_time` in microsecondi: 100 ns (`SystemRelativeTime` di WGC) su
+    /// Windows, nanosecondi su macOS, già microsecondi altrove.
+    pub fn capture_micros(frame_time: i64) -> i64 {
+        if cfg!(target_os = "windows") {
+            frame_time / 10
+        } else if cfg!(target_os = "macos") {
+            frame_time / 1000
+        } else {
+            frame_time
+        }
     }
 
     /// Encode a frame to H.264 Annex B format.
@@ -166,7 +607,31 @@ impl FfmpegEncoder {
         &mut self,
         frame_data: FrameData,
         frame_time: i64,
-    ) -> Result<Bytes, anyhow::Error> {
+    ) -> Result<EncodedVideo, anyhow::Error> {
+        match frame_data {
+            FrameData::BGR0(bgr0) => self.push_bgr0(bgr0, frame_time)?,
+            nv12 => self.push_nv12(nv12, frame_time)?,
+        }
+
+        // Pre-allocate output buffer with capacity hint
+        // Typical encoded frame size: ~10-50KB for 800Kbps @ 30fps
+        let mut ret = Vec::with_capacity(32 * 1024);
+        // Encoder con ritardo: l'istante è quello del pacchetto uscito, non del frame appena entrato
+        let mut capture_us = Self::capture_micros(frame_time);
+        while let Some(packet) = self.encoder.take()? {
+            ret.extend_from_slice(packet.data());
+            capture_us = pts_micros(packet.pts()).unwrap_or(capture_us);
+        }
+        if let Some(parameter_sets) = &mut self.parameter_sets {
+            parameter_sets.apply(&mut ret);
+        }
+        Ok(EncodedVideo {
+            data: Bytes::from(ret),
+            capture_us,
+        })
+    }
+
+    fn push_nv12(&mut self, frame_data: FrameData, frame_time: i64) -> Result<(), anyhow::Error> {
         let mut frame = self.frame_pool.take();
         let time_base = frame.time_base();
         frame = frame
@@ -176,41 +641,121 @@ impl FfmpegEncoder {
         match frame_data {
             FrameData::NV12(nv12) => self.write_nv12_planes(&mut frame, nv12),
             FrameData::NV12Ref(nv12) => self.write_nv12_ref_planes(&mut frame, nv12),
-            FrameData::BGR0(bgr0) => match self.pixel_format.as_str() {
-                "bgra" => {
-                    let mut planes = frame.planes_mut();
-                    planes[0].data_mut().copy_from_slice(bgr0);
-                }
-                _ => unimplemented!(),
-            },
+            FrameData::BGR0(_) => unreachable!("BGR0 frames go through push_bgr0"),
         }
 
         // Freeze the frame and push to encoder
         // Note: We don't clone here - the encoder takes ownership temporarily
         // and we get it back via take() for reuse in the frame pool
         let frame = frame.freeze();
-        self.encoder.push(frame.clone())?;
+        match self.scaler.as_mut() {
+            Some(scaler) => self.encoder.push(scaler.scale(&frame)?)?,
+            None => self.encoder.push(frame.clone())?,
+        }
+        self.encode_low_tier(&frame);
         self.frame_pool.put(frame);
+        Ok(())
+    }
 
-        // Pre-allocate output buffer with capacity hint
-        // Typical encoded frame size: ~10-50KB for 800Kbps @ 30fps
-        let mut ret = Vec::with_capacity(32 * 1024);
+    /// Passa lo stesso frame NV12 (un riferimento, non una copia) al thread
+    /// del livello ridotto, solo se qualche peer lo sta usando. Un errore qui
+    /// non ferma il livello principale.
+    fn encode_low_tier(&mut self, frame: &video::VideoFrame) {
+        let Some(link) = &self.simulcast else {
+            return;
+        };
+        if !link.active.load(Ordering::Relaxed) {
+            return;
+        }
+        if self.low_tier.is_none() {
+            match LowTier::spawn(link.clone(), self.max_slice_size) {
+                Ok(tx) => self.low_tier = Some(tx),
+                Err(e) => {
+                    log::warn!("Simulcast low tier unavailable: {}", e);
+                    self.simulcast = None;
+                    return;
+                }
+            }
+        }
+        let Some(tx) = &self.low_tier else {
+            return;
+        };
+        match tx.try_send(frame.clone()) {
+            Ok(()) => {}
+            // Thread ancora sul frame precedente: questo l'encoder ridotto non lo vede
+            Err(std::sync::mpsc::TrySendError::Full(_)) => {}
+            // Encoder ridotto non disponibile, il thread è uscito
+            Err(std::sync::mpsc::TrySendError::Disconnected(_)) => {
+                self.low_tier = None;
+                self.simulcast = None;
+            }
+        }
+    }
+
+    /// BGR0 packed (4 byte/pixel, stride = `len / h`): copia nel frame del
+    /// pool BGR0 e swscale direttamente all'NV12 di uscita dell'encoder.
+    fn push_bgr0(&mut self, bgr0: &[u8], frame_time: i64) -> Result<(), anyhow::Error> {
+        if self.bgr0.is_none() {
+            self.bgr0 = Some(Bgr0Converter::new(
+                self.w,
+                self.h,
+                self.out_w,
+                self.out_h,
+                TimeBase::new(1, 90_000),
+                self.pool_capacity,
+            )?);
+        }
+
+        let mut frame = self.bgr0.as_mut().unwrap().pool.take();
+        let time_base = frame.time_base();
+        frame = frame
+            .with_pts(self.pts_from_frame_time(frame_time, time_base))
+            .with_picture_type(self.next_picture_type());
+
+        {
+            let mut planes = frame.planes_mut();
+            let plane = planes[0].data_mut();
+            let line_size = plane.len() / self.h;
+            let stride = bgr0.len() / self.h;
+            if stride == 0 {
+                anyhow::bail!("Empty BGR0 frame");
+            }
+            let row = (self.w * 4).min(stride).min(line_size);
+            for (src, dst) in bgr0
+                .chunks_exact(stride)
+                .zip(plane.chunks_exact_mut(line_size))
+            {
+                dst[..row].copy_from_slice(&src[..row]);
+            }
+        }
+
+        let frame = frame.freeze();
+        let converter = self.bgr0.as_mut().unwrap();
+        let nv12 = converter.scaler.scale(&frame)?;
+        converter.pool.put(frame);
+        self.encode_low_tier(&nv12);
+        self.encoder.push(nv12)?;
+        Ok(())
+    }
+
+    /// Svuota l'encoder a fine sessione: manda EOS e restituisce i pacchetti
+    /// ancora in coda (Annex B concatenati, vuoto se non c'era nulla).
+    pub fn flush(&mut self) -> Result<EncodedVideo, anyhow::Error> {
+        self.encoder.flush()?;
+        let mut tail = EncodedVideo::default();
+        let mut ret = Vec::new();
         while let Some(packet) = self.encoder.take()? {
             ret.extend_from_slice(packet.data());
+            tail.capture_us = pts_micros(packet.pts()).unwrap_or(tail.capture_us);
         }
-        Ok(Bytes::from(ret))
+        tail.data = Bytes::from(ret);
+        Ok(tail)
     }
 
+    /// Pts a 90 kHz dallo stesso istante che il capturer usa per il timestamp RTP
     #[inline]
     fn pts_from_frame_time(&self, frame_time: i64, time_base: TimeBase) -> Timestamp {
-        let pts = if cfg!(target_os = "windows") {
-            (frame_time as f64 * 9.0 / 1000.0) as i64
-        } else if cfg!(target_os = "macos") {
-            (frame_time as f64 * 9.0 / 1e5) as i64
-        } else {
-            panic!("Unsupported OS")
-        };
-        Timestamp::new(pts, time_base)
+        Timestamp::new(micros_to_pts(Self::capture_micros(frame_time)), time_base)
     }
 
     #[inline]
@@ -339,3 +884,24 @@ impl FfmpegEncoder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_time_round_trips_through_micros_and_pts() {
+        for elapsed in [0, 16_667, 33_333, 1_000_000, 3_600_000_000] {
+            let frame_time = FfmpegEncoder::frame_time(Duration::from_micros(elapsed));
+            let micros = FfmpegEncoder::capture_micros(frame_time);
+            assert_eq!(micros, elapsed as i64);
+
+            // Un tick a 90 kHz vale 11.1 µs
+            let pts = Timestamp::new(micros_to_pts(micros), TimeBase::new(1, 90_000));
+            let back = pts_micros(pts).unwrap();
+            assert!((micros - back).abs() <= 12, "{} → {}", micros, back);
+        }
+        assert_eq!(micros_to_pts(1_000_000), 90_000);
+        assert_eq!(pts_micros(Timestamp::null()), None);
+    }
+}
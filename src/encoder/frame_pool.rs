@@ -10,21 +10,30 @@ use std::collections::VecDeque;
 /// - Falls back to allocation if pool is exhausted
 pub(crate) struct FramePool {
     frames: VecDeque<VideoFrame>,
+    /// Frames kept for reuse, half of them pre-allocated
+    capacity: usize,
     w: usize,
     h: usize,
     time_base: TimeBase,
     pixel_format: PixelFormat,
 }
 
-/// Initial number of frames to pre-allocate in the pool
-const INITIAL_POOL_SIZE: usize = 4;
+/// Frames kept for reuse when the pipeline tuning does not say otherwise
+pub(crate) const DEFAULT_POOL_CAPACITY: usize = 8;
 
 impl FramePool {
-    pub fn new(w: usize, h: usize, time_base: TimeBase, pixel_format: PixelFormat) -> Self {
-        let mut frames = VecDeque::with_capacity(INITIAL_POOL_SIZE);
+    pub fn new(
+        w: usize,
+        h: usize,
+        time_base: TimeBase,
+        pixel_format: PixelFormat,
+        capacity: usize,
+    ) -> Self {
+        let capacity = capacity.max(2);
+        let mut frames = VecDeque::with_capacity(capacity);
 
         // Pre-allocate initial frames to avoid runtime allocation
-        for _ in 0..INITIAL_POOL_SIZE {
+        for _ in 0..capacity / 2 {
             let frame = VideoFrameMut::black(pixel_format, w, h)
                 .with_time_base(time_base)
                 .freeze();
@@ -33,6 +42,7 @@ impl FramePool {
 
         Self {
             frames,
+            capacity,
             w,
             h,
             time_base,
@@ -44,7 +54,7 @@ impl FramePool {
     #[inline]
     pub fn put(&mut self, frame: VideoFrame) {
         // Only keep frames if pool isn't too large (prevent unbounded growth)
-        if self.frames.len() < INITIAL_POOL_SIZE * 2 {
+        if self.frames.len() < self.capacity {
             self.frames.push_back(frame);
         }
     }
@@ -1,5 +1,10 @@
 mod ffmpeg;
 mod frame_pool;
+mod parameter_sets;
 
+pub use ffmpeg::EncodedVideo;
 pub use ffmpeg::FfmpegEncoder;
 pub use ffmpeg::FrameData;
+pub use ffmpeg::RateControl;
+pub use ffmpeg::SimulcastLink;
+pub(crate) use parameter_sets::ParameterSets;
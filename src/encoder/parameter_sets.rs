@@ -0,0 +1,107 @@
+//! SPS/PPS in banda davanti a ogni keyframe
+//!
+//! Non tutti gli encoder della catena ripetono i parameter set a ogni IDR:
+//! alcuni li emettono solo col primo. Un receiver che si collega a metà
+//! stream resterebbe senza SPS/PPS fino a una nuova negoziazione. Qui si
+//! ricordano gli ultimi visti e si antepongono a ogni access unit IDR che
+//! ne è privo.
+
+use crate::pipeline::sender::packetizer::nal_units;
+
+const START_CODE: &[u8] = &[0, 0, 0, 1];
+
+const NAL_IDR: u8 = 5;
+const NAL_SPS: u8 = 7;
+const NAL_PPS: u8 = 8;
+
+#[derive(Default)]
+pub(crate) struct ParameterSets {
+    sps: Option<Vec<u8>>,
+    pps: Option<Vec<u8>>,
+}
+
+impl ParameterSets {
+    /// Aggiorna SPS/PPS da `au` (Annex B) e li antepone se è un IDR che non li contiene
+    pub(crate) fn apply(&mut self, au: &mut Vec<u8>) {
+        let (mut has_sps, mut has_pps, mut has_idr) = (false, false, false);
+        for nal in nal_units(au) {
+            match nal[0] & 0x1F {
+                NAL_SPS => {
+                    has_sps = true;
+                    self.sps = Some(nal.to_vec());
+                }
+                NAL_PPS => {
+                    has_pps = true;
+                    self.pps = Some(nal.to_vec());
+                }
+                NAL_IDR => has_idr = true,
+                _ => {}
+            }
+        }
+        if !has_idr || (has_sps && has_pps) {
+            return;
+        }
+        let (Some(sps), Some(pps)) = (&self.sps, &self.pps) else {
+            return;
+        };
+
+        let mut prefix = Vec::with_capacity(2 * START_CODE.len() + sps.len() + pps.len());
+        for nal in [sps, pps] {
+            prefix.extend_from_slice(START_CODE);
+            prefix.extend_from_slice(nal);
+        }
+        au.splice(0..0, prefix);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPS: &[u8] = &[0x67, 0x42, 0x00, 0x1f];
+    const PPS: &[u8] = &[0x68, 0xce, 0x3c, 0x80];
+    const IDR: &[u8] = &[0x65, 0x88, 0x84];
+    const SLICE: &[u8] = &[0x41, 0x9a, 0x02];
+
+    fn annex_b(nals: &[&[u8]]) -> Vec<u8> {
+        nals.iter()
+            .flat_map(|nal| START_CODE.iter().chain(nal.iter()).copied())
+            .collect()
+    }
+
+    #[test]
+    fn parameter_sets_go_before_a_bare_idr() {
+        let mut sets = ParameterSets::default();
+        let mut first = annex_b(&[SPS, PPS, IDR]);
+        sets.apply(&mut first);
+        assert_eq!(first, annex_b(&[SPS, PPS, IDR]));
+
+        let mut slice = annex_b(&[SLICE]);
+        sets.apply(&mut slice);
+        assert_eq!(slice, annex_b(&[SLICE]));
+
+        let mut idr = annex_b(&[IDR]);
+        sets.apply(&mut idr);
+        assert_eq!(idr, annex_b(&[SPS, PPS, IDR]));
+    }
+
+    #[test]
+    fn nothing_to_prepend_before_the_first_parameter_sets() {
+        let mut sets = ParameterSets::default();
+        let mut idr = annex_b(&[IDR]);
+        sets.apply(&mut idr);
+        assert_eq!(idr, annex_b(&[IDR]));
+    }
+
+    #[test]
+    fn latest_parameter_sets_win() {
+        let sps2: &[u8] = &[0x67, 0x64, 0x00, 0x28];
+        let mut sets = ParameterSets::default();
+        sets.apply(&mut annex_b(&[SPS, PPS, IDR]));
+        sets.apply(&mut annex_b(&[sps2, PPS, IDR]));
+
+        let mut idr = annex_b(&[IDR]);
+        sets.apply(&mut idr);
+        assert_eq!(idr, annex_b(&[sps2, PPS, IDR]));
+    }
+}
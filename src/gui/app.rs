@@ -1,18 +1,26 @@
 #[cfg(target_os = "linux")]
 use crate::app_id;
 use crate::assets::ICON_BYTES;
-use crate::config::Config;
+use crate::capture::ScreenCaptureImpl;
+use crate::capture::display::DisplaySelector;
+use crate::capture::display::thumbnail::THUMBNAIL_REFRESH;
+use crate::config::{Config, MIN_WINDOW_SIZE};
 use crate::gui::common::hotkeys::KeyTypes;
 use crate::gui::common::messages::AppEvent;
+use crate::gui::popup::shortcuts::HotkeyConflict;
 use crate::gui::style::theme::csx::StyleType;
+use crate::gui::style::theme::system::{SYSTEM_THEME_POLL, detect_dark_async};
 use crate::gui::widget::Element;
 use crate::gui::widget::horizontal_space;
-use crate::gui::windows::{WindowType, Windows};
+use crate::gui::windows::main::MainWindowEvent;
+use crate::gui::windows::{WindowManager, WindowMessage, WindowType, Windows};
 use crate::utils::flags::Flags;
 use crate::utils::ipc::ipc;
 use crate::utils::open_link;
 use crate::workers::key_listener::{global_key_listener, valid_iced_key};
 use crate::workers::tray_icon::{tray_icon, tray_icon_listener, tray_menu_listener};
+use castbox::AnyRef;
+use iced::keyboard::key::Named;
 use iced::keyboard::{Event, Key, Modifiers};
 use iced::{
     Event::{Keyboard, Window},
@@ -25,6 +33,9 @@ use std::process::exit;
 use std::time::Duration;
 use tray_icon::TrayIcon;
 
+/// Tempo massimo concesso al registratore per finalizzare il file in chiusura
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+
 /// Apply DWM transparency to a window by its raw HWND (Windows only).
 /// Extends the glass frame into the entire client area for per-pixel alpha compositing.
 /// Requires wgpu to use PostMultiplied/PreMultiplied alpha mode (iced_wgpu 0.14 selects this automatically).
@@ -50,6 +61,24 @@ fn apply_dwm_transparency(raw_id: u64) {
     }
 }
 
+/// Frecce premute con gli stessi modificatori della scorciatoia della lente:
+/// lo scatto (x, y) con cui spostarla.
+fn zoom_nudge(
+    (modifiers, key): &(Modifiers, Key),
+    (zoom_modifiers, _): &(Modifiers, Key),
+) -> Option<(i32, i32)> {
+    if modifiers != zoom_modifiers {
+        return None;
+    }
+    match key {
+        Key::Named(Named::ArrowLeft) => Some((-1, 0)),
+        Key::Named(Named::ArrowRight) => Some((1, 0)),
+        Key::Named(Named::ArrowUp) => Some((0, -1)),
+        Key::Named(Named::ArrowDown) => Some((0, 1)),
+        _ => None,
+    }
+}
+
 pub struct App {
     pub config: Config,
     windows: Windows,
@@ -60,13 +89,18 @@ pub struct App {
 impl App {
     pub fn new(flags: Flags) -> (Self, Task<AppEvent>) {
         let tray_icon = tray_icon().ok();
+        let boot = match (flags.connection_link.clone(), flags.connect_addr.clone()) {
+            (Some(link), _) => Task::done(AppEvent::OpenConnectionLink(link)),
+            (None, Some(addr)) => Task::done(AppEvent::OpenCasterAddress(addr)),
+            (None, None) => Task::done(AppEvent::OpenMainWindow),
+        };
         (
             Self {
                 config: Config::new(flags),
                 windows: Windows::new(),
                 tray_icon,
             },
-            Task::done(AppEvent::OpenMainWindow),
+            boot,
         )
     }
 
@@ -81,19 +115,10 @@ impl App {
 
     /// Helper: returns (width, height, x, y) of the selected display in physical pixels.
     fn caster_display_size(caster: &crate::workers::caster::Caster) -> (f32, f32, f32, f32) {
-        if let Some(display) = caster.get_selected_display() {
-            #[cfg(target_os = "windows")]
-            {
-                display.rect()
-            }
-            #[cfg(not(target_os = "windows"))]
-            {
-                let _ = display;
-                (1920.0, 1080.0, 0.0, 0.0)
-            }
-        } else {
-            (1920.0, 1080.0, 0.0, 0.0)
-        }
+        caster
+            .get_selected_display()
+            .map(|display| ScreenCaptureImpl::display_rect(&display))
+            .unwrap_or((1920.0, 1080.0, 0.0, 0.0))
     }
 
     /// Helper: returns the DPI scale factor for the selected display.
@@ -120,12 +145,9 @@ impl App {
                     window::gain_focus(id)
                 } else {
                     let (id, open_task) = window::open(window::Settings {
-                        size: self.config.window_size,
-                        position: Position::Centered,
-                        min_size: Some(Size {
-                            width: 680f32,
-                            height: 460f32,
-                        }),
+                        size: self.config.window.size(),
+                        position: self.config.window.position(),
+                        min_size: Some(MIN_WINDOW_SIZE),
                         max_size: None,
                         visible: true,
                         resizable: true,
@@ -150,6 +172,26 @@ impl App {
                     open_task.discard().chain(window::gain_focus(id))
                 }
             }
+            AppEvent::OpenConnectionLink(link) => {
+                let Some(id) = self.windows.get_id(WindowType::Main) else {
+                    return Task::done(AppEvent::OpenMainWindow)
+                        .chain(Task::done(AppEvent::OpenConnectionLink(link)));
+                };
+                window::gain_focus(id).chain(Task::done(AppEvent::WindowEvent(
+                    id,
+                    WindowMessage::Main(MainWindowEvent::OpenConnectionLink(link)),
+                )))
+            }
+            AppEvent::OpenCasterAddress(addr) => {
+                let Some(id) = self.windows.get_id(WindowType::Main) else {
+                    return Task::done(AppEvent::OpenMainWindow)
+                        .chain(Task::done(AppEvent::OpenCasterAddress(addr)));
+                };
+                window::gain_focus(id).chain(Task::done(AppEvent::WindowEvent(
+                    id,
+                    WindowMessage::Main(MainWindowEvent::OpenCasterAddress(addr)),
+                )))
+            }
             AppEvent::OpenAreaSelectionWindow => {
                 let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode else {
                     unreachable!("Mode must be Caster here")
@@ -255,6 +297,10 @@ impl App {
                         ..Default::default()
                     });
                     self.windows.insert(id, WindowType::Annotation);
+                    // Ripresa dopo un toggle: stessi strumenti e tratti di prima
+                    if let Some(WindowManager::Annotation(window)) = self.windows.get_manager(id) {
+                        window.on_show(&self.config);
+                    }
                     #[cfg(target_os = "windows")]
                     {
                         open_task
@@ -277,8 +323,27 @@ impl App {
                     Task::none()
                 }
             }
+            AppEvent::ToggleAnnotationWindow => {
+                if !matches!(self.config.mode, Some(crate::config::Mode::Caster(_))) {
+                    return Task::none();
+                }
+                match self.windows.get_id(WindowType::Annotation) {
+                    Some(id) => {
+                        if let Some(WindowManager::Annotation(window)) =
+                            self.windows.get_manager(id)
+                        {
+                            window.on_hide(&self.config);
+                        }
+                        // Resta in memoria fino alla prossima apertura
+                        self.windows.remove(id, true);
+                        window::close(id)
+                    }
+                    None => Task::done(AppEvent::OpenAnnotationWindow),
+                }
+            }
             AppEvent::AreaSelected(rect) => {
                 if let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode {
+                    caster.set_annotation_area(rect.clone());
                     let dpi = Self::caster_dpi_scale(caster);
                     let scaled = crate::gui::common::datastructure::ScreenRect {
                         x: rect.x * dpi,
@@ -291,8 +356,15 @@ impl App {
                 Task::none()
             }
             AppEvent::CloseWindow(id) => {
-                self.windows
-                    .remove(id, self.windows.of_type(id, WindowType::Main));
+                let main = self.windows.of_type(id, WindowType::Main);
+                if main {
+                    self.config.window.save();
+                    if !self.config.general.close_to_tray {
+                        return Task::done(AppEvent::ExitApp);
+                    }
+                }
+                // La finestra principale resta in memoria e riappare dalla tray
+                self.windows.remove(id, main);
                 window::close(id)
             }
             AppEvent::WindowEvent(id, message) => match self.windows.get_manager_mut(id) {
@@ -300,20 +372,45 @@ impl App {
                 None => Task::none(),
             },
             AppEvent::TimeTick => {
-                if let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode
-                    && caster.is_streaming()
-                {
-                    caster.streaming_time += 1;
+                let mut source_lost = None;
+                if let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode {
+                    caster.refresh_windows();
+                    if caster.is_streaming() {
+                        caster.streaming_time += 1;
+                        source_lost = caster.sync_source();
+                        caster.check_idle();
+                    }
                 }
                 self.config.e_time += 1;
-                Task::none()
+                let connect_error = match &self.config.mode {
+                    Some(crate::config::Mode::Receiver(receiver)) => receiver.take_connect_error(),
+                    _ => None,
+                };
+                let Some(id) = self.windows.get_id(WindowType::Main) else {
+                    return Task::none();
+                };
+                match (connect_error, source_lost) {
+                    (Some(error), _) => Task::done(AppEvent::WindowEvent(
+                        id,
+                        WindowMessage::Main(MainWindowEvent::ConnectFailed(error)),
+                    )),
+                    (None, Some(message)) => Task::done(AppEvent::WindowEvent(
+                        id,
+                        WindowMessage::Main(MainWindowEvent::ShowToast(message)),
+                    )),
+                    (None, None) => Task::none(),
+                }
             }
             AppEvent::WindowResized(id, width, height) => {
                 if self.windows.of_type(id, WindowType::Main) {
-                    self.config.window_size = Size {
-                        width: width as f32,
-                        height: height as f32,
-                    };
+                    self.config.window.width = width as f32;
+                    self.config.window.height = height as f32;
+                }
+                Task::none()
+            }
+            AppEvent::WindowMoved(id, position) => {
+                if self.windows.of_type(id, WindowType::Main) {
+                    self.config.window.set_position(position);
                 }
                 Task::none()
             }
@@ -345,41 +442,205 @@ impl App {
                 }
                 Task::none()
             }
+            AppEvent::ToggleCursorHighlight => {
+                if let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode {
+                    caster.toggle_cursor_highlight(self.config.capture.cursor_highlight);
+                }
+                Task::none()
+            }
+            AppEvent::ToggleCursorCapture => {
+                if let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode
+                    && caster.can_hide_cursor()
+                {
+                    caster.toggle_show_cursor();
+                    self.config.capture.hide_cursor = !caster.is_showing_cursor();
+                    self.config.capture.save();
+                }
+                Task::none()
+            }
+            AppEvent::ToggleFollowLock => {
+                if let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode {
+                    caster.toggle_follow_lock();
+                }
+                Task::none()
+            }
+            AppEvent::ToggleZoom => {
+                if let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode {
+                    caster.toggle_zoom(self.config.zoom);
+                }
+                Task::none()
+            }
+            AppEvent::NudgeZoom(dx, dy) => {
+                if let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode {
+                    caster.nudge_zoom(dx, dy);
+                }
+                Task::none()
+            }
+            AppEvent::ToggleDebugOverlay => {
+                if let Some(crate::config::Mode::Receiver(receiver)) = &mut self.config.mode {
+                    receiver.toggle_metrics_overlay();
+                }
+                Task::none()
+            }
+            AppEvent::CycleDisplay => {
+                let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode else {
+                    return Task::none();
+                };
+                let Some(name) = caster.cycle_display() else {
+                    return Task::none();
+                };
+                match self.windows.get_id(WindowType::Main) {
+                    Some(id) => Task::done(AppEvent::WindowEvent(
+                        id,
+                        WindowMessage::Main(MainWindowEvent::ShowToast(name)),
+                    )),
+                    None => Task::none(),
+                }
+            }
+            AppEvent::SystemThemeTick => Task::perform(detect_dark_async(), |dark| match dark {
+                Some(dark) => AppEvent::SystemTheme(dark),
+                None => AppEvent::Ignore,
+            }),
+            AppEvent::DisplayThumbnailsTick => match self.windows.get_id(WindowType::Main) {
+                Some(id) => Task::done(AppEvent::WindowEvent(
+                    id,
+                    WindowMessage::Main(MainWindowEvent::RefreshThumbnails),
+                )),
+                None => Task::none(),
+            },
+            AppEvent::SystemTheme(dark) => match self.windows.get_id(WindowType::Main) {
+                Some(id) => Task::done(AppEvent::WindowEvent(
+                    id,
+                    WindowMessage::Main(MainWindowEvent::SystemTheme(dark)),
+                )),
+                None => Task::none(),
+            },
+            AppEvent::ToggleKeycast => {
+                if let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode {
+                    caster.toggle_keycast();
+                }
+                Task::none()
+            }
             AppEvent::KeyEvent(modifier, key) => {
                 if key == Key::Unidentified {
                     return Task::none();
                 }
+                if let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode {
+                    caster.push_keystroke(modifier, &key, &self.config.keycast_filter);
+                    caster.note_activity();
+                }
                 let item = (modifier, key);
 
-                if self.config.shortcuts.updating != KeyTypes::None {
-                    match self.config.shortcuts.updating {
-                        KeyTypes::Pause => self.config.shortcuts.pause = item,
-                        KeyTypes::Record => self.config.shortcuts.record = item,
-                        KeyTypes::BlankScreen => self.config.shortcuts.blank_screen = item,
-                        KeyTypes::Close => self.config.shortcuts.end_session = item,
-                        _ => {}
+                let updating = self.config.shortcuts.updating;
+                if updating != KeyTypes::None {
+                    // Una combinazione già usata non viene assegnata: il modal mostra il conflitto
+                    let conflict = match self.config.shortcuts.assign(updating, item) {
+                        Ok(()) => {
+                            self.config.shortcuts.save();
+                            HotkeyConflict(None)
+                        }
+                        Err(other) => HotkeyConflict(Some(other)),
+                    };
+                    match self.windows.get_id(WindowType::Main) {
+                        Some(id) => Task::done(AppEvent::WindowEvent(
+                            id,
+                            WindowMessage::Main(MainWindowEvent::PopupMessage(AnyRef::new(
+                                conflict,
+                            ))),
+                        )),
+                        None => Task::none(),
                     }
-                    Task::none()
                 } else if item == self.config.shortcuts.pause
                     || item == self.config.shortcuts.record
                 {
-                    Task::done(AppEvent::CasterToggleStreaming)
+                    // Passa dalla finestra principale per l'eventuale conto alla rovescia
+                    match self.windows.get_id(WindowType::Main) {
+                        Some(id) => Task::done(AppEvent::WindowEvent(
+                            id,
+                            WindowMessage::Main(MainWindowEvent::CasterToggleStreaming),
+                        )),
+                        None => Task::done(AppEvent::CasterToggleStreaming),
+                    }
                 } else if item == self.config.shortcuts.blank_screen {
                     Task::done(AppEvent::BlankScreen)
+                } else if item == self.config.shortcuts.cursor_highlight {
+                    Task::done(AppEvent::ToggleCursorHighlight)
+                } else if item == self.config.shortcuts.cursor_capture {
+                    Task::done(AppEvent::ToggleCursorCapture)
+                } else if item == self.config.shortcuts.keycast {
+                    Task::done(AppEvent::ToggleKeycast)
+                } else if item == self.config.shortcuts.follow_lock {
+                    Task::done(AppEvent::ToggleFollowLock)
+                } else if item == self.config.shortcuts.zoom {
+                    Task::done(AppEvent::ToggleZoom)
+                } else if item == self.config.shortcuts.debug_overlay {
+                    Task::done(AppEvent::ToggleDebugOverlay)
+                } else if item == self.config.shortcuts.cycle_display {
+                    Task::done(AppEvent::CycleDisplay)
+                } else if item == self.config.shortcuts.mute {
+                    Task::done(AppEvent::ToggleAudioMute)
+                } else if item == self.config.shortcuts.annotations {
+                    Task::done(AppEvent::ToggleAnnotationWindow)
+                } else if item == self.config.shortcuts.screenshot {
+                    match self.windows.get_id(WindowType::Main) {
+                        Some(id) => Task::done(AppEvent::WindowEvent(
+                            id,
+                            WindowMessage::Main(MainWindowEvent::Screenshot),
+                        )),
+                        None => Task::none(),
+                    }
+                } else if item == self.config.shortcuts.chapter_mark {
+                    match self.windows.get_id(WindowType::Main) {
+                        Some(id) => Task::done(AppEvent::WindowEvent(
+                            id,
+                            WindowMessage::Main(MainWindowEvent::ChapterMark),
+                        )),
+                        None => Task::none(),
+                    }
                 } else if item == self.config.shortcuts.end_session {
                     Task::done(AppEvent::ExitApp)
+                } else if let Some((dx, dy)) = zoom_nudge(&item, &self.config.shortcuts.zoom) {
+                    // Stessi modificatori della lente + frecce: la sposta
+                    Task::done(AppEvent::NudgeZoom(dx, dy))
                 } else {
                     Task::none()
                 }
             }
             AppEvent::ExitApp => {
+                if self.windows.contains(WindowType::Main) {
+                    self.config.window.save();
+                }
+                // Il registratore deve scrivere il trailer prima dell'uscita,
+                // altrimenti l'MP4 resta senza moov e non è riproducibile
+                match self.config.shutdown() {
+                    Some(recording) => Task::perform(
+                        async move {
+                            if tokio::time::timeout(SHUTDOWN_GRACE, recording)
+                                .await
+                                .is_err()
+                            {
+                                log::warn!("Recording not finalized within {:?}", SHUTDOWN_GRACE);
+                            }
+                        },
+                        |_| AppEvent::Terminate,
+                    ),
+                    None => Task::done(AppEvent::Terminate),
+                }
+            }
+            AppEvent::Terminate => {
                 for (id, _) in self.windows.iter() {
                     let _: Task<AppEvent> = window::close(*id);
                 }
-                self.config.reset_mode();
                 self.config.sos.cancel();
                 exit(0)
             }
+            // Ridisegna i VU meter; il caster controlla il monitor col puntatore
+            AppEvent::TimeTickFPS => {
+                if let Some(crate::config::Mode::Caster(caster)) = &mut self.config.mode {
+                    caster.check_follow_cursor();
+                }
+                Task::none()
+            }
             AppEvent::Ignore => Task::none(),
             _ => Task::none(),
         }
@@ -420,6 +681,9 @@ impl App {
             Subscription::run(tray_menu_listener),
             Subscription::run(tray_icon_listener),
             iced::time::every(Duration::from_secs(1)).map(|_| AppEvent::TimeTick),
+            iced::time::every(SYSTEM_THEME_POLL).map(|_| AppEvent::SystemThemeTick),
+            self.level_meter_subscription(),
+            self.thumbnail_subscription(),
             Subscription::run(ipc),
             self.keyboard_subscription(),
             self.window_subscription(),
@@ -432,8 +696,42 @@ impl App {
         Subscription::batch(batch)
     }
 
+    /// Tick veloce per i VU meter e il follow del puntatore, solo durante lo stream.
+    fn level_meter_subscription(&self) -> Subscription<AppEvent> {
+        let streaming = match &self.config.mode {
+            Some(crate::config::Mode::Caster(caster)) => caster.is_streaming(),
+            Some(crate::config::Mode::Receiver(receiver)) => receiver.is_streaming(),
+            None => false,
+        };
+
+        if streaming {
+            iced::time::every(Duration::from_millis(100)).map(|_| AppEvent::TimeTickFPS)
+        } else {
+            Subscription::none()
+        }
+    }
+
+    /// Miniature dei display, solo mentre il caster sceglie cosa trasmettere.
+    fn thumbnail_subscription(&self) -> Subscription<AppEvent> {
+        match &self.config.mode {
+            Some(crate::config::Mode::Caster(caster)) if !caster.is_streaming() => {
+                iced::time::every(THUMBNAIL_REFRESH).map(|_| AppEvent::DisplayThumbnailsTick)
+            }
+            _ => Subscription::none(),
+        }
+    }
+
     fn keyboard_subscription(&self) -> Subscription<AppEvent> {
-        iced::event::listen_with(|event, _status, _id| match event {
+        iced::event::listen_with(|event, _status, id| match event {
+            // Invio/Esc confermano o chiudono il modal della finestra che li riceve
+            Keyboard(Event::KeyPressed {
+                key: Key::Named(key @ (Named::Enter | Named::Escape)),
+                modifiers,
+                ..
+            }) if modifiers.is_empty() => Some(AppEvent::WindowEvent(
+                id,
+                WindowMessage::Main(MainWindowEvent::PopupKey(key)),
+            )),
             Keyboard(Event::KeyReleased { key, modifiers, .. }) => {
                 if modifiers == Modifiers::empty() && !valid_iced_key(key.clone()) {
                     None
@@ -453,6 +751,7 @@ impl App {
                 size.width as u32,
                 size.height as u32,
             )),
+            Window(window::Event::Moved(position)) => Some(AppEvent::WindowMoved(id, position)),
             _ => None,
         })
     }
@@ -12,9 +12,63 @@ pub enum KeyTypes {
     Record,
     Close,
     BlankScreen,
+    CursorHighlight,
+    CursorCapture,
+    Keycast,
+    FollowLock,
+    Zoom,
+    DebugOverlay,
+    CycleDisplay,
+    Screenshot,
+    Mute,
+    Annotations,
+    ChapterMark,
     None,
 }
 
+impl KeyTypes {
+    /// Tutte le azioni configurabili (senza `None`)
+    pub const ALL: [KeyTypes; 15] = [
+        KeyTypes::Pause,
+        KeyTypes::Record,
+        KeyTypes::Close,
+        KeyTypes::BlankScreen,
+        KeyTypes::CursorHighlight,
+        KeyTypes::CursorCapture,
+        KeyTypes::Keycast,
+        KeyTypes::FollowLock,
+        KeyTypes::Zoom,
+        KeyTypes::DebugOverlay,
+        KeyTypes::CycleDisplay,
+        KeyTypes::Screenshot,
+        KeyTypes::Mute,
+        KeyTypes::Annotations,
+        KeyTypes::ChapterMark,
+    ];
+
+    /// Chiave stabile nel file di configurazione
+    pub fn config_key(&self) -> &'static str {
+        match self {
+            KeyTypes::Pause => "pause",
+            KeyTypes::Record => "record",
+            KeyTypes::Close => "end_session",
+            KeyTypes::BlankScreen => "blank_screen",
+            KeyTypes::CursorHighlight => "cursor_highlight",
+            KeyTypes::CursorCapture => "cursor_capture",
+            KeyTypes::Keycast => "keycast",
+            KeyTypes::FollowLock => "follow_lock",
+            KeyTypes::Zoom => "zoom",
+            KeyTypes::DebugOverlay => "debug_overlay",
+            KeyTypes::CycleDisplay => "cycle_display",
+            KeyTypes::Screenshot => "screenshot",
+            KeyTypes::Mute => "mute",
+            KeyTypes::Annotations => "annotations",
+            KeyTypes::ChapterMark => "chapter_mark",
+            KeyTypes::None => "none",
+        }
+    }
+}
+
 pub fn hotkeys<'a>() -> Element<'a, MainWindowEvent> {
     let header = Container::new(
         crate::row![
@@ -50,6 +104,14 @@ pub fn hotkeys<'a>() -> Element<'a, MainWindowEvent> {
                         .build()
                         .width(160)
                         .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::Record))
+                )
+                .push(
+                    IconButton::new()
+                        .label("Chapter Mark")
+                        .icon(Icon::Clock)
+                        .build()
+                        .width(160)
+                        .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::ChapterMark))
                 ),
             Row::new()
                 .align_y(Alignment::Center)
@@ -70,13 +132,102 @@ pub fn hotkeys<'a>() -> Element<'a, MainWindowEvent> {
                         .width(160)
                         .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::BlankScreen))
                 )
+                .push(
+                    IconButton::new()
+                        .label("Mute Audio")
+                        .icon(Icon::VolumeMute)
+                        .build()
+                        .width(160)
+                        .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::Mute))
+                ),
+            Row::new()
+                .align_y(Alignment::Center)
+                .spacing(15)
+                .push(
+                    IconButton::new()
+                        .label("Cursor")
+                        .icon(Icon::Circle)
+                        .build()
+                        .width(160)
+                        .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::CursorHighlight))
+                )
+                .push(
+                    IconButton::new()
+                        .label("Hide Cursor")
+                        .icon(Icon::Banned)
+                        .build()
+                        .width(160)
+                        .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::CursorCapture))
+                )
+                .push(
+                    IconButton::new()
+                        .label("Keycast")
+                        .icon(Icon::Keyboard)
+                        .build()
+                        .width(160)
+                        .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::Keycast))
+                ),
+            Row::new()
+                .align_y(Alignment::Center)
+                .spacing(15)
+                .push(
+                    IconButton::new()
+                        .label("Follow Lock")
+                        .icon(Icon::Area)
+                        .build()
+                        .width(160)
+                        .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::FollowLock))
+                )
+                .push(
+                    IconButton::new()
+                        .label("Zoom")
+                        .icon(Icon::Screen)
+                        .build()
+                        .width(160)
+                        .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::Zoom))
+                )
+                .push(
+                    IconButton::new()
+                        .label("Annotations")
+                        .icon(Icon::Pencil)
+                        .build()
+                        .width(160)
+                        .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::Annotations))
+                ),
+            Row::new()
+                .align_y(Alignment::Center)
+                .spacing(15)
+                .push(
+                    IconButton::new()
+                        .label("Debug Stats")
+                        .icon(Icon::Info)
+                        .build()
+                        .width(160)
+                        .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::DebugOverlay))
+                )
+                .push(
+                    IconButton::new()
+                        .label("Next Display")
+                        .icon(Icon::Screen)
+                        .build()
+                        .width(160)
+                        .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::CycleDisplay))
+                )
+                .push(
+                    IconButton::new()
+                        .label("Screenshot")
+                        .icon(Icon::Image)
+                        .build()
+                        .width(160)
+                        .on_press(MainWindowEvent::HotkeysTypePage(KeyTypes::Screenshot))
+                )
         ]
         .width(Length::Fill)
         .align_x(Alignment::Center)
         .spacing(15),
     )
     .center(Length::Fill)
-    .height(160)
+    .height(330)
     .class(ContainerType::Standard);
 
     let actions = Container::new(
@@ -87,8 +238,14 @@ pub fn hotkeys<'a>() -> Element<'a, MainWindowEvent> {
                 .icon(Icon::Home)
                 .build()
                 .on_press(MainWindowEvent::Home),
+            IconButton::new()
+                .label("Reset Defaults")
+                .icon(Icon::Sync)
+                .build()
+                .on_press(MainWindowEvent::HotkeysReset),
             horizontal_space().width(Length::Fill),
         ]
+        .spacing(15)
         .align_y(Alignment::Center),
     )
     .center(Length::Fill)
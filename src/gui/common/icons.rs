@@ -45,6 +45,10 @@ pub enum Icon {
     Ok,
     VolumeHigh,
     VolumeMute,
+    Keyboard,
+    Undo,
+    Redo,
+    Trash,
 }
 
 impl Icon {
@@ -91,6 +95,10 @@ impl Icon {
             Icon::Ok => '\u{f058}',
             Icon::VolumeHigh => '\u{f028}',
             Icon::VolumeMute => '\u{f6a9}',
+            Icon::Keyboard => '\u{f11c}',
+            Icon::Undo => '\u{f2ea}',
+            Icon::Redo => '\u{f2f9}',
+            Icon::Trash => '\u{f1f8}',
         }
     }
 
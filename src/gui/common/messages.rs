@@ -1,5 +1,6 @@
 use crate::gui::common::datastructure::ScreenRect;
 use crate::gui::windows::WindowMessage;
+use iced::Point;
 use iced::keyboard::{Key, Modifiers};
 use iced::window::Id;
 
@@ -7,20 +8,30 @@ use iced::window::Id;
 pub enum AppEvent {
     /// Open Main Window
     OpenMainWindow,
+    /// Open a castify:// connection link in the main window
+    OpenConnectionLink(String),
+    /// Connect the main window to a caster address (`--connect`)
+    OpenCasterAddress(String),
     /// Open Annotation Window
     OpenAnnotationWindow,
+    /// Show/hide the annotation window, keeping tool, color and strokes
+    ToggleAnnotationWindow,
     /// Close an app window
     CloseWindow(Id),
     /// Specified Window Event Message
     WindowEvent(Id, WindowMessage),
     /// The app window size has been changed
     WindowResized(Id, u32, u32),
+    /// The app window has been moved
+    WindowMoved(Id, Point),
     /// Time tick update
     TimeTick,
     /// Ignore
     Ignore,
     /// Quit the app
     ExitApp,
+    /// Orderly teardown completed: close the windows and exit
+    Terminate,
     /// Open the supplied web page
     OpenWebPage(String),
     /// blank the recording
@@ -39,4 +50,26 @@ pub enum AppEvent {
     TimeTickFPS,
     /// Toggle audio mute
     ToggleAudioMute,
+    /// Toggle the cursor highlight overlay on the caster
+    ToggleCursorHighlight,
+    /// Show/hide the mouse pointer in the captured frames
+    ToggleCursorCapture,
+    /// Toggle the keycast overlay on the caster
+    ToggleKeycast,
+    /// Lock/unlock the crop while following the active window
+    ToggleFollowLock,
+    /// Apre/chiude la lente d'ingrandimento sullo stream
+    ToggleZoom,
+    /// Sposta la lente di uno scatto (x, y) con le frecce
+    NudgeZoom(i32, i32),
+    /// Show/hide the receiver per-stage latency overlay
+    ToggleDebugOverlay,
+    /// Switch the caster to the next display, even while streaming
+    CycleDisplay,
+    /// Check the OS light/dark preference
+    SystemThemeTick,
+    /// OS light/dark preference (true = dark)
+    SystemTheme(bool),
+    /// Refresh the display selector thumbnails
+    DisplayThumbnailsTick,
 }
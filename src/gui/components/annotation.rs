@@ -1,3 +1,5 @@
+use crate::display::VideoFit;
+use crate::utils::net::webrtc::{RemoteStroke, StrokeKind};
 use iced::Renderer;
 use iced::keyboard::key::Named;
 use iced::keyboard::{Event, Key};
@@ -5,7 +7,7 @@ use iced::mouse::{Cursor, Interaction};
 use iced::widget::Action;
 use iced::widget::canvas;
 use iced::widget::canvas::{Frame, Geometry, Path, Stroke};
-use iced::{Color, Point, Rectangle, mouse};
+use iced::{Color, Point, Rectangle, Size, Vector, mouse};
 use iced_graphics::geometry::LineJoin;
 use iced_graphics::geometry::Style::Solid;
 use iced_graphics::geometry::path::Builder;
@@ -76,10 +78,33 @@ impl ShapeStroke {
 pub struct AnnotationState {
     pub updating: bool,
     pub points: Vec<Point>,
+}
+
+/// Eventi del tratto in corso, pubblicati dal canvas verso la finestra.
+#[derive(Debug, Clone)]
+pub enum StrokeEvent {
+    /// Inizio del tratto, con le dimensioni correnti del canvas
+    Begin(Size),
+    /// Gomma trascinata su un punto, con il raggio di cancellazione
+    Erase(Point, f32),
+    /// Fine del tratto
+    End(Shape, Vec<Point>),
+}
+
+/// Disegni completati, con stack di undo/redo.
+///
+/// Prima di ogni tratto viene salvato uno snapshot di `shapes`: in questo
+/// modo anche la gomma (che modifica le forme esistenti) è annullabile.
+#[derive(Default, Clone)]
+pub struct AnnotationHistory {
     pub shapes: Vec<(Shape, Vec<Point>)>,
+    undo: Vec<Vec<(Shape, Vec<Point>)>>,
+    redo: Vec<Vec<(Shape, Vec<Point>)>>,
 }
 
-impl AnnotationState {
+impl AnnotationHistory {
+    const MAX_UNDO: usize = 64;
+
     // Additional method to check if two points are close enough to be considered an overlap
     fn is_near(point1: Point, point2: Point, threshold: f32) -> bool {
         let dx = point1.x - point2.x;
@@ -87,6 +112,17 @@ impl AnnotationState {
         (dx * dx + dy * dy).sqrt() < threshold
     }
 
+    fn push_undo(&mut self, snapshot: Vec<(Shape, Vec<Point>)>) {
+        self.undo.push(snapshot);
+        if self.undo.len() > Self::MAX_UNDO {
+            self.undo.remove(0);
+        }
+    }
+
+    pub fn begin_stroke(&mut self) {
+        self.push_undo(self.shapes.clone());
+    }
+
     // Method to erase shapes near a given point
     pub fn erase_at(&mut self, eraser_point: Point, eraser_size: f32) {
         self.shapes.retain(|(_, shape_points)| {
@@ -96,6 +132,145 @@ impl AnnotationState {
                 .any(|point| Self::is_near(*point, eraser_point, eraser_size))
         });
     }
+
+    pub fn end_stroke(&mut self, shape: Shape, points: Vec<Point>) {
+        if shape.s_type != ShapeType::Eraser && points.len() >= 2 {
+            self.shapes.push((shape, points));
+        }
+
+        // Tratto senza effetti (click singolo, gomma a vuoto): lo snapshot non serve
+        let changed = self
+            .undo
+            .last()
+            .is_none_or(|snapshot| snapshot.len() != self.shapes.len());
+        if changed {
+            self.redo.clear();
+        } else {
+            self.undo.pop();
+        }
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(previous) = self.undo.pop() {
+            self.redo
+                .push(std::mem::replace(&mut self.shapes, previous));
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(next) = self.redo.pop() {
+            let current = std::mem::replace(&mut self.shapes, next);
+            self.push_undo(current);
+        }
+    }
+
+    pub fn clear_all(&mut self) {
+        if !self.shapes.is_empty() {
+            let current = std::mem::take(&mut self.shapes);
+            self.push_undo(current);
+            self.redo.clear();
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+// ── Annotazioni remote ──────────────────────────────────────────
+
+/// Tratto da inviare ai receiver. `area` è la regione del canvas locale che
+/// corrisponde al frame trasmesso; la gomma non lascia tratti.
+pub fn remote_stroke(shape: &Shape, points: &[Point], area: Rectangle) -> Option<RemoteStroke> {
+    let kind = match shape.s_type {
+        ShapeType::Personal => StrokeKind::Freehand,
+        ShapeType::Line => StrokeKind::Line,
+        ShapeType::Rectangle => StrokeKind::Rectangle,
+        ShapeType::Circle => StrokeKind::Circle,
+        ShapeType::Eraser => return None,
+    };
+    let color = shape.color.into_iced_color(shape.is_solid);
+    Some(RemoteStroke {
+        kind,
+        color: [color.r, color.g, color.b, color.a],
+        width: shape.stroke.f32(),
+        filled: shape.is_filled,
+        points: points
+            .iter()
+            .map(|p| [(p.x - area.x) / area.width, (p.y - area.y) / area.height])
+            .collect(),
+        ref_width: area.width,
+    })
+}
+
+/// Forma e punti di un tratto ricevuto, nel canvas di dimensioni `size`
+fn local_shape(stroke: &RemoteStroke, size: Size) -> (Shape, Vec<Point>) {
+    let s_type = match stroke.kind {
+        StrokeKind::Freehand => ShapeType::Personal,
+        StrokeKind::Line => ShapeType::Line,
+        StrokeKind::Rectangle => ShapeType::Rectangle,
+        StrokeKind::Circle => ShapeType::Circle,
+    };
+    let shape = Shape {
+        s_type,
+        color: ShapeColor::Custom(Color::from(stroke.color)),
+        is_filled: stroke.filled,
+        is_solid: true,
+        ..Shape::default()
+    };
+    let points = stroke
+        .points
+        .iter()
+        .map(|[x, y]| Point::new(x * size.width, y * size.height))
+        .collect();
+    (shape, points)
+}
+
+/// Disegna le annotazioni ricevute dal caster sopra il video.
+pub struct RemoteAnnotations {
+    strokes: Vec<RemoteStroke>,
+    fit: VideoFit,
+    /// Dimensioni del frame: con le bande le annotazioni restano sull'immagine
+    frame_size: (i32, i32),
+}
+
+impl RemoteAnnotations {
+    pub fn new(strokes: Vec<RemoteStroke>, fit: VideoFit, frame_size: (i32, i32)) -> Self {
+        Self {
+            strokes,
+            fit,
+            frame_size,
+        }
+    }
+}
+
+impl<Message, Theme> canvas::Program<Message, Theme> for RemoteAnnotations {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let area = self
+            .fit
+            .inner_rect(Rectangle::with_size(bounds.size()), self.frame_size);
+        frame.translate(Vector::new(area.x, area.y));
+        for stroke in &self.strokes {
+            let scale = area.width / stroke.ref_width.max(1.0);
+            let (shape, points) = local_shape(stroke, area.size());
+            draw_shape(&mut frame, &shape, &points, stroke.width * scale);
+        }
+        vec![frame.into_geometry()]
+    }
 }
 
 pub fn get_draw_points(point1: Point, point2: Point) -> (Point, Point) {
@@ -110,18 +285,26 @@ pub fn get_draw_points(point1: Point, point2: Point) -> (Point, Point) {
     (start, end)
 }
 
-pub struct Annotation<Message> {
+pub struct Annotation<'a, Message> {
     on_esc: Option<Message>,
+    on_undo: Option<Message>,
+    on_redo: Option<Message>,
+    on_stroke: Option<Box<dyn Fn(StrokeEvent) -> Message + 'a>>,
     cache: canvas::Cache,
     shape: Shape,
+    shapes: &'a [(Shape, Vec<Point>)],
 }
 
-impl<Message> Annotation<Message> {
-    pub fn new(shape: Shape) -> Self {
+impl<'a, Message> Annotation<'a, Message> {
+    pub fn new(shape: Shape, shapes: &'a [(Shape, Vec<Point>)]) -> Self {
         Self {
             on_esc: None,
+            on_undo: None,
+            on_redo: None,
+            on_stroke: None,
             cache: Default::default(),
             shape,
+            shapes,
         }
     }
 
@@ -129,9 +312,33 @@ impl<Message> Annotation<Message> {
         self.on_esc = Some(message);
         self
     }
+
+    /// Ctrl+Z
+    pub fn on_undo(mut self, message: Message) -> Self {
+        self.on_undo = Some(message);
+        self
+    }
+
+    /// Ctrl+Y / Ctrl+Shift+Z
+    pub fn on_redo(mut self, message: Message) -> Self {
+        self.on_redo = Some(message);
+        self
+    }
+
+    pub fn on_stroke(mut self, f: impl Fn(StrokeEvent) -> Message + 'a) -> Self {
+        self.on_stroke = Some(Box::new(f));
+        self
+    }
+
+    fn publish_stroke(&self, event: StrokeEvent) -> Option<Action<Message>> {
+        match &self.on_stroke {
+            Some(f) => Some(Action::publish(f(event))),
+            None => Some(Action::request_redraw()),
+        }
+    }
 }
 
-impl<Message: Clone, Theme> canvas::Program<Message, Theme> for Annotation<Message> {
+impl<Message: Clone, Theme> canvas::Program<Message, Theme> for Annotation<'_, Message> {
     type State = AnnotationState;
 
     fn update(
@@ -144,25 +351,30 @@ impl<Message: Clone, Theme> canvas::Program<Message, Theme> for Annotation<Messa
         let cursor_position = cursor.position_in(bounds)?;
 
         match event {
-            iced::Event::Keyboard(Event::KeyPressed { key, .. }) => {
-                if *key == Key::Named(Named::Escape) {
-                    self.on_esc
-                        .clone()
-                        .map(|m| Action::publish(m).and_capture())
-                } else {
-                    None
-                }
+            iced::Event::Keyboard(Event::KeyPressed { key, modifiers, .. }) => {
+                let undo_redo = match key.as_ref() {
+                    Key::Character("z") if modifiers.command() && modifiers.shift() => {
+                        &self.on_redo
+                    }
+                    Key::Character("z") if modifiers.command() => &self.on_undo,
+                    Key::Character("y") if modifiers.command() => &self.on_redo,
+                    Key::Named(Named::Escape) => &self.on_esc,
+                    _ => &None,
+                };
+                undo_redo.clone().map(|m| Action::publish(m).and_capture())
             }
             iced::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
                 state.updating = true;
                 state.points.push(cursor_position);
-                Some(Action::request_redraw())
+                self.publish_stroke(StrokeEvent::Begin(bounds.size()))
             }
             iced::Event::Mouse(mouse::Event::CursorMoved { .. }) => {
                 if state.updating {
                     if self.shape.s_type == ShapeType::Eraser {
-                        state.erase_at(cursor_position, self.shape.stroke.f32() * 5.0);
-                        return Some(Action::request_redraw());
+                        return self.publish_stroke(StrokeEvent::Erase(
+                            cursor_position,
+                            self.shape.stroke.f32() * 5.0,
+                        ));
                     }
 
                     state.points.push(cursor_position);
@@ -172,13 +384,14 @@ impl<Message: Clone, Theme> canvas::Program<Message, Theme> for Annotation<Messa
                 }
             }
             iced::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                if !state.updating {
+                    return None;
+                }
                 state.updating = false;
-                state.shapes.push((self.shape, state.points.clone()));
-
-                state.points.clear();
+                let points = std::mem::take(&mut state.points);
                 self.cache.clear();
 
-                Some(Action::request_redraw())
+                self.publish_stroke(StrokeEvent::End(self.shape, points))
             }
             _ => None,
         }
@@ -193,14 +406,19 @@ impl<Message: Clone, Theme> canvas::Program<Message, Theme> for Annotation<Messa
         cursor: Cursor,
     ) -> Vec<Geometry> {
         let shapes_frame = self.cache.draw(renderer, bounds.size(), |frame| {
-            for (shape, points) in &state.shapes {
-                draw_shape(frame, shape, points);
+            for (shape, points) in self.shapes {
+                draw_shape(frame, shape, points, shape.stroke.f32());
             }
         });
 
         let mut frame = Frame::new(renderer, bounds.size());
 
-        draw_shape(&mut frame, &self.shape, &state.points);
+        draw_shape(
+            &mut frame,
+            &self.shape,
+            &state.points,
+            self.shape.stroke.f32(),
+        );
 
         if self.shape.s_type == ShapeType::Eraser
             && let Some(cursor_pos) = cursor.position_in(bounds)
@@ -224,7 +442,8 @@ impl<Message: Clone, Theme> canvas::Program<Message, Theme> for Annotation<Messa
     }
 }
 
-fn draw_shape(frame: &mut Frame, shape: &Shape, points: &[Point]) {
+/// `width` è lo spessore del tratto in pixel di `frame`
+fn draw_shape(frame: &mut Frame, shape: &Shape, points: &[Point], width: f32) {
     if points.len() >= 2 {
         let color = shape.color.into_iced_color(shape.is_solid);
         match &shape.s_type {
@@ -238,7 +457,7 @@ fn draw_shape(frame: &mut Frame, shape: &Shape, points: &[Point]) {
                     frame.fill(&path, fill);
                 } else {
                     let stroke = Stroke::default()
-                        .with_width(shape.stroke.f32())
+                        .with_width(width)
                         .with_color(color)
                         .with_line_join(LineJoin::Round);
                     frame.stroke(&path, stroke);
@@ -246,9 +465,7 @@ fn draw_shape(frame: &mut Frame, shape: &Shape, points: &[Point]) {
             }
             ShapeType::Line => {
                 let path = Path::line(*points.first().unwrap(), *points.last().unwrap());
-                let stroke = Stroke::default()
-                    .with_width(shape.stroke.f32())
-                    .with_color(color);
+                let stroke = Stroke::default().with_width(width).with_color(color);
                 frame.stroke(&path, stroke);
             }
             ShapeType::Personal => {
@@ -265,7 +482,7 @@ fn draw_shape(frame: &mut Frame, shape: &Shape, points: &[Point]) {
                         &path,
                         Stroke {
                             style: Solid(color),
-                            width: shape.stroke.f32(),
+                            width,
                             line_cap: Default::default(),
                             line_join: LineJoin::Round,
                             line_dash: Default::default(),
@@ -283,9 +500,7 @@ fn draw_shape(frame: &mut Frame, shape: &Shape, points: &[Point]) {
                     let fill = iced::widget::canvas::Fill::from(color);
                     frame.fill(&path, fill);
                 } else {
-                    let stroke = Stroke::default()
-                        .with_width(shape.stroke.f32())
-                        .with_color(color);
+                    let stroke = Stroke::default().with_width(width).with_color(color);
                     frame.stroke(&path, stroke);
                 }
             }
@@ -0,0 +1,194 @@
+use crate::gui::components::annotation::{Shape, ShapeType, get_draw_points};
+use anyhow::Context;
+use iced::{Point, Size};
+use std::fs::File;
+use std::io::BufWriter;
+
+/// Copertura (0.0..=1.0) di una singola forma sul suo bounding box.
+///
+/// Ogni forma viene prima rasterizzata nella maschera e poi composta una
+/// sola volta: i segmenti sovrapposti di un tratto a mano libera non
+/// accumulano opacità, come succede nel canvas.
+struct Mask {
+    x0: i32,
+    y0: i32,
+    w: i32,
+    h: i32,
+    data: Vec<f32>,
+}
+
+impl Mask {
+    fn new(points: &[Point], pad: f32, img_w: i32, img_h: i32) -> Option<Self> {
+        let (mut min_x, mut min_y) = (f32::MAX, f32::MAX);
+        let (mut max_x, mut max_y) = (f32::MIN, f32::MIN);
+        for p in points {
+            min_x = min_x.min(p.x);
+            min_y = min_y.min(p.y);
+            max_x = max_x.max(p.x);
+            max_y = max_y.max(p.y);
+        }
+
+        let x0 = ((min_x - pad).floor() as i32).max(0);
+        let y0 = ((min_y - pad).floor() as i32).max(0);
+        let x1 = ((max_x + pad).ceil() as i32).min(img_w);
+        let y1 = ((max_y + pad).ceil() as i32).min(img_h);
+        if x1 <= x0 || y1 <= y0 {
+            return None;
+        }
+
+        let (w, h) = (x1 - x0, y1 - y0);
+        Some(Self {
+            x0,
+            y0,
+            w,
+            h,
+            data: vec![0.0; (w * h) as usize],
+        })
+    }
+
+    /// Applica `coverage(px, py)` (centro del pixel) a tutta la maschera.
+    fn apply(&mut self, coverage: impl Fn(f32, f32) -> f32) {
+        for y in 0..self.h {
+            for x in 0..self.w {
+                let c = coverage((self.x0 + x) as f32 + 0.5, (self.y0 + y) as f32 + 0.5);
+                let v = &mut self.data[(y * self.w + x) as usize];
+                *v = v.max(c.clamp(0.0, 1.0));
+            }
+        }
+    }
+
+    fn segment(&mut self, a: Point, b: Point, width: f32) {
+        let half = width / 2.0;
+        self.apply(|px, py| half - distance_to_segment(px, py, a, b) + 0.5);
+    }
+
+    fn composite(&self, rgba: &mut [u8], img_w: i32, color: iced::Color) {
+        for y in 0..self.h {
+            for x in 0..self.w {
+                let coverage = self.data[(y * self.w + x) as usize];
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let i = (((self.y0 + y) * img_w + self.x0 + x) * 4) as usize;
+                blend_over(&mut rgba[i..i + 4], color, color.a * coverage);
+            }
+        }
+    }
+}
+
+fn distance_to_segment(px: f32, py: f32, a: Point, b: Point) -> f32 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((px - a.x) * dx + (py - a.y) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (cx, cy) = (a.x + t * dx, a.y + t * dy);
+    ((px - cx).powi(2) + (py - cy).powi(2)).sqrt()
+}
+
+/// "Source over" su pixel RGBA non premoltiplicato.
+fn blend_over(dst: &mut [u8], color: iced::Color, alpha: f32) {
+    let dst_a = dst[3] as f32 / 255.0;
+    let out_a = alpha + dst_a * (1.0 - alpha);
+    if out_a <= 0.0 {
+        return;
+    }
+
+    let src = [color.r, color.g, color.b];
+    for c in 0..3 {
+        let d = dst[c] as f32 / 255.0;
+        let v = (src[c] * alpha + d * dst_a * (1.0 - alpha)) / out_a;
+        dst[c] = (v * 255.0).round() as u8;
+    }
+    dst[3] = (out_a * 255.0).round() as u8;
+}
+
+fn rasterize(rgba: &mut [u8], img_w: i32, img_h: i32, shape: &Shape, points: &[Point]) {
+    if points.len() < 2 {
+        return;
+    }
+
+    let color = shape.color.into_iced_color(shape.is_solid);
+    let width = shape.stroke.f32();
+    let (first, last) = (points[0], points[points.len() - 1]);
+
+    let mask = match shape.s_type {
+        ShapeType::Line => Mask::new(&[first, last], width, img_w, img_h).map(|mut m| {
+            m.segment(first, last, width);
+            m
+        }),
+        ShapeType::Personal => Mask::new(points, width, img_w, img_h).map(|mut m| {
+            for pair in points.windows(2) {
+                m.segment(pair[0], pair[1], width);
+            }
+            m
+        }),
+        ShapeType::Rectangle => {
+            let (tl, br) = get_draw_points(first, last);
+            Mask::new(&[tl, br], width, img_w, img_h).map(|mut m| {
+                if shape.is_filled {
+                    m.apply(|px, py| {
+                        let inside = px >= tl.x && px <= br.x && py >= tl.y && py <= br.y;
+                        if inside { 1.0 } else { 0.0 }
+                    });
+                } else {
+                    let (tr, bl) = (Point::new(br.x, tl.y), Point::new(tl.x, br.y));
+                    m.segment(tl, tr, width);
+                    m.segment(tr, br, width);
+                    m.segment(br, bl, width);
+                    m.segment(bl, tl, width);
+                }
+                m
+            })
+        }
+        ShapeType::Circle => {
+            let radius = first.distance(last);
+            let bounds = [
+                Point::new(first.x - radius, first.y - radius),
+                Point::new(first.x + radius, first.y + radius),
+            ];
+            Mask::new(&bounds, width, img_w, img_h).map(|mut m| {
+                let half = width / 2.0;
+                m.apply(|px, py| {
+                    let d = ((px - first.x).powi(2) + (py - first.y).powi(2)).sqrt();
+                    if shape.is_filled {
+                        radius - d + 0.5
+                    } else {
+                        half - (d - radius).abs() + 0.5
+                    }
+                });
+                m
+            })
+        }
+        ShapeType::Eraser => None,
+    };
+
+    if let Some(mask) = mask {
+        mask.composite(rgba, img_w, color);
+    }
+}
+
+/// Salva le annotazioni come PNG trasparente delle dimensioni del canvas,
+/// da usare come overlay sopra la registrazione.
+pub fn export_png(shapes: &[(Shape, Vec<Point>)], size: Size, path: &str) -> anyhow::Result<()> {
+    let (w, h) = (size.width.round() as i32, size.height.round() as i32);
+    anyhow::ensure!(w > 0 && h > 0, "Invalid canvas size {}x{}", w, h);
+
+    let mut rgba = vec![0u8; (w * h * 4) as usize];
+    for (shape, points) in shapes {
+        rasterize(&mut rgba, w, h, shape, points);
+    }
+
+    let file = File::create(path).with_context(|| format!("Unable to create {}", path))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), w as u32, h as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&rgba))
+        .context("PNG encoding failed")?;
+
+    Ok(())
+}
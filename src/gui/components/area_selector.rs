@@ -7,10 +7,51 @@ use iced::mouse::{Cursor, Interaction};
 use iced::widget::Action;
 use iced::widget::canvas;
 use iced::widget::canvas::{Frame, Geometry, Path, Stroke};
-use iced::{Color, Point, Rectangle, Size, mouse};
+use iced::{Color, Point, Rectangle, Size, Vector, mouse};
 use iced_graphics::geometry;
 use iced_graphics::geometry::{LineCap, LineDash, LineJoin, Style};
 
+/// Lato minimo (in pixel) di una selezione valida
+pub const MIN_AREA: f32 = 50.0;
+
+/// Vincolo sulle proporzioni della selezione
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AspectLock {
+    #[default]
+    Free,
+    Wide,
+    Standard,
+}
+
+impl AspectLock {
+    /// Rapporto larghezza/altezza, `None` se libero
+    pub fn ratio(self) -> Option<f32> {
+        match self {
+            AspectLock::Free => None,
+            AspectLock::Wide => Some(16.0 / 9.0),
+            AspectLock::Standard => Some(4.0 / 3.0),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            AspectLock::Free => AspectLock::Wide,
+            AspectLock::Wide => AspectLock::Standard,
+            AspectLock::Standard => AspectLock::Free,
+        }
+    }
+}
+
+impl std::fmt::Display for AspectLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AspectLock::Free => write!(f, "Free"),
+            AspectLock::Wide => write!(f, "16:9"),
+            AspectLock::Standard => write!(f, "4:3"),
+        }
+    }
+}
+
 #[derive(Default, Clone, Copy)]
 pub struct AreaSelectorState {
     pub updating: bool,
@@ -22,6 +63,8 @@ pub struct AreaSelector<'a, Message> {
     on_release_rect: Option<Box<dyn Fn(ScreenRect) -> Message + 'a>>,
     on_esc: Option<Message>,
     on_confirm: Option<Message>,
+    on_aspect: Option<Message>,
+    aspect: AspectLock,
 }
 
 impl<'a, Message> AreaSelector<'a, Message> {
@@ -30,9 +73,23 @@ impl<'a, Message> AreaSelector<'a, Message> {
             on_release_rect: None,
             on_esc: None,
             on_confirm: None,
+            on_aspect: None,
+            aspect: AspectLock::Free,
         }
     }
 
+    pub fn aspect(mut self, aspect: AspectLock) -> Self {
+        self.aspect = aspect;
+        self
+    }
+
+    /// Messaggio emesso premendo `A` per cambiare le proporzioni; la
+    /// selezione corrente viene azzerata
+    pub fn on_aspect(mut self, message: Message) -> Self {
+        self.on_aspect = Some(message);
+        self
+    }
+
     pub fn on_esc(mut self, message: Message) -> Self {
         self.on_esc = Some(message);
         self
@@ -51,10 +108,49 @@ impl<'a, Message> AreaSelector<'a, Message> {
         self
     }
 
-    fn calc_rect(start: Option<Point>, end: Option<Point>) -> ScreenRect {
-        if let Some(start) = start
-            && let Some(end) = end
+    /// Sposta il vertice trascinato `end` così che il rettangolo ancorato a
+    /// `start` rispetti le proporzioni, resti dentro `bounds` e abbia lati
+    /// pari (NV12)
+    fn constrain(&self, start: Point, end: Point, bounds: Size) -> Point {
+        let (dx, dy) = (end.x - start.x, end.y - start.y);
+        let max_w = if dx < 0.0 {
+            start.x
+        } else {
+            bounds.width - start.x
+        };
+        let max_h = if dy < 0.0 {
+            start.y
+        } else {
+            bounds.height - start.y
+        };
+        let (mut w, mut h) = (dx.abs().min(max_w), dy.abs().min(max_h));
+
+        if let Some(ratio) = self.aspect.ratio() {
+            // Comanda il lato trascinato di più, poi si rientra nei bordi
+            if w / ratio > h {
+                h = w / ratio;
+            } else {
+                w = h * ratio;
+            }
+            if w > max_w {
+                w = max_w;
+                h = w / ratio;
+            }
+            if h > max_h {
+                h = max_h;
+                w = h * ratio;
+            }
+        }
+
+        let (w, h) = (even(w), even(h));
+        Point::new(start.x + w.copysign(dx), start.y + h.copysign(dy))
+    }
+
+    fn calc_rect(&self, state: &AreaSelectorState, bounds: Size) -> ScreenRect {
+        if let Some(start) = state.initial_pos
+            && let Some(end) = state.final_pos
         {
+            let end = self.constrain(start, end, bounds);
             return ScreenRect {
                 x: start.x.min(end.x),
                 y: start.y.min(end.y),
@@ -65,6 +161,34 @@ impl<'a, Message> AreaSelector<'a, Message> {
 
         ScreenRect::default()
     }
+
+    /// Dimensioni live della selezione, sotto il rettangolo (o sopra se
+    /// non c'è spazio); in rosso finché è più piccola di `MIN_AREA`
+    fn draw_readout(frame: &mut Frame, start: Point, end: Point, bounds: Rectangle) {
+        let (width, height) = (end.x - start.x, end.y - start.y);
+        let color = if width < MIN_AREA || height < MIN_AREA {
+            Color::from_rgb8(255, 90, 90)
+        } else {
+            Color::WHITE
+        };
+        let offset = if end.y + 24.0 < bounds.height {
+            Vector::new(0.0, 6.0)
+        } else {
+            Vector::new(0.0, -(height + 22.0))
+        };
+        frame.fill_text(canvas::Text {
+            content: format!("{} × {}", width as u32, height as u32),
+            position: Point::new(start.x, end.y) + offset,
+            color,
+            size: 14.into(),
+            ..canvas::Text::default()
+        });
+    }
+}
+
+/// Arrotonda per difetto al pixel pari
+fn even(v: f32) -> f32 {
+    ((v.max(0.0) as u32) & !1) as f32
 }
 
 impl<'a, Message: Clone, Theme> canvas::Program<Message, Theme> for AreaSelector<'a, Message> {
@@ -89,6 +213,10 @@ impl<'a, Message: Clone, Theme> canvas::Program<Message, Theme> for AreaSelector
                     self.on_confirm
                         .clone()
                         .map(|m| Action::publish(m).and_capture())
+                } else if matches!(key.as_ref(), Key::Character("a" | "A")) && !state.updating {
+                    let message = self.on_aspect.clone()?;
+                    *state = AreaSelectorState::default();
+                    Some(Action::publish(message).and_capture())
                 } else {
                     None
                 }
@@ -113,7 +241,7 @@ impl<'a, Message: Clone, Theme> canvas::Program<Message, Theme> for AreaSelector
                 let message = self
                     .on_release_rect
                     .as_ref()
-                    .map(|callback| callback(Self::calc_rect(state.initial_pos, state.final_pos)));
+                    .map(|callback| callback(self.calc_rect(state, bounds.size())));
 
                 match message {
                     Some(msg) => Some(Action::publish(msg).and_capture()),
@@ -137,6 +265,7 @@ impl<'a, Message: Clone, Theme> canvas::Program<Message, Theme> for AreaSelector
         let overlay = geometry::Fill::from(Color::from_rgba(0.0, 0.0, 0.0, 0.4));
 
         if let (Some(mut initial_pos), Some(mut final_pos)) = (state.initial_pos, state.final_pos) {
+            final_pos = self.constrain(initial_pos, final_pos, bounds.size());
             (initial_pos, final_pos) = evaluate_points(initial_pos, final_pos);
 
             let selection = Path::rectangle(initial_pos, (final_pos - initial_pos).into());
@@ -214,6 +343,10 @@ impl<'a, Message: Clone, Theme> canvas::Program<Message, Theme> for AreaSelector
                 },
             };
             frame.stroke(&selection, edge_stroke);
+
+            if state.updating {
+                Self::draw_readout(&mut frame, initial_pos, final_pos, bounds);
+            }
         } else {
             frame.fill_rectangle(Point::ORIGIN, bounds.size(), overlay);
         }
@@ -2,6 +2,7 @@ use crate::config::Config;
 use crate::gui::widget::{Column, Element};
 use castbox::AnyRef;
 use iced::Length;
+use iced::widget::Id;
 
 pub trait GuiInterface {
     type Message;
@@ -29,6 +30,21 @@ pub trait GuiInterface {
     fn on_close(&self) -> Option<Self::Message> {
         None
     }
+
+    /// Azione primaria, eseguita con Invio (es. connetti/conferma)
+    fn on_submit(&self) -> Option<Self::Message> {
+        None
+    }
+
+    /// Eseguita con Esc; `None` lascia chiudere il modal al chiamante
+    fn on_cancel(&self) -> Option<Self::Message> {
+        self.on_close()
+    }
+
+    /// Campo che riceve il focus all'apertura
+    fn focus(&self) -> Option<Id> {
+        None
+    }
 }
 
 pub trait GuiComponent {
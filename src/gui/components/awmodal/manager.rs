@@ -6,7 +6,8 @@ use crate::gui::style::container::ContainerType;
 use crate::gui::widget::{
     Column, Container, Element, IcedParentExt, Space, Text, horizontal_line, vertical_space,
 };
-use iced::Length;
+use iced::keyboard::key::Named;
+use iced::{Length, Task};
 use std::cell::UnsafeCell;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -44,6 +45,32 @@ where
         self.popup = Some(Arc::new(UnsafeCell::new(p0)));
     }
 
+    /// Sposta il focus sul campo principale del modal visibile
+    pub fn focus<T: Send + 'static>(&self) -> Task<T> {
+        if !self.is_visible() {
+            return Task::none();
+        }
+        match self.get_ref().and_then(|p| p.as_gui().focus()) {
+            Some(id) => iced::widget::operation::focus(id),
+            None => Task::none(),
+        }
+    }
+
+    /// Messaggio associato a Invio/Esc nel modal visibile: `Some(None)` se il
+    /// tasto è per il modal ma non ha un'azione propria, `None` se non c'è
+    /// nessun modal aperto
+    pub fn key_action(&self, key: Named) -> Option<Option<Message>> {
+        if !self.is_visible() {
+            return None;
+        }
+        let gui = self.get_ref()?.as_gui();
+        match key {
+            Named::Enter => Some(gui.on_submit()),
+            Named::Escape => Some(gui.on_cancel()),
+            _ => None,
+        }
+    }
+
     pub fn render<'a, 'b>(&'b self, config: &Config) -> Element<'a, Message>
     where
         'b: 'a,
@@ -0,0 +1,69 @@
+use crate::assets::FONT_FAMILY_BOLD;
+use crate::gui::common::icons::Icon;
+use crate::gui::components::button::IconButton;
+use crate::gui::style::container::ContainerType;
+use crate::gui::widget::{Column, Container, Element, Row, Scrollable, Text, TextInput};
+use crate::utils::net::webrtc::ChatMessage;
+use iced::{Alignment, Length};
+
+/// Storico della chat + campo di scrittura. Il campo invia con Invio.
+pub fn chat_panel<'a, Message: Clone + 'a>(
+    messages: Vec<ChatMessage>,
+    draft: &str,
+    on_input: impl Fn(String) -> Message + 'a,
+    on_send: Message,
+) -> Element<'a, Message> {
+    let history = if messages.is_empty() {
+        Column::new().push(Text::new("No messages yet").size(12))
+    } else {
+        messages
+            .into_iter()
+            .fold(Column::new().spacing(4), |history, message| {
+                let author = if message.local {
+                    String::from("You")
+                } else {
+                    message.author
+                };
+                history.push(
+                    Column::new()
+                        .push(Text::new(author).font(FONT_FAMILY_BOLD).size(11))
+                        .push(Text::new(message.text).size(13)),
+                )
+            })
+    };
+
+    let input = Row::new()
+        .spacing(6)
+        .align_y(Alignment::Center)
+        .push(
+            TextInput::new("Message", draft)
+                .on_input(on_input)
+                .on_submit(on_send.clone())
+                .padding([8, 8])
+                .width(Length::Fill),
+        )
+        .push(
+            IconButton::new()
+                .label("Send")
+                .icon(Icon::Ok)
+                .build()
+                .on_press(on_send),
+        );
+
+    Container::new(
+        Column::new()
+            .spacing(8)
+            .push(
+                Scrollable::new(history.padding([0, 6]))
+                    .anchor_bottom()
+                    .height(Length::Fill)
+                    .width(Length::Fill),
+            )
+            .push(input),
+    )
+    .padding(8)
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .class(ContainerType::Standard)
+    .into()
+}
@@ -0,0 +1,55 @@
+use crate::gui::style::container::ContainerType;
+use crate::gui::widget::{Column, Container, Element, Space, Stack};
+use crate::utils::audio_level::LevelSnapshot;
+use iced::Length;
+
+/// Intervallo mostrato dalla barra: sotto -60 dBFS è silenzio
+const FLOOR_DB: f32 = -60.0;
+const BAR_HEIGHT: f32 = 8.0;
+const PEAK_WIDTH: f32 = 2.0;
+
+/// Ampiezza lineare → frazione della barra (scala in dB).
+fn to_fraction(amplitude: f32) -> f32 {
+    if amplitude <= 0.0 {
+        return 0.0;
+    }
+    let db = 20.0 * amplitude.log10();
+    ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+}
+
+/// Barra del livello audio: riempimento RMS, tacca sul picco, rossa se in clipping.
+pub fn level_meter<'a, Message: 'a>(level: LevelSnapshot, width: f32) -> Element<'a, Message> {
+    let fill = Container::new(Column::new())
+        .width(width * to_fraction(level.rms))
+        .height(BAR_HEIGHT)
+        .class(if level.clipping {
+            ContainerType::LevelClip
+        } else {
+            ContainerType::LevelFill
+        });
+
+    let peak_x = ((width - PEAK_WIDTH) * to_fraction(level.peak)).max(0.0);
+    let peak = Container::new(
+        Container::new(Column::new())
+            .width(PEAK_WIDTH)
+            .height(BAR_HEIGHT)
+            .class(ContainerType::LevelClip),
+    )
+    .padding(iced::Padding {
+        left: peak_x,
+        ..iced::Padding::ZERO
+    });
+
+    let mut bar = Stack::new()
+        .push(Space::new().width(Length::Fixed(width)).height(BAR_HEIGHT))
+        .push(fill);
+    if level.peak > 0.0 {
+        bar = bar.push(peak);
+    }
+
+    Container::new(bar)
+        .width(width)
+        .height(BAR_HEIGHT)
+        .class(ContainerType::LevelTrack)
+        .into()
+}
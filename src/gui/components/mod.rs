@@ -1,8 +1,17 @@
 mod annotation;
+mod annotation_export;
 mod area_selector;
 pub mod awmodal;
 pub mod button;
+mod chat;
+mod level_meter;
 pub mod video;
 
-pub use annotation::{Annotation, Shape, ShapeColor, ShapeStroke, ShapeType};
-pub use area_selector::AreaSelector;
+pub use annotation::{
+    Annotation, AnnotationHistory, RemoteAnnotations, Shape, ShapeColor, ShapeStroke, ShapeType,
+    StrokeEvent, remote_stroke,
+};
+pub use annotation_export::export_png;
+pub use area_selector::{AreaSelector, AspectLock, MIN_AREA};
+pub use chat::chat_panel;
+pub use level_meter::level_meter;
@@ -1,6 +1,7 @@
 //! Video playback components for the receiver UI
 
 mod pipeline;
+pub mod snapshot;
 #[allow(clippy::module_inception)]
 mod video;
 mod video_player;
@@ -9,27 +9,41 @@ use std::{
 };
 
 use super::video::FrameBuffer;
+use crate::capture::ColorSpace;
+use crate::decoder::{PixelLayout, i420_len};
+use crate::display::VideoFit;
 
 #[repr(C)]
 struct Uniforms {
     rect: [f32; 4],
+    /// Offset e scala della luminanza
+    luma: [f32; 4],
+    /// Coefficienti della croma: Cr→R, Cb→G, Cr→G, Cb→B
+    chroma: [f32; 4],
+}
+
+/// GPU resources of one video, shaped after the frame layout
+struct VideoTextures {
+    layout: PixelLayout,
+    /// Y plane, then U and V (I420) or the interleaved UV plane (NV12)
+    planes: Vec<wgpu::Texture>,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// Area covered by the frame, in physical pixels (set by `prepare_uniforms`)
+    area: iced::Rectangle,
 }
 
 pub struct VideoPipeline {
     pipeline: wgpu::RenderPipeline,
     bg0_layout: wgpu::BindGroupLayout,
+    /// NV12 variant: Y (R8) + UV (RG8) sampled directly by `fs_nv12`
+    nv12_pipeline: wgpu::RenderPipeline,
+    nv12_bg0_layout: wgpu::BindGroupLayout,
     sampler: wgpu::Sampler,
-    /// Maps video_id → (Y texture, U texture, V texture, uniform buffer, bind group)
-    textures: BTreeMap<
-        u64,
-        (
-            wgpu::Texture,
-            wgpu::Texture,
-            wgpu::Texture,
-            wgpu::Buffer,
-            wgpu::BindGroup,
-        ),
-    >,
+    /// Maps video_id → textures, uniform buffer and bind group
+    textures: BTreeMap<u64, VideoTextures>,
+    /// Rows padded to `COPY_BYTES_PER_ROW_ALIGNMENT`, reused across frames
+    staging: Vec<u8>,
 }
 
 impl VideoPipeline {
@@ -39,15 +53,21 @@ impl VideoPipeline {
         queue: &wgpu::Queue,
         video_id: u64,
         (width, height): (u32, u32),
+        layout: PixelLayout,
         frame: &[u8],
     ) {
-        let uw = width / 2;
-        let uh = height / 2;
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        // Chroma planes round odd dimensions up, like the decoder output
+        let uw = width.div_ceil(2);
+        let uh = height.div_ceil(2);
         let y_size = (width * height) as usize;
         let uv_size = (uw * uh) as usize;
 
-        // Validate frame size
-        if frame.len() < y_size + uv_size * 2 {
+        // Validate frame size (same for both layouts)
+        if frame.len() < i420_len(width as usize, height as usize) {
             log::warn!(
                 "VideoPipeline::upload() - frame too small: {} < {} ({}x{})",
                 frame.len(),
@@ -58,18 +78,19 @@ impl VideoPipeline {
             return;
         }
 
-        // Check if textures need (re)creation due to resolution change or first frame
+        // Check if textures need (re)creation due to resolution/layout change or first frame
         let needs_recreate = match self.textures.get(&video_id) {
             None => {
                 log::info!(
-                    "VideoPipeline::upload() - first frame, creating textures {}x{}",
+                    "VideoPipeline::upload() - first frame, creating {:?} textures {}x{}",
+                    layout,
                     width,
                     height
                 );
                 true
             }
-            Some((y_tex, _, _, _, _)) => {
-                let cur = y_tex.size();
+            Some(current) => {
+                let cur = current.planes[0].size();
                 if cur.width != width || cur.height != height {
                     log::info!(
                         "VideoPipeline::upload() - resolution changed from {}x{} to {}x{}",
@@ -79,6 +100,13 @@ impl VideoPipeline {
                         height
                     );
                     true
+                } else if current.layout != layout {
+                    log::info!(
+                        "VideoPipeline::upload() - layout changed from {:?} to {:?}",
+                        current.layout,
+                        layout
+                    );
+                    true
                 } else {
                     false
                 }
@@ -89,54 +117,46 @@ impl VideoPipeline {
             // Remove old entry if exists
             self.textures.remove(&video_id);
 
-            let y_tex = device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("video Y texture"),
-                size: wgpu::Extent3d {
-                    width,
-                    height,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::R8Unorm,
-                usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            });
-
-            let u_tex = device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("video U texture"),
-                size: wgpu::Extent3d {
-                    width: uw,
-                    height: uh,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::R8Unorm,
-                usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            });
-
-            let v_tex = device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("video V texture"),
-                size: wgpu::Extent3d {
-                    width: uw,
-                    height: uh,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::R8Unorm,
-                usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            });
-
-            let y_view = y_tex.create_view(&wgpu::TextureViewDescriptor::default());
-            let u_view = u_tex.create_view(&wgpu::TextureViewDescriptor::default());
-            let v_view = v_tex.create_view(&wgpu::TextureViewDescriptor::default());
+            let planes = match layout {
+                PixelLayout::I420 => vec![
+                    create_plane(
+                        device,
+                        "video Y texture",
+                        (width, height),
+                        wgpu::TextureFormat::R8Unorm,
+                    ),
+                    create_plane(
+                        device,
+                        "video U texture",
+                        (uw, uh),
+                        wgpu::TextureFormat::R8Unorm,
+                    ),
+                    create_plane(
+                        device,
+                        "video V texture",
+                        (uw, uh),
+                        wgpu::TextureFormat::R8Unorm,
+                    ),
+                ],
+                PixelLayout::Nv12 => vec![
+                    create_plane(
+                        device,
+                        "video Y texture",
+                        (width, height),
+                        wgpu::TextureFormat::R8Unorm,
+                    ),
+                    create_plane(
+                        device,
+                        "video UV texture",
+                        (uw, uh),
+                        wgpu::TextureFormat::Rg8Unorm,
+                    ),
+                ],
+            };
+            let views: Vec<_> = planes
+                .iter()
+                .map(|t| t.create_view(&wgpu::TextureViewDescriptor::default()))
+                .collect();
 
             let buffer = device.create_buffer(&wgpu::BufferDescriptor {
                 label: Some("video uniform buffer"),
@@ -145,122 +165,101 @@ impl VideoPipeline {
                 mapped_at_creation: false,
             });
 
+            // I420 usa i binding 0/1/2, NV12 il 0 (Y) e il 5 (UV)
+            let texture_bindings: &[u32] = match layout {
+                PixelLayout::I420 => &[0, 1, 2],
+                PixelLayout::Nv12 => &[0, 5],
+            };
+            let mut entries: Vec<_> = texture_bindings
+                .iter()
+                .zip(&views)
+                .map(|(&binding, view)| wgpu::BindGroupEntry {
+                    binding,
+                    resource: wgpu::BindingResource::TextureView(view),
+                })
+                .collect();
+            entries.push(wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(&self.sampler),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: None,
+                }),
+            });
+
             let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
                 label: Some("video bind group"),
-                layout: &self.bg0_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&y_view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::TextureView(&u_view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: wgpu::BindingResource::TextureView(&v_view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 3,
-                        resource: wgpu::BindingResource::Sampler(&self.sampler),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 4,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &buffer,
-                            offset: 0,
-                            size: None,
-                        }),
-                    },
-                ],
+                layout: match layout {
+                    PixelLayout::I420 => &self.bg0_layout,
+                    PixelLayout::Nv12 => &self.nv12_bg0_layout,
+                },
+                entries: &entries,
             });
 
-            self.textures
-                .insert(video_id, (y_tex, u_tex, v_tex, buffer, bind_group));
+            self.textures.insert(
+                video_id,
+                VideoTextures {
+                    layout,
+                    planes,
+                    buffer,
+                    bind_group,
+                    area: iced::Rectangle::default(),
+                },
+            );
         }
 
-        let (y_tex, u_tex, v_tex, _, _) = self.textures.get(&video_id).unwrap();
-
-        let y_data = &frame[..y_size];
-        let u_data = &frame[y_size..y_size + uv_size];
-        let v_data = &frame[y_size + uv_size..];
-
-        // Upload Y plane
-        queue.write_texture(
-            wgpu::TexelCopyTextureInfo {
-                texture: y_tex,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            y_data,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(width),
-                rows_per_image: Some(height),
-            },
-            wgpu::Extent3d {
-                width,
-                height,
-                depth_or_array_layers: 1,
-            },
-        );
-
-        // Upload U plane
-        queue.write_texture(
-            wgpu::TexelCopyTextureInfo {
-                texture: u_tex,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            u_data,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(uw),
-                rows_per_image: Some(uh),
-            },
-            wgpu::Extent3d {
-                width: uw,
-                height: uh,
-                depth_or_array_layers: 1,
-            },
+        let planes = &self.textures.get(&video_id).unwrap().planes;
+        let staging = &mut self.staging;
+        write_plane(
+            queue,
+            &planes[0],
+            &frame[..y_size],
+            (width, height),
+            1,
+            staging,
         );
 
-        // Upload V plane
-        queue.write_texture(
-            wgpu::TexelCopyTextureInfo {
-                texture: v_tex,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            v_data,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(uw),
-                rows_per_image: Some(uh),
-            },
-            wgpu::Extent3d {
-                width: uw,
-                height: uh,
-                depth_or_array_layers: 1,
-            },
-        );
+        match layout {
+            PixelLayout::I420 => {
+                let u_data = &frame[y_size..y_size + uv_size];
+                let v_data = &frame[y_size + uv_size..y_size + uv_size * 2];
+                write_plane(queue, &planes[1], u_data, (uw, uh), 1, staging);
+                write_plane(queue, &planes[2], v_data, (uw, uh), 1, staging);
+            }
+            PixelLayout::Nv12 => {
+                let uv_data = &frame[y_size..y_size + uv_size * 2];
+                write_plane(queue, &planes[1], uv_data, (uw, uh), 2, staging);
+            }
+        }
     }
 
-    fn prepare_uniforms(&mut self, queue: &wgpu::Queue, video_id: u64, bounds: &iced::Rectangle) {
-        if let Some((_, _, _, buffer, _)) = self.textures.get(&video_id) {
+    /// `bounds` is the widget area in logical pixels; the frame goes in the
+    /// part of it chosen by `fit`.
+    fn prepare_uniforms(
+        &mut self,
+        queue: &wgpu::Queue,
+        video_id: u64,
+        bounds: &iced::Rectangle,
+        scale_factor: f32,
+        fit: VideoFit,
+        color: ColorSpace,
+    ) {
+        if let Some(textures) = self.textures.get_mut(&video_id) {
+            let size = textures.planes[0].size();
+            let rect = fit.inner_rect(*bounds, (size.width as i32, size.height as i32));
+            textures.area = rect * iced::Transformation::scale(scale_factor);
+
+            let k = color.yuv_to_rgb();
             let uniforms = Uniforms {
-                rect: [
-                    bounds.x,
-                    bounds.y,
-                    bounds.x + bounds.width,
-                    bounds.y + bounds.height,
-                ],
+                rect: [rect.x, rect.y, rect.x + rect.width, rect.y + rect.height],
+                luma: [k.y_offset, k.y_scale, 0.0, 0.0],
+                chroma: [k.cr_r, k.cb_g, k.cr_g, k.cb_b],
             };
-            queue.write_buffer(buffer, 0, unsafe {
+            queue.write_buffer(&textures.buffer, 0, unsafe {
                 std::slice::from_raw_parts(
                     &uniforms as *const _ as *const u8,
                     size_of::<Uniforms>(),
@@ -273,10 +272,21 @@ impl VideoPipeline {
         &self,
         target: &wgpu::TextureView,
         encoder: &mut wgpu::CommandEncoder,
-        viewport: &iced::Rectangle<u32>,
+        clip_bounds: &iced::Rectangle<u32>,
         video_id: u64,
     ) {
-        if let Some((_, _, _, _, bind_group)) = self.textures.get(&video_id) {
+        if let Some(textures) = self.textures.get(&video_id) {
+            // The bars are left to the background: only the frame area is drawn
+            let area = textures.area;
+            let Some(visible) = iced::Rectangle::from(*clip_bounds).intersection(&area) else {
+                return;
+            };
+            let (x, y) = (visible.x.round() as u32, visible.y.round() as u32);
+            let (width, height) = (visible.width.floor() as u32, visible.height.floor() as u32);
+            if width == 0 || height == 0 {
+                return;
+            }
+
             let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("video render pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -293,16 +303,13 @@ impl VideoPipeline {
                 occlusion_query_set: None,
             });
 
-            pass.set_pipeline(&self.pipeline);
-            pass.set_bind_group(0, bind_group, &[]);
-            pass.set_viewport(
-                viewport.x as _,
-                viewport.y as _,
-                viewport.width as _,
-                viewport.height as _,
-                0.0,
-                1.0,
-            );
+            pass.set_pipeline(match textures.layout {
+                PixelLayout::I420 => &self.pipeline,
+                PixelLayout::Nv12 => &self.nv12_pipeline,
+            });
+            pass.set_bind_group(0, &textures.bind_group, &[]);
+            pass.set_scissor_rect(x, y, width, height);
+            pass.set_viewport(area.x, area.y, area.width, area.height, 0.0, 1.0);
             pass.draw(0..4, 0..1);
         }
     }
@@ -319,94 +326,35 @@ impl iced_wgpu::primitive::Pipeline for VideoPipeline {
             label: Some("video bind group 0 layout"),
             entries: &[
                 // binding 0: Y texture
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
+                texture_layout_entry(0),
                 // binding 1: U texture
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
+                texture_layout_entry(1),
                 // binding 2: V texture
-                wgpu::BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
+                texture_layout_entry(2),
                 // binding 3: sampler
-                wgpu::BindGroupLayoutEntry {
-                    binding: 3,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
+                sampler_layout_entry(),
                 // binding 4: uniforms
-                wgpu::BindGroupLayoutEntry {
-                    binding: 4,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
+                uniforms_layout_entry(),
             ],
         });
 
-        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("video pipeline layout"),
-            bind_group_layouts: &[&bg0_layout],
-            push_constant_ranges: &[],
+        let nv12_bg0_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("video NV12 bind group 0 layout"),
+            entries: &[
+                // binding 0: Y texture
+                texture_layout_entry(0),
+                // binding 5: interleaved UV texture
+                texture_layout_entry(5),
+                // binding 3: sampler
+                sampler_layout_entry(),
+                // binding 4: uniforms
+                uniforms_layout_entry(),
+            ],
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("video pipeline"),
-            layout: Some(&layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[],
-                compilation_options: Default::default(),
-            },
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format,
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            multiview: None,
-            cache: None,
-        });
+        let pipeline = create_render_pipeline(device, &shader, &bg0_layout, "fs_main", format);
+        let nv12_pipeline =
+            create_render_pipeline(device, &shader, &nv12_bg0_layout, "fs_nv12", format);
 
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("video sampler"),
@@ -426,17 +374,167 @@ impl iced_wgpu::primitive::Pipeline for VideoPipeline {
         VideoPipeline {
             pipeline,
             bg0_layout,
+            nv12_pipeline,
+            nv12_bg0_layout,
             sampler,
             textures: BTreeMap::new(),
+            staging: Vec::new(),
         }
     }
 }
 
+fn texture_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn sampler_layout_entry() -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding: 3,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    }
+}
+
+fn uniforms_layout_entry() -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding: 4,
+        visibility: wgpu::ShaderStages::VERTEX,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    bg0_layout: &wgpu::BindGroupLayout,
+    fragment_entry: &str,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("video pipeline layout"),
+        bind_group_layouts: &[bg0_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("video pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(fragment_entry),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+fn create_plane(
+    device: &wgpu::Device,
+    label: &str,
+    (width, height): (u32, u32),
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
+}
+
+/// Upload one tightly packed plane. Rows that are not a multiple of
+/// `COPY_BYTES_PER_ROW_ALIGNMENT` go through `staging` with padding.
+fn write_plane(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    data: &[u8],
+    (width, height): (u32, u32),
+    bytes_per_texel: u32,
+    staging: &mut Vec<u8>,
+) {
+    let row = width * bytes_per_texel;
+    let padded = row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let data = if padded == row {
+        data
+    } else {
+        let (r, p) = (row as usize, padded as usize);
+        staging.resize(p * height as usize, 0);
+        for (dst, src) in staging.chunks_exact_mut(p).zip(data.chunks_exact(r)) {
+            dst[..r].copy_from_slice(src);
+        }
+        &staging[..]
+    };
+
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        data,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(padded),
+            rows_per_image: Some(height),
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
 #[derive(Debug, Clone)]
 pub struct VideoPrimitive {
     video_id: u64,
     frame: Arc<Mutex<FrameBuffer>>,
     has_new_frame: Arc<AtomicBool>,
+    color: ColorSpace,
+    fit: VideoFit,
 }
 
 impl VideoPrimitive {
@@ -445,11 +543,15 @@ impl VideoPrimitive {
         frame: Arc<Mutex<FrameBuffer>>,
         _size: (u32, u32),
         has_new_frame: Arc<AtomicBool>,
+        color: ColorSpace,
+        fit: VideoFit,
     ) -> Self {
         VideoPrimitive {
             video_id,
             frame,
             has_new_frame,
+            color,
+            fit,
         }
     }
 }
@@ -463,20 +565,21 @@ impl Primitive for VideoPrimitive {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         bounds: &iced::Rectangle,
-        _viewport: &iced_wgpu::graphics::Viewport,
+        viewport: &iced_wgpu::graphics::Viewport,
     ) {
         // Upload only when a new frame is available.
         // Keep this non-blocking and re-arm the flag if lock contention occurs.
         let should_upload = self.has_new_frame.swap(false, Ordering::AcqRel);
         if should_upload {
             if let Ok(mut buffer) = self.frame.try_lock()
-                && let Some((frame_data, w, h)) = buffer.read()
+                && let Some((frame_data, w, h, layout)) = buffer.read()
             {
                 pipeline.upload(
                     device,
                     queue,
                     self.video_id,
                     (w as u32, h as u32),
+                    layout,
                     frame_data,
                 );
             } else {
@@ -486,7 +589,14 @@ impl Primitive for VideoPrimitive {
             }
         }
 
-        pipeline.prepare_uniforms(queue, self.video_id, bounds);
+        pipeline.prepare_uniforms(
+            queue,
+            self.video_id,
+            bounds,
+            viewport.scale_factor(),
+            self.fit,
+            self.color,
+        );
     }
 
     fn render(
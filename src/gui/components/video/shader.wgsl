@@ -5,6 +5,10 @@ struct VertexOutput {
 
 struct Uniforms {
     rect: vec4<f32>,
+    // Y offset, Y scale (range), unused, unused
+    luma: vec4<f32>,
+    // Cr→R, Cb→G, Cr→G, Cb→B (matrix)
+    chroma: vec4<f32>,
 }
 
 @group(0) @binding(0)
@@ -22,6 +26,10 @@ var samp: sampler;
 @group(0) @binding(4)
 var<uniform> uniforms: Uniforms;
 
+// NV12: interleaved chroma (U in .r, V in .g), used instead of bindings 1/2
+@group(0) @binding(5)
+var uv_tex: texture_2d<f32>;
+
 @vertex
 fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
     var out: VertexOutput;
@@ -32,17 +40,29 @@ fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
     return out;
 }
 
+fn yuv_to_rgb(y: f32, u: f32, v: f32) -> vec4<f32> {
+    // Matrix and range from the playback settings (ColorSpace)
+    let yn = (y - uniforms.luma.x) * uniforms.luma.y;
+    let cb = u - 0.5;
+    let cr = v - 0.5;
+    let k = uniforms.chroma;
+    let r = yn + k.x * cr;
+    let g = yn - k.y * cb - k.z * cr;
+    let b = yn + k.w * cb;
+    return vec4<f32>(clamp(r, 0.0, 1.0), clamp(g, 0.0, 1.0), clamp(b, 0.0, 1.0), 1.0);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let y = textureSample(y_tex, samp, in.uv).r;
     let u = textureSample(u_tex, samp, in.uv).r;
     let v = textureSample(v_tex, samp, in.uv).r;
-    // BT.709 limited range
-    let yn = (y - 0.0627) * 1.1644;
-    let cb = u - 0.5;
-    let cr = v - 0.5;
-    let r = yn + 1.7927 * cr;
-    let g = yn - 0.2132 * cb - 0.5329 * cr;
-    let b = yn + 2.1124 * cb;
-    return vec4<f32>(clamp(r, 0.0, 1.0), clamp(g, 0.0, 1.0), clamp(b, 0.0, 1.0), 1.0);
+    return yuv_to_rgb(y, u, v);
+}
+
+@fragment
+fn fs_nv12(in: VertexOutput) -> @location(0) vec4<f32> {
+    let y = textureSample(y_tex, samp, in.uv).r;
+    let uv = textureSample(uv_tex, samp, in.uv).rg;
+    return yuv_to_rgb(y, uv.x, uv.y);
 }
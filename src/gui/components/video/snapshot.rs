@@ -0,0 +1,36 @@
+//! Screenshot of the received stream
+//!
+//! Converts the last decoded frame with the same matrix and range as the
+//! shader, so the PNG matches what is on screen.
+
+use crate::capture::ColorSpace;
+use crate::decoder::{VideoFrame, i420_len};
+use anyhow::{Context, ensure};
+use std::fs::File;
+use std::io::BufWriter;
+
+/// Salva un frame decodificato come PNG
+pub fn save_png(frame: &VideoFrame, color: ColorSpace, path: &str) -> anyhow::Result<()> {
+    let (width, height, yuv) = (frame.width, frame.height, &frame.data);
+    let (w, h) = (width as usize, height as usize);
+    ensure!(w > 0 && h > 0, "Invalid frame size {}x{}", width, height);
+    // NV12 e I420 hanno la stessa dimensione
+    ensure!(
+        yuv.len() >= i420_len(w, h),
+        "Incomplete frame ({} bytes)",
+        yuv.len()
+    );
+
+    let rgba = frame.to_rgba(color);
+
+    let file = File::create(path).with_context(|| format!("Unable to create {}", path))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&rgba))
+        .context("PNG encoding failed")?;
+
+    Ok(())
+}
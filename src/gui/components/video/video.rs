@@ -1,19 +1,30 @@
-use crate::decoder::VideoFrame;
+use crate::capture::{ColorSpace, StreamProfile};
+use crate::decoder::{PixelLayout, VideoFrame, i420_len};
+use crate::display::policy::COMPLETENESS_MAX_WAIT;
+use crate::display::{DisplayPolicy, VideoFit};
+use crate::pipeline::health::{DropSource, PipelineHealth};
+use castbox::Arw;
 use std::cell::RefCell;
-use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU8, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
+use tokio::time::MissedTickBehavior;
+
+/// Ogni quanto `Completeness` ricontrolla se il renderer ha caricato il
+/// frame precedente (un redraw a 60 Hz dura ~16 ms)
+const COMPLETENESS_POLL: Duration = Duration::from_millis(2);
 
 /// Shared frame buffer for video frame sharing between reader and GUI.
 /// Uses a simple mutex-protected buffer with a "dirty" flag.
 #[derive(Debug)]
 pub struct FrameBuffer {
-    /// The frame data (YUV420p format)
+    /// The frame data (YUV420p or NV12, see `layout`)
     data: Vec<u8>,
     /// Frame dimensions
     width: i32,
     height: i32,
+    layout: PixelLayout,
     /// Whether new data is available
     has_data: bool,
 }
@@ -24,13 +35,14 @@ impl FrameBuffer {
             data: Vec::new(),
             width: 0,
             height: 0,
+            layout: PixelLayout::I420,
             has_data: false,
         }
     }
 
     /// Write frame data to the buffer
-    pub fn write(&mut self, data: &[u8], width: i32, height: i32) {
-        let expected_size = (width * height * 3 / 2) as usize;
+    pub fn write(&mut self, data: &[u8], width: i32, height: i32, layout: PixelLayout) {
+        let expected_size = i420_len(width.max(0) as usize, height.max(0) as usize);
         if self.data.len() != expected_size {
             self.data.resize(expected_size, 0);
         }
@@ -38,13 +50,14 @@ impl FrameBuffer {
         self.data[..len].copy_from_slice(&data[..len]);
         self.width = width;
         self.height = height;
+        self.layout = layout;
         self.has_data = true;
     }
 
     /// Read frame data from the buffer. Returns None if no data is available.
-    pub fn read(&mut self) -> Option<(&[u8], i32, i32)> {
+    pub fn read(&mut self) -> Option<(&[u8], i32, i32, PixelLayout)> {
         if self.has_data && !self.data.is_empty() {
-            Some((&self.data, self.width, self.height))
+            Some((&self.data, self.width, self.height, self.layout))
         } else {
             None
         }
@@ -69,6 +82,19 @@ pub struct Internal {
     /// Dynamic width/height updated by the reader task (for dynamic resolution)
     pub dyn_width: Option<Arc<AtomicI32>>,
     pub dyn_height: Option<Arc<AtomicI32>>,
+
+    /// Stream profile announced by the caster: sizes the player before the first frame
+    pub profile_hint: Option<Arw<Option<StreamProfile>>>,
+
+    /// `DisplayPolicy` letta dal task di lettura ad ogni frame
+    pub display_policy: Arc<AtomicU8>,
+    /// Dove contare i frame mai arrivati al renderer
+    pub drop_health: Option<Arc<PipelineHealth>>,
+
+    /// Matrice e range usati per riconvertire i frame in RGB
+    pub color: ColorSpace,
+    /// Bande o stiramento quando le proporzioni non coincidono
+    pub fit: VideoFit,
 }
 
 /// Video component: riceve frame H.264 (o raw RGBA) da un canale Tokio
@@ -93,6 +119,11 @@ impl Video {
             is_eos: false,
             dyn_width: None,
             dyn_height: None,
+            profile_hint: None,
+            display_policy: Arc::new(AtomicU8::new(DisplayPolicy::default() as u8)),
+            drop_health: None,
+            color: ColorSpace::default(),
+            fit: VideoFit::default(),
         }))
     }
 
@@ -114,6 +145,11 @@ impl Video {
         let width_ref = Arc::clone(&width);
         let height_ref = Arc::clone(&height);
 
+        let (display_policy, drop_health) = {
+            let inner = self.0.borrow();
+            (Arc::clone(&inner.display_policy), inner.drop_health.clone())
+        };
+
         // Aggiorna lo stato interno
         {
             let mut inner = self.0.borrow_mut();
@@ -151,104 +187,167 @@ impl Video {
 
             log::info!("Video reader task started, waiting for frames...");
 
-            loop {
-                // Use try_recv to check for pending frames without blocking
-                match rx.try_recv() {
-                    Ok(vf) => {
-                        frame_count += 1;
+            // Attende il prossimo frame senza polling; None = canale chiuso
+            while let Some(vf) = rx.recv().await {
+                frame_count += 1;
 
-                        // Check if there are more frames waiting - if so, skip to newest
-                        // This prevents accumulating latency when rendering is slow
-                        let mut latest_vf = vf;
-                        let mut frames_skipped = 0u64;
+                let mut latest_vf = vf;
+                let mut frames_skipped = 0u64;
+                match DisplayPolicy::from_u8(display_policy.load(Ordering::Relaxed)) {
+                    // Check if there are more frames waiting - if so, skip to newest
+                    // This prevents accumulating latency when rendering is slow
+                    DisplayPolicy::Latency => {
                         while let Ok(newer_vf) = rx.try_recv() {
                             latest_vf = newer_vf;
                             frames_skipped += 1;
                         }
-
-                        if frames_skipped > 0 {
-                            skipped_count += frames_skipped;
-                            if frames_skipped > 5 {
-                                log::debug!(
-                                    "Video reader: skipped {} old frames to catch up",
-                                    frames_skipped
-                                );
-                            }
-                        }
-
-                        // Log stats every 5 seconds
-                        if last_stats.elapsed().as_secs() >= 5 {
-                            if skipped_count > 0 {
-                                log::info!(
-                                    "Video reader: {} frames processed, {} skipped ({:.1}%)",
-                                    frame_count,
-                                    skipped_count,
-                                    (skipped_count as f64 / (frame_count + skipped_count) as f64)
-                                        * 100.0
-                                );
-                            } else {
-                                log::info!("Video reader: {} frames processed", frame_count);
+                    }
+                    // Aspetta che il renderer carichi il frame precedente
+                    DisplayPolicy::Completeness => {
+                        let deadline = tokio::time::sleep(COMPLETENESS_MAX_WAIT);
+                        tokio::pin!(deadline);
+                        let mut poll = tokio::time::interval(COMPLETENESS_POLL);
+                        poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                        while has_new_frame_ref.load(Ordering::Acquire) {
+                            tokio::select! {
+                                _ = poll.tick() => {}
+                                _ = &mut deadline => break,
                             }
-                            last_stats = Instant::now();
                         }
+                    }
+                }
+                // Il frame precedente non è mai stato caricato dal renderer
+                if has_new_frame_ref.load(Ordering::Acquire) {
+                    frames_skipped += 1;
+                }
 
-                        let new_w = latest_vf.width as i32;
-                        let new_h = latest_vf.height as i32;
-
-                        // Write to frame buffer - use try_lock to avoid blocking
-                        match frame_ref.try_lock() {
-                            Ok(mut buffer) => {
-                                buffer.write(&latest_vf.data, new_w, new_h);
-                                log::debug!(
-                                    "Video reader: wrote frame {}x{}, {} bytes",
-                                    new_w,
-                                    new_h,
-                                    latest_vf.data.len()
-                                );
-                            }
-                            Err(_) => {
-                                log::debug!("Video reader: buffer locked, skipping frame");
-                            }
-                        }
+                if frames_skipped > 0 {
+                    skipped_count += frames_skipped;
+                    if let Some(health) = &drop_health {
+                        health.record_frame_drops(DropSource::Display, frames_skipped);
+                    }
+                    if frames_skipped > 5 {
+                        log::debug!(
+                            "Video reader: skipped {} old frames to catch up",
+                            frames_skipped
+                        );
+                    }
+                }
 
-                        // Signal that a new frame is available
-                        has_new_frame_ref.store(true, Ordering::Release);
-                        width_ref.store(new_w, Ordering::Release);
-                        height_ref.store(new_h, Ordering::Release);
+                // Log stats every 5 seconds
+                if last_stats.elapsed().as_secs() >= 5 {
+                    if skipped_count > 0 {
+                        log::info!(
+                            "Video reader: {} frames processed, {} skipped ({:.1}%)",
+                            frame_count,
+                            skipped_count,
+                            (skipped_count as f64 / (frame_count + skipped_count) as f64) * 100.0
+                        );
+                    } else {
+                        log::info!("Video reader: {} frames processed", frame_count);
                     }
-                    Err(mpsc::error::TryRecvError::Empty) => {
-                        // No frame available - wait a short time
-                        tokio::time::sleep(std::time::Duration::from_micros(500)).await;
+                    last_stats = Instant::now();
+                }
+
+                let new_w = latest_vf.width as i32;
+                let new_h = latest_vf.height as i32;
+
+                // Write to frame buffer - use try_lock to avoid blocking
+                match frame_ref.try_lock() {
+                    Ok(mut buffer) => {
+                        buffer.write(&latest_vf.data, new_w, new_h, latest_vf.layout);
+                        log::debug!(
+                            "Video reader: wrote frame {}x{}, {} bytes",
+                            new_w,
+                            new_h,
+                            latest_vf.data.len()
+                        );
                     }
-                    Err(mpsc::error::TryRecvError::Disconnected) => {
-                        // Channel closed
-                        break;
+                    Err(_) => {
+                        log::debug!("Video reader: buffer locked, skipping frame");
+                        if let Some(health) = &drop_health {
+                            health.record_frame_drop(DropSource::Display);
+                        }
                     }
                 }
+
+                // Signal that a new frame is available
+                has_new_frame_ref.store(true, Ordering::Release);
+                width_ref.store(new_w, Ordering::Release);
+                height_ref.store(new_h, Ordering::Release);
             }
             // Il canale si è chiuso → end of stream
             is_eos_ref.store(true, Ordering::SeqCst);
         });
     }
 
+    /// Politica di drop dei frame e contatore dei frame scartati; va
+    /// chiamata prima di `set_stream`.
+    pub fn set_display_policy(&mut self, policy: Arc<AtomicU8>, health: Arc<PipelineHealth>) {
+        let mut inner = self.0.borrow_mut();
+        inner.display_policy = policy;
+        inner.drop_health = Some(health);
+    }
+
+    /// Collega il profilo negoziato col caster (risoluzione + fps attesi).
+    pub fn set_profile_hint(&mut self, profile: Arw<Option<StreamProfile>>) {
+        self.0.borrow_mut().profile_hint = Some(profile);
+    }
+
+    /// Spazio colore con cui disegnare (e fotografare) i frame ricevuti
+    pub fn set_color_space(&mut self, color: ColorSpace) {
+        self.0.borrow_mut().color = color;
+    }
+
+    /// Come adattare il frame all'area del player
+    pub fn set_fit(&mut self, fit: VideoFit) {
+        self.0.borrow_mut().fit = fit;
+    }
+
+    #[inline(always)]
+    pub fn fit(&self) -> VideoFit {
+        self.0.borrow().fit
+    }
+
+    /// Parte di `bounds` coperta dal frame con l'adattamento scelto
+    pub fn frame_area(&self, bounds: iced::Rectangle) -> iced::Rectangle {
+        self.fit().inner_rect(bounds, self.size())
+    }
+
+    fn hinted_profile(&self) -> Option<StreamProfile> {
+        let inner = self.0.borrow();
+        inner.profile_hint.as_ref().and_then(|hint| *hint.as_ref())
+    }
+
     /// Get the size/resolution of the video as `(width, height)`.
     #[inline(always)]
     pub fn size(&self) -> (i32, i32) {
-        let inner = self.0.borrow();
-        if let (Some(w), Some(h)) = (&inner.dyn_width, &inner.dyn_height) {
-            let dw = w.load(Ordering::SeqCst);
-            let dh = h.load(Ordering::SeqCst);
-            if dw > 0 && dh > 0 {
-                return (dw, dh);
+        {
+            let inner = self.0.borrow();
+            if let (Some(w), Some(h)) = (&inner.dyn_width, &inner.dyn_height) {
+                let dw = w.load(Ordering::SeqCst);
+                let dh = h.load(Ordering::SeqCst);
+                if dw > 0 && dh > 0 {
+                    return (dw, dh);
+                }
+            }
+        }
+        match self.hinted_profile() {
+            Some(p) if !p.is_native() => (p.width as i32, p.height as i32),
+            _ => {
+                let inner = self.0.borrow();
+                (inner.width, inner.height)
             }
         }
-        (inner.width, inner.height)
     }
 
     /// Get the framerate of the video as frames per second.
     #[inline(always)]
     pub fn framerate(&self) -> f64 {
-        self.0.borrow().framerate
+        match self.hinted_profile() {
+            Some(p) => p.fps as f64,
+            None => self.0.borrow().framerate,
+        }
     }
 
     /// Set if the media is paused or not.
@@ -262,6 +361,19 @@ impl Video {
         self.0.borrow().paused
     }
 
+    /// Copia dell'ultimo frame ricevuto per gli screenshot
+    pub fn snapshot(&self) -> Option<VideoFrame> {
+        let inner = self.0.borrow();
+        let mut frame = inner.frame.lock().ok()?;
+        let (data, width, height, layout) = frame.read()?;
+        Some(VideoFrame {
+            data: data.to_vec(),
+            width: width as u32,
+            height: height as u32,
+            layout,
+        })
+    }
+
     /// Get if the stream ended (channel closed).
     #[inline(always)]
     pub fn eos(&self) -> bool {
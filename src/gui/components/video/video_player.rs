@@ -1,5 +1,7 @@
 use crate::gui::components::video::pipeline::VideoPrimitive;
 use crate::gui::components::video::Video;
+#[cfg(feature = "remote-control")]
+use crate::utils::remote_control::{RemoteButton, RemoteInput};
 use iced::{
     advanced::{self, layout, widget, Widget},
     Element,
@@ -17,6 +19,8 @@ where
     video: &'a Video,
     on_end_of_stream: Option<Message>,
     on_new_frame: Option<Message>,
+    #[cfg(feature = "remote-control")]
+    on_input: Option<Box<dyn Fn(RemoteInput) -> Message + 'a>>,
     _phantom: PhantomData<(Theme, Renderer)>,
 }
 
@@ -30,6 +34,8 @@ where
             video,
             on_end_of_stream: None,
             on_new_frame: None,
+            #[cfg(feature = "remote-control")]
+            on_input: None,
             _phantom: Default::default(),
         }
     }
@@ -49,6 +55,56 @@ where
             ..self
         }
     }
+
+    /// Mouse events over the frame, in coordinates normalized to the video.
+    #[cfg(feature = "remote-control")]
+    pub fn on_input(self, on_input: impl Fn(RemoteInput) -> Message + 'a) -> Self {
+        VideoPlayer {
+            on_input: Some(Box::new(on_input)),
+            ..self
+        }
+    }
+}
+
+/// `frame` is the area covered by the picture: the bars around it don't count.
+#[cfg(feature = "remote-control")]
+fn remote_input(
+    event: &iced::Event,
+    frame: iced::Rectangle,
+    cursor: advanced::mouse::Cursor,
+) -> Option<RemoteInput> {
+    use iced::mouse::{Button, Event, ScrollDelta};
+
+    let iced::Event::Mouse(event) = event else {
+        return None;
+    };
+    let position = cursor.position_in(frame)?;
+    let (x, y) = (position.x / frame.width, position.y / frame.height);
+    let button = |button: &Button| match button {
+        Button::Left => Some(RemoteButton::Left),
+        Button::Right => Some(RemoteButton::Right),
+        Button::Middle => Some(RemoteButton::Middle),
+        _ => None,
+    };
+
+    match event {
+        Event::CursorMoved { .. } => Some(RemoteInput::MouseMove { x, y }),
+        Event::ButtonPressed(b) | Event::ButtonReleased(b) => Some(RemoteInput::MouseButton {
+            x,
+            y,
+            button: button(b)?,
+            pressed: matches!(event, Event::ButtonPressed(_)),
+        }),
+        Event::WheelScrolled { delta } => {
+            let lines = match *delta {
+                ScrollDelta::Lines { y: lines, .. } => lines,
+                // ~40 px per scatto, come i touchpad più comuni
+                ScrollDelta::Pixels { y: pixels, .. } => pixels / 40.0,
+            };
+            (lines != 0.0).then_some(RemoteInput::Scroll { x, y, lines })
+        }
+        _ => None,
+    }
 }
 
 impl<'a, Message, Theme, Renderer> Widget<Message, Theme, Renderer>
@@ -70,26 +126,8 @@ where
         _renderer: &Renderer,
         limits: &layout::Limits,
     ) -> layout::Node {
-        let bounds = limits.resolve(iced::Length::Fill, iced::Length::Fill, limits.max());
-        let (width, height) = self.video.size();
-        if width <= 0 || height <= 0 || bounds.width <= 0.0 || bounds.height <= 0.0 {
-            return layout::Node::new(bounds);
-        }
-
-        let video_aspect = width as f32 / height as f32;
-        if !video_aspect.is_finite() || video_aspect <= 0.0 {
-            return layout::Node::new(bounds);
-        }
-
-        // Fixed aspect ratio + never exceed available size.
-        let viewport_aspect = bounds.width / bounds.height;
-        let size = if viewport_aspect > video_aspect {
-            iced::Size::new(bounds.height * video_aspect, bounds.height)
-        } else {
-            iced::Size::new(bounds.width, bounds.width / video_aspect)
-        };
-
-        layout::Node::new(size)
+        // Letterbox or stretch happen when drawing, see `VideoFit`
+        layout::Node::new(limits.resolve(iced::Length::Fill, iced::Length::Fill, limits.max()))
     }
 
     fn draw(
@@ -111,6 +149,8 @@ where
                 Arc::clone(&inner.frame),
                 (w as _, h as _),
                 Arc::clone(&inner.has_new_frame),
+                inner.color,
+                inner.fit,
             ),
         );
     }
@@ -119,13 +159,24 @@ where
         &mut self,
         _state: &mut widget::Tree,
         event: &iced::Event,
-        _layout: advanced::Layout<'_>,
-        _cursor: advanced::mouse::Cursor,
+        layout: advanced::Layout<'_>,
+        cursor: advanced::mouse::Cursor,
         _renderer: &Renderer,
         _clipboard: &mut dyn advanced::Clipboard,
         shell: &mut advanced::Shell<'_, Message>,
         _viewport: &iced::Rectangle,
     ) {
+        #[cfg(feature = "remote-control")]
+        if let Some(on_input) = &self.on_input
+            && let Some(input) = remote_input(event, self.video.frame_area(layout.bounds()), cursor)
+        {
+            shell.publish(on_input(input));
+            shell.capture_event();
+            return;
+        }
+        #[cfg(not(feature = "remote-control"))]
+        let _ = (layout, cursor);
+
         let inner = self.video.0.borrow();
 
         if let iced::Event::Window(iced::window::Event::RedrawRequested(now)) = event {
@@ -137,7 +188,7 @@ where
             }
 
             if !inner.paused {
-                let redraw_interval = 1.0 / inner.framerate.max(1.0);
+                let redraw_interval = 1.0 / self.video.framerate().max(1.0);
                 let until_redraw =
                     redraw_interval - (*now - inner.next_redraw).as_secs_f64() % redraw_interval;
                 let next = *now + Duration::from_secs_f64(until_redraw);
@@ -1,19 +1,32 @@
 use crate::assets::FONT_FAMILY_BOLD;
+use crate::capture::budget::DataCap;
+use crate::capture::display::DisplaySelector;
+use crate::capture::display::thumbnail::THUMBNAIL_WIDTH;
+use crate::capture::{FpsCap, ScreenCaptureImpl, StreamProfile};
 use crate::config::Config;
 use crate::gui::common::icons::Icon;
 use crate::gui::components::button::{Dimensions, IconButton};
+use crate::gui::components::{chat_panel, level_meter};
 use crate::gui::style::button::ButtonType;
 use crate::gui::style::container::ContainerType;
 use crate::gui::widget::{
-    Column, Container, Element, PickList, Text, horizontal_space, vertical_space,
+    Button, Column, Container, Element, PickList, Row, Scrollable, Text, TextInput,
+    horizontal_space, vertical_space,
 };
 use crate::gui::windows::main::MainWindowEvent;
 use crate::row;
 use crate::utils::string::format_seconds;
-use iced::Length;
 use iced::alignment::{Horizontal, Vertical};
+use iced::widget::Image;
+use iced::widget::image::Handle;
+use iced::widget::scrollable::{Direction, Scrollbar};
+use iced::{Alignment, ContentFit, Length};
 
-pub fn caster_page<'a>(config: &Config) -> Element<'a, MainWindowEvent> {
+pub fn caster_page<'a>(
+    config: &Config,
+    thumbnails: &[Option<Handle>],
+    chat_draft: &str,
+) -> Element<'a, MainWindowEvent> {
     let Some(crate::config::Mode::Caster(caster)) = &config.mode else {
         unreachable!("Mode must be Caster here")
     };
@@ -24,14 +37,118 @@ pub fn caster_page<'a>(config: &Config) -> Element<'a, MainWindowEvent> {
 
     content = if caster.is_streaming() {
         is_streaming = true;
+
+        let mut status = row![
+            Icon::Clock.to_text(),
+            horizontal_space().width(7),
+            Text::new(format_seconds(caster.streaming_time).to_string()).font(FONT_FAMILY_BOLD),
+            horizontal_space().width(25),
+            Icon::VolumeHigh.to_text(),
+            horizontal_space().width(7),
+            level_meter(caster.audio_level(), 120.0)
+        ]
+        .align_y(Alignment::Center);
+
+        if caster.is_idle_paused() {
+            status = status
+                .push(horizontal_space().width(25))
+                .push(Icon::Pause.to_text())
+                .push(horizontal_space().width(7))
+                .push(Text::new("Paused, no receivers"));
+        }
+
+        if let Some((used, cap)) = caster.data_usage() {
+            status = status
+                .push(horizontal_space().width(25))
+                .push(Icon::Download.to_text())
+                .push(horizontal_space().width(7))
+                .push(Text::new(format!("{:.1} / {:.0} MB/min", used, cap)));
+        }
+
+        let actions = row![
+            IconButton::new()
+                .label("Annotations")
+                .icon(Icon::Image)
+                .build()
+                .width(130)
+                .on_press(MainWindowEvent::ToggleAnnotationWindow),
+            IconButton::new()
+                .label("Manual SDP")
+                .icon(Icon::Sync)
+                .build()
+                .width(130)
+                .on_press(MainWindowEvent::ShowSDP),
+            IconButton::new()
+                .label("Link")
+                .icon(Icon::Copy)
+                .build()
+                .width(100)
+                .on_press(MainWindowEvent::CopyConnectionLink),
+            IconButton::new()
+                .label(if caster.is_keycast() {
+                    "Hide Keys"
+                } else {
+                    "Show Keys"
+                })
+                .icon(Icon::Keyboard)
+                .build()
+                .width(130)
+                .on_press(MainWindowEvent::ToggleKeycast),
+        ]
+        .spacing(5);
+
+        // Portal e backend generico includono sempre il puntatore
+        let actions = if caster.can_hide_cursor() {
+            actions.push(
+                IconButton::new()
+                    .label(if caster.is_showing_cursor() {
+                        "Hide Cursor"
+                    } else {
+                        "Show Cursor"
+                    })
+                    .icon(Icon::Circle)
+                    .build()
+                    .width(130)
+                    .on_press(MainWindowEvent::ToggleCursorCapture),
+            )
+        } else {
+            actions
+        };
+
+        let actions = actions.push(
+            IconButton::new()
+                .label(if caster.is_audio_muted() {
+                    "Unmute"
+                } else {
+                    "Mute"
+                })
+                .icon(if caster.is_audio_muted() {
+                    Icon::VolumeMute
+                } else {
+                    Icon::VolumeHigh
+                })
+                .build()
+                .width(130)
+                .on_press(MainWindowEvent::ToggleAudioMute),
+        );
+
+        #[cfg(feature = "remote-control")]
+        let actions = actions.push(
+            IconButton::new()
+                .label(if caster.is_remote_control_allowed() {
+                    "Revoke Control"
+                } else {
+                    "Allow Control"
+                })
+                .icon(Icon::Connect)
+                .build()
+                .width(150)
+                .on_press(MainWindowEvent::CasterAllowControl),
+        );
+
         content
             .push(
-                Container::new(row![
-                    Icon::Clock.to_text(),
-                    horizontal_space().width(7),
-                    Text::new(format_seconds(caster.streaming_time).to_string())
-                        .font(FONT_FAMILY_BOLD)
-                ])
+                Container::new(status)
                 .width(Length::Fill)
                 .height(Length::Fill)
                 .align_x(Horizontal::Center)
@@ -40,37 +157,7 @@ pub fn caster_page<'a>(config: &Config) -> Element<'a, MainWindowEvent> {
                 .class(ContainerType::Standard),
             )
             .push(
-                Container::new(
-                    row![
-                        IconButton::new()
-                            .label("Annotations")
-                            .icon(Icon::Image)
-                            .build()
-                            .width(180)
-                            .on_press(MainWindowEvent::ShowAnnotationWindow),
-                        IconButton::new()
-                            .label("Manual SDP")
-                            .icon(Icon::Sync)
-                            .build()
-                            .width(180)
-                            .on_press(MainWindowEvent::ShowSDP),
-                        IconButton::new()
-                            .label(if caster.is_audio_muted() {
-                                "Unmute"
-                            } else {
-                                "Mute"
-                            })
-                            .icon(if caster.is_audio_muted() {
-                                Icon::VolumeMute
-                            } else {
-                                Icon::VolumeHigh
-                            })
-                            .build()
-                            .width(140)
-                            .on_press(MainWindowEvent::ToggleAudioMute)
-                    ]
-                    .spacing(5),
-                )
+                Container::new(actions)
                 .width(Length::Fill)
                 .height(Length::Fill)
                 .align_x(Horizontal::Center)
@@ -81,7 +168,20 @@ pub fn caster_page<'a>(config: &Config) -> Element<'a, MainWindowEvent> {
     } else {
         content
             .push(
-                Container::new(row![displays_picklist(config)])
+                Container::new(
+                    row![
+                        if thumbnails.iter().any(Option::is_some) {
+                            displays_thumbnails(config, thumbnails)
+                        } else {
+                            displays_picklist(config)
+                        },
+                        windows_picklist(config),
+                        profile_picklist(config),
+                        fps_cap_picklist(config),
+                        data_cap_picklist(config)
+                    ]
+                    .spacing(10),
+                )
                     .center(Length::Fill)
                     .height(80)
                     .class(ContainerType::Standard),
@@ -100,27 +200,96 @@ pub fn caster_page<'a>(config: &Config) -> Element<'a, MainWindowEvent> {
                         .icon(Icon::Area)
                         .dim(Dimensions::Large)
                         .build()
-                        .on_press(MainWindowEvent::AreaSelection)
+                        .on_press(MainWindowEvent::AreaSelection),
+                    horizontal_space().width(10),
+                    IconButton::new()
+                        .label(if caster.is_following_window() {
+                            "Following"
+                        } else {
+                            "Follow Window"
+                        })
+                        .icon(Icon::Cast)
+                        .dim(Dimensions::Large)
+                        .build()
+                        .on_press(MainWindowEvent::AreaFollowWindow)
                 ])
                 .center(Length::Fill)
                 .height(80)
                 .class(ContainerType::Standard),
             )
             .push(
-                Container::new(row![
-                    IconButton::new()
-                        .label("Home")
-                        .icon(Icon::Home)
-                        .build()
-                        .on_press(MainWindowEvent::Home)
-                ])
+                Container::new(
+                    row![
+                        IconButton::new()
+                            .label("Home")
+                            .icon(Icon::Home)
+                            .build()
+                            .on_press(MainWindowEvent::Home),
+                        horizontal_space().width(10),
+                        IconButton::new()
+                            .label(if caster.is_audio_only() {
+                                "Audio Only"
+                            } else {
+                                "Audio + Video"
+                            })
+                            .icon(if caster.is_audio_only() {
+                                Icon::VolumeHigh
+                            } else {
+                                Icon::Video
+                            })
+                            .build()
+                            .width(160)
+                            .on_press(MainWindowEvent::CasterToggleAudioOnly),
+                        horizontal_space().width(10),
+                        IconButton::new()
+                            .label(if !caster.is_test_pattern() {
+                                "Live Screen"
+                            } else if cfg!(feature = "test-capture") {
+                                "Test Pattern"
+                            } else {
+                                "Test Tone"
+                            })
+                            .icon(if caster.is_test_pattern() {
+                                Icon::Image
+                            } else {
+                                Icon::Screen
+                            })
+                            .build()
+                            .width(160)
+                            .on_press(MainWindowEvent::CasterToggleTestPattern),
+                        horizontal_space().width(10),
+                        TextInput::new("Caster name", caster.instance_name())
+                            .on_input(MainWindowEvent::CasterChangeName)
+                            .padding([11, 8])
+                            .width(160),
+                        horizontal_space().width(10),
+                        TextInput::new("Passphrase", &config.manual_passphrase)
+                            .secure(true)
+                            .on_input(MainWindowEvent::ManualPassphrase)
+                            .padding([11, 8])
+                            .width(140)
+                    ]
+                    .align_y(Alignment::Center),
+                )
                 .center(Length::Fill)
                 .height(80)
                 .class(ContainerType::Standard),
             )
     };
 
-    content = content.push(vertical_space()).push(
+    // Durante lo stream la chat con i receiver occupa lo spazio libero
+    content = if is_streaming {
+        content.push(chat_panel(
+            caster.chat_messages(),
+            chat_draft,
+            MainWindowEvent::ChatInput,
+            MainWindowEvent::ChatSend,
+        ))
+    } else {
+        content.push(vertical_space())
+    };
+
+    content = content.push(
         Container::new(if is_streaming {
             IconButton::new()
                 .icon(Icon::Pause)
@@ -165,7 +334,7 @@ fn displays_picklist(config: &Config) -> Container<'static, MainWindowEvent> {
         return Container::new(iced::widget::Space::new());
     }
 
-    let options: Vec<String> = displays.iter().map(|d| d.to_string()).collect();
+    let options: Vec<String> = displays.iter().map(display_option).collect();
     let selected = caster
         .get_selected_display()
         .and_then(|sel| displays.iter().position(|d| d == &sel))
@@ -184,3 +353,157 @@ fn displays_picklist(config: &Config) -> Container<'static, MainWindowEvent> {
         .align_x(Horizontal::Center)
         .align_y(Vertical::Center)
 }
+
+/// Segnala il monitor principale nella lista dei display
+const PRIMARY_SUFFIX: &str = " · Primary";
+
+fn display_option(display: &<ScreenCaptureImpl as DisplaySelector>::Display) -> String {
+    let mut name = display.to_string();
+    if ScreenCaptureImpl::is_primary(display) {
+        name.push_str(PRIMARY_SUFFIX);
+    }
+    name
+}
+
+/// Singola finestra come sorgente, al posto di un intero monitor
+fn windows_picklist(config: &Config) -> Container<'static, MainWindowEvent> {
+    let Some(crate::config::Mode::Caster(caster)) = &config.mode else {
+        unreachable!("Mode must be Caster here")
+    };
+
+    let windows = caster.get_windows();
+
+    if windows.is_empty() {
+        return Container::new(iced::widget::Space::new());
+    }
+
+    // La voce della lista, col titolo aggiornato
+    let selected = caster
+        .get_selected_display()
+        .and_then(|sel| windows.iter().find(|w| **w == sel).cloned());
+
+    Container::new(
+        PickList::new(
+            windows.to_vec(),
+            selected,
+            MainWindowEvent::CasterChangeWindow,
+        )
+        .placeholder("Window...")
+        .width(160)
+        .padding([11, 8]),
+    )
+    .align_x(Horizontal::Center)
+    .align_y(Vertical::Center)
+}
+
+/// Selettore con l'anteprima di ogni display, al posto della lista a tendina
+fn displays_thumbnails(
+    config: &Config,
+    thumbnails: &[Option<Handle>],
+) -> Container<'static, MainWindowEvent> {
+    let Some(crate::config::Mode::Caster(caster)) = &config.mode else {
+        unreachable!("Mode must be Caster here")
+    };
+
+    let displays = caster.get_displays();
+    let selected = caster
+        .get_selected_display()
+        .and_then(|sel| displays.iter().position(|d| d == &sel))
+        .unwrap_or(0);
+
+    let strip = displays
+        .iter()
+        .enumerate()
+        .fold(Row::new().spacing(6), |strip, (idx, display)| {
+            let name = display.to_string();
+            // Senza risoluzione, già visibile nell'anteprima
+            let mut label = name.split(" (").next().unwrap_or(&name).to_string();
+            if ScreenCaptureImpl::is_primary(display) {
+                label.push_str(PRIMARY_SUFFIX);
+            }
+
+            let preview: Element<'static, MainWindowEvent> =
+                match thumbnails.get(idx).cloned().flatten() {
+                    Some(handle) => Image::new(handle)
+                        .width(THUMBNAIL_WIDTH as f32)
+                        .height(54)
+                        .content_fit(ContentFit::Contain)
+                        .into(),
+                    None => Container::new(Icon::Screen.to_text().size(24))
+                        .center_x(THUMBNAIL_WIDTH as f32)
+                        .center_y(54)
+                        .into(),
+                };
+
+            strip.push(
+                Button::new(
+                    Column::new()
+                        .align_x(Alignment::Center)
+                        .spacing(2)
+                        .push(preview)
+                        .push(Text::new(label).size(10)),
+                )
+                .padding(2)
+                .class(if idx == selected {
+                    ButtonType::Disabled
+                } else {
+                    ButtonType::Transparent
+                })
+                .on_press(MainWindowEvent::CasterChangeDisplay(idx)),
+            )
+        });
+
+    Container::new(Scrollable::new(strip).direction(Direction::Horizontal(
+        Scrollbar::new().width(3).scroller_width(3),
+    )))
+    .max_width(320)
+    .align_x(Horizontal::Center)
+    .align_y(Vertical::Center)
+}
+
+fn profile_picklist(config: &Config) -> Container<'static, MainWindowEvent> {
+    let Some(crate::config::Mode::Caster(caster)) = &config.mode else {
+        unreachable!("Mode must be Caster here")
+    };
+
+    Container::new(
+        PickList::new(
+            StreamProfile::PRESETS,
+            Some(caster.stream_profile()),
+            MainWindowEvent::CasterChangeProfile,
+        )
+        .padding([11, 8]),
+    )
+    .align_x(Horizontal::Center)
+    .align_y(Vertical::Center)
+}
+
+fn fps_cap_picklist(config: &Config) -> Container<'static, MainWindowEvent> {
+    Container::new(
+        PickList::new(
+            FpsCap::PRESETS,
+            Some(config.capture.max_fps),
+            MainWindowEvent::CasterChangeFpsCap,
+        )
+        .padding([11, 8]),
+    )
+    .align_x(Horizontal::Center)
+    .align_y(Vertical::Center)
+}
+
+fn data_cap_picklist(config: &Config) -> Container<'static, MainWindowEvent> {
+    let Some(crate::config::Mode::Caster(caster)) = &config.mode else {
+        unreachable!("Mode must be Caster here")
+    };
+
+    Container::new(
+        PickList::new(
+            DataCap::PRESETS,
+            Some(caster.data_cap()),
+            MainWindowEvent::CasterChangeDataCap,
+        )
+        .padding([11, 8]),
+    )
+    .align_x(Horizontal::Center)
+    .align_y(Vertical::Center)
+}
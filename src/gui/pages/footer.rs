@@ -38,6 +38,18 @@ pub fn footer<'a>() -> Container<'a, MainWindowEvent> {
             )),
         )
         .push(horizontal_space().width(Length::Fill))
+        .push(
+            Button::new(
+                Icon::Folder
+                    .to_text()
+                    .size(15.0)
+                    .align_x(Horizontal::Center)
+                    .align_y(Vertical::Center)
+                    .line_height(LineHeight::Relative(1.0)),
+            )
+            .class(ButtonType::Transparent)
+            .on_press(MainWindowEvent::SettingsPage),
+        )
         .push(
             Button::new(
                 Icon::Info
@@ -4,6 +4,7 @@ use crate::gui::common::icons::Icon;
 use crate::gui::components::button::{IconButton, Key4Board};
 use crate::gui::style::button::ButtonType;
 use crate::gui::style::container::ContainerType;
+use crate::gui::style::theme::csx::StyleType;
 use crate::gui::widget::{horizontal_space, vertical_space, Container, Element, Row, Space, Text};
 use crate::gui::windows::main::{MainWindow, MainWindowEvent};
 use iced::keyboard::{Key, Modifiers};
@@ -97,10 +98,27 @@ pub fn initial_page<'a>(main_window: &MainWindow, config: &Config) -> Element<'a
                 .push(horizontal_space().width(Length::Fill))
                 .push(
                     IconButton::new()
-                        .label("Theme")
+                        .label("Accent")
+                        .icon(Icon::Circle)
+                        .color(
+                            main_window
+                                .accent
+                                .unwrap_or(main_window.theme.get_palette().action)
+                        )
+                        .build()
+                        .on_press(MainWindowEvent::AccentPicker)
+                )
+                .push(horizontal_space().width(10))
+                .push(
+                    IconButton::new()
+                        .label(if main_window.theme_pinned {
+                            main_window.theme.display_name()
+                        } else {
+                            "System"
+                        })
                         .icon(Icon::Sync)
                         .build()
-                        .on_press(MainWindowEvent::ThemeUpdate(main_window.theme.toggle()))
+                        .on_press(theme_cycle(main_window))
                 )
                 .align_y(Alignment::Center)
                 .width(Length::Fill)
@@ -154,6 +172,15 @@ pub fn initial_page<'a>(main_window: &MainWindow, config: &Config) -> Element<'a
         .into()
 }
 
+/// Il pulsante tema scorre i temi fissi e poi torna a seguire il sistema
+fn theme_cycle(main_window: &MainWindow) -> MainWindowEvent {
+    match (&main_window.theme, main_window.theme_pinned) {
+        (StyleType::Darcula, true) => MainWindowEvent::ThemeFollowSystem,
+        (theme, true) => MainWindowEvent::ThemeUpdate(theme.toggle()),
+        (_, false) => MainWindowEvent::ThemeUpdate(StyleType::LightVenus),
+    }
+}
+
 fn shortcuts<Message: 'static>(
     key_bind: &(Modifiers, Key),
     str: &'static str,
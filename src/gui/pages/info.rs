@@ -1,14 +1,16 @@
 use crate::config::{app_name, app_version};
 use crate::gui::common::icons::Icon;
 use crate::gui::components::button::IconButton;
+use crate::gui::style::container::ContainerType;
 use crate::gui::widget::vertical_space;
-use crate::gui::widget::{Column, Container, Element};
+use crate::gui::widget::{Column, Container, Element, Text};
 use crate::gui::windows::main::MainWindowEvent;
 use iced::Length;
 use iced::alignment::{Horizontal, Vertical};
 
-pub fn info_page<'a>() -> Element<'a, MainWindowEvent> {
-    let content = Column::new()
+/// `selftest`: riepilogo dell'ultimo self-test, se eseguito
+pub fn info_page<'a>(selftest: Option<&str>, running: bool) -> Element<'a, MainWindowEvent> {
+    let mut content = Column::new()
         .push(vertical_space().height(Length::Fill))
         .push(
             IconButton::new()
@@ -54,8 +56,56 @@ pub fn info_page<'a>() -> Element<'a, MainWindowEvent> {
                 .width(240)
                 .height(40),
         )
-        .spacing(8)
-        .push(vertical_space().height(Length::Fill));
+        .push(vertical_space().height(10))
+        .push({
+            let button = IconButton::new()
+                .icon(Icon::Sync)
+                .label(if running { "Testing..." } else { "Self-test" })
+                .build()
+                .width(240)
+                .height(40);
+            if running {
+                button
+            } else {
+                button.on_press(MainWindowEvent::RunSelfTest)
+            }
+        })
+        .push(
+            IconButton::new()
+                .icon(Icon::Folder)
+                .label("Open log folder")
+                .build()
+                .width(240)
+                .height(40)
+                .on_press(MainWindowEvent::OpenLogFolder),
+        )
+        .push(
+            IconButton::new()
+                .icon(Icon::Copy)
+                .label("Copy diagnostics")
+                .build()
+                .width(240)
+                .height(40)
+                .on_press(MainWindowEvent::CopyDiagnostics),
+        )
+        .spacing(8);
+
+    if let Some(report) = selftest {
+        content = content
+            .push(
+                Container::new(Text::new(report.to_string()).size(12))
+                    .padding(10)
+                    .class(ContainerType::Standard),
+            )
+            .push(
+                IconButton::new()
+                    .icon(Icon::Copy)
+                    .label("Copy")
+                    .build()
+                    .on_press(MainWindowEvent::CopyToClipboard(report.to_string())),
+            );
+    }
+    let content = content.push(vertical_space().height(Length::Fill));
 
     Container::new(content)
         .width(Length::Fill)
@@ -4,3 +4,4 @@ pub mod home;
 pub mod info;
 pub mod popup;
 pub mod receiver;
+pub mod settings;
@@ -1,5 +1,7 @@
 use crate::gui::components::awmodal::{GuiComponent, GuiInterface};
+use crate::gui::popup::accent::AccentModal;
 use crate::gui::popup::ip::IPModal;
+use crate::gui::popup::permissions::PermissionsModal;
 use crate::gui::popup::shortcuts::ShortcutModal;
 use crate::gui::popup::wrtc::WrtcModal;
 use crate::gui::windows::main::MainWindowEvent;
@@ -8,6 +10,8 @@ pub enum PopupType {
     IP(IPModal),
     HotkeyUpdate(ShortcutModal),
     ManualWRTC(WrtcModal),
+    Accent(AccentModal),
+    Permissions(PermissionsModal),
 }
 
 impl GuiComponent for PopupType {
@@ -18,6 +22,8 @@ impl GuiComponent for PopupType {
             PopupType::IP(modal) => modal,
             PopupType::HotkeyUpdate(modal) => modal,
             PopupType::ManualWRTC(modal) => modal,
+            PopupType::Accent(modal) => modal,
+            PopupType::Permissions(modal) => modal,
         }
     }
 
@@ -26,6 +32,8 @@ impl GuiComponent for PopupType {
             PopupType::IP(modal) => modal,
             PopupType::HotkeyUpdate(modal) => modal,
             PopupType::ManualWRTC(modal) => modal,
+            PopupType::Accent(modal) => modal,
+            PopupType::Permissions(modal) => modal,
         }
     }
 }
@@ -1,17 +1,28 @@
 use crate::assets::FONT_FAMILY_BOLD;
 use crate::config::{Config, Mode};
+use crate::decoder::MAX_VOLUME;
+use crate::display::VideoFit;
 use crate::gui::common::icons::Icon;
 use crate::gui::components::button::IconButton;
 use crate::gui::components::video::{Video, VideoPlayer};
+use crate::gui::components::{RemoteAnnotations, chat_panel, level_meter};
 use crate::gui::style::container::ContainerType;
 use crate::gui::style::text::TextType;
-use crate::gui::widget::{Column, Container, Element, Row};
+use crate::gui::widget::{Canvas, Column, Container, Element, PickList, Row, Slider, Stack};
 use crate::gui::windows::main::MainWindowEvent;
+use crate::pipeline::health::HealthSummary;
+use crate::pipeline::receiver::LatencyProfile;
+use crate::pipeline::{ConnectionState, DropSource, MetricsSnapshot};
 use iced::widget::Text;
 use iced::{Alignment, Length};
 use iced::{Padding, alignment};
 
-pub fn client_page<'a, 'b>(video: &'b Video, config: &Config) -> Element<'a, MainWindowEvent>
+/// `chat` è il testo in scrittura quando il pannello chat è aperto
+pub fn client_page<'a, 'b>(
+    video: &'b Video,
+    config: &Config,
+    chat: Option<&str>,
+) -> Element<'a, MainWindowEvent>
 where
     'b: 'a,
 {
@@ -50,6 +61,61 @@ where
                 .build()
                 .on_press(MainWindowEvent::ToggleAudioMute),
         )
+        .push(
+            Slider::new(
+                0..=(MAX_VOLUME * 100.0) as u32,
+                config.playback.volume_percent,
+                MainWindowEvent::PlaybackVolume,
+            )
+            .step(5u32)
+            .on_release(MainWindowEvent::PlaybackVolumeSave)
+            .width(100),
+        )
+        .push(Text::new(format!("{}%", config.playback.volume_percent)).size(14))
+        .push(level_meter(client.audio_level(), 100.0))
+        .push(
+            IconButton::new()
+                .label(if chat.is_some() { "Hide Chat" } else { "Chat" })
+                .icon(Icon::User)
+                .build()
+                .on_press(MainWindowEvent::ToggleChat),
+        )
+        .push(
+            IconButton::new()
+                .label(match config.playback.fit {
+                    VideoFit::Fit => "Fill",
+                    VideoFit::Fill => "Fit",
+                })
+                .icon(match config.playback.fit {
+                    VideoFit::Fit => Icon::Screen,
+                    VideoFit::Fill => Icon::Area,
+                })
+                .build()
+                .on_press(MainWindowEvent::PlaybackFitToggle),
+        );
+
+    #[cfg(feature = "remote-control")]
+    let actions = actions.push(
+        IconButton::new()
+            .label(if client.is_controlling() {
+                "Release"
+            } else {
+                "Control"
+            })
+            .icon(Icon::Connect)
+            .build()
+            .on_press(MainWindowEvent::ToggleRemoteControl),
+    );
+
+    let actions = actions
+        .push(
+            PickList::new(
+                LatencyProfile::ALL,
+                Some(client.latency_profile()),
+                MainWindowEvent::LatencyProfile,
+            )
+            .padding([11, 8]),
+        )
         .push({
             let mut button = IconButton::new().label("Exit").icon(Icon::Stop).build();
             if !client.is_saving() {
@@ -65,27 +131,114 @@ where
             _ => unreachable!("Mode must be Client here"),
         };
 
-        if client.is_streaming() {
-            Container::new(VideoPlayer::new(video))
-                .height(Length::Fill)
-                .width(Length::Fill)
-                .align_x(alignment::Horizontal::Center)
-                .class(ContainerType::Video)
-        } else {
+        if client.is_streaming() && client.is_audio_only() {
             Container::new(
-                Text::new("Waiting for the Caster...")
-                    .font(FONT_FAMILY_BOLD)
-                    .size(22.0)
-                    .class(TextType::White),
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push(Icon::VolumeHigh.to_text().size(22.0).class(TextType::White))
+                    .push(
+                        Text::new("Audio only")
+                            .font(FONT_FAMILY_BOLD)
+                            .size(22.0)
+                            .class(TextType::White),
+                    ),
             )
             .height(Length::Fill)
             .width(Length::Fill)
             .align_x(alignment::Horizontal::Center)
             .align_y(alignment::Vertical::Center)
             .class(ContainerType::Video)
+        } else if client.is_streaming() {
+            let player = || {
+                let player = VideoPlayer::new(video);
+                #[cfg(feature = "remote-control")]
+                let player = if client.is_controlling() {
+                    player.on_input(MainWindowEvent::RemoteInput)
+                } else {
+                    player
+                };
+                player
+            };
+
+            // Same area and fit as the video, so the annotations match the frame
+            let annotations = client.annotations();
+            let player = if annotations.is_empty() {
+                Element::from(player())
+            } else {
+                Stack::new()
+                    .push(player())
+                    .push(
+                        Canvas::new(RemoteAnnotations::new(
+                            annotations,
+                            video.fit(),
+                            video.size(),
+                        ))
+                        .width(Length::Fill)
+                        .height(Length::Fill),
+                    )
+                    .into()
+            };
+
+            // Stallo/riconnessione: il messaggio resta sopra l'ultimo frame
+            let player = match client.connection_state() {
+                state @ (ConnectionState::Buffering
+                | ConnectionState::Stalled
+                | ConnectionState::Reconnecting) => Stack::new()
+                    .push(player)
+                    .push(
+                        Container::new(connection_status(state, config.e_time))
+                            .center(Length::Fill),
+                    )
+                    .into(),
+                _ => player,
+            };
+
+            let player = if client.is_metrics_overlay_visible() {
+                Stack::new()
+                    .push(player)
+                    .push(metrics_overlay(
+                        client.metrics().snapshot(),
+                        client.health().summary(),
+                    ))
+                    .into()
+            } else {
+                player
+            };
+
+            Container::new(player)
+                .height(Length::Fill)
+                .width(Length::Fill)
+                .align_x(alignment::Horizontal::Center)
+                .class(ContainerType::Video)
+        } else {
+            Container::new(connection_status(client.connection_state(), config.e_time))
+                .height(Length::Fill)
+                .width(Length::Fill)
+                .align_x(alignment::Horizontal::Center)
+                .align_y(alignment::Vertical::Center)
+                .class(ContainerType::Video)
         }
     };
 
+    let video: Element<'a, MainWindowEvent> = match chat {
+        Some(draft) => Row::new()
+            .spacing(10)
+            .push(video)
+            .push(
+                Container::new(chat_panel(
+                    client.chat_messages(),
+                    draft,
+                    MainWindowEvent::ChatInput,
+                    MainWindowEvent::ChatSend,
+                ))
+                .width(260)
+                .height(Length::Fill),
+            )
+            .into(),
+        None => video.into(),
+    };
+
     let content = Column::new()
         .spacing(20)
         .push(video)
@@ -105,3 +258,108 @@ where
         })
         .into()
 }
+
+/// Icona + messaggio per lo stato della connessione; nelle fasi di attesa i
+/// puntini avanzano ad ogni tick come indicatore di attività.
+fn connection_status<'a>(state: ConnectionState, tick: u64) -> Element<'a, MainWindowEvent> {
+    let (icon, message) = match state {
+        ConnectionState::Idle | ConnectionState::Negotiating => {
+            (Icon::Sync, "Waiting for the Caster")
+        }
+        ConnectionState::Connecting => (Icon::Connect, "Connecting to the Caster"),
+        ConnectionState::Buffering => (Icon::Sync, "Buffering"),
+        ConnectionState::Playing => (Icon::Video, "Playing"),
+        ConnectionState::Stalled => (Icon::Warning, "Stream stalled, waiting for frames"),
+        ConnectionState::Reconnecting => (Icon::Warning, "Connection lost, reconnecting"),
+        ConnectionState::Failed => (Icon::Error, "Unable to reach the Caster"),
+    };
+
+    let message = if state.is_waiting() {
+        format!("{}{}", message, ".".repeat((tick % 4) as usize))
+    } else {
+        message.to_string()
+    };
+
+    Row::new()
+        .spacing(10)
+        .align_y(Alignment::Center)
+        .push(icon.to_text().size(22.0).class(TextType::White))
+        .push(
+            Text::new(message)
+                .font(FONT_FAMILY_BOLD)
+                .size(22.0)
+                .class(TextType::White),
+        )
+        .into()
+}
+
+/// Overlay di debug: latenza media e coda di ogni stadio del receiver, la
+/// stima glass-to-glass (PTS contro orologio di sistema) e dove si perdono i frame.
+fn metrics_overlay<'a>(
+    snapshot: MetricsSnapshot,
+    health: HealthSummary,
+) -> Element<'a, MainWindowEvent> {
+    let line = |text: String| Text::new(text).size(12.0).class(TextType::White);
+
+    let mut lines = Column::new().spacing(2).push(
+        Text::new("Pipeline latency")
+            .font(FONT_FAMILY_BOLD)
+            .size(12.0)
+            .class(TextType::White),
+    );
+    for sample in snapshot.stages {
+        lines = lines.push(line(if sample.samples == 0 {
+            format!("{:<8} —  (queue {})", sample.stage, sample.queue_depth)
+        } else {
+            format!(
+                "{:<8} {:>6.1} ms  (queue {})",
+                sample.stage,
+                sample.avg.as_secs_f64() * 1000.0,
+                sample.queue_depth
+            )
+        }));
+    }
+    lines = lines
+        .push(line(format!(
+            "{:<8} {:>6.1} ms",
+            "Stages",
+            snapshot.pipeline_total().as_secs_f64() * 1000.0
+        )))
+        .push(line(match snapshot.glass_to_glass {
+            Some(latency) => format!(
+                "{:<8} {:>6.1} ms (est.)",
+                "G2G",
+                latency.as_secs_f64() * 1000.0
+            ),
+            None => format!("{:<8} —", "G2G"),
+        }));
+    if let Some(decoder) = snapshot.decoder {
+        lines = lines.push(line(format!("{:<8} {}", "Decoder", decoder)));
+    }
+
+    // Solo gli stadi del receiver: cattura e invio si contano sul caster
+    lines = lines.push(
+        Text::new("Drops")
+            .font(FONT_FAMILY_BOLD)
+            .size(12.0)
+            .class(TextType::White),
+    );
+    for source in [DropSource::Reorder, DropSource::Sync, DropSource::Display] {
+        let count = health.drops_by_source[source as usize];
+        let worst = health.bottleneck() == Some(source);
+        let marker = if worst { "  (most)" } else { "" };
+        lines = lines.push(line(format!("{}: {}{}", source, count, marker)));
+    }
+
+    Container::new(
+        Container::new(lines)
+            .padding(8)
+            .class(ContainerType::DarkFilter),
+    )
+    .padding(10)
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .align_x(alignment::Horizontal::Left)
+    .align_y(alignment::Vertical::Top)
+    .into()
+}
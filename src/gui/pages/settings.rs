@@ -0,0 +1,1006 @@
+use crate::assets::FONT_FAMILY_BOLD;
+use crate::capture::audio::{OpusBitrate, OpusFrameDuration};
+use crate::capture::overlay::{RingColor, RingSize};
+use crate::capture::timestamp::TimestampFormat;
+use crate::capture::watermark::WatermarkCorner;
+use crate::capture::{BitrateMode, ColorSpace, EncodeScale, HdrMode, Simulcast};
+use crate::config::{Config, DEFAULT_FILENAME_TEMPLATE, FILENAME_TOKENS};
+use crate::display::DisplayPolicy;
+use crate::gui::common::icons::Icon;
+use crate::gui::components::button::IconButton;
+use crate::gui::style::container::ContainerType;
+use crate::gui::style::text::TextType;
+use crate::gui::style::theme::system::ThemeChoice;
+use crate::gui::widget::{
+    Column, Container, Element, PickList, Row, Slider, Text, TextInput, horizontal_space,
+    vertical_space,
+};
+use crate::gui::windows::main::MainWindowEvent;
+use crate::pipeline::receiver::av_offset::MAX_AV_OFFSET_MS;
+use crate::pipeline::receiver::{AvOffset, LatencyProfile};
+use crate::pipeline::sender::RtpMtu;
+use crate::pipeline::stats_log::StatsFormat;
+use crate::utils::logging::LogLevel;
+use crate::utils::path::shorten_path;
+use crate::workers::cursor_follow::FollowCursorDelay;
+use crate::workers::idle::IdleTimeout;
+use iced::{Alignment, Length};
+
+/// Voce della lista delle uscite audio che corrisponde a `None`
+const SYSTEM_DEFAULT_DEVICE: &str = "System default";
+
+pub fn settings_page<'a>(
+    config: &Config,
+    theme: ThemeChoice,
+    warning: Option<&str>,
+    watermark_warning: Option<&str>,
+    output_devices: &[String],
+    exclude_draft: &str,
+    webhook_draft: &str,
+) -> Element<'a, MainWindowEvent> {
+    let header = Container::new(
+        crate::row![
+            horizontal_space().width(Length::Fill),
+            Text::new("Output Settings").font(FONT_FAMILY_BOLD).size(18),
+            horizontal_space().width(Length::Fill),
+        ]
+        .align_y(Alignment::Center),
+    )
+    .center(Length::Fill)
+    .height(80)
+    .class(ContainerType::Standard);
+
+    let directory = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Save folder")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(Text::new(shorten_path(config.output.directory.clone())).size(14))
+        .push(horizontal_space().width(Length::Fill))
+        .push(
+            IconButton::new()
+                .label("Browse")
+                .icon(Icon::Folder)
+                .build()
+                .on_press(MainWindowEvent::OutputPickDirectory),
+        );
+
+    let template = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("File name")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            TextInput::new(DEFAULT_FILENAME_TEMPLATE, &config.output.filename_template)
+                .on_input(MainWindowEvent::OutputTemplate)
+                .padding([8, 12]),
+        );
+
+    let segment_field = |value: u32, on_input: fn(String) -> MainWindowEvent| {
+        let value = if value == 0 {
+            String::new()
+        } else {
+            value.to_string()
+        };
+        TextInput::new("off", &value)
+            .on_input(on_input)
+            .padding([8, 12])
+            .width(80)
+    };
+
+    let segments = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Split every")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(segment_field(
+            config.output.segment_minutes,
+            MainWindowEvent::OutputSegmentMinutes,
+        ))
+        .push(Text::new("minutes or").size(14))
+        .push(segment_field(
+            config.output.segment_megabytes,
+            MainWindowEvent::OutputSegmentMegabytes,
+        ))
+        .push(Text::new("MB").size(14));
+
+    let countdown = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Countdown")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(segment_field(
+            config.output.countdown_seconds,
+            MainWindowEvent::OutputCountdown,
+        ))
+        .push(Text::new("seconds before streaming or recording").size(14));
+
+    let resume = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Resume within")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(segment_field(
+            config.output.resume_seconds,
+            MainWindowEvent::OutputResume,
+        ))
+        .push(Text::new("seconds of a dropped connection, in the same file").size(14));
+
+    // Il dispositivo salvato resta selezionabile anche se ora è scollegato
+    let selected_device = config
+        .playback
+        .output_device
+        .clone()
+        .unwrap_or_else(|| String::from(SYSTEM_DEFAULT_DEVICE));
+    let mut devices = vec![String::from(SYSTEM_DEFAULT_DEVICE)];
+    devices.extend(output_devices.iter().cloned());
+    if !devices.contains(&selected_device) {
+        devices.push(selected_device.clone());
+    }
+
+    let playback = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Audio output")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(devices, Some(selected_device), |device| {
+                MainWindowEvent::PlaybackDevice((device != SYSTEM_DEFAULT_DEVICE).then_some(device))
+            })
+            .padding([8, 12])
+            .width(Length::Fill),
+        );
+
+    let display_policy = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Frame dropping")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(
+                DisplayPolicy::ALL,
+                Some(config.playback.display_policy),
+                MainWindowEvent::PlaybackDisplayPolicy,
+            )
+            .padding([8, 12])
+            .width(Length::Fill),
+        );
+
+    // Colori slavati o troppo scuri: matrice o range diversi da quelli del caster
+    let color_space = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Colors")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(
+                ColorSpace::ALL,
+                Some(config.playback.color),
+                MainWindowEvent::PlaybackColorSpace,
+            )
+            .padding([8, 12])
+            .width(Length::Fill),
+        );
+
+    let av_offset = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("A/V offset")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            Slider::new(
+                -MAX_AV_OFFSET_MS..=MAX_AV_OFFSET_MS,
+                config.playback.av_offset_ms,
+                MainWindowEvent::PlaybackAvOffset,
+            )
+            .step(10i64)
+            .on_release(MainWindowEvent::PlaybackAvOffsetSave)
+            .width(Length::Fill),
+        )
+        .push(
+            Text::new(AvOffset::label(config.playback.av_offset_ms))
+                .size(14)
+                .width(150),
+        );
+
+    let max_latency = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Max latency")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(segment_field(
+            config.playback.max_latency_ms,
+            MainWindowEvent::PlaybackMaxLatency,
+        ))
+        .push(Text::new("ms buffered before skipping to the next keyframe").size(14));
+
+    let connect_timeout = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Connect timeout")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(segment_field(
+            config.playback.connect_timeout_secs,
+            MainWindowEvent::PlaybackConnectTimeout,
+        ))
+        .push(Text::new("seconds to reach the caster, empty to wait forever").size(14));
+
+    let encode_scale = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Encode size")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(
+                EncodeScale::ALL,
+                Some(config.encode_scale),
+                MainWindowEvent::CasterEncodeScale,
+            )
+            .padding([8, 12])
+            .width(Length::Fill),
+        );
+
+    // Vale per il prossimo stream: il secondo encoder nasce con la cattura
+    let simulcast = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Slow receivers")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(
+                Simulcast::ALL,
+                Some(config.capture.simulcast),
+                MainWindowEvent::CasterSimulcast,
+            )
+            .padding([8, 12])
+            .width(Length::Fill),
+        );
+
+    // Profondità di tutte le code: il receiver cambia subito, il caster dal prossimo stream
+    let buffering = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Buffering")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(
+                LatencyProfile::ALL,
+                Some(config.latency_profile),
+                MainWindowEvent::LatencyProfile,
+            )
+            .padding([8, 12])
+            .width(Length::Fill),
+        );
+
+    // Traffico prevedibile sui link con banda rigida, dal prossimo stream
+    let bitrate_mode = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Bitrate")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(
+                BitrateMode::PRESETS,
+                Some(config.bitrate_mode),
+                MainWindowEvent::CasterBitrateMode,
+            )
+            .padding([8, 12])
+            .width(Length::Fill),
+        );
+
+    let hdr = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("HDR desktop")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(
+                HdrMode::ALL,
+                Some(config.capture.hdr),
+                MainWindowEvent::CaptureHdrMode,
+            )
+            .padding([8, 12])
+            .width(Length::Fill),
+        );
+
+    // Senza receiver la cattura va in pausa, riparte al primo che si collega
+    let idle_timeout = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Idle pause")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(
+                IdleTimeout::PRESETS,
+                Some(config.capture.idle_timeout),
+                MainWindowEvent::CaptureIdleTimeout,
+            )
+            .padding([8, 12])
+            .width(Length::Fill),
+        );
+
+    // Slice dell'encoder, dal prossimo stream
+    let rtp_mtu = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Slice size")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(
+                RtpMtu::PRESETS,
+                Some(config.capture.rtp_mtu),
+                MainWindowEvent::CaptureRtpMtu,
+            )
+            .padding([8, 12])
+            .width(Length::Fill),
+        );
+
+    let toggle = |label: &str, on: bool, message: MainWindowEvent| {
+        IconButton::new()
+            .label(&format!("{}: {}", label, if on { "On" } else { "Off" }))
+            .icon(if on { Icon::Ok } else { Icon::Banned })
+            .build()
+            .on_press(message)
+    };
+
+    // Il receiver presenta i frame a cadenza fissa tenendo l'ultimo arrivato
+    let smooth_motion = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Motion")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(toggle(
+            "Smooth (even frame pacing)",
+            config.playback.smooth_motion,
+            MainWindowEvent::PlaybackSmoothMotionToggle,
+        ));
+
+    // Il cambio di monitor avviene solo dopo il ritardo scelto sul nuovo
+    let follow_cursor = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Follow cursor")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(toggle(
+            "Switch to the monitor under the cursor",
+            config.capture.follow_cursor,
+            MainWindowEvent::CaptureFollowCursorToggle,
+        ))
+        .push(
+            PickList::new(
+                FollowCursorDelay::PRESETS,
+                Some(config.capture.follow_cursor_delay),
+                MainWindowEvent::CaptureFollowCursorDelay,
+            )
+            .padding([8, 12])
+            .width(Length::Fill),
+        );
+
+    // Stile dell'anello acceso con la scorciatoia, anche a stream avviato
+    let cursor_ring = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Cursor ring")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(
+                RingSize::ALL,
+                RingSize::of(&config.capture.cursor_highlight),
+                MainWindowEvent::CaptureCursorRingSize,
+            )
+            .padding([8, 12])
+            .width(Length::Fill),
+        )
+        .push(
+            PickList::new(
+                RingColor::ALL,
+                RingColor::of(&config.capture.cursor_highlight),
+                MainWindowEvent::CaptureCursorRingColor,
+            )
+            .padding([8, 12])
+            .width(Length::Fill),
+        );
+
+    let content_aware = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Static content")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(toggle(
+            "Slow down when nothing moves",
+            config.content_aware,
+            MainWindowEvent::CasterContentAwareToggle,
+        ));
+
+    let exclude_input = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Hidden windows")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            TextInput::new("Part of the title or window class", exclude_draft)
+                .on_input(MainWindowEvent::ExcludeWindowInput)
+                .on_submit(MainWindowEvent::ExcludeWindowAdd)
+                .padding([8, 12]),
+        )
+        .push(
+            IconButton::new()
+                .label("Add")
+                .icon(Icon::Ok)
+                .build()
+                .on_press(MainWindowEvent::ExcludeWindowAdd),
+        );
+
+    // Le finestre trovate vengono coperte di nero prima dell'encoding
+    let exclude_list = config.capture.exclude_windows.iter().enumerate().fold(
+        Column::new().spacing(6),
+        |list, (index, pattern)| {
+            list.push(
+                Row::new()
+                    .spacing(12)
+                    .align_y(Alignment::Center)
+                    .push(horizontal_space().width(120))
+                    .push(Text::new(pattern.clone()).size(14))
+                    .push(horizontal_space().width(Length::Fill))
+                    .push(
+                        IconButton::new()
+                            .label("Remove")
+                            .icon(Icon::Trash)
+                            .build()
+                            .on_press(MainWindowEvent::ExcludeWindowRemove(index)),
+                    ),
+            )
+        },
+    );
+
+    // Vale per il prossimo caster: il formato viene negoziato nell'offerta
+    let low_latency_audio = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Audio")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(toggle(
+            "Low-latency PCM (LAN only)",
+            config.audio_encode.low_latency,
+            MainWindowEvent::CasterLowLatencyAudioToggle,
+        ));
+
+    // Encoder Opus del prossimo caster, ignorato con il PCM a bassa latenza
+    let audio = &config.audio_encode;
+    let opus = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(horizontal_space().width(120))
+        .push(
+            PickList::new(
+                OpusBitrate::PRESETS,
+                Some(OpusBitrate(audio.bitrate)),
+                MainWindowEvent::AudioBitrate,
+            )
+            .padding([8, 12]),
+        )
+        .push(
+            PickList::new(
+                OpusFrameDuration::PRESETS,
+                Some(OpusFrameDuration(audio.frame_duration_ms)),
+                MainWindowEvent::AudioFrameDuration,
+            )
+            .padding([8, 12]),
+        )
+        .push(toggle(
+            "Stereo",
+            audio.stereo,
+            MainWindowEvent::AudioStereoToggle,
+        ))
+        .push(toggle("VBR", audio.vbr, MainWindowEvent::AudioVbrToggle));
+
+    let clipboard = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Clipboard")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(toggle(
+            "Share mine",
+            config.clipboard_share,
+            MainWindowEvent::ClipboardShareToggle,
+        ))
+        .push(toggle(
+            "Accept theirs",
+            config.clipboard_accept,
+            MainWindowEvent::ClipboardAcceptToggle,
+        ));
+
+    let webhook_input = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Webhooks")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            TextInput::new("https://example.com/castify", webhook_draft)
+                .on_input(MainWindowEvent::WebhookInput)
+                .on_submit(MainWindowEvent::WebhookAdd)
+                .padding([8, 12]),
+        )
+        .push(
+            IconButton::new()
+                .label("Add")
+                .icon(Icon::Ok)
+                .build()
+                .on_press(MainWindowEvent::WebhookAdd),
+        );
+
+    let webhook_list = config.webhooks.urls.iter().enumerate().fold(
+        Column::new().spacing(6),
+        |list, (index, url)| {
+            list.push(
+                Row::new()
+                    .spacing(12)
+                    .align_y(Alignment::Center)
+                    .push(horizontal_space().width(120))
+                    .push(Text::new(url.clone()).size(14))
+                    .push(horizontal_space().width(Length::Fill))
+                    .push(
+                        IconButton::new()
+                            .label("Remove")
+                            .icon(Icon::Trash)
+                            .build()
+                            .on_press(MainWindowEvent::WebhookRemove(index)),
+                    ),
+            )
+        },
+    );
+
+    let window = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Window")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(toggle(
+            "Close to tray",
+            config.general.close_to_tray,
+            MainWindowEvent::CloseToTrayToggle,
+        ));
+
+    // Un tema fisso resta anche al riavvio, come dal pulsante della home
+    let theme = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Theme")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(ThemeChoice::ALL, Some(theme), |choice| match choice {
+                ThemeChoice::FollowSystem => MainWindowEvent::ThemeFollowSystem,
+                ThemeChoice::Pinned(theme) => MainWindowEvent::ThemeUpdate(theme),
+            })
+            .padding([8, 12]),
+        );
+
+    let permissions = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Permissions")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            IconButton::new()
+                .label("Check Now")
+                .icon(Icon::Sync)
+                .build()
+                .on_press(MainWindowEvent::CheckPermissions(false)),
+        );
+
+    let logging = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Log level")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            PickList::new(
+                LogLevel::ALL,
+                Some(config.logging.level),
+                MainWindowEvent::LogLevel,
+            )
+            .padding([8, 12]),
+        )
+        .push(toggle(
+            "Write to file",
+            config.logging.to_file,
+            MainWindowEvent::LogToFileToggle,
+        ));
+
+    let stats_log = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Stats log")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            IconButton::new()
+                .label(if config.output.stats_log { "On" } else { "Off" })
+                .icon(if config.output.stats_log {
+                    Icon::Ok
+                } else {
+                    Icon::Banned
+                })
+                .build()
+                .on_press(MainWindowEvent::StatsLogToggle),
+        )
+        .push(
+            PickList::new(
+                StatsFormat::ALL,
+                Some(config.output.stats_format),
+                MainWindowEvent::StatsLogFormat,
+            )
+            .padding([8, 12]),
+        )
+        .push(Text::new(shorten_path(config.output.stats_directory().to_string())).size(14))
+        .push(horizontal_space().width(Length::Fill))
+        .push(
+            IconButton::new()
+                .label("Browse")
+                .icon(Icon::Folder)
+                .build()
+                .on_press(MainWindowEvent::StatsLogPickDirectory),
+        );
+
+    let watermark = &config.watermark;
+    let watermark_file = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Watermark")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            Text::new(if watermark.path.is_empty() {
+                String::from("None")
+            } else {
+                shorten_path(watermark.path.clone())
+            })
+            .size(14),
+        )
+        .push(horizontal_space().width(Length::Fill))
+        .push(
+            IconButton::new()
+                .label("Browse")
+                .icon(Icon::Folder)
+                .build()
+                .on_press(MainWindowEvent::WatermarkPickFile),
+        )
+        .push({
+            let mut button = IconButton::new().label("Remove").icon(Icon::Stop).build();
+            if !watermark.path.is_empty() {
+                button = button.on_press(MainWindowEvent::WatermarkClear);
+            }
+            button
+        });
+
+    let percent_field = |value: u32, on_input: fn(String) -> MainWindowEvent| {
+        TextInput::new("100", &value.to_string())
+            .on_input(on_input)
+            .padding([8, 12])
+            .width(70)
+    };
+
+    let watermark_style = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(horizontal_space().width(120))
+        .push(
+            PickList::new(
+                WatermarkCorner::ALL,
+                Some(watermark.corner),
+                MainWindowEvent::WatermarkCorner,
+            )
+            .padding([8, 12]),
+        )
+        .push(Text::new("opacity").size(14))
+        .push(percent_field(
+            watermark.opacity,
+            MainWindowEvent::WatermarkOpacity,
+        ))
+        .push(Text::new("% scale").size(14))
+        .push(percent_field(
+            watermark.scale,
+            MainWindowEvent::WatermarkScale,
+        ))
+        .push(Text::new("%").size(14));
+
+    let timestamp = &config.timestamp;
+    let timestamp_toggle = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(
+            Text::new("Timestamp")
+                .font(FONT_FAMILY_BOLD)
+                .size(14)
+                .width(120),
+        )
+        .push(
+            IconButton::new()
+                .label(if timestamp.enabled { "On" } else { "Off" })
+                .icon(if timestamp.enabled {
+                    Icon::Ok
+                } else {
+                    Icon::Banned
+                })
+                .build()
+                .on_press(MainWindowEvent::TimestampToggle),
+        )
+        .push(
+            PickList::new(
+                TimestampFormat::ALL,
+                Some(timestamp.format),
+                MainWindowEvent::TimestampFormat,
+            )
+            .padding([8, 12]),
+        )
+        .push(
+            TextInput::new("Label (optional)", &timestamp.label)
+                .on_input(MainWindowEvent::TimestampLabel)
+                .padding([8, 12])
+                .width(Length::Fill),
+        );
+
+    let timestamp_style = Row::new()
+        .spacing(12)
+        .align_y(Alignment::Center)
+        .push(horizontal_space().width(120))
+        .push(
+            PickList::new(
+                WatermarkCorner::ALL,
+                Some(timestamp.corner),
+                MainWindowEvent::TimestampCorner,
+            )
+            .padding([8, 12]),
+        )
+        .push(Text::new("opacity").size(14))
+        .push(percent_field(
+            timestamp.opacity,
+            MainWindowEvent::TimestampOpacity,
+        ))
+        .push(Text::new("%").size(14));
+
+    let preview = config.output.file_name("recording", "1920x1080");
+    let preview = if config.output.segment_policy().is_enabled() {
+        format!("{}_001.mp4", preview)
+    } else {
+        format!("{}.mp4", preview)
+    };
+
+    let mut content = Column::new()
+        .spacing(15)
+        .push(directory)
+        .push(template)
+        .push(segments)
+        .push(countdown)
+        .push(resume)
+        .push(Text::new(format!("Tokens: {}", FILENAME_TOKENS.join(" "))).size(12))
+        .push(Text::new(format!("Example: {}", preview)).size(12))
+        .push(playback)
+        .push(display_policy)
+        .push(smooth_motion)
+        .push(color_space)
+        .push(av_offset)
+        .push(max_latency)
+        .push(connect_timeout)
+        .push(encode_scale)
+        .push(simulcast)
+        .push(buffering)
+        .push(Text::new("Low latency reacts faster, Smooth rides out a choppy network").size(12))
+        .push(bitrate_mode)
+        .push(hdr)
+        .push(
+            Text::new("Tone-maps HDR monitors to SDR from the next stream (Windows only)").size(12),
+        )
+        .push(idle_timeout)
+        .push(follow_cursor)
+        .push(Text::new("Keeps the stream on the monitor you are using (Windows only)").size(12))
+        .push(cursor_ring)
+        .push(Text::new("Drawn around the pointer while the highlight shortcut is on (Windows only)").size(12))
+        .push(rtp_mtu)
+        .push(
+            Text::new(
+                "Smaller encoder slices keep video packets small over a VPN (x264 and Quick Sync only)",
+            )
+            .size(12),
+        )
+        .push(permissions)
+        .push(content_aware)
+        .push(exclude_input)
+        .push(exclude_list)
+        .push(
+            Text::new("Matching windows are blacked out in the stream (Windows only)").size(12),
+        )
+        .push(low_latency_audio)
+        .push(opus)
+        .push(clipboard)
+        .push(webhook_input)
+        .push(webhook_list)
+        .push(
+            Text::new("POSTs stream, client and recording events as JSON").size(12),
+        )
+        .push(window)
+        .push(theme)
+        .push(logging)
+        .push(stats_log)
+        .push(watermark_file)
+        .push(watermark_style);
+
+    if let Some(warning) = watermark_warning {
+        content = content.push(
+            Text::new(warning.to_string())
+                .size(12)
+                .class(TextType::Danger),
+        );
+    }
+
+    content = content
+        .push(timestamp_toggle)
+        .push(timestamp_style)
+        .push(Text::new("Labels use capital letters, digits and + - . , / : %").size(12));
+
+    if let Some(warning) = warning {
+        content = content.push(
+            Text::new(warning.to_string())
+                .size(12)
+                .class(TextType::Danger),
+        );
+    }
+
+    let settings = Container::new(content.padding(15))
+        .width(Length::Fill)
+        .class(ContainerType::Standard);
+
+    let actions = Container::new(
+        crate::row![
+            horizontal_space().width(Length::Fill),
+            IconButton::new()
+                .label("Home")
+                .icon(Icon::Home)
+                .build()
+                .on_press(MainWindowEvent::Home),
+            IconButton::new()
+                .label("Reset Defaults")
+                .icon(Icon::Sync)
+                .build()
+                .on_press(MainWindowEvent::OutputReset),
+            horizontal_space().width(Length::Fill),
+        ]
+        .spacing(15)
+        .align_y(Alignment::Center),
+    )
+    .center(Length::Fill)
+    .height(80)
+    .class(ContainerType::Standard);
+
+    let content = crate::column![header, settings, vertical_space(), actions]
+        .spacing(10)
+        .padding(15);
+
+    Container::new(content).center(Length::Fill).into()
+}
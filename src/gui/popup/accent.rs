@@ -0,0 +1,115 @@
+use crate::config::Config;
+use crate::gui::common::icons::Icon;
+use crate::gui::components::awmodal::GuiInterface;
+use crate::gui::components::button::IconButton;
+use crate::gui::style::button::ButtonType;
+use crate::gui::style::text::TextType;
+use crate::gui::style::theme::accent::{ACCENT_PRESETS, parse_color, to_hex};
+use crate::gui::widget::{Column, Element, IcedButtonExt, Row, Text, TextInput};
+use crate::gui::windows::main::MainWindowEvent;
+use castbox::AnyRef;
+use iced::Color;
+use iced::widget::Id;
+
+const COLOR_INPUT: &str = "accent-modal-color";
+
+pub struct AccentModal {
+    input: String,
+    error: Option<String>,
+}
+
+impl AccentModal {
+    pub fn new(current: Option<Color>) -> Self {
+        AccentModal {
+            input: current.map(to_hex).unwrap_or_default(),
+            error: None,
+        }
+    }
+}
+
+impl GuiInterface for AccentModal {
+    type Message = MainWindowEvent;
+
+    fn title(&self) -> String {
+        String::from("Accent Color")
+    }
+
+    fn update(&mut self, value: AnyRef, _config: &Config) {
+        if let Some(input) = value.try_downcast_ref::<String>() {
+            self.input = input.clone();
+            self.error = (!self.input.trim().is_empty() && parse_color(&self.input).is_none())
+                .then(|| String::from("Use #rrggbb or r, g, b (0-255)"));
+        }
+    }
+
+    fn on_submit(&self) -> Option<Self::Message> {
+        parse_color(&self.input).map(|color| MainWindowEvent::AccentColor(Some(color)))
+    }
+
+    fn focus(&self) -> Option<Id> {
+        Some(Id::new(COLOR_INPUT))
+    }
+
+    fn view<'a, 'b>(&'a self, _config: &Config) -> Element<'b, Self::Message>
+    where
+        'b: 'a,
+        Self::Message: Clone + 'b,
+    {
+        let current = parse_color(&self.input);
+
+        let presets = ACCENT_PRESETS
+            .iter()
+            .fold(Row::new().spacing(8), |row, (_, color)| {
+                let color = *color;
+                row.push(
+                    IconButton::new()
+                        .icon(Icon::Circle)
+                        .color(color)
+                        .build()
+                        .on_press(MainWindowEvent::AccentColor(Some(color)))
+                        .height(36)
+                        .width(36)
+                        .padding(0)
+                        .class(if current == Some(color) {
+                            ButtonType::Disabled
+                        } else {
+                            ButtonType::Standard
+                        }),
+                )
+            });
+
+        let input = TextInput::new("#e1822d or 225, 130, 45", &self.input)
+            .on_input(|value| MainWindowEvent::PopupMessage(AnyRef::new(value)))
+            .id(COLOR_INPUT)
+            .padding([8, 12]);
+
+        let mut content = Column::new().spacing(12).push(presets).push(input);
+
+        if let Some(error) = &self.error {
+            content = content.push(Text::new(error.clone()).size(12).class(TextType::Danger));
+        }
+
+        content
+            .push(
+                Row::new()
+                    .spacing(12)
+                    .push(
+                        IconButton::new()
+                            .label("Apply")
+                            .icon(Icon::Save)
+                            .build()
+                            .on_press_if(current.is_some(), move || {
+                                MainWindowEvent::AccentColor(current)
+                            }),
+                    )
+                    .push(
+                        IconButton::new()
+                            .label("Theme Default")
+                            .icon(Icon::Sync)
+                            .build()
+                            .on_press(MainWindowEvent::AccentColor(None)),
+                    ),
+            )
+            .into()
+    }
+}
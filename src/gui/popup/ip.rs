@@ -2,21 +2,118 @@ use crate::config::Config;
 use crate::gui::common::icons::Icon;
 use crate::gui::components::awmodal::GuiInterface;
 use crate::gui::components::button::{Dimensions, IconButton};
-use crate::gui::widget::{Column, Element, IcedButtonExt, Row, TextInput};
+use crate::gui::style::text::TextType;
+use crate::gui::widget::{Column, Element, IcedButtonExt, PickList, Row, Text, TextInput};
 use crate::gui::windows::main::MainWindowEvent;
+use crate::utils::net::common::is_connection_link;
 use castbox::AnyRef;
+use iced::widget::Id;
+use std::net::SocketAddr;
+
+/// Campo dell'indirizzo, con il focus all'apertura del modal
+const ADDRESS_INPUT: &str = "ip-modal-address";
+
+/// Errore di parsing/risoluzione dell'indirizzo, mostrato sotto il campo.
+pub struct InvalidAddress(pub String);
+
+/// Una scansione mDNS è partita: il risultato arriva come `Vec<(String, SocketAddr)>`.
+pub struct DiscoveryStarted;
 
 pub struct IPModal {
     ip: String,
+    error: Option<String>,
+    /// Caster trovati via mDNS, `None` se la scansione è in corso
+    discovered: Option<Vec<(String, SocketAddr)>>,
 }
 
 impl IPModal {
     pub fn new() -> Self {
-        IPModal { ip: String::new() }
+        IPModal {
+            ip: String::new(),
+            error: None,
+            discovered: Some(Vec::new()),
+        }
     }
 
+    fn discovery_view<'b>(&self) -> Element<'b, MainWindowEvent> {
+        let scan = IconButton::new()
+            .label("Scan")
+            .icon(Icon::Sync)
+            .build()
+            .on_press_if(self.discovered.is_some(), || {
+                MainWindowEvent::DiscoverCasters
+            });
+
+        let list: Element<'b, MainWindowEvent> = match &self.discovered {
+            None => Text::new("Searching for casters...").size(12).into(),
+            Some(casters) if casters.is_empty() => {
+                Text::new("No casters found on the local network")
+                    .size(12)
+                    .into()
+            }
+            Some(casters) => {
+                let casters = casters.clone();
+                let options: Vec<String> = casters
+                    .iter()
+                    .map(|(name, addr)| format!("{} ({})", name, addr))
+                    .collect();
+                PickList::new(options.clone(), None::<String>, move |val| {
+                    let idx = options.iter().position(|v| v == &val).unwrap_or(0);
+                    MainWindowEvent::ConnectToCaster(casters[idx].1.to_string())
+                })
+                .placeholder("Discovered casters")
+                .padding([8, 12])
+                .into()
+            }
+        };
+
+        Row::new()
+            .spacing(12)
+            .align_y(iced::Alignment::Center)
+            .push(list)
+            .push(scan)
+            .into()
+    }
+
+    /// Connessioni recenti: riconnessione rapida all'ultimo caster e lista degli altri
+    fn recent_view<'b>(&self, config: &Config) -> Option<Element<'b, MainWindowEvent>> {
+        let last = config.recent_casters.last()?.to_string();
+
+        let reconnect = IconButton::new()
+            .label(&format!("Reconnect to {}", last))
+            .icon(Icon::Connection)
+            .build()
+            .on_press(MainWindowEvent::ConnectToCaster(last));
+
+        let recent = PickList::new(
+            config.recent_casters.addresses().to_vec(),
+            None::<String>,
+            MainWindowEvent::ConnectToCaster,
+        )
+        .placeholder("Recent casters")
+        .padding([8, 12]);
+
+        let clear = IconButton::new()
+            .icon(Icon::Trash)
+            .build()
+            .on_press(MainWindowEvent::ClearRecentCasters);
+
+        Some(
+            Row::new()
+                .spacing(12)
+                .align_y(iced::Alignment::Center)
+                .push(reconnect)
+                .push(recent)
+                .push(clear)
+                .into(),
+        )
+    }
+
+    /// Caratteri ammessi in IPv4, IPv6 (anche tra parentesi) e hostname.
     fn parse_ip(ip: String) -> String {
-        ip.chars().filter(|c| ".0123456789:".contains(*c)).collect()
+        ip.chars()
+            .filter(|c| c.is_ascii_alphanumeric() || ".:-[]".contains(*c))
+            .collect()
     }
 }
 
@@ -28,18 +125,44 @@ impl GuiInterface for IPModal {
     }
 
     fn update(&mut self, value: AnyRef, _config: &Config) {
-        self.ip = value.try_downcast_ref::<String>().unwrap().clone();
+        if let Some(InvalidAddress(error)) = value.try_downcast_ref::<InvalidAddress>() {
+            self.error = Some(error.clone());
+        } else if value.try_downcast_ref::<DiscoveryStarted>().is_some() {
+            self.discovered = None;
+        } else if let Some(casters) = value.try_downcast_ref::<Vec<(String, SocketAddr)>>() {
+            self.discovered = Some(casters.clone());
+        } else if let Some(ip) = value.try_downcast_ref::<String>() {
+            self.ip = ip.clone();
+            self.error = None;
+        }
+    }
+
+    fn on_submit(&self) -> Option<Self::Message> {
+        (!self.ip.is_empty()).then(|| MainWindowEvent::ConnectToCaster(self.ip.clone()))
+    }
+
+    fn on_cancel(&self) -> Option<Self::Message> {
+        Some(MainWindowEvent::Home)
+    }
+
+    fn focus(&self) -> Option<Id> {
+        Some(Id::new(ADDRESS_INPUT))
     }
 
-    fn view<'a, 'b>(&'a self, _config: &Config) -> Element<'b, Self::Message>
+    fn view<'a, 'b>(&'a self, config: &Config) -> Element<'b, Self::Message>
     where
         'b: 'a,
         Self::Message: Clone + 'b,
     {
-        let input = TextInput::new("192.168.1.2", &self.ip)
+        let input = TextInput::new("192.168.1.2, [::1] or hostname", &self.ip)
             .on_input(move |new_value| {
-                MainWindowEvent::PopupMessage(AnyRef::new(IPModal::parse_ip(new_value)))
+                if is_connection_link(&new_value) {
+                    MainWindowEvent::OpenConnectionLink(new_value)
+                } else {
+                    MainWindowEvent::PopupMessage(AnyRef::new(IPModal::parse_ip(new_value)))
+                }
             })
+            .id(ADDRESS_INPUT)
             .padding([8, 12]);
 
         let ip = self.ip.clone();
@@ -53,9 +176,25 @@ impl GuiInterface for IPModal {
                 MainWindowEvent::ConnectToCaster(ip.clone())
             });
 
-        Column::new()
-            .spacing(12)
-            .push(input)
+        let mut content = Column::new().spacing(12).push(self.discovery_view());
+        if let Some(recent) = self.recent_view(config) {
+            content = content.push(recent);
+        }
+        content = content.push(input).push(
+            TextInput::new(
+                "Passphrase for manual SDP and remote control (optional)",
+                &config.manual_passphrase,
+            )
+            .secure(true)
+            .on_input(MainWindowEvent::ManualPassphrase)
+            .padding([8, 12]),
+        );
+
+        if let Some(error) = &self.error {
+            content = content.push(Text::new(error.clone()).size(12).class(TextType::Danger));
+        }
+
+        content
             .push(
                 Row::new()
                     .spacing(12)
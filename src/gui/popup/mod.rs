@@ -1,3 +1,5 @@
+pub mod accent;
 pub mod ip;
+pub mod permissions;
 pub mod shortcuts;
 pub mod wrtc;
@@ -0,0 +1,133 @@
+use crate::assets::FONT_FAMILY_BOLD;
+use crate::capture::permissions::{Access, PermissionCheck};
+use crate::config::Config;
+use crate::gui::common::icons::Icon;
+use crate::gui::components::awmodal::GuiInterface;
+use crate::gui::components::button::IconButton;
+use crate::gui::style::text::TextType;
+use crate::gui::widget::{Column, Element, Row, Text};
+use crate::gui::windows::main::MainWindowEvent;
+use castbox::AnyRef;
+use iced::Alignment;
+
+/// Checklist dei permessi di cattura, con le istruzioni per concederli
+pub struct PermissionsModal {
+    /// `None` finché il controllo è in corso
+    checks: Option<Vec<PermissionCheck>>,
+    /// Aperto prima di avviare il caster: "Continue" entra in modalità caster
+    before_casting: bool,
+}
+
+impl PermissionsModal {
+    pub fn new(before_casting: bool) -> Self {
+        PermissionsModal {
+            checks: None,
+            before_casting,
+        }
+    }
+
+    fn continue_message(&self) -> MainWindowEvent {
+        if self.before_casting {
+            MainWindowEvent::StartCaster
+        } else {
+            MainWindowEvent::ClosePopup(None)
+        }
+    }
+}
+
+impl GuiInterface for PermissionsModal {
+    type Message = MainWindowEvent;
+
+    fn title(&self) -> String {
+        String::from("Capture Permissions")
+    }
+
+    fn update(&mut self, value: AnyRef, _config: &Config) {
+        if let Some(checks) = value.try_downcast_ref::<Vec<PermissionCheck>>() {
+            self.checks = Some(checks.clone());
+        }
+    }
+
+    fn on_submit(&self) -> Option<Self::Message> {
+        self.checks.is_some().then(|| self.continue_message())
+    }
+
+    fn view<'a, 'b>(&'a self, _config: &Config) -> Element<'b, Self::Message>
+    where
+        'b: 'a,
+        Self::Message: Clone + 'b,
+    {
+        let Some(checks) = &self.checks else {
+            return Text::new("Checking permissions…").size(14).into();
+        };
+
+        let mut content = Column::new().spacing(12);
+        for check in checks {
+            let (status, class) = match &check.access {
+                Access::Granted => (String::from("Allowed"), TextType::Standard),
+                Access::OnFirstUse => (
+                    String::from("Asked by the system when casting starts"),
+                    TextType::Standard,
+                ),
+                Access::Remembered => (
+                    String::from("Previous choice remembered, confirmed when casting starts"),
+                    TextType::Standard,
+                ),
+                Access::Missing(reason) => (reason.clone(), TextType::Danger),
+            };
+            let mut row = Row::new()
+                .spacing(12)
+                .align_y(Alignment::Center)
+                .push(
+                    Text::new(check.permission.to_string())
+                        .font(FONT_FAMILY_BOLD)
+                        .size(14)
+                        .width(120),
+                )
+                .push(Text::new(status).size(14).class(class));
+            if check.is_missing()
+                && let Some(url) = check.permission.settings_url()
+            {
+                row = row.push(
+                    IconButton::new()
+                        .label("Open Settings")
+                        .icon(Icon::Settings)
+                        .build()
+                        .on_press(MainWindowEvent::OpenWebPage(String::from(url))),
+                );
+            }
+            content = content.push(row);
+            if check.access != Access::Granted {
+                content = content.push(Text::new(check.permission.how_to()).size(12));
+            }
+        }
+
+        let missing = checks.iter().any(PermissionCheck::is_missing);
+        let continue_label = match (self.before_casting, missing) {
+            (true, true) => "Continue Anyway",
+            (true, false) => "Start Casting",
+            (false, _) => "Close",
+        };
+
+        content
+            .push(
+                Row::new()
+                    .spacing(12)
+                    .push(
+                        IconButton::new()
+                            .label("Check Again")
+                            .icon(Icon::Sync)
+                            .build()
+                            .on_press(MainWindowEvent::CheckPermissions(self.before_casting)),
+                    )
+                    .push(
+                        IconButton::new()
+                            .label(continue_label)
+                            .icon(Icon::Ok)
+                            .build()
+                            .on_press(self.continue_message()),
+                    ),
+            )
+            .into()
+    }
+}
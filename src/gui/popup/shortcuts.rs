@@ -2,18 +2,25 @@ use crate::config::Config;
 use crate::gui::common::hotkeys::KeyTypes;
 use crate::gui::components::awmodal::GuiInterface;
 use crate::gui::components::button::{IconButton, Key4Board};
+use crate::gui::style::text::TextType;
 use crate::gui::widget::{Column, Element, Row, Text};
 use crate::gui::windows::main::MainWindowEvent;
+use castbox::AnyRef;
 use iced::keyboard::{Key, Modifiers};
 
+/// Esito dell'ultima combinazione premuta: l'azione che la usa già, se c'è
+pub struct HotkeyConflict(pub Option<KeyTypes>);
+
 pub struct ShortcutModal {
     key: KeyTypes,
+    conflict: Option<KeyTypes>,
 }
 
 impl ShortcutModal {
     pub fn new() -> Self {
         ShortcutModal {
             key: KeyTypes::None,
+            conflict: None,
         }
     }
 
@@ -30,21 +37,25 @@ impl GuiInterface for ShortcutModal {
         format!("Updating hotkey for: {:?}", self.key)
     }
 
+    fn update(&mut self, value: AnyRef, _config: &Config) {
+        if let Some(HotkeyConflict(conflict)) = value.try_downcast_ref::<HotkeyConflict>() {
+            self.conflict = *conflict;
+        }
+    }
+
+    fn on_submit(&self) -> Option<Self::Message> {
+        Some(MainWindowEvent::ClosePopup(None))
+    }
+
     fn view<'a, 'b>(&'a self, config: &Config) -> Element<'b, Self::Message>
     where
         'b: 'a,
         Self::Message: Clone + 'b,
     {
         let default = (Modifiers::empty(), Key::Unidentified);
-        let c_key = match self.key {
-            KeyTypes::Pause => &config.shortcuts.pause,
-            KeyTypes::Record => &config.shortcuts.record,
-            KeyTypes::Close => &config.shortcuts.end_session,
-            KeyTypes::BlankScreen => &config.shortcuts.blank_screen,
-            _ => &default,
-        };
+        let c_key = config.shortcuts.get(self.key).unwrap_or(&default);
 
-        Column::new()
+        let mut content = Column::new()
             .spacing(12)
             .push(
                 Row::new()
@@ -52,7 +63,27 @@ impl GuiInterface for ShortcutModal {
                     .push(Key4Board::from_key(&c_key.1).build())
                     .spacing(5),
             )
-            .push(Text::new("Press any desired key.").height(20).size(12))
+            .push(Text::new("Press any desired key.").height(20).size(12));
+
+        if self.key == KeyTypes::Zoom {
+            content = content.push(
+                Text::new("The arrow keys with the same modifiers move the magnified region.")
+                    .size(12),
+            );
+        }
+
+        if let Some(other) = self.conflict {
+            content = content.push(
+                Text::new(format!(
+                    "Already used by {:?}, pick another combination.",
+                    other
+                ))
+                .size(12)
+                .class(TextType::Danger),
+            );
+        }
+
+        content
             .push(
                 IconButton::new()
                     .label("Ok")
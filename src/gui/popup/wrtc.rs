@@ -13,6 +13,10 @@ use crate::utils::status::Status;
 use castbox::AnyRef;
 use iced::Length;
 use iced::alignment;
+use iced::widget::Id;
+
+/// Campo dell'SDP remoto, con il focus quando è il passo corrente
+const REMOTE_SDP_INPUT: &str = "wrtc-modal-remote-sdp";
 
 struct HandleSDP {
     sdp: String,
@@ -53,6 +57,19 @@ impl WrtcModal {
         }
     }
 
+    /// Il passo corrente chiede di incollare l'SDP remoto
+    fn awaiting_remote(&self) -> bool {
+        match self.status.get() {
+            0 => !self.doing_offer,
+            1 => self.doing_offer,
+            _ => false,
+        }
+    }
+
+    fn abort(&self) -> MainWindowEvent {
+        MainWindowEvent::ClosePopup((!self.doing_offer).then_some(Page::Home))
+    }
+
     fn show_sdp<'a>(&self) -> Element<'a, MainWindowEvent> {
         if self.local_sdp.sdp.is_empty() {
             return Column::new()
@@ -66,7 +83,6 @@ impl WrtcModal {
                 .into();
         }
         let local_sdp = self.local_sdp.clone();
-        let receiver = !self.doing_offer;
         Column::new()
             .spacing(10)
             .push(
@@ -94,11 +110,7 @@ impl WrtcModal {
                             .label("Abort")
                             .icon(Icon::Close)
                             .build()
-                            .on_press(MainWindowEvent::ClosePopup(if receiver {
-                                Some(Page::Home)
-                            } else {
-                                None
-                            })),
+                            .on_press(self.abort()),
                     ),
             )
             .into()
@@ -106,7 +118,6 @@ impl WrtcModal {
 
     fn get_remote_sdp<'a>(&self) -> Element<'a, MainWindowEvent> {
         let rsdp_watcher = self.remote_sdp.watcher.clone();
-        let receiver = !self.doing_offer;
         Column::new()
             .spacing(20)
             .push(
@@ -114,6 +125,7 @@ impl WrtcModal {
                     .on_input(move |new_value| {
                         MainWindowEvent::PopupMessage(AnyRef::new(new_value))
                     })
+                    .id(REMOTE_SDP_INPUT)
                     .padding([8, 12]),
             )
             .push(
@@ -134,11 +146,7 @@ impl WrtcModal {
                             .label("Abort")
                             .icon(Icon::Close)
                             .build()
-                            .on_press(MainWindowEvent::ClosePopup(if receiver {
-                                Some(Page::Home)
-                            } else {
-                                None
-                            })),
+                            .on_press(self.abort()),
                     ),
             )
             .into()
@@ -156,6 +164,33 @@ impl GuiInterface for WrtcModal {
         self.remote_sdp.sdp = value.try_downcast_ref::<String>().unwrap().clone();
     }
 
+    /// Invio conferma l'SDP incollato, copia quello locale o ritenta
+    fn on_submit(&self) -> Option<Self::Message> {
+        match self.status.get() {
+            0 | 1 if self.awaiting_remote() => {
+                if self.remote_sdp.sdp.is_empty() {
+                    return None;
+                }
+                self.remote_sdp.watcher.cancel();
+                Some(MainWindowEvent::Ignore)
+            }
+            0 | 1 if !self.local_sdp.sdp.is_empty() => {
+                self.local_sdp.watcher.cancel();
+                Some(MainWindowEvent::CopyToClipboard(self.local_sdp.sdp.clone()))
+            }
+            400 => Some(MainWindowEvent::ShowSDP),
+            _ => None,
+        }
+    }
+
+    fn on_cancel(&self) -> Option<Self::Message> {
+        Some(self.abort())
+    }
+
+    fn focus(&self) -> Option<Id> {
+        self.awaiting_remote().then(|| Id::new(REMOTE_SDP_INPUT))
+    }
+
     fn view<'a, 'b>(&'a self, _config: &Config) -> Element<'b, Self::Message>
     where
         'b: 'a,
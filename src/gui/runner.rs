@@ -15,7 +15,7 @@ use super::app::App;
 /// including fonts, themes, and subscriptions. If the GUI fails to initialize,
 /// it displays an error dialog before exiting.
 pub fn run(flags: Flags) {
-    let app = iced::daemon(move || App::new(flags), App::update, App::view)
+    let app = iced::daemon(move || App::new(flags.clone()), App::update, App::view)
         .settings(iced::Settings {
             id: Some(app_id()),
             ..Default::default()
@@ -31,7 +31,7 @@ pub fn run(flags: Flags) {
         .subscription(App::subscription);
 
     if let Err(e) = app.run() {
-        eprintln!("Failed to initialize GUI: {e:?}");
+        log::error!("Failed to initialize GUI: {e:?}");
 
         if let Err(e) = DialogBuilder::message()
             .set_title("Gui error")
@@ -40,7 +40,7 @@ pub fn run(flags: Flags) {
             .alert()
             .show()
         {
-            eprintln!("Failed to display error dialog: {e:?}");
+            log::error!("Failed to display error dialog: {e:?}");
         }
     }
 }
@@ -13,6 +13,9 @@ pub enum ContainerType {
     Footer,
     DarkFilter,
     Line,
+    LevelTrack,
+    LevelFill,
+    LevelClip,
 }
 
 impl Catalog for StyleType {
@@ -35,6 +38,12 @@ impl Catalog for StyleType {
                     a: 0.8,
                     ..Color::BLACK
                 }),
+                ContainerType::LevelTrack => Background::Color(Color {
+                    a: 0.35,
+                    ..Color::BLACK
+                }),
+                ContainerType::LevelFill => Background::Color(palette.action),
+                ContainerType::LevelClip => Background::Color(palette.danger),
                 _ => Background::Color(Color::TRANSPARENT),
             }),
             border: Border {
@@ -42,6 +51,7 @@ impl Catalog for StyleType {
                     ContainerType::Video => 3.0.into(),
                     ContainerType::Modal => 8.0.into(),
                     ContainerType::Standard => 6.0.into(),
+                    ContainerType::LevelTrack | ContainerType::LevelFill => 2.0.into(),
                     _ => 0.0.into(),
                 },
                 width: match class {
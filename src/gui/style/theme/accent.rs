@@ -0,0 +1,86 @@
+//! User-defined accent color
+//!
+//! The accent replaces the `action` color of whatever theme is active, so it
+//! survives theme switches and OS light/dark changes. It is stored as a hex
+//! string next to the other settings, together with the theme pinned by the
+//! user, if any.
+
+use crate::config::{load_json, save_json};
+use crate::gui::style::theme::csx::StyleType;
+use crate::rgba8;
+use iced::Color;
+use serde::{Deserialize, Serialize};
+
+const APPEARANCE_FILE: &str = "appearance.json";
+
+/// Colori proposti nel selettore, oltre all'input libero
+pub const ACCENT_PRESETS: [(&str, Color); 6] = [
+    ("Orange", rgba8!(225, 130, 45, 1.0)),
+    ("Blue", rgba8!(52, 120, 246, 1.0)),
+    ("Green", rgba8!(46, 160, 90, 1.0)),
+    ("Purple", rgba8!(136, 84, 208, 1.0)),
+    ("Pink", rgba8!(219, 68, 132, 1.0)),
+    ("Teal", rgba8!(26, 158, 160, 1.0)),
+];
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Appearance {
+    accent: Option<String>,
+    /// Tema scelto a mano, `None` segue il sistema
+    theme: Option<StyleType>,
+}
+
+/// `#rrggbb`, `rrggbb` o `r,g,b` (0-255)
+pub fn parse_color(input: &str) -> Option<Color> {
+    let input = input.trim();
+
+    if input.contains(',') {
+        let channels: Vec<u8> = input
+            .split(',')
+            .map(|c| c.trim().parse::<u8>())
+            .collect::<Result<_, _>>()
+            .ok()?;
+        return match channels[..] {
+            [r, g, b] => Some(Color::from_rgb8(r, g, b)),
+            _ => None,
+        };
+    }
+
+    let hex = input.strip_prefix('#').unwrap_or(input);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::from_rgb8(channel(0)?, channel(2)?, channel(4)?))
+}
+
+pub fn to_hex(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Accento salvato, `None` se si usa quello del tema
+pub fn load_accent() -> Option<Color> {
+    load_json::<Appearance>(APPEARANCE_FILE)
+        .accent
+        .as_deref()
+        .and_then(parse_color)
+}
+
+pub fn save_accent(accent: Option<Color>) {
+    let mut appearance = load_json::<Appearance>(APPEARANCE_FILE);
+    appearance.accent = accent.map(to_hex);
+    save_json(APPEARANCE_FILE, &appearance, "accent color");
+}
+
+/// Tema fissato dall'utente, `None` se segue il sistema
+pub fn load_pinned_theme() -> Option<StyleType> {
+    load_json::<Appearance>(APPEARANCE_FILE).theme
+}
+
+pub fn save_pinned_theme(theme: Option<&StyleType>) {
+    let mut appearance = load_json::<Appearance>(APPEARANCE_FILE);
+    appearance.theme = theme.cloned();
+    save_json(APPEARANCE_FILE, &appearance, "theme");
+}
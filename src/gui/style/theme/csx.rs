@@ -29,6 +29,17 @@ impl StyleType {
         }
     }
 
+    /// Tema con l'accento scelto dall'utente al posto del colore `action`
+    pub fn with_accent(&self, accent: Option<Color>) -> Self {
+        match accent {
+            Some(action) => StyleType::Custom(Palette {
+                action,
+                ..self.get_palette()
+            }),
+            None => self.clone(),
+        }
+    }
+
     pub fn display_name(&self) -> &'static str {
         match self {
             StyleType::DarkVenus => "Dark Venus",
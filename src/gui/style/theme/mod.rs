@@ -1,3 +1,5 @@
+pub mod accent;
 pub mod color;
 pub mod csx;
 pub mod palette;
+pub mod system;
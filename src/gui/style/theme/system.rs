@@ -0,0 +1,69 @@
+//! OS light/dark preference
+//!
+//! The main window follows the OS theme until the user picks one explicitly:
+//! the preference is read at startup and polled afterwards, since not every
+//! platform notifies changes.
+
+use crate::gui::style::theme::csx::StyleType;
+use std::fmt;
+use std::time::Duration;
+
+/// Intervallo di controllo del tema di sistema
+pub const SYSTEM_THEME_POLL: Duration = Duration::from_secs(3);
+
+/// Tema che corrisponde alla preferenza del sistema operativo
+pub fn system_theme(dark: bool) -> StyleType {
+    if dark {
+        StyleType::DarkVenus
+    } else {
+        StyleType::LightVenus
+    }
+}
+
+/// Voce del selettore del tema nelle impostazioni
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThemeChoice {
+    FollowSystem,
+    Pinned(StyleType),
+}
+
+impl ThemeChoice {
+    /// Stessi temi fissi del pulsante nella home
+    pub const ALL: [ThemeChoice; 5] = [
+        ThemeChoice::FollowSystem,
+        ThemeChoice::Pinned(StyleType::LightVenus),
+        ThemeChoice::Pinned(StyleType::SmokedLightBlue),
+        ThemeChoice::Pinned(StyleType::DarkVenus),
+        ThemeChoice::Pinned(StyleType::Darcula),
+    ];
+}
+
+impl fmt::Display for ThemeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeChoice::FollowSystem => write!(f, "Follow system"),
+            ThemeChoice::Pinned(theme) => write!(f, "{}", theme.display_name()),
+        }
+    }
+}
+
+/// `Some(true)` se il sistema usa il tema scuro, `None` se non è determinabile
+pub fn detect_dark() -> Option<bool> {
+    match dark_light::detect() {
+        Ok(dark_light::Mode::Dark) => Some(true),
+        Ok(dark_light::Mode::Light) => Some(false),
+        Ok(dark_light::Mode::Unspecified) => None,
+        Err(e) => {
+            log::debug!("System theme detection failed: {}", e);
+            None
+        }
+    }
+}
+
+/// Come [`detect_dark`], fuori dal thread della GUI (su Linux interroga D-Bus)
+pub async fn detect_dark_async() -> Option<bool> {
+    tokio::task::spawn_blocking(detect_dark)
+        .await
+        .ok()
+        .flatten()
+}
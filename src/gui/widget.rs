@@ -17,7 +17,7 @@ pub type Stack<'a, Message> = w::Stack<'a, Message, Theme, IcedRenderer>;
 
 pub type PickList<'a, T, L, V, Message> = w::PickList<'a, T, L, V, Message, Theme, IcedRenderer>;
 pub type Scrollable<'a, Message> = w::Scrollable<'a, Message, Theme, IcedRenderer>;
-//pub type Slider<'a, T, Message> = w::Slider<'a, T, Message, Theme>;
+pub type Slider<'a, T, Message> = w::Slider<'a, T, Message, Theme>;
 pub type Canvas<P, Message> = w::Canvas<P, Message, Theme, IcedRenderer>;
 
 use crate::gui::style::container::ContainerType;
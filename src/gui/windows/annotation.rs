@@ -1,22 +1,28 @@
-use crate::config::Config;
+use crate::config::{Config, Mode, image_saving_path};
 use crate::gui::common::icons::Icon;
 use crate::gui::common::messages::AppEvent;
 use crate::gui::components::button::IconButton;
-use crate::gui::components::{Annotation, Shape, ShapeColor, ShapeStroke, ShapeType};
+use crate::gui::components::{
+    Annotation, AnnotationHistory, Shape, ShapeColor, ShapeStroke, ShapeType, StrokeEvent,
+    export_png, remote_stroke,
+};
 use crate::gui::style::button::ButtonType;
 use crate::gui::style::theme::csx::StyleType;
 use crate::gui::widget::{
     Canvas, Column, Container, Element, Row, Stack, horizontal_space, vertical_space,
 };
 use crate::gui::windows::GuiWindow;
+use crate::utils::net::webrtc::AnnotationEvent;
 use iced::Length::Fill;
-use iced::Task;
 use iced::alignment;
 use iced::window::Id;
+use iced::{Size, Task};
 
 pub struct AnnotationWindow {
     shape: Shape,
     show_toolbar: bool,
+    history: AnnotationHistory,
+    canvas_size: Size,
 }
 
 #[derive(Debug, Clone)]
@@ -24,16 +30,72 @@ pub enum AnnotationWindowEvent {
     ChooseShapeType(ShapeType, bool, bool),
     ChangeColor(ShapeColor),
     ChangeStroke(ShapeStroke),
+    Stroke(StrokeEvent),
+    Undo,
+    Redo,
+    ClearAll,
+    ExportPng,
     Exit,
     Ignore,
     ToggleToolbar,
 }
 
+/// Tipo di modifica da propagare ai receiver.
+enum RemoteChange {
+    /// Aggiunto un tratto in coda
+    Added,
+    /// Insieme dei tratti cambiato (undo/redo, gomma, clear)
+    Replaced,
+}
+
 impl AnnotationWindow {
     pub fn new() -> Self {
         AnnotationWindow {
             shape: Default::default(),
             show_toolbar: false,
+            history: AnnotationHistory::default(),
+            canvas_size: Size::ZERO,
+        }
+    }
+
+    /// Invia ai receiver il cambiamento appena applicato alla history.
+    fn sync_remote(&self, config: &Config, event: RemoteChange) {
+        let Some(Mode::Caster(caster)) = &config.mode else {
+            return;
+        };
+        let area = caster.annotation_area(self.canvas_size);
+        if area.width <= 0.0 || area.height <= 0.0 {
+            return;
+        }
+
+        let to_remote =
+            |(shape, points): &(Shape, Vec<iced::Point>)| remote_stroke(shape, points, area);
+        caster.send_annotation(match event {
+            RemoteChange::Added => match self.history.shapes.last().and_then(to_remote) {
+                Some(last) => AnnotationEvent::Add(last),
+                None => return,
+            },
+            RemoteChange::Replaced if self.history.shapes.is_empty() => AnnotationEvent::Clear,
+            RemoteChange::Replaced => {
+                AnnotationEvent::Sync(self.history.shapes.iter().filter_map(to_remote).collect())
+            }
+        });
+    }
+
+    /// Finestra nascosta con il toggle: i receiver tolgono l'overlay, i
+    /// tratti restano nella history per la prossima apertura
+    pub fn on_hide(&self, config: &Config) {
+        if let Some(Mode::Caster(caster)) = &config.mode
+            && !self.history.shapes.is_empty()
+        {
+            caster.send_annotation(AnnotationEvent::Clear);
+        }
+    }
+
+    /// Finestra riaperta dopo un [`Self::on_hide`]: i receiver ritrovano i tratti
+    pub fn on_show(&self, config: &Config) {
+        if !self.history.shapes.is_empty() {
+            self.sync_remote(config, RemoteChange::Replaced);
         }
     }
 
@@ -108,6 +170,21 @@ impl AnnotationWindow {
                 })
         };
 
+        let history_icon = |icon, message, enabled: bool| {
+            IconButton::new()
+                .icon(icon)
+                .build()
+                .on_press_maybe(enabled.then_some(message))
+                .height(36)
+                .width(36)
+                .padding(0)
+                .class(if enabled {
+                    ButtonType::Standard
+                } else {
+                    ButtonType::Disabled
+                })
+        };
+
         Row::new()
             .push(horizontal_space().width(Fill))
             .push(panel(
@@ -176,6 +253,31 @@ impl AnnotationWindow {
                     )
                     .spacing(8),
             ))
+            .push(horizontal_space().width(5))
+            .push(panel(
+                Row::new()
+                    .push(history_icon(
+                        Icon::Undo,
+                        AnnotationWindowEvent::Undo,
+                        self.history.can_undo(),
+                    ))
+                    .push(history_icon(
+                        Icon::Redo,
+                        AnnotationWindowEvent::Redo,
+                        self.history.can_redo(),
+                    ))
+                    .push(history_icon(
+                        Icon::Trash,
+                        AnnotationWindowEvent::ClearAll,
+                        !self.history.shapes.is_empty(),
+                    ))
+                    .push(history_icon(
+                        Icon::Save,
+                        AnnotationWindowEvent::ExportPng,
+                        !self.history.shapes.is_empty(),
+                    ))
+                    .spacing(8),
+            ))
             .push(horizontal_space().width(15))
             .push(panel(
                 Row::new().push(
@@ -201,7 +303,7 @@ impl GuiWindow for AnnotationWindow {
         String::from("")
     }
 
-    fn update(&mut self, id: Id, message: Self::Message, _config: &mut Config) -> Task<AppEvent> {
+    fn update(&mut self, id: Id, message: Self::Message, config: &mut Config) -> Task<AppEvent> {
         match message {
             AnnotationWindowEvent::ChooseShapeType(shape_type, is_filled, is_solid) => {
                 self.shape.s_type = shape_type;
@@ -217,8 +319,62 @@ impl GuiWindow for AnnotationWindow {
                 self.shape.color = color;
                 Task::none()
             }
+            AnnotationWindowEvent::Stroke(event) => {
+                let count = self.history.shapes.len();
+                match event {
+                    StrokeEvent::Begin(size) => {
+                        self.canvas_size = size;
+                        self.history.begin_stroke();
+                    }
+                    StrokeEvent::Erase(point, size) => {
+                        self.history.erase_at(point, size);
+                        if self.history.shapes.len() != count {
+                            self.sync_remote(config, RemoteChange::Replaced);
+                        }
+                    }
+                    StrokeEvent::End(shape, points) => {
+                        self.history.end_stroke(shape, points);
+                        if self.history.shapes.len() > count {
+                            self.sync_remote(config, RemoteChange::Added);
+                        }
+                    }
+                }
+                Task::none()
+            }
+            AnnotationWindowEvent::Undo => {
+                self.history.undo();
+                self.sync_remote(config, RemoteChange::Replaced);
+                Task::none()
+            }
+            AnnotationWindowEvent::Redo => {
+                self.history.redo();
+                self.sync_remote(config, RemoteChange::Replaced);
+                Task::none()
+            }
+            AnnotationWindowEvent::ClearAll => {
+                self.history.clear_all();
+                self.sync_remote(config, RemoteChange::Replaced);
+                Task::none()
+            }
+            AnnotationWindowEvent::ExportPng => {
+                if let Some(path) = image_saving_path(&config.output.directory) {
+                    match export_png(&self.history.shapes, self.canvas_size, &path) {
+                        Ok(()) => log::info!("Annotations saved to {}", path),
+                        Err(e) => log::error!("Annotations export failed: {:#}", e),
+                    }
+                }
+                Task::none()
+            }
             AnnotationWindowEvent::Ignore => Task::none(),
-            AnnotationWindowEvent::Exit => Task::done(AppEvent::CloseWindow(id)),
+            AnnotationWindowEvent::Exit => {
+                // The local overlay goes away with the window: receivers follow
+                if let Some(Mode::Caster(caster)) = &config.mode
+                    && !self.history.shapes.is_empty()
+                {
+                    caster.send_annotation(AnnotationEvent::Clear);
+                }
+                Task::done(AppEvent::CloseWindow(id))
+            }
             AnnotationWindowEvent::ToggleToolbar => {
                 self.show_toolbar = !self.show_toolbar;
                 Task::none()
@@ -262,7 +418,13 @@ impl GuiWindow for AnnotationWindow {
 
         Stack::new()
             .push(
-                Canvas::new(Annotation::new(self.shape).on_esc(AnnotationWindowEvent::Exit))
+                Canvas::new(
+                    Annotation::new(self.shape, &self.history.shapes)
+                        .on_esc(AnnotationWindowEvent::Exit)
+                        .on_undo(AnnotationWindowEvent::Undo)
+                        .on_redo(AnnotationWindowEvent::Redo)
+                        .on_stroke(AnnotationWindowEvent::Stroke),
+                )
                     .width(Fill)
                     .height(Fill),
             )
@@ -2,7 +2,7 @@ use crate::assets::FONT_FAMILY_BOLD;
 use crate::config::{Config, app_name};
 use crate::gui::common::datastructure::ScreenRect;
 use crate::gui::common::messages::AppEvent;
-use crate::gui::components::AreaSelector;
+use crate::gui::components::{AreaSelector, AspectLock, MIN_AREA};
 use crate::gui::style::container::ContainerType;
 use crate::gui::style::theme::csx::StyleType;
 use crate::gui::widget::{
@@ -17,6 +17,7 @@ use iced::window::Id;
 pub struct ASWindow {
     area: Option<ScreenRect>,
     invalid: bool,
+    aspect: AspectLock,
 }
 
 #[derive(Debug, Clone)]
@@ -24,6 +25,7 @@ pub enum ASWindowEvent {
     AreaSelected(ScreenRect),
     AreaAbort,
     Invalid,
+    CycleAspect,
     ExitAbort,
     ExitValid,
 }
@@ -33,6 +35,7 @@ impl ASWindow {
         ASWindow {
             area: None,
             invalid: false,
+            aspect: AspectLock::Free,
         }
     }
 }
@@ -61,6 +64,12 @@ impl GuiWindow for ASWindow {
                 self.area = None;
                 Task::none()
             }
+            ASWindowEvent::CycleAspect => {
+                self.aspect = self.aspect.next();
+                self.invalid = false;
+                self.area = None;
+                Task::none()
+            }
             ASWindowEvent::ExitAbort => Task::done(AppEvent::CloseWindow(id)),
             ASWindowEvent::ExitValid => {
                 if !self.invalid && self.area.is_some() {
@@ -77,26 +86,28 @@ impl GuiWindow for ASWindow {
 
     fn view(&self, _config: &Config) -> Element<'_, Self::Message> {
         let text_hint = if self.invalid {
-            "Invalid selection"
+            format!("Selection smaller than {0}×{0}", MIN_AREA as u32)
         } else if self.area.is_some() {
-            "Enter to Confirm | Click to Reset"
+            "Enter to Confirm | Click to Reset".to_string()
         } else {
-            "Esc to Cancel"
+            format!("Esc to Cancel | A: aspect {}", self.aspect)
         };
 
         Stack::new()
             .push(
                 Canvas::new(
                     AreaSelector::new()
+                        .aspect(self.aspect)
                         .on_release_rect(|rect| {
-                            if rect.height < 50.0 || rect.width < 50.0 {
-                                ASWindowEvent::Invalid
-                            } else if rect.height < 1.0 || rect.width < 1.0 {
+                            if rect.height < 1.0 || rect.width < 1.0 {
                                 ASWindowEvent::AreaAbort
+                            } else if rect.height < MIN_AREA || rect.width < MIN_AREA {
+                                ASWindowEvent::Invalid
                             } else {
                                 ASWindowEvent::AreaSelected(rect)
                             }
                         })
+                        .on_aspect(ASWindowEvent::CycleAspect)
                         .on_esc(ASWindowEvent::ExitAbort)
                         .on_confirm(ASWindowEvent::ExitValid),
                 )
@@ -1,10 +1,24 @@
-use crate::assets::{CAST_SERVICE_PORT, FRAME_RATE};
-use crate::config::{app_name, saving_path, Config, Mode};
+use crate::assets::{CAST_SERVICE_PORT, FONT_FAMILY_BOLD};
+use crate::capture::audio::{OpusBitrate, OpusFrameDuration};
+use crate::capture::budget::DataCap;
+use crate::capture::display::DisplaySelector;
+use crate::capture::display::thumbnail::grab_thumbnails;
+use crate::capture::overlay::{RingColor, RingSize};
+use crate::capture::permissions::{self, PermissionCheck};
+use crate::capture::timestamp::TimestampFormat;
+use crate::capture::watermark::{WatermarkCorner, WatermarkImage};
+use crate::capture::{
+    BitrateMode, CaptureError, ColorSpace, EncodeScale, FpsCap, HdrMode, ScreenCaptureImpl,
+    Simulcast, StreamProfile, prepare_capture,
+};
+use crate::config::{Config, Mode, OutputSettings, app_name, pick_directory, pick_watermark};
+use crate::decoder::AudioPlayer;
+use crate::display::DisplayPolicy;
 use crate::gui::common::datastructure::ScreenRect;
 use crate::gui::common::hotkeys::{hotkeys, KeyTypes};
 use crate::gui::common::messages::AppEvent;
 use crate::gui::components::awmodal::{AwModalManager, GuiComponent};
-use crate::gui::components::video::Video;
+use crate::gui::components::video::{Video, snapshot};
 use crate::gui::pages::caster::caster_page;
 use crate::gui::pages::footer::footer;
 use crate::gui::pages::home;
@@ -12,23 +26,47 @@ use crate::gui::pages::home::initial_page;
 use crate::gui::pages::info::info_page;
 use crate::gui::pages::popup::PopupType;
 use crate::gui::pages::receiver::client_page;
-use crate::gui::popup::ip::IPModal;
+use crate::gui::pages::settings::settings_page;
+use crate::gui::popup::accent::AccentModal;
+use crate::gui::popup::ip::{DiscoveryStarted, IPModal, InvalidAddress};
+use crate::gui::popup::permissions::PermissionsModal;
 use crate::gui::popup::shortcuts::ShortcutModal;
 use crate::gui::popup::wrtc::WrtcModal;
 use crate::gui::style::container::ContainerType;
+use crate::gui::style::theme::accent::{
+    load_accent, load_pinned_theme, save_accent, save_pinned_theme,
+};
 use crate::gui::style::theme::csx::StyleType;
-use crate::gui::widget::{Column, Container, Element, Space, Stack};
-use crate::gui::windows::GuiWindow;
-use crate::utils::net::webrtc::SDPICEExchangeWRTC;
+use crate::gui::style::theme::system::{ThemeChoice, detect_dark, system_theme};
+use crate::gui::widget::{Column, Container, Element, Space, Stack, Text};
+use crate::gui::windows::{GuiWindow, WindowMessage};
+use crate::pipeline::receiver::LatencyProfile;
+use crate::pipeline::sender::RtpMtu;
+use crate::pipeline::stats_log::StatsFormat;
+use crate::utils::diagnostics::Diagnostics;
+use crate::utils::logging::LogLevel;
+use crate::utils::net::common::{
+    DISCOVERY_WINDOW, connection_link, find_casters, parse_caster_addr, parse_connection_link,
+};
+use crate::utils::net::webhook::is_webhook_url;
+use crate::utils::net::webrtc::{MAX_CHAT_LEN, SDPICEExchangeWRTC};
+use crate::utils::path::{log_dir, shorten_path};
+#[cfg(feature = "remote-control")]
+use crate::utils::remote_control::RemoteInput;
+use crate::utils::selftest::{SELFTEST_FRAMES, SELFTEST_SIZE, SelfTestReport};
 use crate::workers::caster::Caster;
+use crate::workers::cursor_follow::FollowCursorDelay;
+use crate::workers::idle::IdleTimeout;
 use crate::workers::receiver::Receiver;
 use arboard::Clipboard;
 use castbox::AnyRef;
-use iced::{window::Id, Length, Task};
-use std::net::SocketAddr;
-use std::str::FromStr;
+use iced::keyboard::key::Named;
+use iced::widget::image::Handle;
+use iced::{Alignment, Color, Length, Task, window::Id};
+use native_dialog::{DialogBuilder, MessageLevel};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
@@ -37,6 +75,7 @@ pub enum Page {
     Caster,
     Client,
     Hotkeys,
+    Settings,
     Info,
 }
 
@@ -45,61 +84,413 @@ pub enum MainWindowEvent {
     Home,
     Mode(home::Message),
     CasterToggleStreaming,
+    /// Sessione di cattura pronta (`prepare_capture`), si crea il caster
+    CasterPrepared,
+    CasterToggleAudioOnly,
+    /// Barre colore e tono a 1kHz al posto di schermo e audio, per provare la connessione
+    CasterToggleTestPattern,
     CasterChangeDisplay(usize),
+    /// Finestra da trasmettere, tra quelle di `get_windows()`
+    CasterChangeWindow(<ScreenCaptureImpl as DisplaySelector>::Display),
+    RefreshThumbnails,
+    /// Miniature dei display, nello stesso ordine di `get_displays()`
+    DisplayThumbnails(Vec<Option<Handle>>),
+    CasterChangeProfile(StreamProfile),
+    /// Tetto di fps della cattura, salvato subito
+    CasterChangeFpsCap(FpsCap),
+    CasterChangeDataCap(DataCap),
+    /// Tetto alla risoluzione codificata (pagina impostazioni)
+    CasterEncodeScale(EncodeScale),
+    /// Secondo encoder per i receiver lenti, dal prossimo stream (pagina impostazioni)
+    CasterSimulcast(Simulcast),
+    /// VBR o bitrate costante, dal prossimo stream (pagina impostazioni)
+    CasterBitrateMode(BitrateMode),
+    /// Tone-mapping dei desktop HDR, dal prossimo avvio dello stream
+    CaptureHdrMode(HdrMode),
+    /// Payload RTP massimo, dal prossimo avvio dello stream
+    CaptureRtpMtu(RtpMtu),
+    /// Pausa automatica della cattura senza receiver connessi
+    CaptureIdleTimeout(IdleTimeout),
+    /// Segue il puntatore tra i monitor durante lo stream
+    CaptureFollowCursorToggle,
+    CaptureFollowCursorDelay(FollowCursorDelay),
+    CaptureCursorRingSize(RingSize),
+    CaptureCursorRingColor(RingColor),
+    /// Frame rate ridotto a schermo fermo (pagina impostazioni)
+    CasterContentAwareToggle,
+    /// Voce in scrittura per la lista delle finestre escluse
+    ExcludeWindowInput(String),
+    /// Aggiunge la voce in scrittura alle finestre escluse
+    ExcludeWindowAdd,
+    /// Rimuove una voce dalle finestre escluse
+    ExcludeWindowRemove(usize),
+    /// URL in scrittura per i webhook
+    WebhookInput(String),
+    /// Aggiunge l'URL in scrittura ai webhook
+    WebhookAdd,
+    /// Rimuove un URL dai webhook
+    WebhookRemove(usize),
+    /// Audio PCM non compresso, dal prossimo caster (pagina impostazioni)
+    CasterLowLatencyAudioToggle,
+    /// Parametri Opus, dal prossimo caster
+    AudioBitrate(OpusBitrate),
+    AudioFrameDuration(OpusFrameDuration),
+    AudioStereoToggle,
+    AudioVbrToggle,
+    CasterChangeName(String),
+    /// Buffer del receiver subito, del caster dal prossimo stream
+    LatencyProfile(LatencyProfile),
+    ManualPassphrase(String),
+    /// Risultato di una scansione mDNS: (nome istanza, indirizzo)
+    CastersDiscovered(Vec<(String, SocketAddr)>),
+    DiscoverCasters,
     PopupMessage(AnyRef),
     ClosePopup(Option<Page>),
+    /// Invio/Esc senza modificatori: conferma o chiude il modal aperto
+    PopupKey(Named),
     ConnectToCaster(String),
+    /// Il caster non ha risposto in tempo: si torna al modal con l'errore
+    ConnectFailed(String),
+    /// Svuota la lista delle connessioni recenti
+    ClearRecentCasters,
+    /// Entra in modalità caster (dopo l'eventuale checklist dei permessi)
+    StartCaster,
+    /// Verifica dei permessi di cattura; `true` se prima di avviare il caster
+    CheckPermissions(bool),
+    PermissionsChecked(bool, Vec<PermissionCheck>),
+    /// Link `castify://` aperto dal sistema o incollato nel campo indirizzo
+    OpenConnectionLink(String),
+    /// Indirizzo del caster da `--connect` o da un link già letto
+    OpenCasterAddress(String),
+    CopyConnectionLink,
     SaveCapture,
     SaveCaptureStop,
     HotkeysPage,
     HotkeysTypePage(KeyTypes),
+    HotkeysReset,
+    SettingsPage,
+    OutputPickDirectory,
+    /// Modello del nome dei file salvati (token `{date}`, `{time}`, ...)
+    OutputTemplate(String),
+    /// Durata massima di un segmento di registrazione, in minuti
+    OutputSegmentMinutes(String),
+    /// Dimensione massima di un segmento di registrazione, in MB
+    OutputSegmentMegabytes(String),
+    OutputReset,
+    /// Secondi di conto alla rovescia prima di stream/registrazione
+    OutputCountdown(String),
+    /// Cadute più brevi continuano la registrazione nello stesso file
+    OutputResume(String),
+    /// Attiva/disattiva il log delle statistiche dello stream
+    StatsLogToggle,
+    /// Invia all'altro lato il testo copiato qui
+    ClipboardShareToggle,
+    /// Applica il testo copiato dall'altro lato
+    ClipboardAcceptToggle,
+    /// Chiudere la finestra la nasconde nella tray invece di uscire
+    CloseToTrayToggle,
+    StatsLogFormat(StatsFormat),
+    StatsLogPickDirectory,
+    /// Verbosità del log, applicata subito
+    LogLevel(LogLevel),
+    /// Copia del log su file nella cartella di configurazione
+    LogToFileToggle,
+    /// Apre la cartella dei log per allegarli a una segnalazione
+    OpenLogFolder,
+    /// Copia negli appunti il riepilogo diagnostico per una segnalazione
+    CopyDiagnostics,
+    /// Un secondo del conto alla rovescia con la generazione indicata
+    CountdownTick(u64),
+    /// Dispositivo di uscita del receiver, `None` = default di sistema
+    PlaybackDevice(Option<String>),
+    PlaybackDisplayPolicy(DisplayPolicy),
+    /// Presentazione a cadenza fissa (movimento fluido)
+    PlaybackSmoothMotionToggle,
+    /// Matrice e range con cui riconvertire i frame in RGB
+    PlaybackColorSpace(ColorSpace),
+    /// Passa dal video con le bande a quello stirato sulla finestra e viceversa
+    PlaybackFitToggle,
+    /// Correzione del lip-sync in ms mentre si trascina lo slider
+    PlaybackAvOffset(i64),
+    /// Slider rilasciato: il valore viene salvato
+    PlaybackAvOffsetSave,
+    /// Volume dell'audio ricevuto, in percentuale
+    PlaybackVolume(u32),
+    /// Salva il volume al rilascio dello slider
+    PlaybackVolumeSave,
+    /// Ritardo massimo nei buffer del receiver, in ms (vuoto = disattivato)
+    PlaybackMaxLatency(String),
+    /// Secondi per raggiungere il caster (vuoto = senza limite)
+    PlaybackConnectTimeout(String),
+    WatermarkPickFile,
+    WatermarkClear,
+    WatermarkCorner(WatermarkCorner),
+    /// Opacità del logo in percentuale
+    WatermarkOpacity(String),
+    /// Scala del logo in percentuale
+    WatermarkScale(String),
+    TimestampToggle,
+    TimestampCorner(WatermarkCorner),
+    TimestampFormat(TimestampFormat),
+    /// Etichetta davanti all'orario
+    TimestampLabel(String),
+    /// Opacità dell'orario in percentuale
+    TimestampOpacity(String),
+    /// Salva il frame ricevuto corrente come PNG
+    Screenshot,
+    /// Capitolo sul frame corrente della registrazione
+    ChapterMark,
     AreaSelection,
     AreaSelectedFullScreen,
+    AreaFollowWindow,
     ExitApp,
     OpenWebPage(String),
+    /// Tema scelto dall'utente: resta fisso anche se cambia quello di sistema
     ThemeUpdate(StyleType),
-    ShowAnnotationWindow,
+    /// Torna a seguire il tema del sistema operativo
+    ThemeFollowSystem,
+    /// Preferenza del sistema operativo (true = scuro)
+    SystemTheme(bool),
+    AccentPicker,
+    /// Accento personalizzato, `None` torna a quello del tema
+    AccentColor(Option<Color>),
+    ToggleAnnotationWindow,
     OpenInfo,
+    /// Avvia il self-test (encoder, audio, display) dalla pagina info
+    RunSelfTest,
+    /// Riepilogo del self-test concluso
+    SelfTestDone(String),
     Ignore,
     ShowSDP,
     CopyToClipboard(String),
     ToggleAudioMute,
+    ToggleKeycast,
+    /// Mostra/nasconde il puntatore nello stream
+    ToggleCursorCapture,
+    /// Messaggio breve sopra la pagina (es. nome del nuovo display)
+    ShowToast(String),
+    /// Testo in scrittura nel pannello chat
+    ChatInput(String),
+    /// Invia il testo in scrittura all'altro lato della sessione
+    ChatSend,
+    /// Mostra/nasconde la chat accanto al video del receiver
+    ToggleChat,
+    /// Evento del mouse sul video, inoltrato al caster
+    #[cfg(feature = "remote-control")]
+    RemoteInput(RemoteInput),
+    /// Il receiver inizia/smette di controllare il caster
+    #[cfg(feature = "remote-control")]
+    ToggleRemoteControl,
+    /// Il caster consente/revoca il controllo remoto
+    #[cfg(feature = "remote-control")]
+    CasterAllowControl,
+}
+
+/// Durata del toast sopra la pagina
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+
+/// Azione avviata alla fine del conto alla rovescia
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CountdownAction {
+    Streaming,
+    Recording,
+}
+
+struct Countdown {
+    action: CountdownAction,
+    remaining: u32,
+    /// Scarta i tick di un conto alla rovescia annullato
+    generation: u64,
 }
 
 pub struct MainWindow {
     pub theme: StyleType,
+    /// Tema scelto a mano, ignora le preferenze del sistema
+    pub theme_pinned: bool,
+    /// Accento scelto dall'utente, applicato sopra qualsiasi tema
+    pub accent: Option<Color>,
     page: Page,
     prev_page: Page,
     popup: AwModalManager<PopupType>,
     video: Video,
+    toast: Option<(String, Instant)>,
+    /// Cartella di salvataggio non scrivibile, mostrato nella pagina impostazioni
+    output_warning: Option<String>,
+    display_thumbnails: Vec<Option<Handle>>,
+    /// Uscite audio elencate nella pagina impostazioni
+    output_devices: Vec<String>,
+    /// PNG del watermark non caricabile, mostrato nella pagina impostazioni
+    watermark_warning: Option<String>,
+    /// PNG del watermark già decodificato, per i cambi di angolo/scala/opacità
+    watermark_image: Option<WatermarkImage>,
+    countdown: Option<Countdown>,
+    countdown_generation: u64,
+    /// Messaggio di chat non ancora inviato
+    chat_draft: String,
+    /// Voce non ancora aggiunta alle finestre escluse
+    exclude_draft: String,
+    /// URL non ancora aggiunto ai webhook
+    webhook_draft: String,
+    chat_open: bool,
+    /// Riepilogo dell'ultimo self-test, mostrato nella pagina info
+    selftest: Option<String>,
+    selftest_running: bool,
 }
 
 impl MainWindow {
     pub fn new() -> Self {
+        let pinned = load_pinned_theme();
         Self {
-            theme: StyleType::default(),
+            theme_pinned: pinned.is_some(),
+            theme: pinned.unwrap_or_else(|| detect_dark().map(system_theme).unwrap_or_default()),
+            accent: load_accent(),
             page: Page::Home,
             popup: AwModalManager::new(),
             video: Video::new(),
             prev_page: Page::Home,
+            toast: None,
+            output_warning: None,
+            display_thumbnails: Vec::new(),
+            output_devices: Vec::new(),
+            watermark_warning: None,
+            watermark_image: None,
+            countdown: None,
+            countdown_generation: 0,
+            chat_draft: String::new(),
+            exclude_draft: String::new(),
+            webhook_draft: String::new(),
+            chat_open: false,
+            selftest: None,
+            selftest_running: false,
         }
     }
 
+    /// Campo numerico dei limiti: vuoto = 0 (disattivato), altro testo ignorato
+    fn parse_limit(value: &str) -> Option<u32> {
+        match value.trim() {
+            "" => Some(0),
+            value => value.parse().ok(),
+        }
+    }
+
+    fn output_warning(output: &OutputSettings) -> Option<String> {
+        output.check_writable().err().map(|e| format!("{:#}", e))
+    }
+
+    /// Applica il logo configurato al caster attivo; se il PNG non si
+    /// carica l'errore resta da mostrare nelle impostazioni.
+    fn apply_watermark(&mut self, config: &mut Config) {
+        let (watermark, warning) = match config.watermark.build(&mut self.watermark_image) {
+            Ok(watermark) => (watermark, None),
+            Err(e) => (None, Some(format!("{:#}", e))),
+        };
+        if let Some(caster) = Self::caster_mut(config) {
+            caster.set_watermark(watermark);
+        }
+        self.watermark_warning = warning;
+    }
+
+    /// Applica l'orario configurato al caster attivo.
+    fn apply_timestamp(config: &mut Config) {
+        let timestamp = config.timestamp.build();
+        if let Some(caster) = Self::caster_mut(config) {
+            caster.set_timestamp(timestamp);
+        }
+    }
+
+    fn start_countdown(&mut self, id: Id, action: CountdownAction, seconds: u32) -> Task<AppEvent> {
+        self.countdown_generation += 1;
+        self.countdown = Some(Countdown {
+            action,
+            remaining: seconds,
+            generation: self.countdown_generation,
+        });
+        Self::countdown_tick(id, self.countdown_generation)
+    }
+
+    fn countdown_tick(id: Id, generation: u64) -> Task<AppEvent> {
+        Task::future(async move {
+            sleep(Duration::from_secs(1)).await;
+            AppEvent::WindowEvent(
+                id,
+                WindowMessage::Main(MainWindowEvent::CountdownTick(generation)),
+            )
+        })
+    }
+
+    /// Annulla il conto alla rovescia in corso, se c'è
+    fn cancel_countdown(&mut self, id: Id) -> Option<Task<AppEvent>> {
+        self.countdown.take().map(|_| {
+            Task::done(AppEvent::WindowEvent(
+                id,
+                WindowMessage::Main(MainWindowEvent::ShowToast(String::from(
+                    "Countdown cancelled",
+                ))),
+            ))
+        })
+    }
+
+    fn start_recording(id: Id, config: &mut Config) -> Task<AppEvent> {
+        let output = config.output.clone();
+        let Some(client) = Self::receiver_mut(config) else {
+            return Task::none();
+        };
+        let message = match output.check_writable() {
+            Err(e) => format!("Cannot record: {}", e),
+            Ok(()) => match client.save_stream(&output) {
+                Some(path) => format!("Recording to {}", shorten_path(path)),
+                None => return Task::none(),
+            },
+        };
+        Task::done(AppEvent::WindowEvent(
+            id,
+            WindowMessage::Main(MainWindowEvent::ShowToast(message)),
+        ))
+    }
+
     pub fn change_page(&mut self, page: Page) {
         self.prev_page = self.page;
         self.page = page;
     }
 
+    /// La cattura non parte (niente monitor, permessi, driver): si resta
+    /// nella home e l'utente vede il motivo invece di un crash.
+    fn capture_error_dialog(error: &CaptureError) {
+        let hint = match error {
+            CaptureError::PermissionDenied => {
+                "\n\nAllow screen recording for this app in the system settings and try again."
+            }
+            CaptureError::NoDisplays => "\n\nConnect a display and try again.",
+            CaptureError::GraphicsInit(_) => "\n\nUpdate the graphics driver and try again.",
+            _ => "",
+        };
+        if let Err(e) = DialogBuilder::message()
+            .set_title("Cannot start casting")
+            .set_text(format!("{}{}", error, hint).as_str())
+            .set_level(MessageLevel::Error)
+            .alert()
+            .show()
+        {
+            log::error!("Failed to display capture error dialog: {:?}", e);
+        }
+    }
+
     /// Collega il canale video dal Receiver al componente Video per il rendering.
     fn attach_video_stream(&mut self, receiver: &mut Receiver) {
         if let Some(rx) = receiver.launch(true) {
-            self.video.set_stream(rx, FRAME_RATE);
+            self.video
+                .set_display_policy(receiver.display_policy_ref(), receiver.health().clone());
+            self.video.set_stream(rx, receiver.present_rate());
+            self.video.set_profile_hint(receiver.stream_profile());
         }
     }
 
     fn attach_video_stream_manual(&mut self, receiver: &mut Receiver) {
         if let Some(rx) = receiver.launch(false) {
-            self.video.set_stream(rx, FRAME_RATE);
+            self.video
+                .set_display_policy(receiver.display_policy_ref(), receiver.health().clone());
+            self.video.set_stream(rx, receiver.present_rate());
+            self.video.set_profile_hint(receiver.stream_profile());
         }
     }
 
@@ -109,6 +500,75 @@ impl MainWindow {
         }
     }
 
+    /// Nuovo receiver con le impostazioni di riproduzione salvate
+    fn start_receiver(config: &mut Config) {
+        let mut receiver = Receiver::new(config.sos.clone());
+        receiver.set_latency_profile(config.latency_profile);
+        receiver.set_output_device(config.playback.output_device.clone());
+        receiver.set_display_policy(config.playback.display_policy);
+        receiver.set_av_offset_ms(config.playback.av_offset_ms);
+        receiver.set_max_latency_ms(config.playback.max_latency_ms);
+        receiver.set_volume_percent(config.playback.volume_percent);
+        receiver.set_smooth_motion(config.playback.smooth_motion);
+        receiver.set_connect_timeout_secs(config.playback.connect_timeout_secs);
+        receiver.set_stats_log(config.output.stats_log_target("receiver"));
+        config.mode = Some(Mode::Receiver(receiver));
+        Self::apply_clipboard_sharing(config);
+        Self::apply_webhooks(config);
+        Self::apply_passphrase(config);
+    }
+
+    /// Passphrase della sessione: autentica l'SDP manuale e il controllo remoto
+    fn apply_passphrase(config: &Config) {
+        let passphrase = Some(config.manual_passphrase.clone()).filter(|p| !p.is_empty());
+        match &config.mode {
+            Some(Mode::Caster(caster)) => {
+                caster.get_connection_handler().set_passphrase(passphrase)
+            }
+            Some(Mode::Receiver(receiver)) => {
+                receiver.get_connection_handler().set_passphrase(passphrase)
+            }
+            None => {}
+        }
+    }
+
+    /// Condivisione clipboard scelta nelle impostazioni, applicata al worker attivo
+    fn apply_clipboard_sharing(config: &Config) {
+        let (share, accept) = (config.clipboard_share, config.clipboard_accept);
+        match &config.mode {
+            Some(Mode::Caster(caster)) => caster.set_clipboard_sharing(share, accept),
+            Some(Mode::Receiver(receiver)) => receiver.set_clipboard_sharing(share, accept),
+            None => {}
+        }
+    }
+
+    /// URL dei webhook dalle impostazioni, applicati al worker attivo
+    fn apply_webhooks(config: &Config) {
+        let urls = config.webhooks.urls.clone();
+        match &config.mode {
+            Some(Mode::Caster(caster)) => caster.set_webhooks(urls),
+            Some(Mode::Receiver(receiver)) => receiver.set_webhooks(urls),
+            None => {}
+        }
+    }
+
+    /// Salva la lista delle finestre escluse e la applica al caster attivo
+    fn apply_exclude_windows(config: &mut Config) {
+        config.capture.save();
+        let patterns = config.capture.exclude_windows.clone();
+        if let Some(caster) = Self::caster_mut(config) {
+            caster.set_privacy_exclude(patterns);
+        }
+    }
+
+    fn theme_choice(&self) -> ThemeChoice {
+        if self.theme_pinned {
+            ThemeChoice::Pinned(self.theme.clone())
+        } else {
+            ThemeChoice::FollowSystem
+        }
+    }
+
     fn receiver_mut(config: &mut Config) -> Option<&mut Receiver> {
         match &mut config.mode {
             Some(Mode::Receiver(receiver)) => Some(receiver),
@@ -123,19 +583,12 @@ impl MainWindow {
         }
     }
 
-    fn parse_caster_addr(caster_ip: &str) -> Result<SocketAddr, ()> {
-        let caster_ip = if caster_ip.contains(':') {
-            caster_ip.to_owned()
-        } else {
-            format!("{}:{}", caster_ip, CAST_SERVICE_PORT)
-        };
-        SocketAddr::from_str(&caster_ip).map_err(|_| ())
-    }
-
     fn active_sdp_provider(
         &mut self,
         config: &mut Config,
     ) -> Option<(bool, Arc<dyn SDPICEExchangeWRTC>)> {
+        self.video.set_color_space(config.playback.color);
+        self.video.set_fit(config.playback.fit);
         match &mut config.mode {
             Some(Mode::Caster(caster)) => Some((
                 true,
@@ -161,11 +614,12 @@ impl GuiWindow for MainWindow {
         app_name()
     }
 
-    fn update(&mut self, _id: Id, message: MainWindowEvent, config: &mut Config) -> Task<AppEvent> {
+    fn update(&mut self, id: Id, message: MainWindowEvent, config: &mut Config) -> Task<AppEvent> {
         match message {
             MainWindowEvent::Home => {
                 config.shortcuts.updating = KeyTypes::None;
                 config.reset_mode();
+                self.countdown = None;
                 self.popup.hide();
                 self.change_page(Page::Home);
                 Task::none()
@@ -173,25 +627,189 @@ impl GuiWindow for MainWindow {
             MainWindowEvent::Mode(mode) => {
                 match mode {
                     home::Message::ButtonCaster => {
-                        config.mode =
-                            Some(Mode::Caster(Caster::new(config.fps, config.sos.clone())));
-                        self.change_page(Page::Caster);
+                        // Su macOS e Wayland prima si verificano i permessi di cattura
+                        let next = if permissions::is_gated() {
+                            MainWindowEvent::CheckPermissions(true)
+                        } else {
+                            MainWindowEvent::StartCaster
+                        };
+                        return Task::done(AppEvent::WindowEvent(id, WindowMessage::Main(next)));
                     }
                     home::Message::ButtonReceiver => {
-                        config.mode = Some(Mode::Receiver(Receiver::new(config.sos.clone())));
+                        Self::start_receiver(config);
                         self.popup.set(PopupType::IP(IPModal::new()));
                         self.popup.show();
+                        return Task::batch([
+                            self.popup.focus(),
+                            Task::done(AppEvent::WindowEvent(
+                                id,
+                                WindowMessage::Main(MainWindowEvent::DiscoverCasters),
+                            )),
+                        ]);
                     }
                 }
                 Task::none()
             }
+            MainWindowEvent::StartCaster => {
+                self.popup.hide();
+                // Il dialog del portal (Wayland) aspetta l'utente: fuori dal thread della UI
+                Task::perform(tokio::task::spawn_blocking(prepare_capture), move |_| {
+                    AppEvent::WindowEvent(id, WindowMessage::Main(MainWindowEvent::CasterPrepared))
+                })
+            }
+            MainWindowEvent::CasterPrepared => {
+                if Self::caster_mut(config).is_some() {
+                    return Task::none();
+                }
+                let caster = match Caster::new(
+                    config.fps,
+                    config.stream_profile,
+                    config.audio_encode,
+                    config.data_cap,
+                    config.caster_name.clone(),
+                    config.sos.clone(),
+                ) {
+                    Ok(caster) => caster,
+                    Err(e) => {
+                        log::error!("Cannot start the caster: {}", e);
+                        Self::capture_error_dialog(&e);
+                        return Task::none();
+                    }
+                };
+                config.mode = Some(Mode::Caster(caster));
+                let stats_log = config.output.stats_log_target("caster");
+                let encode_scale = config.encode_scale;
+                let content_aware = config.content_aware;
+                let fps_cap = config.capture.max_fps;
+                let simulcast = config.capture.simulcast;
+                let bitrate_mode = config.bitrate_mode;
+                let exclude_windows = config.capture.exclude_windows.clone();
+                let hdr = config.capture.hdr;
+                let rtp_mtu = config.capture.rtp_mtu;
+                let idle_timeout = config.capture.idle_timeout;
+                let follow_cursor = config.capture.follow_cursor;
+                let follow_cursor_delay = config.capture.follow_cursor_delay;
+                let hide_cursor = config.capture.hide_cursor;
+                let latency_profile = config.latency_profile;
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_stats_log(stats_log);
+                    caster.set_encode_scale(encode_scale);
+                    caster.set_simulcast(simulcast);
+                    caster.set_latency_profile(latency_profile);
+                    caster.set_bitrate_mode(bitrate_mode);
+                    caster.set_fps_cap(fps_cap);
+                    caster.set_content_aware(content_aware);
+                    caster.set_privacy_exclude(exclude_windows);
+                    caster.set_hdr_mode(hdr);
+                    caster.set_rtp_mtu(rtp_mtu);
+                    caster.set_idle_timeout(idle_timeout);
+                    caster.set_follow_cursor_delay(follow_cursor_delay);
+                    caster.set_follow_cursor(follow_cursor);
+                    caster.set_show_cursor(!hide_cursor);
+                }
+                Self::apply_clipboard_sharing(config);
+                Self::apply_webhooks(config);
+                Self::apply_passphrase(config);
+                self.apply_watermark(config);
+                Self::apply_timestamp(config);
+                self.display_thumbnails.clear();
+                self.change_page(Page::Caster);
+                Task::done(AppEvent::WindowEvent(
+                    id,
+                    WindowMessage::Main(MainWindowEvent::RefreshThumbnails),
+                ))
+            }
+            MainWindowEvent::CheckPermissions(before_casting) => {
+                // Dalle impostazioni la checklist si apre subito, in attesa del risultato
+                if !before_casting {
+                    self.popup
+                        .set(PopupType::Permissions(PermissionsModal::new(false)));
+                    self.popup.show();
+                }
+                Task::perform(
+                    tokio::task::spawn_blocking(permissions::check),
+                    move |checks| {
+                        AppEvent::WindowEvent(
+                            id,
+                            WindowMessage::Main(MainWindowEvent::PermissionsChecked(
+                                before_casting,
+                                checks.unwrap_or_default(),
+                            )),
+                        )
+                    },
+                )
+            }
+            MainWindowEvent::PermissionsChecked(before_casting, checks) => {
+                let showing = self.popup.is_visible()
+                    && matches!(self.popup.get_ref(), Some(PopupType::Permissions(_)));
+                let missing = checks.iter().any(PermissionCheck::is_missing);
+                // Tutto a posto e checklist già vista: si entra direttamente
+                if before_casting && !showing && !missing && config.capture.permissions_checked {
+                    return Task::done(AppEvent::WindowEvent(
+                        id,
+                        WindowMessage::Main(MainWindowEvent::StartCaster),
+                    ));
+                }
+                if !config.capture.permissions_checked {
+                    config.capture.permissions_checked = true;
+                    config.capture.save();
+                }
+                if !showing {
+                    self.popup.set(PopupType::Permissions(PermissionsModal::new(
+                        before_casting,
+                    )));
+                    self.popup.show();
+                }
+                self.popup_update(AnyRef::new(checks), config);
+                Task::none()
+            }
+            MainWindowEvent::RefreshThumbnails => {
+                let Some(caster) = Self::caster_mut(config) else {
+                    return Task::none();
+                };
+                if caster.is_streaming() {
+                    return Task::none();
+                }
+                Task::perform(grab_thumbnails(caster.get_displays()), move |thumbnails| {
+                    let handles = thumbnails
+                        .into_iter()
+                        .map(|t| t.map(|t| Handle::from_rgba(t.width, t.height, t.rgba)))
+                        .collect();
+                    AppEvent::WindowEvent(
+                        id,
+                        WindowMessage::Main(MainWindowEvent::DisplayThumbnails(handles)),
+                    )
+                })
+            }
+            MainWindowEvent::DisplayThumbnails(thumbnails) => {
+                self.display_thumbnails = thumbnails;
+                Task::none()
+            }
+            MainWindowEvent::DiscoverCasters => {
+                self.popup_update(AnyRef::new(DiscoveryStarted), config);
+                Task::future(async move {
+                    let casters = tokio::task::spawn_blocking(|| find_casters(DISCOVERY_WINDOW))
+                        .await
+                        .unwrap_or_default();
+                    AppEvent::WindowEvent(
+                        id,
+                        WindowMessage::Main(MainWindowEvent::CastersDiscovered(casters)),
+                    )
+                })
+            }
+            MainWindowEvent::CastersDiscovered(casters) => {
+                self.popup_update(AnyRef::new(casters), config);
+                Task::none()
+            }
             MainWindowEvent::ShowSDP => {
+                let passphrase = Some(config.manual_passphrase.clone()).filter(|p| !p.is_empty());
                 if let Some((is_caster, sdp)) = self.active_sdp_provider(config) {
+                    sdp.set_passphrase(passphrase);
                     self.popup
                         .set(PopupType::ManualWRTC(WrtcModal::new(is_caster)));
                     self.popup.show();
 
-                    Task::future(async move {
+                    let exchange = Task::future(async move {
                         let remote_sdp = sdp.get_sdp().await;
                         if !remote_sdp.starts_with("Wrong") {
                             sdp.set_remote_sdp(remote_sdp).await;
@@ -199,12 +817,60 @@ impl GuiWindow for MainWindow {
 
                         sleep(Duration::from_millis(1500)).await;
                         AppEvent::Ignore
-                    })
+                    });
+                    Task::batch([self.popup.focus(), exchange])
                 } else {
                     Task::none()
                 }
             }
-            MainWindowEvent::CasterToggleStreaming => Task::done(AppEvent::CasterToggleStreaming),
+            MainWindowEvent::CasterToggleStreaming => {
+                if let Some(cancelled) = self.cancel_countdown(id) {
+                    return cancelled;
+                }
+                let seconds = config.output.countdown_seconds;
+                match &config.mode {
+                    // Il conto alla rovescia precede solo l'avvio, mai la pausa
+                    Some(Mode::Caster(caster)) if seconds > 0 && !caster.is_streaming() => {
+                        self.start_countdown(id, CountdownAction::Streaming, seconds)
+                    }
+                    _ => Task::done(AppEvent::CasterToggleStreaming),
+                }
+            }
+            MainWindowEvent::CountdownTick(generation) => {
+                let Some(countdown) = self
+                    .countdown
+                    .as_mut()
+                    .filter(|c| c.generation == generation)
+                else {
+                    return Task::none();
+                };
+                countdown.remaining = countdown.remaining.saturating_sub(1);
+                if countdown.remaining > 0 {
+                    return Self::countdown_tick(id, generation);
+                }
+                match self.countdown.take().map(|c| c.action) {
+                    Some(CountdownAction::Streaming) => match &config.mode {
+                        Some(Mode::Caster(caster)) if !caster.is_streaming() => {
+                            Task::done(AppEvent::CasterToggleStreaming)
+                        }
+                        _ => Task::none(),
+                    },
+                    Some(CountdownAction::Recording) => Self::start_recording(id, config),
+                    None => Task::none(),
+                }
+            }
+            MainWindowEvent::CasterToggleAudioOnly => {
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.toggle_audio_only();
+                }
+                Task::none()
+            }
+            MainWindowEvent::CasterToggleTestPattern => {
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.toggle_test_pattern();
+                }
+                Task::none()
+            }
             MainWindowEvent::CasterChangeDisplay(idx) => {
                 if let Some(caster) = Self::caster_mut(config) {
                     let displays = caster.get_displays();
@@ -214,12 +880,236 @@ impl GuiWindow for MainWindow {
                 }
                 Task::none()
             }
+            MainWindowEvent::CasterChangeWindow(window) => {
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.change_display(window);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CasterChangeProfile(profile) => {
+                config.stream_profile = profile;
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_stream_profile(profile);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CasterChangeFpsCap(cap) => {
+                config.capture.max_fps = cap;
+                config.capture.save();
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_fps_cap(cap);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CaptureHdrMode(mode) => {
+                config.capture.hdr = mode;
+                config.capture.save();
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_hdr_mode(mode);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CaptureIdleTimeout(timeout) => {
+                config.capture.idle_timeout = timeout;
+                config.capture.save();
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_idle_timeout(timeout);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CaptureFollowCursorToggle => {
+                config.capture.follow_cursor = !config.capture.follow_cursor;
+                config.capture.save();
+                let enabled = config.capture.follow_cursor;
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_follow_cursor(enabled);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CaptureFollowCursorDelay(delay) => {
+                config.capture.follow_cursor_delay = delay;
+                config.capture.save();
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_follow_cursor_delay(delay);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CaptureCursorRingSize(size) => {
+                size.apply(&mut config.capture.cursor_highlight);
+                config.capture.save();
+                let style = config.capture.cursor_highlight;
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_cursor_highlight_style(style);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CaptureCursorRingColor(color) => {
+                color.apply(&mut config.capture.cursor_highlight);
+                config.capture.save();
+                let style = config.capture.cursor_highlight;
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_cursor_highlight_style(style);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CaptureRtpMtu(mtu) => {
+                config.capture.rtp_mtu = mtu;
+                config.capture.save();
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_rtp_mtu(mtu);
+                }
+                Task::none()
+            }
+            MainWindowEvent::ManualPassphrase(passphrase) => {
+                config.manual_passphrase = passphrase;
+                Self::apply_passphrase(config);
+                Task::none()
+            }
+            MainWindowEvent::CasterChangeName(name) => {
+                config.caster_name = name.clone();
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_instance_name(name);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CasterChangeDataCap(cap) => {
+                config.data_cap = cap;
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_data_cap(cap);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CasterEncodeScale(scale) => {
+                config.encode_scale = scale;
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_encode_scale(scale);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CasterBitrateMode(mode) => {
+                config.bitrate_mode = mode;
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_bitrate_mode(mode);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CasterSimulcast(simulcast) => {
+                config.capture.simulcast = simulcast;
+                config.capture.save();
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_simulcast(simulcast);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CasterContentAwareToggle => {
+                config.content_aware = !config.content_aware;
+                let content_aware = config.content_aware;
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_content_aware(content_aware);
+                }
+                Task::none()
+            }
+            MainWindowEvent::ExcludeWindowInput(text) => {
+                self.exclude_draft = text;
+                Task::none()
+            }
+            MainWindowEvent::ExcludeWindowAdd => {
+                let pattern = self.exclude_draft.trim().to_string();
+                if pattern.is_empty() || config.capture.exclude_windows.contains(&pattern) {
+                    return Task::none();
+                }
+                self.exclude_draft.clear();
+                config.capture.exclude_windows.push(pattern);
+                Self::apply_exclude_windows(config);
+                Task::none()
+            }
+            MainWindowEvent::ExcludeWindowRemove(index) => {
+                if index < config.capture.exclude_windows.len() {
+                    config.capture.exclude_windows.remove(index);
+                    Self::apply_exclude_windows(config);
+                }
+                Task::none()
+            }
+            MainWindowEvent::WebhookInput(text) => {
+                self.webhook_draft = text;
+                Task::none()
+            }
+            MainWindowEvent::WebhookAdd => {
+                let url = self.webhook_draft.trim().to_string();
+                if !is_webhook_url(&url) {
+                    self.toast = Some((
+                        String::from("Webhook URLs must be full http:// or https:// addresses"),
+                        Instant::now(),
+                    ));
+                    return Task::none();
+                }
+                self.webhook_draft.clear();
+                if !config.webhooks.urls.contains(&url) {
+                    config.webhooks.urls.push(url);
+                    config.webhooks.save();
+                    Self::apply_webhooks(config);
+                }
+                Task::none()
+            }
+            MainWindowEvent::WebhookRemove(index) => {
+                if index < config.webhooks.urls.len() {
+                    config.webhooks.urls.remove(index);
+                    config.webhooks.save();
+                    Self::apply_webhooks(config);
+                }
+                Task::none()
+            }
+            MainWindowEvent::CasterLowLatencyAudioToggle => {
+                config.audio_encode.low_latency = !config.audio_encode.low_latency;
+                config.audio_encode.save();
+                Task::none()
+            }
+            MainWindowEvent::AudioBitrate(bitrate) => {
+                config.audio_encode.bitrate = bitrate.0;
+                config.audio_encode.save();
+                Task::none()
+            }
+            MainWindowEvent::AudioFrameDuration(duration) => {
+                config.audio_encode.frame_duration_ms = duration.0;
+                config.audio_encode.save();
+                Task::none()
+            }
+            MainWindowEvent::AudioStereoToggle => {
+                config.audio_encode.stereo = !config.audio_encode.stereo;
+                config.audio_encode.save();
+                Task::none()
+            }
+            MainWindowEvent::AudioVbrToggle => {
+                config.audio_encode.vbr = !config.audio_encode.vbr;
+                config.audio_encode.save();
+                Task::none()
+            }
+            MainWindowEvent::LatencyProfile(profile) => {
+                config.latency_profile = profile;
+                if let Some(receiver) = Self::receiver_mut(config) {
+                    receiver.set_latency_profile(profile);
+                }
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.set_latency_profile(profile);
+                }
+                Task::none()
+            }
             MainWindowEvent::PopupMessage(value) => {
                 self.popup_update(value, config);
                 Task::none()
             }
+            MainWindowEvent::PopupKey(key) => {
+                let message = match self.popup.key_action(key) {
+                    Some(Some(message)) => message,
+                    Some(None) if key == Named::Escape => MainWindowEvent::ClosePopup(None),
+                    _ => return Task::none(),
+                };
+                Task::done(AppEvent::WindowEvent(id, WindowMessage::Main(message)))
+            }
             MainWindowEvent::ClosePopup(page) => {
                 self.popup.hide();
+                // Chiuso il modal, i tasti tornano a essere scorciatoie
+                config.shortcuts.updating = KeyTypes::None;
                 if let Some(p) = page {
                     self.page = p;
                 }
@@ -229,6 +1119,307 @@ impl GuiWindow for MainWindow {
                 self.change_page(Page::Hotkeys);
                 Task::none()
             }
+            MainWindowEvent::SettingsPage => {
+                self.output_warning = Self::output_warning(&config.output);
+                self.output_devices = AudioPlayer::list_output_devices();
+                self.change_page(Page::Settings);
+                Task::none()
+            }
+            MainWindowEvent::OutputPickDirectory => {
+                if let Some(directory) = pick_directory(&config.output.directory) {
+                    config.output.directory = directory;
+                    config.output.save();
+                    self.output_warning = Self::output_warning(&config.output);
+                }
+                Task::none()
+            }
+            MainWindowEvent::OutputTemplate(template) => {
+                config.output.filename_template = template;
+                config.output.save();
+                Task::none()
+            }
+            MainWindowEvent::OutputSegmentMinutes(value) => {
+                if let Some(minutes) = Self::parse_limit(&value) {
+                    config.output.segment_minutes = minutes;
+                    config.output.save();
+                }
+                Task::none()
+            }
+            MainWindowEvent::OutputSegmentMegabytes(value) => {
+                if let Some(megabytes) = Self::parse_limit(&value) {
+                    config.output.segment_megabytes = megabytes;
+                    config.output.save();
+                }
+                Task::none()
+            }
+            MainWindowEvent::OutputCountdown(value) => {
+                if let Some(seconds) = Self::parse_limit(&value) {
+                    config.output.countdown_seconds = seconds.min(60);
+                    config.output.save();
+                }
+                Task::none()
+            }
+            MainWindowEvent::OutputResume(value) => {
+                if let Some(seconds) = Self::parse_limit(&value) {
+                    config.output.resume_seconds = seconds.min(600);
+                    config.output.save();
+                }
+                Task::none()
+            }
+            MainWindowEvent::StatsLogToggle => {
+                config.output.stats_log = !config.output.stats_log;
+                config.output.save();
+                Task::none()
+            }
+            MainWindowEvent::ClipboardShareToggle => {
+                config.clipboard_share = !config.clipboard_share;
+                Self::apply_clipboard_sharing(config);
+                Task::none()
+            }
+            MainWindowEvent::ClipboardAcceptToggle => {
+                config.clipboard_accept = !config.clipboard_accept;
+                Self::apply_clipboard_sharing(config);
+                Task::none()
+            }
+            MainWindowEvent::CloseToTrayToggle => {
+                config.general.close_to_tray = !config.general.close_to_tray;
+                config.general.save();
+                Task::none()
+            }
+            MainWindowEvent::LogLevel(level) => {
+                config.logging.level = level;
+                config.logging.apply();
+                config.logging.save();
+                Task::none()
+            }
+            MainWindowEvent::LogToFileToggle => {
+                config.logging.to_file = !config.logging.to_file;
+                config.logging.apply();
+                config.logging.save();
+                Task::none()
+            }
+            MainWindowEvent::OpenLogFolder => {
+                if let Some(dir) = log_dir() {
+                    return Task::done(AppEvent::OpenWebPage(dir.to_string_lossy().to_string()));
+                }
+                self.toast = Some((String::from("No log folder available"), Instant::now()));
+                Task::none()
+            }
+            MainWindowEvent::StatsLogFormat(format) => {
+                config.output.stats_format = format;
+                config.output.save();
+                Task::none()
+            }
+            MainWindowEvent::StatsLogPickDirectory => {
+                if let Some(directory) = pick_directory(config.output.stats_directory()) {
+                    config.output.stats_directory = directory;
+                    config.output.save();
+                }
+                Task::none()
+            }
+            MainWindowEvent::OutputReset => {
+                config.output = OutputSettings::default();
+                config.output.save();
+                self.output_warning = Self::output_warning(&config.output);
+                Task::none()
+            }
+            MainWindowEvent::PlaybackDevice(device) => {
+                if let Some(receiver) = Self::receiver_mut(config) {
+                    receiver.set_output_device(device.clone());
+                }
+                config.playback.output_device = device;
+                config.playback.save();
+                Task::none()
+            }
+            MainWindowEvent::PlaybackDisplayPolicy(policy) => {
+                if let Some(receiver) = Self::receiver_mut(config) {
+                    receiver.set_display_policy(policy);
+                }
+                config.playback.display_policy = policy;
+                config.playback.save();
+                Task::none()
+            }
+            MainWindowEvent::PlaybackSmoothMotionToggle => {
+                let smooth = !config.playback.smooth_motion;
+                if let Some(receiver) = Self::receiver_mut(config) {
+                    receiver.set_smooth_motion(smooth);
+                }
+                config.playback.smooth_motion = smooth;
+                config.playback.save();
+                Task::none()
+            }
+            MainWindowEvent::PlaybackColorSpace(color) => {
+                self.video.set_color_space(color);
+                config.playback.color = color;
+                config.playback.save();
+                Task::none()
+            }
+            MainWindowEvent::PlaybackFitToggle => {
+                let fit = config.playback.fit.toggled();
+                self.video.set_fit(fit);
+                config.playback.fit = fit;
+                config.playback.save();
+                Task::none()
+            }
+            MainWindowEvent::PlaybackAvOffset(offset_ms) => {
+                if let Some(receiver) = Self::receiver_mut(config) {
+                    receiver.set_av_offset_ms(offset_ms);
+                }
+                config.playback.av_offset_ms = offset_ms;
+                Task::none()
+            }
+            MainWindowEvent::PlaybackAvOffsetSave => {
+                config.playback.save();
+                Task::none()
+            }
+            MainWindowEvent::PlaybackVolume(percent) => {
+                if let Some(receiver) = Self::receiver_mut(config) {
+                    receiver.set_volume_percent(percent);
+                }
+                config.playback.volume_percent = percent;
+                Task::none()
+            }
+            MainWindowEvent::PlaybackVolumeSave => {
+                config.playback.save();
+                Task::none()
+            }
+            MainWindowEvent::PlaybackMaxLatency(value) => {
+                if let Some(max_ms) = Self::parse_limit(&value) {
+                    if let Some(receiver) = Self::receiver_mut(config) {
+                        receiver.set_max_latency_ms(max_ms);
+                    }
+                    config.playback.max_latency_ms = max_ms;
+                    config.playback.save();
+                }
+                Task::none()
+            }
+            MainWindowEvent::PlaybackConnectTimeout(value) => {
+                if let Some(secs) = Self::parse_limit(&value) {
+                    if let Some(receiver) = Self::receiver_mut(config) {
+                        receiver.set_connect_timeout_secs(secs);
+                    }
+                    config.playback.connect_timeout_secs = secs;
+                    config.playback.save();
+                }
+                Task::none()
+            }
+            MainWindowEvent::WatermarkPickFile => {
+                if let Some(path) = pick_watermark(&config.watermark.path) {
+                    config.watermark.path = path;
+                    config.watermark.save();
+                    self.apply_watermark(config);
+                }
+                Task::none()
+            }
+            MainWindowEvent::WatermarkClear => {
+                config.watermark.path.clear();
+                config.watermark.save();
+                self.apply_watermark(config);
+                Task::none()
+            }
+            MainWindowEvent::WatermarkCorner(corner) => {
+                config.watermark.corner = corner;
+                config.watermark.save();
+                self.apply_watermark(config);
+                Task::none()
+            }
+            MainWindowEvent::WatermarkOpacity(value) => {
+                if let Some(opacity) = Self::parse_limit(&value) {
+                    config.watermark.opacity = opacity.min(100);
+                    config.watermark.save();
+                    self.apply_watermark(config);
+                }
+                Task::none()
+            }
+            MainWindowEvent::WatermarkScale(value) => {
+                if let Some(scale) = Self::parse_limit(&value) {
+                    config.watermark.scale = scale.min(400);
+                    config.watermark.save();
+                    self.apply_watermark(config);
+                }
+                Task::none()
+            }
+            MainWindowEvent::TimestampToggle => {
+                config.timestamp.enabled = !config.timestamp.enabled;
+                config.timestamp.save();
+                Self::apply_timestamp(config);
+                Task::none()
+            }
+            MainWindowEvent::TimestampCorner(corner) => {
+                config.timestamp.corner = corner;
+                config.timestamp.save();
+                Self::apply_timestamp(config);
+                Task::none()
+            }
+            MainWindowEvent::TimestampFormat(format) => {
+                config.timestamp.format = format;
+                config.timestamp.save();
+                Self::apply_timestamp(config);
+                Task::none()
+            }
+            MainWindowEvent::TimestampLabel(label) => {
+                config.timestamp.label = label;
+                config.timestamp.save();
+                Self::apply_timestamp(config);
+                Task::none()
+            }
+            MainWindowEvent::TimestampOpacity(value) => {
+                if let Some(opacity) = Self::parse_limit(&value) {
+                    config.timestamp.opacity = opacity.min(100);
+                    config.timestamp.save();
+                    Self::apply_timestamp(config);
+                }
+                Task::none()
+            }
+            MainWindowEvent::HotkeysReset => {
+                config.shortcuts.reset();
+                Task::done(AppEvent::WindowEvent(
+                    id,
+                    WindowMessage::Main(MainWindowEvent::ShowToast(String::from(
+                        "Shortcuts restored to defaults",
+                    ))),
+                ))
+            }
+            MainWindowEvent::Screenshot => {
+                let snapshot = match config.mode {
+                    Some(Mode::Receiver(_)) => Some(self.video.snapshot()),
+                    _ => None,
+                };
+                let message = match snapshot {
+                    None => String::from("Screenshots are available while receiving"),
+                    Some(None) => String::from("No frame to capture yet"),
+                    Some(Some(frame)) => {
+                        let monitor = format!("{}x{}", frame.width, frame.height);
+                        let path = config.output.file_path("screenshot", &monitor, "png");
+                        let color = config.playback.color;
+                        match snapshot::save_png(&frame, color, &path) {
+                            Ok(()) => format!("Screenshot saved to {}", shorten_path(path)),
+                            Err(e) => {
+                                log::error!("Screenshot failed: {:#}", e);
+                                String::from("Screenshot failed")
+                            }
+                        }
+                    }
+                };
+                Task::done(AppEvent::WindowEvent(
+                    id,
+                    WindowMessage::Main(MainWindowEvent::ShowToast(message)),
+                ))
+            }
+            MainWindowEvent::ChapterMark => {
+                let mark = match &mut config.mode {
+                    Some(Mode::Receiver(receiver)) => receiver.mark_recording(),
+                    _ => None,
+                };
+                let message = match mark {
+                    Some(n) => format!("Chapter {} marked", n),
+                    None => String::from("Chapter marks are available while recording"),
+                };
+                Task::done(AppEvent::WindowEvent(
+                    id,
+                    WindowMessage::Main(MainWindowEvent::ShowToast(message)),
+                ))
+            }
             MainWindowEvent::HotkeysTypePage(key) => {
                 config.shortcuts.updating = key;
                 self.popup
@@ -237,37 +1428,114 @@ impl GuiWindow for MainWindow {
                 Task::none()
             }
             MainWindowEvent::ConnectToCaster(caster_ip) => {
-                self.popup.hide();
-                self.change_page(Page::Client);
-
                 if caster_ip != "auto" {
-                    match Self::parse_caster_addr(&caster_ip) {
+                    match parse_caster_addr(&caster_ip) {
                         Ok(caster_socket_addr) => {
                             let Some(client) = Self::receiver_mut(config) else {
                                 return Task::none();
                             };
-                            client.set_caster_addr(caster_socket_addr)
+                            client.set_caster_addr(caster_socket_addr);
+                            config.recent_casters.remember(&caster_ip);
+                            config.recent_casters.save();
                         }
-                        Err(_) => {
-                            self.popup_update(AnyRef::new(String::new()), config);
+                        Err(e) => {
+                            // Il popup resta aperto con l'errore sotto il campo
+                            self.popup_update(AnyRef::new(InvalidAddress(e.to_string())), config);
                             return Task::none();
                         }
                     }
                 }
 
+                self.popup.hide();
+                self.change_page(Page::Client);
+                self.video.set_color_space(config.playback.color);
+                self.video.set_fit(config.playback.fit);
+
                 let Some(client) = Self::receiver_mut(config) else {
                     return Task::none();
                 };
                 self.attach_video_stream(client);
                 Task::none()
             }
-            MainWindowEvent::SaveCapture => {
-                let Some(client) = Self::receiver_mut(config) else {
+            MainWindowEvent::ConnectFailed(error) => {
+                let addr = Self::receiver_mut(config)
+                    .and_then(|receiver| receiver.caster_addr())
+                    .map(|addr| addr.to_string())
+                    .unwrap_or_default();
+                // Il receiver fallito non si riusa: se ne prepara uno nuovo
+                config.reset_mode();
+                Self::start_receiver(config);
+                self.change_page(Page::Home);
+                self.popup.set(PopupType::IP(IPModal::new()));
+                self.popup_update(AnyRef::new(addr), config);
+                self.popup_update(AnyRef::new(InvalidAddress(error)), config);
+                self.popup.show();
+                self.popup.focus()
+            }
+            MainWindowEvent::ClearRecentCasters => {
+                config.recent_casters.clear();
+                config.recent_casters.save();
+                Task::none()
+            }
+            MainWindowEvent::OpenConnectionLink(link) => {
+                let addr = match parse_connection_link(&link) {
+                    Ok(parsed) => parsed.addr,
+                    Err(e) => {
+                        log::warn!("Ignoring connection link: {}", e);
+                        return Task::none();
+                    }
+                };
+                Task::done(AppEvent::WindowEvent(
+                    id,
+                    WindowMessage::Main(MainWindowEvent::OpenCasterAddress(addr)),
+                ))
+            }
+            MainWindowEvent::OpenCasterAddress(addr) => {
+                match &config.mode {
+                    // Non si interrompe una sessione già avviata
+                    Some(Mode::Caster(_)) => return Task::none(),
+                    Some(Mode::Receiver(_)) if self.page == Page::Client => return Task::none(),
+                    Some(Mode::Receiver(_)) => {}
+                    None => Self::start_receiver(config),
+                }
+
+                // Campo precompilato: se la connessione fallisce l'errore resta visibile
+                self.popup.set(PopupType::IP(IPModal::new()));
+                self.popup_update(AnyRef::new(addr.clone()), config);
+                self.popup.show();
+                Task::done(AppEvent::WindowEvent(
+                    id,
+                    WindowMessage::Main(MainWindowEvent::ConnectToCaster(addr)),
+                ))
+            }
+            MainWindowEvent::CopyConnectionLink => {
+                let ip = config
+                    .local_ip
+                    .or(*config.public_ip.as_ref())
+                    .map(IpAddr::V4);
+                let (Some(ip), Some(caster)) = (ip, Self::caster_mut(config)) else {
                     return Task::none();
                 };
-                let saving_path = saving_path();
-                client.save_stream(saving_path);
-                Task::none()
+                let link = connection_link(
+                    SocketAddr::new(ip, CAST_SERVICE_PORT),
+                    caster.instance_name(),
+                );
+                Task::done(AppEvent::WindowEvent(
+                    id,
+                    WindowMessage::Main(MainWindowEvent::CopyToClipboard(link)),
+                ))
+            }
+            MainWindowEvent::SaveCapture => {
+                if let Some(cancelled) = self.cancel_countdown(id) {
+                    return cancelled;
+                }
+                let seconds = config.output.countdown_seconds;
+                match &config.mode {
+                    Some(Mode::Receiver(client)) if seconds > 0 && !client.is_saving() => {
+                        self.start_countdown(id, CountdownAction::Recording, seconds)
+                    }
+                    _ => Self::start_recording(id, config),
+                }
             }
             MainWindowEvent::SaveCaptureStop => {
                 let Some(client) = Self::receiver_mut(config) else {
@@ -284,15 +1552,78 @@ impl GuiWindow for MainWindow {
                 }
                 Task::none()
             }
-            MainWindowEvent::ShowAnnotationWindow => Task::done(AppEvent::OpenAnnotationWindow),
+            MainWindowEvent::RunSelfTest => {
+                if self.selftest_running {
+                    return Task::none();
+                }
+                self.selftest_running = true;
+                let selftest = tokio::task::spawn_blocking(|| {
+                    SelfTestReport::run(SELFTEST_FRAMES, SELFTEST_SIZE).to_string()
+                });
+                Task::perform(selftest, move |report| {
+                    let report = report.unwrap_or_else(|e| format!("Self-test failed: {}", e));
+                    AppEvent::WindowEvent(
+                        id,
+                        WindowMessage::Main(MainWindowEvent::SelfTestDone(report)),
+                    )
+                })
+            }
+            MainWindowEvent::CopyDiagnostics => {
+                let report = Diagnostics::collect(config.mode.as_ref(), self.selftest.as_deref());
+                let copied = Clipboard::new().and_then(|mut c| c.set_text(report.to_string()));
+                let message = match copied {
+                    Ok(()) => String::from("Diagnostics copied to the clipboard"),
+                    Err(e) => format!("Unable to copy the diagnostics: {}", e),
+                };
+                self.toast = Some((message, Instant::now()));
+                Task::none()
+            }
+            MainWindowEvent::SelfTestDone(report) => {
+                log::info!("Self-test:\n{}", report);
+                self.selftest_running = false;
+                self.selftest = Some(report);
+                Task::none()
+            }
+            MainWindowEvent::ToggleAnnotationWindow => Task::done(AppEvent::ToggleAnnotationWindow),
             MainWindowEvent::OpenWebPage(s) => Task::done(AppEvent::OpenWebPage(s)),
             MainWindowEvent::AreaSelection => Task::done(AppEvent::OpenAreaSelectionWindow),
             MainWindowEvent::AreaSelectedFullScreen => {
                 Task::done(AppEvent::AreaSelected(ScreenRect::default()))
             }
+            MainWindowEvent::AreaFollowWindow => {
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.follow_active_window();
+                }
+                Task::none()
+            }
             MainWindowEvent::ExitApp => Task::done(AppEvent::ExitApp),
             MainWindowEvent::ThemeUpdate(theme) => {
+                save_pinned_theme(Some(&theme));
                 self.theme = theme;
+                self.theme_pinned = true;
+                Task::none()
+            }
+            MainWindowEvent::ThemeFollowSystem => {
+                save_pinned_theme(None);
+                self.theme_pinned = false;
+                Task::done(AppEvent::SystemThemeTick)
+            }
+            MainWindowEvent::SystemTheme(dark) => {
+                if !self.theme_pinned {
+                    self.theme = system_theme(dark);
+                }
+                Task::none()
+            }
+            MainWindowEvent::AccentPicker => {
+                self.popup
+                    .set(PopupType::Accent(AccentModal::new(self.accent)));
+                self.popup.show();
+                self.popup.focus()
+            }
+            MainWindowEvent::AccentColor(accent) => {
+                self.accent = accent;
+                save_accent(accent);
+                self.popup.hide();
                 Task::none()
             }
             MainWindowEvent::Ignore => Task::none(),
@@ -303,20 +1634,111 @@ impl GuiWindow for MainWindow {
                 Task::none()
             }
             MainWindowEvent::ToggleAudioMute => Task::done(AppEvent::ToggleAudioMute),
+            MainWindowEvent::ToggleKeycast => Task::done(AppEvent::ToggleKeycast),
+            MainWindowEvent::ToggleCursorCapture => Task::done(AppEvent::ToggleCursorCapture),
+            MainWindowEvent::ShowToast(message) => {
+                self.toast = Some((message, Instant::now()));
+                Task::none()
+            }
+            MainWindowEvent::ChatInput(text) => {
+                self.chat_draft = text.chars().take(MAX_CHAT_LEN).collect();
+                Task::none()
+            }
+            MainWindowEvent::ChatSend => {
+                match &config.mode {
+                    Some(Mode::Caster(caster)) => caster.send_chat(&self.chat_draft),
+                    Some(Mode::Receiver(receiver)) => receiver.send_chat(&self.chat_draft),
+                    None => return Task::none(),
+                }
+                self.chat_draft.clear();
+                Task::none()
+            }
+            MainWindowEvent::ToggleChat => {
+                self.chat_open = !self.chat_open;
+                Task::none()
+            }
+            #[cfg(feature = "remote-control")]
+            MainWindowEvent::RemoteInput(input) => {
+                if let Some(receiver) = Self::receiver_mut(config) {
+                    receiver.send_input(input);
+                }
+                Task::none()
+            }
+            #[cfg(feature = "remote-control")]
+            MainWindowEvent::ToggleRemoteControl => {
+                if let Some(receiver) = Self::receiver_mut(config) {
+                    receiver.toggle_remote_control();
+                }
+                Task::none()
+            }
+            #[cfg(feature = "remote-control")]
+            MainWindowEvent::CasterAllowControl => {
+                if let Some(caster) = Self::caster_mut(config) {
+                    caster.toggle_remote_control();
+                }
+                Task::none()
+            }
         }
     }
 
     fn view(&self, config: &Config) -> Element<'_, MainWindowEvent> {
         let body = match self.page {
             Page::Home => initial_page(self, config),
-            Page::Caster => caster_page(config),
-            Page::Client => client_page(&self.video, config),
+            Page::Caster => caster_page(config, &self.display_thumbnails, &self.chat_draft),
+            Page::Client => {
+                let chat = self.chat_open.then_some(self.chat_draft.as_str());
+                client_page(&self.video, config, chat)
+            }
             Page::Hotkeys => hotkeys(),
-            Page::Info => info_page(),
+            Page::Settings => settings_page(
+                config,
+                self.theme_choice(),
+                self.output_warning.as_deref(),
+                self.watermark_warning.as_deref(),
+                &self.output_devices,
+                &self.exclude_draft,
+                &self.webhook_draft,
+            ),
+            Page::Info => info_page(self.selftest.as_deref(), self.selftest_running),
         };
 
         let mut content = Column::new().push(body).push(footer());
 
+        // Il toast scompare al primo ridisegno dopo TOAST_DURATION (TimeTick)
+        if let Some((message, shown_at)) = &self.toast
+            && shown_at.elapsed() < TOAST_DURATION
+        {
+            let toast = Container::new(Text::new(message.clone()).size(14))
+                .padding([8, 16])
+                .class(ContainerType::Modal);
+            content = Column::new().push(
+                Stack::new().push(content).push(
+                    Container::new(toast)
+                        .width(Length::Fill)
+                        .height(Length::Fill)
+                        .align_x(Alignment::Center)
+                        .align_y(Alignment::End)
+                        .padding(60),
+                ),
+            );
+        }
+
+        // Fino allo zero nessun frame viene inviato o registrato
+        if let Some(countdown) = &self.countdown {
+            let number = Container::new(
+                Text::new(countdown.remaining.to_string())
+                    .font(FONT_FAMILY_BOLD)
+                    .size(96),
+            )
+            .padding([8, 40])
+            .class(ContainerType::Modal);
+            content = Column::new().push(
+                Stack::new()
+                    .push(content)
+                    .push(Container::new(number).center(Length::Fill)),
+            );
+        }
+
         if self.popup.is_visible() {
             let darkened_background = Container::new(Space::new())
                 .width(Length::Fill)
@@ -334,6 +1756,6 @@ impl GuiWindow for MainWindow {
     }
 
     fn theme(&self) -> StyleType {
-        self.theme.clone()
+        self.theme.with_accent(self.accent)
     }
 }
@@ -8,4 +8,4 @@ pub mod area_selector;
 pub mod main;
 mod manager;
 
-pub use manager::{GuiWindow, WindowMessage, WindowType, Windows};
+pub use manager::{GuiWindow, WindowManager, WindowMessage, WindowType, Windows};
@@ -1,10 +1,10 @@
 #![cfg_attr(all(target_os = "windows", not(debug_assertions)), windows_subsystem = "windows")]
 
-use crate::config::{app_id, app_name, app_version};
+use crate::config::{LogSettings, app_name, app_version};
 use crate::utils::flags::Flags;
+use crate::utils::ipc::{InstanceMessage, send_to_running};
+use crate::utils::logging::{self, LogLevel};
 use clap::{Arg, Command};
-use interprocess::local_socket::traits::Stream;
-use interprocess::local_socket::{GenericNamespaced, ToNsName};
 use std::{panic, process};
 
 pub mod assets;
@@ -20,8 +20,6 @@ pub mod workers;
 pub mod xmacro;
 
 fn main() {
-    tracing_subscriber::fmt::init();
-
     let app_name = Box::leak(app_name().into_boxed_str());
 
     let matches = Command::new(&*app_name)
@@ -40,20 +38,82 @@ fn main() {
                 .num_args(0..=1)
                 .default_value("no"),
         )
+        .arg(
+            Arg::new("selftest")
+                .long("selftest")
+                .value_name("FILE")
+                .help("Probe encoders, audio and displays, then print or save the summary.")
+                .num_args(0..=1)
+                .default_missing_value("-"),
+        )
+        .arg(
+            Arg::new("log-level")
+                .long("log-level")
+                .value_name("LEVEL")
+                .help("Log verbosity for this run (error, warn, info, debug, trace).")
+                .value_parser(|s: &str| s.parse::<LogLevel>().map_err(|e| e.to_string()))
+                .required(false),
+        )
+        .arg(
+            Arg::new("connect")
+                .long("connect")
+                .value_name("ADDRESS")
+                .help("Connect to the caster at ADDRESS (ip, ip:port or hostname).")
+                .required(false),
+        )
+        .arg(
+            Arg::new("link")
+                .value_name("LINK")
+                .help("castify:// connection link to open.")
+                .required(false),
+        )
         .get_matches();
 
+    // Il livello da riga di comando vale solo per questa esecuzione
+    let log_settings = LogSettings::load();
+    let log_level = matches
+        .get_one::<LogLevel>("log-level")
+        .copied()
+        .unwrap_or(log_settings.level);
+    logging::init(log_level, log_settings.to_file);
+
+    if let Some(target) = matches.get_one::<String>("selftest") {
+        let runtime = tokio::runtime::Runtime::new().expect("Unable to start the runtime");
+        let _guard = runtime.enter();
+        let report = utils::selftest::SelfTestReport::run(
+            utils::selftest::SELFTEST_FRAMES,
+            utils::selftest::SELFTEST_SIZE,
+        );
+        if target == "-" {
+            utils::selftest::attach_console();
+            println!("{}", report);
+        } else if let Err(e) = std::fs::write(target, format!("{}\n", report)) {
+            log::error!("Failed to write the self-test report to {}: {}", target, e);
+            process::exit(1);
+        }
+        return;
+    }
+
     let multi_instances = match matches.get_one::<String>("multi-instance") {
         Some(val) => &val.to_lowercase() == "yes",
         None => false,
     };
 
+    let connection_link = matches.get_one::<String>("link").cloned();
+    let connect_addr = matches.get_one::<String>("connect").cloned();
+
     if !multi_instances {
-        let name = app_id().to_ns_name::<GenericNamespaced>().unwrap();
-        if interprocess::local_socket::Stream::connect(name).is_ok() {
+        // L'istanza già aperta riceve link e indirizzo e si collega lei
+        let messages =
+            InstanceMessage::from_args(connection_link.as_deref(), connect_addr.as_deref());
+        if send_to_running(&messages) {
+            log::info!("{} is already running: arguments handed over", app_name);
             return;
-        };
+        }
     }
 
+    std::thread::spawn(utils::url_scheme::register);
+
     // kill the main thread as soon as a secondary thread panics
     let orig_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
@@ -70,5 +130,7 @@ fn main() {
 
     gui::run(Flags {
         multi_instance: multi_instances,
+        connection_link,
+        connect_addr,
     });
 }
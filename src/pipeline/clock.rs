@@ -1,5 +1,6 @@
 //! Media clock for audio-video synchronization
 
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicI64, Ordering};
 use std::time::{Duration, Instant};
@@ -198,6 +199,95 @@ impl MediaClock {
     }
 }
 
+/// Caster clock time of the first sample sent on each track
+///
+/// RTP timestamps start from a random base on every track, so the receiver
+/// cannot line up audio and video from RTP alone. The caster announces the
+/// `MediaClock` time of the first sample of each track over signaling; the
+/// receiver binds it to the first RTP timestamp it sees on that track and
+/// places both media on the caster timeline instead of relying on arrival order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockAnchor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video_us: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_us: Option<i64>,
+}
+
+impl ClockAnchor {
+    pub fn video(pts: Timestamp) -> Self {
+        Self {
+            video_us: Some(pts.micros),
+            audio_us: None,
+        }
+    }
+
+    pub fn audio(pts: Timestamp) -> Self {
+        Self {
+            video_us: None,
+            audio_us: Some(pts.micros),
+        }
+    }
+
+    /// Fill in the tracks announced by `other`, the first announcement wins
+    pub fn merge(&mut self, other: ClockAnchor) {
+        self.video_us = self.video_us.or(other.video_us);
+        self.audio_us = self.audio_us.or(other.audio_us);
+    }
+
+    /// How much later the first audio sample was captured than the first
+    /// video sample, once both tracks have been announced
+    pub fn audio_offset_us(&self) -> Option<i64> {
+        Some(self.audio_us? - self.video_us?)
+    }
+}
+
+/// Maps encoder capture times onto the [`MediaClock`] timeline
+///
+/// Video PTS (and so the RTP timestamp) follow the capture timestamp the
+/// encoder was fed with, not the moment the encoded frame reaches the
+/// capturer: encode time jitter no longer leaks into the stream timing.
+/// The capture clock is anchored to the media clock on the first frame and
+/// re-anchored when it jumps (new capture session, different backend).
+#[derive(Debug, Clone, Default)]
+pub struct CaptureTimeline {
+    /// Media clock micros minus capture micros
+    offset_us: Option<i64>,
+    last_us: Option<i64>,
+}
+
+impl CaptureTimeline {
+    /// Distance from the media clock beyond which the capture clock is re-anchored
+    pub const RESYNC: Duration = Duration::from_secs(1);
+
+    /// PTS of a frame captured at `capture_us`, given the media clock `now`.
+    /// Always strictly increasing.
+    pub fn pts(&mut self, capture_us: i64, now: Timestamp) -> Timestamp {
+        let resync_us = Self::RESYNC.as_micros() as i64;
+        let mapped = self
+            .offset_us
+            .map(|offset| capture_us + offset)
+            .filter(|pts| (pts - now.micros).abs() < resync_us);
+        let pts = mapped.unwrap_or_else(|| {
+            self.offset_us = Some(now.micros - capture_us);
+            now.micros
+        });
+        let pts = match self.last_us {
+            Some(last) if pts <= last => last + 1,
+            _ => pts,
+        };
+        self.last_us = Some(pts);
+        Timestamp::from_micros(pts)
+    }
+
+    /// Same mapping for another layer of the same capture (simulcast),
+    /// without moving the anchor; `None` before the first frame
+    pub fn map(&self, capture_us: i64) -> Option<Timestamp> {
+        self.offset_us
+            .map(|offset| Timestamp::from_micros(capture_us + offset))
+    }
+}
+
 impl Default for MediaClock {
     fn default() -> Self {
         Self::new()
@@ -302,4 +392,73 @@ mod tests {
         clock.adjust_video_offset(Duration::from_millis(30), true);
         assert_eq!(clock.video_offset(), Duration::from_millis(120));
     }
+
+    #[test]
+    fn test_clock_anchor_merge() {
+        let mut anchor = ClockAnchor::video(Timestamp::from_micros(1_000));
+        assert_eq!(anchor.audio_offset_us(), None);
+
+        anchor.merge(ClockAnchor::audio(Timestamp::from_micros(41_000)));
+        // A repeated announcement does not move the anchor
+        anchor.merge(ClockAnchor::video(Timestamp::from_micros(9_000)));
+
+        assert_eq!(anchor.video_us, Some(1_000));
+        assert_eq!(anchor.audio_offset_us(), Some(40_000));
+    }
+
+    #[test]
+    fn test_clock_anchor_json_skips_unknown_tracks() {
+        let json = serde_json::to_string(&ClockAnchor::audio(Timestamp::from_micros(5))).unwrap();
+        assert_eq!(json, r#"{"audio_us":5}"#);
+        let parsed: ClockAnchor = serde_json::from_str("{}").unwrap();
+        assert_eq!(parsed, ClockAnchor::default());
+    }
+
+    #[test]
+    fn test_capture_timeline_follows_capture_times() {
+        let mut timeline = CaptureTimeline::default();
+        // WGC-like capture clock (uptime), media clock just started
+        let capture_base = 5_000_000_000i64;
+        assert_eq!(
+            timeline
+                .pts(capture_base, Timestamp::from_micros(2_000))
+                .micros,
+            2_000
+        );
+
+        // Frames arrive late and with jitter, PTS keep the capture spacing
+        let arrivals = [40_000, 80_000, 105_000, 150_000];
+        let mut last = 2_000;
+        for (n, arrival) in arrivals.into_iter().enumerate() {
+            let capture_us = capture_base + (n as i64 + 1) * 33_333;
+            let pts = timeline
+                .pts(capture_us, Timestamp::from_micros(arrival))
+                .micros;
+            assert_eq!(pts, 2_000 + (n as i64 + 1) * 33_333);
+            assert!(pts > last);
+            last = pts;
+        }
+        assert_eq!(
+            timeline.map(capture_base + 33_333),
+            Some(Timestamp::from_micros(35_333))
+        );
+    }
+
+    #[test]
+    fn test_capture_timeline_resyncs_and_stays_monotonic() {
+        let mut timeline = CaptureTimeline::default();
+        timeline.pts(1_000_000, Timestamp::from_micros(10_000));
+
+        // Capture restarted from zero: re-anchored on the media clock
+        let pts = timeline.pts(0, Timestamp::from_micros(500_000));
+        assert_eq!(pts.micros, 500_000);
+        assert_eq!(
+            timeline.pts(33_333, Timestamp::from_micros(540_000)).micros,
+            533_333
+        );
+
+        // Same capture time twice: never goes backwards or repeats
+        let pts = timeline.pts(33_333, Timestamp::from_micros(560_000));
+        assert_eq!(pts.micros, 533_334);
+    }
 }
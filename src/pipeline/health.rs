@@ -1,10 +1,56 @@
 //! Health monitoring and metrics for pipeline
 
+use crate::pipeline::state::{ConnectionState, ConnectionStatus};
+use std::fmt;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use tokio::sync::mpsc;
 
+/// Where a frame (or packet) was lost, to tell a slow encoder from a full
+/// network or an overloaded receiver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropSource {
+    /// Caster: the encoder output was not drained in time by the capturer
+    Capture,
+    /// Caster: the queue toward the WebRTC sender was full (network backpressure)
+    Send,
+    /// Receiver: RTP packets that never arrived, skipped by the jitter buffer
+    Reorder,
+    /// Receiver: frames late against the audio clock or superseded before display
+    Sync,
+    /// Receiver: decoded frames the display could not keep up with
+    Display,
+}
+
+impl DropSource {
+    pub const ALL: [DropSource; 5] = [
+        DropSource::Capture,
+        DropSource::Send,
+        DropSource::Reorder,
+        DropSource::Sync,
+        DropSource::Display,
+    ];
+
+    /// Reorder losses are RTP packets, not frames: they stay out of `frame_drops`
+    pub fn counts_packets(self) -> bool {
+        self == DropSource::Reorder
+    }
+}
+
+impl fmt::Display for DropSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            DropSource::Capture => "Capture (encoder behind)",
+            DropSource::Send => "Send (network full)",
+            DropSource::Reorder => "Reorder (packets lost)",
+            DropSource::Sync => "Sync (late frames)",
+            DropSource::Display => "Display (receiver behind)",
+        };
+        write!(f, "{}", label)
+    }
+}
+
 /// Health metrics for a pipeline
 ///
 /// Tracks various counters and timestamps to monitor pipeline health.
@@ -13,6 +59,9 @@ pub struct PipelineHealth {
     /// Number of frames dropped due to backpressure or errors
     pub frame_drops: AtomicU64,
 
+    /// Drops split by [`DropSource`], indexed by `source as usize`
+    pub drops_by_source: [AtomicU64; 5],
+
     /// Number of decode failures
     pub decode_failures: AtomicU64,
 
@@ -30,6 +79,15 @@ pub struct PipelineHealth {
 
     /// Number of keyframes processed
     pub keyframes_processed: AtomicU64,
+
+    /// Audio output ran out of samples
+    pub audio_underruns: AtomicU64,
+
+    /// Audio samples dropped for lack of room or to cap latency
+    pub audio_overruns: AtomicU64,
+
+    /// Buffers flushed by the max-latency guard
+    pub latency_resets: AtomicU64,
 }
 
 impl PipelineHealth {
@@ -41,18 +99,30 @@ impl PipelineHealth {
             .as_micros() as u64;
         Self {
             frame_drops: AtomicU64::new(0),
+            drops_by_source: Default::default(),
             decode_failures: AtomicU64::new(0),
             network_errors: AtomicU64::new(0),
             last_frame_time: AtomicU64::new(now_micros),
             frames_processed: AtomicU64::new(0),
             bytes_processed: AtomicU64::new(0),
             keyframes_processed: AtomicU64::new(0),
+            audio_underruns: AtomicU64::new(0),
+            audio_overruns: AtomicU64::new(0),
+            latency_resets: AtomicU64::new(0),
         }
     }
 
-    /// Record a dropped frame
-    pub fn record_frame_drop(&self) {
-        self.frame_drops.fetch_add(1, Ordering::Relaxed);
+    /// Record a frame dropped at `source`
+    pub fn record_frame_drop(&self, source: DropSource) {
+        self.record_frame_drops(source, 1);
+    }
+
+    /// Record `count` frames (or packets, for [`DropSource::Reorder`]) lost at `source`
+    pub fn record_frame_drops(&self, source: DropSource, count: u64) {
+        self.drops_by_source[source as usize].fetch_add(count, Ordering::Relaxed);
+        if !source.counts_packets() {
+            self.frame_drops.fetch_add(count, Ordering::Relaxed);
+        }
     }
 
     /// Record a decode failure
@@ -65,6 +135,21 @@ impl PipelineHealth {
         self.network_errors.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Record an audio buffer underrun
+    pub fn record_audio_underrun(&self) {
+        self.audio_underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record an audio buffer overrun
+    pub fn record_audio_overrun(&self) {
+        self.audio_overruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a flush by the max-latency guard
+    pub fn record_latency_reset(&self) {
+        self.latency_resets.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Record a successfully processed frame
     pub fn record_frame(&self, size: usize, is_keyframe: bool) {
         let now_micros = std::time::SystemTime::now()
@@ -85,6 +170,11 @@ impl PipelineHealth {
         self.frame_drops.load(Ordering::Relaxed)
     }
 
+    /// Get the number of drops recorded at `source`
+    pub fn drops(&self, source: DropSource) -> u64 {
+        self.drops_by_source[source as usize].load(Ordering::Relaxed)
+    }
+
     /// Get the number of decode failures
     pub fn decode_failures(&self) -> u64 {
         self.decode_failures.load(Ordering::Relaxed)
@@ -115,6 +205,21 @@ impl PipelineHealth {
         self.keyframes_processed.load(Ordering::Relaxed)
     }
 
+    /// Get the number of audio underruns
+    pub fn audio_underruns(&self) -> u64 {
+        self.audio_underruns.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of audio overruns
+    pub fn audio_overruns(&self) -> u64 {
+        self.audio_overruns.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of max-latency flushes
+    pub fn latency_resets(&self) -> u64 {
+        self.latency_resets.load(Ordering::Relaxed)
+    }
+
     /// Calculate the frame drop rate as a percentage
     pub fn frame_drop_rate(&self) -> f64 {
         let drops = self.frame_drops();
@@ -141,11 +246,15 @@ impl PipelineHealth {
         HealthSummary {
             frames_processed: self.frames_processed(),
             frame_drops: self.frame_drops(),
+            drops_by_source: DropSource::ALL.map(|source| self.drops(source)),
             decode_failures: self.decode_failures(),
             network_errors: self.network_errors(),
             bytes_processed: self.bytes_processed(),
             keyframes_processed: self.keyframes_processed(),
             frame_drop_rate: self.frame_drop_rate(),
+            audio_underruns: self.audio_underruns(),
+            audio_overruns: self.audio_overruns(),
+            latency_resets: self.latency_resets(),
         }
     }
 }
@@ -161,25 +270,53 @@ impl Default for PipelineHealth {
 pub struct HealthSummary {
     pub frames_processed: u64,
     pub frame_drops: u64,
+    /// Drops split by [`DropSource`], indexed by `source as usize`
+    pub drops_by_source: [u64; 5],
     pub decode_failures: u64,
     pub network_errors: u64,
     pub bytes_processed: u64,
     pub keyframes_processed: u64,
     pub frame_drop_rate: f64,
+    pub audio_underruns: u64,
+    pub audio_overruns: u64,
+    pub latency_resets: u64,
+}
+
+impl HealthSummary {
+    /// Source with the most drops, `None` while nothing was lost
+    pub fn bottleneck(&self) -> Option<DropSource> {
+        DropSource::ALL
+            .into_iter()
+            .filter(|&source| self.drops_by_source[source as usize] > 0)
+            .max_by_key(|&source| self.drops_by_source[source as usize])
+    }
+
+    /// One `label: count` pair per source, for logs and the diagnostics report
+    pub fn drop_breakdown(&self) -> String {
+        DropSource::ALL
+            .iter()
+            .map(|&source| format!("{}: {}", source, self.drops_by_source[source as usize]))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
 
 impl std::fmt::Display for HealthSummary {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Health: {} frames ({} drops, {:.2}%), {} decode failures, {} network errors, {} bytes, {} keyframes",
+            "Health: {} frames ({} drops, {:.2}%; {}), {} decode failures, {} network errors, {} bytes, {} keyframes, audio {} underruns / {} overruns, {} latency resets",
             self.frames_processed,
             self.frame_drops,
             self.frame_drop_rate,
+            self.drop_breakdown(),
             self.decode_failures,
             self.network_errors,
             self.bytes_processed,
-            self.keyframes_processed
+            self.keyframes_processed,
+            self.audio_underruns,
+            self.audio_overruns,
+            self.latency_resets
         )
     }
 }
@@ -228,6 +365,8 @@ pub struct HealthMonitor {
     check_interval: Duration,
     stall_threshold: Duration,
     drop_rate_threshold: f64,
+    /// Receiver connection state pushed to `Stalled` and back to `Playing`
+    connection: Option<ConnectionStatus>,
 }
 
 impl HealthMonitor {
@@ -239,6 +378,7 @@ impl HealthMonitor {
             check_interval: Duration::from_secs(5),
             stall_threshold: Duration::from_secs(5),
             drop_rate_threshold: 10.0, // 10% drop rate
+            connection: None,
         }
     }
 
@@ -260,6 +400,12 @@ impl HealthMonitor {
         self
     }
 
+    /// Drive the receiver connection state from stall detection
+    pub fn with_connection_status(mut self, connection: ConnectionStatus) -> Self {
+        self.connection = Some(connection);
+        self
+    }
+
     /// Run the health monitor (blocking loop)
     pub async fn run(&self) {
         let mut interval = tokio::time::interval(self.check_interval);
@@ -270,7 +416,8 @@ impl HealthMonitor {
             interval.tick().await;
 
             // Check for stall
-            if self.health.is_stalled(self.stall_threshold) {
+            let stalled = self.health.is_stalled(self.stall_threshold);
+            if stalled {
                 let _ = self
                     .alert_tx
                     .send(HealthAlert::Stalled {
@@ -278,6 +425,20 @@ impl HealthMonitor {
                     })
                     .await;
             }
+            // Audio-only streams never record video frames: nothing to stall
+            if let Some(connection) = &self.connection
+                && self.health.frames_processed() > 0
+            {
+                match connection.get() {
+                    ConnectionState::Playing if stalled => {
+                        connection.set(ConnectionState::Stalled);
+                    }
+                    ConnectionState::Stalled if !stalled => {
+                        connection.set(ConnectionState::Playing);
+                    }
+                    _ => {}
+                }
+            }
 
             // Check for high drop rate
             let drop_rate = self.health.frame_drop_rate();
@@ -334,13 +495,32 @@ mod tests {
         assert_eq!(health.frame_drops(), 0);
 
         // Record some drops
-        health.record_frame_drop();
-        health.record_frame_drop();
+        health.record_frame_drop(DropSource::Send);
+        health.record_frame_drop(DropSource::Sync);
 
         assert_eq!(health.frame_drops(), 2);
         assert!(health.frame_drop_rate() > 0.0);
     }
 
+    #[test]
+    fn test_drops_by_source() {
+        let health = PipelineHealth::new();
+        health.record_frame_drops(DropSource::Reorder, 7);
+        health.record_frame_drops(DropSource::Send, 3);
+        health.record_frame_drop(DropSource::Capture);
+
+        assert_eq!(health.drops(DropSource::Reorder), 7);
+        assert_eq!(health.drops(DropSource::Send), 3);
+        assert_eq!(health.drops(DropSource::Sync), 0);
+        // Lost packets are not dropped frames
+        assert_eq!(health.frame_drops(), 4);
+
+        let summary = health.summary();
+        assert_eq!(summary.bottleneck(), Some(DropSource::Reorder));
+        assert!(summary.drop_breakdown().contains("Send (network full): 3"));
+        assert_eq!(PipelineHealth::new().summary().bottleneck(), None);
+    }
+
     #[test]
     fn test_stall_detection() {
         let health = PipelineHealth::new();
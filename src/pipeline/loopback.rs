@@ -0,0 +1,271 @@
+//! Loopback: caster e receiver nello stesso processo, senza GUI né rete
+//!
+//! Una [`TestPatternCapture`] codifica barre colore note, i frame H.264 vengono
+//! pacchettizzati in RTP come farebbe la track WebRTC e passano dal
+//! [`ReceiverCoordinator`] (reorder → decode → sync) fino a un
+//! [`TripleBuffer`], dove i pixel decodificati vengono confrontati con il
+//! pattern. Serve a sviluppo e CI per coprire l'intero percorso
+//! encode → RTP → reorder → decode → sync.
+
+use crate::assets::FRAME_RATE;
+use crate::capture::synthetic::{TestPattern, TestPatternCapture};
+use crate::capture::{CaptureOpts, ScreenCapture, StreamProfile};
+use crate::decoder::{PixelLayout, VideoFrame};
+use crate::display::{FrameDelivery, TripleBuffer};
+use crate::encoder::{EncodedVideo, FfmpegEncoder};
+use crate::pipeline::nack::RetransmitCache;
+use crate::pipeline::receiver::latency_guard::DEFAULT_MAX_LATENCY_MS;
+use crate::pipeline::receiver::{LatencyGuard, ReceiverCoordinator};
+use crate::pipeline::recovery::LossRecovery;
+use crate::pipeline::sender::packetizer::RtpMtu;
+use crate::pipeline::tuning::PipelineTuning;
+use crate::pipeline::types::Timestamp;
+use crate::utils::net::webrtc::{VIDEO_CLOCK_RATE, VideoRtpClock};
+use anyhow::{Result, ensure};
+use rtc::rtp::codec::h264::H264Payloader;
+use rtc::rtp::packetizer::{Packetizer, new_packetizer};
+use rtc::rtp::sequence::new_fixed_sequencer;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+/// Sorgente usata con il profilo "Native" (non c'è uno schermo da cui leggerla)
+const NATIVE_SIZE: (u32, u32) = (640, 360);
+/// Differenza di luma tollerata rispetto al pattern (perdite del codec)
+const LUMA_TOLERANCE: u8 = 16;
+/// Attesa degli ultimi frame dopo lo stop della sorgente
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Esito di [`run_loopback`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoopbackReport {
+    /// Access unit H.264 prodotti dall'encoder
+    pub encoded_frames: u64,
+    /// Pacchetti RTP consegnati al receiver
+    pub packets: u64,
+    /// Frame arrivati al TripleBuffer
+    pub displayed_frames: u64,
+    /// Frame letti dal TripleBuffer che rispettano il pattern
+    pub pattern_frames: u64,
+    /// Generazione dell'ultimo frame letto dal TripleBuffer
+    pub last_generation: u64,
+}
+
+/// Trasmette per `duration` il pattern sintetico al receiver nello stesso
+/// processo e verifica che i frame decodificati escano dal `TripleBuffer`.
+///
+/// Fallisce se nessun frame con il pattern atteso raggiunge il buffer.
+/// Richiede un runtime tokio multi-thread e FFmpeg con un encoder H.264.
+pub async fn run_loopback(profile: StreamProfile, duration: Duration) -> Result<LoopbackReport> {
+    let (src_w, src_h) = if profile.is_native() {
+        NATIVE_SIZE
+    } else {
+        (profile.width, profile.height)
+    };
+
+    // ── Caster ───────────────────────────────────────────────────
+    let mut capture = TestPatternCapture::new(src_w, src_h).with_fps(profile.fps_cap());
+    let pattern = capture.pattern();
+    let (out_w, out_h) = profile.output_size(pattern.width, pattern.height);
+    let expected = TestPattern::new(out_w, out_h);
+
+    let (_opts_tx, opts_rx) = watch::channel(CaptureOpts {
+        blank_screen: false,
+        crop: None,
+        paused: false,
+        max_fps: FRAME_RATE,
+        fps_ceiling: profile.fps_cap(),
+        profile,
+        scale_to: None,
+        show_cursor: true,
+        cursor_highlight: None,
+        keycast: None,
+        zoom: None,
+        data_budget: None,
+        watermark: None,
+        timestamp: None,
+        content_aware: false,
+        privacy_exclude: Vec::new(),
+        hdr: Default::default(),
+        bitrate_mode: Default::default(),
+        frame_pool: PipelineTuning::default().frame_pool,
+        max_slice_size: None,
+    });
+    let (encoded_tx, mut encoded_rx) = mpsc::channel::<EncodedVideo>(16);
+    let encoder = FfmpegEncoder::new_scaled(pattern.width, pattern.height, out_w, out_h)?;
+    // Il decoder del receiver chiede un IDR direttamente all'encoder
+    let force_idr = Arc::clone(&encoder.force_idr);
+    capture.start_capture(encoder, encoded_tx, opts_rx).await?;
+
+    // ── "Rete": pacchettizzazione RTP ────────────────────────────
+    let (raw_video_tx, raw_video_rx) = mpsc::channel::<(Vec<u8>, bool, u16, u32)>(1024);
+    // Richieste NACK su un canale a parte, come farebbero i feedback RTCP
+    let recovery = LossRecovery::from_env();
+    let (nack_tx, mut nack_rx) = mpsc::channel::<u16>(256);
+    let first_frame = Duration::from_secs(1) / profile.fps_cap();
+    let packetizer = tokio::spawn(async move {
+        // Stesso packetizer e stessa timeline di `TrackLocalStaticSample::write_sample`
+        let mut rtp = new_packetizer(
+            RtpMtu::default().bytes(),
+            96,
+            1,
+            Box::new(H264Payloader::default()),
+            Box::new(new_fixed_sequencer(0)),
+            VIDEO_CLOCK_RATE as u32,
+        );
+        let mut rtp_clock = VideoRtpClock::new();
+        let (mut frames, mut packets) = (0u64, 0u64);
+        let mut cache = (recovery == LossRecovery::Nack).then(RetransmitCache::default);
+
+        loop {
+            tokio::select! {
+                au = encoded_rx.recv() => {
+                    let Some(au) = au else {
+                        break;
+                    };
+                    frames += 1;
+                    // La durata del sample fa avanzare il timestamp RTP, come sulla track
+                    let duration = rtp_clock
+                        .sample_duration(Timestamp::from_micros(au.capture_us), first_frame);
+                    let samples = (duration.as_secs_f64() * VIDEO_CLOCK_RATE as f64) as u32;
+                    let rtp_packets = match rtp.packetize(&au.data, samples) {
+                        Ok(rtp_packets) => rtp_packets,
+                        Err(e) => {
+                            log::warn!("Loopback: RTP packetization failed: {}", e);
+                            continue;
+                        }
+                    };
+                    for packet in rtp_packets {
+                        let (seq, timestamp, marker) = (
+                            packet.header.sequence_number,
+                            packet.header.timestamp,
+                            packet.header.marker,
+                        );
+                        let payload = packet.payload.to_vec();
+                        if let Some(cache) = &mut cache {
+                            cache.push(seq, &payload, marker, timestamp);
+                        }
+                        if raw_video_tx.send((payload, marker, seq, timestamp)).await.is_err() {
+                            return (frames, packets);
+                        }
+                        packets += 1;
+                    }
+                }
+                Some(lost) = nack_rx.recv() => {
+                    // Ritrasmissione con il numero di sequenza originale
+                    if let Some((payload, marker, timestamp)) =
+                        cache.as_mut().and_then(|cache| cache.get(lost))
+                        && raw_video_tx.send((payload, marker, lost, timestamp)).await.is_err()
+                    {
+                        return (frames, packets);
+                    }
+                }
+            }
+        }
+        (frames, packets)
+    });
+
+    // ── Receiver ─────────────────────────────────────────────────
+    let mut coordinator = ReceiverCoordinator::new()
+        .with_keyframe_request(force_idr)
+        .with_latency_guard(LatencyGuard::new(DEFAULT_MAX_LATENCY_MS));
+    if recovery == LossRecovery::Nack {
+        coordinator = coordinator.with_nack(nack_tx);
+    }
+    let (_audio_tx, audio_rx) = mpsc::channel::<Vec<u8>>(1);
+    let (mut video_rx, _save_tx) =
+        coordinator.launch_pipeline(raw_video_rx, audio_rx, Arc::new(AtomicBool::new(true)));
+
+    let buffer = TripleBuffer::new(|| VideoFrame {
+        data: Vec::new(),
+        width: 0,
+        height: 0,
+        layout: PixelLayout::I420,
+    });
+    let mut delivery = FrameDelivery::new();
+    let mut report = LoopbackReport::default();
+
+    let mut present = |frame: VideoFrame, report: &mut LoopbackReport| {
+        *buffer.write() = frame;
+        report.displayed_frames += 1;
+
+        let read = buffer.read();
+        delivery.observe(read.generation());
+        report.last_generation = delivery.last_generation();
+        if expected.matches_luma(&read.data, read.width, read.height, LUMA_TOLERANCE) {
+            report.pattern_frames += 1;
+        }
+    };
+
+    let deadline = tokio::time::Instant::now() + duration;
+    while let Ok(Some(frame)) = tokio::time::timeout_at(deadline, video_rx.recv()).await {
+        present(frame, &mut report);
+    }
+
+    capture.stop_capture().await?;
+    let (frames, packets) = packetizer.await?;
+    report.encoded_frames = frames;
+    report.packets = packets;
+
+    // Ultimi frame ancora in reorder/decode/sync
+    while let Ok(Some(frame)) = tokio::time::timeout(DRAIN_TIMEOUT, video_rx.recv()).await {
+        present(frame, &mut report);
+    }
+    coordinator.stop();
+
+    ensure!(
+        report.pattern_frames > 0,
+        "No frame matching the test pattern reached the display buffer ({:?})",
+        report
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_render() {
+        let pattern = TestPattern::new(127, 72);
+        assert_eq!((pattern.width, pattern.height), (128, 72));
+        assert_eq!(pattern.bar_at(127), 7);
+
+        let frame = pattern.render(0);
+        assert_eq!(frame.luminance_bytes.len(), 128 * 72);
+        assert_eq!(frame.chrominance_bytes.len(), 128 * 36);
+        // Il piano Y di un NV12 è anche quello di un YUV420p
+        assert!(pattern.matches_luma(&frame.luminance_bytes, 128, 72, 0));
+
+        let mut yuv = frame.luminance_bytes.clone();
+        yuv[18 * 128 + pattern.bar_center(3) as usize] = 0;
+        assert!(!pattern.matches_luma(&yuv, 128, 72, LUMA_TOLERANCE));
+    }
+
+    #[test]
+    fn test_pattern_box_moves() {
+        let pattern = TestPattern::new(128, 72);
+        let (x0, y0) = pattern.box_position(0);
+        let (x1, y1) = pattern.box_position(1);
+        assert_eq!((x1 - x0, y0), (4, y1));
+        assert!(y0 >= 36);
+
+        // Il riquadro cambia il frame, le barre no
+        let (a, b) = (pattern.render(0), pattern.render(1));
+        assert_ne!(a.luminance_bytes, b.luminance_bytes);
+        assert_eq!(a.luminance_bytes[..36 * 128], b.luminance_bytes[..36 * 128]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_loopback_end_to_end() {
+        let report = run_loopback(StreamProfile::new(320, 180, 30), Duration::from_secs(2))
+            .await
+            .unwrap();
+
+        assert!(report.encoded_frames > 0);
+        assert!(report.packets >= report.encoded_frames);
+        assert!(report.pattern_frames > 0);
+        assert_eq!(report.last_generation, report.displayed_frames);
+    }
+}
@@ -0,0 +1,216 @@
+//! Per-stage latency metrics for the receiver pipeline
+//!
+//! Every receiver stage records how long it spent on an item and how deep its
+//! input queue was; the debug overlay reads a snapshot to show where latency
+//! accumulates. Averages are exponential moving averages, so readers never
+//! need to reset anything.
+
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Receiver stages tracked by [`StageMetrics`], in pipeline order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Jitter buffer / packet reordering
+    Reorder,
+    /// Depacketize + H.264 decode
+    Decode,
+    /// A/V sync queue
+    Sync,
+    /// Frames waiting for the GUI
+    Display,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 4] = [Stage::Reorder, Stage::Decode, Stage::Sync, Stage::Display];
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stage::Reorder => "Reorder",
+            Stage::Decode => "Decode",
+            Stage::Sync => "Sync",
+            Stage::Display => "Display",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Weight of the newest sample in the moving averages (1/8)
+const EWMA_SHIFT: u32 = 3;
+
+#[derive(Default)]
+struct StageSlot {
+    avg_us: AtomicU64,
+    queue_depth: AtomicU64,
+    samples: AtomicU64,
+}
+
+/// Shared per-stage timing and queue depth, written by the stages
+///
+/// All counters use atomic operations, like [`PipelineHealth`](super::PipelineHealth).
+#[derive(Default)]
+pub struct StageMetrics {
+    stages: [StageSlot; 4],
+    /// Estimated glass-to-glass latency (moving average, microseconds)
+    glass_to_glass_us: AtomicU64,
+    glass_to_glass_samples: AtomicU64,
+    /// Decoder in use (set on creation and on fallback, rarely written)
+    decoder: Mutex<Option<&'static str>>,
+}
+
+fn ewma(slot: &AtomicU64, first: bool, sample: u64) {
+    let _ = slot.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |avg| {
+        Some(if first {
+            sample
+        } else {
+            avg - (avg >> EWMA_SHIFT) + (sample >> EWMA_SHIFT)
+        })
+    });
+}
+
+impl StageMetrics {
+    /// Create an empty metrics instance
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the time a stage spent on one item and its current queue depth
+    pub fn record(&self, stage: Stage, elapsed: Duration, queue_depth: usize) {
+        let slot = &self.stages[stage as usize];
+        let first = slot.samples.fetch_add(1, Ordering::Relaxed) == 0;
+        ewma(&slot.avg_us, first, elapsed.as_micros() as u64);
+        slot.queue_depth
+            .store(queue_depth as u64, Ordering::Relaxed);
+    }
+
+    /// Record only the queue depth (stages whose time is spent waiting)
+    pub fn record_queue(&self, stage: Stage, queue_depth: usize) {
+        self.stages[stage as usize]
+            .queue_depth
+            .store(queue_depth as u64, Ordering::Relaxed);
+    }
+
+    /// Record one glass-to-glass latency estimate
+    pub fn record_glass_to_glass(&self, latency: Duration) {
+        let first = self.glass_to_glass_samples.fetch_add(1, Ordering::Relaxed) == 0;
+        ewma(&self.glass_to_glass_us, first, latency.as_micros() as u64);
+    }
+
+    /// Record which decoder the decode stage is using
+    pub fn set_decoder(&self, name: &'static str) {
+        *self.decoder.lock().unwrap_or_else(|e| e.into_inner()) = Some(name);
+    }
+
+    /// Point-in-time copy for the overlay
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let stages = Stage::ALL.map(|stage| {
+            let slot = &self.stages[stage as usize];
+            StageSample {
+                stage,
+                avg: Duration::from_micros(slot.avg_us.load(Ordering::Relaxed)),
+                queue_depth: slot.queue_depth.load(Ordering::Relaxed) as usize,
+                samples: slot.samples.load(Ordering::Relaxed),
+            }
+        });
+        let glass_to_glass = (self.glass_to_glass_samples.load(Ordering::Relaxed) > 0)
+            .then(|| Duration::from_micros(self.glass_to_glass_us.load(Ordering::Relaxed)));
+
+        MetricsSnapshot {
+            stages,
+            glass_to_glass,
+            decoder: *self.decoder.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+/// Glass-to-glass estimate from the PTS of a frame and the wall clock.
+///
+/// `origin` is the instant the first keyframe was received (PTS 0): a frame
+/// with PTS `pts_us` should ideally be shown at `origin + pts`, anything later
+/// is latency the pipeline has accumulated. The one-way network delay of the
+/// first keyframe cannot be observed without a shared clock and is not included.
+pub fn glass_to_glass(origin: Instant, pts_us: i64, now: Instant) -> Duration {
+    let wall = now.saturating_duration_since(origin);
+    wall.saturating_sub(Duration::from_micros(pts_us.max(0) as u64))
+}
+
+/// One stage in a [`MetricsSnapshot`]
+#[derive(Debug, Clone, Copy)]
+pub struct StageSample {
+    pub stage: Stage,
+    pub avg: Duration,
+    pub queue_depth: usize,
+    pub samples: u64,
+}
+
+/// Per-stage latency breakdown shown by the debug overlay
+#[derive(Debug, Clone, Copy)]
+pub struct MetricsSnapshot {
+    pub stages: [StageSample; 4],
+    pub glass_to_glass: Option<Duration>,
+    /// Decoder in use, `None` until the first one is created
+    pub decoder: Option<&'static str>,
+}
+
+impl MetricsSnapshot {
+    /// Sum of the average time spent in every stage
+    pub fn pipeline_total(&self) -> Duration {
+        self.stages.iter().map(|s| s.avg).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_moving_average() {
+        let metrics = StageMetrics::new();
+        metrics.record(Stage::Decode, Duration::from_micros(8000), 3);
+        metrics.record(Stage::Decode, Duration::from_micros(0), 1);
+
+        let decode = metrics.snapshot().stages[Stage::Decode as usize];
+        assert_eq!(decode.stage, Stage::Decode);
+        assert_eq!(decode.avg, Duration::from_micros(7000));
+        assert_eq!(decode.queue_depth, 1);
+        assert_eq!(decode.samples, 2);
+    }
+
+    #[test]
+    fn test_snapshot_without_samples() {
+        let metrics = StageMetrics::new();
+        metrics.record_queue(Stage::Display, 5);
+
+        let snapshot = metrics.snapshot();
+        assert!(snapshot.glass_to_glass.is_none());
+        assert_eq!(snapshot.stages[Stage::Display as usize].queue_depth, 5);
+        assert_eq!(snapshot.pipeline_total(), Duration::ZERO);
+        assert!(snapshot.decoder.is_none());
+    }
+
+    #[test]
+    fn test_decoder_label() {
+        let metrics = StageMetrics::new();
+        metrics.set_decoder("NVDEC");
+        metrics.set_decoder("Software");
+
+        assert_eq!(metrics.snapshot().decoder, Some("Software"));
+    }
+
+    #[test]
+    fn test_glass_to_glass_estimate() {
+        let origin = Instant::now();
+        let now = origin + Duration::from_millis(1100);
+
+        assert_eq!(
+            glass_to_glass(origin, 1_000_000, now),
+            Duration::from_millis(100)
+        );
+        // Frame "in anticipo": nessuna latenza negativa
+        assert_eq!(glass_to_glass(origin, 2_000_000, now), Duration::ZERO);
+    }
+}
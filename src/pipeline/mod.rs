@@ -15,17 +15,35 @@
 //! - Coordinators chain stages together and manage lifecycle
 //! - MediaClock provides timestamp correlation for A/V sync
 //! - Health monitoring tracks metrics and enables recovery
+//! - StageMetrics records per-stage latency for the debug overlay
+//! - `nack` re-requests lost RTP packets from a sender-side cache
+//! - `recovery` turns NACK on or off per session
+//! - `simulcast` picks the encoder tier each receiver is served from
+//! - `stats_log` appends periodic health snapshots to a CSV/JSONL file
+//! - `tuning` gathers every channel and buffer size into per-profile presets
+//! - `loopback` runs caster and receiver in one process for end-to-end tests
+//!   (`test-capture` feature)
 
 pub mod clock;
 pub mod health;
+#[cfg(any(test, feature = "test-capture"))]
+pub mod loopback;
+pub mod metrics;
+pub mod nack;
 pub mod receiver;
+pub mod recovery;
 pub mod sender;
+pub mod simulcast;
 pub mod stage;
 pub mod state;
+pub mod stats_log;
+pub mod tuning;
 pub mod types;
 
-pub use clock::MediaClock;
-pub use health::{HealthMonitor, PipelineHealth};
+pub use clock::{ClockAnchor, MediaClock};
+pub use health::{DropSource, HealthMonitor, PipelineHealth};
+pub use metrics::{MetricsSnapshot, Stage, StageMetrics};
 pub use stage::{PipelineCoordinator, PipelineStage};
-pub use state::PipelineState;
+pub use state::{ConnectionState, ConnectionStatus, PipelineState};
+pub use tuning::PipelineTuning;
 pub use types::{MediaFrame, MediaKind, Timestamp};
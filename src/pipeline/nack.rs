@@ -0,0 +1,170 @@
+//! NACK retransmission for RTP video
+//!
+//! When the jitter buffer sees a gap it asks the sender for the missing
+//! sequence numbers; the sender answers from a small cache of recently sent
+//! packets, bounded by age and count. Retransmitted packets arrive on the
+//! normal media path with their original sequence number. Cheap on low-RTT
+//! links, where a resend lands well within the jitter delay.
+//!
+//! WebRTC sessions negotiate NACK over RTCP and use the webrtc-rs
+//! interceptors instead (see `create_peer_connection`); this covers the
+//! pipeline's own RTP path.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Age after which a sent packet can no longer be retransmitted
+pub const RETRANSMIT_MAX_AGE: Duration = Duration::from_secs(1);
+/// Packets kept by the sender for retransmission
+pub const RETRANSMIT_MAX_PACKETS: usize = 1024;
+/// Wait before asking again for a packet that did not show up
+const NACK_RETRY_INTERVAL: Duration = Duration::from_millis(30);
+/// Requests for the same packet before giving up
+const MAX_NACK_RETRIES: u8 = 3;
+
+/// Sender side: recently sent packets, oldest first
+pub struct RetransmitCache {
+    packets: VecDeque<(Instant, u16, Vec<u8>, bool, u32)>,
+    max_age: Duration,
+    max_packets: usize,
+}
+
+impl Default for RetransmitCache {
+    fn default() -> Self {
+        Self::new(RETRANSMIT_MAX_AGE, RETRANSMIT_MAX_PACKETS)
+    }
+}
+
+impl RetransmitCache {
+    pub fn new(max_age: Duration, max_packets: usize) -> Self {
+        Self {
+            packets: VecDeque::with_capacity(max_packets),
+            max_age,
+            max_packets,
+        }
+    }
+
+    pub fn push(&mut self, seq: u16, payload: &[u8], marker: bool, timestamp: u32) {
+        self.prune(Instant::now());
+        if self.packets.len() >= self.max_packets {
+            self.packets.pop_front();
+        }
+        self.packets
+            .push_back((Instant::now(), seq, payload.to_vec(), marker, timestamp));
+    }
+
+    /// Packet to resend, `None` if it already left the cache
+    pub fn get(&mut self, seq: u16) -> Option<(Vec<u8>, bool, u32)> {
+        self.prune(Instant::now());
+        self.packets
+            .iter()
+            .rev()
+            .find(|(_, s, ..)| *s == seq)
+            .map(|(_, _, payload, marker, timestamp)| (payload.clone(), *marker, *timestamp))
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while self
+            .packets
+            .front()
+            .is_some_and(|(sent_at, ..)| now.duration_since(*sent_at) > self.max_age)
+        {
+            self.packets.pop_front();
+        }
+    }
+}
+
+/// Receiver side: which missing packets to request, and when to retry
+#[derive(Default)]
+pub struct NackTracker {
+    /// Last request time and number of requests per missing packet
+    requested: HashMap<u16, (Instant, u8)>,
+    recovered: u64,
+}
+
+impl NackTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `seq` should be (re)requested now
+    pub fn should_request(&mut self, seq: u16, now: Instant) -> bool {
+        match self.requested.get_mut(&seq) {
+            None => {
+                self.requested.insert(seq, (now, 1));
+                true
+            }
+            Some((last, retries)) => {
+                if *retries >= MAX_NACK_RETRIES || now.duration_since(*last) < NACK_RETRY_INTERVAL {
+                    return false;
+                }
+                *last = now;
+                *retries += 1;
+                true
+            }
+        }
+    }
+
+    /// A packet arrived; counts it if it answered a request
+    pub fn received(&mut self, seq: u16) {
+        if self.requested.remove(&seq).is_some() {
+            self.recovered += 1;
+        }
+    }
+
+    /// The jitter buffer gave up on `seq`
+    pub fn lost(&mut self, seq: u16) {
+        self.requested.remove(&seq);
+    }
+
+    /// Packets that arrived after being requested
+    pub fn recovered(&self) -> u64 {
+        self.recovered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_bounded_by_count() {
+        let mut cache = RetransmitCache::new(Duration::from_secs(10), 3);
+        for seq in 0..5u16 {
+            cache.push(seq, &[seq as u8], false, seq as u32);
+        }
+        assert!(cache.get(1).is_none());
+        assert!(cache.get(2).is_some());
+        assert_eq!(cache.get(4), Some((vec![4], false, 4)));
+    }
+
+    #[test]
+    fn test_cache_bounded_by_age() {
+        let mut cache = RetransmitCache::new(Duration::from_millis(20), 100);
+        cache.push(7, &[1, 2, 3], true, 90);
+        assert!(cache.get(7).is_some());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.get(7).is_none());
+        assert!(cache.packets.is_empty());
+    }
+
+    #[test]
+    fn test_tracker_retries_then_gives_up() {
+        let mut tracker = NackTracker::new();
+        let start = Instant::now();
+        assert!(tracker.should_request(10, start));
+        // Troppo presto per ripetere la richiesta
+        assert!(!tracker.should_request(10, start + Duration::from_millis(5)));
+
+        let mut now = start;
+        for _ in 1..MAX_NACK_RETRIES {
+            now += NACK_RETRY_INTERVAL;
+            assert!(tracker.should_request(10, now));
+        }
+        assert!(!tracker.should_request(10, now + NACK_RETRY_INTERVAL));
+
+        tracker.received(10);
+        tracker.received(11);
+        assert_eq!(tracker.recovered(), 1);
+    }
+}
@@ -0,0 +1,135 @@
+//! Manual A/V offset for the receiver
+//!
+//! Some setups (Bluetooth headsets, TVs with heavy post-processing) add a
+//! constant delay to one of the two outputs that no timestamp can see. The
+//! user dials it out by hand: a positive offset delays video, a negative
+//! one delays audio.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Largest offset accepted from the settings, in either direction
+pub const MAX_AV_OFFSET_MS: i64 = 500;
+
+/// Offset shared between the settings and the playout tasks
+#[derive(Debug, Clone, Default)]
+pub struct AvOffset(Arc<AtomicI64>);
+
+impl AvOffset {
+    pub fn new(offset_ms: i64) -> Self {
+        let offset = Self::default();
+        offset.set_ms(offset_ms);
+        offset
+    }
+
+    /// Clamped to ±[`MAX_AV_OFFSET_MS`], takes effect on the next frame
+    pub fn set_ms(&self, offset_ms: i64) {
+        let offset_ms = offset_ms.clamp(-MAX_AV_OFFSET_MS, MAX_AV_OFFSET_MS);
+        self.0.store(offset_ms * 1000, Ordering::Relaxed);
+    }
+
+    pub fn ms(&self) -> i64 {
+        self.us() / 1000
+    }
+
+    /// Signed offset: > 0 video later, < 0 audio later
+    pub fn us(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn video_delay(&self) -> Duration {
+        Duration::from_micros(self.us().max(0) as u64)
+    }
+
+    pub fn audio_delay(&self) -> Duration {
+        Duration::from_micros((-self.us()).max(0) as u64)
+    }
+
+    /// Settings label, e.g. "+120 ms (video later)"
+    pub fn label(offset_ms: i64) -> String {
+        match offset_ms {
+            0 => String::from("0 ms"),
+            ms if ms > 0 => format!("+{} ms (video later)", ms),
+            ms => format!("{} ms (audio later)", ms),
+        }
+    }
+}
+
+/// FIFO that hands items back once their due time has passed.
+/// Items are expected in due order, as they are pushed by a single stream.
+#[derive(Debug)]
+pub struct DelayLine<T> {
+    queue: VecDeque<(Instant, T)>,
+}
+
+impl<T> Default for DelayLine<T> {
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl<T> DelayLine<T> {
+    pub fn push(&mut self, item: T, due: Instant) {
+        self.queue.push_back((due, item));
+    }
+
+    /// Oldest item whose due time is not after `now`
+    pub fn pop_due(&mut self, now: Instant) -> Option<T> {
+        match self.queue.front() {
+            Some((due, _)) if *due <= now => self.queue.pop_front().map(|(_, item)| item),
+            _ => None,
+        }
+    }
+
+    pub fn next_due(&self) -> Option<Instant> {
+        self.queue.front().map(|(due, _)| *due)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_is_clamped_and_split() {
+        let offset = AvOffset::new(800);
+        assert_eq!(offset.ms(), MAX_AV_OFFSET_MS);
+        assert_eq!(offset.video_delay(), Duration::from_millis(500));
+        assert_eq!(offset.audio_delay(), Duration::ZERO);
+
+        offset.set_ms(-120);
+        assert_eq!(offset.us(), -120_000);
+        assert_eq!(offset.video_delay(), Duration::ZERO);
+        assert_eq!(offset.audio_delay(), Duration::from_millis(120));
+    }
+
+    #[test]
+    fn test_offset_label() {
+        assert_eq!(AvOffset::label(0), "0 ms");
+        assert_eq!(AvOffset::label(120), "+120 ms (video later)");
+        assert_eq!(AvOffset::label(-40), "-40 ms (audio later)");
+    }
+
+    #[test]
+    fn test_delay_line_releases_in_order_when_due() {
+        let start = Instant::now();
+        let mut line = DelayLine::default();
+        line.push(1, start + Duration::from_millis(10));
+        line.push(2, start + Duration::from_millis(20));
+
+        assert_eq!(line.pop_due(start), None);
+        assert_eq!(line.next_due(), Some(start + Duration::from_millis(10)));
+        assert_eq!(line.pop_due(start + Duration::from_millis(15)), Some(1));
+        assert_eq!(line.pop_due(start + Duration::from_millis(15)), None);
+        assert_eq!(line.pop_due(start + Duration::from_millis(25)), Some(2));
+        assert!(line.is_empty());
+    }
+}
@@ -1,20 +1,27 @@
 //! Receiver pipeline coordinator
 //!
 //! Chains receive → reorder → decode → sync stages and manages their lifecycle.
+//! An optional [`NetworkImpairment`] can sit between receive and reorder.
 
 use crate::decoder::{AudioPlayer, VideoFrame};
+use crate::display::DisplayPolicy;
 use crate::pipeline::PipelineStage;
 use crate::pipeline::clock::MediaClock;
 use crate::pipeline::health::PipelineHealth;
+use crate::pipeline::metrics::StageMetrics;
+use crate::pipeline::receiver::av_offset::AvOffset;
 use crate::pipeline::receiver::decode_stage::DecodeStage;
-use crate::pipeline::receiver::reorder_stage::{ReorderConfig, ReorderStage, RtpPacket};
-use crate::pipeline::receiver::sync_stage::{SyncConfig, SyncStage};
+use crate::pipeline::receiver::impairment::{ImpairmentConfig, NetworkImpairment};
+use crate::pipeline::receiver::latency::LatencyProfile;
+use crate::pipeline::receiver::latency_guard::LatencyGuard;
+use crate::pipeline::receiver::reorder_stage::{ReorderStage, RtpPacket};
+use crate::pipeline::receiver::sync_stage::SyncStage;
 use crate::pipeline::state::PipelineState;
 use crate::workers::save_stream::SavePacket;
-use log::{error, info};
+use log::{error, info, warn};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
 /// Coordinates the receiver pipeline: Receive → Reorder → Decode → Sync → Display
@@ -23,7 +30,21 @@ use tokio::sync::mpsc;
 pub struct ReceiverCoordinator {
     clock: MediaClock,
     health: Arc<PipelineHealth>,
+    metrics: Arc<StageMetrics>,
+    latency: LatencyProfile,
     state: PipelineState,
+    /// Simulated network impairment before the reorder stage (debug only)
+    impairment: Option<ImpairmentConfig>,
+    /// Raised by the decode stage when it needs a fresh IDR
+    keyframe_request: Option<Arc<AtomicBool>>,
+    display_policy: DisplayPolicy,
+    av_offset: AvOffset,
+    /// Retransmission requests from the reorder stage back to the sender
+    nack: Option<mpsc::Sender<u16>>,
+    /// Flushes reorder/decode/sync when the buffered media gets too old
+    latency_guard: Option<LatencyGuard>,
+    /// Smooth motion: the sync stage presents on this cadence
+    pacing: Option<Duration>,
 
     /// Audio playback position for A/V sync
     audio_position: Arc<AtomicI64>,
@@ -44,11 +65,70 @@ impl ReceiverCoordinator {
         Self {
             clock,
             health,
+            metrics: Arc::new(StageMetrics::new()),
+            latency: LatencyProfile::default(),
             state: PipelineState::Idle,
+            impairment: ImpairmentConfig::from_env(),
+            keyframe_request: None,
+            display_policy: DisplayPolicy::default(),
+            av_offset: AvOffset::default(),
+            nack: None,
+            latency_guard: None,
+            pacing: None,
             audio_position: Arc::new(AtomicI64::new(0)),
         }
     }
 
+    /// Use the buffer sizes of `profile` for the next launch
+    pub fn with_latency_profile(mut self, profile: LatencyProfile) -> Self {
+        self.latency = profile;
+        self
+    }
+
+    /// Inject loss/duplication/reordering/jitter between receive and reorder
+    pub fn with_impairment(mut self, config: ImpairmentConfig) -> Self {
+        self.impairment = Some(config);
+        self
+    }
+
+    /// Flag to raise when the decoder is recreated (e.g. the encoder's `force_idr`)
+    pub fn with_keyframe_request(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.keyframe_request = Some(flag);
+        self
+    }
+
+    /// Latency (drop superseded frames) vs completeness in the sync stage
+    pub fn with_display_policy(mut self, policy: DisplayPolicy) -> Self {
+        self.display_policy = policy;
+        self
+    }
+
+    /// Manual lip-sync correction applied by the sync stage
+    pub fn with_av_offset(mut self, av_offset: AvOffset) -> Self {
+        self.av_offset = av_offset;
+        self
+    }
+
+    /// Sequence numbers the sender's `RetransmitCache` should resend
+    pub fn with_nack(mut self, nack_tx: mpsc::Sender<u16>) -> Self {
+        self.nack = Some(nack_tx);
+        self
+    }
+
+    /// Drop to keyframe-only when the reorder + sync backlog exceeds the
+    /// guard's ceiling
+    pub fn with_latency_guard(mut self, guard: LatencyGuard) -> Self {
+        self.latency_guard = Some(guard);
+        self
+    }
+
+    /// Present frames on the display cadence `interval`, holding the latest.
+    /// The live receiver does not use the coordinator (see [`SyncStage::with_pacing`]).
+    pub fn with_pacing(mut self, interval: Duration) -> Self {
+        self.pacing = Some(interval);
+        self
+    }
+
     /// Get the pipeline clock
     pub fn clock(&self) -> &MediaClock {
         &self.clock
@@ -59,6 +139,11 @@ impl ReceiverCoordinator {
         &self.health
     }
 
+    /// Get the per-stage latency metrics
+    pub fn metrics(&self) -> &Arc<StageMetrics> {
+        &self.metrics
+    }
+
     /// Get the current pipeline state
     pub fn state(&self) -> &PipelineState {
         &self.state
@@ -93,18 +178,44 @@ impl ReceiverCoordinator {
         let save_tx_audio = save_tx.clone();
 
         // Set up video pipeline stages
-        let mut reorder = ReorderStage::new(ReorderConfig::default(), health.clone());
-        let mut decode = DecodeStage::new(clock.clone(), health.clone());
-        let mut sync = SyncStage::new(SyncConfig::default(), health.clone());
+        let metrics = self.metrics.clone();
+        let tuning = self.latency.tuning();
+        let mut reorder =
+            ReorderStage::new(tuning.reorder, health.clone()).with_metrics(metrics.clone());
+        if let Some(nack_tx) = &self.nack {
+            reorder = reorder.with_nack(nack_tx.clone());
+        }
+        let mut decode =
+            DecodeStage::new(clock.clone(), health.clone()).with_metrics(metrics.clone());
+        if let Some(flag) = &self.keyframe_request {
+            decode = decode.with_keyframe_request(Arc::clone(flag));
+        }
+        let mut sync = SyncStage::new(tuning.sync, health.clone())
+            .with_metrics(metrics.clone(), clock.base())
+            .with_display_policy(self.display_policy)
+            .with_av_offset(self.av_offset.clone());
+        if let Some(guard) = &self.latency_guard {
+            reorder = reorder.with_latency_guard(guard.clone());
+            decode = decode.with_latency_guard(guard.clone());
+            sync = sync.with_latency_guard(guard.clone());
+        }
+        if let Some(interval) = self.pacing {
+            sync = sync.with_pacing(interval);
+        }
 
         // Wire stages: raw_video → reorder → decode → sync → output
-        let (raw_to_reorder_tx, raw_to_reorder_rx) = mpsc::channel::<RtpPacket>(128);
+        let (raw_to_reorder_tx, raw_to_reorder_rx) =
+            mpsc::channel::<RtpPacket>(tuning.packet_queue);
+        let raw_to_reorder_rx = match self.impairment.clone() {
+            Some(config) => NetworkImpairment::new(config).spawn(raw_to_reorder_rx),
+            None => raw_to_reorder_rx,
+        };
         reorder.set_input(raw_to_reorder_rx);
-        let reorder_to_decode_rx = reorder.take_output();
+        let reorder_to_decode_rx = reorder.take_output(tuning.reordered_queue);
         decode.set_input(reorder_to_decode_rx);
-        let decode_to_sync_rx = decode.take_output();
+        let decode_to_sync_rx = decode.take_output(tuning.decoded_queue);
         sync.set_video_input(decode_to_sync_rx);
-        let sync_output_rx = sync.take_video_output();
+        let sync_output_rx = sync.take_video_output(tuning.video_output);
 
         // Spawn video receive → reorder adapter
         let _health_recv = health.clone();
@@ -194,10 +305,35 @@ impl ReceiverCoordinator {
             }
         });
 
+        // Max-latency guard: flush every stage and restart from a fresh IDR
+        if let Some(guard) = self.latency_guard.clone() {
+            let health_guard = health.clone();
+            let keyframe_request = self.keyframe_request.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_millis(50));
+                loop {
+                    interval.tick().await;
+                    if !guard.should_reset() {
+                        continue;
+                    }
+                    warn!(
+                        "ReceiverCoordinator: {:?} buffered (max {} ms), flushing to the next keyframe",
+                        guard.buffered(),
+                        guard.max_ms()
+                    );
+                    guard.trigger();
+                    health_guard.record_latency_reset();
+                    if let Some(flag) = &keyframe_request {
+                        flag.store(true, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+
         // Start health monitoring
         let health_mon = health.clone();
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+            let mut interval = tokio::time::interval(Duration::from_secs(30));
             loop {
                 interval.tick().await;
                 let summary = health_mon.summary();
@@ -2,17 +2,25 @@
 //!
 //! Wraps H264Depacketizer + FfmpegDecoder for video and AudioPlayer for audio,
 //! producing decoded frames for A/V sync.
+//!
+//! A run of decode failures recreates the decoder and asks the caster for a
+//! keyframe; nothing is forwarded until a clean IDR decodes again. If the
+//! decoder keeps failing after a bounded number of re-creations the stage
+//! gives up and reports `Failed`.
 
-use crate::decoder::{FfmpegDecoder, H264Depacketizer, VideoFrame};
+use crate::decoder::{FfmpegDecoder, H264Depacketizer, VideoFrame, i420_len};
 use crate::pipeline::PipelineStage;
 use crate::pipeline::clock::MediaClock;
 use crate::pipeline::health::PipelineHealth;
+use crate::pipeline::metrics::{Stage, StageMetrics};
+use crate::pipeline::receiver::latency_guard::LatencyGuard;
 use crate::pipeline::receiver::reorder_stage::RtpPacket;
 use crate::pipeline::types::Timestamp;
-use anyhow::Result;
+use anyhow::{Result, bail};
 use async_trait::async_trait;
-use log::{info, warn};
+use log::{error, info, warn};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
 use tokio::sync::mpsc;
 
@@ -23,6 +31,51 @@ pub struct TimedVideoFrame {
     pub pts: Timestamp,
     pub correlation_id: u64,
     pub is_keyframe: bool,
+    /// When decoding finished (time spent in the sync queue starts here)
+    pub decoded_at: Instant,
+}
+
+/// Decode failures in a row before the decoder is recreated
+pub const MAX_CONSECUTIVE_FAILURES: u32 = 10;
+/// Decoder re-creations without a decoded frame before giving up
+pub const MAX_DECODER_RESETS: u32 = 3;
+
+/// Next step after a failed decode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// Keep feeding the current decoder
+    Continue,
+    /// Recreate the decoder and wait for an IDR
+    Reinit,
+    /// Too many re-creations without a frame
+    GiveUp,
+}
+
+/// Counts failures and bounds decoder re-creations
+#[derive(Debug, Default)]
+pub struct DecoderRecovery {
+    consecutive_failures: u32,
+    resets: u32,
+}
+
+impl DecoderRecovery {
+    pub fn on_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.resets = 0;
+    }
+
+    pub fn on_failure(&mut self) -> Recovery {
+        self.consecutive_failures += 1;
+        if self.consecutive_failures < MAX_CONSECUTIVE_FAILURES {
+            return Recovery::Continue;
+        }
+        self.consecutive_failures = 0;
+        if self.resets >= MAX_DECODER_RESETS {
+            return Recovery::GiveUp;
+        }
+        self.resets += 1;
+        Recovery::Reinit
+    }
 }
 
 /// Decode stage: depacketizes RTP and decodes H.264 into raw video frames
@@ -31,6 +84,10 @@ pub struct DecodeStage {
     health: Arc<PipelineHealth>,
     input_rx: Option<mpsc::Receiver<RtpPacket>>,
     output_tx: Option<mpsc::Sender<TimedVideoFrame>>,
+    metrics: Option<Arc<StageMetrics>>,
+    /// Set to ask the caster for an IDR (same flag as the encoder's `force_idr`)
+    keyframe_request: Option<Arc<AtomicBool>>,
+    latency_guard: Option<LatencyGuard>,
 }
 
 /// Return true if the H.264 access unit contains an IDR (nal type 5) or SPS/PPS (7/8).
@@ -63,6 +120,33 @@ impl DecodeStage {
             health,
             input_rx: None,
             output_tx: None,
+            metrics: None,
+            keyframe_request: None,
+            latency_guard: None,
+        }
+    }
+
+    /// Record decode time and input queue depth into shared stage metrics
+    pub fn with_metrics(mut self, metrics: Arc<StageMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Flag raised when the decoder is recreated and needs a fresh keyframe
+    pub fn with_keyframe_request(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.keyframe_request = Some(flag);
+        self
+    }
+
+    /// Drop the partial access unit and wait for an IDR after a latency reset
+    pub fn with_latency_guard(mut self, guard: LatencyGuard) -> Self {
+        self.latency_guard = Some(guard);
+        self
+    }
+
+    fn request_keyframe(&self) {
+        if let Some(flag) = &self.keyframe_request {
+            flag.store(true, Ordering::Relaxed);
         }
     }
 
@@ -71,9 +155,9 @@ impl DecodeStage {
         self.input_rx = Some(rx);
     }
 
-    /// Get the output channel for decoded video frames
-    pub fn take_output(&mut self) -> mpsc::Receiver<TimedVideoFrame> {
-        let (tx, rx) = mpsc::channel::<TimedVideoFrame>(8);
+    /// Get the output channel for decoded video frames, `capacity` deep
+    pub fn take_output(&mut self, capacity: usize) -> mpsc::Receiver<TimedVideoFrame> {
+        let (tx, rx) = mpsc::channel::<TimedVideoFrame>(capacity);
         self.output_tx = Some(tx);
         rx
     }
@@ -94,9 +178,13 @@ impl PipelineStage for DecodeStage {
         let mut depacketizer = H264Depacketizer::new();
         let mut decoder =
             FfmpegDecoder::new().map_err(|e| anyhow::anyhow!("Failed to create decoder: {}", e))?;
+        if let Some(metrics) = &self.metrics {
+            metrics.set_decoder(decoder.label());
+        }
 
-        let mut consecutive_failures: u32 = 0;
+        let mut recovery = DecoderRecovery::default();
         let mut waiting_for_keyframe = true;
+        let mut latency_generation = self.latency_guard.as_ref().map_or(0, |g| g.generation());
         let _start_time = Instant::now();
         let mut total_frames = 0u64;
         let mut decoded_frames = 0u64;
@@ -106,6 +194,15 @@ impl PipelineStage for DecodeStage {
         while let Some(packet) = input_rx.recv().await {
             total_frames += 1;
 
+            // I pacchetti precedenti sono stati scartati: il prossimo frame
+            // decodificabile è un keyframe
+            if let Some(guard) = &self.latency_guard
+                && guard.take_reset(&mut latency_generation)
+            {
+                depacketizer.reset();
+                waiting_for_keyframe = true;
+            }
+
             // Depacketize RTP into H.264 access units
             if let Some(h264_au) = depacketizer.push(&packet.payload, packet.marker) {
                 // Wait for first keyframe
@@ -119,8 +216,16 @@ impl PipelineStage for DecodeStage {
                 }
 
                 // Decode H.264 to YUV420p
-                if let Some((yuv, w, h)) = decoder.decode(&h264_au) {
-                    consecutive_failures = 0;
+                let decode_start = Instant::now();
+                let decoded = decoder.decode(&h264_au);
+                if let Some(metrics) = &self.metrics {
+                    metrics.record(Stage::Decode, decode_start.elapsed(), input_rx.len());
+                }
+                // Un frame troncato conta come fallimento: mai inoltrare
+                // frame parziali al TripleBuffer
+                let decoded = decoded.filter(|(yuv, w, h)| yuv.len() >= i420_len(*w, *h));
+                if let Some((yuv, w, h)) = decoded {
+                    recovery.on_success();
                     decoded_frames += 1;
 
                     let pts = self.clock.timestamp_from_instant(packet.received_at);
@@ -134,10 +239,12 @@ impl PipelineStage for DecodeStage {
                             data: yuv,
                             width: w as u32,
                             height: h as u32,
+                            layout: decoder.layout(),
                         },
                         pts,
                         correlation_id,
                         is_keyframe,
+                        decoded_at: Instant::now(),
                     };
 
                     if output_tx.send(timed_frame).await.is_err() {
@@ -145,14 +252,35 @@ impl PipelineStage for DecodeStage {
                         break;
                     }
                 } else {
-                    consecutive_failures += 1;
                     self.health.record_decode_failure();
 
-                    if consecutive_failures >= 10 {
-                        warn!("DecodeStage: 10 consecutive failures, resetting (waiting for IDR)");
-                        depacketizer.reset();
-                        consecutive_failures = 0;
-                        waiting_for_keyframe = true;
+                    match recovery.on_failure() {
+                        Recovery::Continue => {}
+                        Recovery::Reinit => {
+                            warn!(
+                                "DecodeStage: {} consecutive failures, recreating decoder (waiting for IDR)",
+                                MAX_CONSECUTIVE_FAILURES
+                            );
+                            // Un decoder hardware che non ha mai prodotto frame
+                            // lascia il posto al successivo della catena
+                            decoder.skip_if_unusable();
+                            decoder = FfmpegDecoder::new().map_err(|e| {
+                                anyhow::anyhow!("Failed to recreate decoder: {}", e)
+                            })?;
+                            if let Some(metrics) = &self.metrics {
+                                metrics.set_decoder(decoder.label());
+                            }
+                            depacketizer.reset();
+                            waiting_for_keyframe = true;
+                            self.request_keyframe();
+                        }
+                        Recovery::GiveUp => {
+                            error!(
+                                "DecodeStage: decoder still failing after {} re-creations, giving up",
+                                MAX_DECODER_RESETS
+                            );
+                            bail!("decoder failed {} times in a row", MAX_DECODER_RESETS);
+                        }
                     }
                 }
             }
@@ -169,3 +297,62 @@ impl PipelineStage for DecodeStage {
         "DecodeStage"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fail(recovery: &mut DecoderRecovery, times: u32) -> Vec<Recovery> {
+        (0..times).map(|_| recovery.on_failure()).collect()
+    }
+
+    #[test]
+    fn test_reinit_after_consecutive_failures() {
+        let mut recovery = DecoderRecovery::default();
+        let steps = fail(&mut recovery, MAX_CONSECUTIVE_FAILURES);
+        assert!(
+            steps[..steps.len() - 1]
+                .iter()
+                .all(|s| *s == Recovery::Continue)
+        );
+        assert_eq!(steps.last(), Some(&Recovery::Reinit));
+    }
+
+    #[test]
+    fn test_success_resets_counters() {
+        let mut recovery = DecoderRecovery::default();
+        fail(&mut recovery, MAX_CONSECUTIVE_FAILURES - 1);
+        recovery.on_success();
+        assert!(
+            fail(&mut recovery, MAX_CONSECUTIVE_FAILURES - 1)
+                .iter()
+                .all(|s| *s == Recovery::Continue)
+        );
+
+        // Un frame decodificato dopo una re-inizializzazione azzera anche i tentativi
+        let mut recovery = DecoderRecovery::default();
+        for _ in 0..MAX_DECODER_RESETS {
+            fail(&mut recovery, MAX_CONSECUTIVE_FAILURES);
+            recovery.on_success();
+        }
+        assert_eq!(
+            fail(&mut recovery, MAX_CONSECUTIVE_FAILURES).last(),
+            Some(&Recovery::Reinit)
+        );
+    }
+
+    #[test]
+    fn test_gives_up_after_bounded_resets() {
+        let mut recovery = DecoderRecovery::default();
+        for _ in 0..MAX_DECODER_RESETS {
+            assert_eq!(
+                fail(&mut recovery, MAX_CONSECUTIVE_FAILURES).last(),
+                Some(&Recovery::Reinit)
+            );
+        }
+        assert_eq!(
+            fail(&mut recovery, MAX_CONSECUTIVE_FAILURES).last(),
+            Some(&Recovery::GiveUp)
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! Simulated network impairment between ReceiveStage and ReorderStage
+//!
+//! Debug/test layer that wraps the packet receiver feeding the reordering
+//! (the [`RtpPacket`] channel of the pipeline, or the raw RTP channel of the
+//! receiver worker) and injects packet loss, duplication, reordering and added
+//! delay/jitter. Every random choice comes from a seeded generator, so the same
+//! configuration produces the same packet sequence run after run.
+//!
+//! Enabled by setting [`IMPAIRMENT_ENV`], e.g.
+//! `CASTIFY_IMPAIR="loss=5,dup=1,reorder=4,delay=20,jitter=30,seed=7"`
+//! (percentages and milliseconds).
+
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::pipeline::receiver::reorder_stage::RtpPacket;
+
+/// Variabile d'ambiente con la configurazione dell'impairment
+pub const IMPAIRMENT_ENV: &str = "CASTIFY_IMPAIR";
+
+/// Knobs of the simulated network
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImpairmentConfig {
+    /// Probability of dropping a packet (0.0..=1.0)
+    pub loss: f64,
+    /// Probability of delivering a packet twice (0.0..=1.0)
+    pub duplicate: f64,
+    /// Packets held and released in random order (0 or 1 = no reordering)
+    pub reorder_window: usize,
+    /// Fixed delay added to every packet
+    pub delay: Duration,
+    /// Extra random delay, uniform in `0..=jitter`
+    pub jitter: Duration,
+    /// Seed of the deterministic generator
+    pub seed: u64,
+}
+
+impl Default for ImpairmentConfig {
+    fn default() -> Self {
+        Self {
+            loss: 0.0,
+            duplicate: 0.0,
+            reorder_window: 0,
+            delay: Duration::ZERO,
+            jitter: Duration::ZERO,
+            seed: 1,
+        }
+    }
+}
+
+impl ImpairmentConfig {
+    /// Configurazione da [`IMPAIRMENT_ENV`], `None` se assente o non valida
+    pub fn from_env() -> Option<Self> {
+        let spec = std::env::var(IMPAIRMENT_ENV).ok()?;
+        match spec.parse::<Self>() {
+            Ok(config) => Some(config),
+            Err(e) => {
+                warn!("Ignoring {}={:?}: {:#}", IMPAIRMENT_ENV, spec, e);
+                None
+            }
+        }
+    }
+
+    fn has_delay(&self) -> bool {
+        !self.delay.is_zero() || !self.jitter.is_zero()
+    }
+}
+
+/// `key=value` separati da virgola: `loss`, `dup` (%), `reorder` (pacchetti),
+/// `delay`, `jitter` (ms), `seed`
+impl FromStr for ImpairmentConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let mut config = ImpairmentConfig::default();
+        for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = item
+                .split_once('=')
+                .with_context(|| format!("expected key=value, got {:?}", item))?;
+            let value = value.trim();
+            let percent = |value: &str| -> Result<f64> {
+                let p: f64 = value.parse()?;
+                if !(0.0..=100.0).contains(&p) {
+                    bail!("{} out of 0..100", p);
+                }
+                Ok(p / 100.0)
+            };
+            match key.trim() {
+                "loss" => config.loss = percent(value)?,
+                "dup" => config.duplicate = percent(value)?,
+                "reorder" => config.reorder_window = value.parse()?,
+                "delay" => config.delay = Duration::from_millis(value.parse()?),
+                "jitter" => config.jitter = Duration::from_millis(value.parse()?),
+                "seed" => config.seed = value.parse()?,
+                other => bail!("unknown impairment {:?}", other),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// SplitMix64: piccolo, veloce e riproducibile a partire dal seed
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniforme in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.next_f64() < probability
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            0
+        } else {
+            self.next_u64() % bound
+        }
+    }
+}
+
+/// Packet that can go through the impairment layer
+pub trait ImpairedPacket: Clone + Send + 'static {
+    /// Called when the packet is handed on, after its simulated delay
+    fn delivered(&mut self) {}
+}
+
+impl ImpairedPacket for RtpPacket {
+    fn delivered(&mut self) {
+        self.received_at = Instant::now();
+    }
+}
+
+/// Payload, marker, sequence number and timestamp, as read from the video track
+impl ImpairedPacket for (Vec<u8>, bool, u16, u32) {}
+
+/// Impairment statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImpairmentStats {
+    pub received: u64,
+    pub dropped: u64,
+    pub duplicated: u64,
+    /// Packets released ahead of an earlier one still in the window
+    pub reordered: u64,
+}
+
+/// Deterministic impairment state machine
+pub struct NetworkImpairment<T = RtpPacket> {
+    config: ImpairmentConfig,
+    rng: SplitMix64,
+    /// Pacchetti trattenuti per il riordino
+    window: Vec<T>,
+    stats: ImpairmentStats,
+}
+
+impl<T: ImpairedPacket> NetworkImpairment<T> {
+    pub fn new(config: ImpairmentConfig) -> Self {
+        let rng = SplitMix64(config.seed);
+        Self {
+            window: Vec::with_capacity(config.reorder_window),
+            config,
+            rng,
+            stats: ImpairmentStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> ImpairmentStats {
+        self.stats
+    }
+
+    /// Apply loss/duplication/reordering to one packet.
+    ///
+    /// Returns the packets to forward now, each with the delay to wait before
+    /// delivering it.
+    pub fn apply(&mut self, packet: T) -> Vec<(Duration, T)> {
+        self.stats.received += 1;
+
+        if self.rng.chance(self.config.loss) {
+            self.stats.dropped += 1;
+            return Vec::new();
+        }
+
+        let mut incoming = vec![packet];
+        if self.rng.chance(self.config.duplicate) {
+            self.stats.duplicated += 1;
+            incoming.push(incoming[0].clone());
+        }
+
+        let mut out = Vec::new();
+        for packet in incoming {
+            if self.config.reorder_window <= 1 {
+                out.push(packet);
+                continue;
+            }
+            self.window.push(packet);
+            if self.window.len() >= self.config.reorder_window {
+                let idx = self.rng.below(self.window.len() as u64) as usize;
+                if idx != 0 {
+                    self.stats.reordered += 1;
+                }
+                out.push(self.window.remove(idx));
+            }
+        }
+
+        out.into_iter().map(|p| (self.next_delay(), p)).collect()
+    }
+
+    /// Packets still held in the reorder window, in arrival order
+    pub fn flush(&mut self) -> Vec<(Duration, T)> {
+        let held: Vec<T> = self.window.drain(..).collect();
+        held.into_iter().map(|p| (self.next_delay(), p)).collect()
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let jitter_us = self.config.jitter.as_micros() as u64;
+        self.config.delay + Duration::from_micros(self.rng.below(jitter_us + 1))
+    }
+
+    /// Wrap `input`: returns the impaired receiver to feed the reordering.
+    /// The task ends when either side closes.
+    pub fn spawn(mut self, mut input: mpsc::Receiver<T>) -> mpsc::Receiver<T> {
+        let (tx, rx) = mpsc::channel::<T>(input.max_capacity());
+        let delayed = self.config.has_delay();
+
+        tokio::spawn(async move {
+            info!("NetworkImpairment active: {:?}", self.config);
+
+            // `false` quando il consumatore ha chiuso il canale
+            let deliver = |tx: &mpsc::Sender<T>, delay: Duration, mut packet: T| {
+                let tx = tx.clone();
+                async move {
+                    if tx.is_closed() {
+                        return false;
+                    }
+                    if delayed {
+                        // Ogni pacchetto ha il proprio ritardo: con jitter
+                        // l'ordine di consegna cambia come su una rete reale
+                        tokio::spawn(async move {
+                            tokio::time::sleep(delay).await;
+                            packet.delivered();
+                            let _ = tx.send(packet).await;
+                        });
+                        true
+                    } else {
+                        packet.delivered();
+                        tx.send(packet).await.is_ok()
+                    }
+                }
+            };
+
+            'recv: while let Some(packet) = input.recv().await {
+                for (delay, packet) in self.apply(packet) {
+                    if !deliver(&tx, delay, packet).await {
+                        break 'recv;
+                    }
+                }
+            }
+            for (delay, packet) in self.flush() {
+                if !deliver(&tx, delay, packet).await {
+                    break;
+                }
+            }
+
+            let stats = self.stats();
+            info!(
+                "NetworkImpairment ended: {} received, {} dropped, {} duplicated, {} reordered",
+                stats.received, stats.dropped, stats.duplicated, stats.reordered
+            );
+        });
+
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_packet(seq: u16) -> RtpPacket {
+        RtpPacket {
+            payload: vec![seq as u8],
+            marker: true,
+            sequence_number: seq,
+            timestamp: seq as u32 * 3000,
+            received_at: Instant::now(),
+        }
+    }
+
+    fn run(config: ImpairmentConfig, count: u16) -> Vec<(Duration, u16)> {
+        let mut impairment = NetworkImpairment::new(config);
+        let mut out: Vec<(Duration, u16)> = (0..count)
+            .flat_map(|seq| impairment.apply(make_packet(seq)))
+            .map(|(d, p)| (d, p.sequence_number))
+            .collect();
+        out.extend(
+            impairment
+                .flush()
+                .into_iter()
+                .map(|(d, p)| (d, p.sequence_number)),
+        );
+        out
+    }
+
+    #[test]
+    fn test_passthrough_by_default() {
+        let out = run(ImpairmentConfig::default(), 50);
+        let seqs: Vec<u16> = out.iter().map(|(_, s)| *s).collect();
+        assert_eq!(seqs, (0..50).collect::<Vec<_>>());
+        assert!(out.iter().all(|(d, _)| d.is_zero()));
+    }
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let config: ImpairmentConfig = "loss=10,dup=5,reorder=4,delay=10,jitter=20,seed=42"
+            .parse()
+            .unwrap();
+        assert_eq!(run(config.clone(), 500), run(config.clone(), 500));
+
+        let other = ImpairmentConfig {
+            seed: 43,
+            ..config.clone()
+        };
+        assert_ne!(run(config, 500), run(other, 500));
+    }
+
+    #[test]
+    fn test_loss_and_duplication() {
+        let all_lost = ImpairmentConfig {
+            loss: 1.0,
+            ..Default::default()
+        };
+        assert!(run(all_lost, 100).is_empty());
+
+        let all_dup = ImpairmentConfig {
+            duplicate: 1.0,
+            ..Default::default()
+        };
+        let out = run(all_dup, 100);
+        assert_eq!(out.len(), 200);
+        assert!(out.chunks(2).all(|pair| pair[0].1 == pair[1].1));
+
+        let config = ImpairmentConfig {
+            loss: 0.2,
+            seed: 9,
+            ..Default::default()
+        };
+        let mut impairment = NetworkImpairment::new(config);
+        for seq in 0..1000 {
+            impairment.apply(make_packet(seq));
+        }
+        let dropped = impairment.stats().dropped;
+        assert!((120..280).contains(&dropped), "dropped {}", dropped);
+    }
+
+    #[test]
+    fn test_reorder_keeps_every_packet() {
+        let config = ImpairmentConfig {
+            reorder_window: 5,
+            seed: 3,
+            ..Default::default()
+        };
+        let out = run(config, 200);
+        let mut seqs: Vec<u16> = out.iter().map(|(_, s)| *s).collect();
+        assert_ne!(seqs, (0..200).collect::<Vec<_>>());
+        seqs.sort_unstable();
+        assert_eq!(seqs, (0..200).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_delay_within_jitter() {
+        let config: ImpairmentConfig = "delay=20,jitter=30".parse().unwrap();
+        let out = run(config, 200);
+        assert!(
+            out.iter().all(|(d, _)| {
+                *d >= Duration::from_millis(20) && *d <= Duration::from_millis(50)
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("loss=150".parse::<ImpairmentConfig>().is_err());
+        assert!("drop=5".parse::<ImpairmentConfig>().is_err());
+        assert!("loss".parse::<ImpairmentConfig>().is_err());
+        assert_eq!(
+            "".parse::<ImpairmentConfig>().unwrap(),
+            ImpairmentConfig::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spawn_forwards_and_closes() {
+        let (tx, rx) = mpsc::channel(16);
+        let config = ImpairmentConfig {
+            reorder_window: 3,
+            seed: 5,
+            ..Default::default()
+        };
+        let mut out = NetworkImpairment::new(config).spawn(rx);
+
+        for seq in 0..10 {
+            tx.send(make_packet(seq)).await.unwrap();
+        }
+        drop(tx);
+
+        let mut seqs = Vec::new();
+        while let Some(packet) = out.recv().await {
+            seqs.push(packet.sequence_number);
+        }
+        seqs.sort_unstable();
+        assert_eq!(seqs, (0..10).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_spawn_stops_when_output_closes() {
+        for spec in ["", "delay=5"] {
+            let (tx, rx) = mpsc::channel(16);
+            let config: ImpairmentConfig = spec.parse().unwrap();
+            drop(NetworkImpairment::new(config).spawn(rx));
+
+            // Al primo pacchetto da consegnare il task si accorge della chiusura
+            tx.send(make_packet(0)).await.unwrap();
+            tokio::time::timeout(Duration::from_secs(1), tx.closed())
+                .await
+                .unwrap_or_else(|_| panic!("impairment {:?} still running", spec));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_raw_rtp_tuples() {
+        let (tx, rx) = mpsc::channel(16);
+        let config: ImpairmentConfig = "dup=100".parse().unwrap();
+        let mut out = NetworkImpairment::new(config).spawn(rx);
+
+        tx.send((vec![1], true, 7, 3000)).await.unwrap();
+        drop(tx);
+        assert_eq!(out.recv().await, Some((vec![1], true, 7, 3000)));
+        assert_eq!(out.recv().await, Some((vec![1], true, 7, 3000)));
+        assert_eq!(out.recv().await, None);
+    }
+}
@@ -0,0 +1,61 @@
+//! Latency profiles
+//!
+//! Selects the [`PipelineTuning`] preset of both sides: interactive
+//! use (remote control) wants frames as soon as possible, lossy links want a
+//! deeper buffer to ride out jitter and retransmissions.
+
+use std::fmt;
+
+use crate::pipeline::receiver::reorder_stage::ReorderConfig;
+use crate::pipeline::tuning::PipelineTuning;
+
+/// Trade-off between responsiveness and smoothness on the receiver
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LatencyProfile {
+    /// Short jitter buffer: for remote control and other interactive use
+    LowLatency = 0,
+    /// The historical defaults
+    #[default]
+    Balanced = 1,
+    /// Deep buffers for lossy or high-jitter links
+    Smooth = 2,
+}
+
+impl LatencyProfile {
+    pub const ALL: [LatencyProfile; 3] = [
+        LatencyProfile::LowLatency,
+        LatencyProfile::Balanced,
+        LatencyProfile::Smooth,
+    ];
+
+    /// Decode a value stored with `as u8` (unknown values map to the default)
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0 => LatencyProfile::LowLatency,
+            2 => LatencyProfile::Smooth,
+            _ => LatencyProfile::Balanced,
+        }
+    }
+
+    /// Every buffer size of the caster and receiver pipelines for this profile
+    pub fn tuning(self) -> PipelineTuning {
+        PipelineTuning::for_profile(self)
+    }
+
+    /// Jitter buffer settings for this profile
+    pub fn reorder_config(self) -> ReorderConfig {
+        self.tuning().reorder
+    }
+}
+
+impl fmt::Display for LatencyProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            LatencyProfile::LowLatency => "Low latency",
+            LatencyProfile::Balanced => "Balanced",
+            LatencyProfile::Smooth => "Smooth",
+        };
+        write!(f, "{}", label)
+    }
+}
@@ -0,0 +1,135 @@
+//! Maximum-latency guard for the receiver pipeline
+//!
+//! The reorder and sync stages report how long their oldest item has been
+//! waiting. When the combined delay exceeds the configured ceiling the
+//! coordinator triggers a reset: every stage flushes what it holds, the
+//! decoder waits for a fresh keyframe and the caster is asked for an IDR.
+//! Playing a backlog seconds late is worse than a short freeze.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Default ceiling for the buffered media (0 disables the guard)
+pub const DEFAULT_MAX_LATENCY_MS: u32 = 1000;
+
+#[derive(Debug, Default)]
+struct Inner {
+    max_ms: AtomicU32,
+    reorder_us: AtomicU64,
+    sync_us: AtomicU64,
+    /// Incremented on every reset; stages flush when it changes
+    generation: AtomicU64,
+}
+
+/// Shared between the coordinator (which decides) and the stages (which
+/// report their backlog and flush on a new generation)
+#[derive(Debug, Clone, Default)]
+pub struct LatencyGuard {
+    inner: Arc<Inner>,
+}
+
+impl LatencyGuard {
+    pub fn new(max_ms: u32) -> Self {
+        let guard = Self::default();
+        guard.set_max_ms(max_ms);
+        guard
+    }
+
+    /// Change the ceiling at runtime; 0 disables the guard
+    pub fn set_max_ms(&self, max_ms: u32) {
+        self.inner.max_ms.store(max_ms, Ordering::Relaxed);
+    }
+
+    pub fn max_ms(&self) -> u32 {
+        self.inner.max_ms.load(Ordering::Relaxed)
+    }
+
+    /// Age of the oldest packet in the jitter buffer
+    pub fn report_reorder(&self, age: Duration) {
+        self.inner
+            .reorder_us
+            .store(age.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Age of the oldest frame waiting in the sync queue
+    pub fn report_sync(&self, age: Duration) {
+        self.inner
+            .sync_us
+            .store(age.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Combined delay currently held by the reorder and sync stages
+    pub fn buffered(&self) -> Duration {
+        Duration::from_micros(
+            self.inner.reorder_us.load(Ordering::Relaxed)
+                + self.inner.sync_us.load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn should_reset(&self) -> bool {
+        let max_ms = self.max_ms();
+        max_ms > 0 && self.buffered() > Duration::from_millis(max_ms as u64)
+    }
+
+    /// Start a new generation: the stages flush on their next check
+    pub fn trigger(&self) {
+        self.inner.reorder_us.store(0, Ordering::Relaxed);
+        self.inner.sync_us.store(0, Ordering::Relaxed);
+        self.inner.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.inner.generation.load(Ordering::Acquire)
+    }
+
+    /// True once per reset for the stage holding `seen`
+    pub fn take_reset(&self, seen: &mut u64) -> bool {
+        let current = self.generation();
+        if current == *seen {
+            return false;
+        }
+        *seen = current;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reset_above_threshold() {
+        let guard = LatencyGuard::new(500);
+        guard.report_reorder(Duration::from_millis(200));
+        guard.report_sync(Duration::from_millis(250));
+        assert!(!guard.should_reset());
+
+        guard.report_sync(Duration::from_millis(350));
+        assert_eq!(guard.buffered(), Duration::from_millis(550));
+        assert!(guard.should_reset());
+
+        guard.trigger();
+        assert_eq!(guard.buffered(), Duration::ZERO);
+        assert!(!guard.should_reset());
+    }
+
+    #[test]
+    fn test_disabled_guard_never_resets() {
+        let guard = LatencyGuard::new(0);
+        guard.report_reorder(Duration::from_secs(10));
+        assert!(!guard.should_reset());
+    }
+
+    #[test]
+    fn test_each_stage_sees_reset_once() {
+        let guard = LatencyGuard::new(100);
+        let (mut reorder_seen, mut sync_seen) = (guard.generation(), guard.generation());
+        assert!(!guard.take_reset(&mut reorder_seen));
+
+        guard.trigger();
+        assert!(guard.take_reset(&mut reorder_seen));
+        assert!(!guard.take_reset(&mut reorder_seen));
+        assert!(guard.take_reset(&mut sync_seen));
+    }
+}
@@ -2,23 +2,35 @@
 //!
 //! This module contains the receiver-side pipeline stages:
 //! - ReceiveStage: RTP packet reception
+//! - NetworkImpairment: optional simulated loss/jitter before reordering (debug)
 //! - ReorderStage: Packet reordering + jitter buffer
 //! - DecodeStage: H.264/Opus decoding
 //! - SyncStage: Audio-video synchronization
+//! - LatencyProfile: jitter/sync presets (low latency ↔ smooth)
+//! - LatencyGuard: flushes to the next keyframe when buffered media gets too old
+//! - AvOffset: manual lip-sync correction set by the user
 //!
 //! The receiver pipeline flow:
 //! ```text
 //! Network → Receive → Reorder → Decode → Sync → Display/Audio Output
 //! ```
 
+pub mod av_offset;
 pub mod coordinator;
 pub mod decode_stage;
+pub mod impairment;
+pub mod latency;
+pub mod latency_guard;
 pub mod receive_stage;
 pub mod reorder_stage;
 pub mod sync_stage;
 
+pub use av_offset::{AvOffset, DelayLine};
 pub use coordinator::ReceiverCoordinator;
-pub use decode_stage::{DecodeStage, TimedVideoFrame};
+pub use decode_stage::{DecodeStage, DecoderRecovery, Recovery, TimedVideoFrame};
+pub use impairment::{ImpairmentConfig, NetworkImpairment};
+pub use latency::LatencyProfile;
+pub use latency_guard::LatencyGuard;
 pub use receive_stage::ReceiveStage;
 pub use reorder_stage::{JitterBuffer, ReorderConfig, ReorderStage, RtpPacket};
 pub use sync_stage::{AudioPlaybackTracker, SyncConfig, SyncStage};
@@ -13,7 +13,10 @@ use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
 use crate::pipeline::PipelineStage;
-use crate::pipeline::health::PipelineHealth;
+use crate::pipeline::health::{DropSource, PipelineHealth};
+use crate::pipeline::metrics::{Stage, StageMetrics};
+use crate::pipeline::nack::NackTracker;
+use crate::pipeline::receiver::latency_guard::LatencyGuard;
 
 /// An RTP packet with metadata for reordering
 #[derive(Debug, Clone)]
@@ -64,6 +67,10 @@ pub struct JitterBuffer {
     packets_received: u64,
     packets_reordered: u64,
     packets_lost: u64,
+    /// Decides which missing packets to re-request from the sender
+    nack: Option<NackTracker>,
+    /// Sequence numbers to request, collected by `drain_ready`
+    nack_requests: Vec<u16>,
 }
 
 impl JitterBuffer {
@@ -76,14 +83,29 @@ impl JitterBuffer {
             packets_received: 0,
             packets_reordered: 0,
             packets_lost: 0,
+            nack: None,
+            nack_requests: Vec::new(),
         }
     }
 
+    /// Collect NACKs for missing packets while waiting for them
+    pub fn enable_nack(&mut self) {
+        self.nack.get_or_insert_with(NackTracker::new);
+    }
+
+    /// Sequence numbers to ask the sender to retransmit
+    pub fn take_nack_requests(&mut self) -> Vec<u16> {
+        std::mem::take(&mut self.nack_requests)
+    }
+
     /// Insert a packet into the jitter buffer
     pub fn insert(&mut self, packet: RtpPacket) {
         self.packets_received += 1;
 
         let seq = packet.sequence_number;
+        if let Some(nack) = &mut self.nack {
+            nack.received(seq);
+        }
 
         if self.expected_seq.is_none() {
             self.expected_seq = Some(seq);
@@ -146,12 +168,19 @@ impl JitterBuffer {
                         break;
                     }
                 } else {
-                    // Expected packet missing - check if it's time to skip
+                    // Expected packet missing - ask for a retransmission
+                    let front_seq = front.sequence_number;
+                    self.request_missing(expected, front_seq, now);
+
+                    // Check if it's time to skip
                     let oldest_time = self.buffer.front().map(|p| p.received_at);
                     if let Some(oldest) = oldest_time {
                         let wait_time = now.duration_since(oldest);
                         if wait_time > self.config.jitter_delay * 2 {
                             // Packet is too late, consider it lost
+                            if let Some(nack) = &mut self.nack {
+                                nack.lost(expected);
+                            }
                             self.packets_lost += 1;
                             self.expected_seq = Some(expected.wrapping_add(1));
                             continue; // Try next expected sequence
@@ -167,6 +196,21 @@ impl JitterBuffer {
         output
     }
 
+    /// Queue NACKs for the gap `expected .. front_seq`
+    fn request_missing(&mut self, expected: u16, front_seq: u16, now: Instant) {
+        let Some(nack) = &mut self.nack else {
+            return;
+        };
+        let gap = front_seq
+            .wrapping_sub(expected)
+            .min(self.config.max_reorder_distance);
+        for seq in (0..gap).map(|i| expected.wrapping_add(i)) {
+            if nack.should_request(seq, now) {
+                self.nack_requests.push(seq);
+            }
+        }
+    }
+
     /// Force drain all buffered packets (for shutdown)
     pub fn drain_all(&mut self) -> Vec<RtpPacket> {
         let mut result: Vec<RtpPacket> = self.buffer.drain(..).collect();
@@ -174,6 +218,25 @@ impl JitterBuffer {
         result
     }
 
+    /// How long the oldest buffered packet has been waiting
+    pub fn oldest_age(&self) -> Duration {
+        self.buffer
+            .iter()
+            .map(|p| p.received_at)
+            .min()
+            .map(|t| t.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// Discard everything and resync on the next packet; returns how many
+    /// packets were dropped
+    pub fn flush(&mut self) -> usize {
+        let dropped = self.buffer.len();
+        self.buffer.clear();
+        self.expected_seq = None;
+        dropped
+    }
+
     /// Check if seq_a comes after seq_b (handling wrapping)
     fn seq_comes_after(&self, seq_a: u16, seq_b: u16) -> bool {
         let diff = seq_a.wrapping_sub(seq_b);
@@ -202,6 +265,11 @@ impl JitterBuffer {
         }
     }
 
+    /// Packets retransmitted after a NACK
+    pub fn recovered(&self) -> u64 {
+        self.nack.as_ref().map_or(0, NackTracker::recovered)
+    }
+
     /// Get statistics
     pub fn stats(&self) -> (u64, u64, u64, usize) {
         (
@@ -217,16 +285,90 @@ impl JitterBuffer {
 pub struct ReorderStage {
     jitter_buffer: JitterBuffer,
     input_rx: Option<mpsc::Receiver<RtpPacket>>,
+    /// Retransmission requests back to the sender, if enabled
+    nack_tx: Option<mpsc::Sender<u16>>,
     output_tx: Option<mpsc::Sender<RtpPacket>>,
+    metrics: Option<Arc<StageMetrics>>,
+    /// Max-latency guard and the last reset generation handled
+    latency_guard: Option<(LatencyGuard, u64)>,
+    health: Arc<PipelineHealth>,
+    /// Losses of the jitter buffer already added to `health`
+    reported_lost: u64,
 }
 
 impl ReorderStage {
     /// Create a new reorder stage
-    pub fn new(config: ReorderConfig, _health: Arc<PipelineHealth>) -> Self {
+    pub fn new(config: ReorderConfig, health: Arc<PipelineHealth>) -> Self {
         Self {
             jitter_buffer: JitterBuffer::new(config),
             input_rx: None,
+            nack_tx: None,
             output_tx: None,
+            metrics: None,
+            latency_guard: None,
+            health,
+            reported_lost: 0,
+        }
+    }
+
+    /// Ask the sender on `tx` to retransmit packets missing from the buffer
+    pub fn with_nack(mut self, tx: mpsc::Sender<u16>) -> Self {
+        self.jitter_buffer.enable_nack();
+        self.nack_tx = Some(tx);
+        self
+    }
+
+    fn send_nacks(&mut self) {
+        let Some(tx) = &self.nack_tx else {
+            return;
+        };
+        for seq in self.jitter_buffer.take_nack_requests() {
+            // Canale pieno: la richiesta verrà ripetuta al prossimo drain
+            if tx.try_send(seq).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Report the jitter buffer backlog and flush it on a latency reset
+    pub fn with_latency_guard(mut self, guard: LatencyGuard) -> Self {
+        let generation = guard.generation();
+        self.latency_guard = Some((guard, generation));
+        self
+    }
+
+    /// Packets given up as lost since the last call, counted as reorder losses
+    fn record_losses(&mut self) {
+        let (_, _, lost, _) = self.jitter_buffer.stats();
+        if lost > self.reported_lost {
+            self.health
+                .record_frame_drops(DropSource::Reorder, lost - self.reported_lost);
+            self.reported_lost = lost;
+        }
+    }
+
+    fn check_latency(&mut self) {
+        let Some((guard, seen)) = &mut self.latency_guard else {
+            return;
+        };
+        if guard.take_reset(seen) {
+            let dropped = self.jitter_buffer.flush();
+            warn!("ReorderStage: latency reset, {} packets flushed", dropped);
+        }
+        guard.report_reorder(self.jitter_buffer.oldest_age());
+    }
+
+    /// Record hold time and buffer depth into shared stage metrics
+    pub fn with_metrics(mut self, metrics: Arc<StageMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Record how long a released packet sat in the jitter buffer
+    fn record_release(&self, packet: &RtpPacket) {
+        if let Some(metrics) = &self.metrics {
+            let (_, _, _, buffered) = self.jitter_buffer.stats();
+            metrics.record(Stage::Reorder, packet.received_at.elapsed(), buffered);
         }
     }
 
@@ -235,9 +377,9 @@ impl ReorderStage {
         self.input_rx = Some(rx);
     }
 
-    /// Get the output channel
-    pub fn take_output(&mut self) -> mpsc::Receiver<RtpPacket> {
-        let (tx, rx) = mpsc::channel::<RtpPacket>(256);
+    /// Get the output channel, holding up to `capacity` packets
+    pub fn take_output(&mut self, capacity: usize) -> mpsc::Receiver<RtpPacket> {
+        let (tx, rx) = mpsc::channel::<RtpPacket>(capacity);
         self.output_tx = Some(tx);
         rx
     }
@@ -264,10 +406,15 @@ impl PipelineStage for ReorderStage {
                 packet = input_rx.recv() => {
                     match packet {
                         Some(pkt) => {
+                            self.check_latency();
                             self.jitter_buffer.insert(pkt);
 
                             // Drain ready packets
-                            for ready_pkt in self.jitter_buffer.drain_ready() {
+                            let ready = self.jitter_buffer.drain_ready();
+                            self.send_nacks();
+                            self.record_losses();
+                            for ready_pkt in ready {
+                                self.record_release(&ready_pkt);
                                 if output_tx.send(ready_pkt).await.is_err() {
                                     info!("ReorderStage: output channel closed");
                                     return Ok(());
@@ -284,8 +431,14 @@ impl PipelineStage for ReorderStage {
                     }
                 }
                 _ = tokio::time::sleep(drain_interval) => {
+                    self.check_latency();
+
                     // Periodically drain ready packets even without new input
-                    for ready_pkt in self.jitter_buffer.drain_ready() {
+                    let ready = self.jitter_buffer.drain_ready();
+                    self.send_nacks();
+                    self.record_losses();
+                    for ready_pkt in ready {
+                        self.record_release(&ready_pkt);
                         if output_tx.send(ready_pkt).await.is_err() {
                             return Ok(());
                         }
@@ -297,8 +450,12 @@ impl PipelineStage for ReorderStage {
             if last_stats_log.elapsed().as_secs() >= 30 {
                 let (received, reordered, lost, buffered) = self.jitter_buffer.stats();
                 info!(
-                    "ReorderStage: {} received, {} reordered, {} lost, {} buffered",
-                    received, reordered, lost, buffered
+                    "ReorderStage: {} received, {} reordered, {} lost, {} recovered, {} buffered",
+                    received,
+                    reordered,
+                    lost,
+                    self.jitter_buffer.recovered(),
+                    buffered
                 );
                 last_stats_log = Instant::now();
             }
@@ -452,4 +609,54 @@ mod tests {
         assert!(reordered > 0);
         assert_eq!(buffered, 3);
     }
+
+    #[test]
+    fn test_flush_resyncs_on_next_packet() {
+        let config = ReorderConfig {
+            jitter_delay: Duration::from_millis(0),
+            ..Default::default()
+        };
+        let mut jb = JitterBuffer::new(config);
+
+        jb.insert(make_packet(1));
+        jb.insert(make_packet(3));
+        assert_eq!(jb.flush(), 2);
+        assert_eq!(jb.oldest_age(), Duration::ZERO);
+
+        // After a flush the sequence restarts from the next packet received
+        jb.insert(make_packet(40));
+        let ready = jb.drain_ready();
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].sequence_number, 40);
+    }
+
+    #[test]
+    fn test_nack_requests_gap_once() {
+        let config = ReorderConfig {
+            jitter_delay: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let mut jb = JitterBuffer::new(config);
+        jb.enable_nack();
+
+        jb.insert(make_packet(1));
+        jb.insert(make_packet(4));
+        std::thread::sleep(Duration::from_millis(60));
+        let ready = jb.drain_ready();
+        assert_eq!(ready.len(), 1);
+        assert_eq!(jb.take_nack_requests(), vec![2, 3]);
+
+        // Still within the retry interval: nothing new to ask for
+        jb.drain_ready();
+        assert!(jb.take_nack_requests().is_empty());
+
+        // The retransmissions fill the gap
+        jb.insert(make_packet(2));
+        jb.insert(make_packet(3));
+        std::thread::sleep(Duration::from_millis(60));
+        let seqs: Vec<u16> = jb.drain_ready().iter().map(|p| p.sequence_number).collect();
+        assert_eq!(seqs, vec![2, 3, 4]);
+        assert_eq!(jb.recovered(), 2);
+        assert_eq!(jb.stats().2, 0);
+    }
 }
@@ -13,10 +13,14 @@ use std::sync::atomic::{AtomicI64, Ordering};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
-use crate::decoder::VideoFrame;
+use crate::decoder::{PixelLayout, VideoFrame};
+use crate::display::DisplayPolicy;
 use crate::pipeline::PipelineStage;
-use crate::pipeline::health::PipelineHealth;
+use crate::pipeline::health::{DropSource, PipelineHealth};
+use crate::pipeline::metrics::{self, Stage, StageMetrics};
+use crate::pipeline::receiver::av_offset::AvOffset;
 use crate::pipeline::receiver::decode_stage::TimedVideoFrame;
+use crate::pipeline::receiver::latency_guard::LatencyGuard;
 
 /// Configuration for A/V synchronization
 #[derive(Debug, Clone)]
@@ -113,6 +117,16 @@ impl AudioPlaybackTracker {
 /// 4. If yes, release the video frame
 /// 5. If video is too far behind audio, drop video frames to catch up
 /// 6. If video is too far ahead of audio, wait
+/// 7. With [`DisplayPolicy::Latency`], a due frame superseded by a newer due
+///    frame is dropped instead of being released one tick later
+///
+/// The manual [`AvOffset`] shifts the audio position video is compared to:
+/// a positive offset holds video back, a negative one releases it earlier.
+///
+/// With smooth motion the queue is checked on a fixed display cadence instead
+/// of every 5ms, and only the newest due frame is presented: an uneven capture
+/// cadence is evened out, while the display keeps showing the last frame when
+/// nothing new is due.
 pub struct SyncStage {
     /// Video frame queue ordered by PTS
     video_queue: VecDeque<TimedVideoFrame>,
@@ -131,6 +145,14 @@ pub struct SyncStage {
     /// Statistics
     frames_released: u64,
     frames_dropped: u64,
+    /// Stage metrics + clock base the PTS are relative to (glass-to-glass)
+    metrics: Option<(Arc<StageMetrics>, Instant)>,
+    policy: DisplayPolicy,
+    av_offset: AvOffset,
+    /// Max-latency guard and the last reset generation handled
+    latency_guard: Option<(LatencyGuard, u64)>,
+    /// Present on this fixed cadence, holding the latest frame
+    pacing: Option<Duration>,
 }
 
 impl SyncStage {
@@ -146,7 +168,109 @@ impl SyncStage {
             playout_start: None,
             frames_released: 0,
             frames_dropped: 0,
+            metrics: None,
+            policy: DisplayPolicy::default(),
+            av_offset: AvOffset::default(),
+            latency_guard: None,
+            pacing: None,
+        }
+    }
+
+    /// Present frames on the display cadence `interval` (smooth motion).
+    /// Staged pipeline only (loopback and tests): the live receiver paces
+    /// in its display task, at the monitor's refresh rate.
+    pub fn with_pacing(mut self, interval: Duration) -> Self {
+        self.pacing = Some(interval);
+        self
+    }
+
+    /// Manual lip-sync correction, read on every tick
+    pub fn with_av_offset(mut self, av_offset: AvOffset) -> Self {
+        self.av_offset = av_offset;
+        self
+    }
+
+    /// Whether frames that are already due get skipped to the newest one
+    pub fn with_display_policy(mut self, policy: DisplayPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Report the queue backlog and flush it on a latency reset
+    pub fn with_latency_guard(mut self, guard: LatencyGuard) -> Self {
+        let generation = guard.generation();
+        self.latency_guard = Some((guard, generation));
+        self
+    }
+
+    fn check_latency(&mut self) {
+        let Some((guard, seen)) = &mut self.latency_guard else {
+            return;
+        };
+        if guard.take_reset(seen) {
+            let dropped = self.video_queue.len() as u64;
+            self.video_queue.clear();
+            self.frames_dropped += dropped;
+            self.health.record_frame_drops(DropSource::Sync, dropped);
+        }
+        let age = self
+            .video_queue
+            .front()
+            .map(|f| f.decoded_at.elapsed())
+            .unwrap_or_default();
+        guard.report_sync(age);
+    }
+
+    fn record_drop(&mut self) {
+        self.frames_dropped += 1;
+        self.health.record_frame_drop(DropSource::Sync);
+    }
+
+    /// Latency policy or pacing: drop `frame` (and the next ones) while a newer
+    /// frame is already due; `due_us` is the release limit, `None` in passthrough.
+    fn skip_superseded(
+        &mut self,
+        mut frame: TimedVideoFrame,
+        due_us: Option<i64>,
+    ) -> TimedVideoFrame {
+        if self.policy != DisplayPolicy::Latency && self.pacing.is_none() {
+            return frame;
+        }
+        while self
+            .video_queue
+            .front()
+            .is_some_and(|next| due_us.is_none_or(|due| next.pts.micros <= due))
+        {
+            frame = self.video_queue.pop_front().unwrap();
+            self.record_drop();
         }
+        frame
+    }
+
+    /// Record queue time, depth and glass-to-glass latency; `clock_base` is
+    /// the `MediaClock` base the frame PTS are relative to.
+    pub fn with_metrics(mut self, metrics: Arc<StageMetrics>, clock_base: Instant) -> Self {
+        self.metrics = Some((metrics, clock_base));
+        self
+    }
+
+    /// Hand a frame to the output, recording how long it waited for sync
+    fn release(&mut self, frame: TimedVideoFrame, output: &mut Vec<VideoFrame>) {
+        self.frames_released += 1;
+        if let Some((stage_metrics, clock_base)) = &self.metrics {
+            let now = Instant::now();
+            stage_metrics.record(
+                Stage::Sync,
+                now.saturating_duration_since(frame.decoded_at),
+                self.video_queue.len(),
+            );
+            stage_metrics.record_glass_to_glass(metrics::glass_to_glass(
+                *clock_base,
+                frame.pts.micros,
+                now,
+            ));
+        }
+        output.push(frame.frame);
     }
 
     /// Get a shared reference to the audio position tracker
@@ -159,17 +283,18 @@ impl SyncStage {
         self.video_input_rx = Some(rx);
     }
 
-    /// Get the video output channel
-    pub fn take_video_output(&mut self) -> mpsc::Receiver<VideoFrame> {
-        let (tx, rx) = mpsc::channel::<VideoFrame>(3);
+    /// Get the video output channel, `capacity` frames deep
+    pub fn take_video_output(&mut self, capacity: usize) -> mpsc::Receiver<VideoFrame> {
+        let (tx, rx) = mpsc::channel::<VideoFrame>(capacity);
         self.video_output_tx = Some(tx);
         rx
     }
 
     /// Process video queue: release frames whose PTS is ready
     fn process_video_queue(&mut self) -> Vec<VideoFrame> {
+        self.check_latency();
         let mut output = Vec::new();
-        let audio_pos_us = self.audio_tracker.position();
+        let audio_pos_us = self.audio_tracker.position() - self.av_offset.us();
 
         // If audio hasn't started yet, check playout delay
         if !self.audio_tracker.is_started()
@@ -195,13 +320,12 @@ impl SyncStage {
                     // Check if frame is too old (behind audio by more than max_drift)
                     if audio_pos_us - video_pts_us > max_drift_us {
                         // Frame is too late, drop it
-                        self.frames_dropped += 1;
-                        self.health.record_frame_drop();
+                        self.record_drop();
                         continue; // Check next frame
                     }
 
-                    self.frames_released += 1;
-                    output.push(frame.frame);
+                    let frame = self.skip_superseded(frame, Some(audio_pos_us + tolerance_us));
+                    self.release(frame, &mut output);
                     break; // Release one frame per tick
                 } else {
                     // Video is ahead of audio, wait
@@ -210,8 +334,8 @@ impl SyncStage {
             } else {
                 // No audio reference - release immediately (passthrough mode)
                 let frame = self.video_queue.pop_front().unwrap();
-                self.frames_released += 1;
-                output.push(frame.frame);
+                let frame = self.skip_superseded(frame, None);
+                self.release(frame, &mut output);
                 break;
             }
         }
@@ -219,8 +343,7 @@ impl SyncStage {
         // Drop excess frames if queue is too large
         while self.video_queue.len() > self.config.max_video_queue {
             self.video_queue.pop_front();
-            self.frames_dropped += 1;
-            self.health.record_frame_drop();
+            self.record_drop();
         }
 
         output
@@ -247,6 +370,9 @@ impl PipelineStage for SyncStage {
 
         let mut last_stats_log = Instant::now();
         let sync_tick = Duration::from_millis(5); // Check sync every 5ms
+        // Smooth motion: new frames wait for the next present tick
+        let mut present = tokio::time::interval(self.pacing.unwrap_or(sync_tick));
+        present.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
         loop {
             tokio::select! {
@@ -254,6 +380,9 @@ impl PipelineStage for SyncStage {
                     match frame {
                         Some(timed_frame) => {
                             self.video_queue.push_back(timed_frame);
+                            if self.pacing.is_some() {
+                                continue;
+                            }
 
                             // Process queue
                             for vf in self.process_video_queue() {
@@ -273,7 +402,7 @@ impl PipelineStage for SyncStage {
                         }
                     }
                 }
-                _ = tokio::time::sleep(sync_tick) => {
+                _ = present.tick() => {
                     // Periodic sync check
                     for vf in self.process_video_queue() {
                         if video_output.send(vf).await.is_err() {
@@ -325,10 +454,12 @@ mod tests {
                 data: vec![0u8; (w * h * 3 / 2) as usize],
                 width: w,
                 height: h,
+                layout: PixelLayout::I420,
             },
             pts: Timestamp::from_micros(pts_us),
             correlation_id: 0,
             is_keyframe: false,
+            decoded_at: Instant::now(),
         }
     }
 
@@ -414,4 +545,111 @@ mod tests {
         assert_eq!(output.len(), 1);
         assert_eq!(stage.frames_dropped, 1);
     }
+
+    #[test]
+    fn test_av_offset_biases_release() {
+        let config = SyncConfig {
+            playout_delay: Duration::from_millis(0),
+            frame_tolerance: Duration::from_millis(33),
+            max_drift: Duration::from_millis(200),
+            ..Default::default()
+        };
+        let health = Arc::new(PipelineHealth::new());
+        let av_offset = AvOffset::new(100);
+        let mut stage = SyncStage::new(config, health).with_av_offset(av_offset.clone());
+        stage.playout_start = Some(Instant::now());
+        stage.audio_tracker.mark_started();
+        stage.audio_tracker.update_position(100_000);
+        stage
+            .video_queue
+            .push_back(make_timed_frame(90_000, 320, 240));
+
+        // Video held back by 100ms: not due yet
+        assert!(stage.process_video_queue().is_empty());
+
+        // Audio later instead: the frame becomes due
+        av_offset.set_ms(-100);
+        assert_eq!(stage.process_video_queue().len(), 1);
+    }
+
+    fn stage_with_due_frames(policy: DisplayPolicy) -> SyncStage {
+        let config = SyncConfig {
+            playout_delay: Duration::from_millis(0),
+            frame_tolerance: Duration::from_millis(33),
+            max_drift: Duration::from_millis(200),
+            ..Default::default()
+        };
+        let health = Arc::new(PipelineHealth::new());
+        let mut stage = SyncStage::new(config, health).with_display_policy(policy);
+        stage.playout_start = Some(Instant::now());
+        stage.audio_tracker.mark_started();
+        stage.audio_tracker.update_position(100_000);
+
+        // Three frames already due, one still ahead of audio
+        for pts in [40_000, 60_000, 90_000, 300_000] {
+            stage.video_queue.push_back(make_timed_frame(pts, 320, 240));
+        }
+        stage
+    }
+
+    #[test]
+    fn test_latency_policy_skips_to_newest_due_frame() {
+        let mut stage = stage_with_due_frames(DisplayPolicy::Latency);
+
+        let output = stage.process_video_queue();
+        assert_eq!(output.len(), 1);
+        assert_eq!(stage.frames_dropped, 2);
+        assert_eq!(stage.health.frame_drops(), 2);
+        // Only the frame ahead of audio is left
+        assert_eq!(stage.video_queue.len(), 1);
+    }
+
+    #[test]
+    fn test_completeness_policy_releases_every_frame() {
+        let mut stage = stage_with_due_frames(DisplayPolicy::Completeness);
+
+        let released: usize = (0..3).map(|_| stage.process_video_queue().len()).sum();
+        assert_eq!(released, 3);
+        assert_eq!(stage.frames_dropped, 0);
+        assert_eq!(stage.video_queue.len(), 1);
+    }
+
+    #[test]
+    fn test_latency_reset_flushes_queue() {
+        let guard = LatencyGuard::new(100);
+        let mut stage =
+            stage_with_due_frames(DisplayPolicy::Completeness).with_latency_guard(guard.clone());
+
+        guard.trigger();
+        let output = stage.process_video_queue();
+        assert!(output.is_empty());
+        assert!(stage.video_queue.is_empty());
+        assert_eq!(stage.health.frame_drops(), 4);
+        assert_eq!(guard.buffered(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_pacing_presents_newest_due_frame() {
+        let config = SyncConfig {
+            playout_delay: Duration::from_millis(0),
+            frame_tolerance: Duration::from_millis(33),
+            max_drift: Duration::from_millis(200),
+            ..Default::default()
+        };
+        let health = Arc::new(PipelineHealth::new());
+        let mut stage = SyncStage::new(config, health)
+            .with_display_policy(DisplayPolicy::Completeness)
+            .with_pacing(Duration::from_millis(16));
+        stage.playout_start = Some(Instant::now());
+        stage.audio_tracker.mark_started();
+        stage.audio_tracker.update_position(100_000);
+        for pts in [40_000, 60_000, 90_000, 300_000] {
+            stage.video_queue.push_back(make_timed_frame(pts, 320, 240));
+        }
+
+        // Even with the completeness policy only the newest due frame is shown
+        assert_eq!(stage.process_video_queue().len(), 1);
+        assert_eq!(stage.frames_dropped, 2);
+        assert_eq!(stage.video_queue.len(), 1);
+    }
 }
@@ -0,0 +1,81 @@
+//! Loss recovery mode for an RTP video session
+//!
+//! NACK costs nothing until a packet is lost but needs the resend to arrive
+//! within the jitter delay. It is turned on or off per session through
+//! [`RECOVERY_ENV`], e.g. `CASTIFY_RECOVERY=nack` or `CASTIFY_RECOVERY=off`.
+//! WebRTC sessions use NACK unless it is turned `off`, see [`LossRecovery::for_webrtc`].
+
+use anyhow::{Result, bail};
+use log::warn;
+use std::str::FromStr;
+
+/// Variabile d'ambiente con la modalità di recupero delle perdite
+pub const RECOVERY_ENV: &str = "CASTIFY_RECOVERY";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LossRecovery {
+    /// Lost packets are skipped by the jitter buffer
+    #[default]
+    Off,
+    /// Retransmission on request, see [`crate::pipeline::nack`]
+    Nack,
+}
+
+impl LossRecovery {
+    /// Modalità da [`RECOVERY_ENV`], `Off` se assente o non valida
+    pub fn from_env() -> Self {
+        let Ok(spec) = std::env::var(RECOVERY_ENV) else {
+            return Self::Off;
+        };
+        match spec.parse::<Self>() {
+            Ok(mode) => mode,
+            Err(e) => {
+                warn!("Ignoring {}={:?}: {:#}", RECOVERY_ENV, spec, e);
+                Self::Off
+            }
+        }
+    }
+
+    /// Modalità di una sessione WebRTC: NACK tramite gli interceptor di
+    /// webrtc-rs, salvo `off` esplicito
+    pub fn for_webrtc() -> Self {
+        match std::env::var(RECOVERY_ENV) {
+            Ok(spec) => spec.parse::<Self>().unwrap_or_else(|e| {
+                warn!("Ignoring {}={:?}: {:#}", RECOVERY_ENV, spec, e);
+                Self::Nack
+            }),
+            Err(_) => Self::Nack,
+        }
+    }
+}
+
+/// `off` or `nack`
+impl FromStr for LossRecovery {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        match spec.trim().to_ascii_lowercase().as_str() {
+            "off" | "" => Ok(Self::Off),
+            "nack" => Ok(Self::Nack),
+            _ => bail!("expected off or nack"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_modes() {
+        assert_eq!("off".parse::<LossRecovery>().unwrap(), LossRecovery::Off);
+        assert_eq!(
+            " NACK ".parse::<LossRecovery>().unwrap(),
+            LossRecovery::Nack
+        );
+        assert!("nack=3".parse::<LossRecovery>().is_err());
+        // FEC non arriva ai peer WebRTC: non è una modalità selezionabile
+        assert!("fec".parse::<LossRecovery>().is_err());
+        assert!("fec=20".parse::<LossRecovery>().is_err());
+    }
+}
@@ -6,11 +6,10 @@ use crate::assets::FRAME_RATE;
 use crate::capture::capturer::CaptureOpts;
 use crate::capture::display::DisplaySelector;
 use crate::capture::{ScreenCapture, ScreenCaptureImpl};
-use crate::encoder::FfmpegEncoder;
-use crate::pipeline::PipelineStage;
+use crate::encoder::{EncodedVideo, FfmpegEncoder};
+use crate::pipeline::{PipelineStage, PipelineTuning};
 use anyhow::Result;
 use async_trait::async_trait;
-use bytes::Bytes;
 use log::{error, info};
 use std::sync::Arc;
 use tokio::sync::{mpsc, watch};
@@ -23,7 +22,7 @@ pub struct CaptureStage {
     capture: Arc<tokio::sync::Mutex<ScreenCaptureImpl>>,
     opts_tx: watch::Sender<CaptureOpts>,
     opts_rx: watch::Receiver<CaptureOpts>,
-    output_tx: Option<mpsc::Sender<Bytes>>,
+    output_tx: Option<mpsc::Sender<EncodedVideo>>,
     is_running: bool,
 }
 
@@ -38,6 +37,22 @@ impl CaptureStage {
             crop: None,
             paused: false,
             max_fps: FRAME_RATE,
+            fps_ceiling: FRAME_RATE,
+            profile: Default::default(),
+            scale_to: None,
+            show_cursor: true,
+            cursor_highlight: None,
+            keycast: None,
+            zoom: None,
+            data_budget: None,
+            watermark: None,
+            timestamp: None,
+            content_aware: false,
+            privacy_exclude: Vec::new(),
+            hdr: Default::default(),
+            bitrate_mode: Default::default(),
+            frame_pool: PipelineTuning::default().frame_pool,
+            max_slice_size: None,
         };
         let (opts_tx, opts_rx) = watch::channel(default_opts);
 
@@ -51,15 +66,15 @@ impl CaptureStage {
     }
 
     /// Get the output channel for encoded frames
-    pub fn take_output(&mut self) -> Option<mpsc::Receiver<Bytes>> {
-        let (tx, rx) = mpsc::channel::<Bytes>(16);
+    pub fn take_output(&mut self) -> Option<mpsc::Receiver<EncodedVideo>> {
+        let (tx, rx) = mpsc::channel::<EncodedVideo>(16);
         self.output_tx = Some(tx);
         Some(rx)
     }
 
     /// Start capture with the given encoder dimensions
     pub async fn start_capture(&mut self, enc_w: u32, enc_h: u32) -> Result<()> {
-        let encoder = FfmpegEncoder::new(enc_w, enc_h);
+        let encoder = FfmpegEncoder::new(enc_w, enc_h)?;
         let output_tx = self
             .output_tx
             .take()
@@ -89,9 +104,23 @@ impl CaptureStage {
         self.opts_tx.send_modify(|o| o.blank_screen = blank);
     }
 
-    /// Set crop rectangle
-    pub fn set_crop(&self, crop: Option<crate::capture::CropRect>) {
+    /// Set crop rectangle, clamped to the capture display
+    pub fn set_crop(&self, crop: Option<crate::capture::CropRect>) -> Result<()> {
+        let crop = match crop {
+            None => None,
+            Some(rect) => {
+                let display = self
+                    .capture
+                    .try_lock()
+                    .map(|cap| cap.display().resolution())
+                    .map_err(|_| anyhow::anyhow!("Capture is busy, crop not applied"))?;
+                Some(rect.clamped(display).ok_or_else(|| {
+                    anyhow::anyhow!("Crop {:?} is outside the display {:?}", rect, display)
+                })?)
+            }
+        };
         self.opts_tx.send_modify(|o| o.crop = crop);
+        Ok(())
     }
 
     /// Get current capture resolution
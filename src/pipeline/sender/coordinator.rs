@@ -10,7 +10,7 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use tokio_util::sync::CancellationToken;
 
 use crate::capture::ScreenCaptureImpl;
-use crate::capture::audio::AudioCapture;
+use crate::capture::audio::{AudioCapture, AudioEncodeConfig};
 use crate::capture::capturer::CropRect;
 use crate::capture::display::DisplaySelector;
 use crate::gui::common::datastructure::ScreenRect;
@@ -18,6 +18,7 @@ use crate::pipeline::clock::MediaClock;
 use crate::pipeline::health::PipelineHealth;
 use crate::pipeline::sender::capture_stage::CaptureStage;
 use crate::pipeline::state::PipelineState;
+use crate::utils::audio_level::AudioLevel;
 use crate::utils::net::webrtc::WebRTCServer;
 use crate::utils::sos::SignalOfStop;
 
@@ -102,7 +103,8 @@ impl SenderCoordinator {
 
         // mDNS + port forwarding
         self.sos.spawn(async move {
-            match crate::utils::net::common::caster_discover_service() {
+            let name = crate::utils::net::common::default_instance_name();
+            match crate::utils::net::common::caster_discover_service(&name) {
                 Ok(_) => info!("SenderCoordinator: registered on mDNS"),
                 Err(e) => error!("mDNS Error: {}", e),
             }
@@ -113,10 +115,18 @@ impl SenderCoordinator {
 
         // Start audio capture
         let audio_cancel = CancellationToken::new();
-        match AudioCapture::start(audio_cancel.clone()) {
+        match AudioCapture::start(
+            audio_cancel.clone(),
+            AudioEncodeConfig::default(),
+            AudioLevel::default(),
+            Arc::clone(&self.audio_muted),
+            self.clock.clone(),
+        ) {
             Ok(audio_rx) => {
                 info!("SenderCoordinator: audio capture started");
-                self.server.get_handler().send_audio_frames(audio_rx);
+                self.server
+                    .get_handler()
+                    .send_audio_frames(audio_rx, AudioEncodeConfig::default().frame_duration());
             }
             Err(e) => {
                 error!("Failed to start audio capture: {}", e);
@@ -237,27 +247,11 @@ impl SenderCoordinator {
         self.audio_muted.load(Ordering::Relaxed)
     }
 
+    /// Silence the outgoing audio without stopping the capture thread
     pub fn toggle_audio_mute(&mut self) {
         let muted = !self.audio_muted.load(Ordering::Relaxed);
         self.audio_muted.store(muted, Ordering::Relaxed);
-        if muted {
-            if let Some(cancel) = self.audio_cancel.take() {
-                cancel.cancel();
-                info!("Audio muted");
-            }
-        } else {
-            let audio_cancel = CancellationToken::new();
-            match AudioCapture::start(audio_cancel.clone()) {
-                Ok(audio_rx) => {
-                    self.server.get_handler().send_audio_frames(audio_rx);
-                    info!("Audio unmuted");
-                }
-                Err(e) => {
-                    error!("Failed to restart audio capture: {}", e);
-                }
-            }
-            self.audio_cancel = Some(audio_cancel);
-        }
+        info!("Audio {}", if muted { "muted" } else { "unmuted" });
     }
 
     // ── Crop / area ─────────────────────────────────────────────
@@ -268,8 +262,13 @@ impl SenderCoordinator {
         } else {
             None
         };
-        self.capture_stage.set_crop(crop);
-        true
+        match self.capture_stage.set_crop(crop) {
+            Ok(()) => true,
+            Err(e) => {
+                error!("Ignoring recording area: {}", e);
+                false
+            }
+        }
     }
 
     // ── WebRTC ──────────────────────────────────────────────────
@@ -4,14 +4,14 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
-use bytes::Bytes;
 use log::info;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use tokio::sync::mpsc;
 
+use crate::encoder::EncodedVideo;
 use crate::pipeline::PipelineStage;
-use crate::pipeline::clock::MediaClock;
+use crate::pipeline::clock::{CaptureTimeline, MediaClock};
 use crate::pipeline::health::PipelineHealth;
 use crate::pipeline::types::{MediaFrame, MediaKind};
 
@@ -24,10 +24,12 @@ pub struct EncodeStage {
     pub force_idr: Arc<AtomicBool>,
     /// Media clock for timestamp correlation
     clock: MediaClock,
+    /// Capture times of the encoded frames mapped onto `clock`
+    timeline: CaptureTimeline,
     /// Health metrics
     health: Arc<PipelineHealth>,
     /// Input channel (raw H.264 from capture)
-    input_rx: Option<mpsc::Receiver<Bytes>>,
+    input_rx: Option<mpsc::Receiver<EncodedVideo>>,
     /// Output channel (MediaFrame with PTS)
     output_tx: Option<mpsc::Sender<MediaFrame>>,
 }
@@ -38,6 +40,7 @@ impl EncodeStage {
         Self {
             force_idr: Arc::new(AtomicBool::new(false)),
             clock,
+            timeline: CaptureTimeline::default(),
             health,
             input_rx: None,
             output_tx: None,
@@ -45,7 +48,7 @@ impl EncodeStage {
     }
 
     /// Set the input channel (encoded frames from CaptureStage)
-    pub fn set_input(&mut self, rx: mpsc::Receiver<Bytes>) {
+    pub fn set_input(&mut self, rx: mpsc::Receiver<EncodedVideo>) {
         self.input_rx = Some(rx);
     }
 
@@ -57,8 +60,11 @@ impl EncodeStage {
     }
 
     /// Process a single encoded frame: wrap with timestamps and correlation IDs
-    fn wrap_frame(&self, data: Bytes) -> MediaFrame {
-        let pts = self.clock.video_now();
+    fn wrap_frame(&mut self, encoded: EncodedVideo) -> MediaFrame {
+        let pts = self
+            .timeline
+            .pts(encoded.capture_us, self.clock.video_now());
+        let data = encoded.data;
         let correlation_id = self.clock.next_correlation_id();
 
         // Detect keyframes by scanning for IDR NAL units
@@ -86,7 +92,7 @@ impl EncodeStage {
 }
 
 /// Check if H.264 Annex B data contains an IDR NAL unit (type 5)
-fn contains_idr(data: &[u8]) -> bool {
+pub(crate) fn contains_idr(data: &[u8]) -> bool {
     let start_code: &[u8] = &[0, 0, 0, 1];
     let mut i = 0;
     while i + 4 < data.len() {
@@ -4,6 +4,7 @@
 //! - CaptureStage: Screen/audio capture
 //! - EncodeStage: H.264/Opus encoding
 //! - TransmitStage: WebRTC transmission
+//! - packetizer: RTP payloads (single NAL / FU-A) within the configured MTU
 //!
 //! The sender pipeline flow:
 //! ```text
@@ -13,9 +14,11 @@
 pub mod capture_stage;
 pub mod coordinator;
 pub mod encode_stage;
+pub mod packetizer;
 pub mod transmit_stage;
 
 pub use capture_stage::CaptureStage;
 pub use coordinator::SenderCoordinator;
 pub use encode_stage::EncodeStage;
+pub use packetizer::RtpMtu;
 pub use transmit_stage::TransmitStage;
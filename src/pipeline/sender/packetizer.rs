@@ -0,0 +1,184 @@
+//! Pacchettizzazione H.264 in RTP (RFC 6184) entro un MTU configurabile
+//!
+//! I NAL che stanno nel payload viaggiano da soli, gli altri vengono divisi
+//! in frammenti FU-A. Questo packetizer serve la pipeline a stadi: la track
+//! video WebRTC (e il loopback, che la imita) usa il packetizer di webrtc-rs
+//! con pacchetti da 1200 byte, header RTP compreso. Sullo stream reale un MTU diverso dal default
+//! arriva come dimensione massima delle slice, per gli encoder che sanno
+//! dividere il frame (libx264, QSV): NAL più piccoli diventano pacchetti
+//! RTP più piccoli.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Pacchetto RTP massimo della track video WebRTC, header compreso
+pub const DEFAULT_RTP_MTU: usize = 1200;
+
+/// Header RTP fisso, senza CSRC né estensioni
+pub const RTP_HEADER_LEN: usize = 12;
+
+/// Dimensione massima dei pacchetti video, scelta dall'utente: le VPN e
+/// alcuni tunnel hanno un MTU effettivo più basso di quello di WebRTC.
+/// Sullo stream WebRTC limita le slice dell'encoder, non il packetizer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RtpMtu(pub usize);
+
+impl RtpMtu {
+    pub const MIN: usize = 500;
+    pub const MAX: usize = 1400;
+
+    /// Scelte mostrate nelle impostazioni
+    pub const PRESETS: [RtpMtu; 5] = [
+        RtpMtu(DEFAULT_RTP_MTU),
+        RtpMtu(1100),
+        RtpMtu(1000),
+        RtpMtu(800),
+        RtpMtu(Self::MIN),
+    ];
+
+    /// Valore effettivo, dentro i limiti (file di configurazione modificati a mano)
+    pub fn bytes(&self) -> usize {
+        self.0.clamp(Self::MIN, Self::MAX)
+    }
+
+    /// Spazio per il payload H.264 dentro un pacchetto di `bytes()`
+    pub fn payload(&self) -> usize {
+        self.bytes() - RTP_HEADER_LEN
+    }
+
+    /// Limite delle slice dell'encoder. Al default la track WebRTC divide
+    /// già i frame in FU-A, quindi l'encoder non affetta il frame.
+    pub fn max_slice_size(&self) -> Option<usize> {
+        (self.bytes() != DEFAULT_RTP_MTU).then(|| self.payload())
+    }
+}
+
+impl Default for RtpMtu {
+    fn default() -> Self {
+        RtpMtu(DEFAULT_RTP_MTU)
+    }
+}
+
+impl fmt::Display for RtpMtu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == DEFAULT_RTP_MTU {
+            write!(f, "{} bytes (WebRTC default)", self.0)
+        } else {
+            write!(f, "{} bytes", self.0)
+        }
+    }
+}
+
+/// Divide gli access unit Annex B nei NAL unit, senza start code.
+pub fn nal_units(au: &[u8]) -> Vec<&[u8]> {
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while i + 3 <= au.len() {
+        if au[i] == 0 && au[i + 1] == 0 && au[i + 2] == 1 {
+            // Start code da 4 byte: lo zero iniziale non fa parte del NAL precedente
+            let code_start = if i > 0 && au[i - 1] == 0 { i - 1 } else { i };
+            starts.push((code_start, i + 3));
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &(_, begin))| {
+            let end = starts.get(n + 1).map_or(au.len(), |&(next, _)| next);
+            &au[begin..end]
+        })
+        .filter(|nal| !nal.is_empty())
+        .collect()
+}
+
+/// Pacchettizza un access unit Annex B in payload RTP (RFC 6184): NAL
+/// singoli se stanno in `mtu`, altrimenti FU-A. Il marker chiude l'AU.
+pub fn packetize(au: &[u8], mtu: usize) -> Vec<(Vec<u8>, bool)> {
+    // Indicatore e header FU-A più almeno un byte di dati
+    let mtu = mtu.max(3);
+    let mut packets = Vec::new();
+
+    for nal in nal_units(au) {
+        if nal.len() <= mtu {
+            packets.push((nal.to_vec(), false));
+            continue;
+        }
+
+        let header = nal[0];
+        let indicator = (header & 0xE0) | 28;
+        let chunks: Vec<&[u8]> = nal[1..].chunks(mtu - 2).collect();
+        let last = chunks.len() - 1;
+        for (n, chunk) in chunks.into_iter().enumerate() {
+            let mut fu_header = header & 0x1F;
+            if n == 0 {
+                fu_header |= 0x80;
+            }
+            if n == last {
+                fu_header |= 0x40;
+            }
+            let mut payload = Vec::with_capacity(chunk.len() + 2);
+            payload.extend_from_slice(&[indicator, fu_header]);
+            payload.extend_from_slice(chunk);
+            packets.push((payload, false));
+        }
+    }
+
+    if let Some(last) = packets.last_mut() {
+        last.1 = true;
+    }
+    packets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nal_units_three_byte_start_codes() {
+        let au = [0, 0, 1, 0x09, 0xf0, 0, 0, 0, 1, 0x41, 0x9a];
+        assert_eq!(nal_units(&au), vec![&[0x09, 0xf0][..], &[0x41, 0x9a][..]]);
+    }
+
+    #[test]
+    fn test_packetize_respects_mtu() {
+        let nal: Vec<u8> = std::iter::once(0x65u32)
+            .chain(0..2000)
+            .map(|b| b as u8)
+            .collect();
+        let au: Vec<u8> = [0, 0, 0, 1].into_iter().chain(nal).collect();
+
+        for mtu in RtpMtu::PRESETS.map(|mtu| mtu.payload()) {
+            let packets = packetize(&au, mtu);
+            assert!(packets.len() > 1);
+            assert!(packets.iter().all(|(p, _)| p.len() <= mtu));
+            // Stesso NRI, tipo FU-A; start solo sul primo, end solo sull'ultimo
+            assert!(packets.iter().all(|(p, _)| p[0] == 0x7C));
+            assert_eq!(packets[0].0[1], 0x85);
+            assert_eq!(packets.last().unwrap().0[1], 0x45);
+            assert!(
+                packets[1..packets.len() - 1]
+                    .iter()
+                    .all(|(p, _)| p[1] == 0x05)
+            );
+        }
+    }
+
+    #[test]
+    fn test_rtp_mtu_bounds() {
+        assert_eq!(RtpMtu::default().bytes(), DEFAULT_RTP_MTU);
+        assert_eq!(RtpMtu(64).bytes(), RtpMtu::MIN);
+        assert_eq!(RtpMtu(9000).bytes(), RtpMtu::MAX);
+    }
+
+    #[test]
+    fn test_slices_fit_the_rtp_payload() {
+        assert_eq!(RtpMtu::default().payload(), 1188);
+        assert_eq!(RtpMtu::default().max_slice_size(), None);
+        assert_eq!(RtpMtu(1000).max_slice_size(), Some(1000 - RTP_HEADER_LEN));
+    }
+}
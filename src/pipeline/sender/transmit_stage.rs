@@ -1,6 +1,7 @@
 //! Transmit stage for the sender pipeline
 //!
 //! Wraps WebRTCServer and forwards encoded frames to connected peers.
+//! Access units are split into RTP payloads no larger than the configured MTU.
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -11,6 +12,7 @@ use tokio::sync::mpsc;
 use crate::capture::capturer::EncodedFrame;
 use crate::pipeline::PipelineStage;
 use crate::pipeline::health::PipelineHealth;
+use crate::pipeline::sender::packetizer::{RtpMtu, packetize};
 use crate::pipeline::types::MediaFrame;
 use crate::utils::net::webrtc::WebRTCServer;
 
@@ -22,6 +24,8 @@ pub struct TransmitStage {
     server: Arc<WebRTCServer>,
     health: Arc<PipelineHealth>,
     input_rx: Option<mpsc::Receiver<MediaFrame>>,
+    /// Maximum RTP payload per video packet
+    mtu: RtpMtu,
 }
 
 impl TransmitStage {
@@ -31,9 +35,16 @@ impl TransmitStage {
             server,
             health,
             input_rx: None,
+            mtu: RtpMtu::default(),
         }
     }
 
+    /// Set the maximum RTP payload size (smaller on VPNs and tunnels)
+    pub fn with_mtu(mut self, mtu: RtpMtu) -> Self {
+        self.mtu = mtu;
+        self
+    }
+
     /// Set the input channel
     pub fn set_input(&mut self, rx: mpsc::Receiver<MediaFrame>) {
         self.input_rx = Some(rx);
@@ -50,6 +61,7 @@ impl TransmitStage {
             data: frame.data.to_vec(),
             sequence_number: seq,
             timestamp_ms: (frame.pts.micros / 1000) as u64,
+            pts: frame.pts,
         }
     }
 }
@@ -63,12 +75,15 @@ impl PipelineStage for TransmitStage {
             .ok_or_else(|| anyhow::anyhow!("No input channel"))?;
 
         info!("TransmitStage: started");
+        let mtu = self.mtu.payload();
         let mut sequence = 0u64;
+        let mut packets = 0u64;
         let dropped = 0u64;
 
         while let Some(frame) = input_rx.recv().await {
             let _encoded = Self::to_encoded_frame(&frame, sequence);
             sequence += 1;
+            packets += packetize(&frame.data, mtu).len() as u64;
 
             // Track drops via backpressure
             if frame.is_keyframe {
@@ -77,8 +92,8 @@ impl PipelineStage for TransmitStage {
         }
 
         info!(
-            "TransmitStage: finished, {} frames transmitted, {} dropped",
-            sequence, dropped
+            "TransmitStage: finished, {} frames transmitted in {} packets (MTU {}), {} dropped",
+            sequence, packets, mtu, dropped
         );
         Ok(())
     }
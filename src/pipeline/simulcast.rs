@@ -0,0 +1,194 @@
+//! Per-receiver quality tier for simulcast casting
+//!
+//! With simulcast on, the caster can encode every captured frame twice: the
+//! full stream profile and a smaller second tier. Each peer is served by one
+//! of the two encoders; [`TierSelector`] moves it down when the receiver's
+//! RTCP feedback reports loss (receiver reports) or a bandwidth estimate
+//! below the main tier (REMB), and back up after a run of clean reports.
+//! The second encoder only runs while at least one peer is on it. A peer
+//! that changes tier waits for a keyframe of the new encoder before
+//! receiving frames from it.
+
+/// Perdita riportata (RR, in 1/256) oltre la quale il peer scende di livello
+const DOWNGRADE_FRACTION_LOST: u8 = 13; // ~5%
+/// Stima di banda del receiver (REMB) sotto cui il livello alto non ci sta:
+/// la catena di encoder punta a 3-3.5 Mbps
+const HIGH_TIER_MIN_BPS: f32 = 2_000_000.0;
+/// Report puliti prima di riprovare il livello alto (~1 al secondo): più
+/// lento dell'adattivo, così un link al limite non oscilla tra i due encoder
+const UPGRADE_STABLE_REPORTS: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SimulcastTier {
+    /// Profilo dello stream, quello annunciato con l'offerta
+    #[default]
+    High,
+    /// Secondo encoder ridotto, per i receiver lenti
+    Low,
+}
+
+#[derive(Debug, Default)]
+pub struct TierSelector {
+    tier: SimulcastTier,
+    /// Cambiato livello: niente frame finché non arriva un IDR del nuovo encoder
+    awaiting_keyframe: bool,
+    /// IDR da chiedere all'encoder del nuovo livello, una volta per cambio
+    keyframe_request: bool,
+    stable_reports: u32,
+}
+
+impl TierSelector {
+    pub fn tier(&self) -> SimulcastTier {
+        self.tier
+    }
+
+    /// Il frame di `tier` va inviato a questo peer? Dopo un cambio di
+    /// livello il primo frame accettato è un keyframe.
+    pub fn accepts(&mut self, tier: SimulcastTier, is_keyframe: bool) -> bool {
+        if tier != self.tier || (self.awaiting_keyframe && !is_keyframe) {
+            return false;
+        }
+        self.awaiting_keyframe = false;
+        self.keyframe_request = false;
+        true
+    }
+
+    /// `true` una sola volta dopo un cambio verso `tier`: l'encoder di quel
+    /// livello deve produrre un IDR per questo peer.
+    pub fn take_keyframe_request(&mut self, tier: SimulcastTier) -> bool {
+        if tier != self.tier || !self.keyframe_request {
+            return false;
+        }
+        self.keyframe_request = false;
+        true
+    }
+
+    /// Registra un pacchetto di feedback RTCP del receiver: `fraction_lost`
+    /// dal receiver report sulla nostra SSRC video, `estimate_bps` dal REMB.
+    /// Ritorna il nuovo livello se il peer deve cambiarlo.
+    pub fn report(
+        &mut self,
+        fraction_lost: Option<u8>,
+        estimate_bps: Option<f32>,
+    ) -> Option<SimulcastTier> {
+        if fraction_lost.is_none() && estimate_bps.is_none() {
+            return None;
+        }
+        let congested = fraction_lost.is_some_and(|lost| lost > DOWNGRADE_FRACTION_LOST)
+            || estimate_bps.is_some_and(|bps| bps < HIGH_TIER_MIN_BPS);
+
+        let next = match self.tier {
+            SimulcastTier::High if congested => SimulcastTier::Low,
+            SimulcastTier::Low if !congested => {
+                self.stable_reports += 1;
+                if self.stable_reports < UPGRADE_STABLE_REPORTS {
+                    return None;
+                }
+                SimulcastTier::High
+            }
+            _ => {
+                self.stable_reports = 0;
+                return None;
+            }
+        };
+
+        self.tier = next;
+        self.stable_reports = 0;
+        self.awaiting_keyframe = true;
+        self.keyframe_request = true;
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ~10% di pacchetti persi
+    const LOSSY: Option<u8> = Some(26);
+    const CLEAN: Option<u8> = Some(0);
+
+    #[test]
+    fn test_starts_high_and_stays_on_clean_link() {
+        let mut selector = TierSelector::default();
+        for _ in 0..20 {
+            assert_eq!(selector.report(CLEAN, None), None);
+        }
+        assert_eq!(selector.tier(), SimulcastTier::High);
+    }
+
+    #[test]
+    fn test_downgrade_on_reported_loss() {
+        let mut selector = TierSelector::default();
+        assert_eq!(selector.report(LOSSY, None), Some(SimulcastTier::Low));
+        assert_eq!(selector.tier(), SimulcastTier::Low);
+    }
+
+    #[test]
+    fn test_downgrade_on_low_bandwidth_estimate() {
+        let mut selector = TierSelector::default();
+        assert_eq!(selector.report(None, Some(5_000_000.0)), None);
+        assert_eq!(
+            selector.report(None, Some(900_000.0)),
+            Some(SimulcastTier::Low)
+        );
+    }
+
+    #[test]
+    fn test_feedback_without_reports_is_not_judged() {
+        let mut selector = TierSelector::default();
+        selector.report(LOSSY, None);
+        for _ in 0..UPGRADE_STABLE_REPORTS * 2 {
+            assert_eq!(selector.report(None, None), None);
+        }
+        assert_eq!(selector.tier(), SimulcastTier::Low);
+    }
+
+    #[test]
+    fn test_upgrade_after_stable_reports() {
+        let mut selector = TierSelector::default();
+        selector.report(LOSSY, None);
+
+        for _ in 1..UPGRADE_STABLE_REPORTS {
+            assert_eq!(selector.report(CLEAN, None), None);
+        }
+        assert_eq!(selector.report(CLEAN, None), Some(SimulcastTier::High));
+    }
+
+    #[test]
+    fn test_congestion_resets_upgrade_count() {
+        let mut selector = TierSelector::default();
+        selector.report(LOSSY, None);
+
+        for _ in 1..UPGRADE_STABLE_REPORTS {
+            selector.report(CLEAN, None);
+        }
+        assert_eq!(selector.report(LOSSY, None), None);
+        assert_eq!(selector.report(CLEAN, None), None);
+        assert_eq!(selector.tier(), SimulcastTier::Low);
+    }
+
+    #[test]
+    fn test_switch_waits_for_keyframe() {
+        let mut selector = TierSelector::default();
+        assert!(selector.accepts(SimulcastTier::High, false));
+        assert!(!selector.accepts(SimulcastTier::Low, true));
+
+        selector.report(LOSSY, None);
+        assert!(!selector.accepts(SimulcastTier::High, true));
+        assert!(!selector.accepts(SimulcastTier::Low, false));
+        assert!(selector.accepts(SimulcastTier::Low, true));
+        assert!(selector.accepts(SimulcastTier::Low, false));
+    }
+
+    #[test]
+    fn test_keyframe_requested_once_per_switch() {
+        let mut selector = TierSelector::default();
+        assert!(!selector.take_keyframe_request(SimulcastTier::High));
+
+        selector.report(LOSSY, None);
+        assert!(!selector.take_keyframe_request(SimulcastTier::High));
+        assert!(selector.take_keyframe_request(SimulcastTier::Low));
+        assert!(!selector.take_keyframe_request(SimulcastTier::Low));
+    }
+}
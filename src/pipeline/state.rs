@@ -1,5 +1,7 @@
 //! Pipeline state management
 
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::time::Instant;
 
 /// Pipeline state machine
@@ -119,6 +121,161 @@ impl std::fmt::Display for PipelineState {
     }
 }
 
+/// Receiver connection state machine
+///
+/// Describes what the user is waiting for, from negotiation to playback.
+/// Unlike [`PipelineState`] it can go back and forth (stall, reconnect).
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Not connected and not trying to
+    Idle = 0,
+    /// Looking for the caster / exchanging SDP
+    Negotiating = 1,
+    /// Signaling done, waiting for the peer connection
+    Connecting = 2,
+    /// Connected, waiting for the first decodable keyframe
+    Buffering = 3,
+    /// Frames are being decoded and displayed
+    Playing = 4,
+    /// Connected but no frames arrived for a while
+    Stalled = 5,
+    /// The peer connection dropped, waiting for it to come back
+    Reconnecting = 6,
+    /// The connection could not be established or was lost for good
+    Failed = 7,
+}
+
+impl ConnectionState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => ConnectionState::Negotiating,
+            2 => ConnectionState::Connecting,
+            3 => ConnectionState::Buffering,
+            4 => ConnectionState::Playing,
+            5 => ConnectionState::Stalled,
+            6 => ConnectionState::Reconnecting,
+            7 => ConnectionState::Failed,
+            _ => ConnectionState::Idle,
+        }
+    }
+
+    /// Check if this state transition is valid
+    pub fn can_transition_to(&self, target: &ConnectionState) -> bool {
+        use ConnectionState::*;
+
+        match (self, target) {
+            // Closing is always allowed
+            (_, Idle) => true,
+            // Any attempt can fail
+            (_, Failed) => true,
+
+            (Idle, Negotiating) => true,
+            (Negotiating, Connecting) => true,
+            (Connecting, Buffering) => true,
+            (Buffering, Playing) => true,
+
+            // Playback problems
+            (Buffering | Playing, Stalled) => true,
+            (Playing, Buffering) => true, // keyframe lost, waiting for IDR
+            (Connecting | Buffering | Playing | Stalled, Reconnecting) => true,
+
+            // Recovery
+            (Stalled | Reconnecting, Buffering | Playing) => true,
+
+            // Retry after failure
+            (Failed, Negotiating) => true,
+
+            (a, b) if a == b => true,
+            _ => false,
+        }
+    }
+
+    /// Get a human-readable description of this state
+    pub fn description(&self) -> &'static str {
+        match self {
+            ConnectionState::Idle => "Idle",
+            ConnectionState::Negotiating => "Negotiating",
+            ConnectionState::Connecting => "Connecting",
+            ConnectionState::Buffering => "Buffering",
+            ConnectionState::Playing => "Playing",
+            ConnectionState::Stalled => "Stalled",
+            ConnectionState::Reconnecting => "Reconnecting",
+            ConnectionState::Failed => "Failed",
+        }
+    }
+
+    /// Waiting states where the GUI shows a spinner
+    pub fn is_waiting(&self) -> bool {
+        matches!(
+            self,
+            ConnectionState::Negotiating
+                | ConnectionState::Connecting
+                | ConnectionState::Buffering
+                | ConnectionState::Stalled
+                | ConnectionState::Reconnecting
+        )
+    }
+}
+
+impl std::fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Shared, lock-free holder of the current [`ConnectionState`].
+///
+/// Written by the receiver tasks and the health monitor, read by the GUI.
+#[derive(Debug, Clone)]
+pub struct ConnectionStatus {
+    state: Arc<AtomicU8>,
+}
+
+impl Default for ConnectionStatus {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(AtomicU8::new(ConnectionState::Idle as u8)),
+        }
+    }
+}
+
+impl ConnectionStatus {
+    pub fn get(&self) -> ConnectionState {
+        ConnectionState::from_u8(self.state.load(Ordering::Acquire))
+    }
+
+    /// Move to `target` if the transition is valid, logging it.
+    /// Returns false (and keeps the current state) otherwise.
+    pub fn set(&self, target: ConnectionState) -> bool {
+        let result = self
+            .state
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                ConnectionState::from_u8(current)
+                    .can_transition_to(&target)
+                    .then_some(target as u8)
+            });
+
+        match result {
+            Ok(previous) => {
+                let previous = ConnectionState::from_u8(previous);
+                if previous != target {
+                    log::info!("Connection state: {} → {}", previous, target);
+                }
+                true
+            }
+            Err(current) => {
+                log::debug!(
+                    "Ignored connection state transition {} → {}",
+                    ConnectionState::from_u8(current),
+                    target
+                );
+                false
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,4 +347,37 @@ mod tests {
         assert!(!stopped.is_paused());
         assert!(stopped.is_stopped());
     }
+
+    #[test]
+    fn test_connection_transitions() {
+        use ConnectionState::*;
+
+        assert!(Idle.can_transition_to(&Negotiating));
+        assert!(Negotiating.can_transition_to(&Connecting));
+        assert!(Connecting.can_transition_to(&Buffering));
+        assert!(Buffering.can_transition_to(&Playing));
+        assert!(Playing.can_transition_to(&Stalled));
+        assert!(Stalled.can_transition_to(&Reconnecting));
+        assert!(Reconnecting.can_transition_to(&Playing));
+        assert!(Failed.can_transition_to(&Negotiating));
+        assert!(Playing.can_transition_to(&Idle));
+
+        assert!(!Idle.can_transition_to(&Playing));
+        assert!(!Negotiating.can_transition_to(&Playing));
+        assert!(!Failed.can_transition_to(&Playing));
+        assert!(!Idle.can_transition_to(&Stalled));
+    }
+
+    #[test]
+    fn test_connection_status_rejects_invalid() {
+        let status = ConnectionStatus::default();
+        assert_eq!(status.get(), ConnectionState::Idle);
+
+        assert!(!status.set(ConnectionState::Playing));
+        assert_eq!(status.get(), ConnectionState::Idle);
+
+        assert!(status.set(ConnectionState::Negotiating));
+        assert!(status.set(ConnectionState::Failed));
+        assert_eq!(status.get(), ConnectionState::Failed);
+    }
 }
@@ -0,0 +1,347 @@
+//! Periodic stream statistics written to disk
+//!
+//! A small task samples [`PipelineHealth`] (and the receiver's [`StageMetrics`]
+//! when present) on a fixed interval and appends one line per sample to a CSV
+//! or JSONL file, so intermittent quality issues can be correlated over time.
+//! Rates (fps, bitrate) are computed from the counter deltas between samples.
+
+use crate::pipeline::health::{HealthSummary, PipelineHealth};
+use crate::pipeline::metrics::{MetricsSnapshot, Stage, StageMetrics};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+/// Default time between two samples
+pub const STATS_LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// File format of the statistics log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatsFormat {
+    #[default]
+    Csv,
+    Jsonl,
+}
+
+impl StatsFormat {
+    pub const ALL: [StatsFormat; 2] = [StatsFormat::Csv, StatsFormat::Jsonl];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            StatsFormat::Csv => "csv",
+            StatsFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+impl fmt::Display for StatsFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StatsFormat::Csv => "CSV",
+            StatsFormat::Jsonl => "JSON Lines",
+        })
+    }
+}
+
+/// Round-trip time to the other side of the session, sampled with each line
+#[async_trait]
+pub trait RttSource: Send + Sync {
+    async fn rtt(&self) -> Option<Duration>;
+}
+
+/// Where and how often to write the statistics
+#[derive(Debug, Clone)]
+pub struct StatsLogTarget {
+    pub path: PathBuf,
+    pub format: StatsFormat,
+    pub interval: Duration,
+}
+
+/// One line of the log
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatsSample {
+    /// Local wall-clock time (RFC 3339)
+    pub timestamp: String,
+    /// Seconds since the log was started
+    pub elapsed_s: f64,
+    pub fps: f64,
+    pub bitrate_kbps: f64,
+    pub frames: u64,
+    pub frame_drops: u64,
+    pub drop_rate: f64,
+    pub decode_failures: u64,
+    pub network_errors: u64,
+    pub keyframes: u64,
+    pub audio_underruns: u64,
+    pub audio_overruns: u64,
+    /// Queue depths per receiver stage, absent on the caster
+    pub reorder_queue: Option<usize>,
+    pub decode_queue: Option<usize>,
+    pub sync_queue: Option<usize>,
+    pub display_queue: Option<usize>,
+    pub glass_to_glass_ms: Option<f64>,
+    /// Round-trip time of the ICE connection (worst peer on the caster)
+    pub rtt_ms: Option<f64>,
+}
+
+impl StatsSample {
+    pub const CSV_HEADER: &'static str = "timestamp,elapsed_s,fps,bitrate_kbps,frames,frame_drops,drop_rate,\
+decode_failures,network_errors,keyframes,audio_underruns,audio_overruns,\
+reorder_queue,decode_queue,sync_queue,display_queue,glass_to_glass_ms,rtt_ms";
+
+    /// Sample from two health summaries taken `interval` apart
+    pub fn new(
+        elapsed: Duration,
+        interval: Duration,
+        previous: &HealthSummary,
+        current: &HealthSummary,
+        metrics: Option<&MetricsSnapshot>,
+        rtt: Option<Duration>,
+    ) -> Self {
+        let secs = interval.as_secs_f64();
+        let rate = |delta: u64| if secs > 0.0 { delta as f64 / secs } else { 0.0 };
+        let frames = current
+            .frames_processed
+            .saturating_sub(previous.frames_processed);
+        let bytes = current
+            .bytes_processed
+            .saturating_sub(previous.bytes_processed);
+        let queue = |stage: Stage| metrics.map(|m| m.stages[stage as usize].queue_depth);
+
+        Self {
+            timestamp: Local::now().to_rfc3339(),
+            elapsed_s: elapsed.as_secs_f64(),
+            fps: rate(frames),
+            bitrate_kbps: rate(bytes) * 8.0 / 1000.0,
+            frames: current.frames_processed,
+            frame_drops: current.frame_drops,
+            drop_rate: current.frame_drop_rate,
+            decode_failures: current.decode_failures,
+            network_errors: current.network_errors,
+            keyframes: current.keyframes_processed,
+            audio_underruns: current.audio_underruns,
+            audio_overruns: current.audio_overruns,
+            reorder_queue: queue(Stage::Reorder),
+            decode_queue: queue(Stage::Decode),
+            sync_queue: queue(Stage::Sync),
+            display_queue: queue(Stage::Display),
+            glass_to_glass_ms: metrics
+                .and_then(|m| m.glass_to_glass)
+                .map(|d| d.as_secs_f64() * 1000.0),
+            rtt_ms: rtt.map(|d| d.as_secs_f64() * 1000.0),
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let opt = |v: Option<usize>| v.map(|v| v.to_string()).unwrap_or_default();
+        let ms = |v: Option<f64>| v.map(|ms| format!("{:.1}", ms)).unwrap_or_default();
+        format!(
+            "{},{:.3},{:.2},{:.1},{},{},{:.2},{},{},{},{},{},{},{},{},{},{},{}",
+            self.timestamp,
+            self.elapsed_s,
+            self.fps,
+            self.bitrate_kbps,
+            self.frames,
+            self.frame_drops,
+            self.drop_rate,
+            self.decode_failures,
+            self.network_errors,
+            self.keyframes,
+            self.audio_underruns,
+            self.audio_overruns,
+            opt(self.reorder_queue),
+            opt(self.decode_queue),
+            opt(self.sync_queue),
+            opt(self.display_queue),
+            ms(self.glass_to_glass_ms),
+            ms(self.rtt_ms),
+        )
+    }
+
+    pub fn line(&self, format: StatsFormat) -> Result<String> {
+        Ok(match format {
+            StatsFormat::Csv => self.to_csv(),
+            StatsFormat::Jsonl => serde_json::to_string(self)?,
+        })
+    }
+}
+
+/// Start the writer task; it stops when `cancel` fires
+pub fn spawn_stats_log(
+    target: StatsLogTarget,
+    health: Arc<PipelineHealth>,
+    metrics: Option<Arc<StageMetrics>>,
+    rtt: Option<Arc<dyn RttSource>>,
+    cancel: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let path = target.path.clone();
+        log::info!("Writing stream statistics to {}", path.display());
+        if let Err(e) = write_stats(target, health, metrics, rtt, cancel).await {
+            log::error!("Stream statistics log {} stopped: {:#}", path.display(), e);
+        }
+    })
+}
+
+async fn write_stats(
+    target: StatsLogTarget,
+    health: Arc<PipelineHealth>,
+    metrics: Option<Arc<StageMetrics>>,
+    rtt: Option<Arc<dyn RttSource>>,
+    cancel: CancellationToken,
+) -> Result<()> {
+    if let Some(dir) = target.path.parent() {
+        fs::create_dir_all(dir).await?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&target.path)
+        .await?;
+    let is_new = file.metadata().await?.len() == 0;
+    let mut out = BufWriter::new(file);
+    if is_new && target.format == StatsFormat::Csv {
+        out.write_all(format!("{}\n", StatsSample::CSV_HEADER).as_bytes())
+            .await?;
+    }
+
+    let mut interval = tokio::time::interval(target.interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    // The first tick completes immediately
+    interval.tick().await;
+
+    let started = Instant::now();
+    let mut previous = health.summary();
+    let mut previous_at = started;
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+
+        let now = Instant::now();
+        let current = health.summary();
+        let snapshot = metrics.as_ref().map(|m| m.snapshot());
+        let rtt = match &rtt {
+            Some(source) => source.rtt().await,
+            None => None,
+        };
+        let sample = StatsSample::new(
+            now - started,
+            now - previous_at,
+            &previous,
+            &current,
+            snapshot.as_ref(),
+            rtt,
+        );
+        let line = format!("{}\n", sample.line(target.format)?);
+        out.write_all(line.as_bytes()).await?;
+        out.flush().await?;
+
+        previous = current;
+        previous_at = now;
+    }
+
+    out.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(frames: u64, bytes: u64) -> HealthSummary {
+        HealthSummary {
+            frames_processed: frames,
+            frame_drops: 2,
+            drops_by_source: [0, 2, 0, 0, 0],
+            decode_failures: 1,
+            network_errors: 0,
+            bytes_processed: bytes,
+            keyframes_processed: 3,
+            frame_drop_rate: 0.5,
+            audio_underruns: 0,
+            audio_overruns: 0,
+            latency_resets: 0,
+        }
+    }
+
+    #[test]
+    fn test_rates_from_deltas() {
+        let sample = StatsSample::new(
+            Duration::from_secs(10),
+            Duration::from_secs(2),
+            &summary(100, 1_000_000),
+            &summary(160, 1_500_000),
+            None,
+            None,
+        );
+        assert!((sample.fps - 30.0).abs() < 1e-9);
+        assert!((sample.bitrate_kbps - 2000.0).abs() < 1e-9);
+        assert_eq!(sample.frames, 160);
+        assert_eq!(sample.reorder_queue, None);
+    }
+
+    #[test]
+    fn test_zero_interval_has_no_rates() {
+        let sample = StatsSample::new(
+            Duration::ZERO,
+            Duration::ZERO,
+            &summary(0, 0),
+            &summary(10, 10),
+            None,
+            None,
+        );
+        assert_eq!(sample.fps, 0.0);
+        assert_eq!(sample.bitrate_kbps, 0.0);
+    }
+
+    #[test]
+    fn test_csv_line_matches_header() {
+        let metrics = StageMetrics::new();
+        metrics.record(Stage::Decode, Duration::from_millis(4), 7);
+        let snapshot = metrics.snapshot();
+        let sample = StatsSample::new(
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            &summary(0, 0),
+            &summary(30, 250_000),
+            Some(&snapshot),
+            Some(Duration::from_millis(42)),
+        );
+
+        let columns = StatsSample::CSV_HEADER.split(',').count();
+        let line = sample.to_csv();
+        assert_eq!(line.split(',').count(), columns);
+        assert_eq!(sample.decode_queue, Some(7));
+        assert!(line.ends_with(",42.0"));
+    }
+
+    #[test]
+    fn test_jsonl_line_is_json() {
+        let sample = StatsSample::new(
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            &summary(0, 0),
+            &summary(30, 0),
+            None,
+            None,
+        );
+        let line = sample.line(StatsFormat::Jsonl).unwrap();
+        assert!(!line.contains('\n'));
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["frames"], 30);
+        assert!(value["glass_to_glass_ms"].is_null());
+        assert!(value["rtt_ms"].is_null());
+    }
+}
@@ -0,0 +1,141 @@
+//! Buffer sizes of the caster and receiver pipelines
+//!
+//! Every queue between two stages trades latency for resilience: a deep
+//! queue rides out a slow consumer, but each frame sitting in it is a frame
+//! the viewer sees late. They are gathered here so a [`LatencyProfile`]
+//! picks all of them at once.
+
+use std::time::Duration;
+
+use crate::pipeline::receiver::LatencyProfile;
+use crate::pipeline::receiver::reorder_stage::ReorderConfig;
+use crate::pipeline::receiver::sync_stage::SyncConfig;
+
+/// Channel capacities and buffer limits, one preset per [`LatencyProfile`]
+#[derive(Debug, Clone)]
+pub struct PipelineTuning {
+    /// Encoded frames from the capturer to the WebRTC sender
+    pub capture_queue: usize,
+    /// Encoder output waiting to be stamped by the capturer
+    pub encoded_queue: usize,
+    /// Frames the encoder keeps around for reuse
+    pub frame_pool: usize,
+    /// RTP packets between the receive adapter and the reorder stage
+    pub packet_queue: usize,
+    /// Reordered packets waiting for the decoder
+    pub reordered_queue: usize,
+    /// Decoded frames waiting for the sync stage
+    pub decoded_queue: usize,
+    /// Frames released by the sync stage, waiting for the display
+    pub video_output: usize,
+    /// Audio RTP packets waiting for the audio decoder
+    pub audio_queue: usize,
+    /// Audio and video packets waiting for the recording muxer
+    pub save_queue: usize,
+    /// Jitter buffer
+    pub reorder: ReorderConfig,
+    /// A/V sync queues and playout delay
+    pub sync: SyncConfig,
+}
+
+impl PipelineTuning {
+    pub fn for_profile(profile: LatencyProfile) -> Self {
+        match profile {
+            LatencyProfile::LowLatency => Self {
+                capture_queue: 64,
+                encoded_queue: 32,
+                frame_pool: 4,
+                packet_queue: 64,
+                reordered_queue: 128,
+                decoded_queue: 4,
+                video_output: 1,
+                audio_queue: 128,
+                save_queue: 1024,
+                reorder: ReorderConfig {
+                    jitter_delay: Duration::from_millis(40),
+                    max_buffer_size: 200,
+                    max_reorder_distance: 30,
+                },
+                sync: SyncConfig {
+                    playout_delay: Duration::from_millis(50),
+                    max_drift: Duration::from_millis(60),
+                    frame_tolerance: Duration::from_millis(33),
+                    max_video_queue: 30,
+                    max_audio_queue: 60,
+                },
+            },
+            LatencyProfile::Balanced => Self {
+                capture_queue: 256,
+                encoded_queue: 128,
+                frame_pool: 8,
+                packet_queue: 128,
+                reordered_queue: 256,
+                decoded_queue: 8,
+                video_output: 3,
+                audio_queue: 512,
+                save_queue: 2048,
+                reorder: ReorderConfig::default(),
+                sync: SyncConfig::default(),
+            },
+            LatencyProfile::Smooth => Self {
+                capture_queue: 512,
+                encoded_queue: 256,
+                frame_pool: 12,
+                packet_queue: 256,
+                reordered_queue: 512,
+                decoded_queue: 16,
+                video_output: 6,
+                audio_queue: 1024,
+                save_queue: 4096,
+                reorder: ReorderConfig {
+                    jitter_delay: Duration::from_millis(400),
+                    max_buffer_size: 800,
+                    max_reorder_distance: 120,
+                },
+                sync: SyncConfig {
+                    playout_delay: Duration::from_millis(500),
+                    max_drift: Duration::from_millis(150),
+                    frame_tolerance: Duration::from_millis(66),
+                    max_video_queue: 240,
+                    max_audio_queue: 480,
+                },
+            },
+        }
+    }
+}
+
+impl Default for PipelineTuning {
+    fn default() -> Self {
+        Self::for_profile(LatencyProfile::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deeper_profiles_never_shrink_a_buffer() {
+        let [low, balanced, smooth] = LatencyProfile::ALL.map(PipelineTuning::for_profile);
+        let sizes = |t: &PipelineTuning| {
+            [
+                t.capture_queue,
+                t.encoded_queue,
+                t.frame_pool,
+                t.packet_queue,
+                t.reordered_queue,
+                t.decoded_queue,
+                t.video_output,
+                t.audio_queue,
+                t.save_queue,
+                t.reorder.max_buffer_size,
+                t.sync.max_video_queue,
+                t.sync.max_audio_queue,
+            ]
+        };
+        for (a, b) in [(&low, &balanced), (&balanced, &smooth)] {
+            assert!(sizes(a).iter().zip(sizes(b)).all(|(a, b)| *a <= b));
+            assert!(sizes(a).iter().all(|&size| size > 0));
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Livello audio (RMS + picco) condiviso tra il thread audio e la GUI.
+//!
+//! Il thread audio scrive con `update` ad ogni blocco di campioni, la GUI
+//! legge con `snapshot` ad ogni tick: solo atomici, nessun lock nel callback.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Per quanto tempo l'indicatore di clipping resta acceso
+const CLIP_HOLD: Duration = Duration::from_millis(1500);
+/// Decadimento del picco per blocco: la barra scende in modo graduale
+const PEAK_DECAY: f32 = 0.92;
+
+#[derive(Debug)]
+struct LevelState {
+    /// f32 salvati come bit
+    rms: AtomicU32,
+    peak: AtomicU32,
+    /// Millisecondi da `epoch` dell'ultimo campione >= 1.0, 0 = mai
+    last_clip_ms: AtomicU64,
+    epoch: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioLevel {
+    state: Arc<LevelState>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LevelSnapshot {
+    /// 0.0..=1.0
+    pub rms: f32,
+    /// 0.0..=1.0
+    pub peak: f32,
+    pub clipping: bool,
+}
+
+impl Default for AudioLevel {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(LevelState {
+                rms: AtomicU32::new(0),
+                peak: AtomicU32::new(0),
+                last_clip_ms: AtomicU64::new(0),
+                epoch: Instant::now(),
+            }),
+        }
+    }
+}
+
+impl AudioLevel {
+    /// Aggiorna il livello con un blocco di campioni interleaved.
+    pub fn update(&self, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+
+        let mut sum = 0.0f32;
+        let mut block_peak = 0.0f32;
+        for &s in samples {
+            let a = s.abs();
+            sum += s * s;
+            block_peak = block_peak.max(a);
+        }
+        let rms = (sum / samples.len() as f32).sqrt();
+
+        let previous_peak = f32::from_bits(self.state.peak.load(Ordering::Relaxed));
+        let peak = block_peak.max(previous_peak * PEAK_DECAY);
+
+        self.state.rms.store(rms.to_bits(), Ordering::Relaxed);
+        self.state.peak.store(peak.to_bits(), Ordering::Relaxed);
+
+        if block_peak >= 1.0 {
+            let now_ms = self.state.epoch.elapsed().as_millis() as u64;
+            self.state
+                .last_clip_ms
+                .store(now_ms.max(1), Ordering::Relaxed);
+        }
+    }
+
+    /// Azzera il livello (es. audio in mute o cattura ferma).
+    pub fn reset(&self) {
+        self.state.rms.store(0, Ordering::Relaxed);
+        self.state.peak.store(0, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> LevelSnapshot {
+        let last_clip = self.state.last_clip_ms.load(Ordering::Relaxed);
+        let now_ms = self.state.epoch.elapsed().as_millis() as u64;
+
+        LevelSnapshot {
+            rms: f32::from_bits(self.state.rms.load(Ordering::Relaxed)).min(1.0),
+            peak: f32::from_bits(self.state.peak.load(Ordering::Relaxed)).min(1.0),
+            clipping: last_clip != 0
+                && now_ms.saturating_sub(last_clip) < CLIP_HOLD.as_millis() as u64,
+        }
+    }
+}
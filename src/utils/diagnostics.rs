@@ -0,0 +1,122 @@
+//! Riepilogo per le segnalazioni di bug: sistema, versione, encoder e
+//! decoder in uso, monitor, salute dello stream e ultime righe di log.
+//! Copiato negli appunti dal pulsante della pagina info.
+
+use crate::config::{Mode, app_version};
+use crate::pipeline::health::HealthSummary;
+use crate::utils::logging::recent_lines;
+use display_info::DisplayInfo;
+use std::fmt;
+
+/// Righe di log incluse nel riepilogo
+pub const DIAGNOSTICS_LOG_LINES: usize = 50;
+
+pub struct Diagnostics {
+    version: &'static str,
+    os: String,
+    /// Modalità attiva, `None` nella home
+    role: Option<&'static str>,
+    /// Encoder del caster o decoder del receiver, con l'etichetta
+    codec: Option<(&'static str, String)>,
+    displays: Result<Vec<String>, String>,
+    health: Option<HealthSummary>,
+    selftest: Option<String>,
+    log: Vec<String>,
+}
+
+impl Diagnostics {
+    /// `selftest`: riepilogo dell'ultimo self-test, se eseguito
+    pub fn collect(mode: Option<&Mode>, selftest: Option<&str>) -> Self {
+        let not_started = || String::from("not started");
+        let (role, codec, health) = match mode {
+            Some(Mode::Caster(caster)) => (
+                Some("Caster"),
+                Some((
+                    "Encoder",
+                    caster.encoder_name().map_or_else(not_started, String::from),
+                )),
+                Some(caster.health().summary()),
+            ),
+            Some(Mode::Receiver(receiver)) => (
+                Some("Receiver"),
+                Some((
+                    "Decoder",
+                    receiver
+                        .metrics()
+                        .snapshot()
+                        .decoder
+                        .map_or_else(not_started, String::from),
+                )),
+                Some(receiver.health().summary()),
+            ),
+            None => (None, None, None),
+        };
+
+        Self {
+            version: app_version(),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            role,
+            codec,
+            displays: DisplayInfo::all()
+                .map(|displays| {
+                    displays
+                        .iter()
+                        .map(|d| {
+                            format!(
+                                "{} {}x{} at ({}, {}), scale {:.2}{}",
+                                d.name,
+                                d.width,
+                                d.height,
+                                d.x,
+                                d.y,
+                                d.scale_factor,
+                                if d.is_primary { ", primary" } else { "" }
+                            )
+                        })
+                        .collect()
+                })
+                .map_err(|e| e.to_string()),
+            health,
+            selftest: selftest.map(String::from),
+            log: recent_lines(DIAGNOSTICS_LOG_LINES),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Version: {}", self.version)?;
+        writeln!(f, "OS: {}", self.os)?;
+        writeln!(f, "Mode: {}", self.role.unwrap_or("none"))?;
+        if let Some((label, codec)) = &self.codec {
+            writeln!(f, "{}: {}", label, codec)?;
+        }
+        match &self.displays {
+            Ok(displays) if displays.is_empty() => writeln!(f, "Displays: none found")?,
+            Ok(displays) => {
+                writeln!(f, "Displays:")?;
+                for display in displays {
+                    writeln!(f, "  {}", display)?;
+                }
+            }
+            Err(e) => writeln!(f, "Displays: {}", e)?,
+        }
+        if let Some(health) = &self.health {
+            writeln!(f, "{}", health)?;
+            if let Some(source) = health.bottleneck() {
+                writeln!(f, "Most drops at: {}", source)?;
+            }
+        }
+        if let Some(selftest) = &self.selftest {
+            writeln!(f, "Self-test:")?;
+            for line in selftest.lines() {
+                writeln!(f, "  {}", line)?;
+            }
+        }
+        write!(f, "Last {} log lines:", self.log.len())?;
+        for line in &self.log {
+            write!(f, "\n  {}", line)?;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,8 @@
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Flags {
     pub multi_instance: bool,
+    /// Link `castify://` con cui è stata lanciata l'app
+    pub connection_link: Option<String>,
+    /// Indirizzo del caster passato con `--connect`
+    pub connect_addr: Option<String>,
 }
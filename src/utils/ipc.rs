@@ -1,12 +1,85 @@
 use crate::config::app_id;
 use crate::gui::common::messages::AppEvent;
+use crate::utils::net::common::is_connection_link;
 use iced::{
     futures::{SinkExt, Stream},
     stream,
 };
 use interprocess::local_socket::{
-    GenericNamespaced, ListenerOptions, ToNsName, traits::tokio::Listener,
+    GenericNamespaced, ListenerOptions, ToNsName, traits::Stream as _, traits::tokio::Listener,
 };
+use std::io::Write;
+use tokio::io::AsyncReadExt;
+
+/// Richiesta di una seconda istanza a quella già aperta, una per riga:
+/// `focus`, `link castify://...` oppure `connect host:porta`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstanceMessage {
+    /// Porta in primo piano la finestra principale
+    Focus,
+    /// Link di connessione `castify://`
+    Link(String),
+    /// Indirizzo del caster passato con `--connect`
+    Connect(String),
+}
+
+impl InstanceMessage {
+    /// Messaggi per gli argomenti di questa istanza
+    pub fn from_args(link: Option<&str>, connect: Option<&str>) -> Vec<Self> {
+        let mut messages = vec![Self::Focus];
+        messages.extend(link.map(|link| Self::Link(link.to_owned())));
+        messages.extend(connect.map(|addr| Self::Connect(addr.to_owned())));
+        messages
+    }
+
+    fn encode(&self) -> String {
+        match self {
+            Self::Focus => String::from("focus\n"),
+            Self::Link(link) => format!("link {}\n", link.trim()),
+            Self::Connect(addr) => format!("connect {}\n", addr.trim()),
+        }
+    }
+
+    fn decode(line: &str) -> Option<Self> {
+        let line = line.trim();
+        // Versioni precedenti: il link da solo, senza comando
+        if is_connection_link(line) {
+            return Some(Self::Link(line.to_owned()));
+        }
+        let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let arg = arg.trim();
+        match command {
+            "focus" => Some(Self::Focus),
+            "link" if !arg.is_empty() => Some(Self::Link(arg.to_owned())),
+            "connect" if !arg.is_empty() => Some(Self::Connect(arg.to_owned())),
+            _ => None,
+        }
+    }
+
+    fn into_event(self) -> AppEvent {
+        match self {
+            Self::Focus => AppEvent::OpenMainWindow,
+            Self::Link(link) => AppEvent::OpenConnectionLink(link),
+            Self::Connect(addr) => AppEvent::OpenCasterAddress(addr),
+        }
+    }
+}
+
+/// Inoltra `messages` all'istanza già aperta; false se non ce n'è una.
+pub fn send_to_running(messages: &[InstanceMessage]) -> bool {
+    let name = app_id().to_ns_name::<GenericNamespaced>().unwrap();
+    let Ok(mut stream) = interprocess::local_socket::Stream::connect(name) else {
+        return false;
+    };
+    let payload: String = messages.iter().map(InstanceMessage::encode).collect();
+    if let Err(e) = stream.write_all(payload.as_bytes()) {
+        log::warn!(
+            "Unable to hand the arguments to the running instance: {}",
+            e
+        );
+    }
+    true
+}
 
 pub fn ipc() -> impl Stream<Item = AppEvent> {
     stream::channel(
@@ -18,11 +91,57 @@ pub fn ipc() -> impl Stream<Item = AppEvent> {
 
             if let Ok(listener) = listener_opts.create_tokio() {
                 loop {
-                    if let Ok(_stream) = listener.accept().await {
-                        output.send(AppEvent::OpenMainWindow).await.unwrap();
+                    if let Ok(mut stream) = listener.accept().await {
+                        // Una seconda istanza inoltra i suoi argomenti
+                        let mut payload = String::new();
+                        let _ = stream.read_to_string(&mut payload).await;
+                        let mut messages: Vec<_> = payload
+                            .lines()
+                            .filter_map(InstanceMessage::decode)
+                            .collect();
+                        if messages.is_empty() {
+                            messages.push(InstanceMessage::Focus);
+                        }
+                        for message in messages {
+                            log::info!("Second instance: {:?}", message);
+                            output.send(message.into_event()).await.unwrap();
+                        }
                     }
                 }
             }
         },
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = InstanceMessage::from_args(
+            Some("castify://connect?addr=10.0.0.2%3A31413"),
+            Some("10.0.0.2"),
+        );
+        let payload: String = messages.iter().map(InstanceMessage::encode).collect();
+        let decoded: Vec<_> = payload
+            .lines()
+            .filter_map(InstanceMessage::decode)
+            .collect();
+        assert_eq!(decoded, messages);
+    }
+
+    #[test]
+    fn bare_link_from_older_versions() {
+        assert_eq!(
+            InstanceMessage::decode("castify://connect?addr=h"),
+            Some(InstanceMessage::Link(String::from(
+                "castify://connect?addr=h"
+            )))
+        );
+        // Un host che inizia per "castify" non è un link
+        assert_eq!(InstanceMessage::decode("castify.lan:31413"), None);
+        assert_eq!(InstanceMessage::decode("connect "), None);
+        assert_eq!(InstanceMessage::decode("reboot now"), None);
+    }
+}
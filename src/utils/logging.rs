@@ -0,0 +1,197 @@
+//! Logging setup: console output, optional rotating file, level changeable at runtime
+//!
+//! `log` records are bridged into `tracing`, so both end up in the same
+//! subscriber. The level is applied through a reload handle; the file copy is
+//! gated by a flag, so the settings page can toggle both without a restart.
+//! `RUST_LOG`, when set, takes precedence at startup until the level is
+//! changed from the settings.
+//! The last lines are also kept in memory for the diagnostics report.
+
+use crate::config::app_id;
+use crate::utils::path::log_dir;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{Registry, reload};
+
+/// File di log tenuti nella cartella (uno al giorno)
+const MAX_LOG_FILES: usize = 7;
+
+static LEVEL_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+/// Tiene vivo il thread che scrive su file fino all'uscita
+static FILE_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+static FILE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Righe tenute in memoria per il riepilogo diagnostico
+const RECENT_LINES: usize = 200;
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Writer del layer in memoria: ogni evento arriva già formattato
+struct RecentWriter;
+
+impl io::Write for RecentWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+        for line in String::from_utf8_lossy(buf).lines() {
+            if recent.len() == RECENT_LINES {
+                recent.pop_front();
+            }
+            recent.push_back(line.to_string());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+
+    fn env_filter(self) -> EnvFilter {
+        EnvFilter::default().add_directive(self.filter().into())
+    }
+}
+
+/// Livello massimo per il bridge `log` → `tracing`
+fn log_filter(filter: LevelFilter) -> log::LevelFilter {
+    match filter {
+        LevelFilter::OFF => log::LevelFilter::Off,
+        LevelFilter::ERROR => log::LevelFilter::Error,
+        LevelFilter::WARN => log::LevelFilter::Warn,
+        LevelFilter::INFO => log::LevelFilter::Info,
+        LevelFilter::DEBUG => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogLevel::Error => "Error",
+            LogLevel::Warn => "Warning",
+            LogLevel::Info => "Info",
+            LogLevel::Debug => "Debug",
+            LogLevel::Trace => "Trace",
+        })
+    }
+}
+
+/// Nome del livello, senza distinzione tra maiuscole e minuscole (`--log-level`)
+impl FromStr for LogLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "error" => Ok(LogLevel::Error),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            "trace" => Ok(LogLevel::Trace),
+            other => anyhow::bail!("unknown log level {:?}", other),
+        }
+    }
+}
+
+/// Install the global subscriber; call once, before anything logs
+pub fn init(level: LogLevel, to_file: bool) {
+    // RUST_LOG (anche per modulo) vale fino al primo cambio dalle impostazioni
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| level.env_filter());
+    let max_level = filter.max_level_hint().unwrap_or(LevelFilter::TRACE);
+    let (filter, handle) = reload::Layer::new(filter);
+
+    // Senza cartella di configurazione si logga solo su console
+    let file_layer = log_dir().and_then(|dir| {
+        let appender = RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(app_id())
+            .filename_suffix("log")
+            .max_log_files(MAX_LOG_FILES)
+            .build(dir)
+            .ok()?;
+        let (writer, guard) = tracing_appender::non_blocking(appender);
+        let _ = FILE_GUARD.set(guard);
+        Some(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(writer.with_filter(|_| FILE_ENABLED.load(Ordering::Relaxed))),
+        )
+    });
+
+    FILE_ENABLED.store(to_file, Ordering::Relaxed);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(file_layer)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(|| RecentWriter),
+        )
+        .init();
+    let _ = LEVEL_HANDLE.set(handle);
+    log::set_max_level(log_filter(max_level));
+}
+
+/// Change the verbosity of the running app
+pub fn set_level(level: LogLevel) {
+    if let Some(handle) = LEVEL_HANDLE.get()
+        && let Err(e) = handle.reload(level.env_filter())
+    {
+        log::warn!("Unable to change the log level: {}", e);
+        return;
+    }
+    // Il bridge `log` → `tracing` filtra per conto suo
+    log::set_max_level(log_filter(level.filter()));
+}
+
+/// Start or stop copying the log to the rotating file
+pub fn set_file_enabled(enabled: bool) {
+    FILE_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Ultime `count` righe di log, dalla più vecchia
+pub fn recent_lines(count: usize) -> Vec<String> {
+    let recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    recent
+        .iter()
+        .skip(recent.len().saturating_sub(count))
+        .cloned()
+        .collect()
+}
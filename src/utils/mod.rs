@@ -3,17 +3,24 @@
 //! This module provides various utility functions and data structures
 //! used throughout the application.
 
+pub mod audio_level;
 pub mod bimap;
+pub mod diagnostics;
 pub mod flags;
 mod helpers;
 pub mod ipc;
+pub mod logging;
 pub mod monitors;
 pub mod net;
 pub mod path;
 pub mod perf;
+#[cfg(feature = "remote-control")]
+pub mod remote_control;
+pub mod selftest;
 pub mod sos;
 pub mod status;
 pub mod string;
+pub mod url_scheme;
 
 pub use helpers::{
     SendResult, evaluate_points, open_link, result_to_option, try_send, try_send_log,
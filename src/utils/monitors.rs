@@ -14,6 +14,19 @@ pub struct XMonitor {
 
 unsafe impl Send for XMonitor {}
 
+impl XMonitor {
+    /// Area del monitor nelle coordinate logiche usate dalle finestre iced
+    pub fn logical_rect(&self) -> (f32, f32, f32, f32) {
+        let sc = if self.sc > 0.0 { self.sc } else { 1.0 };
+        (
+            self.x as f32 / sc,
+            self.y as f32 / sc,
+            self.width as f32 / sc,
+            self.height as f32 / sc,
+        )
+    }
+}
+
 pub struct Monitors {
     main: u32,
     monitors: HashMap<u32, XMonitor>,
@@ -43,6 +56,17 @@ impl Monitors {
         self.monitors.get(&self.main)
     }
 
+    /// Monitor che contiene il punto (coordinate logiche), altrimenti il principale
+    pub fn at_point(&self, x: f32, y: f32) -> Option<&XMonitor> {
+        self.monitors
+            .values()
+            .find(|monitor| {
+                let (mx, my, mw, mh) = monitor.logical_rect();
+                x >= mx && x < mx + mw && y >= my && y < my + mh
+            })
+            .or_else(|| self.get_monitor())
+    }
+
     pub fn get_monitor_id(&self) -> u32 {
         self.main
     }
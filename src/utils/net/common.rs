@@ -1,38 +1,267 @@
 use crate::assets::CAST_SERVICE_PORT;
+use anyhow::{anyhow, bail};
 use local_ip_address::local_ip;
 use mdns_sd::{IfKind, ServiceDaemon, ServiceEvent, ServiceInfo};
 use natpmp::Natpmp;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::str::FromStr;
 use std::thread::sleep;
 use std::time::Duration;
 
 const LOCAL_DISCOVERY_SERVICE_NAME: &str = "_screen_caster._tcp.local.";
+/// Schema URL dei link di connessione (`castify://connect?addr=...`)
+pub const LINK_SCHEME: &str = "castify";
+/// Inizio di ogni link: lo schema da solo è anche l'inizio di hostname
+/// validi come `castify-pc`
+pub const LINK_PREFIX: &str = "castify://";
+/// Durata di una scansione mDNS dei caster
+pub const DISCOVERY_WINDOW: Duration = Duration::from_secs(3);
 
-pub fn find_caster() -> Option<SocketAddr> {
-    // Create a daemon
-    let mdns = ServiceDaemon::new().expect("Failed to create daemon");
-    // Browse for a service type.
-    let receiver = mdns
-        .browse(LOCAL_DISCOVERY_SERVICE_NAME)
-        .expect("Failed to browse");
-
-    let mut addr: Option<SocketAddr> = None;
-
-    while let Some(event) = receiver.iter().next() {
-        println!("waiting for a caster");
-        if let ServiceEvent::ServiceResolved(info) = event {
-            let ip_addr = info.get_addresses_v4().iter().next()?.to_string();
-            println!("Resolved caster service at: {:?}", ip_addr);
-            addr = Option::from(SocketAddr::new(ip_addr.parse().unwrap(), info.get_port()));
+/// Tra più indirizzi dello stesso caster sceglie sempre lo stesso:
+/// IPv4 prima di IPv6, indirizzi routabili prima di loopback/link-local,
+/// a parità il minore (i set di mDNS/DNS non hanno un ordine stabile).
+fn preferred_address(addrs: impl IntoIterator<Item = IpAddr>) -> Option<IpAddr> {
+    addrs.into_iter().min_by_key(|ip| {
+        let local = match ip {
+            IpAddr::V4(v4) => v4.is_loopback() || v4.is_link_local(),
+            IpAddr::V6(v6) => v6.is_loopback() || v6.is_unicast_link_local(),
+        };
+        (ip.is_ipv6(), local, *ip)
+    })
+}
+
+/// Interpreta l'indirizzo del caster inserito dall'utente.
+///
+/// Accetta `ip`, `ip:porta`, IPv6 nudo (`::1`), IPv6 tra parentesi con o
+/// senza porta (`[::1]:porta`) e hostname DNS (`host`, `host:porta`),
+/// usando `CAST_SERVICE_PORT` se la porta manca.
+pub fn parse_caster_addr(input: &str) -> anyhow::Result<SocketAddr> {
+    let input = input.trim();
+    if input.is_empty() {
+        bail!("Enter an IP address or hostname");
+    }
+    // Link incollato nel campo: vale l'indirizzo contenuto
+    if is_connection_link(input) {
+        return parse_caster_addr(&parse_connection_link(input)?.addr);
+    }
+
+    // ip:porta oppure [v6]:porta
+    if let Ok(addr) = SocketAddr::from_str(input) {
+        return Ok(addr);
+    }
+    // IPv4 o IPv6 senza porta
+    if let Ok(ip) = IpAddr::from_str(input) {
+        return Ok(SocketAddr::new(ip, CAST_SERVICE_PORT));
+    }
+    // [v6] senza porta
+    if let Some(inner) = input.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        let ip = Ipv6Addr::from_str(inner).map_err(|_| anyhow!("Invalid IPv6 address"))?;
+        return Ok(SocketAddr::new(IpAddr::V6(ip), CAST_SERVICE_PORT));
+    }
+
+    let (host, port) = match input.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port
+                .parse::<u16>()
+                .map_err(|_| anyhow!("Invalid port \"{}\"", port))?;
+            (host, port)
+        }
+        None => (input, CAST_SERVICE_PORT),
+    };
+
+    let valid_host = !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if !valid_host {
+        bail!("\"{}\" is not a valid address or hostname", host);
+    }
+
+    let resolved = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| anyhow!("Unable to resolve {}: {}", host, e))?;
+    let ip = preferred_address(resolved.map(|addr| addr.ip()))
+        .ok_or_else(|| anyhow!("{} has no addresses", host))?;
+
+    Ok(SocketAddr::new(ip, port))
+}
+
+/// Contenuto di un link `castify://connect?addr=ip:porta&name=...`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionLink {
+    pub addr: String,
+    pub name: Option<String>,
+}
+
+/// Link da condividere con chi deve collegarsi al caster.
+pub fn connection_link(addr: SocketAddr, name: &str) -> String {
+    let mut link = format!(
+        "{}connect?addr={}",
+        LINK_PREFIX,
+        percent_encode(&addr.to_string())
+    );
+    if !name.trim().is_empty() {
+        link.push_str("&name=");
+        link.push_str(&percent_encode(name.trim()));
+    }
+    link
+}
+
+/// `input` è un link `castify://` e non un indirizzo
+pub fn is_connection_link(input: &str) -> bool {
+    input
+        .trim()
+        .get(..LINK_PREFIX.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(LINK_PREFIX))
+}
+
+/// Interpreta un link di connessione; l'indirizzo non viene risolto
+/// (lo fa `parse_caster_addr` al momento di collegarsi).
+pub fn parse_connection_link(input: &str) -> anyhow::Result<ConnectionLink> {
+    let input = input.trim();
+    if !is_connection_link(input) {
+        bail!("Not a {} link", LINK_SCHEME);
+    }
+    let rest = &input[LINK_PREFIX.len()..];
+    let query = rest
+        .strip_prefix("connect")
+        .map(|s| s.trim_start_matches('/'))
+        .and_then(|s| s.strip_prefix('?'))
+        .ok_or_else(|| anyhow!("Unsupported {} link", LINK_SCHEME))?;
+
+    let mut addr = None;
+    let mut name = None;
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value)?;
+        match key {
+            "addr" => addr = Some(value),
+            "name" => name = Some(value).filter(|n| !n.is_empty()),
+            _ => {}
+        }
+    }
+
+    let addr = addr
+        .filter(|a| !a.is_empty())
+        .ok_or_else(|| anyhow!("The link does not contain an address"))?;
+    Ok(ConnectionLink { addr, name })
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~:[]".contains(&byte) {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    out
+}
+
+fn percent_decode(value: &str) -> anyhow::Result<String> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = value
+                    .get(i + 1..i + 3)
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| anyhow!("Malformed link"))?;
+                out.push(hex);
+                i += 3;
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).map_err(|_| anyhow!("Malformed link"))
+}
+
+/// Nome mostrato ai receiver quando l'utente non ne sceglie uno.
+pub fn default_instance_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| String::from("ScreenCaster"))
+}
+
+/// Raccoglie per `window` tutti i caster annunciati via mDNS.
+///
+/// Ritorna (nome istanza, indirizzo) senza duplicati, ordinati per nome:
+/// con più caster sulla stessa LAN è l'utente a scegliere.
+pub fn find_casters(window: Duration) -> Vec<(String, SocketAddr)> {
+    let mdns = match ServiceDaemon::new() {
+        Ok(mdns) => mdns,
+        Err(e) => {
+            log::error!("mDNS daemon error: {}", e);
+            return Vec::new();
+        }
+    };
+    let receiver = match mdns.browse(LOCAL_DISCOVERY_SERVICE_NAME) {
+        Ok(receiver) => receiver,
+        Err(e) => {
+            log::error!("mDNS browse error: {}", e);
+            let _ = mdns.shutdown();
+            return Vec::new();
+        }
+    };
+
+    let suffix = format!(".{}", LOCAL_DISCOVERY_SERVICE_NAME);
+    let deadline = std::time::Instant::now() + window;
+    let mut casters: Vec<(String, SocketAddr)> = Vec::new();
+
+    while let Some(remaining) = deadline.checked_duration_since(std::time::Instant::now()) {
+        let Ok(event) = receiver.recv_timeout(remaining) else {
             break;
+        };
+        let ServiceEvent::ServiceResolved(info) = event else {
+            continue;
+        };
+
+        // Il caster annuncia solo IPv4 (IPv6 disabilitato in caster_discover_service)
+        let Some(ip_addr) =
+            preferred_address(info.get_addresses_v4().iter().map(|ip| IpAddr::V4(*ip)))
+        else {
+            continue;
+        };
+
+        let fullname = info.get_fullname();
+        let name = fullname
+            .strip_suffix(&suffix)
+            .unwrap_or(fullname)
+            .to_owned();
+        let addr = SocketAddr::new(ip_addr, info.get_port());
+        log::info!("Resolved caster \"{}\" at {}", name, addr);
+
+        match casters.iter_mut().find(|(n, _)| *n == name) {
+            Some(entry) => entry.1 = addr,
+            None => casters.push((name, addr)),
         }
     }
-    mdns.shutdown().unwrap();
+    let _ = mdns.shutdown();
 
-    addr
+    casters.sort_by(|a, b| a.0.cmp(&b.0));
+    casters
 }
 
-pub fn caster_discover_service() -> Result<ServiceDaemon, Box<dyn std::error::Error>> {
+pub fn caster_discover_service(
+    instance_name: &str,
+) -> Result<ServiceDaemon, Box<dyn std::error::Error>> {
     let mdns = ServiceDaemon::new()?;
     mdns.disable_interface(IfKind::IPv6).unwrap();
 
@@ -42,7 +271,7 @@ pub fn caster_discover_service() -> Result<ServiceDaemon, Box<dyn std::error::Er
 
     let my_service = ServiceInfo::new(
         LOCAL_DISCOVERY_SERVICE_NAME,
-        "ScreenCaster",
+        instance_name,
         &host_name,
         ip,
         CAST_SERVICE_PORT,
@@ -70,7 +299,25 @@ pub fn port_forwarding() -> Result<(), Box<dyn std::error::Error>> {
 
     // Handle response (wait for a while for the response to come back)
     let mapping = natpmp.read_response_or_retry()?;
-    println!("Port forwarding setup complete: {:?}", mapping);
+    log::info!("Port forwarding setup complete: {:?}", mapping);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_full_prefix_makes_a_link() {
+        assert!(is_connection_link(" castify://connect?addr=10.0.0.2:31413"));
+        assert!(is_connection_link("Castify://connect?addr=10.0.0.2"));
+        assert!(!is_connection_link("castify-pc.local:31413"));
+        assert!(!is_connection_link("castify"));
+
+        let addr = SocketAddr::from(([10, 0, 0, 2], 31413));
+        let link = parse_connection_link(&connection_link(addr, "Sala riunioni")).unwrap();
+        assert_eq!(link.addr, "10.0.0.2:31413");
+        assert_eq!(link.name.as_deref(), Some("Sala riunioni"));
+    }
+}
@@ -1,2 +1,3 @@
 pub mod common;
+pub mod webhook;
 pub mod webrtc;
@@ -0,0 +1,121 @@
+//! Webhook HTTP per collegare Castify ad altri strumenti (scene OBS, stato
+//! Discord, server di log).
+//!
+//! Ogni evento è un POST JSON `{"event", "timestamp", "clients"}` verso tutti
+//! gli URL configurati, inviato in un task a parte: un endpoint lento o
+//! irraggiungibile finisce nel log e non blocca mai la pipeline.
+
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde_json::{Value, json};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Oltre questo tempo la richiesta viene abbandonata
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    StreamStart,
+    StreamStop,
+    ClientConnect,
+    ClientDisconnect,
+    RecordingStart,
+    RecordingStop,
+}
+
+impl WebhookEvent {
+    /// Nome dell'evento nel payload
+    fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::StreamStart => "stream_start",
+            WebhookEvent::StreamStop => "stream_stop",
+            WebhookEvent::ClientConnect => "client_connect",
+            WebhookEvent::ClientDisconnect => "client_disconnect",
+            WebhookEvent::RecordingStart => "recording_start",
+            WebhookEvent::RecordingStop => "recording_stop",
+        }
+    }
+}
+
+/// URL di destinazione condivisi: i cloni vedono subito una lista aggiornata.
+#[derive(Clone, Default)]
+pub struct Webhooks {
+    urls: Arc<RwLock<Vec<String>>>,
+    client: reqwest::Client,
+}
+
+impl Webhooks {
+    pub fn set_urls(&self, urls: Vec<String>) {
+        *self.urls.write().unwrap() = urls;
+    }
+
+    /// Invia `event` a tutti gli URL senza attendere la risposta.
+    pub fn fire(&self, event: WebhookEvent, clients: usize) {
+        let urls = self.urls.read().unwrap().clone();
+        if urls.is_empty() {
+            return;
+        }
+
+        let payload = payload(event, clients, Utc::now());
+        for url in urls {
+            let request = self
+                .client
+                .post(&url)
+                .timeout(WEBHOOK_TIMEOUT)
+                .json(&payload);
+            tokio::spawn(async move {
+                match request.send().await.and_then(|r| r.error_for_status()) {
+                    Ok(_) => log::debug!("Webhook {} sent to {}", event.name(), url),
+                    Err(e) => log::warn!("Webhook {} to {} failed: {}", event.name(), url, e),
+                }
+            });
+        }
+    }
+}
+
+/// Corpo JSON di un evento
+fn payload(event: WebhookEvent, clients: usize, at: DateTime<Utc>) -> Value {
+    json!({
+        "event": event.name(),
+        "timestamp": at.to_rfc3339(),
+        "clients": clients,
+    })
+}
+
+/// Un URL utilizzabile come webhook: http o https, con un host.
+pub fn is_webhook_url(url: &str) -> bool {
+    Url::parse(url.trim()).is_ok_and(|url| {
+        matches!(url.scheme(), "http" | "https") && url.host_str().is_some_and(|h| !h.is_empty())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn only_http_urls_with_a_host_are_webhooks() {
+        assert!(is_webhook_url("http://localhost:8080/hook"));
+        assert!(is_webhook_url("  HTTPS://example.com/obs?scene=live "));
+        assert!(!is_webhook_url("https://"));
+        assert!(!is_webhook_url("ftp://example.com/hook"));
+        assert!(!is_webhook_url("example.com/hook"));
+        assert!(!is_webhook_url("http://exa mple.com"));
+        assert!(!is_webhook_url(""));
+    }
+
+    #[test]
+    fn payload_carries_event_time_and_clients() {
+        let at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap();
+        assert_eq!(
+            payload(WebhookEvent::ClientConnect, 3, at),
+            json!({
+                "event": "client_connect",
+                "timestamp": "2024-05-01T12:30:00+00:00",
+                "clients": 3,
+            })
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! Annotazioni del caster inoltrate ai receiver
+//!
+//! Tipi del protocollo, indipendenti dalla GUI: la conversione da e verso le
+//! forme disegnate sta in `gui::components::annotation`.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StrokeKind {
+    Freehand,
+    Line,
+    Rectangle,
+    Circle,
+}
+
+/// Tratto inviato ai receiver. Le coordinate sono normalizzate (0.0..=1.0)
+/// rispetto all'area trasmessa, `ref_width` è la larghezza di quell'area nel
+/// canvas del caster e serve a riscalare lo spessore del tratto.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteStroke {
+    pub kind: StrokeKind,
+    /// RGBA in 0.0..=1.0, trasparenza compresa
+    pub color: [f32; 4],
+    /// Spessore in pixel del canvas del caster
+    pub width: f32,
+    pub filled: bool,
+    pub points: Vec<[f32; 2]>,
+    pub ref_width: f32,
+}
+
+/// Delta delle annotazioni inviati sul data channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AnnotationEvent {
+    /// Nuovo tratto completato
+    Add(RemoteStroke),
+    /// Stato completo (undo/redo, gomma, peer appena connesso)
+    Sync(Vec<RemoteStroke>),
+    Clear,
+}
+
+impl AnnotationEvent {
+    pub fn apply(self, strokes: &mut Vec<RemoteStroke>) {
+        match self {
+            AnnotationEvent::Add(stroke) => strokes.push(stroke),
+            AnnotationEvent::Sync(all) => *strokes = all,
+            AnnotationEvent::Clear => strokes.clear(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stroke(x: f32) -> RemoteStroke {
+        RemoteStroke {
+            kind: StrokeKind::Line,
+            color: [1.0, 0.0, 0.0, 1.0],
+            width: 5.0,
+            filled: false,
+            points: vec![[0.0, 0.0], [x, x]],
+            ref_width: 1280.0,
+        }
+    }
+
+    #[test]
+    fn events_rebuild_the_caster_drawing() {
+        let mut strokes = Vec::new();
+        AnnotationEvent::Add(stroke(0.5)).apply(&mut strokes);
+        AnnotationEvent::Add(stroke(0.7)).apply(&mut strokes);
+        assert_eq!(strokes, vec![stroke(0.5), stroke(0.7)]);
+
+        AnnotationEvent::Sync(vec![stroke(0.2)]).apply(&mut strokes);
+        assert_eq!(strokes, vec![stroke(0.2)]);
+
+        AnnotationEvent::Clear.apply(&mut strokes);
+        assert!(strokes.is_empty());
+    }
+}
@@ -1,6 +1,14 @@
+use crate::capture::FpsCap;
+use crate::capture::audio::EncodedAudio;
 use crate::capture::capturer::CaptureFpsController;
+use crate::pipeline::simulcast::SimulcastTier;
+use crate::pipeline::stats_log::RttSource;
+use crate::pipeline::types::Timestamp;
+use crate::utils::net::webrtc::annotation::AnnotationEvent;
+use crate::utils::net::webrtc::chat::ChatMessage;
 use crate::utils::net::webrtc::peer::WRTCPeer;
 use crate::utils::sos::SignalOfStop;
+use async_trait::async_trait;
 use rtc::media::Sample;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
@@ -18,7 +26,8 @@ enum StreamProfile {
 impl StreamProfile {
     fn target_frame_interval(self) -> Duration {
         match self {
-            StreamProfile::High => Duration::from_millis(16),
+            // Nessun pacing oltre il tetto di cattura (fino a FpsCap::MAX)
+            StreamProfile::High => Duration::from_millis(5),
             StreamProfile::Balanced => Duration::from_millis(25),
             StreamProfile::Low => Duration::from_millis(41),
             StreamProfile::Emergency => Duration::from_millis(83),
@@ -27,7 +36,8 @@ impl StreamProfile {
 
     fn capture_fps_cap(self) -> u32 {
         match self {
-            StreamProfile::High => 60,
+            // Il tetto dell'utente e il display decidono
+            StreamProfile::High => FpsCap::MAX,
             StreamProfile::Balanced => 40,
             StreamProfile::Low => 24,
             StreamProfile::Emergency => 15,
@@ -170,6 +180,70 @@ fn contains_idr(data: &[u8]) -> bool {
     false
 }
 
+/// Durata di un pacchetto Opus sulla timeline RTP. Una pausa della cattura
+/// (buffer del dispositivo svuotato in ritardo, sleep del sistema) allunga
+/// il salto fino al prossimo pacchetto, sempre a multipli interi del frame:
+/// il receiver continua a vedere pacchetti allineati alla griglia Opus.
+fn audio_sample_duration(
+    previous: Option<Timestamp>,
+    pts: Timestamp,
+    frame_duration: Duration,
+) -> Duration {
+    let Some(previous) = previous else {
+        return frame_duration;
+    };
+    let frame_us = frame_duration.as_micros().max(1) as i64;
+    let frames = ((pts.micros - previous.micros) + frame_us / 2) / frame_us;
+    frame_duration * frames.max(1) as u32
+}
+
+/// Clock RTP del video H.264
+pub(crate) const VIDEO_CLOCK_RATE: u64 = 90_000;
+/// Avanzamento minimo del timestamp RTP tra due frame (1 ms)
+const MIN_FRAME_TICKS: u64 = VIDEO_CLOCK_RATE / 1000;
+
+/// Timeline RTP del video ricavata dai PTS di cattura. Il packetizer di
+/// webrtc-rs tronca ogni `Sample.duration` a tick interi: sommando durate
+/// calcolate frame per frame l'errore si accumula. Qui ogni durata è la
+/// differenza tra tick cumulativi, così il resto passa al frame successivo.
+pub(crate) struct VideoRtpClock {
+    /// Posizione sui PTS (in tick) raggiunta dalla timeline RTP
+    timeline: Option<u64>,
+}
+
+impl VideoRtpClock {
+    pub(crate) fn new() -> Self {
+        Self { timeline: None }
+    }
+
+    fn ticks(pts: Timestamp) -> u64 {
+        pts.micros.max(0) as u64 * VIDEO_CLOCK_RATE / 1_000_000
+    }
+
+    /// Durata del sample con PTS `pts`; `first` vale per il primo frame
+    pub(crate) fn sample_duration(&mut self, pts: Timestamp, first: Duration) -> Duration {
+        let ticks = Self::ticks(pts);
+        let frame_ticks = match self.timeline {
+            Some(timeline) => {
+                let frame_ticks = ticks.saturating_sub(timeline).max(MIN_FRAME_TICKS);
+                self.timeline = Some(timeline + frame_ticks);
+                frame_ticks
+            }
+            None => {
+                self.timeline = Some(ticks);
+                (first.as_micros() as u64 * VIDEO_CLOCK_RATE / 1_000_000).max(MIN_FRAME_TICKS)
+            }
+        };
+        ticks_duration(frame_ticks)
+    }
+}
+
+/// Durata che il packetizer riconverte esattamente in `ticks`: calcola
+/// `(secs * clock) as u32`, quindi i nanosecondi vanno arrotondati per eccesso
+fn ticks_duration(ticks: u64) -> Duration {
+    Duration::from_nanos((ticks * 1_000_000_000).div_ceil(VIDEO_CLOCK_RATE))
+}
+
 fn is_rtp_backpressure(err: &(dyn std::error::Error + 'static)) -> bool {
     err.to_string().contains("Full(SenderRtp(")
 }
@@ -184,6 +258,13 @@ pub struct WebRTCCaster {
     /// Shared force_idr flag for manual peer creation
     force_idr: std::sync::Mutex<Arc<AtomicBool>>,
     capture_fps_controller: std::sync::Mutex<Option<CaptureFpsController>>,
+    /// Keyframe flag and on/off switch of the simulcast low tier, `None`
+    /// with a single encoder
+    simulcast: std::sync::Mutex<Option<(Arc<AtomicBool>, Arc<AtomicBool>)>>,
+    /// New peers are created without the video track
+    audio_only: AtomicBool,
+    /// New peers send raw PCM audio instead of Opus
+    low_latency_audio: AtomicBool,
 }
 
 impl WebRTCCaster {
@@ -195,9 +276,43 @@ impl WebRTCCaster {
             peers_version: Arc::new(AtomicU64::new(0)),
             force_idr: std::sync::Mutex::new(Arc::new(AtomicBool::new(false))),
             capture_fps_controller: std::sync::Mutex::new(None),
+            simulcast: std::sync::Mutex::new(None),
+            audio_only: AtomicBool::new(false),
+            low_latency_audio: AtomicBool::new(false),
         }
     }
 
+    /// Peers with an established connection
+    pub async fn connected_peers(&self) -> usize {
+        self.peers
+            .read()
+            .await
+            .iter()
+            .filter(|p| p.is_connected())
+            .count()
+    }
+
+    pub fn set_audio_only(&self, audio_only: bool) {
+        self.audio_only.store(audio_only, Ordering::Relaxed);
+    }
+
+    pub fn set_low_latency_audio(&self, low_latency: bool) {
+        self.low_latency_audio.store(low_latency, Ordering::Relaxed);
+    }
+
+    /// Create a peer with the tracks matching the current casting mode.
+    pub async fn create_peer(
+        &self,
+        force_idr: Arc<AtomicBool>,
+    ) -> Result<Arc<WRTCPeer>, Box<dyn std::error::Error + Send + Sync>> {
+        WRTCPeer::with_tracks(
+            force_idr,
+            !self.audio_only.load(Ordering::Relaxed),
+            self.low_latency_audio.load(Ordering::Relaxed),
+        )
+        .await
+    }
+
     pub fn set_force_idr(&self, flag: Arc<AtomicBool>) {
         *self.force_idr.lock().unwrap() = flag;
     }
@@ -206,6 +321,13 @@ impl WebRTCCaster {
         *self.capture_fps_controller.lock().unwrap() = Some(controller);
     }
 
+    /// Serve each peer from one of two encoders; must be set before the
+    /// send loops start. `active` runs the low-tier encoder, and is kept
+    /// on only while some peer is served from it.
+    pub fn set_simulcast(&self, force_idr: Arc<AtomicBool>, active: Arc<AtomicBool>) {
+        *self.simulcast.lock().unwrap() = Some((force_idr, active));
+    }
+
     pub async fn push(&self, peer: Arc<WRTCPeer>) {
         self.peers.write().await.push(peer);
         self.peers_version.fetch_add(1, Ordering::Relaxed);
@@ -217,7 +339,7 @@ impl WebRTCCaster {
             self.manual
                 .lock()
                 .await
-                .replace(WRTCPeer::new(force_idr).await.unwrap());
+                .replace(self.create_peer(force_idr).await.unwrap());
         }
         Arc::clone(self.manual.lock().await.as_ref().unwrap())
     }
@@ -231,20 +353,71 @@ impl WebRTCCaster {
         }
     }
 
+    /// Forward an annotation delta to every connected peer.
+    pub async fn broadcast_annotation(&self, event: &AnnotationEvent) {
+        for peer in self.peers.read().await.iter().filter(|p| p.is_online()) {
+            peer.send_annotation(event.clone());
+        }
+    }
+
+    /// Forward a chat message to every connected peer.
+    pub async fn broadcast_chat(&self, message: &ChatMessage) {
+        for peer in self.peers.read().await.iter().filter(|p| p.is_online()) {
+            peer.send_chat(message.text.clone());
+        }
+    }
+
+    /// Forward the text copied on the caster to every connected peer.
+    pub async fn broadcast_clipboard(&self, text: &str) {
+        for peer in self.peers.read().await.iter().filter(|p| p.is_online()) {
+            peer.send_clipboard(text.to_string());
+        }
+    }
+
     pub fn send_video_frames(
+        &self,
+        receiver: tokio::sync::mpsc::Receiver<crate::capture::capturer::EncodedFrame>,
+    ) {
+        let capture_fps_controller = self.capture_fps_controller.lock().unwrap().clone();
+        self.spawn_video_loop(receiver, SimulcastTier::High, capture_fps_controller);
+    }
+
+    /// Frames of the simulcast low tier, sent to the peers whose RTCP
+    /// feedback reports a link too slow for the main one.
+    pub fn send_simulcast_frames(
+        &self,
+        receiver: tokio::sync::mpsc::Receiver<crate::capture::capturer::EncodedFrame>,
+    ) {
+        self.spawn_video_loop(receiver, SimulcastTier::Low, None);
+    }
+
+    /// Send loop of one encoder. Only the main tier drives the capture fps.
+    fn spawn_video_loop(
         &self,
         mut receiver: tokio::sync::mpsc::Receiver<crate::capture::capturer::EncodedFrame>,
+        tier: SimulcastTier,
+        capture_fps_controller: Option<CaptureFpsController>,
     ) {
         let peers = Arc::clone(&self.peers);
         let peers_version = Arc::clone(&self.peers_version);
-        let capture_fps_controller = self.capture_fps_controller.lock().unwrap().clone();
+        // A peer moved to this tier asks its encoder for an IDR; the main
+        // loop also turns the low tier on and off
+        let simulcast = self
+            .simulcast
+            .lock()
+            .unwrap()
+            .clone()
+            .map(|(low_idr, low_active)| match tier {
+                SimulcastTier::High => (self.force_idr.lock().unwrap().clone(), Some(low_active)),
+                SimulcastTier::Low => (low_idr, None),
+            });
 
         self.sos.spawn(async move {
-            log::info!("=== WEBRTC SENDER: Video send loop STARTED ===");
+            log::info!("=== WEBRTC SENDER: Video send loop STARTED ({:?} tier) ===", tier);
 
             let mut cached_peers: Vec<Arc<WRTCPeer>> = Vec::new();
             let mut last_version: u64 = u64::MAX;
-            let mut last_sent_timestamp_ms: Option<u64> = None;
+            let mut rtp_clock = VideoRtpClock::new();
             let mut total_frames_sent = 0u64;
             let mut last_stats_log = Instant::now();
             let mut adaptive = AdaptiveVideoController::new();
@@ -264,13 +437,11 @@ impl WebRTCCaster {
                     continue;
                 }
 
-                let frame_duration = match last_sent_timestamp_ms {
-                    Some(previous_ts) => Duration::from_millis(
-                        frame.timestamp_ms.saturating_sub(previous_ts).max(1),
-                    ),
-                    None => adaptive.profile.target_frame_interval(),
-                };
-                last_sent_timestamp_ms = Some(frame.timestamp_ms);
+                // La durata fa avanzare il timestamp RTP: distanza reale tra
+                // gli istanti di cattura, così la timeline RTP segue il MediaClock
+                let pts = frame.pts;
+                let frame_duration =
+                    rtp_clock.sample_duration(pts, adaptive.profile.target_frame_interval());
 
                 let sample = Sample {
                     data: frame.data.into(),
@@ -294,13 +465,23 @@ impl WebRTCCaster {
                 let send_started = Instant::now();
                 let mut send_failures = 0u64;
                 let mut send_backpressure = 0u64;
+                let mut served = 0usize;
                 for peer in &cached_peers {
+                    if let Some((tier_idr, _)) = &simulcast
+                        && !peer.accepts_video_tier(tier, is_keyframe)
+                    {
+                        if peer.take_tier_keyframe_request(tier) {
+                            tier_idr.store(true, Ordering::Relaxed);
+                        }
+                        continue;
+                    }
+                    served += 1;
+
                     let send = tokio::time::timeout(
                         Duration::from_millis(80),
-                        peer.send_video_sample(&sample),
+                        peer.send_video_sample(&sample, pts),
                     )
                     .await;
-
                     match send {
                         Ok(Ok(())) => {}
                         Ok(Err(e)) => {
@@ -317,9 +498,17 @@ impl WebRTCCaster {
                         }
                     }
                 }
+                if let Some((_, Some(low_active))) = &simulcast {
+                    let in_use = cached_peers
+                        .iter()
+                        .any(|p| p.video_tier() == SimulcastTier::Low);
+                    if low_active.swap(in_use, Ordering::Relaxed) != in_use {
+                        log::info!("Simulcast low tier {}", if in_use { "on" } else { "off" });
+                    }
+                }
 
                 let send_ms = send_started.elapsed().as_secs_f64() * 1000.0;
-                adaptive.observe(send_ms, send_failures, cached_peers.len());
+                adaptive.observe(send_ms, send_failures, served);
                 if send_backpressure > 0 {
                     for _ in 0..send_backpressure {
                         if let Some(profile) = adaptive.degrade_for_backpressure() {
@@ -346,18 +535,18 @@ impl WebRTCCaster {
                     applied_capture_fps = target_capture_fps;
                 }
 
-                if !cached_peers.is_empty() && send_failures == cached_peers.len() as u64 {
+                if served > 0 && send_failures == served as u64 {
                     consecutive_all_peer_failures =
                         consecutive_all_peer_failures.saturating_add(1);
                     if consecutive_all_peer_failures == 1 && send_backpressure > 0 {
                         log::warn!(
                             "Video sender backpressure on all {} peers, dropping frame",
-                            cached_peers.len()
+                            served
                         );
                     } else if consecutive_all_peer_failures >= 10 {
                         log::error!(
                             "Failed to send video to all {} peers for {} consecutive frames",
-                            cached_peers.len(),
+                            served,
                             consecutive_all_peer_failures
                         );
                     }
@@ -369,9 +558,10 @@ impl WebRTCCaster {
 
                 if last_stats_log.elapsed().as_secs() >= 30 {
                     log::info!(
-                        "Video sender stats: {} frames sent, {} active peers, avg_send={:.1}ms",
+                        "Video sender stats ({:?} tier): {} frames sent, {} peers served, avg_send={:.1}ms",
+                        tier,
                         total_frames_sent,
-                        cached_peers.len(),
+                        served,
                         adaptive.avg_send_ms
                     );
                     last_stats_log = Instant::now();
@@ -386,13 +576,18 @@ impl WebRTCCaster {
             }
 
             log::error!(
-                "=== WEBRTC SENDER: Video send loop EXITED after {} frames ===",
+                "=== WEBRTC SENDER: Video send loop EXITED ({:?} tier) after {} frames ===",
+                tier,
                 total_frames_sent
             );
         });
     }
 
-    pub fn send_audio_frames(&self, mut receiver: tokio::sync::mpsc::Receiver<Vec<u8>>) {
+    pub fn send_audio_frames(
+        &self,
+        mut receiver: tokio::sync::mpsc::Receiver<EncodedAudio>,
+        frame_duration: Duration,
+    ) {
         let peers = Arc::clone(&self.peers);
         let peers_version = Arc::clone(&self.peers_version);
 
@@ -401,13 +596,15 @@ impl WebRTCCaster {
             let mut last_version: u64 = u64::MAX;
             let mut total_frames_sent = 0u64;
             let mut last_stats_log = Instant::now();
+            let mut last_pts: Option<Timestamp> = None;
 
-            while let Some(data) = receiver.recv().await {
+            while let Some(packet) = receiver.recv().await {
                 let sample = Sample {
-                    data: data.into(),
-                    duration: Duration::from_millis(10),
+                    data: packet.data.into(),
+                    duration: audio_sample_duration(last_pts, packet.pts, frame_duration),
                     ..Default::default()
                 };
+                last_pts = Some(packet.pts);
 
                 let current_version = peers_version.load(Ordering::Relaxed);
                 if current_version != last_version {
@@ -420,7 +617,7 @@ impl WebRTCCaster {
 
                 let mut send_failures = 0;
                 for peer in &cached_peers {
-                    if let Err(e) = peer.send_audio_sample(&sample).await {
+                    if let Err(e) = peer.send_audio_sample(&sample, packet.pts).await {
                         log::warn!("Failed to send audio sample to peer: {}", e);
                         send_failures += 1;
                     }
@@ -450,3 +647,100 @@ impl WebRTCCaster {
         self.sos.cancel()
     }
 }
+
+/// Il peer più lento: è lui a dare il ritardo percepito
+#[async_trait]
+impl RttSource for WebRTCCaster {
+    async fn rtt(&self) -> Option<Duration> {
+        let peers: Vec<Arc<WRTCPeer>> = self
+            .peers
+            .read()
+            .await
+            .iter()
+            .filter(|p| p.is_connected())
+            .cloned()
+            .collect();
+        let mut worst = None;
+        for peer in peers {
+            worst = worst.max(peer.rtt().await);
+        }
+        worst
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use rtc::rtp::codec::h264::H264Payloader;
+    use rtc::rtp::packetizer::{Packetizer, new_packetizer};
+    use rtc::rtp::sequence::new_fixed_sequencer;
+
+    #[test]
+    fn rtp_timestamps_follow_the_capture_clock_without_drift() {
+        // Stesso packetizer e stessa conversione di `TrackLocalStaticSample::write_sample`
+        let mut packetizer = new_packetizer(
+            1200,
+            96,
+            1,
+            Box::new(H264Payloader::default()),
+            Box::new(new_fixed_sequencer(0)),
+            VIDEO_CLOCK_RATE as u32,
+        );
+        let mut clock = VideoRtpClock::new();
+        let first = Duration::from_micros(16_667);
+        let au = Bytes::from_static(&[0, 0, 0, 1, 0x65, 0x88, 0x84]);
+
+        // Un'ora a 60 fps: ogni durata, troncata da sola, perderebbe una frazione di tick
+        let frames = 60 * 3600;
+        let mut first_ts = None;
+        let mut previous_ts = None;
+        for n in 0..=frames {
+            let pts = Timestamp::from_micros(n * 1_000_000 / 60);
+            let duration = clock.sample_duration(pts, first);
+            let samples = (duration.as_secs_f64() * VIDEO_CLOCK_RATE as f64) as u32;
+            let packets = packetizer.packetize(&au, samples).unwrap();
+            let ts = packets[0].header.timestamp;
+            let origin = *first_ts.get_or_insert(ts);
+            if let Some(previous) = previous_ts {
+                assert!(ts.wrapping_sub(previous) >= MIN_FRAME_TICKS as u32);
+            }
+            previous_ts = Some(ts);
+
+            // Il frame n porta il timestamp del frame precedente più la prima durata
+            if n > 0 {
+                let prev_pts = Timestamp::from_micros((n - 1) * 1_000_000 / 60);
+                let expected = VideoRtpClock::ticks(prev_pts) + 1500;
+                assert_eq!(ts.wrapping_sub(origin) as u64, expected, "frame {}", n);
+            }
+        }
+    }
+
+    #[test]
+    fn durations_round_trip_to_whole_ticks() {
+        for ticks in [1, 90, 1499, 1500, 1501, 3003, 90_000, 9_000_000] {
+            let duration = ticks_duration(ticks);
+            assert_eq!(
+                (duration.as_secs_f64() * VIDEO_CLOCK_RATE as f64) as u64,
+                ticks
+            );
+        }
+    }
+
+    #[test]
+    fn short_gaps_are_repaid_by_the_next_frame() {
+        let mut clock = VideoRtpClock::new();
+        let first = Duration::from_millis(16);
+        clock.sample_duration(Timestamp::from_micros(0), first);
+        // Due frame a 100 µs: il secondo avanza comunque di 1 ms
+        let short = clock.sample_duration(Timestamp::from_micros(100), first);
+        assert_eq!(short, ticks_duration(MIN_FRAME_TICKS));
+        // ...che viene restituito qui: la timeline torna sui PTS
+        let next = clock.sample_duration(Timestamp::from_micros(33_433), first);
+        let total = ticks_duration(1440) + short + next;
+        assert_eq!(
+            (total.as_secs_f64() * VIDEO_CLOCK_RATE as f64).round() as u64,
+            1440 + VideoRtpClock::ticks(Timestamp::from_micros(33_433))
+        );
+    }
+}
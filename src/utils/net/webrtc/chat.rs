@@ -0,0 +1,60 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Caratteri massimi di un messaggio, il resto viene tagliato
+pub const MAX_CHAT_LEN: usize = 500;
+/// Messaggi tenuti in memoria per sessione
+const MAX_CHAT_HISTORY: usize = 200;
+
+/// Messaggio di chat. Sul data channel viaggia solo il testo: l'autore lo
+/// mette chi lo riceve, dal peer da cui arriva.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub author: String,
+    pub text: String,
+    /// Scritto da questo lato della sessione
+    pub local: bool,
+}
+
+impl ChatMessage {
+    /// Messaggio locale, `None` se vuoto dopo il trim
+    pub fn new(author: &str, text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        Some(Self {
+            author: author.to_string(),
+            text: text.chars().take(MAX_CHAT_LEN).collect(),
+            local: true,
+        })
+    }
+
+    /// Messaggio ricevuto dal peer `author`
+    pub fn remote(author: String, text: String) -> Self {
+        Self {
+            author,
+            text,
+            local: false,
+        }
+    }
+}
+
+/// Storico della chat della sessione, condiviso tra peer e GUI
+#[derive(Debug, Clone, Default)]
+pub struct ChatLog(Arc<Mutex<VecDeque<ChatMessage>>>);
+
+impl ChatLog {
+    pub fn push(&self, mut message: ChatMessage) {
+        message.text = message.text.chars().take(MAX_CHAT_LEN).collect();
+        let mut log = self.0.lock().unwrap();
+        if log.len() == MAX_CHAT_HISTORY {
+            log.pop_front();
+        }
+        log.push_back(message);
+    }
+
+    pub fn messages(&self) -> Vec<ChatMessage> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
@@ -0,0 +1,130 @@
+use arboard::Clipboard;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Testo più lungo di così non viene né inviato né applicato
+const MAX_CLIPBOARD_LEN: usize = 64 * 1024;
+/// Intervallo di lettura della clipboard locale
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Sincronizzazione della clipboard (solo testo) con l'altro lato della
+/// sessione. Le due direzioni si abilitano separatamente, entrambe spente
+/// di default.
+#[derive(Debug, Default)]
+pub struct ClipboardSync {
+    /// Invia all'altro lato il testo copiato qui
+    share: AtomicBool,
+    /// Applica alla clipboard locale il testo ricevuto
+    accept: AtomicBool,
+    /// Ultimo testo visto o applicato: evita di rimandare indietro
+    /// quello che si è appena ricevuto
+    last: Mutex<Option<String>>,
+    /// La prossima lettura fissa solo il riferimento: quello che era già
+    /// copiato quando la condivisione è partita non viene inviato
+    primed: AtomicBool,
+}
+
+impl ClipboardSync {
+    pub fn set_sharing(&self, share: bool, accept: bool) {
+        self.share.store(share, Ordering::Relaxed);
+        self.accept.store(accept, Ordering::Relaxed);
+    }
+
+    /// Attende il prossimo testo copiato localmente da inviare.
+    ///
+    /// La clipboard viene letta solo a condivisione accesa e con un peer
+    /// collegato (`connected`); alla ripartenza la prima lettura non invia
+    /// nulla, così non parte quello che era già stato copiato.
+    pub async fn next_copy(&self, connected: impl Fn() -> bool) -> String {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            if !self.share.load(Ordering::Relaxed) || !connected() {
+                self.primed.store(false, Ordering::Relaxed);
+                continue;
+            }
+            let Some(text) = Clipboard::new().ok().and_then(|mut c| c.get_text().ok()) else {
+                continue;
+            };
+            if let Some(text) = self.detect(text) {
+                return text;
+            }
+        }
+    }
+
+    /// Testo da inviare se diverso dall'ultimo visto
+    fn detect(&self, text: String) -> Option<String> {
+        let baseline = !self.primed.swap(true, Ordering::Relaxed);
+        let mut last = self.last.lock().unwrap();
+        if last.as_deref() == Some(text.as_str()) {
+            return None;
+        }
+        *last = Some(text.clone());
+
+        if baseline || text.is_empty() {
+            return None;
+        }
+        if text.len() > MAX_CLIPBOARD_LEN {
+            log::debug!("Clipboard text not shared: {} bytes", text.len());
+            return None;
+        }
+        Some(text)
+    }
+
+    /// Testo ricevuto dall'altro lato
+    pub fn apply(&self, text: String) {
+        if !self.accept.load(Ordering::Relaxed) || text.len() > MAX_CLIPBOARD_LEN {
+            return;
+        }
+        let mut last = self.last.lock().unwrap();
+        if last.as_deref() == Some(text.as_str()) {
+            return;
+        }
+        match Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text.clone())) {
+            Ok(()) => *last = Some(text),
+            Err(e) => log::warn!("Cannot set the remote clipboard text: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primed() -> ClipboardSync {
+        let sync = ClipboardSync::default();
+        assert_eq!(sync.detect("already copied".into()), None);
+        sync
+    }
+
+    #[test]
+    fn first_read_only_sets_the_baseline() {
+        let sync = primed();
+        assert_eq!(sync.detect("already copied".into()), None);
+        assert_eq!(sync.detect("new".into()), Some("new".into()));
+    }
+
+    #[test]
+    fn unchanged_text_is_sent_once() {
+        let sync = primed();
+        assert_eq!(sync.detect("a".into()), Some("a".into()));
+        assert_eq!(sync.detect("a".into()), None);
+        assert_eq!(sync.detect("b".into()), Some("b".into()));
+        assert_eq!(sync.detect("a".into()), Some("a".into()));
+    }
+
+    #[test]
+    fn empty_and_oversized_text_is_not_sent() {
+        let sync = primed();
+        assert_eq!(sync.detect(String::new()), None);
+        assert_eq!(sync.detect("x".repeat(MAX_CLIPBOARD_LEN + 1)), None);
+    }
+
+    #[test]
+    fn restarting_sets_a_new_baseline() {
+        let sync = primed();
+        sync.primed.store(false, Ordering::Relaxed);
+        assert_eq!(sync.detect("copied while off".into()), None);
+        assert_eq!(sync.detect("after".into()), Some("after".into()));
+    }
+}
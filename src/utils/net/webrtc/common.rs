@@ -1,16 +1,23 @@
-use rtc::interceptor::Registry;
+use crate::capture::StreamProfile;
+use crate::capture::audio::pcm::{L16_MIME, PCM_CHANNELS, PCM_SAMPLE_RATE};
+use crate::pipeline::clock::ClockAnchor;
+use rtc::interceptor::{NackGeneratorBuilder, NackResponderBuilder, Registry};
 use rtc::media_stream::MediaStreamTrack;
-use rtc::peer_connection::configuration::interceptor_registry::register_default_interceptors;
+use rtc::peer_connection::configuration::interceptor_registry::{
+    configure_rtcp_reports, configure_simulcast_extension_headers, configure_twcc_receiver_only,
+};
 use rtc::peer_connection::configuration::media_engine::{
     MIME_TYPE_H264, MIME_TYPE_OPUS, MediaEngine,
 };
 use rtc::rtp_transceiver::rtp_sender::{
-    RTCRtpCodec, RTCRtpCodingParameters, RTCRtpEncodingParameters, RtpCodecKind,
+    RTCPFeedback, RTCRtpCodec, RTCRtpCodecParameters, RTCRtpCodingParameters,
+    RTCRtpEncodingParameters, RtpCodecKind, TYPE_RTCP_FB_NACK,
 };
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use local_ip_address::local_ip;
 use webrtc::media_stream::track_local::static_sample::TrackLocalStaticSample;
 use webrtc::peer_connection::{
@@ -20,20 +27,84 @@ use webrtc::peer_connection::{
 use webrtc::runtime::default_runtime;
 
 static NEXT_SSRC: AtomicU32 = AtomicU32::new(1);
+/// Payload type dinamico dell'audio L16 (audio a bassa latenza)
+const L16_PAYLOAD_TYPE: u8 = 118;
+/// Ogni quanto il receiver chiede i pacchetti mancanti: la ritrasmissione
+/// deve arrivare entro il jitter buffer più corto (40 ms, bassa latenza)
+const NACK_INTERVAL: Duration = Duration::from_millis(20);
+/// Pacchetti video tenuti dal caster per le ritrasmissioni (potenza di 2)
+const NACK_HISTORY: u16 = 2048;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct SignalMessage {
     pub sdp: Option<RTCSessionDescription>,
     pub candidate: Option<RTCIceCandidateInit>,
+    /// Resolution/fps announced by the caster together with its offer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<StreamProfile>,
+    /// Token of the caster broadcast, sent with the offer. A receiver that
+    /// reconnects to the same token keeps recording to the same file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// Caster clock time of the first sample sent on a track.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<ClockAnchor>,
+}
+
+impl SignalMessage {
+    pub fn clock(anchor: ClockAnchor) -> Self {
+        SignalMessage {
+            clock: Some(anchor),
+            ..Default::default()
+        }
+    }
 }
 
+/// `nack` announces retransmission of lost video packets in the SDP: the
+/// interceptors are always there, but only act on streams that negotiated it.
 pub async fn create_peer_connection(
     handler: Arc<dyn PeerConnectionEventHandler>,
+    pcm_audio: bool,
+    nack: bool,
 ) -> Result<Arc<dyn PeerConnection>, Box<dyn std::error::Error + Send + Sync>> {
     let mut media_engine = MediaEngine::default();
     media_engine.register_default_codecs()?;
+    // Il caster lo registra solo con l'audio a bassa latenza, il receiver sempre
+    if pcm_audio {
+        media_engine.register_codec(
+            RTCRtpCodecParameters {
+                rtp_codec: RTCRtpCodec {
+                    mime_type: L16_MIME.to_string(),
+                    clock_rate: PCM_SAMPLE_RATE,
+                    channels: PCM_CHANNELS as u16,
+                    ..Default::default()
+                },
+                payload_type: L16_PAYLOAD_TYPE,
+                ..Default::default()
+            },
+            RtpCodecKind::Audio,
+        )?;
+    }
 
-    let registry = register_default_interceptors(Registry::new(), &mut media_engine)?;
+    // PLI sempre: il receiver chiede un keyframe dopo un reset del decoder
+    let feedback = |parameter: &str| RTCPFeedback {
+        typ: TYPE_RTCP_FB_NACK.to_owned(),
+        parameter: parameter.to_owned(),
+    };
+    media_engine.register_feedback(feedback("pli"), RtpCodecKind::Video);
+    if nack {
+        media_engine.register_feedback(feedback(""), RtpCodecKind::Video);
+    }
+    let registry = Registry::new()
+        .with(
+            NackGeneratorBuilder::new()
+                .with_interval(NACK_INTERVAL)
+                .build(),
+        )
+        .with(NackResponderBuilder::new().with_size(NACK_HISTORY).build());
+    let registry = configure_rtcp_reports(registry);
+    configure_simulcast_extension_headers(&mut media_engine)?;
+    let registry = configure_twcc_receiver_only(registry, &mut media_engine)?;
 
     let config = RTCConfigurationBuilder::new()
         .with_ice_servers(vec![RTCIceServer {
@@ -80,10 +151,17 @@ pub fn create_video_track() -> Result<Arc<TrackLocalStaticSample>, Box<dyn std::
     )
 }
 
-pub fn create_audio_track() -> Result<Arc<TrackLocalStaticSample>, Box<dyn std::error::Error + Send + Sync>> {
+/// Opus, or raw L16 PCM with `low_latency`
+pub fn create_audio_track(
+    low_latency: bool,
+) -> Result<Arc<TrackLocalStaticSample>, Box<dyn std::error::Error + Send + Sync>> {
     create_track(
         RtpCodecKind::Audio,
-        MIME_TYPE_OPUS,
+        if low_latency {
+            L16_MIME
+        } else {
+            MIME_TYPE_OPUS
+        },
         48000,
         2,
         "castify-audio",
@@ -0,0 +1,91 @@
+//! Messaggi del data channel della sessione
+//!
+//! Quello che non è audio o video viaggia sul data channel aperto dal
+//! caster insieme all'offerta: è cifrato da DTLS come i media, mentre il
+//! websocket di signaling è in chiaro e serve solo a negoziare.
+
+use crate::utils::net::webrtc::annotation::AnnotationEvent;
+#[cfg(feature = "remote-control")]
+use crate::utils::remote_control::RemoteInput;
+#[cfg(feature = "remote-control")]
+use base64::Engine;
+#[cfg(feature = "remote-control")]
+use base64::engine::general_purpose::STANDARD;
+#[cfg(feature = "remote-control")]
+use hkdf::Hkdf;
+#[cfg(feature = "remote-control")]
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "remote-control")]
+use sha2::Sha256;
+
+/// Etichetta del data channel creato dal caster
+pub const DATA_CHANNEL_LABEL: &str = "castify";
+
+/// Salt HKDF per la prova d'accesso al controllo remoto
+#[cfg(feature = "remote-control")]
+const CONTROL_SALT: &[u8] = b"castify-remote-control-v1";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum DataMessage {
+    /// Primo messaggio del receiver: senza una prova valida il caster
+    /// ignora i suoi eventi di input
+    #[cfg(feature = "remote-control")]
+    Auth { proof: String },
+    /// Evento del mouse di un receiver che controlla il caster
+    #[cfg(feature = "remote-control")]
+    Input(RemoteInput),
+    /// Testo copiato da uno dei due lati, a condivisione clipboard accesa
+    Clipboard(String),
+    /// Annotazioni disegnate dal caster
+    Annotation(AnnotationEvent),
+    /// Messaggio di chat, senza autore: lo stabilisce chi lo riceve
+    Chat(String),
+}
+
+#[cfg(feature = "remote-control")]
+fn control_mac(passphrase: Option<&str>, session: &str) -> Hmac<Sha256> {
+    let hk = Hkdf::<Sha256>::new(
+        Some(CONTROL_SALT),
+        passphrase.unwrap_or_default().as_bytes(),
+    );
+    let mut key = [0u8; 32];
+    hk.expand(b"control-auth", &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    let mut mac = Hmac::<Sha256>::new_from_slice(&key).expect("HMAC accepts any key length");
+    mac.update(session.as_bytes());
+    mac
+}
+
+/// HMAC del token della sessione con la chiave derivata dalla passphrase.
+/// Senza passphrase dimostra solo di aver ricevuto l'offerta di questa sessione.
+#[cfg(feature = "remote-control")]
+pub fn control_proof(passphrase: Option<&str>, session: &str) -> String {
+    STANDARD.encode(control_mac(passphrase, session).finalize().into_bytes())
+}
+
+#[cfg(feature = "remote-control")]
+pub fn verify_control_proof(passphrase: Option<&str>, session: &str, proof: &str) -> bool {
+    STANDARD
+        .decode(proof)
+        .is_ok_and(|tag| control_mac(passphrase, session).verify_slice(&tag).is_ok())
+}
+
+#[cfg(all(test, feature = "remote-control"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proof_needs_the_same_passphrase_and_session() {
+        let proof = control_proof(Some("secret"), "abc-123");
+        assert!(verify_control_proof(Some("secret"), "abc-123", &proof));
+        assert!(!verify_control_proof(Some("other"), "abc-123", &proof));
+        assert!(!verify_control_proof(None, "abc-123", &proof));
+        assert!(!verify_control_proof(Some("secret"), "abc-124", &proof));
+        assert!(!verify_control_proof(
+            Some("secret"),
+            "abc-123",
+            "not base64!"
+        ));
+    }
+}
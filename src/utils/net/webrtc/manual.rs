@@ -1,23 +1,50 @@
+use crate::capture::StreamProfile;
 use crate::utils::string;
 use async_trait::async_trait;
 use base64::engine::GeneralPurpose;
 use base64::engine::general_purpose::PAD;
 use base64::{Engine, alphabet};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use std::error::Error;
 use webrtc::peer_connection::{RTCIceCandidateInit, RTCSessionDescription};
 
+type HmacSha256 = Hmac<Sha256>;
+
+/// Salt HKDF per la chiave di autenticazione dello scambio SDP manuale
+const AUTH_SALT: &[u8] = b"castify-manual-sdp-v1";
+
 #[async_trait]
 pub trait SDPICEExchangeWRTC: Send + Sync {
     async fn get_sdp(&self) -> String;
 
     async fn set_remote_sdp(&self, sdp: String) -> bool;
+
+    /// Passphrase condivisa: firma l'SDP locale e richiede che quello remoto
+    /// sia firmato con la stessa. `None` = scambio non autenticato.
+    fn set_passphrase(&self, passphrase: Option<String>);
+}
+
+/// Chiave HMAC derivata dalla passphrase.
+fn auth_key(passphrase: &str) -> [u8; 32] {
+    let hk = Hkdf::<Sha256>::new(Some(AUTH_SALT), passphrase.as_bytes());
+    let mut key = [0u8; 32];
+    hk.expand(b"sdp-auth", &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct SDPICEExchange {
     ice_candidates: Vec<RTCIceCandidateInit>,
     sdp: RTCSessionDescription,
+    #[serde(default)]
+    profile: Option<StreamProfile>,
+    /// HMAC-SHA256 (base64) del resto del pacchetto, presente se c'è una passphrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mac: Option<String>,
 }
 
 impl SDPICEExchange {
@@ -25,6 +52,8 @@ impl SDPICEExchange {
         SDPICEExchange {
             ice_candidates: Vec::new(),
             sdp: RTCSessionDescription::default(),
+            profile: None,
+            mac: None,
         }
     }
 
@@ -32,6 +61,8 @@ impl SDPICEExchange {
         SDPICEExchange {
             ice_candidates: Vec::new(),
             sdp,
+            profile: None,
+            mac: None,
         }
     }
 
@@ -43,21 +74,135 @@ impl SDPICEExchange {
         self.sdp.clone()
     }
 
+    pub fn set_profile(&mut self, profile: Option<StreamProfile>) {
+        self.profile = profile;
+    }
+
+    pub fn get_profile(&self) -> Option<StreamProfile> {
+        self.profile
+    }
+
     pub fn get_ice_candidates(&self) -> Vec<RTCIceCandidateInit> {
         self.ice_candidates.clone()
     }
 
-    pub fn pack(&self) -> Result<String, Box<dyn Error + Sync + Send>> {
-        let str = serde_json::to_string(&self)?;
+    /// HMAC sul JSON del pacchetto senza `mac` (chiavi ordinate: stabile
+    /// anche dopo un giro di deserializzazione).
+    fn auth_tag(&self, passphrase: &str) -> Result<HmacSha256, Box<dyn Error + Sync + Send>> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(map) = value.as_object_mut() {
+            map.remove("mac");
+        }
+        let mut mac = HmacSha256::new_from_slice(&auth_key(passphrase))?;
+        mac.update(value.to_string().as_bytes());
+        Ok(mac)
+    }
+
+    pub fn pack(&self, passphrase: Option<&str>) -> Result<String, Box<dyn Error + Sync + Send>> {
+        let str = match passphrase {
+            Some(passphrase) => {
+                let tag = self.auth_tag(passphrase)?.finalize().into_bytes();
+                let signed = SDPICEExchange {
+                    ice_candidates: self.ice_candidates.clone(),
+                    sdp: self.sdp.clone(),
+                    profile: self.profile,
+                    mac: Some(GeneralPurpose::new(&alphabet::STANDARD, PAD).encode(tag)),
+                };
+                serde_json::to_string(&signed)?
+            }
+            None => serde_json::to_string(&self)?,
+        };
         let str = string::compress_string(&str)?;
         let str = GeneralPurpose::new(&alphabet::STANDARD, PAD).encode(str);
         Ok(str)
     }
 
-    pub fn unpack(packed: String) -> Result<SDPICEExchange, Box<dyn Error + Sync + Send>> {
+    /// Decodifica il pacchetto remoto; con una passphrase locale la firma è
+    /// obbligatoria e deve corrispondere, senza passphrase non deve esserci.
+    pub fn unpack(
+        packed: String,
+        passphrase: Option<&str>,
+    ) -> Result<SDPICEExchange, Box<dyn Error + Sync + Send>> {
         let str = GeneralPurpose::new(&alphabet::STANDARD, PAD).decode(packed.trim())?;
         let str = string::decompress_string(&str)?;
         let exchanger = serde_json::from_str::<SDPICEExchange>(&str)?;
+
+        match (passphrase, &exchanger.mac) {
+            (Some(passphrase), Some(tag)) => {
+                let tag = GeneralPurpose::new(&alphabet::STANDARD, PAD).decode(tag)?;
+                exchanger
+                    .auth_tag(passphrase)?
+                    .verify_slice(&tag)
+                    .map_err(|_| "Wrong passphrase for the remote SDP")?;
+            }
+            (Some(_), None) => return Err("Remote SDP is not protected by a passphrase".into()),
+            (None, Some(_)) => return Err("Remote SDP requires a passphrase".into()),
+            (None, None) => {}
+        }
+
         Ok(exchanger)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange() -> SDPICEExchange {
+        let sdp = "v=0\r\no=- 1 1 IN IP4 0.0.0.0\r\ns=-\r\nt=0 0\r\n";
+        SDPICEExchange::new_with_spd(RTCSessionDescription::offer(sdp.into()).unwrap())
+    }
+
+    /// Modifica il JSON dentro un pacchetto lasciando la firma com'è
+    fn tamper(packed: &str, edit: impl FnOnce(&mut serde_json::Value)) -> String {
+        let engine = GeneralPurpose::new(&alphabet::STANDARD, PAD);
+        let json = string::decompress_string(&engine.decode(packed).unwrap()).unwrap();
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        edit(&mut value);
+        engine.encode(string::compress_string(&value.to_string()).unwrap())
+    }
+
+    #[test]
+    fn signed_exchange_round_trips() {
+        let packed = exchange().pack(Some("secret")).unwrap();
+        let unpacked = SDPICEExchange::unpack(packed, Some("secret")).unwrap();
+        assert_eq!(unpacked.get_sdp().sdp, exchange().get_sdp().sdp);
+
+        let packed = exchange().pack(None).unwrap();
+        assert!(SDPICEExchange::unpack(packed, None).is_ok());
+    }
+
+    #[test]
+    fn passphrase_must_match_on_both_sides() {
+        let signed = exchange().pack(Some("secret")).unwrap();
+        assert!(SDPICEExchange::unpack(signed.clone(), Some("other")).is_err());
+        assert!(SDPICEExchange::unpack(signed, None).is_err());
+
+        let unsigned = exchange().pack(None).unwrap();
+        assert!(SDPICEExchange::unpack(unsigned, Some("secret")).is_err());
+    }
+
+    #[test]
+    fn tampered_exchange_is_rejected() {
+        let packed = exchange().pack(Some("secret")).unwrap();
+        // Ricompresso senza modifiche resta valido
+        let untouched = tamper(&packed, |_| {});
+        assert!(SDPICEExchange::unpack(untouched, Some("secret")).is_ok());
+
+        let sdp = tamper(&packed, |value| {
+            value["sdp"]["sdp"] = "v=0\r\ns=attacker\r\n".into();
+        });
+        assert!(SDPICEExchange::unpack(sdp, Some("secret")).is_err());
+
+        let candidate = "candidate:1 1 udp 1 10.0.0.1 9 typ host";
+        let candidates = tamper(&packed, |value| {
+            value["ice_candidates"] = serde_json::json!([{ "candidate": candidate }]);
+        });
+        assert!(SDPICEExchange::unpack(candidates, Some("secret")).is_err());
+
+        let stripped = tamper(&packed, |value| {
+            value.as_object_mut().unwrap().remove("mac");
+        });
+        assert!(SDPICEExchange::unpack(stripped, Some("secret")).is_err());
+    }
+}
@@ -1,10 +1,17 @@
+mod annotation;
 mod caster;
+mod chat;
+mod clipboard;
 mod common;
+mod data;
 mod manual;
 mod peer;
 mod receiver;
 mod server;
 
+pub use annotation::{AnnotationEvent, RemoteStroke, StrokeKind};
+pub(crate) use caster::{VIDEO_CLOCK_RATE, VideoRtpClock};
+pub use chat::{ChatLog, ChatMessage, MAX_CHAT_LEN};
 pub use manual::SDPICEExchangeWRTC;
 pub use receiver::WebRTCReceiver;
 pub use server::WebRTCServer;
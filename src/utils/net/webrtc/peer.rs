@@ -1,24 +1,46 @@
+use crate::capture::StreamProfile;
+use crate::pipeline::clock::ClockAnchor;
+use crate::pipeline::recovery::LossRecovery;
+use crate::pipeline::simulcast::{SimulcastTier, TierSelector};
+use crate::pipeline::types::Timestamp;
+use crate::utils::net::webrtc::annotation::{AnnotationEvent, RemoteStroke};
+use crate::utils::net::webrtc::chat::{ChatLog, ChatMessage};
+use crate::utils::net::webrtc::clipboard::ClipboardSync;
 use crate::utils::net::webrtc::common::{
     SignalMessage, create_audio_track, create_peer_connection, create_video_track,
 };
+use crate::utils::net::webrtc::data::{DATA_CHANNEL_LABEL, DataMessage};
+#[cfg(feature = "remote-control")]
+use crate::utils::net::webrtc::data::{control_proof, verify_control_proof};
+#[cfg(feature = "remote-control")]
+use crate::utils::remote_control::{RemoteControl, RemoteInput};
 use crate::utils::sos::SignalOfStop;
 use async_tungstenite::WebSocketStream;
 use async_tungstenite::tokio::ConnectStream;
 use async_tungstenite::tungstenite::{Message, Utf8Bytes};
+use castbox::Arw;
 use futures_util::StreamExt;
 use iced::futures::executor::block_on;
 use once_cell::sync::Lazy;
 use rtc::media::Sample;
-use std::sync::Arc;
+use rtc::rtcp;
+use rtc::rtcp::payload_feedbacks::full_intra_request::FullIntraRequest;
+use rtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use rtc::rtcp::payload_feedbacks::receiver_estimated_maximum_bitrate::ReceiverEstimatedMaximumBitrate;
+use rtc::rtcp::receiver_report::ReceiverReport;
+use std::ops::DerefMut;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use tokio::sync::{Notify, broadcast};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, broadcast, mpsc};
+use webrtc::data_channel::{DataChannel, DataChannelEvent, RTCDataChannelState};
 use webrtc::media_stream::Track;
-use webrtc::media_stream::track_local::TrackLocal;
 use webrtc::media_stream::track_local::static_sample::TrackLocalStaticSample;
+use webrtc::media_stream::track_local::{TrackLocal, TrackLocalEvent};
 use webrtc::media_stream::track_remote::TrackRemote;
 use webrtc::peer_connection::{
     PeerConnection, PeerConnectionEventHandler, RTCIceGatheringState, RTCPeerConnectionState,
-    RTCSdpType, RTCSessionDescription,
+    RTCSdpType, RTCSessionDescription, RTCStatsReportEntry, StatsSelector,
 };
 
 static WRTC_PEER_UUID: Lazy<AtomicU32> = Lazy::new(|| AtomicU32::new(0));
@@ -26,9 +48,11 @@ static WRTC_PEER_UUID: Lazy<AtomicU32> = Lazy::new(|| AtomicU32::new(0));
 #[derive(Clone)]
 struct WRTCPeerHandler {
     online: Arc<AtomicBool>,
+    media_ready: Arc<AtomicBool>,
     ice_complete: Arc<AtomicBool>,
     ice_notify: Arc<Notify>,
     track_tx: broadcast::Sender<Arc<dyn TrackRemote>>,
+    data_channel_tx: mpsc::UnboundedSender<Arc<dyn DataChannel>>,
 }
 
 #[async_trait::async_trait]
@@ -46,11 +70,13 @@ impl PeerConnectionEventHandler for WRTCPeerHandler {
         match state {
             RTCPeerConnectionState::Connected => {
                 self.online.store(true, Ordering::Relaxed);
+                self.media_ready.store(true, Ordering::Relaxed);
             }
             RTCPeerConnectionState::Disconnected
             | RTCPeerConnectionState::Failed
             | RTCPeerConnectionState::Closed => {
                 self.online.store(false, Ordering::Relaxed);
+                self.media_ready.store(false, Ordering::Relaxed);
             }
             _ => {}
         }
@@ -59,81 +85,218 @@ impl PeerConnectionEventHandler for WRTCPeerHandler {
     async fn on_track(&self, track: Arc<dyn TrackRemote>) {
         let _ = self.track_tx.send(track);
     }
+
+    async fn on_data_channel(&self, data_channel: Arc<dyn DataChannel>) {
+        let _ = self.data_channel_tx.send(data_channel);
+    }
 }
 
 pub struct WRTCPeer {
     connection: Arc<dyn PeerConnection>,
-    video_track: Arc<TrackLocalStaticSample>,
+    /// `None` for audio-only casters: the offer carries no video m-line
+    video_track: Option<Arc<TrackLocalStaticSample>>,
     audio_track: Arc<TrackLocalStaticSample>,
     video_ssrc: u32,
     audio_ssrc: u32,
     online: Arc<AtomicBool>,
+    /// Connection established: samples written from now on reach the remote
+    media_ready: Arc<AtomicBool>,
+    /// Clock anchor of each track already announced (caster side only).
+    video_anchored: AtomicBool,
+    audio_anchored: AtomicBool,
+    /// Simulcast encoder this peer is served from (caster side only),
+    /// moved by the receiver's RTCP feedback.
+    video_tier: Arc<std::sync::Mutex<TierSelector>>,
     ice_complete: Arc<AtomicBool>,
     ice_notify: Arc<Notify>,
     track_tx: broadcast::Sender<Arc<dyn TrackRemote>>,
+    /// Stream profile announced by the remote caster (receiver side only).
+    remote_profile: std::sync::Mutex<Option<StreamProfile>>,
+    /// Broadcast token: our own on the caster, the caster's on the receiver.
+    session: std::sync::Mutex<Option<String>>,
+    /// The remote offer had no video track (receiver side only).
+    remote_audio_only: AtomicBool,
+    /// Annotations drawn by the remote caster (receiver side only).
+    remote_annotations: Arw<Vec<RemoteStroke>>,
+    /// Caster clock anchors of the remote tracks (receiver side only).
+    remote_clock: std::sync::Mutex<ClockAnchor>,
+    /// Chat history of the session, shared with the GUI (and the other peers on the caster).
+    chat: std::sync::Mutex<ChatLog>,
+    /// Author of the chat messages coming from the remote peer.
+    remote_name: std::sync::Mutex<String>,
+    /// Applies the text copied on the remote side, if accepted.
+    clipboard: std::sync::Mutex<Option<Arc<ClipboardSync>>>,
+    /// Where incoming mouse events are replayed (caster side only).
+    #[cfg(feature = "remote-control")]
+    remote_control: std::sync::Mutex<Option<Arc<RemoteControl>>>,
+    /// Passphrase shared by caster and receiver, proof of who may control the caster.
+    #[cfg(feature = "remote-control")]
+    passphrase: std::sync::Mutex<Arw<Option<String>>>,
+    /// The receiver proved it knows the passphrase (caster side only).
+    #[cfg(feature = "remote-control")]
+    control_authenticated: AtomicBool,
+    /// Messages for the data channel, sent once it is open.
+    data_tx: mpsc::UnboundedSender<DataMessage>,
+    data_rx: std::sync::Mutex<Option<mpsc::UnboundedReceiver<DataMessage>>>,
+    /// For the tasks spawned by the peer, which must not keep it alive.
+    me: Weak<WRTCPeer>,
+    /// Messages queued for the signaling websocket once negotiation is running.
+    signal_tx: mpsc::UnboundedSender<SignalMessage>,
+    signal_rx: std::sync::Mutex<Option<mpsc::UnboundedReceiver<SignalMessage>>>,
     id: u32,
     sos: SignalOfStop,
 }
 
 impl WRTCPeer {
     pub async fn new(
-        _force_idr: Arc<AtomicBool>,
+        force_idr: Arc<AtomicBool>,
+    ) -> Result<Arc<WRTCPeer>, Box<dyn std::error::Error + Send + Sync>> {
+        // Il receiver accetta l'audio PCM se il caster lo offre, e i NACK
+        Self::build(force_idr, true, false, true, true).await
+    }
+
+    /// Create a peer, optionally without the video track (audio-only casting)
+    /// and with raw PCM audio instead of Opus (low-latency audio).
+    pub async fn with_tracks(
+        force_idr: Arc<AtomicBool>,
+        video: bool,
+        low_latency_audio: bool,
+    ) -> Result<Arc<WRTCPeer>, Box<dyn std::error::Error + Send + Sync>> {
+        let nack = LossRecovery::for_webrtc() == LossRecovery::Nack;
+        Self::build(force_idr, video, low_latency_audio, low_latency_audio, nack).await
+    }
+
+    /// `accept_pcm_audio` registers the L16 codec: only then it appears in the
+    /// SDP, which is how the receiver tells the two audio formats apart.
+    /// `nack` offers retransmission of lost video packets.
+    async fn build(
+        force_idr: Arc<AtomicBool>,
+        video: bool,
+        low_latency_audio: bool,
+        accept_pcm_audio: bool,
+        nack: bool,
     ) -> Result<Arc<WRTCPeer>, Box<dyn std::error::Error + Send + Sync>> {
         let sos = SignalOfStop::new();
         let online = Arc::new(AtomicBool::new(true));
+        let media_ready = Arc::new(AtomicBool::new(false));
         let ice_complete = Arc::new(AtomicBool::new(false));
         let ice_notify = Arc::new(Notify::new());
         let (track_tx, _) = broadcast::channel(8);
+        let (data_channel_tx, mut data_channel_rx) = mpsc::unbounded_channel();
 
         let handler = Arc::new(WRTCPeerHandler {
             online: Arc::clone(&online),
+            media_ready: Arc::clone(&media_ready),
             ice_complete: Arc::clone(&ice_complete),
             ice_notify: Arc::clone(&ice_notify),
             track_tx: track_tx.clone(),
+            data_channel_tx,
         });
 
-        let connection = create_peer_connection(handler).await?;
-        let video_track = create_video_track()?;
-        let audio_track = create_audio_track()?;
+        let connection = create_peer_connection(handler, accept_pcm_audio, nack).await?;
+        let video_track = if video {
+            Some(create_video_track()?)
+        } else {
+            None
+        };
+        let audio_track = create_audio_track(low_latency_audio)?;
 
-        connection
-            .add_track(Arc::clone(&video_track) as Arc<dyn TrackLocal>)
-            .await
-            .map_err(|e| format!("WebRTC video track error: {e}"))?;
+        if let Some(video_track) = &video_track {
+            connection
+                .add_track(Arc::clone(video_track) as Arc<dyn TrackLocal>)
+                .await
+                .map_err(|e| format!("WebRTC video track error: {e}"))?;
+        }
         connection
             .add_track(Arc::clone(&audio_track) as Arc<dyn TrackLocal>)
             .await
             .map_err(|e| format!("WebRTC audio track error: {e}"))?;
 
-        let video_ssrc = *video_track
-            .ssrcs()
-            .await
-            .first()
-            .ok_or_else(|| std::io::Error::other("video track missing SSRC"))?;
+        let video_ssrc = match &video_track {
+            Some(track) => *track
+                .ssrcs()
+                .await
+                .first()
+                .ok_or_else(|| std::io::Error::other("video track missing SSRC"))?,
+            None => 0,
+        };
         let audio_ssrc = *audio_track
             .ssrcs()
             .await
             .first()
             .ok_or_else(|| std::io::Error::other("audio track missing SSRC"))?;
 
-        Ok(Arc::new(WRTCPeer {
+        let video_tier = Arc::new(std::sync::Mutex::new(TierSelector::default()));
+        if let Some(video_track) = &video_track {
+            spawn_video_feedback(
+                &sos,
+                Arc::clone(video_track),
+                video_ssrc,
+                force_idr,
+                Arc::clone(&video_tier),
+            );
+        }
+
+        let (signal_tx, signal_rx) = mpsc::unbounded_channel();
+        let (data_tx, data_rx) = mpsc::unbounded_channel();
+        let channel_sos = sos.clone();
+        let id = WRTC_PEER_UUID.fetch_add(1, Ordering::Relaxed);
+
+        let peer = Arc::new_cyclic(|me| WRTCPeer {
             connection,
             video_track,
             audio_track,
             video_ssrc,
             audio_ssrc,
             online,
+            media_ready,
+            video_anchored: AtomicBool::new(false),
+            audio_anchored: AtomicBool::new(false),
+            video_tier,
             ice_complete,
             ice_notify,
             track_tx,
-            id: WRTC_PEER_UUID.fetch_add(1, Ordering::Relaxed),
+            remote_profile: std::sync::Mutex::new(None),
+            session: std::sync::Mutex::new(None),
+            remote_audio_only: AtomicBool::new(false),
+            remote_annotations: Arw::new(Vec::new()),
+            remote_clock: std::sync::Mutex::new(ClockAnchor::default()),
+            chat: std::sync::Mutex::new(ChatLog::default()),
+            remote_name: std::sync::Mutex::new(format!("Peer {}", id)),
+            clipboard: std::sync::Mutex::new(None),
+            #[cfg(feature = "remote-control")]
+            remote_control: std::sync::Mutex::new(None),
+            #[cfg(feature = "remote-control")]
+            passphrase: std::sync::Mutex::new(Arw::new(None)),
+            #[cfg(feature = "remote-control")]
+            control_authenticated: AtomicBool::new(false),
+            data_tx,
+            data_rx: std::sync::Mutex::new(Some(data_rx)),
+            me: me.clone(),
+            signal_tx,
+            signal_rx: std::sync::Mutex::new(Some(signal_rx)),
+            id,
             sos,
-        }))
+        });
+
+        // Il data channel del receiver arriva dal caster, insieme all'offerta
+        let me = Arc::downgrade(&peer);
+        channel_sos.spawn(async move {
+            while let Some(channel) = data_channel_rx.recv().await {
+                let Some(peer) = me.upgrade() else {
+                    break;
+                };
+                peer.attach_data_channel(channel, true);
+            }
+        });
+
+        Ok(peer)
     }
 
     pub async fn disconnect(&self) {
         log::info!("Peer {} has disconnected", self.id);
         self.online.store(false, Ordering::Relaxed);
+        self.media_ready.store(false, Ordering::Relaxed);
         self.sos.cancelled();
         let _ = self.connection.close().await;
     }
@@ -151,10 +314,242 @@ impl WRTCPeer {
         self.track_tx.subscribe()
     }
 
+    pub fn remote_profile(&self) -> Option<StreamProfile> {
+        *self.remote_profile.lock().unwrap()
+    }
+
+    pub fn set_remote_profile(&self, profile: Option<StreamProfile>) {
+        if let Some(p) = profile {
+            log::info!(
+                "Peer {}: remote stream profile {}x{}@{}",
+                self.id,
+                p.width,
+                p.height,
+                p.fps
+            );
+        }
+        *self.remote_profile.lock().unwrap() = profile;
+    }
+
+    pub fn session(&self) -> Option<String> {
+        self.session.lock().unwrap().clone()
+    }
+
+    pub fn set_session(&self, session: Option<String>) {
+        *self.session.lock().unwrap() = session;
+    }
+
+    /// Whether the caster offered only audio, known once the offer is applied.
+    pub fn is_remote_audio_only(&self) -> bool {
+        self.remote_audio_only.load(Ordering::Relaxed)
+    }
+
+    pub fn remote_annotations(&self) -> Arw<Vec<RemoteStroke>> {
+        Arw::clone(&self.remote_annotations)
+    }
+
+    /// Queue an annotation delta for the remote peer, sent over the data channel.
+    pub fn send_annotation(&self, event: AnnotationEvent) {
+        let _ = self.data_tx.send(DataMessage::Annotation(event));
+    }
+
+    /// Store incoming chat messages in `log` instead of a private history.
+    pub fn set_chat_log(&self, log: ChatLog) {
+        *self.chat.lock().unwrap() = log;
+    }
+
+    /// Name the messages of the remote peer are shown with: the remote
+    /// cannot choose it.
+    pub fn set_remote_name(&self, name: String) {
+        *self.remote_name.lock().unwrap() = name;
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Queue a chat message for the remote peer, sent over the data channel.
+    pub fn send_chat(&self, text: String) {
+        let _ = self.data_tx.send(DataMessage::Chat(text));
+    }
+
+    pub fn set_clipboard_sync(&self, clipboard: Arc<ClipboardSync>) {
+        *self.clipboard.lock().unwrap() = Some(clipboard);
+    }
+
+    /// Queue clipboard text for the remote peer, sent over the data channel.
+    pub fn send_clipboard(&self, text: String) {
+        let _ = self.data_tx.send(DataMessage::Clipboard(text));
+    }
+
+    /// Replay the mouse events sent by the remote receiver through `control`.
+    #[cfg(feature = "remote-control")]
+    pub fn set_remote_control(&self, control: Arc<RemoteControl>) {
+        *self.remote_control.lock().unwrap() = Some(control);
+    }
+
+    /// Passphrase that authenticates the receiver before its input is replayed.
+    #[cfg(feature = "remote-control")]
+    pub fn set_passphrase(&self, passphrase: Arw<Option<String>>) {
+        *self.passphrase.lock().unwrap() = passphrase;
+    }
+
+    /// Queue a mouse event for the remote caster, sent over the data channel.
+    #[cfg(feature = "remote-control")]
+    pub fn send_input(&self, input: RemoteInput) {
+        let _ = self.data_tx.send(DataMessage::Input(input));
+    }
+
+    /// Serve the session data channel: queued messages go out once it is
+    /// open, incoming ones are applied to the peer. The receiver, which gets
+    /// the channel from the caster, introduces itself first.
+    fn attach_data_channel(&self, channel: Arc<dyn DataChannel>, from_remote: bool) {
+        let Some(mut data_rx) = self.data_rx.lock().unwrap().take() else {
+            log::warn!("Peer {}: ignoring an extra data channel", self.id);
+            return;
+        };
+        #[cfg(feature = "remote-control")]
+        if from_remote {
+            let passphrase = self.passphrase.lock().unwrap().as_ref().clone();
+            let session = self.session().unwrap_or_default();
+            let proof = control_proof(passphrase.as_deref(), &session);
+            let _ = self.data_tx.send(DataMessage::Auth { proof });
+        }
+        #[cfg(not(feature = "remote-control"))]
+        let _ = from_remote;
+
+        let me = self.me.clone();
+        let id = self.id;
+        self.sos.spawn(async move {
+            let mut open = matches!(channel.ready_state().await, Ok(RTCDataChannelState::Open));
+            loop {
+                tokio::select! {
+                    event = channel.poll() => match event {
+                        Some(DataChannelEvent::OnOpen) => open = true,
+                        Some(DataChannelEvent::OnMessage(message)) => {
+                            let Some(peer) = me.upgrade() else {
+                                break;
+                            };
+                            match serde_json::from_slice::<DataMessage>(&message.data) {
+                                Ok(message) => peer.handle_data(message),
+                                Err(e) => {
+                                    log::debug!("Peer {}: bad data channel message: {}", id, e)
+                                }
+                            }
+                        }
+                        Some(DataChannelEvent::OnClose) | None => break,
+                        Some(_) => {}
+                    },
+                    Some(outgoing) = data_rx.recv(), if open => {
+                        let Ok(text) = serde_json::to_string(&outgoing) else {
+                            continue;
+                        };
+                        if let Err(e) = channel.send_text(&text).await {
+                            log::warn!("Peer {}: data channel send failed: {}", id, e);
+                        }
+                    }
+                }
+            }
+            log::info!("Peer {}: data channel closed", id);
+        });
+    }
+
+    fn handle_data(&self, message: DataMessage) {
+        match message {
+            #[cfg(feature = "remote-control")]
+            DataMessage::Auth { proof } => {
+                let passphrase = self.passphrase.lock().unwrap().as_ref().clone();
+                let session = self.session().unwrap_or_default();
+                let valid = verify_control_proof(passphrase.as_deref(), &session, &proof);
+                if !valid {
+                    log::warn!("Peer {}: wrong remote control passphrase", self.id);
+                }
+                self.control_authenticated.store(valid, Ordering::Relaxed);
+            }
+            #[cfg(feature = "remote-control")]
+            DataMessage::Input(input) => {
+                if !self.control_authenticated.load(Ordering::Relaxed) {
+                    return;
+                }
+                if let Some(control) = self.remote_control.lock().unwrap().as_ref() {
+                    control.handle(input);
+                }
+            }
+            DataMessage::Clipboard(text) => {
+                if let Some(clipboard) = self.clipboard.lock().unwrap().as_ref() {
+                    clipboard.apply(text);
+                }
+            }
+            DataMessage::Annotation(event) => {
+                event.apply(self.remote_annotations.as_mut().deref_mut());
+            }
+            DataMessage::Chat(text) => {
+                let author = self.remote_name.lock().unwrap().clone();
+                self.chat
+                    .lock()
+                    .unwrap()
+                    .push(ChatMessage::remote(author, text));
+            }
+        }
+    }
+
+    /// Clock anchors announced so far by the caster.
+    pub fn remote_clock(&self) -> ClockAnchor {
+        *self.remote_clock.lock().unwrap()
+    }
+
+    /// Announce the caster clock time of the first sample that reaches the
+    /// remote on a track. Samples written before the connection is up are
+    /// lost, so the anchor waits for `media_ready`.
+    fn announce_clock(&self, anchored: &AtomicBool, anchor: ClockAnchor) {
+        if !self.media_ready.load(Ordering::Relaxed) || anchored.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        log::debug!("Peer {}: clock anchor {:?}", self.id, anchor);
+        let _ = self.signal_tx.send(SignalMessage::clock(anchor));
+    }
+
     pub fn is_online(&self) -> bool {
         self.online.load(Ordering::Relaxed)
     }
 
+    /// Connection established and not yet dropped
+    pub fn is_connected(&self) -> bool {
+        self.media_ready.load(Ordering::Relaxed)
+    }
+
+    /// Round-trip time of the nominated ICE candidate pair, once measured.
+    pub async fn rtt(&self) -> Option<Duration> {
+        let report = self
+            .connection
+            .get_stats(Instant::now(), StatsSelector::None)
+            .await;
+        report.iter().find_map(|entry| match entry {
+            RTCStatsReportEntry::IceCandidatePair(pair)
+                if pair.nominated && pair.current_round_trip_time > 0.0 =>
+            {
+                Some(Duration::from_secs_f64(pair.current_round_trip_time))
+            }
+            _ => None,
+        })
+    }
+
+    /// Whether a video frame from the `tier` encoder goes to this peer.
+    pub fn accepts_video_tier(&self, tier: SimulcastTier, is_keyframe: bool) -> bool {
+        self.video_tier.lock().unwrap().accepts(tier, is_keyframe)
+    }
+
+    /// Simulcast encoder this peer is currently served from.
+    pub fn video_tier(&self) -> SimulcastTier {
+        self.video_tier.lock().unwrap().tier()
+    }
+
+    /// Whether the `tier` encoder owes this peer an IDR after a tier switch
+    /// (true once per switch).
+    pub fn take_tier_keyframe_request(&self, tier: SimulcastTier) -> bool {
+        self.video_tier.lock().unwrap().take_keyframe_request(tier)
+    }
+
     pub async fn wait_ice(&self) {
         if self.ice_complete.load(Ordering::Relaxed) {
             return;
@@ -172,6 +567,14 @@ impl WRTCPeer {
         &self,
         wait: bool,
     ) -> Result<RTCSessionDescription, Box<dyn std::error::Error + Send + Sync>> {
+        // Il data channel va creato prima dell'offerta, che così lo annuncia
+        if self.data_rx.lock().unwrap().is_some() {
+            let channel = self
+                .connection
+                .create_data_channel(DATA_CHANNEL_LABEL, None)
+                .await?;
+            self.attach_data_channel(channel, false);
+        }
         self.ice_complete.store(false, Ordering::Relaxed);
         let offer = self.connection.create_offer(None).await?;
         self.connection.set_local_description(offer.clone()).await?;
@@ -189,6 +592,11 @@ impl WRTCPeer {
         offer: RTCSessionDescription,
         wait: bool,
     ) -> Result<RTCSessionDescription, Box<dyn std::error::Error + Send + Sync>> {
+        let audio_only = !offer.sdp.contains("m=video");
+        if audio_only {
+            log::info!("Peer {}: remote offer is audio-only", self.id);
+        }
+        self.remote_audio_only.store(audio_only, Ordering::Relaxed);
         self.set_remote_sdp(offer).await?;
         self.ice_complete.store(false, Ordering::Relaxed);
         let answer = self.connection.create_answer(None).await?;
@@ -210,21 +618,30 @@ impl WRTCPeer {
         Ok(())
     }
 
+    /// Write a video sample captured at `pts` on the caster `MediaClock`.
     pub async fn send_video_sample(
         &self,
         sample: &Sample,
+        pts: Timestamp,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.video_track
+        let Some(video_track) = &self.video_track else {
+            return Err("Peer has no video track (audio-only)".into());
+        };
+        self.announce_clock(&self.video_anchored, ClockAnchor::video(pts));
+        video_track
             .sample_writer(self.video_ssrc)
             .write_sample(sample)
             .await?;
         Ok(())
     }
 
+    /// Write an audio sample captured at `pts` on the caster `MediaClock`.
     pub async fn send_audio_sample(
         &self,
         sample: &Sample,
+        pts: Timestamp,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.announce_clock(&self.audio_anchored, ClockAnchor::audio(pts));
         self.audio_track
             .sample_writer(self.audio_ssrc)
             .write_sample(sample)
@@ -236,6 +653,7 @@ impl WRTCPeer {
         self: Arc<Self>,
         ws_stream: WebSocketStream<ConnectStream>,
         init_offer: bool,
+        profile: Option<StreamProfile>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
@@ -243,7 +661,9 @@ impl WRTCPeer {
             let offer = self.create_offer(true).await?;
             let offer_message = SignalMessage {
                 sdp: Some(offer),
-                candidate: None,
+                profile,
+                session: self.session(),
+                ..Default::default()
             };
             ws_sender
                 .send(Message::Text(Utf8Bytes::from(serde_json::to_string(
@@ -252,8 +672,37 @@ impl WRTCPeer {
                 .await?;
         }
 
-        while let Some(Ok(msg)) = ws_receiver.next().await {
+        let mut signal_rx = self
+            .signal_rx
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or("Peer signaling already running")?;
+
+        loop {
+            let msg = tokio::select! {
+                msg = ws_receiver.next() => match msg {
+                    Some(Ok(msg)) => msg,
+                    _ => break,
+                },
+                Some(outgoing) = signal_rx.recv() => {
+                    ws_sender
+                        .send(Message::Text(Utf8Bytes::from(serde_json::to_string(&outgoing)?)))
+                        .await?;
+                    continue;
+                }
+            };
+
             if let Ok(signal) = serde_json::from_str::<SignalMessage>(&msg.to_string()) {
+                if signal.profile.is_some() {
+                    self.set_remote_profile(signal.profile);
+                }
+                if signal.session.is_some() {
+                    self.set_session(signal.session);
+                }
+                if let Some(anchor) = signal.clock {
+                    self.remote_clock.lock().unwrap().merge(anchor);
+                }
                 if let Some(sdp) = signal.sdp {
                     match sdp.sdp_type {
                         RTCSdpType::Offer => {
@@ -264,7 +713,7 @@ impl WRTCPeer {
                                 .send(Message::Text(Utf8Bytes::from(
                                     serde_json::to_string(&SignalMessage {
                                         sdp: Some(answer),
-                                        candidate: None,
+                                        ..Default::default()
                                     })?,
                                 )))
                                 .await?;
@@ -285,6 +734,58 @@ impl WRTCPeer {
     }
 }
 
+/// Keyframe requests (PLI/FIR) sent by the receiver about our video track
+/// raise the encoder's `force_idr`; its receiver reports and bandwidth
+/// estimates pick the simulcast tier.
+fn spawn_video_feedback(
+    sos: &SignalOfStop,
+    track: Arc<TrackLocalStaticSample>,
+    ssrc: u32,
+    force_idr: Arc<AtomicBool>,
+    tier: Arc<std::sync::Mutex<TierSelector>>,
+) {
+    sos.spawn(async move {
+        while let Some(TrackLocalEvent::OnRtcpPacket(packets)) = track.poll().await {
+            if packets
+                .iter()
+                .any(|packet| is_keyframe_request(packet.as_ref()))
+            {
+                log::debug!("Keyframe requested by the receiver");
+                force_idr.store(true, Ordering::Relaxed);
+            }
+
+            let (mut fraction_lost, mut estimate_bps) = (None, None);
+            for packet in &packets {
+                let packet = packet.as_any();
+                if let Some(rr) = packet.downcast_ref::<ReceiverReport>() {
+                    fraction_lost = rr
+                        .reports
+                        .iter()
+                        .find(|report| report.ssrc == ssrc)
+                        .map(|report| report.fraction_lost)
+                        .or(fraction_lost);
+                } else if let Some(remb) = packet.downcast_ref::<ReceiverEstimatedMaximumBitrate>()
+                {
+                    estimate_bps = Some(remb.bitrate);
+                }
+            }
+            if let Some(next) = tier.lock().unwrap().report(fraction_lost, estimate_bps) {
+                log::info!(
+                    "Simulcast tier {:?} (loss {:?}/256, estimate {:?} bps)",
+                    next,
+                    fraction_lost,
+                    estimate_bps
+                );
+            }
+        }
+    });
+}
+
+fn is_keyframe_request(packet: &dyn rtcp::Packet) -> bool {
+    let packet = packet.as_any();
+    packet.is::<PictureLossIndication>() || packet.is::<FullIntraRequest>()
+}
+
 impl Drop for WRTCPeer {
     fn drop(&mut self) {
         block_on(async move {
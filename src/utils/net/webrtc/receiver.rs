@@ -1,3 +1,12 @@
+use crate::capture::StreamProfile;
+use crate::capture::audio::pcm::L16_MIME;
+use crate::pipeline::clock::ClockAnchor;
+use crate::pipeline::stats_log::RttSource;
+use crate::utils::net::webrtc::annotation::RemoteStroke;
+use crate::utils::net::webrtc::chat::{ChatLog, ChatMessage};
+use crate::utils::net::webrtc::clipboard::ClipboardSync;
+#[cfg(feature = "remote-control")]
+use crate::utils::remote_control::RemoteInput;
 use crate::utils::net::webrtc::manual::{SDPICEExchange, SDPICEExchangeWRTC};
 use crate::utils::net::webrtc::peer::WRTCPeer;
 use crate::utils::sos::SignalOfStop;
@@ -8,10 +17,13 @@ use async_tungstenite::tokio::{ConnectStream, connect_async};
 use async_tungstenite::tungstenite::Error;
 use async_tungstenite::tungstenite::handshake::client::Response;
 use castbox::Arw;
+use rtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
 use rtc::rtp_transceiver::rtp_sender::RtpCodecKind;
 use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
 use webrtc::media_stream::track_remote::{TrackRemote, TrackRemoteEvent};
 
 /// Type alias for video RTP packet channel: (payload, marker, sequence_number, timestamp)
@@ -27,6 +39,16 @@ pub struct WebRTCReceiver {
     video_tx: Arw<Option<VideoPacketSender>>,
     /// Pre-registered audio channel (set before connection)
     audio_tx: Arw<Option<AudioPacketSender>>,
+    /// Video track of the caster, target of the keyframe requests
+    video_track: Arw<Option<Arc<dyn TrackRemote>>>,
+    /// The audio track negotiated raw L16 PCM instead of Opus
+    low_latency_audio: Arc<AtomicBool>,
+    /// Passphrase che autentica lo scambio SDP manuale
+    passphrase: Arw<Option<String>>,
+    /// Chat with the caster, kept for the whole session
+    chat: ChatLog,
+    /// Clipboard shared with the caster, both directions off by default
+    clipboard: Arc<ClipboardSync>,
 }
 
 impl Default for WebRTCReceiver {
@@ -43,6 +65,11 @@ impl WebRTCReceiver {
             manual_handler: Arw::new(None),
             video_tx: Arw::new(None),
             audio_tx: Arw::new(None),
+            video_track: Arw::new(None),
+            low_latency_audio: Arc::new(AtomicBool::new(false)),
+            passphrase: Arw::new(None),
+            chat: ChatLog::default(),
+            clipboard: Arc::new(ClipboardSync::default()),
         }
     }
 
@@ -50,9 +77,25 @@ impl WebRTCReceiver {
         if self.peer.as_ref().is_none() {
             let dummy_idr = Arc::new(AtomicBool::new(false));
             let peer = WRTCPeer::new(dummy_idr).await.unwrap();
+            peer.set_chat_log(self.chat.clone());
+            peer.set_remote_name(String::from("Caster"));
+            peer.set_clipboard_sync(Arc::clone(&self.clipboard));
+            #[cfg(feature = "remote-control")]
+            peer.set_passphrase(Arw::clone(&self.passphrase));
+
+            let clipboard = Arc::clone(&self.clipboard);
+            let clipboard_peer = Arc::clone(&peer);
+            self.sos.spawn(async move {
+                loop {
+                    let text = clipboard.next_copy(|| clipboard_peer.is_connected()).await;
+                    clipboard_peer.send_clipboard(text);
+                }
+            });
 
             let video_tx = Arw::clone(&self.video_tx);
             let audio_tx = Arw::clone(&self.audio_tx);
+            let video_track = Arw::clone(&self.video_track);
+            let low_latency_audio = Arc::clone(&self.low_latency_audio);
             let sos = self.sos.clone();
             let mut track_rx = peer.subscribe_tracks();
 
@@ -65,7 +108,8 @@ impl WebRTCReceiver {
                     match track.kind().await {
                         RtpCodecKind::Audio => {
                             if let Some(audio_tx) = audio_tx_opt {
-                                spawn_audio_track_reader(sos, track, audio_tx);
+                                let pcm = Arc::clone(&low_latency_audio);
+                                spawn_audio_track_reader(sos, track, audio_tx, pcm);
                             } else {
                                 log::warn!(
                                     "Audio track received but no audio channel registered"
@@ -73,6 +117,7 @@ impl WebRTCReceiver {
                             }
                         }
                         RtpCodecKind::Video => {
+                            video_track.as_mut().replace(Arc::clone(&track));
                             if let Some(video_tx) = video_tx_opt {
                                 spawn_video_track_reader(sos, track, video_tx);
                             } else {
@@ -93,6 +138,8 @@ impl WebRTCReceiver {
         self.peer.as_ref().as_ref().unwrap().clone()
     }
 
+    /// Open the signaling websocket and negotiate with the caster. Returns
+    /// once the peer connection is up, or with the reason it is not.
     pub async fn connect(
         &self,
         ws_server_url: &str,
@@ -115,11 +162,38 @@ impl WebRTCReceiver {
         let (ws_stream, _) = conn.unwrap();
         let peer = self.get_lazy_peer().await;
 
+        // La negoziazione resta aperta per tutta la sessione (ICE, chat,
+        // annotazioni): qui si aspetta solo che la connessione salga
+        let (done_tx, mut done_rx) = oneshot::channel();
+        let negotiating = Arc::clone(&peer);
         self.sos.spawn(async move {
-            let _ = peer.negotiate(ws_stream, false).await;
+            let result = negotiating.negotiate(ws_stream, false, None).await;
+            let _ = done_tx.send(result.map_err(|e| e.to_string()));
         });
 
-        Ok(())
+        let mut poll = tokio::time::interval(Duration::from_millis(100));
+        loop {
+            tokio::select! {
+                result = &mut done_rx => {
+                    return Err(match result {
+                        Ok(Err(e)) => format!("Negotiation with the caster failed: {e}"),
+                        _ => String::from("The caster closed the connection during negotiation"),
+                    }
+                    .into());
+                }
+                _ = poll.tick() => {
+                    if peer.is_connected() {
+                        return Ok(());
+                    }
+                    if !peer.is_online() {
+                        return Err("The connection to the caster failed".into());
+                    }
+                    if self.sos.cancelled() {
+                        return Err(Error::ConnectionClosed.into());
+                    }
+                }
+            }
+        }
     }
 
     pub async fn receive_video(&self, video_tx: VideoPacketSender, audio_tx: AudioPacketSender) {
@@ -129,6 +203,91 @@ impl WebRTCReceiver {
         log::info!("WebRTCReceiver: channel registration complete");
     }
 
+    /// Ask the caster for a keyframe (RTCP PLI), once its video track arrived.
+    pub async fn request_keyframe(&self) {
+        let Some(track) = self.video_track.as_ref().clone() else {
+            return;
+        };
+        let Some(&media_ssrc) = track.ssrcs().await.first() else {
+            return;
+        };
+        let pli = PictureLossIndication {
+            sender_ssrc: 0,
+            media_ssrc,
+        };
+        if let Err(e) = track.write_rtcp(vec![Box::new(pli)]).await {
+            log::warn!("Keyframe request to the caster failed: {}", e);
+        }
+    }
+
+    /// Stream profile announced by the caster, once negotiation has completed.
+    pub fn remote_profile(&self) -> Option<StreamProfile> {
+        self.peer
+            .as_ref()
+            .as_ref()
+            .and_then(|peer| peer.remote_profile())
+    }
+
+    /// Token of the caster broadcast, once negotiation has completed.
+    pub fn remote_session(&self) -> Option<String> {
+        self.peer.as_ref().as_ref().and_then(|peer| peer.session())
+    }
+
+    /// Annotations drawn by the caster, once the peer exists.
+    pub fn remote_annotations(&self) -> Option<Arw<Vec<RemoteStroke>>> {
+        self.peer
+            .as_ref()
+            .as_ref()
+            .map(|peer| peer.remote_annotations())
+    }
+
+    pub fn chat_log(&self) -> ChatLog {
+        self.chat.clone()
+    }
+
+    /// Send a chat message to the caster, once the peer exists.
+    pub fn send_chat(&self, message: ChatMessage) {
+        if let Some(peer) = self.peer.as_ref().as_ref() {
+            peer.send_chat(message.text.clone());
+            self.chat.push(message);
+        }
+    }
+
+    /// Send a mouse event to the caster, once the peer exists.
+    #[cfg(feature = "remote-control")]
+    pub fn send_input(&self, input: RemoteInput) {
+        if let Some(peer) = self.peer.as_ref().as_ref() {
+            peer.send_input(input);
+        }
+    }
+
+    pub fn set_clipboard_sharing(&self, share: bool, accept: bool) {
+        self.clipboard.set_sharing(share, accept);
+    }
+
+    /// Caster clock anchors of the audio/video tracks announced so far.
+    pub fn remote_clock(&self) -> ClockAnchor {
+        self.peer
+            .as_ref()
+            .as_ref()
+            .map(|peer| peer.remote_clock())
+            .unwrap_or_default()
+    }
+
+    /// True when the caster negotiated an audio-only session.
+    pub fn is_audio_only(&self) -> bool {
+        self.peer
+            .as_ref()
+            .as_ref()
+            .is_some_and(|peer| peer.is_remote_audio_only())
+    }
+
+    /// True when the caster sends raw PCM audio (low-latency audio), known
+    /// from the codec of the audio track before its first packet is queued.
+    pub fn is_low_latency_audio(&self) -> bool {
+        self.low_latency_audio.load(Ordering::Relaxed)
+    }
+
     pub async fn is_connected(&self) -> bool {
         self.get_lazy_peer().await.is_online()
     }
@@ -139,6 +298,14 @@ impl WebRTCReceiver {
     }
 }
 
+#[async_trait]
+impl RttSource for WebRTCReceiver {
+    async fn rtt(&self) -> Option<Duration> {
+        let peer = self.peer.as_ref().as_ref().cloned()?;
+        peer.rtt().await
+    }
+}
+
 #[async_trait]
 impl SDPICEExchangeWRTC for WebRTCReceiver {
     async fn get_sdp(&self) -> String {
@@ -150,16 +317,22 @@ impl SDPICEExchangeWRTC for WebRTCReceiver {
             .as_ref()
             .as_ref()
             .unwrap()
-            .pack()
+            .pack(self.passphrase.as_ref().as_deref())
             .unwrap()
     }
 
     async fn set_remote_sdp(&self, remote_sdp: String) -> bool {
-        let Ok(exchanger_offer) = SDPICEExchange::unpack(remote_sdp) else {
-            return false;
+        let passphrase = self.passphrase.as_ref().clone();
+        let exchanger_offer = match SDPICEExchange::unpack(remote_sdp, passphrase.as_deref()) {
+            Ok(exchanger) => exchanger,
+            Err(e) => {
+                log::warn!("Manual SDP rejected: {}", e);
+                return false;
+            }
         };
 
         let peer = self.get_lazy_peer().await;
+        peer.set_remote_profile(exchanger_offer.get_profile());
         self.manual_handler.as_mut().replace(SDPICEExchange::new());
 
         let res = peer
@@ -179,20 +352,36 @@ impl SDPICEExchangeWRTC for WebRTCReceiver {
 
         res
     }
+
+    fn set_passphrase(&self, passphrase: Option<String>) {
+        *self.passphrase.as_mut() = passphrase;
+    }
 }
 
 fn spawn_audio_track_reader(
     sos: SignalOfStop,
     track: Arc<dyn TrackRemote>,
     audio_tx: AudioPacketSender,
+    low_latency_audio: Arc<AtomicBool>,
 ) {
     sos.spawn(async move {
+        let mut codec_known = false;
         while let Some(event) = track.poll().await {
             if let TrackRemoteEvent::OnRtpPacket(packet) = event {
                 let payload = packet.payload.to_vec();
                 if payload.is_empty() {
                     continue;
                 }
+                // Formato negoziato per questa SSRC, letto una volta sola
+                if !codec_known {
+                    codec_known = true;
+                    let codec = track.codec(packet.header.ssrc).await;
+                    let pcm = codec.is_some_and(|c| c.mime_type.eq_ignore_ascii_case(L16_MIME));
+                    if pcm {
+                        log::info!("Caster sends raw PCM audio");
+                    }
+                    low_latency_audio.store(pcm, Ordering::Relaxed);
+                }
                 let timestamp = packet.header.timestamp;
                 if try_send(&audio_tx, (payload, timestamp)).is_closed() {
                     log::error!("Audio channel closed");
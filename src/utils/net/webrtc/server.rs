@@ -1,13 +1,26 @@
 use crate::assets::CAST_SERVICE_PORT;
+use crate::capture::StreamProfile;
+use crate::utils::net::webhook::{WebhookEvent, Webhooks};
+use crate::utils::net::webrtc::annotation::{AnnotationEvent, RemoteStroke};
 use crate::utils::net::webrtc::caster::WebRTCCaster;
+use crate::utils::net::webrtc::chat::{ChatLog, ChatMessage};
+use crate::utils::net::webrtc::clipboard::ClipboardSync;
 use crate::utils::net::webrtc::manual::{SDPICEExchange, SDPICEExchangeWRTC};
-use crate::utils::net::webrtc::peer::WRTCPeer;
+#[cfg(feature = "remote-control")]
+use crate::utils::remote_control::RemoteControl;
 use crate::utils::sos::SignalOfStop;
 use async_trait::async_trait;
 use async_tungstenite::tokio::accept_async;
+use castbox::Arw;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
 use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// How often connected receivers are counted for the client webhooks.
+/// Receivers that join within the same interval produce a single event.
+const CLIENT_POLL: Duration = Duration::from_secs(1);
 
 pub struct WebRTCServer {
     sos: SignalOfStop,
@@ -16,16 +29,56 @@ pub struct WebRTCServer {
     /// frame will be an IDR keyframe. Initialized as a no-op flag;
     /// replaced with the encoder's actual flag via `set_force_idr()`.
     force_idr: std::sync::Mutex<Arc<AtomicBool>>,
+    /// Resolved stream profile announced to peers during negotiation.
+    profile: std::sync::Mutex<Option<StreamProfile>>,
+    /// Current annotations, replayed to peers that connect late.
+    annotations: std::sync::Mutex<Vec<RemoteStroke>>,
+    /// Annotation deltas are forwarded by a single task to keep them ordered.
+    annotation_tx: mpsc::UnboundedSender<AnnotationEvent>,
+    annotation_rx: std::sync::Mutex<Option<mpsc::UnboundedReceiver<AnnotationEvent>>>,
+    /// Chat of the session: messages from every receiver and from the caster.
+    chat: ChatLog,
+    chat_tx: mpsc::UnboundedSender<ChatMessage>,
+    chat_rx: std::sync::Mutex<Option<mpsc::UnboundedReceiver<ChatMessage>>>,
+    /// Clipboard shared with the receivers, both directions off by default.
+    clipboard: Arc<ClipboardSync>,
+    /// Mouse events of the receivers, replayed only once the caster allows it.
+    #[cfg(feature = "remote-control")]
+    remote_control: Arc<RemoteControl>,
+    /// Passphrase che autentica lo scambio SDP manuale e il controllo remoto
+    passphrase: Arw<Option<String>>,
+    /// HTTP notifications of stream and client events.
+    webhooks: Webhooks,
+    /// Receivers connected at the last count.
+    clients: Arc<AtomicUsize>,
+    /// Token of this broadcast, sent to every receiver with the offer.
+    session: String,
 }
 
 impl WebRTCServer {
     pub fn new() -> Arc<WebRTCServer> {
         let sos = SignalOfStop::new();
+        let (annotation_tx, annotation_rx) = mpsc::unbounded_channel();
+        let (chat_tx, chat_rx) = mpsc::unbounded_channel();
 
         let server = WebRTCServer {
             sos: sos.clone(),
             caster: Arc::new(WebRTCCaster::new()),
             force_idr: std::sync::Mutex::new(Arc::new(AtomicBool::new(false))),
+            profile: std::sync::Mutex::new(None),
+            annotations: std::sync::Mutex::new(Vec::new()),
+            annotation_tx,
+            annotation_rx: std::sync::Mutex::new(Some(annotation_rx)),
+            chat: ChatLog::default(),
+            chat_tx,
+            chat_rx: std::sync::Mutex::new(Some(chat_rx)),
+            clipboard: Arc::new(ClipboardSync::default()),
+            #[cfg(feature = "remote-control")]
+            remote_control: Arc::new(RemoteControl::default()),
+            passphrase: Arw::new(None),
+            webhooks: Webhooks::default(),
+            clients: Arc::new(AtomicUsize::new(0)),
+            session: new_session_token(),
         };
 
         Arc::new(server)
@@ -38,6 +91,49 @@ impl WebRTCServer {
         self.caster.set_force_idr(flag);
     }
 
+    /// Set the profile (actual encoder size + fps) sent to receivers with the offer.
+    pub fn set_stream_profile(&self, profile: StreamProfile) {
+        *self.profile.lock().unwrap() = Some(profile);
+    }
+
+    pub fn stream_profile(&self) -> Option<StreamProfile> {
+        *self.profile.lock().unwrap()
+    }
+
+    /// Send an annotation delta to all receivers.
+    pub fn send_annotation(&self, event: AnnotationEvent) {
+        event.clone().apply(&mut self.annotations.lock().unwrap());
+        let _ = self.annotation_tx.send(event);
+    }
+
+    pub fn chat_log(&self) -> ChatLog {
+        self.chat.clone()
+    }
+
+    /// Send a chat message to all receivers.
+    pub fn send_chat(&self, message: ChatMessage) {
+        self.chat.push(message.clone());
+        let _ = self.chat_tx.send(message);
+    }
+
+    #[cfg(feature = "remote-control")]
+    pub fn remote_control(&self) -> Arc<RemoteControl> {
+        Arc::clone(&self.remote_control)
+    }
+
+    pub fn set_clipboard_sharing(&self, share: bool, accept: bool) {
+        self.clipboard.set_sharing(share, accept);
+    }
+
+    pub fn webhooks(&self) -> &Webhooks {
+        &self.webhooks
+    }
+
+    /// Receivers currently connected, as sent with the webhooks.
+    pub fn connected_clients(&self) -> usize {
+        self.clients.load(Ordering::Relaxed)
+    }
+
     fn trigger_idr(&self) {
         self.force_idr
             .lock()
@@ -49,26 +145,89 @@ impl WebRTCServer {
     pub fn run(self: Arc<Self>) {
         let self_clone = Arc::clone(&self);
 
+        if let Some(mut annotation_rx) = self.annotation_rx.lock().unwrap().take() {
+            let caster = self.get_handler();
+            self.sos.spawn(async move {
+                while let Some(event) = annotation_rx.recv().await {
+                    caster.broadcast_annotation(&event).await;
+                }
+            });
+        }
+
+        if let Some(mut chat_rx) = self.chat_rx.lock().unwrap().take() {
+            let caster = self.get_handler();
+            self.sos.spawn(async move {
+                while let Some(message) = chat_rx.recv().await {
+                    caster.broadcast_chat(&message).await;
+                }
+            });
+        }
+
+        let caster = self.get_handler();
+        let webhooks = self.webhooks.clone();
+        let clients = Arc::clone(&self.clients);
+        self.sos.spawn(async move {
+            let mut interval = tokio::time::interval(CLIENT_POLL);
+            loop {
+                interval.tick().await;
+                let connected = caster.connected_peers().await;
+                let previous = clients.swap(connected, Ordering::Relaxed);
+                if connected > previous {
+                    webhooks.fire(WebhookEvent::ClientConnect, connected);
+                } else if connected < previous {
+                    webhooks.fire(WebhookEvent::ClientDisconnect, connected);
+                }
+            }
+        });
+
+        let clipboard = Arc::clone(&self.clipboard);
+        let caster = self.get_handler();
+        let clients = Arc::clone(&self.clients);
+        self.sos.spawn(async move {
+            loop {
+                let text = clipboard
+                    .next_copy(|| clients.load(Ordering::Relaxed) > 0)
+                    .await;
+                caster.broadcast_clipboard(&text).await;
+            }
+        });
+
         self.sos.spawn(async move {
             if let Ok(listener) =
                 TcpListener::bind(format!("0.0.0.0:{}", CAST_SERVICE_PORT).to_string()).await
             {
-                println!("Server listener on: {:?}", listener);
+                log::info!("Server listener on: {:?}", listener);
 
                 while let Ok((stream, _)) = listener.accept().await {
-                    println!("Incoming connection: {:?}", stream);
+                    log::info!("Incoming connection: {:?}", stream);
                     let self_clone2 = Arc::clone(&self_clone);
                     // launch peer related operations
                     self_clone.sos.spawn(async move {
                         if let Ok(ws_stream) = accept_async(stream).await {
                             let force_idr = self_clone2.force_idr.lock().unwrap().clone();
-                            if let Ok(peer) = WRTCPeer::new(force_idr).await {
+                            if let Ok(peer) = self_clone2.caster.create_peer(force_idr).await {
+                                peer.set_chat_log(self_clone2.chat_log());
+                                peer.set_remote_name(format!("Receiver {}", peer.id()));
+                                peer.set_clipboard_sync(Arc::clone(&self_clone2.clipboard));
+                                #[cfg(feature = "remote-control")]
+                                peer.set_remote_control(self_clone2.remote_control());
+                                #[cfg(feature = "remote-control")]
+                                peer.set_passphrase(Arw::clone(&self_clone2.passphrase));
+                                peer.set_session(Some(self_clone2.session.clone()));
                                 self_clone2.caster.push(Arc::clone(&peer)).await;
+                                // Replay the current drawing, delivered once the data channel opens
+                                let annotations = self_clone2.annotations.lock().unwrap().clone();
+                                if !annotations.is_empty() {
+                                    peer.send_annotation(AnnotationEvent::Sync(annotations));
+                                }
                                 // Force an IDR frame so the new receiver gets video immediately
                                 self_clone2.trigger_idr();
-                                if let Err(e) = Arc::clone(&peer).negotiate(ws_stream, true).await {
+                                let profile = self_clone2.stream_profile();
+                                if let Err(e) =
+                                    Arc::clone(&peer).negotiate(ws_stream, true, profile).await
+                                {
                                     peer.disconnect().await;
-                                    eprintln!("Error handling signaling: {}", e);
+                                    log::error!("Error handling signaling: {}", e);
                                 }
                             }
                         }
@@ -88,21 +247,49 @@ impl WebRTCServer {
     }
 }
 
+/// Unique enough to tell two broadcasts apart, even from the same machine.
+fn new_session_token() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    format!("{:x}-{:x}", nanos, std::process::id())
+}
+
 #[async_trait]
 impl SDPICEExchangeWRTC for WebRTCServer {
     async fn get_sdp(&self) -> String {
         let peer = self.get_handler().get_manual_connection().await;
+        peer.set_chat_log(self.chat_log());
+        peer.set_remote_name(format!("Receiver {}", peer.id()));
+        peer.set_clipboard_sync(Arc::clone(&self.clipboard));
+        #[cfg(feature = "remote-control")]
+        {
+            peer.set_remote_control(self.remote_control());
+            peer.set_passphrase(Arw::clone(&self.passphrase));
+        }
+        // Il disegno corrente parte appena si apre il data channel
+        let annotations = self.annotations.lock().unwrap().clone();
+        if !annotations.is_empty() {
+            peer.send_annotation(AnnotationEvent::Sync(annotations));
+        }
 
         let offer = peer.create_offer(true).await.unwrap_or_default();
 
-        SDPICEExchange::new_with_spd(offer)
-            .pack()
-            .unwrap_or_default()
+        let mut exchanger = SDPICEExchange::new_with_spd(offer);
+        exchanger.set_profile(self.stream_profile());
+        let passphrase = self.passphrase.as_ref().clone();
+        exchanger.pack(passphrase.as_deref()).unwrap_or_default()
     }
 
     async fn set_remote_sdp(&self, remote_sdp: String) -> bool {
-        let Ok(exchanger) = SDPICEExchange::unpack(remote_sdp) else {
-            return false;
+        let passphrase = self.passphrase.as_ref().clone();
+        let exchanger = match SDPICEExchange::unpack(remote_sdp, passphrase.as_deref()) {
+            Ok(exchanger) => exchanger,
+            Err(e) => {
+                log::warn!("Manual SDP rejected: {}", e);
+                return false;
+            }
         };
 
         let peer = self.get_handler().get_manual_connection().await;
@@ -122,4 +309,8 @@ impl SDPICEExchangeWRTC for WebRTCServer {
 
         res
     }
+
+    fn set_passphrase(&self, passphrase: Option<String>) {
+        *self.passphrase.as_mut() = passphrase;
+    }
 }
@@ -1,7 +1,7 @@
 use crate::config::app_name;
 use std::env::var_os;
 use std::fs::DirBuilder;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 fn home_path() -> Option<String> {
     #[cfg(not(target_os = "windows"))]
@@ -45,3 +45,38 @@ pub fn default_saving_path() -> String {
     path.replace("/", std::path::MAIN_SEPARATOR_STR)
         .replace("\\", std::path::MAIN_SEPARATOR_STR)
 }
+
+/// Cartella di configurazione dell'app (creata se manca)
+fn config_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let dir = var_os("APPDATA").map(|appdata| PathBuf::from(appdata).join(app_name()));
+
+    #[cfg(target_os = "macos")]
+    let dir = home_path().map(|home| {
+        PathBuf::from(home)
+            .join("Library/Application Support")
+            .join(app_name())
+    });
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let dir = var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_path().map(|home| PathBuf::from(home).join(".config")))
+        .map(|config| config.join(crate::config::app_id()));
+
+    let dir = dir?;
+    DirBuilder::new().recursive(true).create(&dir).ok()?;
+    Some(dir)
+}
+
+/// Percorso di un file nella cartella di configurazione
+pub fn config_file_path(name: &str) -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(name))
+}
+
+/// Cartella dei file di log, dentro quella di configurazione
+pub fn log_dir() -> Option<PathBuf> {
+    let dir = config_dir()?.join("logs");
+    DirBuilder::new().recursive(true).create(&dir).ok()?;
+    Some(dir)
+}
@@ -10,6 +10,8 @@ pub struct PipelineStats {
     pub frames_encoded: AtomicU64,
     pub frames_skipped: AtomicU64,
     pub frames_dropped: AtomicU64,
+    /// Frame identici al precedente, non codificati
+    pub frames_duplicate: AtomicU64,
     pub current_fps: AtomicU64,
     encoder_name: String,
 }
@@ -24,6 +26,7 @@ impl PipelineStats {
             frames_encoded: AtomicU64::new(0),
             frames_skipped: AtomicU64::new(0),
             frames_dropped: AtomicU64::new(0),
+            frames_duplicate: AtomicU64::new(0),
             current_fps: AtomicU64::new(60),
             encoder_name,
         }
@@ -33,10 +36,11 @@ impl PipelineStats {
         let n = self.frames_encoded.load(Ordering::Relaxed).max(1);
         let skipped = self.frames_skipped.load(Ordering::Relaxed);
         let dropped = self.frames_dropped.load(Ordering::Relaxed);
+        let duplicate = self.frames_duplicate.load(Ordering::Relaxed);
         let fps = self.current_fps.load(Ordering::Relaxed);
 
         log::info!(
-            "Pipeline [{}]: fps={} capture={:.1}ms encode={:.1}ms send={:.1}ms | encoded={} skipped={} dropped={}",
+            "Pipeline [{}]: fps={} capture={:.1}ms encode={:.1}ms send={:.1}ms | encoded={} skipped={} dropped={} duplicate={}",
             self.encoder_name,
             fps,
             self.capture_us.load(Ordering::Relaxed) as f64 / n as f64 / 1000.0,
@@ -45,6 +49,7 @@ impl PipelineStats {
             n,
             skipped,
             dropped,
+            duplicate,
         );
 
         // Reset counters for next interval
@@ -55,5 +60,6 @@ impl PipelineStats {
         self.frames_encoded.store(0, Ordering::Relaxed);
         self.frames_skipped.store(0, Ordering::Relaxed);
         self.frames_dropped.store(0, Ordering::Relaxed);
+        self.frames_duplicate.store(0, Ordering::Relaxed);
     }
 }
@@ -0,0 +1,224 @@
+//! Controllo remoto del caster (solo mouse, feature `remote-control`)
+//!
+//! Il receiver invia gli eventi del mouse in coordinate normalizzate sul
+//! frame trasmesso; il caster li riporta sullo schermo tenendo conto di
+//! display e crop e li riproduce con le API di input del sistema. Niente
+//! viene iniettato finché l'utente del caster non abilita il controllo.
+
+use crate::capture::{CaptureOpts, CropRect};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::watch;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RemoteButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// Evento del receiver; `x`/`y` in [0, 1] sul frame ricevuto
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RemoteInput {
+    MouseMove {
+        x: f32,
+        y: f32,
+    },
+    MouseButton {
+        x: f32,
+        y: f32,
+        button: RemoteButton,
+        pressed: bool,
+    },
+    /// Rotella verticale, in scatti (positivo = verso l'alto)
+    Scroll {
+        x: f32,
+        y: f32,
+        lines: f32,
+    },
+}
+
+impl RemoteInput {
+    fn position(&self) -> (f32, f32) {
+        match *self {
+            Self::MouseMove { x, y }
+            | Self::MouseButton { x, y, .. }
+            | Self::Scroll { x, y, .. } => (x, y),
+        }
+    }
+}
+
+/// Lato caster: decide se e dove riprodurre gli eventi ricevuti
+#[derive(Default)]
+pub struct RemoteControl {
+    allowed: AtomicBool,
+    /// Display catturato (w, h, x, y) in pixel fisici del desktop virtuale
+    display: Mutex<Option<(f32, f32, f32, f32)>>,
+    /// Opzioni della cattura, per il crop corrente
+    opts: Mutex<Option<watch::Receiver<CaptureOpts>>>,
+}
+
+impl RemoteControl {
+    pub fn is_allowed(&self) -> bool {
+        self.allowed.load(Ordering::Relaxed)
+    }
+
+    pub fn set_allowed(&self, allowed: bool) {
+        self.allowed.store(allowed, Ordering::Relaxed);
+        log::info!(
+            "Remote control {}",
+            if allowed { "allowed" } else { "revoked" }
+        );
+    }
+
+    /// Aggiornato a ogni cambio di display: il crop invece viene letto live
+    pub fn set_target(&self, display: (f32, f32, f32, f32), opts: watch::Receiver<CaptureOpts>) {
+        *self.display.lock().unwrap() = Some(display);
+        *self.opts.lock().unwrap() = Some(opts);
+    }
+
+    pub fn handle(&self, input: RemoteInput) {
+        if !self.is_allowed() {
+            return;
+        }
+        let Some(point) = self.screen_point(input.position()) else {
+            return;
+        };
+        if let Err(e) = inject(input, point) {
+            log::debug!("Remote input dropped: {}", e);
+        }
+    }
+
+    /// Punto normalizzato sul frame → pixel del desktop virtuale
+    fn screen_point(&self, (nx, ny): (f32, f32)) -> Option<(i32, i32)> {
+        if !(0.0..=1.0).contains(&nx) || !(0.0..=1.0).contains(&ny) {
+            return None;
+        }
+        let display = (*self.display.lock().unwrap())?;
+        let crop = self
+            .opts
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|opts| opts.borrow().crop);
+        Some(screen_point((nx, ny), display, crop))
+    }
+}
+
+/// Punto normalizzato sul frame trasmesso (crop del display `(w, h, x, y)`,
+/// o il display intero) → pixel del desktop virtuale
+fn screen_point(
+    (nx, ny): (f32, f32),
+    (dw, dh, dx, dy): (f32, f32, f32, f32),
+    crop: Option<CropRect>,
+) -> (i32, i32) {
+    let (fx, fy, fw, fh) = match crop {
+        Some(crop) => (crop.x as f32, crop.y as f32, crop.w as f32, crop.h as f32),
+        None => (0.0, 0.0, dw, dh),
+    };
+    (
+        (dx + fx + nx * (fw - 1.0).max(0.0)).round() as i32,
+        (dy + fy + ny * (fh - 1.0).max(0.0)).round() as i32,
+    )
+}
+
+#[cfg(target_os = "windows")]
+fn inject(input: RemoteInput, (x, y): (i32, i32)) -> anyhow::Result<()> {
+    use std::mem::size_of;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        INPUT, INPUT_0, INPUT_MOUSE, MOUSE_EVENT_FLAGS, MOUSEEVENTF_ABSOLUTE, MOUSEEVENTF_LEFTDOWN,
+        MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE,
+        MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_VIRTUALDESK, MOUSEEVENTF_WHEEL,
+        MOUSEINPUT, SendInput,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        GetSystemMetrics, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN,
+        SM_YVIRTUALSCREEN, WHEEL_DELTA,
+    };
+
+    // Coordinate assolute: 0..65535 sull'intero desktop virtuale
+    let (vx, vy, vw, vh) = unsafe {
+        (
+            GetSystemMetrics(SM_XVIRTUALSCREEN),
+            GetSystemMetrics(SM_YVIRTUALSCREEN),
+            GetSystemMetrics(SM_CXVIRTUALSCREEN).max(2),
+            GetSystemMetrics(SM_CYVIRTUALSCREEN).max(2),
+        )
+    };
+    let dx = ((x - vx) as i64 * 65535 / (vw - 1) as i64) as i32;
+    let dy = ((y - vy) as i64 * 65535 / (vh - 1) as i64) as i32;
+
+    let (flags, data) = match input {
+        RemoteInput::MouseMove { .. } => (MOUSE_EVENT_FLAGS(0), 0),
+        RemoteInput::MouseButton {
+            button, pressed, ..
+        } => {
+            let flags = match (button, pressed) {
+                (RemoteButton::Left, true) => MOUSEEVENTF_LEFTDOWN,
+                (RemoteButton::Left, false) => MOUSEEVENTF_LEFTUP,
+                (RemoteButton::Right, true) => MOUSEEVENTF_RIGHTDOWN,
+                (RemoteButton::Right, false) => MOUSEEVENTF_RIGHTUP,
+                (RemoteButton::Middle, true) => MOUSEEVENTF_MIDDLEDOWN,
+                (RemoteButton::Middle, false) => MOUSEEVENTF_MIDDLEUP,
+            };
+            (flags, 0)
+        }
+        RemoteInput::Scroll { lines, .. } => (
+            MOUSEEVENTF_WHEEL,
+            (lines * WHEEL_DELTA as f32).round() as i32,
+        ),
+    };
+
+    let event = INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx,
+                dy,
+                mouseData: data as u32,
+                dwFlags: flags | MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+    let sent = unsafe { SendInput(&[event], size_of::<INPUT>() as i32) };
+    if sent != 1 {
+        anyhow::bail!("SendInput rejected the event");
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn inject(_input: RemoteInput, _point: (i32, i32)) -> anyhow::Result<()> {
+    anyhow::bail!("input injection is only implemented on Windows")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corners_land_on_the_display_edges() {
+        // Secondo monitor a sinistra del principale
+        let display = (1920.0, 1080.0, -1920.0, 0.0);
+        assert_eq!(screen_point((0.0, 0.0), display, None), (-1920, 0));
+        assert_eq!(screen_point((1.0, 1.0), display, None), (-1, 1079));
+        assert_eq!(screen_point((0.25, 0.25), display, None), (-1440, 270));
+    }
+
+    #[test]
+    fn crop_is_mapped_inside_the_display() {
+        let display = (2560.0, 1440.0, 1920.0, -200.0);
+        let crop = Some(CropRect {
+            x: 100,
+            y: 50,
+            w: 801,
+            h: 601,
+        });
+        assert_eq!(screen_point((0.0, 0.0), display, crop), (2020, -150));
+        assert_eq!(screen_point((1.0, 1.0), display, crop), (2820, 450));
+        assert_eq!(screen_point((0.5, 0.5), display, crop), (2420, 150));
+    }
+}
@@ -0,0 +1,176 @@
+//! Self-test: encoder disponibili, throughput di un encode sintetico,
+//! audio di sistema e display. Il riepilogo è pensato per le segnalazioni
+//! di bug (`--selftest` o pulsante nella pagina info).
+//!
+//! Il self-test non apre il portal ScreenCast: sotto Wayland il dialog di
+//! condivisione chiederebbe all'utente di scegliere uno schermo.
+
+use crate::capture::audio::{AudioCapture, AudioEncodeConfig};
+use crate::capture::capturer::Capturer;
+use crate::capture::{YUVFrame, uses_portal};
+use crate::encoder::{FfmpegEncoder, FrameData};
+use crate::pipeline::clock::MediaClock;
+use crate::utils::audio_level::AudioLevel;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Frame dell'encode sintetico
+pub const SELFTEST_FRAMES: u32 = 120;
+/// Risoluzione dell'encode sintetico
+pub const SELFTEST_SIZE: (u32, u32) = (1920, 1080);
+
+#[cfg(target_os = "windows")]
+const AUDIO_SOURCE: &str = "WASAPI loopback";
+#[cfg(not(target_os = "windows"))]
+const AUDIO_SOURCE: &str = "Audio input";
+
+struct EncodeRun {
+    encoder: String,
+    frames: u32,
+    fps: f64,
+    avg_encode_us: u64,
+}
+
+pub struct SelfTestReport {
+    size: (u32, u32),
+    encoders: Vec<(&'static str, Result<(), String>)>,
+    encode: Result<EncodeRun, String>,
+    audio: Result<(), String>,
+    displays: Result<usize, String>,
+}
+
+impl SelfTestReport {
+    /// Bloccante: va eseguito fuori dal thread della GUI
+    pub fn run(frames: u32, (w, h): (u32, u32)) -> Self {
+        let encoders = FfmpegEncoder::probe_chain(w, h);
+        let encode = if encoders.iter().any(|(_, result)| result.is_ok()) {
+            synthetic_encode(frames, w, h)
+        } else {
+            Err(String::from("no H.264 encoder available"))
+        };
+
+        Self {
+            size: (w, h),
+            encoders,
+            encode,
+            audio: probe_audio(),
+            displays: probe_displays(),
+        }
+    }
+}
+
+/// Le build release su Windows non hanno console: `--selftest` scrive in
+/// quella del terminale da cui è stato lanciato, se c'è.
+#[cfg(target_os = "windows")]
+pub fn attach_console() {
+    use windows::Win32::System::Console::{ATTACH_PARENT_PROCESS, AttachConsole};
+    // Fallisce se il processo ha già una console (build debug): va bene così
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn attach_console() {}
+
+fn probe_displays() -> Result<usize, String> {
+    if uses_portal() {
+        return Err(String::from("chosen in the ScreenCast portal, not probed"));
+    }
+    Capturer::new(30)
+        .map(|capturer| capturer.available_displays().len())
+        .map_err(|e| e.to_string())
+}
+
+/// Encode di `frames` frame NV12 che cambiano ad ogni passo, così
+/// l'encoder non lavora su un'immagine statica.
+fn synthetic_encode(frames: u32, w: u32, h: u32) -> Result<EncodeRun, String> {
+    // Si misura l'encoder da solo, senza la ripetizione di SPS/PPS
+    let mut encoder = FfmpegEncoder::new(w, h)
+        .map_err(|e| e.to_string())?
+        .with_parameter_sets(false);
+    let (w, h) = (w as usize, h as usize);
+    let mut frame = YUVFrame {
+        display_time: 0,
+        width: w as i32,
+        height: h as i32,
+        luminance_bytes: vec![0; w * h],
+        luminance_stride: w as i32,
+        chrominance_bytes: vec![128; w * h.div_ceil(2)],
+        chrominance_stride: w as i32,
+    };
+
+    let frame_duration = Duration::from_secs(1) / 30;
+    let mut encode_time = Duration::ZERO;
+    let mut encoded = 0;
+    let started = Instant::now();
+    for i in 0..frames {
+        for (j, px) in frame.luminance_bytes.iter_mut().enumerate() {
+            *px = ((j % w) as u32 + i * 4) as u8;
+        }
+        let frame_time = FfmpegEncoder::frame_time(frame_duration * i);
+        let t = Instant::now();
+        if let Err(e) = encoder.encode(FrameData::NV12(&frame), frame_time) {
+            log::warn!("Self-test encode failed at frame {}: {}", i, e);
+            break;
+        }
+        encode_time += t.elapsed();
+        encoded += 1;
+    }
+    let elapsed = started.elapsed().as_secs_f64().max(f64::EPSILON);
+
+    Ok(EncodeRun {
+        encoder: encoder.codec_name.clone(),
+        frames: encoded,
+        fps: encoded as f64 / elapsed,
+        avg_encode_us: (encode_time / encoded.max(1)).as_micros() as u64,
+    })
+}
+
+/// Apre e chiude subito la cattura audio usata dal caster
+fn probe_audio() -> Result<(), String> {
+    let cancel = CancellationToken::new();
+    let result = AudioCapture::start(
+        cancel.clone(),
+        AudioEncodeConfig::default(),
+        AudioLevel::default(),
+        Arc::new(AtomicBool::new(true)),
+        MediaClock::new(),
+    );
+    cancel.cancel();
+    result.map(|_| ()).map_err(|e| e.to_string())
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (w, h) = self.size;
+        writeln!(f, "Encoders ({}x{}):", w, h)?;
+        for (codec, result) in &self.encoders {
+            match result {
+                Ok(()) => writeln!(f, "  {:<12} ok", codec)?,
+                Err(e) => writeln!(f, "  {:<12} {}", codec, e)?,
+            }
+        }
+        match &self.encode {
+            Ok(run) => writeln!(
+                f,
+                "Encode: {} frames with {}, {:.1} fps, {:.2} ms/frame",
+                run.frames,
+                run.encoder,
+                run.fps,
+                run.avg_encode_us as f64 / 1000.0
+            )?,
+            Err(e) => writeln!(f, "Encode: {}", e)?,
+        }
+        match &self.audio {
+            Ok(()) => writeln!(f, "{}: ok", AUDIO_SOURCE)?,
+            Err(e) => writeln!(f, "{}: {}", AUDIO_SOURCE, e)?,
+        }
+        match &self.displays {
+            Ok(0) => write!(f, "Displays: none found"),
+            Ok(n) => write!(f, "Displays: {}", n),
+            Err(e) => write!(f, "Displays: {}", e),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Registrazione dello schema `castify://` presso il sistema operativo,
+//! così che aprire un link di connessione lanci l'app con il link come argomento.
+
+use crate::utils::net::common::LINK_SCHEME;
+
+/// Registra lo schema per l'utente corrente (nessun privilegio richiesto).
+///
+/// Su macOS lo schema va dichiarato nell'`Info.plist` del bundle: a runtime
+/// non c'è nulla da fare.
+pub fn register() {
+    let Ok(exe) = std::env::current_exe() else {
+        return;
+    };
+
+    if let Err(e) = register_for(&exe.to_string_lossy()) {
+        log::warn!(
+            "Unable to register the {}:// URL scheme: {}",
+            LINK_SCHEME,
+            e
+        );
+    }
+}
+
+/// `reg.exe` senza finestra di console: l'app è un'applicazione GUI
+#[cfg(target_os = "windows")]
+fn reg_command() -> std::process::Command {
+    use std::os::windows::process::CommandExt;
+
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let mut reg = std::process::Command::new("reg");
+    reg.creation_flags(CREATE_NO_WINDOW);
+    reg
+}
+
+#[cfg(target_os = "windows")]
+fn register_for(exe: &str) -> anyhow::Result<()> {
+    use crate::config::app_name;
+
+    let key = format!(r"HKCU\Software\Classes\{}", LINK_SCHEME);
+    let command = format!("\"{}\" \"%1\"", exe);
+    let entries = [
+        (key.clone(), "", format!("URL:{} link", app_name())),
+        (key.clone(), "URL Protocol", String::new()),
+        (format!(r"{}\shell\open\command", key), "", command),
+    ];
+
+    for (key, value, data) in entries {
+        let name: &[&str] = if value.is_empty() {
+            &["/ve"]
+        } else {
+            &["/v", value]
+        };
+
+        // Voce già presente con lo stesso valore: nessuna scrittura
+        let current = reg_command().args(["query", &key]).args(name).output()?;
+        if current.status.success() && String::from_utf8_lossy(&current.stdout).contains(&data) {
+            continue;
+        }
+
+        let status = reg_command()
+            .args(["add", &key, "/f", "/d", &data])
+            .args(name)
+            .output()?
+            .status;
+        if !status.success() {
+            anyhow::bail!("reg add {} failed ({})", key, status);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn register_for(exe: &str) -> anyhow::Result<()> {
+    use crate::config::app_name;
+    use std::process::Command;
+
+    let home = std::env::var("HOME")?;
+    let dir = std::path::Path::new(&home).join(".local/share/applications");
+    std::fs::create_dir_all(&dir)?;
+
+    let desktop = format!("{}-url.desktop", LINK_SCHEME);
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName={}\nExec=\"{}\" %u\nNoDisplay=true\nMimeType=x-scheme-handler/{};\n",
+        app_name(),
+        exe,
+        LINK_SCHEME
+    );
+    let path = dir.join(&desktop);
+    if std::fs::read_to_string(&path).ok().as_deref() != Some(entry.as_str()) {
+        std::fs::write(&path, entry)?;
+    }
+
+    let mime = format!("x-scheme-handler/{}", LINK_SCHEME);
+    let current = Command::new("xdg-mime")
+        .args(["query", "default", &mime])
+        .output()?;
+    if String::from_utf8_lossy(&current.stdout).trim() != desktop {
+        Command::new("xdg-mime")
+            .args(["default", &desktop, &mime])
+            .output()?;
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn register_for(_exe: &str) -> anyhow::Result<()> {
+    Ok(())
+}
@@ -1,16 +1,31 @@
-use crate::capture::ScreenCaptureImpl;
-use crate::capture::audio::AudioCapture;
+use crate::capture::audio::{AudioCapture, AudioEncodeConfig, TestTone};
+use crate::capture::budget::{BudgetUsage, DataCap};
 use crate::capture::capturer::{Capturer, CropRect};
 use crate::capture::display::DisplaySelector;
+use crate::capture::keycast::{Keycast, KeycastFilter};
+use crate::capture::overlay::CursorHighlight;
+use crate::capture::timestamp::Timestamp;
+use crate::capture::watermark::Watermark;
+use crate::capture::zoom::Zoom;
 use crate::gui::common::datastructure::ScreenRect;
 use crate::pipeline::clock::MediaClock;
 use crate::pipeline::health::PipelineHealth;
+use crate::pipeline::receiver::LatencyProfile;
+use crate::pipeline::sender::RtpMtu;
 use crate::pipeline::state::PipelineState;
-use crate::utils::net::webrtc::WebRTCServer;
+use crate::pipeline::stats_log::{StatsLogTarget, spawn_stats_log};
+use crate::utils::audio_level::{AudioLevel, LevelSnapshot};
+use crate::utils::net::webhook::WebhookEvent;
+use crate::utils::net::webrtc::{AnnotationEvent, ChatMessage, WebRTCServer};
 use crate::utils::sos::SignalOfStop;
+use crate::workers::cursor_follow::{CursorFollow, FollowCursorDelay};
+use crate::workers::idle::{IdleAction, IdleTimeout, IdleWatch};
+use iced::keyboard::{Key, Modifiers};
+use iced::{Rectangle, Size};
 use log::{error, info};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 use tokio_util::sync::CancellationToken;
 
 pub struct Caster {
@@ -18,11 +33,42 @@ pub struct Caster {
     pub streaming_time: u64,
     streaming: bool,
     blank_screen: bool,
+    cursor_highlight: bool,
+    show_cursor: bool,
+    keycast: Option<Keycast>,
+    zoom: Option<Zoom>,
+    /// Solo audio di sistema: niente cattura/encoding video né traccia video nell'SDP
+    audio_only: bool,
+    /// Pattern di test e tono a 1kHz al posto di schermo e audio catturato
+    test_pattern: bool,
+    profile: StreamProfile,
+    encode_scale: EncodeScale,
+    audio_encode: AudioEncodeConfig,
+    data_cap: DataCap,
+    /// Nome annunciato via mDNS ai receiver
+    instance_name: String,
+    /// Consumo stimato dal loop di cattura, presente solo con un tetto attivo
+    budget_usage: Option<BudgetUsage>,
+    /// Area trasmessa nelle coordinate (logiche) della finestra annotazioni
+    annotation_area: Option<ScreenRect>,
     audio_muted: Arc<AtomicBool>,
+    /// Livello dell'audio catturato, mostrato come VU meter
+    audio_level: AudioLevel,
     audio_cancel: Option<CancellationToken>,
+    /// Log periodico delle statistiche, avviato con lo stream
+    stats_log: Option<StatsLogTarget>,
+    stats_cancel: Option<CancellationToken>,
     capturer: Capturer,
     server: Arc<WebRTCServer>,
     sos: SignalOfStop,
+    /// Pausa automatica della cattura senza receiver connessi
+    idle: IdleWatch,
+    /// Cambio di monitor quando il puntatore passa su un altro
+    follow_cursor: CursorFollow,
+    /// Monitor tra cui seguire il puntatore, riletti dopo ogni cambio
+    follow_monitors: Vec<<ScreenCaptureImpl as DisplaySelector>::Display>,
+    /// Finestre trasmissibili, rilette da `refresh_windows()` e non a ogni render
+    windows: Vec<<ScreenCaptureImpl as DisplaySelector>::Display>,
 
     // Pipeline integration
     clock: MediaClock,
@@ -31,24 +77,57 @@ pub struct Caster {
 }
 
 impl Caster {
-    pub fn new(fps: u32, sos: SignalOfStop) -> Self {
+    pub fn new(
+        fps: u32,
+        profile: StreamProfile,
+        audio_encode: AudioEncodeConfig,
+        data_cap: DataCap,
+        instance_name: String,
+        sos: SignalOfStop,
+    ) -> Result<Self, CaptureError> {
         let clock = MediaClock::new();
         let health = Arc::new(PipelineHealth::new());
-
-        Self {
+        let mut capturer = Capturer::new(fps)?;
+        capturer.set_profile(profile);
+        capturer.set_health(Arc::clone(&health));
+        capturer.set_clock(clock.clone());
+        let budget_usage = capturer.set_data_budget(data_cap.mb_per_minute());
+        let windows = capturer.available_windows();
+
+        Ok(Self {
             init: false,
             streaming_time: 0,
             streaming: false,
             blank_screen: false,
+            cursor_highlight: false,
+            show_cursor: true,
+            keycast: None,
+            zoom: None,
+            audio_only: false,
+            test_pattern: false,
+            profile,
+            encode_scale: EncodeScale::default(),
+            audio_encode: audio_encode.validated(),
+            data_cap,
+            instance_name,
+            budget_usage,
+            annotation_area: None,
             audio_muted: Arc::new(AtomicBool::new(false)),
+            audio_level: AudioLevel::default(),
             audio_cancel: None,
-            capturer: Capturer::new(fps),
+            stats_log: None,
+            stats_cancel: None,
+            capturer,
             server: WebRTCServer::new(),
             sos,
+            idle: IdleWatch::default(),
+            follow_cursor: CursorFollow::default(),
+            follow_monitors: Vec::new(),
+            windows,
             clock,
             health,
             pipeline_state: PipelineState::Idle,
-        }
+        })
     }
 
     /// Get the media clock for timestamp correlation
@@ -61,6 +140,11 @@ impl Caster {
         &self.health
     }
 
+    /// H.264 encoder picked when casting started
+    pub fn encoder_name(&self) -> Option<&str> {
+        self.capturer.encoder_name()
+    }
+
     /// Get the current pipeline state
     pub fn pipeline_state(&self) -> &PipelineState {
         &self.pipeline_state
@@ -76,21 +160,33 @@ impl Caster {
         self.pipeline_state = PipelineState::Initializing;
 
         // Avvia la cattura e ottieni il canale con i frame H.264
-        let handle = tokio::runtime::Handle::current();
-        let rx = match tokio::task::block_in_place(|| handle.block_on(self.capturer.start())) {
-            Ok(rx) => rx,
-            Err(e) => {
-                error!("Failed to start capturer: {}", e);
-                self.init = false;
-                self.pipeline_state = PipelineState::Idle;
-                return;
+        let rx = if self.audio_only {
+            info!("Audio-only casting: video capture disabled");
+            None
+        } else {
+            // Senza `test-capture` il test resta sul tono audio
+            #[cfg(any(test, feature = "test-capture"))]
+            self.capturer.set_test_pattern(self.test_pattern);
+            let handle = tokio::runtime::Handle::current();
+            match tokio::task::block_in_place(|| handle.block_on(self.capturer.start())) {
+                Ok(rx) => Some(rx),
+                Err(e) => {
+                    error!("Failed to start capturer: {}", e);
+                    self.init = false;
+                    self.pipeline_state = PipelineState::Idle;
+                    return;
+                }
             }
         };
 
         // mDNS discovery + port forwarding in background
+        let instance_name = self.announced_name();
         self.sos.spawn(async move {
-            match crate::utils::net::common::caster_discover_service() {
-                Ok(_) => info!("Caster running and registered on mDNS"),
+            match crate::utils::net::common::caster_discover_service(&instance_name) {
+                Ok(_) => info!(
+                    "Caster running and registered on mDNS as \"{}\"",
+                    instance_name
+                ),
                 Err(e) => error!("mDNS Error: {}", e),
             }
             if let Err(e) = crate::utils::net::common::port_forwarding() {
@@ -100,12 +196,43 @@ impl Caster {
 
         // Link the encoder's force_idr flag to the server so new peers trigger IDR
         self.server.set_force_idr(self.capturer.force_idr());
+        if let Some((force_idr, active)) = self.capturer.simulcast_control() {
+            self.server.get_handler().set_simulcast(force_idr, active);
+        }
+        self.server.get_handler().set_audio_only(self.audio_only);
+        self.server
+            .get_handler()
+            .set_low_latency_audio(self.audio_encode.low_latency);
+        // L'adattivo abbassa il frame rate di cattura sotto il tetto dell'utente
+        self.server
+            .get_handler()
+            .set_capture_fps_controller(self.capturer.fps_controller());
+        self.announce_profile();
 
         // Avvia il server WebRTC e inoltra i frame
         Arc::clone(&self.server).run();
-        self.server.get_handler().send_video_frames(rx);
+        if let Some(rx) = rx {
+            self.server.get_handler().send_video_frames(rx);
+        }
+        if let Some(rx) = self.capturer.take_simulcast_frames() {
+            self.server.get_handler().send_simulcast_frames(rx);
+        }
 
-        self.start_audio_capture(true);
+        self.start_audio_capture();
+
+        if let Some(target) = self.stats_log.clone() {
+            let cancel = CancellationToken::new();
+            spawn_stats_log(
+                target,
+                self.health.clone(),
+                None,
+                Some(self.server.get_handler()),
+                cancel.clone(),
+            );
+            if let Some(previous) = self.stats_cancel.replace(cancel) {
+                previous.cancel();
+            }
+        }
 
         // Start health monitoring
         let health = self.health.clone();
@@ -128,12 +255,21 @@ impl Caster {
 
     pub fn cast(&mut self) {
         self.lazy_init();
+        self.idle.reset();
+        self.follow_cursor.reset();
         self.capturer.play();
+        if !self.streaming {
+            self.fire_webhook(WebhookEvent::StreamStart);
+        }
         self.streaming = true;
     }
 
     pub fn pause(&mut self) -> bool {
+        self.idle.reset();
         self.capturer.pause();
+        if self.streaming {
+            self.fire_webhook(WebhookEvent::StreamStop);
+        }
         self.streaming = false;
         true
     }
@@ -150,20 +286,194 @@ impl Caster {
         self.streaming
     }
 
+    // ── Idle timeout ────────────────────────────────────────────
+
+    pub fn set_idle_timeout(&mut self, timeout: IdleTimeout) {
+        self.idle.set_timeout(timeout);
+    }
+
+    /// Cattura in pausa perché nessun receiver è connesso
+    pub fn is_idle_paused(&self) -> bool {
+        self.idle.is_paused()
+    }
+
+    /// Chiamata una volta al secondo: mette in pausa la cattura dopo il
+    /// timeout senza client e la riprende quando se ne collega uno.
+    pub fn check_idle(&mut self) {
+        if !self.streaming {
+            return;
+        }
+        let action = self
+            .idle
+            .tick(self.server.connected_clients(), Instant::now());
+        self.apply_idle(action);
+    }
+
+    /// Input locale dell'utente: riprende la cattura messa in pausa
+    pub fn note_activity(&mut self) {
+        if !self.streaming {
+            return;
+        }
+        let action = self.idle.activity(Instant::now());
+        self.apply_idle(action);
+    }
+
+    fn apply_idle(&mut self, action: Option<IdleAction>) {
+        match action {
+            Some(IdleAction::Pause) => {
+                info!("No receivers connected: capture paused until one connects");
+                self.capturer.pause();
+            }
+            Some(IdleAction::Resume) => {
+                info!("Capture resumed after the idle pause");
+                self.capturer.play();
+            }
+            None => {}
+        }
+    }
+
+    /// URL notificati su stream avviato/fermato e receiver connessi/usciti
+    pub fn set_webhooks(&self, urls: Vec<String>) {
+        self.server.webhooks().set_urls(urls);
+    }
+
+    fn fire_webhook(&self, event: WebhookEvent) {
+        self.server
+            .webhooks()
+            .fire(event, self.server.connected_clients());
+    }
+
     // ── Display / monitor management ────────────────────────────
 
     pub fn get_displays(&self) -> Vec<<ScreenCaptureImpl as DisplaySelector>::Display> {
         self.capturer.available_displays()
     }
 
+    /// Finestre top-level che si possono trasmettere al posto di un monitor,
+    /// come lette all'ultimo `refresh_windows()`
+    pub fn get_windows(&self) -> &[<ScreenCaptureImpl as DisplaySelector>::Display] {
+        &self.windows
+    }
+
+    /// Rilegge le finestre aperte (EnumWindows su Windows)
+    pub fn refresh_windows(&mut self) {
+        self.windows = self.capturer.available_windows();
+    }
+
+    /// Cambia display anche durante lo streaming, senza fermare la sessione
     pub fn change_display(&mut self, display: <ScreenCaptureImpl as DisplaySelector>::Display) {
-        self.capturer.select_display(display);
+        let handle = tokio::runtime::Handle::current();
+        match tokio::task::block_in_place(|| handle.block_on(self.capturer.switch_display(display)))
+        {
+            Ok(()) => {
+                // Crop e area annotazioni erano relativi al display precedente
+                self.annotation_area = None;
+                self.announce_profile();
+                #[cfg(feature = "remote-control")]
+                self.refresh_control_target();
+            }
+            Err(e) => error!("Failed to change display: {}", e),
+        }
+    }
+
+    /// Risoluzione cambiata o display sparito durante la cattura (vedi
+    /// `SourceEvent`): il nuovo profilo viene annunciato ai peer successivi,
+    /// quelli connessi ricevono già SPS e IDR alla nuova dimensione.
+    ///
+    /// Se a sparire è la finestra o l'area condivisa lo stream resta oscurato
+    /// e in pausa; ritorna l'avviso da mostrare all'utente.
+    pub fn sync_source(&mut self) -> Option<String> {
+        if self.capturer.take_source_lost() {
+            self.blank_screen = true;
+            self.pause();
+            return Some(String::from(
+                "The shared window or area is gone: casting paused and blanked. Choose another source to resume.",
+            ));
+        }
+        if !self.capturer.take_source_changed() {
+            return None;
+        }
+        self.annotation_area = None;
+        self.follow_monitors.clear();
+        self.announce_profile();
+        #[cfg(feature = "remote-control")]
+        self.refresh_control_target();
+        None
+    }
+
+    /// Passa al display successivo (ciclico); ritorna il nome del nuovo display
+    pub fn cycle_display(&mut self) -> Option<String> {
+        let displays = self.get_displays();
+        let current = self.get_selected_display();
+        let next = match current.and_then(|cur| displays.iter().position(|d| d == &cur)) {
+            Some(idx) => (idx + 1) % displays.len(),
+            None => 0,
+        };
+        let display = displays.into_iter().nth(next)?;
+        let name = display.to_string();
+        self.change_display(display);
+        (self.get_selected_display()?.to_string() == name).then_some(name)
     }
 
     pub fn get_selected_display(&self) -> Option<<ScreenCaptureImpl as DisplaySelector>::Display> {
         self.capturer.selected_display()
     }
 
+    // ── Follow puntatore ────────────────────────────────────────
+
+    /// La cattura passa da sola al monitor col puntatore (solo Windows).
+    pub fn set_follow_cursor(&mut self, enabled: bool) {
+        #[cfg(not(target_os = "windows"))]
+        if enabled {
+            error!("Follow cursor is only supported on Windows");
+            return;
+        }
+        self.follow_cursor.set_enabled(enabled);
+        self.follow_monitors.clear();
+        info!("Follow cursor: {}", enabled);
+    }
+
+    pub fn is_following_cursor(&self) -> bool {
+        self.follow_cursor.is_enabled()
+    }
+
+    pub fn set_follow_cursor_delay(&mut self, delay: FollowCursorDelay) {
+        self.follow_cursor.set_delay(delay);
+    }
+
+    /// Chiamata a ogni tick veloce durante lo streaming: dopo il ritardo
+    /// scelto passa al monitor col puntatore, con un IDR come ogni cambio
+    /// di display al volo.
+    pub fn check_follow_cursor(&mut self) {
+        if !self.streaming
+            || self.audio_only
+            || !self.follow_cursor.is_enabled()
+            || self.capturer.is_following_window()
+        {
+            return;
+        }
+        if self.follow_monitors.is_empty() {
+            self.follow_monitors = self.capturer.cursor_monitors();
+        }
+
+        let current = self
+            .get_selected_display()
+            .and_then(|cur| self.follow_monitors.iter().position(|d| d == &cur));
+        let under_cursor = Capturer::display_under_cursor(&self.follow_monitors);
+        let Some(target) = self
+            .follow_cursor
+            .tick(current, under_cursor, Instant::now())
+        else {
+            return;
+        };
+
+        // L'elenco viene riletto al prossimo tick
+        let display = self.follow_monitors.swap_remove(target);
+        self.follow_monitors.clear();
+        info!("Follow cursor: switching to {}", display.to_string());
+        self.change_display(display);
+    }
+
     // ── Blank screen ────────────────────────────────────────────
 
     pub fn is_blank_screen(&self) -> bool {
@@ -175,35 +485,408 @@ impl Caster {
         self.capturer.set_blank_screen(self.blank_screen);
     }
 
+    // ── Cursor highlight ────────────────────────────────────────
+
+    pub fn is_cursor_highlight(&self) -> bool {
+        self.cursor_highlight
+    }
+
+    pub fn toggle_cursor_highlight(&mut self, style: CursorHighlight) {
+        self.cursor_highlight = !self.cursor_highlight;
+        self.capturer
+            .set_cursor_highlight(self.cursor_highlight.then_some(style));
+    }
+
+    /// Nuovo stile dalle impostazioni, applicato subito se l'anello è acceso
+    pub fn set_cursor_highlight_style(&mut self, style: CursorHighlight) {
+        if self.cursor_highlight {
+            self.capturer.set_cursor_highlight(Some(style));
+        }
+    }
+
+    // ── Cursor capture ──────────────────────────────────────────
+
+    pub fn is_showing_cursor(&self) -> bool {
+        self.show_cursor
+    }
+
+    /// Falso su portal e backend generico: il puntatore resta nei frame
+    pub fn can_hide_cursor(&self) -> bool {
+        self.capturer.can_hide_cursor()
+    }
+
+    pub fn set_show_cursor(&mut self, show: bool) {
+        if !self.can_hide_cursor() {
+            return;
+        }
+        self.show_cursor = show;
+        self.capturer.set_show_cursor(show);
+    }
+
+    pub fn toggle_show_cursor(&mut self) {
+        self.set_show_cursor(!self.show_cursor);
+    }
+
+    // ── Keycast ─────────────────────────────────────────────────
+
+    pub fn is_keycast(&self) -> bool {
+        self.keycast.is_some()
+    }
+
+    pub fn toggle_keycast(&mut self) {
+        self.keycast = match self.keycast.take() {
+            Some(_) => None,
+            None => Some(Keycast::default()),
+        };
+        self.capturer.set_keycast(self.keycast.clone());
+    }
+
+    /// Accoda la combinazione all'overlay se attivo e se passa il filtro.
+    pub fn push_keystroke(&self, modifiers: Modifiers, key: &Key, filter: &KeycastFilter) {
+        if let Some(keycast) = &self.keycast
+            && self.streaming
+            && let Some(label) = filter.label(modifiers, key)
+        {
+            keycast.push(label);
+        }
+    }
+
+    // ── Statistiche ─────────────────────────────────────────────
+
+    /// File in cui scrivere le statistiche quando parte lo stream, `None` per non scriverle
+    pub fn set_stats_log(&mut self, target: Option<StatsLogTarget>) {
+        self.stats_log = target;
+    }
+
+    // ── Watermark ───────────────────────────────────────────────
+
+    /// Logo sopra lo stream, `None` per rimuoverlo. Nessun effetto sui receiver:
+    /// viene composto prima dell'encoding.
+    pub fn set_watermark(&self, watermark: Option<Watermark>) {
+        self.capturer.set_watermark(watermark);
+    }
+
+    /// Orario impresso nello stream, `None` per toglierlo. Come il logo finisce
+    /// anche nelle registrazioni dei receiver.
+    pub fn set_timestamp(&self, timestamp: Option<Timestamp>) {
+        self.capturer.set_timestamp(timestamp);
+    }
+
+    // ── Zoom ────────────────────────────────────────────────────
+
+    pub fn is_zoom(&self) -> bool {
+        self.zoom.is_some()
+    }
+
+    pub fn toggle_zoom(&mut self, zoom: Zoom) {
+        self.zoom = match self.zoom {
+            Some(_) => None,
+            None => Some(zoom),
+        };
+        self.capturer.set_zoom(self.zoom);
+    }
+
+    /// Sposta la lente di uno scatto; ignorato a lente chiusa.
+    pub fn nudge_zoom(&mut self, dx: i32, dy: i32) {
+        if let Some(zoom) = &mut self.zoom {
+            zoom.nudge = (zoom.nudge.0 + dx, zoom.nudge.1 + dy);
+            self.capturer.set_zoom(self.zoom);
+        }
+    }
+
+    // ── Audio only ──────────────────────────────────────────────
+
+    pub fn is_audio_only(&self) -> bool {
+        self.audio_only
+    }
+
+    /// La modalità si sceglie prima di avviare lo stream: i peer già
+    /// negoziati non possono perdere o acquisire la traccia video.
+    pub fn toggle_audio_only(&mut self) {
+        if self.init {
+            info!("Audio-only mode can only be changed before casting starts");
+            return;
+        }
+        self.audio_only = !self.audio_only;
+    }
+
+    // ── Test pattern ────────────────────────────────────────────
+
+    pub fn is_test_pattern(&self) -> bool {
+        self.test_pattern
+    }
+
+    /// Come l'audio-only, si sceglie prima di avviare lo stream: la sorgente
+    /// (schermo o pattern) resta quella per tutta la sessione.
+    pub fn toggle_test_pattern(&mut self) {
+        if self.init {
+            info!("Test pattern can only be changed before casting starts");
+            return;
+        }
+        self.test_pattern = !self.test_pattern;
+    }
+
+    // ── Nome mDNS ───────────────────────────────────────────────
+
+    pub fn instance_name(&self) -> &str {
+        &self.instance_name
+    }
+
+    /// Nome effettivo: quello scelto o, se vuoto, il nome della macchina
+    fn announced_name(&self) -> String {
+        match self.instance_name.trim() {
+            "" => crate::utils::net::common::default_instance_name(),
+            name => name.to_owned(),
+        }
+    }
+
+    /// Il nome viene registrato su mDNS all'avvio dello stream.
+    pub fn set_instance_name(&mut self, name: String) {
+        if self.init {
+            info!("Caster name can only be changed before casting starts");
+            return;
+        }
+        self.instance_name = name;
+    }
+
     // ── Audio mute ──────────────────────────────────────────────
 
     pub fn is_audio_muted(&self) -> bool {
         self.audio_muted.load(Ordering::Relaxed)
     }
 
+    pub fn audio_level(&self) -> LevelSnapshot {
+        self.audio_level.snapshot()
+    }
+
+    /// Silenzia l'audio in uscita: la cattura continua ma codifica silenzio,
+    /// come lo schermo nero per il video
     pub fn toggle_audio_mute(&mut self) {
         let muted = !self.audio_muted.load(Ordering::Relaxed);
         self.audio_muted.store(muted, Ordering::Relaxed);
-        if muted {
-            self.stop_audio_capture();
-            info!("Audio muted");
-        } else {
-            self.start_audio_capture(false);
+        info!("Audio {}", if muted { "muted" } else { "unmuted" });
+    }
+
+    // ── Stream profile ──────────────────────────────────────────
+
+    pub fn stream_profile(&self) -> StreamProfile {
+        self.profile
+    }
+
+    pub fn set_stream_profile(&mut self, profile: StreamProfile) {
+        self.profile = profile;
+        self.capturer.set_profile(profile);
+        self.announce_profile();
+    }
+
+    /// Tetto alla risoluzione codificata, sopra quello del profilo
+    pub fn set_encode_scale(&mut self, scale: EncodeScale) {
+        self.encode_scale = scale;
+        self.capturer.set_scale_to(scale.size());
+        self.announce_profile();
+    }
+
+    /// Tetto di fps dell'utente, limitato alla frequenza del display
+    pub fn set_fps_cap(&mut self, cap: FpsCap) {
+        self.capturer.set_fps_cap(cap);
+        self.announce_profile();
+    }
+
+    /// Secondo encoder ridotto per i receiver lenti: vale dal prossimo avvio
+    /// dello stream, i peer vengono spostati tra i due livelli in base agli invii
+    pub fn set_simulcast(&mut self, simulcast: Simulcast) {
+        if self.init {
+            info!("Simulcast can only be changed before casting starts");
+            return;
+        }
+        self.capturer.set_simulcast(simulcast);
+    }
+
+    /// Profondità dei buffer di cattura ed encoder: vale dal prossimo avvio
+    pub fn set_latency_profile(&mut self, profile: LatencyProfile) {
+        if self.init {
+            info!("Caster buffers can only be changed before casting starts");
+            return;
+        }
+        self.capturer.set_tuning(profile.tuning());
+    }
+
+    /// VBR della catena o bitrate costante, dal prossimo avvio dello stream
+    pub fn set_bitrate_mode(&self, mode: BitrateMode) {
+        self.capturer.set_bitrate_mode(mode);
+    }
+
+    /// Schermo fermo → un frame al secondo, il movimento riporta il frame rate pieno
+    pub fn set_content_aware(&self, enabled: bool) {
+        self.capturer.set_content_aware(enabled);
+    }
+
+    /// Tone-mapping dei desktop HDR, dal prossimo avvio dello stream
+    pub fn set_hdr_mode(&self, mode: HdrMode) {
+        self.capturer.set_hdr_mode(mode);
+    }
+
+    /// Slice dell'encoder entro `mtu`, dal prossimo encoder creato
+    pub fn set_rtp_mtu(&self, mtu: RtpMtu) {
+        let max_slice_size = mtu.max_slice_size();
+        self.capturer.set_max_slice_size(max_slice_size);
+        match max_slice_size {
+            Some(bytes) => info!("Encoder slice size: {} bytes", bytes),
+            None => info!("Encoder slices: off, RTP packetizer fragments frames"),
+        }
+    }
+
+    /// Finestre coperte di nero nello stream (parte del titolo o classe)
+    pub fn set_privacy_exclude(&mut self, patterns: Vec<String>) {
+        self.capturer.set_privacy_exclude(patterns);
+    }
+
+    // ── Data budget ─────────────────────────────────────────────
+
+    pub fn data_cap(&self) -> DataCap {
+        self.data_cap
+    }
+
+    /// Cambia il tetto di traffico, anche a stream avviato.
+    pub fn set_data_cap(&mut self, cap: DataCap) {
+        self.data_cap = cap;
+        self.budget_usage = self.capturer.set_data_budget(cap.mb_per_minute());
+    }
+
+    /// (MB/min stimati, tetto) quando un tetto è attivo.
+    pub fn data_usage(&self) -> Option<(f32, f32)> {
+        let usage = self.budget_usage.as_ref()?;
+        Some((usage.mb_per_minute(), self.data_cap.mb_per_minute()?))
+    }
+
+    /// Aggiorna il profilo concreto annunciato ai nuovi peer in fase di negoziazione.
+    fn announce_profile(&self) {
+        if !self.init || self.audio_only {
+            return;
         }
+        let handle = tokio::runtime::Handle::current();
+        let resolved =
+            tokio::task::block_in_place(|| handle.block_on(self.capturer.resolved_profile()));
+        info!(
+            "Announcing stream profile {}x{}@{}",
+            resolved.width, resolved.height, resolved.fps
+        );
+        self.server.set_stream_profile(resolved);
     }
 
     // ── Resize recording area ───────────────────────────────────
 
     pub fn resize_rec_area(&mut self, rect: ScreenRect) -> bool {
+        self.capturer.set_follow_window(false);
         let crop = if rect.width > 0.0 && rect.height > 0.0 {
             Some(CropRect::from(&rect))
         } else {
             None
         };
-        self.capturer.set_crop(crop);
+        if let Err(e) = self.capturer.set_crop(crop) {
+            error!("Ignoring recording area: {}", e);
+            return false;
+        }
+        self.announce_profile();
         true
     }
 
+    // ── Follow finestra attiva ──────────────────────────────────
+
+    /// Il crop segue la finestra in primo piano (solo Windows).
+    pub fn follow_active_window(&mut self) {
+        self.annotation_area = None;
+        self.capturer.set_follow_window(true);
+    }
+
+    pub fn is_following_window(&self) -> bool {
+        self.capturer.is_following_window()
+    }
+
+    pub fn is_follow_locked(&self) -> bool {
+        self.capturer.is_follow_locked()
+    }
+
+    pub fn toggle_follow_lock(&mut self) {
+        if self.capturer.is_following_window() {
+            self.capturer.toggle_follow_lock();
+        } else {
+            info!("Follow lock ignored: follow window mode is off");
+        }
+    }
+
+    // ── Annotazioni remote ──────────────────────────────────────
+
+    /// Registra l'area selezionata (non scalata per DPI) per mappare le
+    /// annotazioni sul frame trasmesso. Un rettangolo vuoto è lo schermo intero.
+    pub fn set_annotation_area(&mut self, rect: ScreenRect) {
+        self.annotation_area = (rect.width > 0.0 && rect.height > 0.0).then_some(rect);
+    }
+
+    /// Regione del canvas delle annotazioni che corrisponde al frame trasmesso.
+    pub fn annotation_area(&self, canvas: Size) -> Rectangle {
+        match &self.annotation_area {
+            Some(r) => Rectangle::new((r.x, r.y).into(), Size::new(r.width, r.height)),
+            None => Rectangle::with_size(canvas),
+        }
+    }
+
+    pub fn send_annotation(&self, event: AnnotationEvent) {
+        if self.init {
+            self.server.send_annotation(event);
+        }
+    }
+
+    // ── Chat ────────────────────────────────────────────────────
+
+    /// Messaggi della sessione, di tutti i receiver e del caster
+    pub fn chat_messages(&self) -> Vec<ChatMessage> {
+        self.server.chat_log().messages()
+    }
+
+    pub fn send_chat(&self, text: &str) {
+        if self.init
+            && let Some(message) = ChatMessage::new(&self.announced_name(), text)
+        {
+            self.server.send_chat(message);
+        }
+    }
+
+    // ── Clipboard ───────────────────────────────────────────────
+
+    /// `share`: invia ai receiver il testo copiato qui; `accept`: applica il loro
+    pub fn set_clipboard_sharing(&self, share: bool, accept: bool) {
+        self.server.set_clipboard_sharing(share, accept);
+    }
+
+    // ── Controllo remoto ────────────────────────────────────────
+
+    #[cfg(feature = "remote-control")]
+    pub fn is_remote_control_allowed(&self) -> bool {
+        self.server.remote_control().is_allowed()
+    }
+
+    /// Consente (o revoca) ai receiver di muovere il mouse del caster
+    #[cfg(feature = "remote-control")]
+    pub fn toggle_remote_control(&mut self) {
+        let control = self.server.remote_control();
+        if !control.is_allowed() {
+            self.refresh_control_target();
+        }
+        control.set_allowed(!control.is_allowed());
+    }
+
+    #[cfg(feature = "remote-control")]
+    fn refresh_control_target(&self) {
+        let Some(display) = self.get_selected_display() else {
+            return;
+        };
+        self.server.remote_control().set_target(
+            ScreenCaptureImpl::display_rect(&display),
+            self.capturer.opts(),
+        );
+    }
+
     // ── WebRTC ──────────────────────────────────────────────────
 
     pub fn get_connection_handler(&self) -> Arc<WebRTCServer> {
@@ -218,7 +901,13 @@ impl Caster {
         if self.init {
             self.pipeline_state = PipelineState::Stopping;
             self.stop_audio_capture();
+            if let Some(cancel) = self.stats_cancel.take() {
+                cancel.cancel();
+            }
             self.capturer.stop();
+            if self.streaming {
+                self.fire_webhook(WebhookEvent::StreamStop);
+            }
             self.server.close();
             self.init = false;
             self.streaming = false;
@@ -230,24 +919,27 @@ impl Caster {
 }
 
 impl Caster {
-    fn start_audio_capture(&mut self, initial_start: bool) {
+    fn start_audio_capture(&mut self) {
         let audio_cancel = CancellationToken::new();
-        match AudioCapture::start(audio_cancel.clone()) {
+        let start = if self.test_pattern {
+            TestTone::start
+        } else {
+            AudioCapture::start
+        };
+        match start(
+            audio_cancel.clone(),
+            self.audio_encode,
+            self.audio_level.clone(),
+            Arc::clone(&self.audio_muted),
+            self.clock.clone(),
+        ) {
             Ok(audio_rx) => {
-                self.server.get_handler().send_audio_frames(audio_rx);
-                if initial_start {
-                    info!("Audio capture started");
-                } else {
-                    info!("Audio unmuted");
-                }
-            }
-            Err(e) => {
-                if initial_start {
-                    error!("Failed to start audio capture: {}", e);
-                } else {
-                    error!("Failed to restart audio capture: {}", e);
-                }
+                self.server
+                    .get_handler()
+                    .send_audio_frames(audio_rx, self.audio_encode.frame_duration());
+                info!("Audio capture started (test tone: {})", self.test_pattern);
             }
+            Err(e) => error!("Failed to start audio capture: {}", e),
         }
         self.audio_cancel = Some(audio_cancel);
     }
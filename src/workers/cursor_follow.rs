@@ -0,0 +1,152 @@
+//! Cattura che segue il puntatore tra i monitor
+//!
+//! Il caster controlla su quale monitor si trova il puntatore e, se resta su
+//! un altro monitor per il ritardo scelto, passa a quello con lo stesso
+//! cambio al volo di `change_display`: stessa sessione e un IDR al cambio.
+//! Il ritardo evita di saltare avanti e indietro quando il puntatore
+//! attraversa un monitor solo per un attimo.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Permanenza del puntatore su un altro monitor prima del cambio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FollowCursorDelay(pub u32);
+
+impl FollowCursorDelay {
+    pub const PRESETS: [FollowCursorDelay; 4] = [
+        FollowCursorDelay(250),
+        FollowCursorDelay(500),
+        FollowCursorDelay(1000),
+        FollowCursorDelay(2000),
+    ];
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.0 as u64)
+    }
+}
+
+impl Default for FollowCursorDelay {
+    fn default() -> Self {
+        FollowCursorDelay(500)
+    }
+}
+
+impl fmt::Display for FollowCursorDelay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            ms if ms >= 1000 && ms % 1000 == 0 => write!(f, "After {} s", ms / 1000),
+            ms => write!(f, "After {} ms", ms),
+        }
+    }
+}
+
+/// Monitor candidato e da quando il puntatore ci si trova; i monitor sono
+/// indici nell'elenco tenuto dal caster
+#[derive(Debug, Default)]
+pub struct CursorFollow {
+    enabled: bool,
+    delay: FollowCursorDelay,
+    candidate: Option<(usize, Instant)>,
+}
+
+impl CursorFollow {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.reset();
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_delay(&mut self, delay: FollowCursorDelay) {
+        self.delay = delay;
+    }
+
+    /// Stream ripreso: il conto riparte da zero
+    pub fn reset(&mut self) {
+        self.candidate = None;
+    }
+
+    /// `current` è il monitor trasmesso, `under_cursor` quello col puntatore
+    /// (`None` se fuori da tutti o non si sa). Ritorna il monitor su cui
+    /// passare quando il puntatore ci è rimasto abbastanza.
+    pub fn tick(
+        &mut self,
+        current: Option<usize>,
+        under_cursor: Option<usize>,
+        now: Instant,
+    ) -> Option<usize> {
+        let target = match (current, under_cursor) {
+            (Some(current), Some(target)) if self.enabled && current != target => target,
+            _ => {
+                self.candidate = None;
+                return None;
+            }
+        };
+        match self.candidate {
+            Some((candidate, since)) if candidate == target => {
+                if now.duration_since(since) < self.delay.duration() {
+                    return None;
+                }
+                self.candidate = None;
+                Some(target)
+            }
+            _ => {
+                self.candidate = Some((target, now));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn switches_after_the_delay_on_the_new_monitor() {
+        let start = Instant::now();
+        let mut follow = CursorFollow::default();
+        follow.set_enabled(true);
+        follow.set_delay(FollowCursorDelay(500));
+
+        assert_eq!(follow.tick(Some(0), Some(0), start), None);
+        assert_eq!(follow.tick(Some(0), Some(1), start + 100 * MS), None);
+        assert_eq!(follow.tick(Some(0), Some(1), start + 400 * MS), None);
+        assert_eq!(follow.tick(Some(0), Some(1), start + 600 * MS), Some(1));
+    }
+
+    #[test]
+    fn crossing_back_restarts_the_delay() {
+        let start = Instant::now();
+        let mut follow = CursorFollow::default();
+        follow.set_enabled(true);
+
+        follow.tick(Some(0), Some(1), start);
+        // Di nuovo sul monitor trasmesso, poi su un terzo
+        assert_eq!(follow.tick(Some(0), Some(0), start + 300 * MS), None);
+        assert_eq!(follow.tick(Some(0), Some(2), start + 400 * MS), None);
+        assert_eq!(follow.tick(Some(0), Some(2), start + 700 * MS), None);
+        assert_eq!(follow.tick(Some(0), Some(2), start + 900 * MS), Some(2));
+    }
+
+    #[test]
+    fn disabled_or_unknown_never_switches() {
+        let start = Instant::now();
+        let mut follow = CursorFollow::default();
+        assert_eq!(follow.tick(Some(0), Some(1), start), None);
+        assert_eq!(follow.tick(Some(0), Some(1), start + 5000 * MS), None);
+
+        follow.set_enabled(true);
+        // Finestra o "All displays" trasmessi: niente da seguire
+        assert_eq!(follow.tick(None, Some(1), start), None);
+        assert_eq!(follow.tick(None, Some(1), start + 5000 * MS), None);
+        assert_eq!(follow.tick(Some(0), None, start + 6000 * MS), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Pausa automatica del caster senza receiver
+//!
+//! Con lo stream avviato ma nessun client connesso per il tempo scelto,
+//! cattura ed encoding vengono messi in pausa: il caster resta in ascolto e
+//! riparte appena un receiver si collega o l'utente torna a usare il PC.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Attesa senza receiver prima della pausa, scelta nelle impostazioni
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum IdleTimeout {
+    #[default]
+    Off,
+    Minutes(u32),
+}
+
+impl IdleTimeout {
+    pub const PRESETS: [IdleTimeout; 5] = [
+        IdleTimeout::Off,
+        IdleTimeout::Minutes(2),
+        IdleTimeout::Minutes(5),
+        IdleTimeout::Minutes(15),
+        IdleTimeout::Minutes(30),
+    ];
+
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            Self::Off => None,
+            Self::Minutes(minutes) => Some(Duration::from_secs(*minutes as u64 * 60)),
+        }
+    }
+}
+
+impl fmt::Display for IdleTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "Never pause"),
+            Self::Minutes(minutes) => write!(f, "After {} minutes alone", minutes),
+        }
+    }
+}
+
+/// Cosa deve fare il caster dopo un [`IdleWatch::tick`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleAction {
+    Pause,
+    Resume,
+}
+
+/// Stato dell'attesa, aggiornato una volta al secondo dal caster
+#[derive(Debug, Default)]
+pub struct IdleWatch {
+    timeout: IdleTimeout,
+    /// Ultimo client o input locale visto
+    last_activity: Option<Instant>,
+    paused: bool,
+}
+
+impl IdleWatch {
+    pub fn set_timeout(&mut self, timeout: IdleTimeout) {
+        self.timeout = timeout;
+        self.last_activity = None;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Input dell'utente sul PC del caster: riparte il conto
+    pub fn activity(&mut self, now: Instant) -> Option<IdleAction> {
+        self.last_activity = Some(now);
+        self.wake()
+    }
+
+    /// Stream fermato o ripreso a mano: nessuna pausa automatica in corso
+    pub fn reset(&mut self) {
+        self.last_activity = None;
+        self.paused = false;
+    }
+
+    /// `clients` connessi adesso; `None` finché non c'è niente da cambiare
+    pub fn tick(&mut self, clients: usize, now: Instant) -> Option<IdleAction> {
+        if clients > 0 {
+            self.last_activity = Some(now);
+            return self.wake();
+        }
+        // Timeout tolto durante la pausa: si riparte subito
+        let Some(timeout) = self.timeout.duration() else {
+            return self.wake();
+        };
+        let since = *self.last_activity.get_or_insert(now);
+        if !self.paused && now.duration_since(since) >= timeout {
+            self.paused = true;
+            return Some(IdleAction::Pause);
+        }
+        None
+    }
+
+    fn wake(&mut self) -> Option<IdleAction> {
+        std::mem::take(&mut self.paused).then_some(IdleAction::Resume)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn pauses_after_the_timeout_without_clients() {
+        let start = Instant::now();
+        let mut watch = IdleWatch::default();
+        watch.set_timeout(IdleTimeout::Minutes(2));
+
+        assert_eq!(watch.tick(0, start), None);
+        assert_eq!(watch.tick(0, start + MINUTE), None);
+        assert_eq!(watch.tick(0, start + 2 * MINUTE), Some(IdleAction::Pause));
+        assert!(watch.is_paused());
+        // Una sola pausa, non una per tick
+        assert_eq!(watch.tick(0, start + 3 * MINUTE), None);
+    }
+
+    #[test]
+    fn clients_and_input_restart_the_count() {
+        let start = Instant::now();
+        let mut watch = IdleWatch::default();
+        watch.set_timeout(IdleTimeout::Minutes(2));
+
+        watch.tick(0, start);
+        assert_eq!(watch.tick(1, start + MINUTE), None);
+        assert_eq!(watch.tick(0, start + 2 * MINUTE), None);
+        assert_eq!(watch.tick(0, start + 3 * MINUTE), Some(IdleAction::Pause));
+
+        assert_eq!(watch.activity(start + 4 * MINUTE), Some(IdleAction::Resume));
+        assert!(!watch.is_paused());
+        assert_eq!(watch.tick(0, start + 5 * MINUTE), None);
+        assert_eq!(watch.tick(0, start + 6 * MINUTE), Some(IdleAction::Pause));
+        assert_eq!(watch.tick(2, start + 7 * MINUTE), Some(IdleAction::Resume));
+    }
+
+    #[test]
+    fn off_never_pauses() {
+        let start = Instant::now();
+        let mut watch = IdleWatch::default();
+        assert_eq!(watch.tick(0, start), None);
+        assert_eq!(watch.tick(0, start + 600 * MINUTE), None);
+    }
+}
@@ -36,6 +36,9 @@ pub fn global_key_listener() -> impl Stream<Item = AppEvent> {
 
 struct KeyState {
     alt: bool,
+    /// AltGr compone caratteri, non scorciatoie: su Windows arriva
+    /// preceduto da un Ctrl sinistro sintetico
+    alt_gr: bool,
     control: bool,
     shift: bool,
     logo: bool,
@@ -45,6 +48,7 @@ impl KeyState {
     pub fn new() -> Self {
         KeyState {
             alt: false,
+            alt_gr: false,
             control: false,
             shift: false,
             logo: false,
@@ -59,6 +63,7 @@ impl KeyState {
             }
             EventType::KeyRelease(key) => match key {
                 RdevKey::Alt
+                | RdevKey::AltGr
                 | RdevKey::ShiftLeft
                 | RdevKey::ShiftRight
                 | RdevKey::ControlLeft
@@ -77,6 +82,7 @@ impl KeyState {
     fn set_modifier(&mut self, key: RdevKey, is_pressed: bool) {
         match key {
             RdevKey::Alt => self.alt = is_pressed,
+            RdevKey::AltGr => self.alt_gr = is_pressed,
             RdevKey::ShiftLeft | RdevKey::ShiftRight => self.shift = is_pressed,
             RdevKey::ControlLeft | RdevKey::ControlRight => self.control = is_pressed,
             RdevKey::MetaLeft | RdevKey::MetaRight => self.logo = is_pressed,
@@ -110,6 +116,10 @@ impl KeyState {
             RdevKey::Pause => icedKey::Named(Named::Pause),
             RdevKey::Return => icedKey::Named(Named::Enter),
             RdevKey::Escape => icedKey::Named(Named::Escape),
+            RdevKey::LeftArrow => icedKey::Named(Named::ArrowLeft),
+            RdevKey::RightArrow => icedKey::Named(Named::ArrowRight),
+            RdevKey::UpArrow => icedKey::Named(Named::ArrowUp),
+            RdevKey::DownArrow => icedKey::Named(Named::ArrowDown),
 
             _ => icedKey::Unidentified,
         }
@@ -165,10 +175,10 @@ impl KeyState {
 
     fn to_modifiers(&self) -> Modifiers {
         let mut modifiers = Modifiers::empty();
-        if self.alt {
+        if self.alt && !self.alt_gr {
             modifiers |= Modifiers::ALT;
         }
-        if self.control {
+        if self.control && !self.alt_gr {
             modifiers |= Modifiers::CTRL;
         }
         if self.shift {
@@ -4,6 +4,8 @@
 //! screen capture, streaming, receiving, and system integration.
 
 pub mod caster;
+pub mod cursor_follow;
+pub mod idle;
 pub mod key_listener;
 pub mod receiver;
 pub mod save_stream;
@@ -1,19 +1,48 @@
-use crate::decoder::{AudioPlayer, FfmpegDecoder, H264Depacketizer, VideoFrame};
+use crate::assets::FRAME_RATE;
+use crate::capture::StreamProfile;
+use crate::capture::display::primary_refresh_rate;
+use crate::config::OutputSettings;
+use crate::decoder::{AudioPlayer, FfmpegDecoder, H264Depacketizer, MAX_VOLUME, VideoFrame};
+use crate::display::DisplayPolicy;
 use crate::pipeline::clock::MediaClock;
-use crate::pipeline::health::PipelineHealth;
-use crate::pipeline::state::PipelineState;
-use crate::utils::net::common::find_caster;
-use crate::utils::net::webrtc::WebRTCReceiver;
+use crate::pipeline::health::{DropSource, HealthAlert, HealthMonitor, PipelineHealth};
+use crate::pipeline::metrics::{Stage, StageMetrics, glass_to_glass};
+use crate::pipeline::receiver::decode_stage::{MAX_CONSECUTIVE_FAILURES, MAX_DECODER_RESETS};
+use crate::pipeline::receiver::{
+    AvOffset, DecoderRecovery, DelayLine, ImpairmentConfig, LatencyGuard, LatencyProfile,
+    NetworkImpairment, Recovery,
+};
+use crate::pipeline::state::{ConnectionState, ConnectionStatus, PipelineState};
+use crate::pipeline::stats_log::{StatsLogTarget, spawn_stats_log};
+use crate::utils::audio_level::{AudioLevel, LevelSnapshot};
+use crate::utils::net::common::{DISCOVERY_WINDOW, default_instance_name, find_casters};
+use crate::utils::net::webhook::{WebhookEvent, Webhooks};
+use crate::utils::net::webrtc::{ChatMessage, RemoteStroke, WebRTCReceiver};
+#[cfg(feature = "remote-control")]
+use crate::utils::remote_control::RemoteInput;
 use crate::utils::sos::SignalOfStop;
 use crate::utils::{SendResult, try_send};
 use crate::workers::WorkerClose;
 use crate::workers::save_stream::{SavePacket, SaveStream};
+use castbox::Arw;
 use log::{error, info};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU8, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, mpsc};
+use tokio_util::sync::CancellationToken;
+
+/// Senza frame per questo tempo la riproduzione è considerata in stallo
+const STALL_THRESHOLD: Duration = Duration::from_secs(3);
+/// Tempo massimo in `Reconnecting` prima di dichiarare la connessione persa
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+/// Attesa predefinita per raggiungere il caster, in secondi
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u32 = 10;
+/// Attesa tra due tentativi di aprire l'uscita audio dopo un errore
+const PLAYER_RETRY: Duration = Duration::from_secs(2);
+/// Cadenze di presentazione accettate dal monitor, il resto è un valore sospetto
+const PRESENT_RATE_RANGE: std::ops::RangeInclusive<u32> = 24..=360;
 
 /// Return true if the H.264 access unit contains an IDR (nal type 5) or SPS/PPS (7/8).
 fn au_contains_idr_or_sps(au: &[u8]) -> bool {
@@ -41,19 +70,59 @@ fn au_contains_idr_or_sps(au: &[u8]) -> bool {
 pub struct Receiver {
     is_streaming: Arc<AtomicBool>,
     audio_muted: Arc<AtomicBool>,
+    /// Volume di riproduzione in percentuale, letto dal task audio
+    volume: Arc<AtomicU32>,
+    /// Livello dell'audio in riproduzione (VU meter)
+    audio_level: AudioLevel,
+    /// Dispositivo di uscita scelto (`None` = default di sistema)
+    output_device: Arw<Option<String>>,
+    /// Alzato quando cambia il dispositivo: il task audio ricrea il player
+    output_device_changed: Arc<AtomicBool>,
     save_stream: Option<SaveStream>,
     caster_addr: Option<SocketAddr>,
+    /// Oltre questo tempo senza raggiungere il caster la connessione fallisce,
+    /// `None` = attesa illimitata
+    connect_timeout: Option<Duration>,
+    /// Motivo dell'ultimo fallimento della connessione, da mostrare all'utente
+    connect_error: Arw<Option<String>>,
     /// Canale usato dal SaveStream per ricevere copie dei frame
     save_rx: Option<Arc<Mutex<mpsc::Receiver<SavePacket>>>>,
     local_sos: SignalOfStop,
     handler: Arc<WebRTCReceiver>,
+    /// Profilo (risoluzione + fps) annunciato dal caster in fase di negoziazione
+    stream_profile: Arw<Option<StreamProfile>>,
 
     // Pipeline integration
     clock: MediaClock,
     health: Arc<PipelineHealth>,
+    /// Latenza per stadio, mostrata dall'overlay di debug
+    metrics: Arc<StageMetrics>,
+    show_metrics: bool,
+    /// `LatencyProfile` corrente, letto dal task video ad ogni pacchetto
+    latency_profile: Arc<AtomicU8>,
+    /// `DisplayPolicy` corrente, letta dal componente Video ad ogni frame
+    display_policy: Arc<AtomicU8>,
+    /// Frame presentati a cadenza fissa tenendo l'ultimo, letto dal task video
+    smooth_motion: Arc<AtomicBool>,
+    /// Cadenza di presentazione in Hz: la frequenza del monitor, letta a ogni `launch`
+    present_rate: u32,
+    /// Correzione manuale del lip-sync, letta dai task audio e video
+    av_offset: AvOffset,
+    /// Oltre questo ritardo nei buffer si riparte dal keyframe successivo
+    latency_guard: LatencyGuard,
     pipeline_state: PipelineState,
+    /// Stato della connessione mostrato nella pagina del receiver
+    connection: ConnectionStatus,
     /// Audio playback position for A/V sync tracking
     audio_position: Arc<AtomicI64>,
+    /// Log periodico delle statistiche, avviato con la connessione
+    stats_log: Option<StatsLogTarget>,
+    stats_cancel: Option<CancellationToken>,
+    /// Notifiche HTTP di inizio/fine registrazione
+    webhooks: Webhooks,
+    /// Il mouse sul video viene inoltrato al caster
+    #[cfg(feature = "remote-control")]
+    controlling: bool,
 }
 
 impl Receiver {
@@ -64,15 +133,36 @@ impl Receiver {
         Self {
             is_streaming: Arc::new(AtomicBool::new(false)),
             audio_muted: Arc::new(AtomicBool::new(false)),
+            volume: Arc::new(AtomicU32::new(100)),
+            audio_level: AudioLevel::default(),
+            output_device: Arw::new(None),
+            output_device_changed: Arc::new(AtomicBool::new(false)),
             save_stream: None,
             caster_addr: None,
+            connect_timeout: Some(Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS as u64)),
+            connect_error: Arw::new(None),
             save_rx: None,
             local_sos: sos,
             handler: Arc::new(WebRTCReceiver::new()),
+            stream_profile: Arw::new(None),
             clock,
             health,
+            metrics: Arc::new(StageMetrics::new()),
+            show_metrics: false,
+            latency_profile: Arc::new(AtomicU8::new(LatencyProfile::default() as u8)),
+            display_policy: Arc::new(AtomicU8::new(DisplayPolicy::default() as u8)),
+            smooth_motion: Arc::new(AtomicBool::new(false)),
+            present_rate: FRAME_RATE,
+            av_offset: AvOffset::default(),
+            latency_guard: LatencyGuard::default(),
             pipeline_state: PipelineState::Idle,
+            connection: ConnectionStatus::default(),
             audio_position: Arc::new(AtomicI64::new(0)),
+            stats_log: None,
+            stats_cancel: None,
+            webhooks: Webhooks::default(),
+            #[cfg(feature = "remote-control")]
+            controlling: false,
         }
     }
 
@@ -86,85 +176,313 @@ impl Receiver {
         &self.health
     }
 
+    /// Get the per-stage latency metrics
+    pub fn metrics(&self) -> &Arc<StageMetrics> {
+        &self.metrics
+    }
+
     /// Get the current pipeline state
     pub fn pipeline_state(&self) -> &PipelineState {
         &self.pipeline_state
     }
 
+    /// Current connection state (negotiating, buffering, stalled, ...)
+    pub fn connection_state(&self) -> ConnectionState {
+        self.connection.get()
+    }
+
     /// Get the audio playback position for A/V sync tracking
     pub fn audio_position(&self) -> i64 {
         self.audio_position.load(Ordering::Relaxed)
     }
 
+    /// Shared handle to the stream profile announced by the caster.
+    pub fn stream_profile(&self) -> Arw<Option<StreamProfile>> {
+        Arw::clone(&self.stream_profile)
+    }
+
+    /// Snapshot of the annotations currently drawn by the caster.
+    pub fn annotations(&self) -> Vec<RemoteStroke> {
+        self.handler
+            .remote_annotations()
+            .map(|strokes| strokes.as_ref().clone())
+            .unwrap_or_default()
+    }
+
+    /// Chat con il caster, dall'inizio della sessione
+    pub fn chat_messages(&self) -> Vec<ChatMessage> {
+        self.handler.chat_log().messages()
+    }
+
+    pub fn send_chat(&self, text: &str) {
+        if let Some(message) = ChatMessage::new(&default_instance_name(), text) {
+            self.handler.send_chat(message);
+        }
+    }
+
+    /// `share`: invia al caster il testo copiato qui; `accept`: applica il suo
+    pub fn set_clipboard_sharing(&self, share: bool, accept: bool) {
+        self.handler.set_clipboard_sharing(share, accept);
+    }
+
+    #[cfg(feature = "remote-control")]
+    pub fn is_controlling(&self) -> bool {
+        self.controlling
+    }
+
+    /// Inizia/smette di inoltrare il mouse; il caster decide se applicarlo
+    #[cfg(feature = "remote-control")]
+    pub fn toggle_remote_control(&mut self) {
+        self.controlling = !self.controlling;
+    }
+
+    #[cfg(feature = "remote-control")]
+    pub fn send_input(&self, input: RemoteInput) {
+        if self.controlling {
+            self.handler.send_input(input);
+        }
+    }
+
+    pub fn latency_profile(&self) -> LatencyProfile {
+        LatencyProfile::from_u8(self.latency_profile.load(Ordering::Relaxed))
+    }
+
+    /// Applicabile anche durante lo stream: il jitter buffer si adegua al
+    /// pacchetto successivo.
+    pub fn set_latency_profile(&mut self, profile: LatencyProfile) {
+        self.latency_profile.store(profile as u8, Ordering::Relaxed);
+        info!("Receiver latency profile: {}", profile);
+    }
+
+    pub fn display_policy(&self) -> DisplayPolicy {
+        DisplayPolicy::from_u8(self.display_policy.load(Ordering::Relaxed))
+    }
+
+    /// Shared with the Video component, takes effect on the next frame
+    pub fn display_policy_ref(&self) -> Arc<AtomicU8> {
+        Arc::clone(&self.display_policy)
+    }
+
+    pub fn set_display_policy(&mut self, policy: DisplayPolicy) {
+        self.display_policy.store(policy as u8, Ordering::Relaxed);
+        info!("Receiver display policy: {}", policy);
+    }
+
+    pub fn is_smooth_motion(&self) -> bool {
+        self.smooth_motion.load(Ordering::Relaxed)
+    }
+
+    /// Cadenza fissa al posto di un frame per arrivo; effettivo dal prossimo frame
+    pub fn set_smooth_motion(&mut self, smooth: bool) {
+        self.smooth_motion.store(smooth, Ordering::Relaxed);
+        info!("Receiver smooth motion: {}", smooth);
+    }
+
+    /// Frequenza del monitor su cui si presentano i frame, per il componente Video
+    pub fn present_rate(&self) -> u32 {
+        self.present_rate
+    }
+
+    pub fn av_offset_ms(&self) -> i64 {
+        self.av_offset.ms()
+    }
+
+    /// > 0 ritarda il video, < 0 l'audio; effettivo dal prossimo frame
+    pub fn set_av_offset_ms(&mut self, offset_ms: i64) {
+        self.av_offset.set_ms(offset_ms);
+        info!(
+            "Receiver A/V offset: {}",
+            AvOffset::label(self.av_offset.ms())
+        );
+    }
+
+    /// 0 disattiva il controllo; effettivo dal prossimo pacchetto
+    pub fn set_max_latency_ms(&mut self, max_ms: u32) {
+        self.latency_guard.set_max_ms(max_ms);
+        info!("Receiver max latency: {} ms", max_ms);
+    }
+
     pub fn set_caster_addr(&mut self, addr: SocketAddr) {
         self.caster_addr = Some(addr);
     }
 
+    pub fn caster_addr(&self) -> Option<SocketAddr> {
+        self.caster_addr
+    }
+
+    /// 0 = attesa illimitata; effettivo dalla prossima connessione
+    pub fn set_connect_timeout_secs(&mut self, secs: u32) {
+        self.connect_timeout = (secs > 0).then(|| Duration::from_secs(secs as u64));
+    }
+
+    /// Errore della connessione fallita, una volta sola
+    pub fn take_connect_error(&self) -> Option<String> {
+        self.connect_error.as_mut().take()
+    }
+
     /// Avvia la connessione al caster e ritorna il canale con i frame
     /// video da renderizzare (al posto della vecchia Pipeline GStreamer).
     pub fn launch(&mut self, auto: bool) -> Option<mpsc::Receiver<VideoFrame>> {
         self.pipeline_state = PipelineState::Initializing;
-
+        self.connection.set(ConnectionState::Negotiating);
+
+        // Code dimensionate dal profilo di latenza scelto all'avvio
+        let tuning = self.latency_profile().tuning();
+        // Movimento fluido alla frequenza del monitor, FRAME_RATE se ignota
+        self.present_rate = primary_refresh_rate()
+            .filter(|hz| PRESENT_RATE_RANGE.contains(hz))
+            .unwrap_or(FRAME_RATE);
+        let present_interval = Duration::from_micros(1_000_000 / self.present_rate as u64);
         // Canale principale: WebRTC → display
-        // Increased capacity to prevent blocking when GUI is temporarily slow
-        // At 30fps: 1024 frames = ~34 second buffer
-        let (video_tx, video_rx) = mpsc::channel::<VideoFrame>(1024);
-        // Canale per il salvataggio stream (largo per evitare drop quando il muxer è lento)
-        let (save_tx, save_rx) = mpsc::channel::<SavePacket>(2048);
+        let (video_tx, video_rx) = mpsc::channel::<VideoFrame>(tuning.video_output);
+        // Canale per il salvataggio stream
+        let (save_tx, save_rx) = mpsc::channel::<SavePacket>(tuning.save_queue);
 
         self.save_rx = Some(Arc::new(Mutex::new(save_rx)));
 
+        if let Some(target) = self.stats_log.clone() {
+            let cancel = CancellationToken::new();
+            spawn_stats_log(
+                target,
+                self.health.clone(),
+                Some(self.metrics.clone()),
+                Some(self.handler.clone()),
+                cancel.clone(),
+            );
+            if let Some(previous) = self.stats_cancel.replace(cancel) {
+                previous.cancel();
+            }
+        }
+
         let is_streaming = Arc::clone(&self.is_streaming);
         let audio_muted = Arc::clone(&self.audio_muted);
+        let volume = Arc::clone(&self.volume);
+        let audio_level = self.audio_level.clone();
+        let output_device = Arw::clone(&self.output_device);
+        let output_device_changed = Arc::clone(&self.output_device_changed);
         let mut caster_addr = self.caster_addr;
         let handler = Arc::clone(&self.handler);
         let health = self.health.clone();
+        let metrics = self.metrics.clone();
+        let latency_profile = Arc::clone(&self.latency_profile);
+        let smooth_motion = Arc::clone(&self.smooth_motion);
+        let av_offset = self.av_offset.clone();
+        let latency_guard = self.latency_guard.clone();
         let audio_position = self.audio_position.clone();
+        let stream_profile = Arw::clone(&self.stream_profile);
+        let connection = self.connection.clone();
+        let connect_timeout = self.connect_timeout;
+        let connect_error = Arw::clone(&self.connect_error);
+        connect_error.as_mut().take();
 
         // Task di connessione + ricezione
         tokio::spawn(async move {
             // IMPORTANT: Set up receive channels BEFORE connecting
             // This ensures on_track handler is registered before SDP negotiation
-            // Increased RTP channel to 512 packets to handle network bursts
-            let (raw_tx, mut raw_rx) = mpsc::channel::<(Vec<u8>, bool, u16, u32)>(512);
+            let (raw_tx, raw_rx) = mpsc::channel::<(Vec<u8>, bool, u16, u32)>(tuning.packet_queue);
+            // Rete simulata tra la traccia e il riordino, solo con CASTIFY_IMPAIR
+            let mut raw_rx = match ImpairmentConfig::from_env() {
+                Some(config) => NetworkImpairment::new(config).spawn(raw_rx),
+                None => raw_rx,
+            };
             // Audio channel now includes RTP timestamp for proper timing
-            let (audio_tx, mut audio_rx) = mpsc::channel::<(Vec<u8>, u32)>(1024);
+            let (audio_tx, mut audio_rx) = mpsc::channel::<(Vec<u8>, u32)>(tuning.audio_queue);
 
             // Register channels first - this sets up the on_track handler
             handler.receive_video(raw_tx, audio_tx).await;
 
+            let fail = |reason: String| {
+                error!("Connection to the caster failed: {}", reason);
+                connect_error.as_mut().replace(reason);
+                connection.set(ConnectionState::Failed);
+            };
+
             // Auto-discovery del caster se necessario
             if auto {
                 if caster_addr.is_none() {
-                    caster_addr = find_caster();
+                    // Auto: primo caster in ordine di nome
+                    caster_addr = tokio::task::spawn_blocking(|| find_casters(DISCOVERY_WINDOW))
+                        .await
+                        .ok()
+                        .and_then(|casters| casters.into_iter().next())
+                        .map(|(_, addr)| addr);
                 }
 
                 if let Some(socket_addr) = caster_addr {
                     let addr = format!("ws://{}", socket_addr);
                     info!("Connecting to caster at {}", addr);
 
-                    if let Err(e) = handler.connect(&addr).await {
-                        error!("Failed to connect to caster: {}", e);
+                    // Il limite copre websocket e negoziazione, fino alla
+                    // connessione WebRTC stabilita
+                    let connect = handler.connect(&addr);
+                    let result =
+                        match connect_timeout {
+                            Some(limit) => tokio::time::timeout(limit, connect)
+                                .await
+                                .unwrap_or_else(|_| {
+                                    Err(format!(
+                                        "Could not reach caster at {} within {} s",
+                                        socket_addr,
+                                        limit.as_secs()
+                                    )
+                                    .into())
+                                }),
+                            None => connect.await,
+                        };
+                    if let Err(e) = result {
+                        fail(e.to_string());
                         return;
                     }
                 } else {
-                    error!("No caster found");
+                    fail(String::from("No caster found"));
                     return;
                 }
             }
+            connection.set(ConnectionState::Connecting);
 
             if !handler.is_connected().await {
-                error!("Not connected to caster");
+                fail(String::from("Not connected to caster"));
                 return;
             }
 
             is_streaming.store(true, Ordering::Relaxed);
+            connection.set(ConnectionState::Buffering);
             info!("Streaming started");
 
+            spawn_connection_watchdog(
+                health.clone(),
+                connection.clone(),
+                Arc::clone(&handler),
+                Arc::clone(&is_streaming),
+            );
+
+            // The caster's profile arrives with its offer: poll briefly until the
+            // negotiation has delivered it, so the video component can size itself.
+            let handler_profile = Arc::clone(&handler);
+            tokio::spawn(async move {
+                for _ in 0..100 {
+                    if let Some(profile) = handler_profile.remote_profile() {
+                        info!(
+                            "Caster stream profile: {}x{}@{}",
+                            profile.width, profile.height, profile.fps
+                        );
+                        stream_profile.as_mut().replace(profile);
+                        break;
+                    }
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                }
+            });
+
             let save_tx_video = save_tx.clone();
             let health_video = health.clone();
-            // Share first video playout origin with audio task for sync
-            let (first_video_start_tx, mut first_video_start_rx) = mpsc::channel::<Instant>(1);
+            let handler_video = Arc::clone(&handler);
+            let connection_video = connection.clone();
+            let av_offset_video = av_offset.clone();
+
+            // Share first video playout origin with audio task for sync, together
+            // with how far the first keyframe is from the first video packet (µs)
+            let (first_video_start_tx, mut first_video_start_rx) =
+                mpsc::channel::<(Instant, i64)>(1);
 
             // Frame reordering pool
             let video_task = tokio::spawn(async move {
@@ -178,28 +496,102 @@ impl Receiver {
                         return;
                     }
                 };
+                metrics.set_decoder(decoder.label());
 
-                let mut consecutive_failures: u32 = 0;
+                let mut recovery = DecoderRecovery::default();
                 // Start rendering only after we received a keyframe (IDR) or SPS/PPS
                 let mut waiting_for_keyframe = true;
                 // Track first RTP timestamp for proper timestamp normalization
                 let mut first_rtp_timestamp: Option<u32> = None;
+                // Primo pacchetto in ordine: è quello ancorato al MediaClock del caster
+                let mut first_packet_rtp: Option<u32> = None;
+                // Istante del primo keyframe (PTS 0), per la stima glass-to-glass
+                let mut first_video_origin: Option<Instant> = None;
 
-                const MAX_REORDERING: u16 = 30; // Maximum expected packet reordering
-                const MAX_BUFFER_SIZE: usize = 60; // Maximum buffered packets before cleanup (reduced from 200)
-                // Buffer now stores: (payload, marker, rtp_timestamp)
+                // Buffer now stores: (payload, marker, rtp_timestamp, received_at)
                 let mut frame_buffer =
-                    std::collections::HashMap::<u16, (Vec<u8>, bool, u32)>::new();
+                    std::collections::HashMap::<u16, (Vec<u8>, bool, u32, Instant)>::new();
                 let mut expected_seq = None;
                 let mut last_packet_time = Instant::now();
                 let mut total_packets_received = 0u64;
                 let mut last_stats_log = Instant::now();
 
-                loop {
+                // Correzione manuale del lip-sync: con AvOffset > 0 i frame decodificati
+                // attendono qui prima di arrivare al display
+                let mut pending_video = DelayLine::<VideoFrame>::default();
+                // Movimento fluido: i frame escono solo al tick di presentazione,
+                // l'ultimo arrivato sostituisce quelli non ancora mostrati
+                let mut present = tokio::time::interval(present_interval);
+                present.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                let mut latest: Option<VideoFrame> = None;
+
+                // Use try_send to avoid blocking the processing loop
+                // If the display channel is full, drop the frame rather than stall the pipeline
+                let show = |frame: VideoFrame| match try_send(&video_tx, frame) {
+                    SendResult::Sent => {
+                        metrics.record_queue(
+                            Stage::Display,
+                            video_tx.max_capacity() - video_tx.capacity(),
+                        );
+                        true
+                    }
+                    SendResult::Full => {
+                        // Channel full, drop frame - this is better than blocking
+                        // the entire decode pipeline
+                        health_video.record_frame_drop(DropSource::Display);
+                        log::warn!("Video display channel full, dropping frame");
+                        true
+                    }
+                    SendResult::Closed => {
+                        info!("Video display channel closed, stopping");
+                        false
+                    }
+                };
+                // Frame con l'offset A/V scaduto: al display subito o, col movimento
+                // fluido, al prossimo tick. `false` se il display si è chiuso
+                let release = |pending: &mut DelayLine<VideoFrame>,
+                               latest: &mut Option<VideoFrame>| {
+                    let now = Instant::now();
+                    // Il ritardo voluto dall'offset A/V non conta come accumulo
+                    if let Some(due) = pending.next_due() {
+                        latency_guard.report_sync(now.saturating_duration_since(due));
+                    }
+                    while let Some(frame) = pending.pop_due(now) {
+                        if !smooth_motion.load(Ordering::Relaxed) {
+                            if !show(frame) {
+                                return false;
+                            }
+                        } else if latest.replace(frame).is_some() {
+                            // Sostituito prima del tick: non verrà mai mostrato
+                            health_video.record_frame_drop(DropSource::Sync);
+                        }
+                    }
+                    true
+                };
+
+                'receive: loop {
                     // Check for stream timeout every second
-                    let recv_result =
-                        tokio::time::timeout(std::time::Duration::from_secs(1), raw_rx.recv())
-                            .await;
+                    let recv_result = tokio::select! {
+                        result = tokio::time::timeout(Duration::from_secs(1), raw_rx.recv()) => {
+                            result
+                        }
+                        _ = tokio::time::sleep_until(
+                            pending_video.next_due().unwrap_or_else(Instant::now).into()
+                        ), if !pending_video.is_empty() => {
+                            if !release(&mut pending_video, &mut latest) {
+                                break 'receive;
+                            }
+                            continue;
+                        }
+                        _ = present.tick(), if latest.is_some() => {
+                            if let Some(frame) = latest.take()
+                                && !show(frame)
+                            {
+                                break 'receive;
+                            }
+                            continue;
+                        }
+                    };
 
                     let (payload, marker, seq_num, rtp_timestamp) = match recv_result {
                         Ok(Some(packet)) => {
@@ -224,7 +616,10 @@ impl Receiver {
                         Err(_) => {
                             // Timeout - check if stream has stalled
                             let elapsed = last_packet_time.elapsed().as_secs();
-                            if elapsed >= 5 && elapsed.is_multiple_of(5) {
+                            if elapsed >= 5
+                                && elapsed.is_multiple_of(5)
+                                && !handler_video.is_audio_only()
+                            {
                                 error!(
                                     "RECEIVER: No video packets for {} seconds! Buffer: {}, expected: {:?}, total: {}",
                                     elapsed,
@@ -247,7 +642,43 @@ impl Receiver {
                         );
                         last_stats_log = Instant::now();
                     }
-                    frame_buffer.insert(seq_num, (payload, marker, rtp_timestamp));
+                    frame_buffer.insert(seq_num, (payload, marker, rtp_timestamp, Instant::now()));
+
+                    // Troppo ritardo accumulato: si scarta tutto e si riparte dal
+                    // prossimo keyframe invece di riprodurre l'arretrato. I pacchetti
+                    // rimasti dietro `expected_seq` non verranno mai riprodotti e non contano
+                    let oldest = frame_buffer
+                        .iter()
+                        .filter(|&(&seq, _)| {
+                            expected_seq.is_none_or(|exp| seq.wrapping_sub(exp) < u16::MAX / 2)
+                        })
+                        .map(|(_, (_, _, _, received_at))| received_at.elapsed())
+                        .max()
+                        .unwrap_or_default();
+                    latency_guard.report_reorder(oldest);
+                    if latency_guard.should_reset() {
+                        log::warn!(
+                            "{:?} buffered (max {} ms), skipping to the next keyframe",
+                            latency_guard.buffered(),
+                            latency_guard.max_ms()
+                        );
+                        latency_guard.trigger();
+                        health_video.record_latency_reset();
+                        health_video
+                            .record_frame_drops(DropSource::Reorder, frame_buffer.len() as u64);
+                        frame_buffer.clear();
+                        expected_seq = None;
+                        depacketizer.reset();
+                        waiting_for_keyframe = true;
+                        // Senza richiesta si aspetterebbe il prossimo keyframe periodico
+                        handler_video.request_keyframe().await;
+                        continue;
+                    }
+
+                    // Finestra di riordino e attesa massima dal profilo di latenza
+                    let reorder = LatencyProfile::from_u8(latency_profile.load(Ordering::Relaxed))
+                        .reorder_config();
+                    let max_reordering = reorder.max_reorder_distance;
 
                     // Determine expected sequence number
                     if expected_seq.is_none() {
@@ -255,14 +686,14 @@ impl Receiver {
                     }
 
                     // Cleanup stale entries if buffer grows too large
-                    if frame_buffer.len() > MAX_BUFFER_SIZE
+                    if frame_buffer.len() > reorder.max_buffer_size
                         && let Some(exp) = expected_seq
                     {
                         // Remove packets that are too old (beyond reordering window)
                         frame_buffer.retain(|&seq, _| {
                             let diff = seq.wrapping_sub(exp);
                             // Keep packets within the reordering window ahead or behind expected
-                            diff <= MAX_REORDERING || diff >= (u16::MAX - MAX_REORDERING)
+                            diff <= max_reordering || diff >= (u16::MAX - max_reordering)
                         });
                         log::warn!(
                             "Buffer overflow: cleaned up stale packets, {} remain",
@@ -273,7 +704,15 @@ impl Receiver {
                     // Process in-order packets
                     let mut processed_count = 0;
                     while let Some(exp) = expected_seq {
-                        if let Some((data, marker_bit, rtp_ts)) = frame_buffer.remove(&exp) {
+                        if let Some((data, marker_bit, rtp_ts, received_at)) =
+                            frame_buffer.remove(&exp)
+                        {
+                            metrics.record(
+                                Stage::Reorder,
+                                received_at.elapsed(),
+                                frame_buffer.len(),
+                            );
+                            first_packet_rtp.get_or_insert(rtp_ts);
                             // Depacketize and reassemble frames
                             if let Some(h264_au) = depacketizer.push(&data, marker_bit) {
                                 // Use RTP timestamp for proper timing
@@ -283,7 +722,15 @@ impl Receiver {
                                     first_rtp_timestamp = Some(rtp_ts);
                                     // Send first video start instant to audio task for sync.
                                     // Audio and video RTP clocks are independent, so use a shared local origin.
-                                    let _ = first_video_start_tx.send(Instant::now()).await;
+                                    let origin = Instant::now();
+                                    first_video_origin = Some(origin);
+                                    let key_offset_us = first_packet_rtp
+                                        .map(|first| {
+                                            rtp_ts.wrapping_sub(first) as i64 * 1_000_000 / 90_000
+                                        })
+                                        .unwrap_or(0);
+                                    let _ =
+                                        first_video_start_tx.send((origin, key_offset_us)).await;
                                 }
 
                                 // Calculate normalized timestamp relative to first frame
@@ -312,6 +759,14 @@ impl Receiver {
                                     }
                                 }
 
+                                // Il token della sessione precede ogni keyframe: il muxer
+                                // capisce se dopo una caduta sta parlando con lo stesso caster
+                                if au_contains_idr_or_sps(&h264_au)
+                                    && let Some(session) = handler_video.remote_session()
+                                {
+                                    let _ = try_send(&save_tx_video, SavePacket::Session(session));
+                                }
+
                                 // Send to save channel only after we have a valid timestamp
                                 // Use try_send to avoid blocking the video processing loop
                                 if try_send(
@@ -324,42 +779,76 @@ impl Receiver {
                                 }
 
                                 // Decode H.264 → packed YUV420p (GPU converts to RGB)
-                                if let Some((yuv, w, h)) = decoder.decode(&h264_au) {
-                                    consecutive_failures = 0;
+                                let decode_start = Instant::now();
+                                let decoded = decoder.decode(&h264_au);
+                                metrics.record(Stage::Decode, decode_start.elapsed(), raw_rx.len());
+                                if let Some((yuv, w, h)) = decoded {
+                                    recovery.on_success();
+                                    if let Some(origin) = first_video_origin {
+                                        metrics.record_glass_to_glass(glass_to_glass(
+                                            origin,
+                                            ts_us,
+                                            Instant::now(),
+                                        ));
+                                    }
                                     let is_key = au_contains_idr_or_sps(&h264_au);
                                     health_video.record_frame(yuv.len(), is_key);
+                                    if connection_video.get() != ConnectionState::Playing {
+                                        connection_video.set(ConnectionState::Playing);
+                                    }
                                     let frame = VideoFrame {
                                         data: yuv,
                                         width: w as u32,
                                         height: h as u32,
+                                        layout: decoder.layout(),
                                     };
-                                    // Use try_send to avoid blocking the processing loop
-                                    // If the display channel is full, drop the frame rather than stall the pipeline
-                                    match try_send(&video_tx, frame) {
-                                        SendResult::Sent => {}
-                                        SendResult::Full => {
-                                            // Channel full, drop frame - this is better than blocking
-                                            // the entire decode pipeline
+                                    pending_video.push(
+                                        frame,
+                                        Instant::now() + av_offset_video.video_delay(),
+                                    );
+                                    if !release(&mut pending_video, &mut latest) {
+                                        break 'receive;
+                                    }
+                                } else {
+                                    health_video.record_decode_failure();
+                                    match recovery.on_failure() {
+                                        Recovery::Continue => {}
+                                        Recovery::Reinit => {
                                             log::warn!(
-                                                "Video display channel full, dropping frame"
+                                                "{} consecutive decode failures, recreating decoder (waiting for IDR)",
+                                                MAX_CONSECUTIVE_FAILURES
                                             );
+                                            // Un decoder hardware che non ha mai prodotto
+                                            // frame lascia il posto al successivo della catena
+                                            decoder.skip_if_unusable();
+                                            decoder = match FfmpegDecoder::new() {
+                                                Ok(d) => d,
+                                                Err(e) => {
+                                                    error!(
+                                                        "Failed to recreate H.264 decoder: {}",
+                                                        e
+                                                    );
+                                                    connection_video.set(ConnectionState::Failed);
+                                                    break 'receive;
+                                                }
+                                            };
+                                            metrics.set_decoder(decoder.label());
+                                            depacketizer.reset();
+                                            waiting_for_keyframe = true;
+                                            connection_video.set(ConnectionState::Buffering);
+                                            // Senza richiesta si aspetterebbe il prossimo
+                                            // keyframe periodico
+                                            handler_video.request_keyframe().await;
                                         }
-                                        SendResult::Closed => {
-                                            info!("Video display channel closed, stopping");
-                                            break;
+                                        Recovery::GiveUp => {
+                                            error!(
+                                                "Decoder still failing after {} re-creations, giving up",
+                                                MAX_DECODER_RESETS
+                                            );
+                                            connection_video.set(ConnectionState::Failed);
+                                            break 'receive;
                                         }
                                     }
-                                } else {
-                                    consecutive_failures += 1;
-                                    health_video.record_decode_failure();
-                                    if consecutive_failures >= 10 {
-                                        log::warn!(
-                                            "10 consecutive decode failures, resetting depacketizer (waiting for IDR)"
-                                        );
-                                        depacketizer.reset();
-                                        consecutive_failures = 0;
-                                        waiting_for_keyframe = true;
-                                    }
                                 }
                             }
                             expected_seq = Some(exp.wrapping_add(1));
@@ -374,10 +863,28 @@ impl Receiver {
                             let diff = seq_num.wrapping_sub(exp);
 
                             // If current packet is far ahead, consider expected packet lost
-                            if diff > 0 && diff <= MAX_REORDERING {
-                                // Current packet is ahead but within window - keep waiting
-                                break;
-                            } else if diff > MAX_REORDERING && diff < (u16::MAX - MAX_REORDERING) {
+                            if diff > 0 && diff <= max_reordering {
+                                // Current packet is ahead but within window - keep waiting,
+                                // unless something has been held longer than the jitter delay
+                                // Solo i pacchetti davanti al buco: quelli rimasti indietro
+                                // non dicono nulla su quanto si sta aspettando `exp`
+                                let longest_wait = frame_buffer
+                                    .iter()
+                                    .filter(|&(&seq, _)| seq.wrapping_sub(exp) <= max_reordering)
+                                    .map(|(_, (_, _, _, received_at))| received_at.elapsed())
+                                    .max()
+                                    .unwrap_or_default();
+                                if longest_wait < reorder.jitter_delay {
+                                    break;
+                                }
+                                log::debug!(
+                                    "Packet {} not received within {:?}, skipping",
+                                    exp,
+                                    reorder.jitter_delay
+                                );
+                                health_video.record_frame_drop(DropSource::Reorder);
+                                expected_seq = Some(exp.wrapping_add(1));
+                            } else if diff > max_reordering && diff < (u16::MAX - max_reordering) {
                                 // Packet lost or severely delayed - skip it
                                 log::warn!(
                                     "Packet lost or delayed: expected {}, got {} (diff={})",
@@ -385,6 +892,7 @@ impl Receiver {
                                     seq_num,
                                     diff
                                 );
+                                health_video.record_frame_drop(DropSource::Reorder);
                                 expected_seq = Some(exp.wrapping_add(1));
                                 // Continue to try next sequence number
                             } else {
@@ -401,34 +909,74 @@ impl Receiver {
                 );
             });
 
-            // Audio playback: decode Opus and play via cpal
-            let audio_player = match AudioPlayer::new() {
-                Ok(p) => Some(p),
-                Err(e) => {
-                    error!("Failed to create audio player: {}", e);
-                    None
+            // Audio playback: decode Opus (or raw PCM) and play via cpal
+            let open_player = move || {
+                let device = output_device.as_ref().clone();
+                match AudioPlayer::with_device(device.as_deref(), audio_level.clone()) {
+                    Ok(p) => Some(p),
+                    Err(e) => {
+                        error!("Failed to create audio player: {}", e);
+                        None
+                    }
                 }
             };
+            output_device_changed.store(false, Ordering::Relaxed);
+            let audio_player = open_player();
             let save_tx_audio = save_tx.clone();
             let audio_pos_ref = audio_position;
+            let handler_audio = Arc::clone(&handler);
+            let connection_audio = connection.clone();
             // Get first video start instant for sync
             let audio_task = tokio::spawn(async move {
                 let mut player = audio_player;
+                let mut player_opened_at = Instant::now();
                 let mut first_video_start: Option<Instant> = None;
+                // Distanza del primo keyframe dal primo pacchetto video (µs)
+                let mut video_key_offset_us: Option<i64> = None;
                 let mut first_audio_rtp_ts: Option<u32> = None;
                 let mut first_audio_anchor_us: Option<i64> = None;
+                // Ancora presa dal MediaClock del caster invece che dall'arrivo
+                let mut clock_aligned = false;
+                let mut pending_audio = DelayLine::<Vec<u8>>::default();
+                // PCM o Opus, dal codec della traccia audio: non cambia nella sessione
+                let mut low_latency: Option<bool> = None;
+                // Play audio (if not muted)
+                let play = |player: &mut Option<AudioPlayer>, data: &[u8]| {
+                    if !audio_muted.load(Ordering::Relaxed)
+                        && let Some(p) = player
+                    {
+                        p.set_volume(volume.load(Ordering::Relaxed) as f32 / 100.0);
+                        if let Err(e) = p.play(data) {
+                            log::warn!("Audio playback error: {}", e);
+                        }
+                    }
+                };
 
                 loop {
                     tokio::select! {
                         // Wait for audio packet
                         Some((audio_data, rtp_timestamp)) = audio_rx.recv() => {
+                            // Il primo pacchetto ricevuto è quello ancorato dal caster
+                            let audio_first_rtp = *first_audio_rtp_ts.get_or_insert(rtp_timestamp);
+                            let diff_us =
+                                rtp_timestamp.wrapping_sub(audio_first_rtp) as i64 * 1_000_000 / 48_000;
+
                             // Get first video start instant if not already received
                             if first_video_start.is_none() {
                                 match first_video_start_rx.try_recv() {
-                                    Ok(start) => first_video_start = Some(start),
+                                    Ok((start, key_offset_us)) => {
+                                        first_video_start = Some(start);
+                                        video_key_offset_us = Some(key_offset_us);
+                                    }
                                     Err(mpsc::error::TryRecvError::Empty) => {
-                                        // Still waiting for first video packet - skip this audio packet
-                                        continue;
+                                        if handler_audio.is_audio_only() {
+                                            // No video will ever arrive: audio defines the timeline
+                                            first_video_start = Some(Instant::now());
+                                            connection_audio.set(ConnectionState::Playing);
+                                        } else {
+                                            // Still waiting for first video packet - skip this audio packet
+                                            continue;
+                                        }
                                     }
                                     Err(mpsc::error::TryRecvError::Disconnected) => {
                                         // Video task closed - exit audio task
@@ -437,22 +985,32 @@ impl Receiver {
                                 }
                             }
 
-                            // Initialize first audio RTP timestamp and anchor on video timeline
-                            if first_audio_rtp_ts.is_none() {
-                                first_audio_rtp_ts = Some(rtp_timestamp);
-                                if let Some(video_start) = first_video_start {
-                                    first_audio_anchor_us = Some(video_start.elapsed().as_micros() as i64);
+                            // Anchor the audio timeline on the video one. With the caster
+                            // clock anchors both tracks sit on the caster MediaClock; without
+                            // them (manual SDP, older casters) the first audio packet played
+                            // is placed at its arrival time.
+                            if !clock_aligned
+                                && let Some(key_offset_us) = video_key_offset_us
+                                && let Some(audio_offset_us) = handler_audio.remote_clock().audio_offset_us()
+                            {
+                                if first_audio_anchor_us.is_some() {
+                                    log::info!("Audio timeline re-anchored on the caster clock");
                                 }
+                                first_audio_anchor_us = Some(audio_offset_us - key_offset_us);
+                                clock_aligned = true;
+                            }
+                            if first_audio_anchor_us.is_none()
+                                && let Some(video_start) = first_video_start
+                            {
+                                first_audio_anchor_us =
+                                    Some(video_start.elapsed().as_micros() as i64 - diff_us);
                             }
 
                             // Audio RTP uses a different clock domain than video RTP.
                             // Build audio timeline from audio RTP deltas and align it to video start.
-                            let ts_us = match (first_audio_rtp_ts, first_audio_anchor_us) {
-                                (Some(audio_first_rtp), Some(anchor_us)) => {
-                                    let diff = rtp_timestamp.wrapping_sub(audio_first_rtp) as i64;
-                                    anchor_us + (diff * 1_000_000) / 48_000
-                                }
-                                _ => 0,
+                            let ts_us = match first_audio_anchor_us {
+                                Some(anchor_us) => anchor_us + diff_us,
+                                None => 0,
                             };
 
                             // Ensure timestamp is not negative
@@ -461,30 +1019,62 @@ impl Receiver {
                             // Update audio position for A/V sync tracking
                             audio_pos_ref.store(ts_us, Ordering::Relaxed);
 
-                            // Prefer non-blocking send, but don't drop audio packets while saving.
-                            let save_packet = SavePacket::Audio(audio_data.clone(), ts_us);
-                            match save_tx_audio.try_send(save_packet) {
-                                Ok(()) => {}
-                                Err(mpsc::error::TrySendError::Full(packet)) => {
-                                    if save_tx_audio.send(packet).await.is_err() {
+                            // La registrazione accetta solo Opus: l'audio PCM non viene salvato
+                            let low_latency =
+                                *low_latency.get_or_insert_with(|| handler_audio.is_low_latency_audio());
+                            if !low_latency {
+                                // Prefer non-blocking send, but don't drop audio packets while saving.
+                                let save_packet = SavePacket::Audio(audio_data.clone(), ts_us);
+                                match save_tx_audio.try_send(save_packet) {
+                                    Ok(()) => {}
+                                    Err(mpsc::error::TrySendError::Full(packet)) => {
+                                        if save_tx_audio.send(packet).await.is_err() {
+                                            log::warn!("Save channel closed (audio)");
+                                        }
+                                    }
+                                    Err(mpsc::error::TrySendError::Closed(_)) => {
                                         log::warn!("Save channel closed (audio)");
                                     }
                                 }
-                                Err(mpsc::error::TrySendError::Closed(_)) => {
-                                    log::warn!("Save channel closed (audio)");
-                                }
                             }
 
-                            // Play audio (if not muted)
-                            if !audio_muted.load(Ordering::Relaxed)
-                                && let Some(ref mut p) = player
-                                    && let Err(e) = p.play(&audio_data) {
-                                        log::warn!("Audio playback error: {}", e);
-                                    }
+                            // Nuovo dispositivo scelto, quello corrente è sparito o
+                            // l'apertura precedente era fallita
+                            let device_lost = player.as_ref().is_some_and(|p| p.is_device_lost());
+                            let retry =
+                                player.is_none() && player_opened_at.elapsed() >= PLAYER_RETRY;
+                            if output_device_changed.swap(false, Ordering::Relaxed)
+                                || device_lost
+                                || retry
+                            {
+                                // Il vecchio stream va chiuso prima di aprire il nuovo
+                                drop(player.take());
+                                player = open_player();
+                                player_opened_at = Instant::now();
+                            }
+                            if let Some(p) = player.as_mut() {
+                                p.set_low_latency(low_latency);
+                            }
+
+                            // Correzione manuale del lip-sync: con AvOffset < 0 l'audio
+                            // attende qui prima di arrivare al dispositivo
+                            let now = Instant::now();
+                            pending_audio.push(audio_data, now + av_offset.audio_delay());
+                            while let Some(data) = pending_audio.pop_due(now) {
+                                play(&mut player, &data);
+                            }
+                        },
+                        _ = tokio::time::sleep_until(
+                            pending_audio.next_due().unwrap_or_else(Instant::now).into()
+                        ), if !pending_audio.is_empty() => {
+                            while let Some(data) = pending_audio.pop_due(Instant::now()) {
+                                play(&mut player, &data);
+                            }
                         },
                         // Wait for first video start origin if we haven't received any audio yet
-                        Some(start) = first_video_start_rx.recv() => {
+                        Some((start, key_offset_us)) = first_video_start_rx.recv() => {
                             first_video_start = Some(start);
+                            video_key_offset_us = Some(key_offset_us);
                             log::info!("Audio task received first video origin");
                         }
                     }
@@ -502,6 +1092,10 @@ impl Receiver {
             }
 
             is_streaming.store(false, Ordering::Relaxed);
+            // Fine inattesa dello stream (una chiusura voluta porta già a Idle)
+            if connection.get() != ConnectionState::Idle {
+                connection.set(ConnectionState::Failed);
+            }
             info!("Streaming ended. Final health: {}", health.summary());
         });
 
@@ -517,12 +1111,33 @@ impl Receiver {
         self.is_streaming.load(Ordering::Relaxed)
     }
 
+    /// Il caster trasmette solo audio: niente frame video da mostrare.
+    pub fn is_audio_only(&self) -> bool {
+        self.handler.is_audio_only()
+    }
+
     pub fn is_saving(&self) -> bool {
         self.save_stream.as_ref().is_some_and(|s| s.is_saving())
     }
 
+    // ── Overlay di debug ────────────────────────────────────────
+
+    pub fn is_metrics_overlay_visible(&self) -> bool {
+        self.show_metrics
+    }
+
+    pub fn toggle_metrics_overlay(&mut self) {
+        self.show_metrics = !self.show_metrics;
+        info!("Receiver metrics overlay: {}", self.show_metrics);
+    }
+
     // ── Audio mute ──────────────────────────────────────────────
 
+    /// Livello dell'audio riprodotto, per il VU meter.
+    pub fn audio_level(&self) -> LevelSnapshot {
+        self.audio_level.snapshot()
+    }
+
     pub fn is_audio_muted(&self) -> bool {
         self.audio_muted.load(Ordering::Relaxed)
     }
@@ -533,22 +1148,78 @@ impl Receiver {
         info!("Receiver audio muted: {}", muted);
     }
 
+    pub fn volume_percent(&self) -> u32 {
+        self.volume.load(Ordering::Relaxed)
+    }
+
+    /// Volume di riproduzione (100 = invariato), effettivo dal prossimo pacchetto
+    pub fn set_volume_percent(&mut self, percent: u32) {
+        let percent = percent.min((MAX_VOLUME * 100.0) as u32);
+        self.volume.store(percent, Ordering::Relaxed);
+    }
+
+    /// File in cui scrivere le statistiche alla prossima connessione, `None` per non scriverle
+    pub fn set_stats_log(&mut self, target: Option<StatsLogTarget>) {
+        self.stats_log = target;
+    }
+
+    /// URL notificati all'avvio e alla fine di una registrazione
+    pub fn set_webhooks(&self, urls: Vec<String>) {
+        self.webhooks.set_urls(urls);
+    }
+
+    /// Cambia il dispositivo di uscita, anche a stream avviato
+    pub fn set_output_device(&mut self, device: Option<String>) {
+        info!(
+            "Receiver audio output: {}",
+            device.as_deref().unwrap_or("system default")
+        );
+        self.output_device.as_mut().clone_from(&device);
+        self.output_device_changed.store(true, Ordering::Relaxed);
+    }
+
     // ── Salvataggio stream ──────────────────────────────────────
 
-    pub fn save_stream(&mut self, path: String) {
-        if let Some(saver_channel) = &self.save_rx {
-            let mut stream_saver = SaveStream::new(Arc::clone(saver_channel));
-            stream_saver.start(path);
-            self.save_stream = Some(stream_saver);
-        }
+    /// Avvia la registrazione nel file ottenuto espandendo il modello del nome.
+    /// Restituisce il percorso del primo file.
+    pub fn save_stream(&mut self, output: &OutputSettings) -> Option<String> {
+        let saver_channel = self.save_rx.as_ref()?;
+        let monitor = match *self.stream_profile.as_ref() {
+            Some(profile) if profile.width > 0 => format!("{}x{}", profile.width, profile.height),
+            _ => String::from("remote"),
+        };
+        let path = output.file_path("recording", &monitor, "mp4");
+        let segments = output.segment_policy();
+        let first_file = segments.path(&path, 1);
+
+        let mut stream_saver = SaveStream::new(Arc::clone(saver_channel));
+        stream_saver.start(path, segments);
+        self.save_stream = Some(stream_saver);
+        // Lato receiver non ci sono client da contare
+        self.webhooks.fire(WebhookEvent::RecordingStart, 0);
+        Some(first_file)
+    }
+
+    /// Capitolo sul frame corrente della registrazione, `None` se non si sta registrando
+    pub fn mark_recording(&mut self) -> Option<u32> {
+        self.save_stream.as_mut()?.mark()
     }
 
     pub fn save_stop(&mut self) {
         if let Some(mut save_stream) = self.save_stream.take() {
-            save_stream.stop();
+            save_stream.close();
+            self.webhooks.fire(WebhookEvent::RecordingStop, 0);
         }
     }
 
+    /// Come `save_stop`, ma restituisce il task del muxer per attenderne la
+    /// finalizzazione (trailer scritto) prima di uscire.
+    pub fn save_finish(&mut self) -> Option<tokio::task::JoinHandle<()>> {
+        let mut save_stream = self.save_stream.take()?;
+        self.webhooks.fire(WebhookEvent::RecordingStop, 0);
+        save_stream.finish()
+    }
+
     // ── WebRTC ──────────────────────────────────────────────────
 
     pub fn get_connection_handler(&self) -> Arc<WebRTCReceiver> {
@@ -562,13 +1233,68 @@ impl WorkerClose for Receiver {
     fn close(&mut self) {
         self.pipeline_state = PipelineState::Stopping;
         self.save_stop();
+        if let Some(cancel) = self.stats_cancel.take() {
+            cancel.cancel();
+        }
         let handler = Arc::clone(&self.handler);
         tokio::spawn(async move {
             handler.close().await;
         });
         self.is_streaming.store(false, Ordering::Relaxed);
+        self.connection.set(ConnectionState::Idle);
         self.local_sos.cancel();
         self.pipeline_state = PipelineState::Stopped;
         info!("Receiver closed (pipeline state: {})", self.pipeline_state);
     }
 }
+
+/// Health monitor + controllo del peer: porta lo stato in `Stalled` quando i
+/// frame smettono di arrivare, in `Reconnecting` quando il peer va offline e
+/// in `Failed` se non torna entro `RECONNECT_TIMEOUT`.
+fn spawn_connection_watchdog(
+    health: Arc<PipelineHealth>,
+    connection: ConnectionStatus,
+    handler: Arc<WebRTCReceiver>,
+    is_streaming: Arc<AtomicBool>,
+) {
+    tokio::spawn(async move {
+        let (alert_tx, mut alert_rx) = mpsc::channel::<HealthAlert>(16);
+        let monitor = HealthMonitor::new(health, alert_tx)
+            .with_check_interval(Duration::from_secs(1))
+            .with_stall_threshold(STALL_THRESHOLD)
+            .with_connection_status(connection.clone());
+        let monitor_run = monitor.run();
+        tokio::pin!(monitor_run);
+
+        let mut tick = tokio::time::interval(Duration::from_secs(1));
+        let mut offline_since: Option<Instant> = None;
+
+        loop {
+            tokio::select! {
+                _ = &mut monitor_run => break,
+                Some(alert) = alert_rx.recv() => log::warn!("Receiver health: {}", alert),
+                _ = tick.tick() => {
+                    if !is_streaming.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    if handler.is_connected().await {
+                        offline_since = None;
+                        if connection.get() == ConnectionState::Reconnecting {
+                            connection.set(ConnectionState::Buffering);
+                        }
+                        continue;
+                    }
+
+                    let since = *offline_since.get_or_insert_with(Instant::now);
+                    if since.elapsed() >= RECONNECT_TIMEOUT {
+                        error!("Caster unreachable for {:?}, giving up", RECONNECT_TIMEOUT);
+                        connection.set(ConnectionState::Failed);
+                        break;
+                    }
+                    connection.set(ConnectionState::Reconnecting);
+                }
+            }
+        }
+    });
+}
@@ -1,6 +1,12 @@
+use crate::encoder::ParameterSets;
+use crate::pipeline::sender::encode_stage::contains_idr;
+use crate::workers::WorkerClose;
 use log::{error, info};
+use std::fs::File;
+use std::path::Path;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 use tokio::sync::mpsc::Receiver;
 
@@ -8,7 +14,7 @@ use ac_ffmpeg::codec::audio::frame::get_sample_format;
 use ac_ffmpeg::codec::audio::{AudioDecoder, AudioEncoder, AudioFrameMut, ChannelLayout};
 use ac_ffmpeg::codec::video::VideoDecoder;
 use ac_ffmpeg::codec::{CodecParameters, Decoder, Encoder};
-use ac_ffmpeg::format::io::IO;
+use ac_ffmpeg::format::io::{IO, SeekableWriter};
 use ac_ffmpeg::format::muxer::{Muxer, OutputFormat};
 use ac_ffmpeg::packet::PacketMut;
 use ac_ffmpeg::time::TimeBase;
@@ -17,6 +23,8 @@ use ac_ffmpeg::time::TimeBase;
 pub enum SavePacket {
     Video(Vec<u8>, i64), // H.264 Annex B access unit + timestamp_us
     Audio(Vec<u8>, i64), // Opus packet + timestamp_us
+    /// Token della sessione del caster, inviato prima di ogni keyframe
+    Session(String),
 }
 
 #[derive(Debug)]
@@ -24,6 +32,12 @@ pub struct SaveStream {
     saver_channel: Arc<Mutex<Receiver<SavePacket>>>,
     is_saving: Arc<AtomicBool>,
     stop_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    /// Capitoli richiesti e non ancora presi in carico dal muxer
+    pending_marks: Arc<AtomicU32>,
+    /// Capitoli segnati dall'avvio della registrazione
+    mark_count: u32,
+    /// Task del muxer: termina dopo aver scritto il trailer (moov per MP4)
+    task: Option<tokio::task::JoinHandle<()>>,
 }
 
 /// Extract SPS and PPS NAL units from the first Annex B access unit.
@@ -282,6 +296,14 @@ impl AudioTranscoder {
         pkt.with_raw_dts(fixed_dts).with_raw_pts(fixed_pts)
     }
 
+    /// Dopo una caduta l'audio riparte dal timestamp del prossimo pacchetto
+    /// invece di proseguire il conteggio dei campioni.
+    fn reanchor(&mut self) {
+        self.buf_left.clear();
+        self.buf_right.clear();
+        self.next_pts_samples = None;
+    }
+
     fn ensure_pts_anchor(&mut self, relative_ts_us: i64) {
         if self.next_pts_samples.is_none() {
             let start_pts = (relative_ts_us as f64 * 48000.0 / 1_000_000.0) as i64;
@@ -472,32 +494,48 @@ impl SaveStream {
             saver_channel,
             is_saving: Arc::new(AtomicBool::new(false)),
             stop_tx: None,
+            pending_marks: Arc::new(AtomicU32::new(0)),
+            mark_count: 0,
+            task: None,
         }
     }
 
-    pub fn start(&mut self, path: String) {
+    /// Avvia il muxer. Con `segments` attivo la registrazione è divisa in più
+    /// file (`<nome>_001.mp4`, `<nome>_002.mp4`, ...), ognuno finalizzato
+    /// prima di aprire il successivo.
+    pub fn start(&mut self, path: String, segments: SegmentPolicy) {
         self.is_saving.store(true, Ordering::Release);
 
         let is_saving = Arc::clone(&self.is_saving);
         let saver_channel = Arc::clone(&self.saver_channel);
+        let pending_marks = Arc::clone(&self.pending_marks);
         let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
         self.stop_tx = Some(stop_tx);
 
-        tokio::spawn(async move {
-            if let Err(e) =
-                Self::run_muxer(saver_channel, Arc::clone(&is_saving), stop_rx, path).await
+        self.task = Some(tokio::spawn(async move {
+            if let Err(e) = Self::run_muxer(
+                saver_channel,
+                Arc::clone(&is_saving),
+                pending_marks,
+                stop_rx,
+                path,
+                segments,
+            )
+            .await
             {
                 error!("SaveStream muxer error: {}", e);
             }
             is_saving.store(false, Ordering::Release);
-        });
+        }));
     }
 
     async fn run_muxer(
         saver_channel: Arc<Mutex<Receiver<SavePacket>>>,
         is_saving: Arc<AtomicBool>,
+        pending_marks: Arc<AtomicU32>,
         mut stop_rx: tokio::sync::oneshot::Receiver<()>,
         path: String,
+        segments: SegmentPolicy,
     ) -> anyhow::Result<()> {
         // Drain stale packets from the channel before starting.
         // The channel may contain old packets from before save was requested.
@@ -516,6 +554,10 @@ impl SaveStream {
         let first_video: Vec<u8>;
         let first_video_ts: i64;
         let mut buffered_audio: Vec<(Vec<u8>, i64)> = Vec::new();
+        let mut resume = Resume::new(segments.resume_within);
+        // Ultimi SPS/PPS visti, anteposti agli IDR che ne sono privi: ogni
+        // file (primo, segmento, ripresa) può partire da qualunque IDR
+        let mut parameter_sets = ParameterSets::default();
 
         loop {
             let pkt = {
@@ -535,16 +577,18 @@ impl SaveStream {
             };
 
             match pkt {
-                SavePacket::Video(data, ts) => {
+                SavePacket::Video(mut data, ts) => {
                     // Only use this AU as the starting point if it contains SPS+PPS;
                     // otherwise the muxer can't determine video dimensions.
-                    if extract_sps_pps_extradata(&data).is_some() {
+                    parameter_sets.apply(&mut data);
+                    if contains_idr(&data) && extract_sps_pps_extradata(&data).is_some() {
                         log::debug!(
                             "SaveStream: First video packet received with timestamp: {} us",
                             ts
                         );
                         first_video = data;
                         first_video_ts = ts;
+                        resume.start(ts, Instant::now());
                         break;
                     } else {
                         log::debug!("Skipping video AU without SPS/PPS while waiting for keyframe");
@@ -553,128 +597,22 @@ impl SaveStream {
                 SavePacket::Audio(data, ts) => {
                     buffered_audio.push((data, ts));
                 }
+                SavePacket::Session(token) => resume.session(token),
             }
         }
 
-        // Initialize muxer with codec parameters derived from first video AU
-        let (mut muxer, video_idx, audio_idx, mut transcoder) =
-            tokio::task::block_in_place(|| -> anyhow::Result<_> {
-                // Video codec parameters: extract SPS/PPS from first AU for container header
-                let sps_pps = extract_sps_pps_extradata(&first_video);
-                if sps_pps.is_none() {
-                    log::warn!("Could not extract SPS/PPS from first video AU");
-                }
-
-                let mut vdec_builder =
-                    VideoDecoder::builder("h264")?.time_base(TimeBase::new(1, 90_000));
-                if let Some(ref extradata) = sps_pps {
-                    vdec_builder = vdec_builder.extradata(Some(extradata));
-                }
-                let mut vdec = vdec_builder.build()?;
-                let pkt = PacketMut::from(&first_video[..]).freeze();
-                let _ = vdec.try_push(pkt);
-                let _ = vdec.take(); // parse headers
-                let video_params: CodecParameters = vdec.codec_parameters().into();
-
-                // Try AAC transcoding first (best compatibility), fall back to raw Opus passthrough
-                let (audio_params, transcoder) = match AudioTranscoder::new() {
-                    Ok(tc) => {
-                        let params: CodecParameters = tc.encoder.codec_parameters().into();
-                        (params, Some(tc))
-                    }
-                    Err(e) => {
-                        log::warn!(
-                            "AAC transcoder unavailable ({}), falling back to Opus passthrough",
-                            e
-                        );
-                        let aenc = AudioEncoder::builder("libopus")?
-                            .sample_rate(48000)
-                            .channel_layout(ChannelLayout::from_channels(2).unwrap())
-                            .sample_format(get_sample_format("flt"))
-                            .set_option("frame_duration", "10")
-                            .build()?;
-                        let params: CodecParameters = aenc.codec_parameters().into();
-                        (params, None)
-                    }
-                };
-
-                // Create muxer - use MP4 format for better compatibility
-                let file = std::fs::File::create(&path)?;
-                let io = IO::from_seekable_write_stream(file);
-                let format = OutputFormat::guess_from_file_name(&path)
-                    .or_else(|| OutputFormat::find_by_name("mp4"))
-                    .ok_or_else(|| anyhow::anyhow!("No output format found"))?;
-
-                let mut builder = Muxer::builder();
-                let video_idx = builder.add_stream(&video_params)?;
-                let audio_idx = builder.add_stream(&audio_params)?;
-                let muxer = builder
-                    .interleaved(true) // Interleave packets for MP4 compatibility
-                    .build(io, format)?;
-
-                Ok((muxer, video_idx, audio_idx, transcoder))
-            })?;
-
-        // Audio time base: 1/48000 for sample-accurate AAC timestamps
-        let audio_tb = TimeBase::new(1, 48000);
-
-        let audio_mode = if transcoder.is_some() { "AAC" } else { "Opus" };
-        info!("SaveStream started → {} (audio: {})", path, audio_mode);
-
-        // Video time base: 1/90000 for H.264 timestamps
-        const VIDEO_TIMEBASE: i64 = 90_000; // 90kHz
-        let video_tb = TimeBase::new(1, 90_000);
-
-        // Track first packet timestamp to use as time origin (0)
-        let first_video_ts_us = first_video_ts;
-        let mut video_frame_count = 0i64;
-
-        // Write first video packet with PTS/DTS=0
-        tokio::task::block_in_place(|| -> anyhow::Result<()> {
-            log::info!("SaveStream: Writing first video frame with PTS/DTS=0");
-            let pkt = PacketMut::from(&first_video[..])
-                .with_stream_index(video_idx)
-                .with_pts(ac_ffmpeg::time::Timestamp::new(0, video_tb))
-                .with_dts(ac_ffmpeg::time::Timestamp::new(0, video_tb))
-                .freeze();
-            muxer.push(pkt)?;
-            video_frame_count += 1;
-            Ok(())
+        let mut index = 1;
+        let mut segment = tokio::task::block_in_place(|| {
+            Segment::open(segments.path(&path, index), &first_video, first_video_ts)
         })?;
 
         // Process any buffered audio packets (non-fatal: log errors and continue)
-        let mut audio_packet_count = 0u64;
-        for (audio_data, audio_ts) in buffered_audio {
-            audio_packet_count += 1;
-            tokio::task::block_in_place(|| {
-                if let Some(ref mut tc) = transcoder {
-                    if let Err(e) = tc.decode_and_buffer(&audio_data) {
-                        log::warn!("Audio decode error (buffered): {}", e);
-                        return;
-                    }
-
-                    // Calculate relative timestamp for buffered audio using video's time origin
-                    let relative_ts_us = audio_ts - first_video_ts;
-                    // Ensure timestamp is not negative (audio before video starts)
-                    let relative_ts_us = relative_ts_us.max(0);
-
-                    match tc.encode_buffered_with_timestamp(audio_idx, audio_tb, relative_ts_us) {
-                        Ok(packets) => {
-                            log::debug!("Audio (buffered): {} AAC packets encoded", packets.len());
-                            for pkt in packets {
-                                if let Err(e) = muxer.push(pkt) {
-                                    log::warn!("Audio mux error (buffered): {}", e);
-                                }
-                            }
-                        }
-                        Err(e) => log::warn!("Audio encode error (buffered): {}", e),
-                    }
-                } else {
-                    // Opus passthrough: would need timestamp handling
-                    log::warn!("Opus passthrough not implemented for buffered audio");
-                }
-            });
-        }
+        let audio_packet_count = buffered_audio.len();
+        tokio::task::block_in_place(|| {
+            for (audio_data, audio_ts) in buffered_audio {
+                segment.push_audio(&audio_data, audio_ts);
+            }
+        });
         log::info!(
             "Processed {} buffered audio packets before video start",
             audio_packet_count
@@ -685,8 +623,6 @@ impl SaveStream {
         const BATCH_SIZE: usize = 10;
         let mut packet_batch = Vec::with_capacity(BATCH_SIZE);
         let mut check_counter = 0u32;
-        let mut audio_packets_received = 0u64;
-        let mut audio_packets_encoded = 0u64;
 
         loop {
             // Collect a batch of packets
@@ -738,105 +674,602 @@ impl SaveStream {
                 break;
             }
 
+            // Capitoli segnati prima di questi pacchetti: vanno sull'ultimo frame scritto
+            for _ in 0..pending_marks.swap(0, Ordering::AcqRel) {
+                segment.mark();
+            }
+
             // Process entire batch in one block_in_place call
             tokio::task::block_in_place(|| {
-                for data in packet_batch.iter() {
+                for data in packet_batch.iter_mut() {
+                    if let SavePacket::Session(token) = data {
+                        resume.session(token.clone());
+                        continue;
+                    }
+                    if resume.arrived(Instant::now()) {
+                        segment.resume();
+                    }
                     match data {
                         SavePacket::Video(bytes, ts_us) => {
-                            // Calculate relative timestamp using first video packet as origin
-                            let relative_ts_us = ts_us - first_video_ts_us;
-
-                            // Convert microseconds to 90kHz ticks
-                            let pts_val = (relative_ts_us as f64 * VIDEO_TIMEBASE as f64
-                                / 1_000_000.0) as i64;
-
-                            let pkt = PacketMut::from(&bytes[..])
-                                .with_stream_index(video_idx)
-                                .with_pts(ac_ffmpeg::time::Timestamp::new(pts_val, video_tb))
-                                .with_dts(ac_ffmpeg::time::Timestamp::new(pts_val, video_tb))
-                                .freeze();
-                            if let Err(e) = muxer.push(pkt) {
-                                log::warn!("Video mux error: {}, skipping packet", e);
+                            parameter_sets.apply(bytes);
+                            let keyframe =
+                                contains_idr(bytes) && extract_sps_pps_extradata(bytes).is_some();
+                            // Dopo una caduta si riprende solo da un keyframe
+                            if resume.is_waiting() && !keyframe {
+                                continue;
                             }
-                            video_frame_count += 1;
-                        }
-                        SavePacket::Audio(bytes, ts_us) => {
-                            audio_packets_received += 1;
-
-                            // Calculate relative timestamp using video's time origin
-                            let relative_ts_us = ts_us - first_video_ts;
-                            // Ensure timestamp is not negative (audio before video starts)
-                            let relative_ts_us = relative_ts_us.max(0);
-
-                            if let Some(ref mut tc) = transcoder {
-                                if let Err(e) = tc.decode_and_buffer(bytes) {
-                                    log::warn!("Audio decode error: {}", e);
-                                    continue; // Skip this packet, process next
-                                }
-
-                                // Encode with timestamp information
-                                match tc.encode_buffered_with_timestamp(
-                                    audio_idx,
-                                    audio_tb,
-                                    relative_ts_us,
-                                ) {
-                                    Ok(packets) => {
-                                        audio_packets_encoded += packets.len() as u64;
-                                        for pkt in packets {
-                                            if let Err(e) = muxer.push(pkt) {
-                                                log::warn!(
-                                                    "Audio mux error: {}, skipping packet",
-                                                    e
-                                                );
-                                            }
+                            // Si ruota su un IDR, con SPS/PPS già anteposti: il nuovo
+                            // file parte decodificabile
+                            if (segments.is_due(&segment) || resume.needs_new_file()) && keyframe {
+                                match Segment::open(segments.path(&path, index + 1), bytes, *ts_us)
+                                {
+                                    Ok(next) => {
+                                        index += 1;
+                                        resume.start(*ts_us, Instant::now());
+                                        let previous = std::mem::replace(&mut segment, next);
+                                        if let Err(e) = previous.close() {
+                                            error!("SaveStream segment finalize error: {}", e);
                                         }
+                                        continue;
+                                    }
+                                    Err(e) => {
+                                        // Si continua sul file corrente
+                                        error!("SaveStream cannot open next segment: {}", e);
                                     }
-                                    Err(e) => log::warn!("Audio encode error: {}", e),
                                 }
-                            } else {
-                                // Opus passthrough: would need timestamp handling
-                                log::warn!("Opus passthrough not fully implemented");
                             }
+                            segment.push_video(bytes, resume.video_ts(*ts_us));
                         }
+                        SavePacket::Audio(bytes, ts_us) => {
+                            if !resume.is_waiting() {
+                                segment.push_audio(bytes, resume.audio_ts(*ts_us));
+                            }
+                        }
+                        SavePacket::Session(_) => {}
                     }
                 }
             });
         }
 
         // Flush transcoder and close muxer
-        tokio::task::block_in_place(|| -> anyhow::Result<()> {
-            if let Some(ref mut tc) = transcoder {
-                match tc.flush(audio_idx, audio_tb) {
-                    Ok(packets) => {
-                        for pkt in packets {
-                            if let Err(e) = muxer.push(pkt) {
-                                log::warn!("Audio mux error (flush): {}", e);
-                            }
+        tokio::task::block_in_place(|| segment.close())
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(tx) = self.stop_tx.take() {
+            let _ = tx.send(());
+        }
+    }
+
+    pub fn is_saving(&self) -> bool {
+        self.is_saving.load(Ordering::Acquire)
+    }
+
+    /// Segna un capitolo sul frame corrente; restituisce il suo numero, `None`
+    /// se la registrazione è già terminata.
+    pub fn mark(&mut self) -> Option<u32> {
+        if !self.is_saving() {
+            return None;
+        }
+        self.pending_marks.fetch_add(1, Ordering::AcqRel);
+        self.mark_count += 1;
+        Some(self.mark_count)
+    }
+
+    /// Ferma la registrazione e restituisce il task del muxer, da attendere
+    /// per essere sicuri che il file sia finalizzato (es. in chiusura app).
+    pub fn finish(&mut self) -> Option<tokio::task::JoinHandle<()>> {
+        self.stop();
+        self.task.take()
+    }
+}
+
+impl WorkerClose for SaveStream {
+    fn close(&mut self) {
+        self.stop();
+    }
+}
+
+// ── Segments ────────────────────────────────────────────────────
+
+/// Limiti oltre i quali la registrazione passa a un nuovo file
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SegmentPolicy {
+    pub max_duration: Option<Duration>,
+    pub max_bytes: Option<u64>,
+    /// Cadute più brevi continuano nello stesso file (`None` = mai)
+    pub resume_within: Option<Duration>,
+}
+
+impl SegmentPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.max_duration.is_some() || self.max_bytes.is_some()
+    }
+
+    fn is_due(&self, segment: &Segment) -> bool {
+        self.max_duration
+            .is_some_and(|max| segment.started.elapsed() >= max)
+            || self.max_bytes.is_some_and(|max| segment.bytes >= max)
+    }
+
+    /// Percorso del segmento `index` (da 1): `video.mp4` → `video_001.mp4`.
+    /// Senza segmentazione il primo file mantiene il percorso invariato, i
+    /// successivi (nuova sessione dopo una caduta) vengono numerati.
+    pub fn path(&self, path: &str, index: u32) -> String {
+        if !self.is_enabled() && index == 1 {
+            return path.to_string();
+        }
+        let path = Path::new(path);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match path.extension() {
+            Some(ext) => format!("{}_{:03}.{}", stem, index, ext.to_string_lossy()),
+            None => format!("{}_{:03}", stem, index),
+        };
+        path.with_file_name(name).to_string_lossy().into_owned()
+    }
+}
+
+// ── Resume ──────────────────────────────────────────────────────
+
+/// Una pausa così lunga tra due pacchetti è una caduta della connessione
+const DROP_GAP: Duration = Duration::from_secs(2);
+
+/// Scarto tollerato tra tempo dei media e tempo reale dopo una caduta
+const TIMELINE_TOLERANCE_US: i64 = 1_000_000;
+
+/// Continuità della registrazione attraverso le riconnessioni: dopo una
+/// caduta breve dello stesso caster si continua nello stesso file (l'immagine
+/// resta ferma per la durata della caduta), altrimenti si passa a un nuovo file.
+struct Resume {
+    resume_within: Option<Duration>,
+    /// Token del caster che sta alimentando il file
+    session: Option<String>,
+    last_packet: Option<Instant>,
+    /// Durata dell'ultima caduta, finché il primo video non la consuma
+    pending_gap: Option<Duration>,
+    /// Correzione dei timestamp quando la timeline del caster è ripartita
+    offset_us: i64,
+    last_video_us: i64,
+    /// Si scarta tutto fino al prossimo keyframe
+    waiting: bool,
+    new_file: bool,
+}
+
+impl Resume {
+    fn new(resume_within: Option<Duration>) -> Self {
+        Self {
+            resume_within,
+            session: None,
+            last_packet: None,
+            pending_gap: None,
+            offset_us: 0,
+            last_video_us: 0,
+            waiting: false,
+            new_file: false,
+        }
+    }
+
+    /// Il file corrente parte dal keyframe con timestamp `ts_us`
+    fn start(&mut self, ts_us: i64, now: Instant) {
+        self.last_packet = Some(now);
+        self.pending_gap = None;
+        self.offset_us = 0;
+        self.last_video_us = ts_us;
+        self.waiting = false;
+        self.new_file = false;
+    }
+
+    fn session(&mut self, token: String) {
+        if self
+            .session
+            .as_ref()
+            .is_some_and(|current| *current != token)
+        {
+            info!("SaveStream: different caster session, starting a new file");
+            self.new_file = true;
+            self.waiting = true;
+        }
+        self.session = Some(token);
+    }
+
+    /// Registra l'arrivo di un pacchetto; `true` se arriva dopo una caduta
+    fn arrived(&mut self, now: Instant) -> bool {
+        let gap = self
+            .last_packet
+            .replace(now)
+            .map(|last| now.saturating_duration_since(last));
+        let Some(gap) = gap.filter(|gap| *gap >= DROP_GAP) else {
+            return false;
+        };
+        if self.resume_within.is_none_or(|max| gap > max) {
+            info!(
+                "SaveStream: stream back after {:?}, starting a new file",
+                gap
+            );
+            self.new_file = true;
+        } else {
+            info!(
+                "SaveStream: stream back after {:?}, resuming the same file",
+                gap
+            );
+        }
+        self.pending_gap = Some(gap);
+        self.waiting = true;
+        true
+    }
+
+    fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+
+    fn needs_new_file(&self) -> bool {
+        self.new_file
+    }
+
+    /// Timestamp nel file. Se dopo una caduta la timeline del caster non
+    /// corrisponde più al tempo trascorso (tracce rinegoziate), viene
+    /// riallineata subito dopo l'ultimo frame più la durata della caduta.
+    fn video_ts(&mut self, ts_us: i64) -> i64 {
+        let mut ts_us = ts_us + self.offset_us;
+        if let Some(gap) = self.pending_gap.take() {
+            let expected = self.last_video_us + gap.as_micros() as i64;
+            if (ts_us - expected).abs() > TIMELINE_TOLERANCE_US {
+                info!(
+                    "SaveStream: caster timeline moved by {} ms, realigned",
+                    (ts_us - expected) / 1000
+                );
+                self.offset_us += expected - ts_us;
+                ts_us = expected;
+            }
+        }
+        self.waiting = false;
+        self.last_video_us = ts_us;
+        ts_us
+    }
+
+    fn audio_ts(&self, ts_us: i64) -> i64 {
+        ts_us + self.offset_us
+    }
+}
+
+// Video time base: 1/90000 for H.264 timestamps
+const VIDEO_TIMEBASE: i64 = 90_000; // 90kHz
+
+fn video_time_base() -> TimeBase {
+    TimeBase::new(1, 90_000)
+}
+
+/// Audio time base: 1/48000 for sample-accurate AAC timestamps
+fn audio_time_base() -> TimeBase {
+    TimeBase::new(1, 48000)
+}
+
+/// Un file di output: muxer, transcoder audio e origine dei tempi propri
+struct Segment {
+    muxer: Muxer<SeekableWriter<File>>,
+    video_idx: usize,
+    audio_idx: usize,
+    transcoder: Option<AudioTranscoder>,
+    /// Timestamp (us) del primo video AU, origine dei PTS del file
+    origin_ts: i64,
+    path: String,
+    started: Instant,
+    /// Byte di payload scritti, stima della dimensione del file
+    bytes: u64,
+    /// PTS (us, relativo al file) dell'ultimo video AU scritto
+    last_video_us: i64,
+    /// Capitoli segnati in questo file, in us dall'inizio
+    chapters: Vec<i64>,
+    video_frame_count: i64,
+    audio_packets_received: u64,
+    audio_packets_encoded: u64,
+}
+
+impl Segment {
+    /// Crea il file e scrive il primo AU (deve contenere SPS/PPS) con PTS/DTS=0
+    fn open(path: String, first_video: &[u8], first_video_ts: i64) -> anyhow::Result<Self> {
+        // Video codec parameters: extract SPS/PPS from first AU for container header
+        let sps_pps = extract_sps_pps_extradata(first_video);
+        if sps_pps.is_none() {
+            log::warn!("Could not extract SPS/PPS from first video AU");
+        }
+
+        let mut vdec_builder = VideoDecoder::builder("h264")?.time_base(video_time_base());
+        if let Some(ref extradata) = sps_pps {
+            vdec_builder = vdec_builder.extradata(Some(extradata));
+        }
+        let mut vdec = vdec_builder.build()?;
+        let pkt = PacketMut::from(first_video).freeze();
+        let _ = vdec.try_push(pkt);
+        let _ = vdec.take(); // parse headers
+        let video_params: CodecParameters = vdec.codec_parameters().into();
+
+        // Try AAC transcoding first (best compatibility), fall back to raw Opus passthrough
+        let (audio_params, transcoder) = match AudioTranscoder::new() {
+            Ok(tc) => {
+                let params: CodecParameters = tc.encoder.codec_parameters().into();
+                (params, Some(tc))
+            }
+            Err(e) => {
+                log::warn!(
+                    "AAC transcoder unavailable ({}), falling back to Opus passthrough",
+                    e
+                );
+                let aenc = AudioEncoder::builder("libopus")?
+                    .sample_rate(48000)
+                    .channel_layout(ChannelLayout::from_channels(2).unwrap())
+                    .sample_format(get_sample_format("flt"))
+                    .set_option("frame_duration", "10")
+                    .build()?;
+                let params: CodecParameters = aenc.codec_parameters().into();
+                (params, None)
+            }
+        };
+
+        // Create muxer - use MP4 format for better compatibility
+        let file = File::create(&path)?;
+        let io = IO::from_seekable_write_stream(file);
+        let format = OutputFormat::guess_from_file_name(&path)
+            .or_else(|| OutputFormat::find_by_name("mp4"))
+            .ok_or_else(|| anyhow::anyhow!("No output format found"))?;
+
+        let mut builder = Muxer::builder();
+        let video_idx = builder.add_stream(&video_params)?;
+        let audio_idx = builder.add_stream(&audio_params)?;
+        let mut muxer = builder
+            .interleaved(true) // Interleave packets for MP4 compatibility
+            .build(io, format)?;
+
+        let audio_mode = if transcoder.is_some() { "AAC" } else { "Opus" };
+        info!("SaveStream started → {} (audio: {})", path, audio_mode);
+
+        let video_tb = video_time_base();
+        log::info!("SaveStream: Writing first video frame with PTS/DTS=0");
+        let pkt = PacketMut::from(first_video)
+            .with_stream_index(video_idx)
+            .with_pts(ac_ffmpeg::time::Timestamp::new(0, video_tb))
+            .with_dts(ac_ffmpeg::time::Timestamp::new(0, video_tb))
+            .freeze();
+        muxer.push(pkt)?;
+
+        Ok(Self {
+            muxer,
+            video_idx,
+            audio_idx,
+            transcoder,
+            origin_ts: first_video_ts,
+            path,
+            started: Instant::now(),
+            bytes: first_video.len() as u64,
+            last_video_us: 0,
+            chapters: Vec::new(),
+            video_frame_count: 1,
+            audio_packets_received: 0,
+            audio_packets_encoded: 0,
+        })
+    }
+
+    fn push_video(&mut self, bytes: &[u8], ts_us: i64) {
+        // Calculate relative timestamp using first video packet as origin
+        let relative_ts_us = ts_us - self.origin_ts;
+
+        // Convert microseconds to 90kHz ticks
+        let pts_val = (relative_ts_us as f64 * VIDEO_TIMEBASE as f64 / 1_000_000.0) as i64;
+
+        let video_tb = video_time_base();
+        let pkt = PacketMut::from(bytes)
+            .with_stream_index(self.video_idx)
+            .with_pts(ac_ffmpeg::time::Timestamp::new(pts_val, video_tb))
+            .with_dts(ac_ffmpeg::time::Timestamp::new(pts_val, video_tb))
+            .freeze();
+        if let Err(e) = self.muxer.push(pkt) {
+            log::warn!("Video mux error: {}, skipping packet", e);
+        }
+        self.bytes += bytes.len() as u64;
+        self.last_video_us = relative_ts_us;
+        self.video_frame_count += 1;
+    }
+
+    /// Capitolo sul frame appena scritto
+    fn mark(&mut self) {
+        info!(
+            "SaveStream: chapter {} at {} ms",
+            self.chapters.len() + 1,
+            self.last_video_us / 1000
+        );
+        self.chapters.push(self.last_video_us);
+    }
+
+    fn push_audio(&mut self, bytes: &[u8], ts_us: i64) {
+        self.audio_packets_received += 1;
+
+        // Calculate relative timestamp using video's time origin
+        // Ensure timestamp is not negative (audio before video starts)
+        let relative_ts_us = (ts_us - self.origin_ts).max(0);
+
+        let Some(ref mut tc) = self.transcoder else {
+            // Opus passthrough: would need timestamp handling
+            log::warn!("Opus passthrough not fully implemented");
+            return;
+        };
+
+        if let Err(e) = tc.decode_and_buffer(bytes) {
+            log::warn!("Audio decode error: {}", e);
+            return;
+        }
+
+        // Encode with timestamp information
+        let audio_tb = audio_time_base();
+        match tc.encode_buffered_with_timestamp(self.audio_idx, audio_tb, relative_ts_us) {
+            Ok(packets) => {
+                self.audio_packets_encoded += packets.len() as u64;
+                for pkt in packets {
+                    self.bytes += pkt.data().len() as u64;
+                    if let Err(e) = self.muxer.push(pkt) {
+                        log::warn!("Audio mux error: {}, skipping packet", e);
+                    }
+                }
+            }
+            Err(e) => log::warn!("Audio encode error: {}", e),
+        }
+    }
+
+    /// Lo stream riprende dopo una caduta nello stesso file
+    fn resume(&mut self) {
+        if let Some(ref mut tc) = self.transcoder {
+            tc.reanchor();
+        }
+    }
+
+    /// Svuota il transcoder e scrive il trailer: il file resta riproducibile
+    fn close(mut self) -> anyhow::Result<()> {
+        if let Some(ref mut tc) = self.transcoder {
+            let audio_tb = audio_time_base();
+            match tc.flush(self.audio_idx, audio_tb) {
+                Ok(packets) => {
+                    for pkt in packets {
+                        if let Err(e) = self.muxer.push(pkt) {
+                            log::warn!("Audio mux error (flush): {}", e);
                         }
                     }
-                    Err(e) => log::warn!("Audio flush error: {}", e),
                 }
+                Err(e) => log::warn!("Audio flush error: {}", e),
             }
-            muxer.flush()?;
-            let _ = muxer.close()?;
-            Ok(())
-        })?;
+        }
+        self.muxer.flush()?;
+        let _ = self.muxer.close()?;
+
+        // Il muxer non scrive i capitoli: vanno in un file accanto al video
+        if !self.chapters.is_empty() {
+            let sidecar = Path::new(&self.path).with_extension("chapters");
+            match std::fs::write(&sidecar, chapters_text(&self.chapters)) {
+                Ok(()) => info!("SaveStream chapters → {}", sidecar.display()),
+                Err(e) => error!("SaveStream cannot write {}: {}", sidecar.display(), e),
+            }
+        }
 
-        // Already tracked video_frame_count variable
         info!(
             "SaveStream finished → {} ({} video frames, {} audio packets received, {} audio packets encoded)",
-            path, video_frame_count, audio_packets_received, audio_packets_encoded
+            self.path,
+            self.video_frame_count,
+            self.audio_packets_received,
+            self.audio_packets_encoded
         );
         Ok(())
     }
+}
 
-    pub fn stop(&mut self) {
-        if let Some(tx) = self.stop_tx.take() {
-            let _ = tx.send(());
-        }
+/// Capitoli nel formato OGM (`CHAPTER01=00:01:02.345`, `CHAPTER01NAME=...`),
+/// letto da mkvmerge, MP4Box e dalla maggior parte dei player.
+fn chapters_text(chapters: &[i64]) -> String {
+    let mut text = String::new();
+    for (n, &us) in chapters.iter().enumerate() {
+        let ms = us.max(0) / 1000;
+        text.push_str(&format!(
+            "CHAPTER{0:02}={1:02}:{2:02}:{3:02}.{4:03}\nCHAPTER{0:02}NAME=Mark {0}\n",
+            n + 1,
+            ms / 3_600_000,
+            ms / 60_000 % 60,
+            ms / 1000 % 60,
+            ms % 1000,
+        ));
     }
+    text
+}
 
-    pub fn is_saving(&self) -> bool {
-        self.is_saving.load(Ordering::Acquire)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_drop_resumes_the_same_file() {
+        let t0 = Instant::now();
+        let mut resume = Resume::new(Some(Duration::from_secs(30)));
+        resume.session(String::from("a"));
+        resume.start(0, t0);
+
+        assert!(!resume.arrived(t0 + Duration::from_millis(40)));
+        assert_eq!(resume.video_ts(40_000), 40_000);
+
+        assert!(resume.arrived(t0 + Duration::from_secs(10)));
+        assert!(resume.is_waiting());
+        assert!(!resume.needs_new_file());
+        // Stessa timeline del caster: nessuna correzione
+        assert_eq!(resume.video_ts(9_980_000), 9_980_000);
+        assert!(!resume.is_waiting());
+    }
+
+    #[test]
+    fn restarted_timeline_is_realigned_after_the_gap() {
+        let t0 = Instant::now();
+        let mut resume = Resume::new(Some(Duration::from_secs(30)));
+        resume.start(5_000_000, t0);
+
+        assert!(resume.arrived(t0 + Duration::from_secs(4)));
+        // La timeline è ripartita da zero: si continua dopo la caduta
+        assert_eq!(resume.video_ts(0), 9_000_000);
+        assert_eq!(resume.video_ts(40_000), 9_040_000);
+        assert_eq!(resume.audio_ts(20_000), 9_020_000);
+    }
+
+    #[test]
+    fn long_drop_or_new_session_starts_a_new_file() {
+        let t0 = Instant::now();
+        let mut resume = Resume::new(Some(Duration::from_secs(30)));
+        resume.start(0, t0);
+        assert!(resume.arrived(t0 + Duration::from_secs(31)));
+        assert!(resume.needs_new_file());
+
+        let mut resume = Resume::new(Some(Duration::from_secs(30)));
+        resume.session(String::from("a"));
+        resume.start(0, t0);
+        resume.session(String::from("a"));
+        assert!(!resume.needs_new_file());
+        resume.session(String::from("b"));
+        assert!(resume.needs_new_file());
+        assert!(resume.is_waiting());
+
+        let mut resume = Resume::new(None);
+        resume.start(0, t0);
+        assert!(resume.arrived(t0 + Duration::from_secs(3)));
+        assert!(resume.needs_new_file());
+    }
+
+    #[test]
+    fn chapters_use_the_ogm_format() {
+        assert_eq!(
+            chapters_text(&[0, 3_723_456_000]),
+            "CHAPTER01=00:00:00.000\nCHAPTER01NAME=Mark 1\n\
+             CHAPTER02=01:02:03.456\nCHAPTER02NAME=Mark 2\n"
+        );
+    }
+
+    #[test]
+    fn later_files_are_numbered_without_segmentation() {
+        let policy = SegmentPolicy::default();
+        assert_eq!(policy.path("rec.mp4", 1), "rec.mp4");
+        assert!(policy.path("rec.mp4", 2).ends_with("rec_002.mp4"));
+    }
+
+    #[test]
+    fn segments_are_numbered_from_the_first_file() {
+        let policy = SegmentPolicy {
+            max_duration: Some(Duration::from_secs(600)),
+            ..SegmentPolicy::default()
+        };
+        let dir = Path::new("recordings");
+        let path = dir.join("rec.mp4").to_string_lossy().into_owned();
+        assert_eq!(
+            policy.path(&path, 1),
+            dir.join("rec_001.mp4").to_string_lossy()
+        );
+        assert_eq!(
+            policy.path(&path, 12),
+            dir.join("rec_012.mp4").to_string_lossy()
+        );
+        // Oltre le tre cifre il numero si allunga
+        assert_eq!(policy.path("rec.mp4", 1234), "rec_1234.mp4");
+        assert_eq!(policy.path("rec", 2), "rec_002");
+        assert_eq!(policy.path("rec.v2.mkv", 3), "rec.v2_003.mkv");
     }
 }
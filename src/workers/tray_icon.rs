@@ -40,9 +40,13 @@ pub fn tray_icon() -> anyhow::Result<TrayIcon> {
 
     let menu = Menu::new();
     menu.append_items(&[
-        &MenuItem::with_id("open", "Open", true, None),
+        &MenuItem::with_id("open", "Open Main Window", true, None),
         &PredefinedMenuItem::separator(),
-        &MenuItem::with_id("exit", "Exit", true, None),
+        // Stesse azioni delle hotkey, ignorate se non si sta facendo da caster
+        &MenuItem::with_id("streaming", "Start/Stop Streaming", true, None),
+        &MenuItem::with_id("blank", "Blank Screen", true, None),
+        &PredefinedMenuItem::separator(),
+        &MenuItem::with_id("exit", "Quit", true, None),
     ])
     .expect("Tray icon set up failed.");
 
@@ -83,6 +87,8 @@ pub fn tray_menu_listener() -> impl Stream<Item = AppEvent> {
             while let Some(MenuEvent { id: MenuId(id) }) = receiver.recv().await {
                 let event = match id.as_str() {
                     "open" => AppEvent::OpenMainWindow,
+                    "streaming" => AppEvent::CasterToggleStreaming,
+                    "blank" => AppEvent::BlankScreen,
                     "exit" => AppEvent::ExitApp,
                     _ => AppEvent::Ignore,
                 };